@@ -0,0 +1,373 @@
+//! Faucet abuse heuristics
+//!
+//! Per-account daily limits don't stop farms of fresh accounts claiming the
+//! same amount seconds apart. Each claim is scored against recent history on
+//! a few signals; high scores get a harder PoW challenge or are pushed into
+//! the slow queue instead of being rejected.
+//!
+//! The `*_score` functions and `decide` are pure — all state lives in
+//! `AbuseTracker`.
+
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::net::IpAddr;
+
+/// How far back history is kept (and scored)
+const HISTORY_WINDOW_SECS: u64 = 600;
+/// Decisions kept for /admin/abuse_report
+const MAX_DECISIONS: usize = 500;
+
+const BURST_WINDOW_SECS: u64 = 60;
+const BURST_FREE_CLAIMS: usize = 2;
+const BURST_MAX_SCORE: u32 = 40;
+
+const IDENTICAL_WINDOW_SECS: u64 = 60;
+const IDENTICAL_MAX_SCORE: u32 = 30;
+
+const FRESH_WINDOW_SECS: u64 = 120;
+const FRESH_MAX_SCORE: u32 = 30;
+
+/// Default score at which /pow hands out a harder challenge
+pub const DEFAULT_POW_SCORE: u32 = 40;
+/// Default score at which /get_tokens goes through the slow queue
+pub const DEFAULT_SLOW_SCORE: u32 = 70;
+
+/// Default leading zero bits of an ordinary (unflagged) /pow challenge
+pub const BASE_POW_BITS: u32 = 12;
+/// Highest configurable base difficulty; hardened challenges go above it
+pub const MAX_BASE_POW_BITS: u32 = 32;
+/// Extra bits required at `pow_score` over the base; every 10 points above adds 2
+const HARDENED_EXTRA_BITS: u32 = 4;
+const MAX_HARDENED_EXTRA_BITS: u32 = 12;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AbuseConfig {
+    pub pow_score: u32,
+    pub slow_score: u32,
+    /// Leading zero bits of an ordinary challenge (FAUCET_POW_BITS / --pow-bits)
+    pub base_pow_bits: u32,
+}
+
+/// One faucet claim, as seen by the scorer
+#[derive(Debug, Clone)]
+pub struct ClaimEvent {
+    pub timestamp: u64,
+    pub ip_prefix: String,
+    pub account_id: String,
+    pub token: String,
+    pub amount: u64,
+    /// Account had never claimed before this one
+    pub new_account: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct AbuseScore {
+    pub burst: u32,
+    pub identical_amounts: u32,
+    pub fresh_accounts: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "action")]
+pub enum AbuseDecision {
+    Allow,
+    /// Challenge must be solved with this many leading zero bits
+    HardenPow { difficulty_bits: u32 },
+    SlowQueue,
+}
+
+/// Logged decision, exposed via /admin/abuse_report
+#[derive(Debug, Clone, Serialize)]
+pub struct DecisionRecord {
+    pub timestamp: u64,
+    pub endpoint: &'static str,
+    pub ip_prefix: String,
+    pub account_id: String,
+    pub token: String,
+    pub amount: u64,
+    pub score: AbuseScore,
+    pub decision: AbuseDecision,
+}
+
+/// Group addresses the way farms usually rotate them: IPv4 /24, IPv6 /48
+pub fn ip_prefix(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let o = v4.octets();
+            format!("{}.{}.{}.0/24", o[0], o[1], o[2])
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+        }
+    }
+}
+
+fn within(event: &ClaimEvent, other: &ClaimEvent, window_secs: u64) -> bool {
+    event.timestamp.saturating_sub(other.timestamp) <= window_secs
+}
+
+/// Many claims from one IP prefix in a short window
+pub fn burst_score(event: &ClaimEvent, history: &[ClaimEvent]) -> u32 {
+    let recent = history
+        .iter()
+        .filter(|h| h.ip_prefix == event.ip_prefix && within(event, h, BURST_WINDOW_SECS))
+        .count();
+    (recent.saturating_sub(BURST_FREE_CLAIMS) as u32 * 10).min(BURST_MAX_SCORE)
+}
+
+/// Other accounts claiming exactly the same token and amount moments ago
+pub fn identical_amount_score(event: &ClaimEvent, history: &[ClaimEvent]) -> u32 {
+    let accounts: HashSet<&str> = history
+        .iter()
+        .filter(|h| {
+            h.account_id != event.account_id
+                && h.token == event.token
+                && h.amount == event.amount
+                && within(event, h, IDENTICAL_WINDOW_SECS)
+        })
+        .map(|h| h.account_id.as_str())
+        .collect();
+    (accounts.len().saturating_sub(1) as u32 * 10).min(IDENTICAL_MAX_SCORE)
+}
+
+/// A never-seen account arriving right after a run of other never-seen ones
+pub fn fresh_account_score(event: &ClaimEvent, history: &[ClaimEvent]) -> u32 {
+    if !event.new_account {
+        return 0;
+    }
+    let fresh = history
+        .iter()
+        .filter(|h| h.new_account && within(event, h, FRESH_WINDOW_SECS))
+        .count();
+    (fresh as u32 * 5).min(FRESH_MAX_SCORE)
+}
+
+pub fn score_claim(event: &ClaimEvent, history: &[ClaimEvent]) -> AbuseScore {
+    let burst = burst_score(event, history);
+    let identical_amounts = identical_amount_score(event, history);
+    let fresh_accounts = fresh_account_score(event, history);
+    AbuseScore {
+        burst,
+        identical_amounts,
+        fresh_accounts,
+        total: (burst + identical_amounts + fresh_accounts).min(100),
+    }
+}
+
+/// PoW difficulty for a score, `None` below `pow_score`
+pub fn pow_difficulty_bits(score: u32, config: &AbuseConfig) -> Option<u32> {
+    if score < config.pow_score {
+        return None;
+    }
+    let extra = (HARDENED_EXTRA_BITS + (score - config.pow_score) / 10 * 2).min(MAX_HARDENED_EXTRA_BITS);
+    Some(config.base_pow_bits + extra)
+}
+
+/// What /get_tokens does with a claim of this score
+pub fn decide(score: u32, config: &AbuseConfig) -> AbuseDecision {
+    if score >= config.slow_score {
+        AbuseDecision::SlowQueue
+    } else if let Some(difficulty_bits) = pow_difficulty_bits(score, config) {
+        AbuseDecision::HardenPow { difficulty_bits }
+    } else {
+        AbuseDecision::Allow
+    }
+}
+
+/// `target` for the frontend's `hash < target * 1000` check that requires
+/// `difficulty_bits` leading zero bits in the first 8 bytes of the hash
+pub fn pow_target_for_bits(difficulty_bits: u32) -> u64 {
+    (u64::MAX >> difficulty_bits) / 1000
+}
+
+/// Recent claims and decisions (in memory; resets on restart)
+#[derive(Default)]
+pub struct AbuseTracker {
+    history: VecDeque<ClaimEvent>,
+    seen_accounts: HashSet<String>,
+    decisions: VecDeque<DecisionRecord>,
+}
+
+impl AbuseTracker {
+    /// Build the event for a claim, marking accounts never seen before
+    pub fn event(&self, timestamp: u64, ip_prefix: String, account_id: &str, token: &str, amount: u64) -> ClaimEvent {
+        ClaimEvent {
+            timestamp,
+            ip_prefix,
+            new_account: !self.seen_accounts.contains(account_id),
+            account_id: account_id.to_string(),
+            token: token.to_string(),
+            amount,
+        }
+    }
+
+    /// Score without recording (used by /pow)
+    pub fn score(&mut self, event: &ClaimEvent) -> AbuseScore {
+        self.prune(event.timestamp);
+        score_claim(event, self.history.make_contiguous())
+    }
+
+    /// Score and add to history (used by /get_tokens)
+    pub fn record(&mut self, event: ClaimEvent) -> AbuseScore {
+        let score = self.score(&event);
+        self.seen_accounts.insert(event.account_id.clone());
+        self.history.push_back(event);
+        score
+    }
+
+    pub fn log_decision(&mut self, record: DecisionRecord) {
+        if self.decisions.len() >= MAX_DECISIONS {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(record);
+    }
+
+    pub fn decisions(&self) -> impl Iterator<Item = &DecisionRecord> {
+        self.decisions.iter()
+    }
+
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn seen_accounts_len(&self) -> usize {
+        self.seen_accounts.len()
+    }
+
+    fn prune(&mut self, now: u64) {
+        while let Some(front) = self.history.front() {
+            if now.saturating_sub(front.timestamp) <= HISTORY_WINDOW_SECS {
+                break;
+            }
+            self.history.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: AbuseConfig = AbuseConfig { pow_score: DEFAULT_POW_SCORE, slow_score: DEFAULT_SLOW_SCORE, base_pow_bits: BASE_POW_BITS };
+
+    fn claim(timestamp: u64, ip_prefix: &str, account_id: &str, amount: u64, new_account: bool) -> ClaimEvent {
+        ClaimEvent {
+            timestamp,
+            ip_prefix: ip_prefix.to_string(),
+            account_id: account_id.to_string(),
+            token: "MILO".to_string(),
+            amount,
+            new_account,
+        }
+    }
+
+    #[test]
+    fn prefixes_group_ipv4_and_ipv6() {
+        assert_eq!(ip_prefix("1.2.3.4".parse().unwrap()), "1.2.3.0/24");
+        assert_eq!(ip_prefix("2001:db8:1:2::1".parse().unwrap()), "2001:db8:1::/48");
+    }
+
+    #[test]
+    fn burst_counts_recent_claims_from_the_prefix() {
+        let event = claim(1_000, "1.2.3.0/24", "0xa", 1, false);
+        let mut history: Vec<_> = (0..5).map(|i| claim(950 + i, "1.2.3.0/24", "0xb", 1, false)).collect();
+        // Other prefixes and claims outside the window don't count
+        history.push(claim(999, "5.6.7.0/24", "0xc", 1, false));
+        history.push(claim(900, "1.2.3.0/24", "0xd", 1, false));
+        assert_eq!(burst_score(&event, &history), 30);
+        assert_eq!(burst_score(&event, &history[..2]), 0);
+
+        let flood: Vec<_> = (0..20).map(|i| claim(990 + i / 4, "1.2.3.0/24", "0xb", 1, false)).collect();
+        assert_eq!(burst_score(&event, &flood), BURST_MAX_SCORE);
+    }
+
+    #[test]
+    fn identical_amounts_count_other_accounts() {
+        let event = claim(1_000, "x", "0xa", 500, false);
+        let history = [
+            claim(990, "y", "0xb", 500, false),
+            claim(991, "y", "0xc", 500, false),
+            claim(992, "y", "0xd", 500, false),
+            // Same account, other amount, too old
+            claim(993, "y", "0xa", 500, false),
+            claim(994, "y", "0xe", 501, false),
+            claim(900, "y", "0xf", 500, false),
+        ];
+        assert_eq!(identical_amount_score(&event, &history), 20);
+        // One other account claiming the same amount is not a pattern
+        assert_eq!(identical_amount_score(&event, &history[..1]), 0);
+    }
+
+    #[test]
+    fn fresh_accounts_only_score_new_accounts() {
+        let history: Vec<_> = (0..4).map(|i| claim(950 + i, "x", &format!("0x{}", i), 1, true)).collect();
+        assert_eq!(fresh_account_score(&claim(1_000, "x", "0xa", 1, true), &history), 20);
+        assert_eq!(fresh_account_score(&claim(1_000, "x", "0xa", 1, false), &history), 0);
+        // Outside the window
+        assert_eq!(fresh_account_score(&claim(1_200, "x", "0xa", 1, true), &history), 0);
+    }
+
+    #[test]
+    fn decision_by_score() {
+        assert_eq!(decide(0, &CONFIG), AbuseDecision::Allow);
+        assert_eq!(decide(39, &CONFIG), AbuseDecision::Allow);
+        assert_eq!(decide(40, &CONFIG), AbuseDecision::HardenPow { difficulty_bits: 16 });
+        assert_eq!(decide(50, &CONFIG), AbuseDecision::HardenPow { difficulty_bits: 18 });
+        assert_eq!(decide(69, &CONFIG), AbuseDecision::HardenPow { difficulty_bits: 20 });
+        assert_eq!(decide(70, &CONFIG), AbuseDecision::SlowQueue);
+        assert_eq!(decide(100, &CONFIG), AbuseDecision::SlowQueue);
+
+        // The extra difficulty is capped
+        let lenient = AbuseConfig { pow_score: 0, slow_score: 200, base_pow_bits: 12 };
+        assert_eq!(pow_difficulty_bits(100, &lenient), Some(12 + MAX_HARDENED_EXTRA_BITS));
+    }
+
+    #[test]
+    fn pow_target_halves_per_bit() {
+        assert_eq!(pow_target_for_bits(0), u64::MAX / 1000);
+        assert_eq!(pow_target_for_bits(1), (u64::MAX >> 1) / 1000);
+        assert!(pow_target_for_bits(16) < pow_target_for_bits(12));
+    }
+
+    #[test]
+    fn farm_of_fresh_accounts_lands_in_the_slow_queue() {
+        let mut tracker = AbuseTracker::default();
+        let mut last = AbuseScore::default();
+        for i in 0..6 {
+            let event = tracker.event(1_000 + i * 5, "1.2.3.0/24".to_string(), &format!("0xfarm{}", i), "MILO", 500);
+            assert!(event.new_account);
+            last = tracker.record(event);
+        }
+        assert_eq!((last.burst, last.identical_amounts, last.fresh_accounts), (30, 30, 25));
+        assert_eq!(last.total, 85);
+        assert_eq!(decide(last.total, &CONFIG), AbuseDecision::SlowQueue);
+        assert_eq!(tracker.seen_accounts_len(), 6);
+
+        // A returning account is no longer new, and old history is pruned
+        let event = tracker.event(2_000, "1.2.3.0/24".to_string(), "0xfarm0", "MILO", 500);
+        assert!(!event.new_account);
+        assert_eq!(tracker.score(&event).total, 0);
+        assert_eq!(tracker.history_len(), 0);
+    }
+
+    #[test]
+    fn decision_log_is_bounded() {
+        let mut tracker = AbuseTracker::default();
+        for timestamp in 0..MAX_DECISIONS as u64 + 5 {
+            tracker.log_decision(DecisionRecord {
+                timestamp,
+                endpoint: "get_tokens",
+                ip_prefix: "x".to_string(),
+                account_id: "0xa".to_string(),
+                token: "MILO".to_string(),
+                amount: 1,
+                score: AbuseScore::default(),
+                decision: AbuseDecision::Allow,
+            });
+        }
+        assert_eq!(tracker.decisions().count(), MAX_DECISIONS);
+        assert_eq!(tracker.decisions().next().unwrap().timestamp, 5);
+    }
+}
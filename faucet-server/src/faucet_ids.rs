@@ -1,6 +0,0 @@
-//! Auto-generated faucet IDs - DO NOT EDIT MANUALLY
-//! Bu dosya setup_milo scripti çalıştırıldığında güncellenir
-
-pub const MILO_FAUCET_ID: &str = "0xa28b4f998be3a32047b88ae20e0a35";
-pub const MELO_FAUCET_ID: &str = "0x13bde3e49deaf92074138cbcaf8d4f";
-pub const MUSDC_FAUCET_ID: &str = "0x2c09e8d9f4ef022044cfee2d14d3a8";
@@ -0,0 +1,121 @@
+//! Structured logging for the faucet server
+//!
+//! Logs go through `tracing`, filtered by `RUST_LOG` (default `info`) and
+//! written as text or, with `FAUCET_LOG_FORMAT=json`, one JSON object per
+//! line. Every HTTP request runs in a `request` span carrying its
+//! `request_id`; mints take that span to the worker thread, so a claim can
+//! be followed from the handler to its transaction.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value as JsonValue;
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest incoming `X-Request-Id` that is kept instead of replaced
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// `FAUCET_LOG_FORMAT`: `json` or `text` (the default)
+    pub fn from_env() -> Self {
+        match std::env::var("FAUCET_LOG_FORMAT").ok().as_deref().map(str::trim) {
+            Some(v) if v.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Text,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+/// Install the global subscriber; call once, before anything logs
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_target(false);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// An incoming `X-Request-Id` if it is short and printable, otherwise a new id
+fn request_id(req: &Request) -> String {
+    req.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()))
+}
+
+/// Middleware: run the request in a span with its request id, echo the id
+/// in `X-Request-Id` and add it as `request_id` to JSON error bodies (and
+/// to /get_tokens_batch's partial-success `207`)
+pub async fn request_span(req: Request, next: Next) -> Response {
+    let id = request_id(&req);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+    let started = std::time::Instant::now();
+    let mut response = next.run(req).instrument(span.clone()).await;
+    span.in_scope(|| {
+        tracing::debug!(
+            status = response.status().as_u16(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "request finished"
+        )
+    });
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if !is_json || (status.is_success() && status != StatusCode::MULTI_STATUS) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            span.in_scope(|| tracing::warn!(error = %e, "could not read error body"));
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let body = match serde_json::from_slice::<JsonValue>(&bytes) {
+        Ok(JsonValue::Object(mut map)) => {
+            map.insert("request_id".to_string(), JsonValue::String(id));
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(JsonValue::Object(map).to_string())
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
@@ -9,68 +9,155 @@
 //! Port: varsayılan 8084
 //!
 //! Mimarı:
-//!   axum handler → mpsc::Sender<MintRequest> → worker thread (owns !Send Client)
-//!                                            ← oneshot::Receiver<Result<..>>
+//!   axum handler → mpsc::Sender<WorkerRequest> → faucet başına bir worker thread
+//!                                                (kendi !Send Client'ı ve store'u)
+//!                                              ← oneshot::Receiver<Result<..>>
 
-mod faucet_ids;
+mod abuse;
+mod logging;
+mod metrics;
+mod mint_history;
+mod rate_limits;
+mod retry;
 
-use faucet_ids::{MELO_FAUCET_ID, MILO_FAUCET_ID, MUSDC_FAUCET_ID};
+use abuse::{AbuseConfig, AbuseDecision, AbuseTracker, DecisionRecord};
+use logging::LogFormat;
+use metrics::FaucetMetrics;
+use mint_history::{MintHistory, MINT_HISTORY_PATH, RECENT_MINTS_CAPACITY};
+use rate_limits::{Limit, RateLimitStore, ReserveError, RATE_LIMITS_PATH};
+use retry::{ErrorClass, MINT_MAX_RETRIES};
+use integration::account_state::compare_account_state;
+use integration::faucet_config::{
+    load_faucets_config, save_faucets_config, validate_faucets_config, FaucetEntry, FaucetsConfig,
+};
+use integration::ledger::{self, Direction, LedgerEntry};
+use integration::rate_limit::{forwarded_client_ip, parse_trusted_proxies};
+use integration::shutdown::{shutdown_signal, Shutdown, DRAIN_TIMEOUT_SECS};
+use integration::startup_config::{log_startup_config, milo_config_source, ConfigSource, StartupConfig};
 
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Query, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware,
     response::{IntoResponse, Json},
-    routing::get,
+    routing::{get, post},
     Router,
 };
 use miden_client::{
-    account::AccountId,
+    account::{AccountId, NetworkId},
     asset::FungibleAsset,
     builder::ClientBuilder,
+    crypto::FeltRng,
     keystore::FilesystemKeyStore,
-    note::NoteType,
-    rpc::{Endpoint, GrpcClient},
-    transaction::TransactionRequestBuilder,
+    note::{create_p2id_note, Note, NoteType},
+    rpc::{Endpoint, GrpcClient, NodeRpcClient},
+    store::TransactionFilter,
+    transaction::{OutputNote, TransactionId, TransactionRequest, TransactionRequestBuilder, TransactionStatus},
+    utils::Serializable,
+    Felt,
 };
 use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use miden_lib::account::faucets::BasicFungibleFaucet;
+use milo_config::{MiloConfig, Service};
+use milo_registry::TokenRegistry;
 use rand::rngs::StdRng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tower_http::cors::{Any, CorsLayer};
+use tracing::{error, info, warn, Instrument, Span};
 
-const KEYSTORE_PATH: &str = "keystore";
-const STORE_PATH: &str = "faucet_store.sqlite3";
-const MAX_DAILY_AMOUNT: u64 = 10_00000000; // 10 tokens × 10^8 decimals
+const DEFAULT_HOST: &str = "127.0.0.1";
+/// Faucet definitions (written by `milo setup`, re-read by /admin/reload_faucets)
+const FAUCETS_PATH: &str = "faucets.json";
+/// Faucet IDs written by `milo setup`, run from integration/
+const ACCOUNTS_PATH: &str = "../integration/accounts.json";
 const ADMIN_ACCOUNT_ID: &str = "0x9e96e636738fc9104ed2b971931cc7";
+/// Flagged claims wait this long (one at a time) before minting
+const SLOW_QUEUE_DELAY_SECS: u64 = 30;
+/// Unsolved /pow challenges expire after this long
+const CHALLENGE_TTL_SECS: u64 = 300;
+/// Hardened challenges take much longer to solve, so they live longer
+const HARDENED_CHALLENGE_TTL_SECS: u64 = 600;
+/// How often the worker re-reads each faucet's issued supply
+const SUPPLY_REFRESH_SECS: u64 = 300;
+/// How often each worker re-checks its faucet on-chain (FAUCET_RECHECK_SECS)
+const DEFAULT_RECHECK_SECS: u64 = 300;
+/// Minimum gap between claims of one account for one token (FAUCET_REQUEST_COOLDOWN_SECS)
+const DEFAULT_REQUEST_COOLDOWN_SECS: u64 = 30;
+/// Days of mint history kept (FAUCET_MINT_HISTORY_RETENTION_DAYS)
+const DEFAULT_MINT_HISTORY_RETENTION_DAYS: u64 = 30;
+/// Default and largest page of /history and /recent_mints
+const DEFAULT_HISTORY_PAGE: usize = 50;
+const MAX_HISTORY_PAGE: usize = 200;
+/// Most queued mints the worker submits as one transaction (FAUCET_MAX_MINT_BATCH)
+const DEFAULT_MAX_MINT_BATCH: usize = 16;
+/// Claims beyond this many waiting mints get 503 (FAUCET_MAX_MINT_QUEUE)
+const DEFAULT_MAX_MINT_QUEUE: usize = 100;
+/// `retry_after` hint (seconds) on a 503 for a full mint queue
+const MINT_QUEUE_RETRY_AFTER_SECS: u64 = 30;
+/// A `wait=true` claim's worker polls its mint for this long after submitting it
+const COMMIT_WAIT_SECS: u64 = 45;
+const COMMIT_POLL_INTERVAL_MS: u64 = 1000;
+/// A `wait=true` claim stops waiting on the worker after this long (queue
+/// time and submit retries come on top of the commit wait)
+const WAIT_CLAIM_TIMEOUT_SECS: u64 = COMMIT_WAIT_SECS + 45;
+/// /tx_status answers `pending` when the workers take longer than this
+const TX_STATUS_TIMEOUT_SECS: u64 = 5;
+/// /faucet_balance re-reads supply at most this often
+const BALANCE_CACHE_SECS: u64 = 60;
+/// /health flags `low_supply` below this share of max supply (basis points)
+const LOW_SUPPLY_BPS: u64 = 500;
+/// Prefix on worker errors from a private mint, so the handler can reject the flag
+const PRIVATE_MINT_ERROR: &str = "private mint: ";
+/// Prefix on worker errors for mints refused before submission because they
+/// would take the faucet past its max supply
+const SUPPLY_EXHAUSTED_ERROR: &str = "faucet supply exhausted";
+/// Fungible faucets keep their total issuance in the last element of this
+/// reserved storage slot
+const FAUCET_SYSDATA_SLOT: u8 = 0;
 
-/// Tracks daily faucet usage per user+token
-#[derive(Clone)]
-struct RateLimitEntry {
-    total_amount: u64,
-    day: u32, // day number since epoch
+/// faucets.json plus each faucet's on-chain status, published by its worker
+struct FaucetSet {
+    config: FaucetsConfig,
+    status: HashMap<String, FaucetCheck>,
 }
 
-fn current_day() -> u32 {
-    (SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        / 86400) as u32
+/// Result of a worker's last on-chain check of its faucet
+#[derive(Clone, Copy)]
+struct FaucetCheck {
+    active: bool,
+    /// Unix seconds of the check
+    checked_at: u64,
 }
 
-/// Faucet configurations — symbol, faucet account ID, decimals
-const FAUCETS: &[(&str, &str, u64)] = &[
-    ("MILO", MILO_FAUCET_ID, 8),
-    ("MELO", MELO_FAUCET_ID, 8),
-    ("MUSDC", MUSDC_FAUCET_ID, 8),
-];
+impl FaucetSet {
+    /// A faucet by symbol, if it exists on-chain
+    fn active(&self, symbol: &str) -> Option<&FaucetEntry> {
+        self.config
+            .get(symbol)
+            .filter(|_| self.status.get(symbol).is_some_and(|c| c.active))
+    }
+
+    /// Record a worker's check, logging when the faucet comes or goes
+    fn publish(&mut self, symbol: &str, active: bool) {
+        let checked_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let previous = self.status.insert(symbol.to_string(), FaucetCheck { active, checked_at });
+        match previous {
+            Some(p) if !p.active && active => info!(token = %symbol, "faucet artık aktif"),
+            Some(p) if p.active && !active => warn!(token = %symbol, "faucet artık bulunamıyor"),
+            _ => {}
+        }
+    }
+}
 
 // ---------------------------------------------------------------------------
 // Worker ↔ axum channel types
@@ -83,7 +170,81 @@ struct MintRequest {
     recipient_id_hex: String,
     amount: u64,
     token_symbol: String,
-    reply: tokio::sync::oneshot::Sender<Result<String, String>>,
+    /// `Private` puts only the note's commitment on chain
+    note_type: NoteType,
+    /// When the handler queued it, for the mint latency histogram
+    queued_at: Instant,
+    /// The claim's mint queue slot, given back when the worker takes it
+    queue_ticket: QueueTicket,
+    /// The claim's request span; the worker enters it so its logs carry the request id
+    span: Span,
+    /// `wait=true`: answer only once the mint committed or `COMMIT_WAIT_SECS` passed
+    wait_for_commit: bool,
+    reply: tokio::sync::oneshot::Sender<Result<MintReceipt, String>>,
+}
+
+/// A submitted mint, returned by the worker
+struct MintReceipt {
+    tx_id: String,
+    transaction_id: TransactionId,
+    /// ID of the P2ID note created for this request
+    note_id: String,
+    /// Set for private mints
+    private_note: Option<PrivateNote>,
+    /// Set for claims that waited for the commit
+    commit: Option<CommitStatus>,
+}
+
+/// Where a `wait=true` mint stood when the worker stopped waiting
+#[derive(Debug, Clone, Copy)]
+struct CommitStatus {
+    committed: bool,
+    block_num: Option<u32>,
+}
+
+/// Everything a recipient needs to consume a private note: the node only
+/// stores its commitment, so the details are handed over out-of-band
+#[derive(Serialize)]
+struct PrivateNote {
+    note_id: String,
+    /// The full note, serialized (hex)
+    note: String,
+}
+
+/// Sent from the admin diagnostics handler → every worker; `None` means the
+/// tx is not in that worker's store.
+struct TxDiagnosticsRequest {
+    tx_id_hex: String,
+    reply: tokio::sync::oneshot::Sender<Result<Option<JsonValue>, String>>,
+}
+
+/// Sent from /tx_status → every worker; `None` means the tx is unknown to it.
+struct TxStatusRequest {
+    tx_id_hex: String,
+    sync: bool,
+    reply: tokio::sync::oneshot::Sender<Result<Option<JsonValue>, String>>,
+}
+
+/// Sent from /admin/reload_faucets and /admin/recheck → the faucet's worker
+/// with its (possibly changed) entry; replies whether the faucet exists on-chain.
+struct CheckFaucetsRequest {
+    entry: FaucetEntry,
+    reply: tokio::sync::oneshot::Sender<bool>,
+}
+
+/// Sent from /faucet_balance → every worker; each replies once its faucet's
+/// supply is fresh (re-read unless it was read within BALANCE_CACHE_SECS).
+struct FaucetBalanceRequest {
+    reply: tokio::sync::oneshot::Sender<()>,
+}
+
+/// Everything a faucet's worker thread can be asked to do.
+enum WorkerRequest {
+    Mint(MintRequest),
+    TxDiagnostics(TxDiagnosticsRequest),
+    TxStatus(TxStatusRequest),
+    CheckFaucets(CheckFaucetsRequest),
+    FaucetBalance(FaucetBalanceRequest),
 }
 
 // ---------------------------------------------------------------------------
@@ -91,12 +252,212 @@ struct MintRequest {
 // ---------------------------------------------------------------------------
 #[derive(Clone)]
 struct AppState {
-    /// Channel to the worker thread that owns the Miden client
-    mint_tx: Arc<std::sync::mpsc::Sender<MintRequest>>,
-    /// Cached on-chain faucet status (populated at startup via worker)
-    faucet_status: Arc<HashMap<String, bool>>,
+    /// One worker thread per faucet symbol, each owning its own Miden client
+    workers: Arc<Mutex<HashMap<String, FaucetWorker>>>,
+    /// What /admin/reload_faucets needs to start workers for new faucets
+    worker_ctx: WorkerContext,
+    /// Faucet definitions and their cached on-chain status (checked by the
+    /// worker at startup and on every reload)
+    faucets: Arc<Mutex<FaucetSet>>,
     /// Rate limit tracker: key = "account_id:token_symbol"
-    rate_limits: Arc<Mutex<HashMap<String, RateLimitEntry>>>,
+    rate_limits: Arc<RateLimitStore>,
+    /// Seconds an account waits between claims of a token (0 = no cooldown)
+    request_cooldown_secs: u64,
+    /// Proxies whose X-Forwarded-For names the client (FAUCET_TRUSTED_PROXIES)
+    trusted_proxies: Arc<Vec<IpAddr>>,
+    /// Bearer token for /admin/* routes (FAUCET_ADMIN_TOKEN); admin routes are
+    /// disabled when unset
+    admin_token: Option<Arc<String>>,
+    /// Claim history and decisions for the abuse heuristics
+    abuse: Arc<Mutex<AbuseTracker>>,
+    abuse_config: AbuseConfig,
+    /// Issued /pow challenges: key = "challenge:account_id"; each is used once
+    pow_challenges: Arc<Mutex<HashMap<String, IssuedChallenge>>>,
+    /// Serializes slow-queue claims
+    slow_queue: Arc<tokio::sync::Semaphore>,
+    /// Issued/remaining supply per symbol, refreshed by the worker
+    faucet_supply: Arc<Mutex<HashMap<String, FaucetSupply>>>,
+    /// Mints sent to the worker and not yet picked up
+    mint_queue: Arc<MintQueue>,
+    /// Claims past validation and not yet answered: key = (normalized
+    /// account_id, token_symbol); a second claim for the same pair gets 409
+    in_flight_mints: Arc<Mutex<HashSet<(String, String)>>>,
+    /// Counters for /metrics, updated by handlers and workers
+    metrics: Arc<Mutex<FaucetMetrics>>,
+    /// Every mint outcome, written by the workers
+    mint_history: Arc<MintHistory>,
+    /// Set on Ctrl-C/SIGTERM; counts requests the worker still has to answer
+    shutdown: Arc<Shutdown>,
+}
+
+impl AppState {
+    /// Hand a request to `symbol`'s worker, counting it as in flight until
+    /// answered. False when there is no such worker or it is gone.
+    fn send_to_worker(&self, symbol: &str, req: WorkerRequest) -> bool {
+        let Some(tx) = self.workers.lock().unwrap().get(symbol).map(|w| w.tx.clone()) else {
+            return false;
+        };
+        self.shutdown.begin();
+        if tx.send(req).is_err() {
+            self.shutdown.cancel();
+            return false;
+        }
+        true
+    }
+
+    /// Send a request built by `make` to every worker; returns the reply
+    /// receivers of the workers that accepted it
+    fn send_to_all<T>(
+        &self,
+        make: impl Fn(tokio::sync::oneshot::Sender<T>) -> WorkerRequest,
+    ) -> Vec<tokio::sync::oneshot::Receiver<T>> {
+        let symbols: Vec<String> = self.workers.lock().unwrap().keys().cloned().collect();
+        symbols
+            .iter()
+            .filter_map(|symbol| {
+                let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+                self.send_to_worker(symbol, make(reply_tx)).then_some(reply_rx)
+            })
+            .collect()
+    }
+}
+
+/// A claim's slot in the mint queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct QueueTicket(u64);
+
+#[derive(Default)]
+struct QueuedMints {
+    /// Enqueue time per ticket; tickets are issued in order, so the first is the oldest
+    slots: BTreeMap<QueueTicket, Instant>,
+    next: u64,
+}
+
+/// Enqueue times of mints waiting for any faucet's worker. Handlers take a
+/// ticket before sending a mint; the worker that takes the mint gives back
+/// that ticket, so interleaved tokens never free each other's slots.
+struct MintQueue {
+    queued: Mutex<QueuedMints>,
+    max_depth: usize,
+}
+
+impl MintQueue {
+    fn new(max_depth: usize) -> Self {
+        Self { queued: Mutex::new(QueuedMints::default()), max_depth }
+    }
+
+    /// Take a queue slot; `None` when the queue is full
+    fn try_push(&self) -> Option<QueueTicket> {
+        let mut queued = self.queued.lock().unwrap();
+        if queued.slots.len() >= self.max_depth {
+            return None;
+        }
+        let ticket = QueueTicket(queued.next);
+        queued.next += 1;
+        queued.slots.insert(ticket, Instant::now());
+        Some(ticket)
+    }
+
+    /// Give back a slot whose mint never reached the worker
+    fn cancel(&self, ticket: QueueTicket) {
+        self.queued.lock().unwrap().slots.remove(&ticket);
+    }
+
+    /// A worker picked up the mint holding `ticket`
+    fn take(&self, ticket: QueueTicket) {
+        self.queued.lock().unwrap().slots.remove(&ticket);
+    }
+
+    fn depth(&self) -> usize {
+        self.queued.lock().unwrap().slots.len()
+    }
+
+    fn oldest_age(&self) -> Option<Duration> {
+        self.queued.lock().unwrap().slots.values().next().map(Instant::elapsed)
+    }
+}
+
+/// Take a mint queue slot for a claim. When the queue is full the claim's
+/// rate-limit reservation is given back and the 503 body returned.
+fn take_queue_slot(queue: &MintQueue, release: impl FnOnce()) -> Result<QueueTicket, JsonValue> {
+    if let Some(ticket) = queue.try_push() {
+        return Ok(ticket);
+    }
+    release();
+    Err(json!({
+        "error": "Faucet is busy, too many mints are queued. Try again shortly.",
+        "code": "queue_full",
+        "queue_depth": queue.depth(),
+        "retry_after": MINT_QUEUE_RETRY_AFTER_SECS,
+    }))
+}
+
+/// One faucet's supply as last read by the worker (base units)
+#[derive(Clone, Serialize)]
+struct FaucetSupply {
+    issued: u64,
+    max_supply: u64,
+    remaining_supply: u64,
+    /// Unix seconds of the read
+    last_updated: u64,
+}
+
+impl FaucetSupply {
+    /// Less than LOW_SUPPLY_BPS of max supply left to mint
+    fn is_low(&self) -> bool {
+        (self.remaining_supply as u128) * 10_000 < (self.max_supply as u128) * LOW_SUPPLY_BPS as u128
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.remaining_supply == 0
+    }
+
+    /// Count a submitted mint before the next read sees it
+    fn add_issued(&mut self, amount: u64) {
+        self.issued = self.issued.saturating_add(amount);
+        self.remaining_supply = self.max_supply.saturating_sub(self.issued);
+    }
+}
+
+/// A /pow challenge waiting to be redeemed by /get_tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IssuedChallenge {
+    target: u64,
+    hardened: bool,
+    expires_at: u64,
+}
+
+/// Record a challenge issued to `account_id` at `now`, dropping expired ones.
+/// Returns when it expires.
+fn issue_challenge(
+    challenges: &mut HashMap<String, IssuedChallenge>,
+    challenge: &str,
+    account_id: &str,
+    target: u64,
+    hardened: bool,
+    now: u64,
+) -> u64 {
+    let ttl = if hardened { HARDENED_CHALLENGE_TTL_SECS } else { CHALLENGE_TTL_SECS };
+    challenges.retain(|_, c| c.expires_at >= now);
+    challenges.insert(
+        format!("{}:{}", challenge, account_id),
+        IssuedChallenge { target, hardened, expires_at: now + ttl },
+    );
+    now + ttl
+}
+
+/// Take a challenge issued to `account_id` out of the map, so it can be used
+/// only once. `None` if it was never issued to that account, was already
+/// used or has expired.
+fn redeem_challenge(
+    challenges: &mut HashMap<String, IssuedChallenge>,
+    challenge: &str,
+    account_id: &str,
+    now: u64,
+) -> Option<IssuedChallenge> {
+    challenges
+        .remove(&format!("{}:{}", challenge, account_id))
+        .filter(|c| c.expires_at >= now)
 }
 
 // ---------------------------------------------------------------------------
@@ -105,436 +466,3632 @@ struct AppState {
 #[derive(Deserialize)]
 struct PowParams {
     account_id: String,
-    #[allow(dead_code)]
     amount: Option<String>,
     token_symbol: Option<String>,
 }
 
+/// Query of GET /allowance
+#[derive(Deserialize)]
+struct AllowanceParams {
+    account_id: String,
+    token_symbol: Option<String>,
+}
+
+/// Query string of GET /get_tokens or JSON body of POST /get_tokens.
+/// Amount, nonce and note type may be JSON numbers/booleans in the body.
 #[derive(Deserialize)]
 struct GetTokensParams {
     account_id: String,
-    #[allow(dead_code)]
+    /// "true" (or 1) mints a private note
+    #[serde(default, deserialize_with = "opt_scalar_string")]
     is_private_note: Option<String>,
+    /// Base units; kept for existing clients
+    #[serde(default, deserialize_with = "opt_scalar_string")]
     asset_amount: Option<String>,
+    /// Whole tokens as a decimal string ("2.5"), converted with the token's
+    /// decimals; mutually exclusive with `asset_amount`
+    #[serde(default, deserialize_with = "opt_scalar_string")]
+    amount_tokens: Option<String>,
     challenge: String,
-    #[allow(dead_code)]
+    #[serde(deserialize_with = "scalar_string")]
     nonce: String,
     token_symbol: Option<String>,
+    /// "true" (or 1) answers once the mint committed (up to `COMMIT_WAIT_SECS`)
+    /// instead of right after submitting it
+    #[serde(default, deserialize_with = "opt_scalar_string")]
+    wait: Option<String>,
+}
+
+/// Query of GET /get_tokens_batch: an amount per token to mint, one PoW
+/// challenge for all of them
+#[derive(Deserialize)]
+struct GetTokensBatchParams {
+    account_id: String,
+    #[serde(default)]
+    milo: Option<String>,
+    #[serde(default)]
+    melo: Option<String>,
+    #[serde(default)]
+    musdc: Option<String>,
+    #[serde(default)]
+    is_private_note: Option<String>,
+    challenge: String,
+    nonce: String,
+}
+
+/// A string, number or boolean, kept as its string form
+fn scalar_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::String(s) => Ok(s),
+        JsonValue::Number(n) => Ok(n.to_string()),
+        JsonValue::Bool(b) => Ok(b.to_string()),
+        other => Err(serde::de::Error::custom(format!("expected a string or number, got {}", other))),
+    }
+}
+
+fn opt_scalar_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::Null => Ok(None),
+        value => scalar_string(value).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Body of POST /admin/register_faucet; caps are in base units
+#[derive(Deserialize)]
+struct RegisterFaucetParams {
+    symbol: String,
+    faucet_id_hex: String,
+    decimals: u64,
+    /// Omitted means 0: registered but mint-disabled until /admin/limits sets it
+    #[serde(default)]
+    daily_cap: u64,
+    #[serde(default)]
+    max_per_request: Option<u64>,
+}
+
+/// Body of POST /admin/limits; caps are in base units
+#[derive(Deserialize)]
+struct SetLimitsParams {
+    token_symbol: String,
+    daily_cap: u64,
+    /// Largest single claim; omitted means the daily cap
+    #[serde(default)]
+    per_request_cap: Option<u64>,
+    /// Shared by all accounts from one IP; omitted keeps the current setting
+    #[serde(default)]
+    ip_daily_cap: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct HistoryParams {
+    account_id: String,
+    limit: Option<usize>,
+    /// `next_before` of the previous page
+    before: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct RecentMintsParams {
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TxDiagnosticsParams {
+    tx_id: String,
+}
+
+#[derive(Deserialize)]
+struct TxStatusParams {
+    tx_id: String,
+    /// Sync the worker's client first instead of relying on the node's notes
+    #[serde(default)]
+    sync: bool,
 }
 
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
-/// Parse an account-ID that arrives as "0x…" or raw hex digits.
-/// Bech32 (mtst1…) is NOT supported — frontend must send the hex wallet ID.
+const ACCOUNT_ID_FORMATS: &str = "hex wallet ID (0x… or bare hex) or testnet bech32 address (mtst1…)";
+
+/// Parse an account-ID that arrives as "0x…", raw hex digits or a testnet
+/// bech32 address (mtst1…), as shown by wallet UIs.
 fn parse_account_id(s: &str) -> Result<AccountId, String> {
+    let s = s.trim();
+    let is_hex = !s.is_empty() && s.trim_start_matches("0x").trim_start_matches("0X").chars().all(|c| c.is_ascii_hexdigit());
+    if !is_hex {
+        // bech32 is case-insensitive, but a mixed-case string is invalid
+        if s.chars().any(|c| c.is_ascii_uppercase()) && s.chars().any(|c| c.is_ascii_lowercase()) {
+            return Err(format!("Invalid bech32 address: mixed upper and lower case. Expected a {}", ACCOUNT_ID_FORMATS));
+        }
+        let lower = s.to_lowercase();
+        let looks_bech32 = lower
+            .split_once('1')
+            .is_some_and(|(hrp, _)| !hrp.is_empty() && hrp.chars().all(|c| c.is_ascii_lowercase()));
+        let (network, id) = AccountId::from_bech32(&lower).map_err(|e| {
+            if looks_bech32 {
+                format!("Invalid bech32 address (bad checksum or encoding: {}). Copy the full mtst1… address again or send the hex wallet ID", e)
+            } else {
+                format!("Invalid account ID ({}): expected a {}", e, ACCOUNT_ID_FORMATS)
+            }
+        })?;
+        if network != NetworkId::Testnet {
+            return Err(format!("account_id is a {} address; this faucet only serves testnet", network));
+        }
+        return Ok(id);
+    }
     let hex = if s.starts_with("0x") || s.starts_with("0X") {
         s.to_owned()
-    } else if s.chars().all(|c| c.is_ascii_hexdigit()) && !s.is_empty() {
-        format!("0x{}", s)
     } else {
-        return Err(
-            "account_id must be hex (0x…). Send the wallet ID, not the bech32 address."
-                .to_string(),
-        );
+        format!("0x{}", s)
     };
-    AccountId::from_hex(&hex).map_err(|e| format!("Invalid account ID: {}", e))
+    AccountId::from_hex(&hex)
+        .map_err(|e| format!("Invalid account ID ({}): expected a {}", e, ACCOUNT_ID_FORMATS))
 }
 
-fn generate_challenge() -> String {
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let mut bytes = [0u8; 32];
-    bytes[0..8].copy_from_slice(&ts.to_le_bytes());
-    for i in 8..32 {
-        bytes[i] = (ts as u8) ^ (i as u8);
+/// Base units of a decimal token amount ("2.5" → 250000000 at 8 decimals).
+/// More fractional digits than the token has are refused, not rounded.
+fn parse_token_amount(s: &str, decimals: u64) -> Result<u64, String> {
+    let s = s.trim();
+    let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && frac.is_empty()) || !is_digits(whole) || !is_digits(frac) {
+        return Err(format!("bad amount_tokens {:?}: expected a decimal number like \"2.5\"", s));
+    }
+    if frac.len() as u64 > decimals {
+        return Err(format!("amount_tokens {} has more than {} decimal places", s, decimals));
     }
-    hex::encode(bytes)
+    let overflow = || format!("amount_tokens {} is too large", s);
+    let scale = u32::try_from(decimals).ok().and_then(|d| 10u64.checked_pow(d)).ok_or_else(overflow)?;
+    let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| overflow())? };
+    // Right-pad the fraction to `decimals` digits: "5" at 8 decimals is 50000000
+    let frac: u64 = if frac.is_empty() {
+        0
+    } else {
+        let padded = format!("{:0<width$}", frac, width = decimals as usize);
+        padded.parse().map_err(|_| overflow())?
+    };
+    whole.checked_mul(scale).and_then(|w| w.checked_add(frac)).ok_or_else(overflow)
 }
 
-// ---------------------------------------------------------------------------
-// Main
-// ---------------------------------------------------------------------------
-#[tokio::main]
-async fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let port: u16 = args.get(1).and_then(|p| p.parse().ok()).unwrap_or(8084);
+/// Decimal form of a base-unit amount, without trailing zeros (250000000 → "2.5")
+fn format_token_amount(amount: u64, decimals: u64) -> String {
+    let Some(scale) = u32::try_from(decimals).ok().and_then(|d| 10u64.checked_pow(d)) else {
+        return amount.to_string();
+    };
+    let (whole, frac) = (amount / scale, amount % scale);
+    if frac == 0 {
+        return whole.to_string();
+    }
+    let frac = format!("{:0width$}", frac, width = decimals as usize);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
 
-    println!("🚀 Milo Swap Faucet API Server Başlıyor…");
-    println!("   Keystore : {}", KEYSTORE_PATH);
-    println!("   Store    : {}", STORE_PATH);
+/// Lowercase "0x…" form used as the key for rate limits and abuse history.
+/// Hex and bech32 forms of the same account map to the same key.
+fn normalize_account_id(s: &str) -> String {
+    if let Ok(id) = parse_account_id(s) {
+        return id.to_hex();
+    }
+    if s.starts_with("0x") || s.starts_with("0X") {
+        s.to_lowercase()
+    } else {
+        format!("0x{}", s.to_lowercase())
+    }
+}
 
-    // Store persists across restarts (contains faucet accounts & sync state)
-    let store_exists = std::path::Path::new(STORE_PATH).exists();
-    println!("   💾 Store: {}", if store_exists { "mevcut (reusing)" } else { "yeni oluşturulacak" });
+/// Key of an account's daily total for `token` in the rate limit store
+fn account_rate_key(account_id: &str, token: &str) -> String {
+    format!("{}:{}", normalize_account_id(account_id), token)
+}
 
-    // Ensure keystore dir exists and report key count
-    if !std::path::Path::new(KEYSTORE_PATH).exists() {
-        fs::create_dir_all(KEYSTORE_PATH).ok();
+/// Note type a claim asked for: private only for `is_private_note=true` (or `1`)
+fn claim_note_type(is_private_note: Option<&str>) -> NoteType {
+    if matches!(is_private_note.map(str::to_lowercase).as_deref(), Some("true" | "1")) {
+        NoteType::Private
+    } else {
+        NoteType::Public
     }
-    let key_count = fs::read_dir(KEYSTORE_PATH)
-        .map(|it| it.filter(|e| e.is_ok()).count())
-        .unwrap_or(0);
-    println!("📂 Keystore'da {} key dosyası var", key_count);
-    if key_count == 0 {
-        println!("⚠️  Keystore boş — minting çalışmayacak.");
-    }
-
-    // ── channels ────────────────────────────────────────────────────────
-    // health: worker → main   (faucet-status map, sent once at startup)
-    // mint:   main   → worker (one request at a time; processed sequentially)
-    let (health_tx, health_rx) = std::sync::mpsc::channel::<HashMap<String, bool>>();
-    let (mint_tx, mint_rx) = std::sync::mpsc::channel::<MintRequest>();
-
-    // ── worker thread ── owns the !Send Miden client ────────────────────
-    std::thread::spawn(move || {
-        // Own tokio runtime for this thread; block_on drives each future
-        // to completion before we move on — no concurrent access to client.
-        let rt = tokio::runtime::Runtime::new().expect("tokio runtime failed");
-
-        // ── build Miden client ──────────────────────────────────────────
-        println!("\n🔧 Miden client başlatılıyor… (worker)");
-        let mut client = rt.block_on(async {
-            let endpoint = Endpoint::testnet();
-            let rpc_api = Arc::new(GrpcClient::new(&endpoint, 60_000));
-            let keystore = Arc::new(
-                FilesystemKeyStore::<StdRng>::new(PathBuf::from(KEYSTORE_PATH))
-                    .expect("Keystore oluşturulamadı"),
-            );
-            ClientBuilder::new()
-                .rpc(rpc_api)
-                .authenticator(keystore)
-                .sqlite_store(STORE_PATH.into())
-                .build()
-                .await
-                .expect("Miden client oluşturulamadı")
-        });
-        println!("   ✅ client hazır");
-
-        // ── verify each faucet on-chain ──────────────────────────────────
-        println!("\n🔍 Faucet hesapları kontrol ediliyor…");
-        let status_map = rt.block_on(async {
-            let mut m = HashMap::new();
-            for (sym, id_hex, _) in FAUCETS {
-                print!("   {} … ", sym);
-                let ok = match AccountId::from_hex(id_hex) {
-                    Ok(id) => client.import_account_by_id(id).await.is_ok(),
-                    Err(_) => false,
-                };
-                println!("{}", if ok { "✅ aktif" } else { "❌ bulunamadı" });
-                m.insert(sym.to_string(), ok);
-            }
-            m
-        });
+}
 
-        // Store already contains sync state from integration/store.sqlite3
-        // No need to sync_state() on every restart (avoids MMR bug)
+/// Requested token symbol, uppercased; MILO when none is given
+fn claim_token_symbol(token_symbol: Option<&str>) -> String {
+    token_symbol.unwrap_or("MILO").to_uppercase()
+}
 
-        // Send health results back to main thread so axum can start
-        health_tx.send(status_map).expect("main dropped health_rx");
+/// An account's allowance for one token over the trailing 24 hours, as the
+/// claim path enforces it
+struct Allowance {
+    daily_cap: u64,
+    used: u64,
+    /// When the oldest grant in the window expires (now if there is none)
+    reset_at: u64,
+}
 
-        // ── mint request loop ────────────────────────────────────────────
-        println!("🔄 Worker: mint istekleri beklenyor…");
-        loop {
-            let req = match mint_rx.recv() {
-                Ok(r) => r,
-                Err(_) => {
-                    println!("🔄 Worker: channel kapatıldı, çıkıyor.");
-                    break;
-                }
-            };
+impl Allowance {
+    fn remaining(&self) -> u64 {
+        self.daily_cap.saturating_sub(self.used)
+    }
 
-            // Destructure so the async block only borrows the fields it needs;
-            // `reply` stays outside and is used after block_on returns.
-            let MintRequest {
-                faucet_id_hex,
-                recipient_id_hex,
-                amount,
-                token_symbol,
-                reply,
-            } = req;
+    /// The allowance as `X-RateLimit-*` headers
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static("x-ratelimit-limit"), HeaderValue::from(self.daily_cap));
+        headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(self.remaining()));
+        headers.insert(HeaderName::from_static("x-ratelimit-reset"), HeaderValue::from(self.reset_at));
+        headers
+    }
+}
 
-            println!(
-                "   🔄 Worker: mint {} {} → {}",
-                amount, token_symbol, recipient_id_hex
-            );
+fn account_allowance(
+    rate_limits: &RateLimitStore,
+    account_id: &str,
+    token: &str,
+    daily_cap: u64,
+    now: u64,
+) -> rusqlite::Result<Allowance> {
+    let usage = rate_limits.usage(&account_rate_key(account_id, token), now)?;
+    Ok(Allowance { daily_cap, used: usage.used, reset_at: usage.reset_at })
+}
 
-            let result: Result<String, String> = rt.block_on(async {
-                let faucet_id = AccountId::from_hex(&faucet_id_hex)
-                    .map_err(|e| format!("bad faucet_id: {}", e))?;
-                let recipient_id = parse_account_id(&recipient_id_hex)?;
-
-                let asset =
-                    FungibleAsset::new(faucet_id, amount).map_err(|e| format!("asset: {}", e))?;
-
-                let tx_request = TransactionRequestBuilder::new()
-                    .build_mint_fungible_asset(
-                        asset,
-                        recipient_id,
-                        NoteType::Public,
-                        client.rng(),
-                    )
-                    .map_err(|e| format!("build mint tx: {}", e))?;
-
-                client
-                    .submit_new_transaction(faucet_id, tx_request)
-                    .await
-                    .map(|tx_id| tx_id.to_hex())
-                    .map_err(|e| format!("{:?}", e))
-            });
+/// `X-RateLimit-*` headers for a /get_tokens response: the account's cap,
+/// what's left of it over the last 24 hours and when more frees up. Empty for unknown tokens, the
+/// admin account and store errors.
+fn rate_limit_headers(state: &AppState, account_id: &str, token: &str) -> HeaderMap {
+    if normalize_account_id(account_id) == ADMIN_ACCOUNT_ID.to_lowercase() {
+        return HeaderMap::new();
+    }
+    let Some(daily_cap) = state.faucets.lock().unwrap().active(token).map(|f| f.daily_cap) else {
+        return HeaderMap::new();
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    match account_allowance(&state.rate_limits, account_id, token, daily_cap, now) {
+        Ok(allowance) => allowance.headers(),
+        Err(e) => {
+            warn!(error = %e, "rate limit headers");
+            HeaderMap::new()
+        }
+    }
+}
 
-            match &result {
-                Ok(tx_id) => println!("   ✅ Worker: tx {}…", &tx_id[..16.min(tx_id.len())]),
-                Err(e) => println!("   ❌ Worker: {}", e),
-            }
+/// Lowercase "0x…" transaction ID, or None unless it is 32 bytes of hex
+fn normalize_tx_id(s: &str) -> Option<String> {
+    let raw = s.trim().to_lowercase();
+    let hex = if raw.starts_with("0x") { raw } else { format!("0x{}", raw) };
+    (hex.len() == 66 && hex[2..].chars().all(|c| c.is_ascii_hexdigit())).then_some(hex)
+}
 
-            reply.send(result).ok();
-        }
-    });
+/// Key of an IP's daily total for `token`; shared by every account claiming from it
+fn ip_rate_key(ip: IpAddr, token: &str) -> String {
+    format!("ip:{}:{}", ip, token)
+}
+
+/// The account's and the IP's cap a claim must both fit under
+fn claim_limits<'a>(faucet: &FaucetEntry, rate_key: &'a str, ip_key: &'a str, cooldown_secs: u64) -> [Limit<'a>; 2] {
+    [
+        Limit { key: rate_key, max: faucet.daily_cap, min_interval_secs: cooldown_secs },
+        Limit { key: ip_key, max: faucet.ip_daily_cap(), min_interval_secs: 0 },
+    ]
+}
 
-    // ── wait for worker's health-check results ──────────────────────────
-    let faucet_status = health_rx
-        .recv()
-        .expect("Worker thread crashed before health check");
+/// Hash of the PoW scheme, reported by /pow
+const POW_ALGORITHM: &str = "sha256";
+/// What /pow tells clients to compute; must match `verify_pow`
+const POW_SCHEME: &str =
+    "sha256(challenge_bytes || nonce as u64 big-endian); first 8 bytes as big-endian u64 must be < target * 1000";
 
-    let state = AppState {
-        mint_tx: Arc::new(mint_tx),
-        faucet_status: Arc::new(faucet_status),
-        rate_limits: Arc::new(Mutex::new(HashMap::new())),
+/// Same check as the frontend: first 8 bytes of sha256(challenge ‖ nonce_be)
+/// read big-endian must be below `target * 1000`. A malformed challenge or
+/// nonce fails verification.
+fn verify_pow(challenge_hex: &str, nonce: &str, target: u64) -> bool {
+    let Ok(challenge) = hex::decode(challenge_hex.trim_start_matches("0x")) else {
+        return false;
     };
+    let Ok(nonce) = nonce.parse::<u64>() else {
+        return false;
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(&challenge);
+    hasher.update(nonce.to_be_bytes());
+    let hash = hasher.finalize();
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&hash[..8]);
+    u64::from_be_bytes(head) < target.saturating_mul(1000)
+}
 
-    // ── axum router ─────────────────────────────────────────────────────
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+/// Require `Authorization: Bearer <FAUCET_ADMIN_TOKEN>` on admin routes.
+fn check_admin(headers: &HeaderMap, state: &AppState) -> Result<(), (StatusCode, Json<JsonValue>)> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "Admin endpoints disabled (FAUCET_ADMIN_TOKEN not set)" })),
+        ));
+    };
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided != Some(expected.as_str()) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Invalid or missing admin token" })),
+        ));
+    }
+    Ok(())
+}
 
-    let app = Router::new()
-        .route("/", get(|| async { "Milo Faucet API — /health /pow /get_tokens" }))
-        .route("/health", get(health_handler))
-        .route("/pow", get(pow_handler))
-        .route("/get_tokens", get(get_tokens_handler))
-        .layer(cors)
-        .with_state(state);
+/// Random, so challenges can't be predicted or precomputed
+fn generate_challenge() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    println!("\n🌐 http://{}", addr);
-    println!("🛑 Ctrl+C ile dur\n");
+/// Worker side of `/admin/tx_diagnostics`: the local transaction record, the
+/// submitting account's local vs on-chain state, and which output notes the
+/// node knows about.
+async fn tx_diagnostics(
+    client: &miden_client::Client<FilesystemKeyStore<StdRng>>,
+    rpc_api: &GrpcClient,
+    tx_id_hex: &str,
+) -> Result<Option<JsonValue>, String> {
+    let transactions = client
+        .get_transactions(TransactionFilter::All)
+        .await
+        .map_err(|e| format!("get_transactions: {}", e))?;
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
+    let Some(record) = transactions.into_iter().find(|tx| tx.id.to_hex() == tx_id_hex) else {
+        return Ok(None);
+    };
 
-// ---------------------------------------------------------------------------
-// Handlers
-// ---------------------------------------------------------------------------
+    let account = compare_account_state(client, rpc_api, record.details.account_id).await?;
 
-async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
-    let faucets: Vec<JsonValue> = FAUCETS
+    let note_ids: Vec<_> = record.details.output_notes.iter().map(|n| n.id()).collect();
+    let on_chain_ids: Vec<String> = if note_ids.is_empty() {
+        Vec::new()
+    } else {
+        rpc_api
+            .get_notes_by_id(&note_ids)
+            .await
+            .map_err(|e| format!("get_notes_by_id: {}", e))?
+            .iter()
+            .map(|n| n.id().to_hex())
+            .collect()
+    };
+    let output_notes: Vec<JsonValue> = note_ids
         .iter()
-        .map(|(sym, id, decimals)| {
-            let active = state.faucet_status.get(*sym).copied().unwrap_or(false);
-            json!({
-                "symbol": sym,
-                "faucet_id": id,
-                "status": if active { "active" } else { "not_found" },
-                "decimals": decimals,
-            })
+        .map(|id| {
+            let hex = id.to_hex();
+            json!({ "note_id": hex, "on_chain": on_chain_ids.contains(&hex) })
         })
         .collect();
-    Json(json!({ "status": "ok", "faucets": faucets }))
-}
 
-async fn pow_handler(
-    Query(params): Query<PowParams>,
-    State(state): State<AppState>,
-) -> (StatusCode, Json<JsonValue>) {
-    let token = params
-        .token_symbol
-        .as_deref()
-        .unwrap_or("MILO")
-        .to_uppercase();
+    Ok(Some(json!({
+        "tx_id": tx_id_hex,
+        "local_record": {
+            "status": format!("{:?}", record.status),
+            "account_id": record.details.account_id.to_hex(),
+            "submission_height": record.details.submission_height.as_u32(),
+            "expiration_block_num": record.details.expiration_block_num.as_u32(),
+        },
+        "account": account,
+        "output_notes": output_notes,
+    })))
+}
 
-    if !state.faucet_status.get(&token).copied().unwrap_or(false) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "error": format!("Faucet {} not available", token) })),
-        );
+/// Worker side of `/tx_status`. The worker only syncs when asked (`sync`),
+/// so its local record can stay `Pending` after the mint landed; the minted
+/// notes showing up on chain (asked from the node directly) count as
+/// committed too.
+async fn tx_status(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    rpc_api: &GrpcClient,
+    tx_id_hex: &str,
+    sync: bool,
+) -> Result<Option<JsonValue>, String> {
+    if sync {
+        if let Err(e) = client.sync_state().await {
+            warn!(error = %e, "tx_status sync failed, using local state");
+        }
     }
+    let transactions = client
+        .get_transactions(TransactionFilter::All)
+        .await
+        .map_err(|e| format!("get_transactions: {}", e))?;
+    let Some(record) = transactions.into_iter().find(|tx| tx.id.to_hex() == tx_id_hex) else {
+        return Ok(None);
+    };
 
-    println!("📩 /pow  token={}  account={}", token, params.account_id);
+    let note_ids: Vec<_> = record.details.output_notes.iter().map(|n| n.id()).collect();
+    let on_chain = if note_ids.is_empty() {
+        0
+    } else {
+        rpc_api
+            .get_notes_by_id(&note_ids)
+            .await
+            .map_err(|e| format!("get_notes_by_id: {}", e))?
+            .len()
+    };
+    let has_outputs = !note_ids.is_empty() && on_chain == note_ids.len();
 
-    (
-        StatusCode::OK,
-        Json(json!({
-            "challenge": generate_challenge(),
-            "target": 1000u64,
-            "timestamp": SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-        })),
-    )
-}
+    let (status, block_num) = match record.status {
+        TransactionStatus::Discarded(_) => ("discarded", None),
+        TransactionStatus::Committed { block_number, .. } => ("committed", Some(block_number.as_u32())),
+        _ if has_outputs => ("committed", None),
+        _ => ("pending", None),
+    };
 
-/// **GET /get_tokens** — dispatches a mint request to the worker thread and
-/// awaits the on-chain transaction result via a oneshot channel.
-async fn get_tokens_handler(
-    Query(params): Query<GetTokensParams>,
-    State(state): State<AppState>,
-) -> (StatusCode, Json<JsonValue>) {
-    let token = params
-        .token_symbol
-        .as_deref()
-        .unwrap_or("MILO")
-        .to_uppercase();
+    Ok(Some(json!({
+        "tx_id": tx_id_hex,
+        "status": status,
+        "block_num": block_num,
+        "has_outputs": has_outputs,
+        "output_notes": note_ids.iter().map(|id| id.to_hex()).collect::<Vec<_>>(),
+    })))
+}
 
-    println!(
-        "💰 /get_tokens  token={}  account={}  amount={:?}",
-        token, params.account_id, params.asset_amount
-    );
+/// Import every faucet in `config` into the client; a faucet that can't be
+/// fetched from the node is reported as not found.
+async fn check_faucets(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    config: &FaucetsConfig,
+) -> HashMap<String, bool> {
+    let mut status = HashMap::new();
+    for faucet in &config.faucets {
+        let ok = match AccountId::from_hex(&faucet.faucet_id) {
+            Ok(id) => client.import_account_by_id(id).await.is_ok(),
+            Err(_) => false,
+        };
+        if ok {
+            info!(token = %faucet.symbol, faucet_id = %faucet.faucet_id, "faucet hesabı aktif");
+        } else {
+            warn!(token = %faucet.symbol, faucet_id = %faucet.faucet_id, "faucet hesabı bulunamadı");
+        }
+        status.insert(faucet.symbol.clone(), ok);
+    }
+    status
+}
 
-    // ── validate token ──────────────────────────────────────────────────
-    if !state.faucet_status.get(&token).copied().unwrap_or(false) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(json!({ "error": format!("Faucet {} not available", token) })),
-        );
+/// Check a worker's faucet and publish the result; true when it exists on-chain
+async fn check_faucet(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    config: &FaucetsConfig,
+    faucets: &Mutex<FaucetSet>,
+) -> bool {
+    let status = check_faucets(client, config).await;
+    let mut set = faucets.lock().unwrap();
+    let mut active = false;
+    for (symbol, ok) in status {
+        set.publish(&symbol, ok);
+        active |= ok;
     }
+    active
+}
 
-    let faucet_id_hex = match FAUCETS.iter().find(|(s, _, _)| *s == token) {
-        Some((_, id, _)) => *id,
-        None => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({ "error": format!("Unknown token {}", token) })),
-            )
+/// `first` plus whatever mints are already queued behind it, up to `max`.
+/// Other requests pulled off the channel meanwhile go to `deferred`.
+fn collect_mint_batch(
+    first: MintRequest,
+    rx: &std::sync::mpsc::Receiver<WorkerRequest>,
+    max: usize,
+    queue: &MintQueue,
+    deferred: &mut VecDeque<WorkerRequest>,
+) -> Vec<MintRequest> {
+    let mut batch = vec![first];
+    while batch.len() < max {
+        match rx.try_recv() {
+            Ok(WorkerRequest::Mint(r)) => {
+                queue.take(r.queue_ticket);
+                batch.push(r);
+            }
+            Ok(other) => deferred.push_back(other),
+            Err(_) => break,
         }
-    };
-
-    // ── validate recipient ──────────────────────────────────────────────
-    if let Err(e) = parse_account_id(&params.account_id) {
-        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
     }
+    batch
+}
 
-    // ── parse amount ────────────────────────────────────────────────────
-    let amount: u64 = match params.asset_amount.as_deref().unwrap_or("100").parse() {
-        Ok(a) if a > 0 => a,
-        Ok(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({ "error": "amount must be > 0" })),
-            )
+/// Split a batch of mint requests by faucet, keeping arrival order within each
+fn group_mints_by_faucet(batch: Vec<MintRequest>) -> Vec<(String, Vec<MintRequest>)> {
+    let mut groups: Vec<(String, Vec<MintRequest>)> = Vec::new();
+    for req in batch {
+        match groups.iter_mut().find(|(faucet_id, _)| *faucet_id == req.faucet_id_hex) {
+            Some((_, group)) => group.push(req),
+            None => groups.push((req.faucet_id_hex.clone(), vec![req])),
         }
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({ "error": format!("bad amount: {}", e) })),
-            )
+    }
+    groups
+}
+
+/// Split off the mints that don't fit in their faucet's remaining supply, as
+/// last read (plus mints submitted since), taking earlier requests of the
+/// group into account. Each refused mint comes with the supply it would have
+/// had left. Faucets whose supply was never read are not checked.
+fn split_by_supply(
+    supply: &Mutex<HashMap<String, FaucetSupply>>,
+    group: Vec<MintRequest>,
+) -> (Vec<MintRequest>, Vec<(MintRequest, u64)>) {
+    let supply = supply.lock().unwrap();
+    let mut claimed: HashMap<String, u64> = HashMap::new();
+    let (mut fits, mut exhausted) = (Vec::new(), Vec::new());
+    for req in group {
+        let Some(s) = supply.get(&req.token_symbol) else {
+            fits.push(req);
+            continue;
+        };
+        let claimed = claimed.entry(req.token_symbol.clone()).or_insert(0);
+        let remaining = s.remaining_supply.saturating_sub(*claimed);
+        if req.amount > remaining {
+            exhausted.push((req, remaining));
+        } else {
+            *claimed += req.amount;
+            fits.push(req);
         }
-    };
+    }
+    (fits, exhausted)
+}
 
-    // ── rate limit check (admin is exempt) ────────────────────────────
-    let normalized_id = if params.account_id.starts_with("0x") || params.account_id.starts_with("0X") {
-        params.account_id.to_lowercase()
+/// A lone private mint that fails is reported as such, so the handler can
+/// reject the flag; in a batch the error can't be pinned on one request
+fn private_mint_prefix(reqs: &[MintRequest]) -> &'static str {
+    if reqs.len() == 1 && reqs[0].note_type == NoteType::Private {
+        PRIVATE_MINT_ERROR
     } else {
-        format!("0x{}", params.account_id.to_lowercase())
+        ""
+    }
+}
+
+/// One P2ID note per request, minting its amount from `faucet_id` to its
+/// recipient with the request's note type
+fn mint_notes(faucet_id: AccountId, reqs: &[MintRequest], rng: &mut impl FeltRng) -> Result<Vec<Note>, String> {
+    let prefix = private_mint_prefix(reqs);
+    reqs.iter()
+        .map(|req| {
+            let recipient_id = parse_account_id(&req.recipient_id_hex)?;
+            let asset = FungibleAsset::new(faucet_id, req.amount).map_err(|e| format!("asset: {}", e))?;
+            create_p2id_note(faucet_id, recipient_id, vec![asset.into()], req.note_type, Felt::new(0), rng)
+                .map_err(|e| format!("{}build mint note: {}", prefix, e))
+        })
+        .collect()
+}
+
+/// The details a private note's recipient has to import; `None` for public notes
+fn private_note_for(req: &MintRequest, note: &Note) -> Option<PrivateNote> {
+    (req.note_type == NoteType::Private).then(|| PrivateNote {
+        note_id: note.id().to_hex(),
+        note: hex::encode(note.to_bytes()),
+    })
+}
+
+/// One transaction creating every request's note, with each note's id and,
+/// for private mints, the details its recipient has to import
+fn mint_tx_request(
+    faucet_id: AccountId,
+    reqs: &[MintRequest],
+    rng: &mut impl FeltRng,
+) -> Result<(TransactionRequest, Vec<(String, Option<PrivateNote>)>), String> {
+    let notes = mint_notes(faucet_id, reqs, rng)?;
+    let receipt_notes = reqs
+        .iter()
+        .zip(&notes)
+        .map(|(req, note)| (note.id().to_hex(), private_note_for(req, note)))
+        .collect();
+    let tx_request = TransactionRequestBuilder::new()
+        .own_output_notes(notes.into_iter().map(OutputNote::Full).collect())
+        .build()
+        .map_err(|e| format!("{}build mint tx: {}", private_mint_prefix(reqs), e))?;
+    Ok((tx_request, receipt_notes))
+}
+
+/// Mint every request in one transaction from `faucet_id_hex`, one P2ID note
+/// per recipient. Returns a receipt per request (all with the same tx id,
+/// each with its own note id).
+async fn submit_mints(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    faucet_id_hex: &str,
+    reqs: &[MintRequest],
+) -> Result<Vec<MintReceipt>, String> {
+    let prefix = private_mint_prefix(reqs);
+    let faucet_id = AccountId::from_hex(faucet_id_hex)
+        .map_err(|e| format!("bad faucet_id: {}", e))?;
+
+    let (tx_request, receipt_notes) = mint_tx_request(faucet_id, reqs, client.rng())?;
+    let transaction_id = client
+        .submit_new_transaction(faucet_id, tx_request)
+        .await
+        .map_err(|e| format!("{}{:?}", prefix, e))?;
+    let tx_id = transaction_id.to_hex();
+
+    Ok(receipt_notes
+        .into_iter()
+        .map(|(note_id, private_note)| MintReceipt {
+            tx_id: tx_id.clone(),
+            transaction_id,
+            note_id,
+            private_note,
+            commit: None,
+        })
+        .collect())
+}
+
+/// Poll a mint until it commits, is discarded or `COMMIT_WAIT_SECS` pass
+async fn wait_for_commit(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    tx_id: TransactionId,
+) -> CommitStatus {
+    let deadline = Instant::now() + Duration::from_secs(COMMIT_WAIT_SECS);
+    loop {
+        if let Err(e) = client.sync_state().await {
+            warn!(error = %e, "sync while waiting for commit failed");
+        }
+        match client.get_transactions(TransactionFilter::Ids(vec![tx_id])).await {
+            Ok(records) => match records.first().map(|r| &r.status) {
+                Some(TransactionStatus::Committed { block_number, .. }) => {
+                    return CommitStatus { committed: true, block_num: Some(block_number.as_u32()) };
+                }
+                Some(TransactionStatus::Discarded(_)) => return CommitStatus { committed: false, block_num: None },
+                _ => {}
+            },
+            Err(e) => warn!(error = %e, "commit poll failed"),
+        }
+        if Instant::now() >= deadline {
+            return CommitStatus { committed: false, block_num: None };
+        }
+        tokio::time::sleep(Duration::from_millis(COMMIT_POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// Wait for the commit of a submitted group if any of its claims asked to
+/// (`wait=true`). The group shares one transaction, so it is polled once and
+/// the status goes on the receipts of the claims that asked.
+async fn await_commits(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    reqs: &[MintRequest],
+    receipts: &mut [MintReceipt],
+) {
+    let Some(tx_id) = reqs.iter().zip(receipts.iter()).find(|(req, _)| req.wait_for_commit).map(|(_, r)| r.transaction_id)
+    else {
+        return;
     };
-    let is_admin = normalized_id == ADMIN_ACCOUNT_ID.to_lowercase();
+    let status = wait_for_commit(client, tx_id).await;
+    info!(tx_id = %tx_id.to_hex(), committed = status.committed, block_num = ?status.block_num, "commit wait finished");
+    for (req, receipt) in reqs.iter().zip(receipts.iter_mut()) {
+        if req.wait_for_commit {
+            receipt.commit = Some(status);
+        }
+    }
+}
 
-    if !is_admin {
-        let rate_key = format!("{}:{}", normalized_id, token);
-        let today = current_day();
-        let mut limits = state.rate_limits.lock().unwrap();
-        let entry = limits.entry(rate_key).or_insert(RateLimitEntry {
-            total_amount: 0,
-            day: today,
-        });
+/// `submit_mints`, retried up to `MINT_MAX_RETRIES` times with backoff and a
+/// fresh sync while the error is transient. The class of the last error is
+/// returned with it.
+async fn submit_mints_with_retry(
+    client: &mut miden_client::Client<FilesystemKeyStore<StdRng>>,
+    faucet_id_hex: &str,
+    reqs: &[MintRequest],
+) -> Result<Vec<MintReceipt>, (String, ErrorClass)> {
+    retry::retry_transient(
+        client,
+        async |client| submit_mints(client, faucet_id_hex, reqs).await,
+        async |client, retry, error| {
+            let delay = retry::backoff_delay(retry);
+            warn!(error = %error, retry, max_retries = MINT_MAX_RETRIES, delay_ms = delay.as_millis() as u64, "transient mint error, retrying");
+            tokio::time::sleep(delay).await;
+            if let Err(e) = client.sync_state().await {
+                warn!(error = %e, "sync before mint retry failed");
+            }
+        },
+    )
+    .await
+}
+
+/// Shared by every faucet worker; kept in AppState so reloads can start more
+#[derive(Clone)]
+struct WorkerContext {
+    keystore_path: String,
+    /// Base store path; each worker gets its own file next to it
+    store_path: String,
+    /// Node every worker's client talks to (milo.toml `rpc_endpoint`)
+    endpoint: Endpoint,
+    faucet_supply: Arc<Mutex<HashMap<String, FaucetSupply>>>,
+    /// Where workers publish each on-chain check; handlers read it
+    faucets: Arc<Mutex<FaucetSet>>,
+    mint_queue: Arc<MintQueue>,
+    metrics: Arc<Mutex<FaucetMetrics>>,
+    mint_history: Arc<MintHistory>,
+    shutdown: Arc<Shutdown>,
+    max_mint_batch: usize,
+    recheck_interval: Duration,
+}
+
+/// One faucet's worker thread and the channel to it
+struct FaucetWorker {
+    tx: std::sync::mpsc::Sender<WorkerRequest>,
+    handle: std::thread::JoinHandle<()>,
+}
+
+/// Store of one faucet's worker: `faucet_store.sqlite3` → `faucet_store.milo.sqlite3`.
+/// SQLite stores can't be shared between clients, so every worker has its own.
+fn worker_store_path(base: &str, symbol: &str) -> PathBuf {
+    let base = std::path::Path::new(base);
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("faucet_store");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("sqlite3");
+    base.with_file_name(format!("{}.{}.{}", stem, symbol.to_lowercase(), ext))
+}
+
+/// Start the worker thread for `entry`. The receiver resolves once the worker
+/// has checked the faucet on-chain (true when it exists).
+fn spawn_faucet_worker(
+    entry: FaucetEntry,
+    ctx: &WorkerContext,
+) -> (FaucetWorker, tokio::sync::oneshot::Receiver<bool>) {
+    let (tx, rx) = std::sync::mpsc::channel::<WorkerRequest>();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+    let ctx = ctx.clone();
+    let handle = std::thread::spawn(move || run_faucet_worker(entry, ctx, rx, ready_tx));
+    (FaucetWorker { tx, handle }, ready_rx)
+}
+
+/// Body of a faucet's worker thread: owns the !Send Miden client for that
+/// faucet and serves its requests one batch at a time.
+fn run_faucet_worker(
+    entry: FaucetEntry,
+    ctx: WorkerContext,
+    rx: std::sync::mpsc::Receiver<WorkerRequest>,
+    ready_tx: tokio::sync::oneshot::Sender<bool>,
+) {
+    // Own tokio runtime for this thread; block_on drives each future
+    // to completion before we move on — no concurrent access to client.
+    let rt = tokio::runtime::Runtime::new().expect("tokio runtime failed");
+    let symbol = entry.symbol.clone();
+    let _worker_span = tracing::info_span!("worker", token = %symbol).entered();
+
+    // ── build Miden client ──────────────────────────────────────────────
+    let store_path = worker_store_path(&ctx.store_path, &symbol);
+    info!(store = %store_path.display(), "Miden client başlatılıyor");
+    // RPC handle is kept so diagnostics can fetch fresh on-chain state
+    let rpc_api = Arc::new(GrpcClient::new(&ctx.endpoint, 60_000));
+    let mut client = rt.block_on(async {
+        let keystore = Arc::new(
+            FilesystemKeyStore::<StdRng>::new(PathBuf::from(&ctx.keystore_path))
+                .expect("Keystore oluşturulamadı"),
+        );
+        ClientBuilder::new()
+            .rpc(rpc_api.clone())
+            .authenticator(keystore)
+            .sqlite_store(store_path)
+            .build()
+            .await
+            .expect("Miden client oluşturulamadı")
+    });
+
+    // ── verify the faucet on-chain (imports it into this worker's store) ──
+    let mut config = FaucetsConfig { faucets: vec![entry] };
+    let active = rt.block_on(check_faucet(&mut client, &config, &ctx.faucets));
+    ready_tx.send(active).ok();
+
+    // ── request loop ────────────────────────────────────────────────────
+    // Waits time out at the next supply refresh, so supply stays current
+    // even when nobody is minting
+    let mut next_supply_refresh = Instant::now();
+    let mut last_supply_refresh: Option<Instant> = None;
+    // A faucet that was missing or unreachable at startup becomes available
+    // without a restart
+    let mut next_recheck = Instant::now() + ctx.recheck_interval;
+    let mut deferred: VecDeque<WorkerRequest> = VecDeque::new();
+    loop {
+        if ctx.shutdown.should_exit() {
+            info!("kuyruk boşaldı, worker çıkıyor");
+            break;
+        }
+        if Instant::now() >= next_supply_refresh {
+            rt.block_on(refresh_faucet_supply(&client, &rpc_api, &config, &ctx.faucet_supply));
+            last_supply_refresh = Some(Instant::now());
+            next_supply_refresh = Instant::now() + Duration::from_secs(SUPPLY_REFRESH_SECS);
+        }
+        if Instant::now() >= next_recheck {
+            rt.block_on(check_faucet(&mut client, &config, &ctx.faucets));
+            next_recheck = Instant::now() + ctx.recheck_interval;
+        }
+        // Wake at least every second so a shutdown request is noticed
+        let wait = next_supply_refresh
+            .saturating_duration_since(Instant::now())
+            .min(Duration::from_secs(1));
+        // Requests set aside while collecting a mint batch are handled first
+        let next = match deferred.pop_front() {
+            Some(req) => Ok(req),
+            None => rx.recv_timeout(wait),
+        };
+        let first = match next {
+            Ok(WorkerRequest::Mint(r)) => {
+                ctx.mint_queue.take(r.queue_ticket);
+                r
+            }
+            Ok(WorkerRequest::TxDiagnostics(req)) => {
+                info!(tx_id = %req.tx_id_hex, "diagnostics");
+                let result = rt.block_on(tx_diagnostics(&client, &rpc_api, &req.tx_id_hex));
+                req.reply.send(result).ok();
+                ctx.shutdown.finish();
+                continue;
+            }
+            Ok(WorkerRequest::TxStatus(req)) => {
+                let result = rt.block_on(tx_status(&mut client, &rpc_api, &req.tx_id_hex, req.sync));
+                req.reply.send(result).ok();
+                ctx.shutdown.finish();
+                continue;
+            }
+            Ok(WorkerRequest::CheckFaucets(req)) => {
+                config = FaucetsConfig { faucets: vec![req.entry] };
+                let active = rt.block_on(check_faucet(&mut client, &config, &ctx.faucets));
+                req.reply.send(active).ok();
+                ctx.shutdown.finish();
+                next_recheck = Instant::now() + ctx.recheck_interval;
+                // A reloaded faucet gets its supply read right away
+                next_supply_refresh = Instant::now();
+                continue;
+            }
+            Ok(WorkerRequest::FaucetBalance(req)) => {
+                // Queued requests after a refresh are answered from it
+                let fresh = last_supply_refresh
+                    .is_some_and(|t| t.elapsed() < Duration::from_secs(BALANCE_CACHE_SECS));
+                if !fresh {
+                    rt.block_on(refresh_faucet_supply(&client, &rpc_api, &config, &ctx.faucet_supply));
+                    last_supply_refresh = Some(Instant::now());
+                    next_supply_refresh = Instant::now() + Duration::from_secs(SUPPLY_REFRESH_SECS);
+                }
+                req.reply.send(()).ok();
+                ctx.shutdown.finish();
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                info!("channel kapatıldı, worker çıkıyor");
+                break;
+            }
+        };
 
-        // Reset if new day
-        if entry.day != today {
-            entry.total_amount = 0;
-            entry.day = today;
+        let batch = collect_mint_batch(first, &rx, ctx.max_mint_batch, &ctx.mint_queue, &mut deferred);
+
+        // A mint past max supply fails in the kernel with an error that
+        // means nothing to the user: check the cached supply first, reading
+        // it now if it never was
+        if !ctx.faucet_supply.lock().unwrap().contains_key(&config.faucets[0].symbol) {
+            rt.block_on(refresh_faucet_supply(&client, &rpc_api, &config, &ctx.faucet_supply));
+            last_supply_refresh = Some(Instant::now());
+            next_supply_refresh = Instant::now() + Duration::from_secs(SUPPLY_REFRESH_SECS);
+        }
+        let (batch, exhausted) = split_by_supply(&ctx.faucet_supply, batch);
+        for (req, remaining) in exhausted {
+            warn!(token = %req.token_symbol, amount = req.amount, remaining, "mint refused, supply exhausted");
+            finish_mint(req, Err(format!("{}, remaining: {}", SUPPLY_EXHAUSTED_ERROR, remaining)), &ctx);
         }
 
-        if entry.total_amount + amount > MAX_DAILY_AMOUNT {
-            let remaining = MAX_DAILY_AMOUNT.saturating_sub(entry.total_amount);
-            return (
-                StatusCode::TOO_MANY_REQUESTS,
-                Json(json!({
-                    "error": format!(
-                        "Daily limit reached for {}. Max {} per day. Remaining today: {}",
-                        token, MAX_DAILY_AMOUNT, remaining
-                    )
-                })),
+        // Normally one group; a reload can change the faucet ID mid-batch.
+        // A failed mint may mean the cached supply is off: re-read it before
+        // the next batch.
+        let mut mint_failed = false;
+        for (faucet_id_hex, group) in group_mints_by_faucet(batch) {
+            if group.len() > 1 {
+                info!(count = group.len(), "mints batched into one tx");
+                let result = rt.block_on(submit_mints_with_retry(&mut client, &faucet_id_hex, &group));
+                match result {
+                    Ok(mut receipts) => {
+                        rt.block_on(await_commits(&mut client, &group, &mut receipts));
+                        for (req, receipt) in group.into_iter().zip(receipts) {
+                            finish_mint(req, Ok(receipt), &ctx);
+                        }
+                        continue;
+                    }
+                    // The node stayed unreachable through every retry; minting
+                    // one by one would only repeat that for each request
+                    Err((e, ErrorClass::Transient)) => {
+                        mint_failed = true;
+                        for req in group {
+                            finish_mint(req, Err(e.clone()), &ctx);
+                        }
+                        continue;
+                    }
+                    // One bad recipient must not fail the others: mint them one by one
+                    Err((e, ErrorClass::Permanent)) => warn!(error = %e, "batch mint failed, minting individually"),
+                }
+            }
+            for req in group {
+                let reqs = std::slice::from_ref(&req);
+                let result = rt.block_on(
+                    async {
+                        let mut receipts = submit_mints_with_retry(&mut client, &faucet_id_hex, reqs)
+                            .await
+                            .map_err(|(e, _)| e)?;
+                        await_commits(&mut client, reqs, &mut receipts).await;
+                        Ok::<_, String>(receipts.remove(0))
+                    }
+                    .instrument(req.span.clone()),
+                );
+                mint_failed |= result.is_err();
+                finish_mint(req, result, &ctx);
+            }
+        }
+        if mint_failed {
+            next_supply_refresh = Instant::now();
+        }
+    }
+}
+
+/// Log a mint's outcome, record it in the ledger and metrics and answer the handler
+fn finish_mint(req: MintRequest, result: Result<MintReceipt, String>, ctx: &WorkerContext) {
+    let span = req.span.clone();
+    let _entered = span.enter();
+    let private = req.note_type == NoteType::Private;
+    match &result {
+        Ok(MintReceipt { tx_id, note_id, .. }) => {
+            info!(
+                account_id = %req.recipient_id_hex, token = %req.token_symbol, amount = req.amount,
+                private, tx_id = %tx_id, note_id = %note_id, "mint submitted"
+            );
+            if let Some(supply) = ctx.faucet_supply.lock().unwrap().get_mut(&req.token_symbol) {
+                supply.add_issued(req.amount);
+            }
+            ledger::append_entry(
+                ledger::FAUCET_LEDGER_FILE,
+                &LedgerEntry::new(
+                    "faucet",
+                    "mint",
+                    &normalize_account_id(&req.recipient_id_hex),
+                    &req.faucet_id_hex,
+                    req.amount,
+                    Direction::In,
+                    tx_id,
+                ),
             );
         }
+        Err(e) => error!(
+            account_id = %req.recipient_id_hex, token = %req.token_symbol, amount = req.amount,
+            private, error = %e, "mint failed"
+        ),
+    }
+    {
+        let mut metrics = ctx.metrics.lock().unwrap();
+        metrics.record_mint(&req.token_symbol, if result.is_ok() { "success" } else { "error" });
+        metrics.observe_mint_latency(req.queued_at.elapsed());
+    }
+    let recorded = ctx.mint_history.record(
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        &normalize_account_id(&req.recipient_id_hex),
+        &req.token_symbol,
+        req.amount,
+        result.as_ref().map(|r| r.tx_id.as_str()).map_err(String::as_str),
+    );
+    if let Err(e) = recorded {
+        warn!(error = %e, "mint history write failed");
+    }
+    req.reply.send(result).ok();
+    ctx.shutdown.finish();
+}
+
+/// Issued and maximum supply of a fungible faucet. Read from the node, so
+/// mints by other clients count too; private faucets fall back to the local copy.
+async fn read_faucet_supply(
+    client: &miden_client::Client<FilesystemKeyStore<StdRng>>,
+    rpc_api: &GrpcClient,
+    faucet_id: AccountId,
+) -> Result<(u64, u64), String> {
+    let on_chain = rpc_api
+        .get_account_details(faucet_id)
+        .await
+        .map_err(|e| format!("on-chain account: {}", e))?;
+    let account = match on_chain.account() {
+        Some(account) => account.clone(),
+        None => client
+            .get_account(faucet_id)
+            .await
+            .map_err(|e| format!("local account: {}", e))?
+            .ok_or("faucet is private and not in the local store")?
+            .account()
+            .clone(),
+    };
+
+    let issued = account
+        .storage()
+        .get_item(FAUCET_SYSDATA_SLOT)
+        .map_err(|e| format!("faucet storage: {}", e))?[3]
+        .as_int();
+    let max_supply = BasicFungibleFaucet::try_from(account)
+        .map_err(|e| format!("not a basic fungible faucet: {}", e))?
+        .max_supply()
+        .as_int();
+    Ok((issued, max_supply))
+}
+
+/// Re-read every faucet's supply; a faucet that can't be read keeps its last
+/// value (and its older `last_updated`)
+async fn refresh_faucet_supply(
+    client: &miden_client::Client<FilesystemKeyStore<StdRng>>,
+    rpc_api: &GrpcClient,
+    config: &FaucetsConfig,
+    supply: &Mutex<HashMap<String, FaucetSupply>>,
+) {
+    for faucet in &config.faucets {
+        let Ok(faucet_id) = AccountId::from_hex(&faucet.faucet_id) else {
+            continue;
+        };
+        match read_faucet_supply(client, rpc_api, faucet_id).await {
+            Ok((issued, max_supply)) => {
+                let remaining_supply = max_supply.saturating_sub(issued);
+                supply.lock().unwrap().insert(faucet.symbol.clone(), FaucetSupply {
+                    issued,
+                    max_supply,
+                    remaining_supply,
+                    last_updated: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+                });
+            }
+            Err(e) => warn!(token = %faucet.symbol, error = %e, "arz okunamadı"),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Command line
+// ---------------------------------------------------------------------------
+// `faucet-server [port] [--host <ip>] [--keystore <dir>] [--store <file>] [--pow-bits <n>]`
+// Flags also accept `--flag=value`; each falls back to its FAUCET_* env var.
+#[derive(Debug, Default)]
+struct ServerArgs {
+    port: Option<String>,
+    host: Option<String>,
+    keystore: Option<String>,
+    store: Option<String>,
+    pow_bits: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<ServerArgs, String> {
+    let mut parsed = ServerArgs::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            if parsed.port.is_some() {
+                return Err(format!("Beklenmeyen argüman: {}", arg));
+            }
+            parsed.port = Some(arg.clone());
+            continue;
+        };
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => {
+                let value = iter.next().ok_or_else(|| format!("--{} bir değer bekliyor", flag))?;
+                (flag, value.clone())
+            }
+        };
+        let slot = match name {
+            "port" => &mut parsed.port,
+            "host" => &mut parsed.host,
+            "keystore" => &mut parsed.keystore,
+            "store" => &mut parsed.store,
+            "pow-bits" => &mut parsed.pow_bits,
+            _ => return Err(format!("Bilinmeyen seçenek: --{}", name)),
+        };
+        *slot = Some(value);
+    }
+    Ok(parsed)
+}
+
+/// Command-line value, else the env var, else the milo.toml value or
+/// default (`fallback`), with where it came from
+fn resolve_setting(arg: Option<String>, var: &str, fallback: (String, ConfigSource)) -> (String, ConfigSource) {
+    if let Some(value) = arg {
+        return (value, ConfigSource::Arg);
+    }
+    match std::env::var(var).ok().filter(|v| !v.trim().is_empty()) {
+        Some(value) => (value, ConfigSource::Env),
+        None => fallback,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Main
+// ---------------------------------------------------------------------------
+#[tokio::main]
+async fn main() {
+    let log_format = LogFormat::from_env();
+    logging::init(log_format);
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args = match parse_args(&args) {
+        Ok(args) => args,
+        Err(e) => {
+            error!("{}", e);
+            error!("Kullanım: faucet-server [port] [--host <ip>] [--keystore <dizin>] [--store <dosya>] [--pow-bits <n>]");
+            std::process::exit(2);
+        }
+    };
+    let milo_config = match MiloConfig::load(Service::FaucetServer) {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{:#}", e);
+            std::process::exit(2);
+        }
+    };
+    let endpoint = milo_config.endpoint().expect("validated by MiloConfig::load");
+    let from_milo = |key: &str, value: String| (value, milo_config_source(&milo_config, key));
+    let (port, port_source) = resolve_setting(args.port, "FAUCET_PORT", from_milo("port", milo_config.port.to_string()));
+    let (host, host_source) = resolve_setting(args.host, "FAUCET_HOST", (DEFAULT_HOST.to_string(), ConfigSource::Default));
+    let (keystore_path, keystore_source) =
+        resolve_setting(args.keystore, "FAUCET_KEYSTORE_PATH", from_milo("keystore_path", milo_config.keystore_path.clone()));
+    let (store_path, store_source) =
+        resolve_setting(args.store, "FAUCET_STORE_PATH", from_milo("store_path", milo_config.store_path.clone()));
+    let (pow_bits, pow_bits_source) = resolve_setting(
+        args.pow_bits,
+        "FAUCET_POW_BITS",
+        (abuse::BASE_POW_BITS.to_string(), ConfigSource::Default),
+    );
+    let base_pow_bits = match pow_bits.parse::<u32>() {
+        Ok(bits) if (1..=abuse::MAX_BASE_POW_BITS).contains(&bits) => bits,
+        _ => {
+            error!("Geçersiz PoW zorluğu: {} (1 ile {} bit arası olmalı)", pow_bits, abuse::MAX_BASE_POW_BITS);
+            std::process::exit(2);
+        }
+    };
+    let addr = match (host.parse::<IpAddr>(), port.parse::<u16>()) {
+        (Ok(ip), Ok(port)) => SocketAddr::new(ip, port),
+        (Err(_), _) => {
+            error!("Geçersiz host: {} (bir IP adresi olmalı, ör. 0.0.0.0)", host);
+            std::process::exit(2);
+        }
+        (_, Err(_)) => {
+            error!("Geçersiz port: {}", port);
+            std::process::exit(2);
+        }
+    };
+
+    info!("Milo Swap Faucet API Server başlıyor");
+
+    let faucets = match load_faucets_config(FAUCETS_PATH) {
+        Ok(faucets) => faucets,
+        Err(e) => {
+            error!("Faucet tanımları yüklenemedi: {:#} (`milo setup` {} dosyasını yazar)", e, FAUCETS_PATH);
+            std::process::exit(1);
+        }
+    };
+
+    // faucets.json and accounts.json are written by the same setup; a faucet
+    // that differs was edited or left over from an earlier one
+    let registry = match TokenRegistry::load(ACCOUNTS_PATH) {
+        Ok(registry) => registry.install(),
+        Err(e) => {
+            error!("Token registry yüklenemedi: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    for faucet in &faucets.faucets {
+        if let Some(known) = registry.faucet_id_hex(&faucet.symbol) {
+            if !known.eq_ignore_ascii_case(&faucet.faucet_id) {
+                warn!(
+                    token = %faucet.symbol, faucets_json = %faucet.faucet_id, accounts_json = %known,
+                    "faucet ID in {} differs from {}", FAUCETS_PATH, ACCOUNTS_PATH
+                );
+            }
+        }
+    }
+
+    // Store persists across restarts (contains faucet accounts & sync state)
+    // Each faucet's worker keeps its own store next to this path (see worker_store_path)
+    for faucet in &faucets.faucets {
+        let path = worker_store_path(&store_path, &faucet.symbol);
+        info!(
+            token = %faucet.symbol, store = %path.display(),
+            "store {}", if path.exists() { "mevcut (reusing)" } else { "yeni oluşturulacak" }
+        );
+    }
 
-        // Reserve the amount
-        entry.total_amount += amount;
+    // The keystore must already be there (e.g. a mounted volume) before the worker starts
+    if !std::path::Path::new(&keystore_path).is_dir() {
+        error!(
+            "Keystore dizini bulunamadı: {} (--keystore veya FAUCET_KEYSTORE_PATH ile faucet key'lerinin bulunduğu dizini verin)",
+            keystore_path
+        );
+        std::process::exit(1);
+    }
+    let key_count = fs::read_dir(&keystore_path)
+        .map(|it| it.filter(|e| e.is_ok()).count())
+        .unwrap_or(0);
+    info!(keys = key_count, "keystore yüklendi");
+    if key_count == 0 {
+        warn!("Keystore boş — minting çalışmayacak");
     }
 
-    println!(
-        "   💎 mint {} {} → {}{}",
-        amount, token, params.account_id,
-        if is_admin { " (ADMIN)" } else { "" }
+    // ── one worker thread per faucet, each owning its own !Send client ──
+    // Transactions of one faucet account must be serialized; different
+    // faucets are independent, so their mints run in parallel.
+    let faucet_supply: Arc<Mutex<HashMap<String, FaucetSupply>>> = Arc::new(Mutex::new(HashMap::new()));
+    let max_mint_batch = std::env::var("FAUCET_MAX_MINT_BATCH")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_MINT_BATCH);
+    let max_mint_queue = std::env::var("FAUCET_MAX_MINT_QUEUE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_MINT_QUEUE);
+    let recheck_secs = std::env::var("FAUCET_RECHECK_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_RECHECK_SECS);
+    let request_cooldown_secs = std::env::var("FAUCET_REQUEST_COOLDOWN_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_REQUEST_COOLDOWN_SECS);
+    let trusted_proxies_var = std::env::var("FAUCET_TRUSTED_PROXIES").unwrap_or_default();
+    let trusted_proxies = match parse_trusted_proxies("FAUCET_TRUSTED_PROXIES", &trusted_proxies_var) {
+        Ok(proxies) => proxies,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mint_history_retention_days = std::env::var("FAUCET_MINT_HISTORY_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MINT_HISTORY_RETENTION_DAYS);
+    let mint_history = Arc::new(
+        MintHistory::open(MINT_HISTORY_PATH, mint_history_retention_days).expect("Mint geçmişi açılamadı"),
     );
+    let mint_queue = Arc::new(MintQueue::new(max_mint_queue));
+    let metrics = Arc::new(Mutex::new(FaucetMetrics::default()));
+    let shutdown = Arc::new(Shutdown::default());
+    let faucet_set = Arc::new(Mutex::new(FaucetSet { config: faucets.clone(), status: HashMap::new() }));
+    let worker_ctx = WorkerContext {
+        keystore_path: keystore_path.clone(),
+        store_path: store_path.clone(),
+        endpoint,
+        faucet_supply: faucet_supply.clone(),
+        faucets: faucet_set.clone(),
+        mint_queue: mint_queue.clone(),
+        metrics: metrics.clone(),
+        mint_history: mint_history.clone(),
+        shutdown: shutdown.clone(),
+        max_mint_batch,
+        recheck_interval: Duration::from_secs(recheck_secs),
+    };
 
-    // ── send mint request to worker thread ──────────────────────────────
-    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    // Start every worker first so their clients come up concurrently
+    let started: Vec<_> = faucets
+        .faucets
+        .iter()
+        .map(|entry| (entry.symbol.clone(), spawn_faucet_worker(entry.clone(), &worker_ctx)))
+        .collect();
+    let mut workers = HashMap::new();
+    for (symbol, (worker, ready)) in started {
+        // ── wait for the worker's on-chain check (published to faucet_set) ──
+        ready.await.expect("Worker thread crashed before health check");
+        workers.insert(symbol, worker);
+    }
 
-    if state
-        .mint_tx
-        .send(MintRequest {
-            faucet_id_hex: faucet_id_hex.to_string(),
-            recipient_id_hex: params.account_id,
-            amount,
-            token_symbol: token.clone(),
-            reply: reply_tx,
+    let admin_token = std::env::var("FAUCET_ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .map(Arc::new);
+
+    let abuse_config = AbuseConfig {
+        pow_score: std::env::var("FAUCET_ABUSE_POW_SCORE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(abuse::DEFAULT_POW_SCORE),
+        slow_score: std::env::var("FAUCET_ABUSE_SLOW_SCORE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(abuse::DEFAULT_SLOW_SCORE),
+        base_pow_bits,
+    };
+
+    let rate_limits = RateLimitStore::open(RATE_LIMITS_PATH).expect("Rate limit store açılamadı");
+    match rate_limits.prune(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()) {
+        Ok(pruned) => info!(
+            current = rate_limits.count().unwrap_or(0), pruned,
+            "rate limits: eski kayıtlar silindi"
+        ),
+        Err(e) => warn!(error = %e, "eski rate limit kayıtları silinemedi"),
+    }
+
+    let mut config = StartupConfig::new("faucet-server");
+    config
+        .record("host", &host, host_source)
+        .record("port", &port, port_source)
+        .milo("rpc_endpoint", &milo_config.rpc_endpoint, &milo_config)
+        .record("keystore", &keystore_path, keystore_source)
+        .record("store", &store_path, store_source)
+        .record("pow_bits", base_pow_bits, pow_bits_source)
+        .constant("ledger", ledger::FAUCET_LEDGER_FILE)
+        .constant("rate_limits", RATE_LIMITS_PATH)
+        .constant("accounts", ACCOUNTS_PATH)
+        .constant("mint_history", MINT_HISTORY_PATH);
+    for faucet in &faucets.faucets {
+        config.file(
+            &format!("faucet.{}", faucet.symbol),
+            FAUCETS_PATH,
+            format!(
+                "{} ({} decimals, daily cap {}, max per request {}, per IP {})",
+                faucet.faucet_id, faucet.decimals, faucet.daily_cap, faucet.max_per_request(), faucet.ip_daily_cap()
+            ),
+        );
+    }
+    config
+        .secret("FAUCET_ADMIN_TOKEN")
+        .env("FAUCET_ABUSE_POW_SCORE", abuse_config.pow_score)
+        .env("FAUCET_ABUSE_SLOW_SCORE", abuse_config.slow_score)
+        .env("FAUCET_MAX_MINT_BATCH", max_mint_batch)
+        .env("FAUCET_MAX_MINT_QUEUE", max_mint_queue)
+        .env("FAUCET_RECHECK_SECS", recheck_secs)
+        .env("FAUCET_REQUEST_COOLDOWN_SECS", request_cooldown_secs)
+        .env(
+            "FAUCET_TRUSTED_PROXIES",
+            trusted_proxies.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(","),
+        )
+        .env("FAUCET_MINT_HISTORY_RETENTION_DAYS", mint_history_retention_days)
+        .env("FAUCET_LOG_FORMAT", log_format.as_str());
+    log_startup_config(&config);
+
+    let state = AppState {
+        workers: Arc::new(Mutex::new(workers)),
+        worker_ctx,
+        faucets: faucet_set,
+        rate_limits: Arc::new(rate_limits),
+        request_cooldown_secs,
+        trusted_proxies: Arc::new(trusted_proxies),
+        admin_token,
+        abuse: Arc::new(Mutex::new(AbuseTracker::default())),
+        abuse_config,
+        pow_challenges: Arc::new(Mutex::new(HashMap::new())),
+        slow_queue: Arc::new(tokio::sync::Semaphore::new(1)),
+        faucet_supply,
+        mint_queue,
+        in_flight_mints: Arc::new(Mutex::new(HashSet::new())),
+        metrics,
+        mint_history,
+        shutdown: shutdown.clone(),
+    };
+    let state_workers = state.workers.clone();
+
+    // ── axum router ─────────────────────────────────────────────────────
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    let app = Router::new()
+        .route("/", get(|| async { "Milo Faucet API — /health /pow /get_tokens /allowance /tx_status /faucet_balance /history /recent_mints /metrics" }))
+        .route("/health", get(health_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/pow", get(pow_handler))
+        .route("/get_tokens", get(get_tokens_handler).post(post_tokens_handler))
+        .route("/get_tokens_batch", get(get_tokens_batch_handler))
+        .route("/allowance", get(allowance_handler))
+        .route("/tx_status", get(tx_status_handler))
+        .route("/faucet_balance", get(faucet_balance_handler))
+        .route("/history", get(history_handler))
+        .route("/recent_mints", get(recent_mints_handler))
+        .route("/admin/tx_diagnostics", get(tx_diagnostics_handler))
+        .route("/admin/abuse_report", get(abuse_report_handler))
+        .route("/admin/reload_faucets", post(reload_faucets_handler))
+        .route("/admin/recheck", post(recheck_faucets_handler))
+        .route("/admin/limits", get(get_limits_handler).post(set_limits_handler))
+        .route("/admin/register_faucet", post(register_faucet_handler))
+        .layer(middleware::from_fn(logging::request_span))
+        .layer(cors)
+        .with_state(state);
+
+    info!("http://{} dinleniyor (Ctrl+C ile dur)", addr);
+
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    // Peer address is needed for the abuse heuristics' IP prefix signal.
+    // On Ctrl-C/SIGTERM the server future (and the listener) is dropped, so no
+    // new requests come in; claims already sent to the worker get to finish.
+    tokio::select! {
+        result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()) => {
+            result.unwrap();
+            return;
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    info!(in_flight = shutdown.in_flight(), "kapatılıyor: yeni istek alınmıyor");
+    shutdown.request();
+    let drained = shutdown.wait_for_drain(Duration::from_secs(DRAIN_TIMEOUT_SECS)).await;
+    if drained {
+        // Dropping the senders disconnects each worker's channel
+        let workers: Vec<FaucetWorker> = state_workers.lock().unwrap().drain().map(|(_, w)| w).collect();
+        for worker in workers {
+            drop(worker.tx);
+            worker.handle.join().ok();
+        }
+    }
+    info!(drained = shutdown.drained(), abandoned = shutdown.in_flight(), "kapatıldı");
+}
+
+// ---------------------------------------------------------------------------
+// Handlers
+// ---------------------------------------------------------------------------
+
+/// **GET /metrics** — Prometheus text exposition
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let body = state.metrics.lock().unwrap().render(state.mint_queue.depth());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let set = state.faucets.lock().unwrap();
+    let supply = state.faucet_supply.lock().unwrap();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let faucets: Vec<JsonValue> = set
+        .config
+        .faucets
+        .iter()
+        .map(|faucet| {
+            let active = set.active(&faucet.symbol).is_some();
+            json!({
+                "symbol": faucet.symbol,
+                "faucet_id": faucet.faucet_id,
+                "status": if active { "active" } else { "not_found" },
+                // Unix seconds of the worker's last on-chain check
+                "last_checked": set.status.get(&faucet.symbol).map(|c| c.checked_at),
+                "decimals": faucet.decimals,
+                "daily_cap": faucet.daily_cap,
+                "max_per_request": faucet.max_per_request(),
+                "ip_daily_cap": faucet.ip_daily_cap(),
+                "mint_enabled": faucet.daily_cap > 0,
+                "issued": supply.get(&faucet.symbol).map(|s| s.issued),
+                "max_supply": supply.get(&faucet.symbol).map(|s| s.max_supply),
+                "remaining_supply": supply.get(&faucet.symbol).map(|s| s.remaining_supply),
+                "last_updated": supply.get(&faucet.symbol).map(|s| s.last_updated),
+                // Not refreshed within the last interval (e.g. the node was unreachable)
+                "supply_stale": supply
+                    .get(&faucet.symbol)
+                    .map_or(true, |s| now.saturating_sub(s.last_updated) > SUPPLY_REFRESH_SECS),
+                "low_supply": supply.get(&faucet.symbol).is_some_and(FaucetSupply::is_low),
+                // Nothing left to mint; claims get 503 with code "supply_exhausted"
+                "exhausted": supply.get(&faucet.symbol).is_some_and(FaucetSupply::is_exhausted),
+            })
         })
-        .is_err()
-    {
+        .collect();
+    Json(json!({
+        "status": "ok",
+        "faucets": faucets,
+        "recheck_interval_secs": state.worker_ctx.recheck_interval.as_secs(),
+        "mint_queue": {
+            "depth": state.mint_queue.depth(),
+            "max_depth": state.mint_queue.max_depth,
+            "oldest_age_secs": state.mint_queue.oldest_age().map(|age| age.as_secs()),
+        },
+    }))
+}
+
+async fn pow_handler(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<PowParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    let token = params
+        .token_symbol
+        .as_deref()
+        .unwrap_or("MILO")
+        .to_uppercase();
+
+    if state.faucets.lock().unwrap().active(&token).is_none() {
         return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": "Worker thread is down" })),
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("Faucet {} not available", token) })),
         );
     }
 
-    // ── await response from worker ──────────────────────────────────────
-    match reply_rx.await {
-        Ok(Ok(tx_id)) => {
-            println!("   ✅ tx: {}…", &tx_id[..16.min(tx_id.len())]);
-            (
-                StatusCode::OK,
-                Json(json!({
-                    "tx_id": tx_id,
-                    "note_id": tx_id,
-                    "faucet_id": faucet_id_hex,
-                    "amount": amount,
-                    "token_symbol": token,
-                    "status": "success",
-                    "message": "Minted. Wait ~10 s then click Consume Notes."
-                })),
-            )
-        }
-        Ok(Err(e)) => {
-            println!("   ❌ mint error: {}", e);
-            let hint = if e.contains("key") || e.contains("sign") || e.contains("auth") {
-                "Faucet private key missing in keystore/"
-            } else {
-                "Check server logs"
-            };
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": e, "hint": hint })),
-            )
+    info!(token = %token, account_id = %params.account_id, "pow challenge requested");
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let challenge = generate_challenge();
+    let account_id = normalize_account_id(&params.account_id);
+    let amount: u64 = params.amount.as_deref().and_then(|a| a.parse().ok()).unwrap_or(100);
+    let ip_prefix = abuse::ip_prefix(forwarded_client_ip(&headers, peer, &state.trusted_proxies));
+
+    // Score without recording — the claim itself is recorded by /get_tokens
+    let difficulty_bits = if account_id == ADMIN_ACCOUNT_ID.to_lowercase() {
+        None
+    } else {
+        let mut abuse = state.abuse.lock().unwrap();
+        let event = abuse.event(now, ip_prefix.clone(), &account_id, &token, amount);
+        let score = abuse.score(&event);
+        let bits = abuse::pow_difficulty_bits(score.total, &state.abuse_config);
+        if let Some(difficulty_bits) = bits {
+            warn!(score = score.total, difficulty_bits, ip_prefix = %ip_prefix, "hardened pow");
+            abuse.log_decision(DecisionRecord {
+                timestamp: now,
+                endpoint: "pow",
+                ip_prefix,
+                account_id: account_id.clone(),
+                token: token.clone(),
+                amount,
+                score,
+                decision: AbuseDecision::HardenPow { difficulty_bits },
+            });
         }
-        Err(_) => {
-            println!("   ❌ Worker dropped reply channel");
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "error": "Worker thread crashed during mint" })),
-            )
+        bits
+    };
+
+    let hardened = difficulty_bits.is_some();
+    let difficulty_bits = difficulty_bits.unwrap_or(state.abuse_config.base_pow_bits);
+    let target = abuse::pow_target_for_bits(difficulty_bits);
+    let expires_at = issue_challenge(
+        &mut state.pow_challenges.lock().unwrap(),
+        &challenge,
+        &account_id,
+        target,
+        hardened,
+        now,
+    );
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "challenge": challenge,
+            "target": target,
+            "difficulty_bits": difficulty_bits,
+            "algorithm": POW_ALGORITHM,
+            "scheme": POW_SCHEME,
+            "expires_at": expires_at,
+            "timestamp": now,
+        })),
+    )
+}
+
+/// **GET /get_tokens** — query-string form, kept for existing clients
+async fn get_tokens_handler(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<GetTokensParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, HeaderMap, Json<JsonValue>) {
+    claim_with_rate_limit_headers(peer, &headers, params, &state).await
+}
+
+/// **POST /get_tokens** — same claim with a JSON body, so account IDs and
+/// nonces stay out of URLs and proxy logs
+async fn post_tokens_handler(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(params): Json<GetTokensParams>,
+) -> (StatusCode, HeaderMap, Json<JsonValue>) {
+    claim_with_rate_limit_headers(peer, &headers, params, &state).await
+}
+
+/// A single claim, with the allowance left after it in `X-RateLimit-*` headers
+async fn claim_with_rate_limit_headers(
+    peer: SocketAddr,
+    headers: &HeaderMap,
+    params: GetTokensParams,
+    state: &AppState,
+) -> (StatusCode, HeaderMap, Json<JsonValue>) {
+    let account_id = params.account_id.clone();
+    let token = claim_token_symbol(params.token_symbol.as_deref());
+    let (status, body) = claim_tokens(peer, headers, params, state, None).await;
+    (status, rate_limit_headers(state, &account_id, &token), body)
+}
+
+/// **GET /allowance** — an account's cap for a token, what it has claimed
+/// in the last 24 hours and what's left, read the same way /get_tokens
+/// enforces it
+async fn allowance_handler(
+    Query(params): Query<AllowanceParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    let token = claim_token_symbol(params.token_symbol.as_deref());
+    let Some(daily_cap) = state.faucets.lock().unwrap().active(&token).map(|f| f.daily_cap) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("Faucet {} not available", token) })),
+        );
+    };
+    if let Err(e) = parse_account_id(&params.account_id) {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    match account_allowance(&state.rate_limits, &params.account_id, &token, daily_cap, now) {
+        Ok(allowance) => (
+            StatusCode::OK,
+            Json(json!({
+                "account_id": normalize_account_id(&params.account_id),
+                "token_symbol": token,
+                "daily_cap": allowance.daily_cap,
+                "used_24h": allowance.used,
+                "remaining": allowance.remaining(),
+                "reset_at": allowance.reset_at,
+                // The admin account isn't limited
+                "exempt": normalize_account_id(&params.account_id) == ADMIN_ACCOUNT_ID.to_lowercase(),
+            })),
+        ),
+        Err(e) => {
+            error!(error = %e, "rate limit store");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Rate limit store unavailable" })),
+            )
+        }
+    }
+}
+
+/// **GET /get_tokens_batch** — claim several tokens in one round-trip.
+/// One solved /pow challenge covers the batch; each token is then claimed
+/// like a single /get_tokens call, so its own caps, cooldown and abuse
+/// scoring apply. The mints go to each token's worker in parallel. Answers
+/// `200` when every claim succeeded, otherwise `207` with each claim's result.
+async fn get_tokens_batch_handler(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Query(params): Query<GetTokensBatchParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    let requested: Vec<(&str, String)> = [("MILO", &params.milo), ("MELO", &params.melo), ("MUSDC", &params.musdc)]
+        .into_iter()
+        .filter_map(|(token, amount)| amount.clone().map(|amount| (token, amount)))
+        .collect();
+    if requested.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "Give an amount for at least one of milo, melo, musdc" })),
+        );
+    }
+
+    // Redeem the challenge once for the whole batch (the admin needs none)
+    let normalized_id = normalize_account_id(&params.account_id);
+    let redeemed = if normalized_id == ADMIN_ACCOUNT_ID.to_lowercase() {
+        None
+    } else {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let issued = redeem_challenge(&mut state.pow_challenges.lock().unwrap(), &params.challenge, &normalized_id, now);
+        let Some(issued) = issued else {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Unknown, expired or already used challenge: request a new one from /pow",
+                    "code": "invalid_challenge",
+                })),
+            );
+        };
+        if !verify_pow(&params.challenge, &params.nonce, issued.target) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Proof of work does not meet the challenge target",
+                    "code": "invalid_pow",
+                    "target": issued.target,
+                })),
+            );
+        }
+        Some(issued)
+    };
+
+    info!(account_id = %params.account_id, tokens = requested.len(), "batch claim");
+    let claims: Vec<_> = requested
+        .into_iter()
+        .map(|(token, amount)| {
+            let claim = GetTokensParams {
+                account_id: params.account_id.clone(),
+                is_private_note: params.is_private_note.clone(),
+                asset_amount: Some(amount),
+                amount_tokens: None,
+                challenge: params.challenge.clone(),
+                nonce: params.nonce.clone(),
+                token_symbol: Some(token.to_string()),
+                wait: None,
+            };
+            let (headers, state) = (headers.clone(), state.clone());
+            let handle = tokio::spawn(
+                async move { claim_tokens(peer, &headers, claim, &state, redeemed).await }.instrument(Span::current()),
+            );
+            (token, handle)
+        })
+        .collect();
+
+    let mut all_ok = true;
+    let mut results = Vec::with_capacity(claims.len());
+    for (token, handle) in claims {
+        let (status, Json(mut body)) = handle.await.unwrap_or_else(|_| {
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Claim task panicked" })))
+        });
+        all_ok &= status.is_success();
+        body["token_symbol"] = json!(token);
+        body["http_status"] = json!(status.as_u16());
+        results.push(body);
+    }
+
+    let status = if all_ok { StatusCode::OK } else { StatusCode::MULTI_STATUS };
+    (status, Json(json!({ "account_id": params.account_id, "results": results })))
+}
+
+/// An account+token claim in `AppState::in_flight_mints`, removed again when
+/// dropped: on every return of `claim_tokens`, and when the client goes away
+/// mid-claim and the handler future is dropped with it
+struct InFlightMint {
+    set: Arc<Mutex<HashSet<(String, String)>>>,
+    key: (String, String),
+}
+
+impl InFlightMint {
+    /// None if a claim for the same account and token is already in flight
+    fn start(set: &Arc<Mutex<HashSet<(String, String)>>>, account_id: &str, token: &str) -> Option<Self> {
+        let key = (account_id.to_string(), token.to_string());
+        set.lock().unwrap().insert(key.clone()).then(|| Self { set: set.clone(), key })
+    }
+}
+
+impl Drop for InFlightMint {
+    fn drop(&mut self) {
+        self.set.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Validates a claim (PoW, abuse score, daily limit), dispatches the mint
+/// to the worker thread and awaits the result via a oneshot channel.
+/// Shared by both /get_tokens forms and /get_tokens_batch, so they count
+/// against the same limits.
+async fn claim_tokens(
+    peer: SocketAddr,
+    headers: &HeaderMap,
+    params: GetTokensParams,
+    state: &AppState,
+    redeemed: Option<IssuedChallenge>,
+) -> (StatusCode, Json<JsonValue>) {
+    claim_tokens_within(peer, headers, params, state, redeemed, Duration::from_secs(WAIT_CLAIM_TIMEOUT_SECS)).await
+}
+
+/// `claim_tokens`, with a `wait=true` claim giving up on the worker after `wait_timeout`
+async fn claim_tokens_within(
+    peer: SocketAddr,
+    headers: &HeaderMap,
+    params: GetTokensParams,
+    state: &AppState,
+    redeemed: Option<IssuedChallenge>,
+    wait_timeout: Duration,
+) -> (StatusCode, Json<JsonValue>) {
+    let token = claim_token_symbol(params.token_symbol.as_deref());
+
+    info!(
+        token = %token, account_id = %params.account_id, amount = ?params.asset_amount,
+        amount_tokens = ?params.amount_tokens, "claim received"
+    );
+
+    // ── validate token ──────────────────────────────────────────────────
+    // Cloned so a concurrent /admin/reload_faucets can't change it mid-claim
+    let faucet = match state.faucets.lock().unwrap().active(&token).cloned() {
+        Some(faucet) => faucet,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": format!("Faucet {} not available", token) })),
+            )
+        }
+    };
+
+    // ── validate recipient ──────────────────────────────────────────────
+    if let Err(e) = parse_account_id(&params.account_id) {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    }
+
+    // ── parse amount: raw base units or whole tokens, not both ──────────
+    let parsed = match (params.asset_amount.as_deref(), params.amount_tokens.as_deref()) {
+        (Some(_), Some(_)) => Err("Send either asset_amount (base units) or amount_tokens, not both".to_string()),
+        (None, Some(tokens)) => parse_token_amount(tokens, faucet.decimals),
+        (raw, None) => raw.unwrap_or("100").parse::<u64>().map_err(|e| format!("bad amount: {}", e)),
+    };
+    let amount: u64 = match parsed {
+        Ok(a) if a > 0 => a,
+        Ok(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "amount must be > 0" })),
+            )
+        }
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": e })),
+            )
+        }
+    };
+
+    let note_type = claim_note_type(params.is_private_note.as_deref());
+    let wait_for_commit = matches!(params.wait.as_deref().map(str::to_lowercase).as_deref(), Some("true" | "1"));
+
+    // ── per-token caps and rate limit (admin is exempt) ───────────────
+    let normalized_id = normalize_account_id(&params.account_id);
+    let is_admin = normalized_id == ADMIN_ACCOUNT_ID.to_lowercase();
+    if !is_admin {
+        if faucet.daily_cap == 0 {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": format!("Minting {} is disabled on this faucet", token),
+                    "code": "mint_disabled",
+                })),
+            );
+        }
+        if amount > faucet.max_per_request() {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": format!("Max {} {} per request", faucet.max_per_request(), token),
+                    "max_per_request": faucet.max_per_request(),
+                })),
+            );
+        }
+    }
+
+    // ── one claim per account and token at a time (double-clicks) ─────
+    // Checked before the PoW challenge is redeemed and the allowance
+    // reserved, so the rejected duplicate costs the caller neither
+    let Some(in_flight) = InFlightMint::start(&state.in_flight_mints, &normalized_id, &token) else {
+        warn!(account_id = %normalized_id, token = %token, "duplicate claim while a mint is in flight");
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": format!("A {} mint for this account is already in progress", token),
+                "code": "mint_in_progress",
+            })),
+        );
+    };
+
+    // ── abuse heuristics (admin is exempt) ────────────────────────────
+    let decision = if is_admin {
+        AbuseDecision::Allow
+    } else {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let ip_prefix = abuse::ip_prefix(forwarded_client_ip(headers, peer, &state.trusted_proxies));
+
+        // Every claim redeems a live challenge from /pow; removing it makes it
+        // single-use. A batch redeems (and verifies) one for all its claims.
+        let issued = redeemed.or_else(|| {
+            redeem_challenge(&mut state.pow_challenges.lock().unwrap(), &params.challenge, &normalized_id, now)
+        });
+        let Some(issued) = issued.filter(|c| c.expires_at >= now) else {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Unknown, expired or already used challenge: request a new one from /pow",
+                    "code": "invalid_challenge",
+                })),
+            );
+        };
+        let solved = redeemed.is_some() || verify_pow(&params.challenge, &params.nonce, issued.target);
+
+        let mut abuse = state.abuse.lock().unwrap();
+        let event = abuse.event(now, ip_prefix.clone(), &normalized_id, &token, amount);
+        let score = abuse.record(event);
+        let decision = abuse::decide(score.total, &state.abuse_config);
+
+        // A score that calls for hardened PoW can't be redeemed with a normal challenge
+        let rejected = !solved || (matches!(decision, AbuseDecision::HardenPow { .. }) && !issued.hardened);
+        if decision != AbuseDecision::Allow || rejected {
+            warn!(
+                account_id = %normalized_id, token = %token, amount, score = score.total,
+                decision = ?decision, rejected, ip_prefix = %ip_prefix, "abuse decision"
+            );
+            abuse.log_decision(DecisionRecord {
+                timestamp: now,
+                endpoint: "get_tokens",
+                ip_prefix,
+                account_id: normalized_id.clone(),
+                token: token.clone(),
+                amount,
+                score,
+                decision,
+            });
+        }
+        if !solved {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "error": "Proof of work does not meet the challenge target",
+                    "code": "invalid_pow",
+                    "target": issued.target,
+                })),
+            );
+        }
+        if rejected {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "error": "Proof of work required: request a new /pow challenge and solve it",
+                    "score": score.total,
+                })),
+            );
+        }
+        decision
+    };
+
+    // Reserve the amount against both the account's and the IP's cap over the
+    // last 24 hours, so fresh wallets from one address share its allowance;
+    // released again if the mint fails
+    let reservation = if is_admin {
+        None
+    } else {
+        let rate_key = account_rate_key(&params.account_id, &token);
+        let ip_key = ip_rate_key(forwarded_client_ip(headers, peer, &state.trusted_proxies), &token);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let limits = claim_limits(&faucet, &rate_key, &ip_key, state.request_cooldown_secs);
+        match state.rate_limits.reserve(&limits, now, amount) {
+            Ok(grants) => Some(grants),
+            Err(ReserveError::Cooldown { retry_after, .. }) => {
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(json!({
+                        "error": format!("Too many requests for {}. Try again in {} seconds", token, retry_after),
+                        "code": "cooldown",
+                        "retry_after": retry_after,
+                    })),
+                );
+            }
+            Err(ReserveError::LimitReached { key, remaining, reset_at }) => {
+                state.metrics.lock().unwrap().record_rate_limited();
+                let (scope, cap) = if key == ip_key {
+                    ("this IP", faucet.ip_daily_cap())
+                } else {
+                    ("this account", faucet.daily_cap)
+                };
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    Json(json!({
+                        "error": format!(
+                            "Daily limit reached for {} from {}. Max {} per 24 hours. Available now: {}",
+                            token, scope, cap, remaining
+                        ),
+                        "limit": if key == ip_key { "ip" } else { "account" },
+                        "remaining": remaining,
+                        "reset_at": reset_at,
+                    })),
+                );
+            }
+            Err(ReserveError::Store(e)) => {
+                error!(error = %e, "rate limit store");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": "Rate limit store unavailable" })),
+                );
+            }
+        }
+    };
+    let release = || {
+        if let Some(ref grants) = reservation {
+            state.rate_limits.release(grants);
+        }
+    };
+
+    info!(account_id = %params.account_id, token = %token, amount, admin = is_admin, "mint queued");
+
+    // ── slow queue: flagged claims are delayed one at a time, not rejected ──
+    if decision == AbuseDecision::SlowQueue {
+        warn!(account_id = %params.account_id, token = %token, amount, "slow queue");
+        let _permit = state.slow_queue.acquire().await;
+        tokio::time::sleep(Duration::from_secs(SLOW_QUEUE_DELAY_SECS)).await;
+    }
+
+    // ── backpressure: refuse rather than queue behind a long backlog ─────
+    let queue_ticket = match take_queue_slot(&state.mint_queue, &release) {
+        Ok(ticket) => ticket,
+        Err(body) => {
+            state.metrics.lock().unwrap().record_mint(&token, "queue_full");
+            warn!(max_depth = state.mint_queue.max_depth, "mint queue full, rejecting");
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(body));
+        }
+    };
+
+    // ── send mint request to worker thread ──────────────────────────────
+    let (reply_tx, mut reply_rx) = tokio::sync::oneshot::channel();
+
+    if !state.send_to_worker(&token, WorkerRequest::Mint(MintRequest {
+        faucet_id_hex: faucet.faucet_id.clone(),
+        recipient_id_hex: params.account_id,
+        amount,
+        token_symbol: token.clone(),
+        note_type,
+        queued_at: Instant::now(),
+        queue_ticket,
+        span: Span::current(),
+        wait_for_commit,
+        reply: reply_tx,
+    })) {
+        release();
+        state.mint_queue.cancel(queue_ticket);
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Worker thread is down" })),
+        );
+    }
+
+    // ── await response from worker ──────────────────────────────────────
+    // A waiting claim gives up after `wait_timeout`, but the mint goes on:
+    // the worker's answer still settles the reservation, and the account's
+    // in-flight slot stays taken until then
+    let reply = if wait_for_commit {
+        match tokio::time::timeout(wait_timeout, &mut reply_rx).await {
+            Ok(reply) => reply,
+            Err(_) => {
+                warn!(token = %token, amount, "wait=true claim timed out before the worker answered");
+                let grants = reservation.clone();
+                let rate_limits = state.rate_limits.clone();
+                tokio::spawn(async move {
+                    let _in_flight = in_flight;
+                    if !matches!(reply_rx.await, Ok(Ok(_))) {
+                        if let Some(grants) = grants {
+                            rate_limits.release(&grants);
+                        }
+                    }
+                });
+                return (
+                    StatusCode::ACCEPTED,
+                    Json(json!({
+                        "error": format!("Mint not finished within {} seconds", wait_timeout.as_secs()),
+                        "code": "mint_pending",
+                        "hint": "The mint is still queued or committing: check /history for its tx_id",
+                        "committed": false,
+                    })),
+                );
+            }
+        }
+    } else {
+        reply_rx.await
+    };
+
+    match reply {
+        Ok(Ok(MintReceipt { tx_id, note_id, private_note, commit, .. })) => {
+            info!(token = %token, amount, tx_id = %tx_id, note_id = %note_id, "claim minted");
+            let message = match commit {
+                Some(CommitStatus { committed: true, .. }) if private_note.is_none() => {
+                    "Minted and committed. Click Consume Notes."
+                }
+                Some(CommitStatus { committed: false, .. }) => {
+                    "Minted, but not committed yet. Poll /tx_status until committed before consuming."
+                }
+                _ if private_note.is_some() => {
+                    "Minted a private note. Only its commitment is on chain: import `note` with `import_note` on the recipient's client, then consume it once /tx_status reports committed."
+                }
+                _ => "Minted. Poll /tx_status until committed, then click Consume Notes.",
+            };
+            let mut body = json!({
+                "tx_id": tx_id,
+                "note_id": note_id,
+                "faucet_id": faucet.faucet_id,
+                "amount": amount,
+                "amount_tokens": format_token_amount(amount, faucet.decimals),
+                "decimals": faucet.decimals,
+                "token_symbol": token,
+                "note_type": if private_note.is_some() { "private" } else { "public" },
+                "note": private_note.as_ref().map(|n| n.note.as_str()),
+                "private_note": private_note,
+                "status_url": format!("/tx_status?tx_id={}", tx_id),
+                "status": "success",
+                "message": message,
+            });
+            if let Some(commit) = commit {
+                body["committed"] = json!(commit.committed);
+                body["block_num"] = json!(commit.block_num);
+            }
+            (StatusCode::OK, Json(body))
+        }
+        Ok(Err(e)) => {
+            // The worker only replies once its retries are used up; the
+            // claim doesn't count against the caller's allowance
+            error!(token = %token, amount, error = %e, "claim failed");
+            release();
+            let (status, body) = mint_failure_response(&e, &token);
+            (status, Json(body))
+        }
+        Err(_) => {
+            error!("worker dropped reply channel");
+            release();
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Worker thread crashed during mint" })),
+            )
+        }
+    }
+}
+
+/// Status and body for a mint the worker gave up on
+fn mint_failure_response(e: &str, token: &str) -> (StatusCode, JsonValue) {
+    if retry::classify(e) == ErrorClass::Transient {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            json!({
+                "error": e,
+                "code": "node_unavailable",
+                "hint": "The Miden node could not be reached after several retries. Try again shortly.",
+                "retry_after": MINT_QUEUE_RETRY_AFTER_SECS,
+            }),
+        );
+    }
+    if e.starts_with(SUPPLY_EXHAUSTED_ERROR) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            json!({
+                "error": e,
+                "code": "supply_exhausted",
+                "hint": format!("The {} faucet can't mint more than its remaining supply", token),
+            }),
+        );
+    }
+    if let Some(reason) = e.strip_prefix(PRIVATE_MINT_ERROR) {
+        // The client couldn't mint a private note to this recipient; public still works
+        return (
+            StatusCode::BAD_REQUEST,
+            json!({
+                "error": format!("Private note rejected for this recipient: {}", reason),
+                "code": "private_note_rejected",
+                "hint": "Retry without is_private_note to mint a public note",
+            }),
+        );
+    }
+    let hint = if e.contains("key") || e.contains("sign") || e.contains("auth") {
+        "Faucet private key missing in keystore/"
+    } else {
+        "Check server logs for this request_id"
+    };
+    (StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e, "hint": hint }))
+}
+
+/// **GET /history?account_id=0x…&limit=50[&before=<id>]** — an account's
+/// mints, newest first. Pass `next_before` back as `before` for the next page.
+async fn history_handler(
+    Query(params): Query<HistoryParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    let account_id = match parse_account_id(&params.account_id) {
+        Ok(id) => id.to_hex(),
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))),
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_PAGE).clamp(1, MAX_HISTORY_PAGE);
+    match state.mint_history.page(Some(&account_id), params.before, limit) {
+        Ok(mints) => {
+            let next_before = (mints.len() == limit).then(|| mints.last().map(|m| m.id)).flatten();
+            (
+                StatusCode::OK,
+                Json(json!({ "account_id": account_id, "mints": mints, "next_before": next_before })),
+            )
+        }
+        Err(e) => {
+            error!(error = %e, "mint history");
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": "Mint history unavailable" })))
+        }
+    }
+}
+
+/// **GET /recent_mints?limit=50** — the latest mints across all accounts,
+/// newest first (at most the last 200)
+async fn recent_mints_handler(
+    Query(params): Query<RecentMintsParams>,
+    State(state): State<AppState>,
+) -> Json<JsonValue> {
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_PAGE).clamp(1, RECENT_MINTS_CAPACITY);
+    Json(json!({ "mints": state.mint_history.recent(limit) }))
+}
+
+/// **GET /admin/tx_diagnostics?tx_id=0x…** — why didn't this mint commit?
+async fn tx_diagnostics_handler(
+    headers: HeaderMap,
+    Query(params): Query<TxDiagnosticsParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let Some(tx_id_hex) = normalize_tx_id(&params.tx_id) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "tx_id must be a 32-byte hex string" })),
+        );
+    };
+
+    // The mint is in the store of whichever faucet's worker submitted it
+    let replies = state.send_to_all(|reply| {
+        WorkerRequest::TxDiagnostics(TxDiagnosticsRequest { tx_id_hex: tx_id_hex.clone(), reply })
+    });
+    if replies.is_empty() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Worker thread is down" })),
+        );
+    }
+
+    let mut error = None;
+    for reply_rx in replies {
+        match reply_rx.await {
+            Ok(Ok(Some(report))) => return (StatusCode::OK, Json(report)),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => error = Some(e),
+            Err(_) => error = Some("Worker thread crashed during diagnostics".to_string()),
+        }
+    }
+    match error {
+        Some(e) => (StatusCode::BAD_GATEWAY, Json(json!({ "error": e }))),
+        None => (
+            StatusCode::OK,
+            Json(json!({
+                "tx_id": tx_id_hex,
+                "local_record": null,
+                "message": "Transaction not found in the local store",
+            })),
+        ),
+    }
+}
+
+/// **GET /tx_status?tx_id=0x…** — has a mint landed yet? Lets the frontend
+/// poll instead of waiting a fixed time before consuming.
+async fn tx_status_handler(
+    Query(params): Query<TxStatusParams>,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    let Some(tx_id_hex) = normalize_tx_id(&params.tx_id) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "tx_id must be a 32-byte hex string" })),
+        );
+    };
+
+    let replies = state.send_to_all(|reply| {
+        WorkerRequest::TxStatus(TxStatusRequest { tx_id_hex: tx_id_hex.clone(), sync: params.sync, reply })
+    });
+    if replies.is_empty() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Worker thread is down" })),
+        );
+    }
+
+    let lookup = async {
+        let mut error = None;
+        for reply_rx in replies {
+            match reply_rx.await {
+                Ok(Ok(Some(status))) => return Ok(Some(status)),
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => error = Some((StatusCode::BAD_GATEWAY, e)),
+                Err(_) => {
+                    error = Some((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Worker thread crashed during status check".to_string(),
+                    ))
+                }
+            }
+        }
+        error.map_or(Ok(None), Err)
+    };
+
+    // A busy worker (e.g. mid-mint) shouldn't hold the poll; report pending
+    match tokio::time::timeout(Duration::from_secs(TX_STATUS_TIMEOUT_SECS), lookup).await {
+        Ok(Ok(Some(status))) => (StatusCode::OK, Json(status)),
+        Ok(Ok(None)) => (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "tx_id": tx_id_hex,
+                "status": "unknown",
+                "block_num": null,
+                "error": format!("Unknown transaction {}", tx_id_hex),
+            })),
+        ),
+        Ok(Err((code, e))) => (code, Json(json!({ "error": e }))),
+        Err(_) => (
+            StatusCode::OK,
+            Json(json!({ "tx_id": tx_id_hex, "status": "pending", "block_num": null, "timed_out": true })),
+        ),
+    }
+}
+
+/// **GET /admin/abuse_report** — recent abuse-heuristic decisions and scores
+async fn abuse_report_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let abuse = state.abuse.lock().unwrap();
+    let decisions: Vec<&DecisionRecord> = abuse.decisions().rev().collect();
+    let slow_queued = decisions.iter().filter(|d| d.decision == AbuseDecision::SlowQueue).count();
+    let hardened = decisions
+        .iter()
+        .filter(|d| matches!(d.decision, AbuseDecision::HardenPow { .. }))
+        .count();
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "config": {
+                "pow_score": state.abuse_config.pow_score,
+                "slow_score": state.abuse_config.slow_score,
+                "base_pow_bits": state.abuse_config.base_pow_bits,
+            },
+            "tracked_claims": abuse.history_len(),
+            "seen_accounts": abuse.seen_accounts_len(),
+            "hardened_pow": hardened,
+            "slow_queued": slow_queued,
+            "decisions": decisions,
+        })),
+    )
+}
+
+/// **GET /faucet_balance** — issued and remaining supply per token. Supply
+/// is re-read at most once every BALANCE_CACHE_SECS, so this can't be used
+/// to hammer the RPC.
+async fn faucet_balance_handler(State(state): State<AppState>) -> (StatusCode, Json<JsonValue>) {
+    let replies = state.send_to_all(|reply| WorkerRequest::FaucetBalance(FaucetBalanceRequest { reply }));
+    if replies.is_empty() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Worker thread is down" })),
+        );
+    }
+    for reply_rx in replies {
+        if reply_rx.await.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "Worker thread crashed during balance read" })),
+            );
+        }
+    }
+
+    let set = state.faucets.lock().unwrap();
+    let supply = state.faucet_supply.lock().unwrap();
+    let faucets: Vec<JsonValue> = set
+        .config
+        .faucets
+        .iter()
+        .map(|faucet| {
+            let s = supply.get(&faucet.symbol);
+            json!({
+                "symbol": faucet.symbol,
+                "decimals": faucet.decimals,
+                "max_supply": s.map(|s| s.max_supply),
+                "issued": s.map(|s| s.issued),
+                "remaining": s.map(|s| s.remaining_supply),
+                "last_updated": s.map(|s| s.last_updated),
+            })
+        })
+        .collect();
+    (StatusCode::OK, Json(json!({ "faucets": faucets })))
+}
+
+/// **POST /admin/recheck** — re-check every faucet on-chain now instead of
+/// waiting for the workers' next periodic check
+async fn recheck_faucets_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let entries = state.faucets.lock().unwrap().config.faucets.clone();
+    let mut replies = Vec::new();
+    for entry in entries {
+        let symbol = entry.symbol.clone();
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        if state.send_to_worker(&symbol, WorkerRequest::CheckFaucets(CheckFaucetsRequest { entry, reply: reply_tx })) {
+            replies.push((symbol, reply_rx));
+        }
+    }
+    if replies.is_empty() {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": "Worker thread is down" })),
+        );
+    }
+    for (symbol, reply_rx) in replies {
+        if reply_rx.await.is_err() {
+            error!(token = %symbol, "worker dropped recheck reply");
+        }
+    }
+
+    let set = state.faucets.lock().unwrap();
+    let faucets: Vec<JsonValue> = set
+        .config
+        .faucets
+        .iter()
+        .map(|faucet| {
+            json!({
+                "symbol": faucet.symbol,
+                "faucet_id": faucet.faucet_id,
+                "status": if set.active(&faucet.symbol).is_some() { "active" } else { "not_found" },
+                "last_checked": set.status.get(&faucet.symbol).map(|c| c.checked_at),
+            })
+        })
+        .collect();
+    (StatusCode::OK, Json(json!({ "rechecked": true, "faucets": faucets })))
+}
+
+/// **POST /admin/reload_faucets** — re-read faucets.json and re-check every
+/// faucet on-chain without a restart. An invalid file keeps the current set.
+async fn reload_faucets_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let config = match load_faucets_config(FAUCETS_PATH) {
+        Ok(config) => config,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": format!("{:#}", e) })),
+            )
+        }
+    };
+
+    // Existing faucets are re-checked by their worker; new ones get a worker.
+    // Dropping a removed faucet's worker disconnects its channel, so it exits.
+    // Workers publish each check to state.faucets before replying.
+    for entry in &config.faucets {
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        let sent = state.send_to_worker(
+            &entry.symbol,
+            WorkerRequest::CheckFaucets(CheckFaucetsRequest { entry: entry.clone(), reply: reply_tx }),
+        );
+        let reply_rx = if sent {
+            reply_rx
+        } else {
+            let (worker, ready) = spawn_faucet_worker(entry.clone(), &state.worker_ctx);
+            state.workers.lock().unwrap().insert(entry.symbol.clone(), worker);
+            ready
+        };
+        if reply_rx.await.is_err() {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Worker thread for {} crashed during faucet check", entry.symbol) })),
+            );
+        }
+    }
+    state.workers.lock().unwrap().retain(|symbol, _| config.get(symbol).is_some());
+
+    info!(path = FAUCETS_PATH, faucets = config.faucets.len(), "faucets reloaded");
+    let mut set = state.faucets.lock().unwrap();
+    set.status.retain(|symbol, _| config.get(symbol).is_some());
+    set.config = config;
+    let faucets: Vec<JsonValue> = set
+        .config
+        .faucets
+        .iter()
+        .map(|faucet| {
+            let active = set.active(&faucet.symbol).is_some();
+            json!({
+                "symbol": faucet.symbol,
+                "faucet_id": faucet.faucet_id,
+                "status": if active { "active" } else { "not_found" },
+                "daily_cap": faucet.daily_cap,
+                "max_per_request": faucet.max_per_request(),
+            })
+        })
+        .collect();
+
+    (StatusCode::OK, Json(json!({ "reloaded": true, "faucets": faucets })))
+}
+
+/// Each faucet's caps as `/admin/limits` reports them
+fn limits_json(config: &FaucetsConfig) -> Vec<JsonValue> {
+    config
+        .faucets
+        .iter()
+        .map(|faucet| {
+            json!({
+                "token_symbol": faucet.symbol,
+                "daily_cap": faucet.daily_cap,
+                "per_request_cap": faucet.max_per_request(),
+                "ip_daily_cap": faucet.ip_daily_cap(),
+            })
+        })
+        .collect()
+}
+
+/// **GET /admin/limits** — current per-token caps
+async fn get_limits_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let set = state.faucets.lock().unwrap();
+    (StatusCode::OK, Json(json!({ "limits": limits_json(&set.config) })))
+}
+
+/// `config` with `token`'s caps changed as `params` asks, or the error
+/// body refusing the change
+fn config_with_limits(config: &FaucetsConfig, token: &str, params: &SetLimitsParams) -> Result<FaucetsConfig, JsonValue> {
+    if params.daily_cap == 0 || params.per_request_cap == Some(0) || params.ip_daily_cap == Some(0) {
+        return Err(json!({ "error": "daily_cap, per_request_cap and ip_daily_cap must be positive" }));
+    }
+    if params.per_request_cap.is_some_and(|cap| cap > params.daily_cap) {
+        return Err(json!({ "error": "per_request_cap can't be above daily_cap" }));
+    }
+
+    let mut updated = config.clone();
+    let Some(entry) = updated.faucets.iter_mut().find(|f| f.symbol == token) else {
+        return Err(json!({
+            "error": format!("Unknown token: {}", token),
+            "available": config.faucets.iter().map(|f| f.symbol.as_str()).collect::<Vec<_>>(),
+        }));
+    };
+    entry.daily_cap = params.daily_cap;
+    entry.max_per_request = params.per_request_cap;
+    if params.ip_daily_cap.is_some() {
+        entry.ip_daily_cap = params.ip_daily_cap;
+    }
+    validate_faucets_config(&updated).map_err(|e| json!({ "error": format!("{:#}", e) }))?;
+    Ok(updated)
+}
+
+/// **POST /admin/limits** — change a token's caps without a restart.
+/// The change is written to faucets.json first, so it survives restarts and
+/// the next claim sees it.
+async fn set_limits_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(params): Json<SetLimitsParams>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let token = params.token_symbol.to_uppercase();
+    let mut set = state.faucets.lock().unwrap();
+    let config = match config_with_limits(&set.config, &token, &params) {
+        Ok(config) => config,
+        Err(body) => return (StatusCode::BAD_REQUEST, Json(body)),
+    };
+
+    if let Err(e) = save_faucets_config(FAUCETS_PATH, &config) {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "error": format!("{:#}", e) })),
+        );
+    }
+    info!(
+        token = %token, daily_cap = params.daily_cap,
+        max_per_request = params.per_request_cap.unwrap_or(params.daily_cap), path = FAUCETS_PATH,
+        "limits updated"
+    );
+    set.config = config;
+    (StatusCode::OK, Json(json!({ "updated": token, "limits": limits_json(&set.config) })))
+}
+
+/// `config` with `entry` added, or the status and error refusing it: `409`
+/// for a symbol that is already registered, `400` for an invalid result
+fn config_with_faucet(config: &FaucetsConfig, entry: &FaucetEntry) -> Result<FaucetsConfig, (StatusCode, String)> {
+    if config.get(&entry.symbol).is_some() {
+        return Err((StatusCode::CONFLICT, format!("Faucet {} is already registered", entry.symbol)));
+    }
+    let mut config = config.clone();
+    config.faucets.push(entry.clone());
+    validate_faucets_config(&config).map_err(|e| (StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    Ok(config)
+}
+
+/// **POST /admin/register_faucet** — add a token without a deploy. A worker
+/// is started for the faucet and checks it on-chain; only a faucet that
+/// exists is added to faucets.json and starts serving claims.
+async fn register_faucet_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(params): Json<RegisterFaucetParams>,
+) -> (StatusCode, Json<JsonValue>) {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let entry = FaucetEntry {
+        symbol: params.symbol.to_uppercase(),
+        faucet_id: normalize_account_id(&params.faucet_id_hex),
+        decimals: params.decimals,
+        daily_cap: params.daily_cap,
+        max_per_request: params.max_per_request,
+        ip_daily_cap: None,
+    };
+    // Catches a taken symbol or a bad or duplicate faucet ID before a worker
+    // is started for it
+    if let Err((status, error)) = config_with_faucet(&state.faucets.lock().unwrap().config, &entry) {
+        return (status, Json(json!({ "error": error })));
+    }
+
+    // The worker publishes its check to state.faucets before answering
+    let (worker, ready) = spawn_faucet_worker(entry.clone(), &state.worker_ctx);
+    let active = match ready.await {
+        Ok(active) => active,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Worker thread for {} crashed during faucet check", entry.symbol) })),
+            )
+        }
+    };
+    if !active {
+        // Dropping the worker disconnects its channel, so it exits
+        drop(worker);
+        let mut set = state.faucets.lock().unwrap();
+        // A concurrent registration of the symbol may have won meanwhile
+        if set.config.get(&entry.symbol).is_none() {
+            set.status.remove(&entry.symbol);
+        }
+        drop(set);
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": format!("Faucet {} not found on-chain", entry.faucet_id) })),
+        );
+    }
+
+    {
+        let mut set = state.faucets.lock().unwrap();
+        // Checked again against the current config: another registration of
+        // the symbol or faucet may have finished while the worker was checking
+        let config = match config_with_faucet(&set.config, &entry) {
+            Ok(config) => config,
+            Err((status, error)) => {
+                drop(worker);
+                return (status, Json(json!({ "error": error })));
+            }
+        };
+        if let Err(e) = save_faucets_config(FAUCETS_PATH, &config) {
+            set.status.remove(&entry.symbol);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("{:#}", e) })),
+            );
+        }
+        set.config = config;
+        // Under the faucet set's lock, so the config and the workers agree
+        state.workers.lock().unwrap().insert(entry.symbol.clone(), worker);
+    }
+    info!(token = %entry.symbol, faucet_id = %entry.faucet_id, path = FAUCETS_PATH, "faucet registered");
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "registered": entry.symbol,
+            "faucet_id": entry.faucet_id,
+            "decimals": entry.decimals,
+            "daily_cap": entry.daily_cap,
+            "max_per_request": entry.max_per_request(),
+            "mint_enabled": entry.daily_cap > 0,
+        })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHALLENGE: &str = "00112233";
+    const ALICE: &str = "0xa28b4f998be3a32047b88ae20e0a35";
+    const BOB: &str = "0x2c09e8d9f4ef022044cfee2d14d3a8";
+
+    #[test]
+    fn challenge_is_redeemed_once() {
+        let mut challenges = HashMap::new();
+        issue_challenge(&mut challenges, CHALLENGE, ALICE, 42, false, 1_000);
+        let issued = redeem_challenge(&mut challenges, CHALLENGE, ALICE, 1_010).unwrap();
+        assert_eq!(issued, IssuedChallenge { target: 42, hardened: false, expires_at: 1_000 + CHALLENGE_TTL_SECS });
+        // Replaying the solved challenge fails
+        assert!(redeem_challenge(&mut challenges, CHALLENGE, ALICE, 1_011).is_none());
+    }
+
+    #[test]
+    fn challenge_is_bound_to_its_account() {
+        let mut challenges = HashMap::new();
+        issue_challenge(&mut challenges, CHALLENGE, ALICE, 42, false, 1_000);
+        assert!(redeem_challenge(&mut challenges, CHALLENGE, BOB, 1_010).is_none());
+        assert!(redeem_challenge(&mut challenges, "ffff", ALICE, 1_010).is_none());
+        // The failed attempts didn't use up Alice's challenge
+        assert!(redeem_challenge(&mut challenges, CHALLENGE, ALICE, 1_010).is_some());
+    }
+
+    #[test]
+    fn challenge_expires_after_its_ttl() {
+        let mut challenges = HashMap::new();
+        let expires_at = issue_challenge(&mut challenges, CHALLENGE, ALICE, 42, false, 1_000);
+        assert_eq!(expires_at, 1_000 + CHALLENGE_TTL_SECS);
+        assert!(redeem_challenge(&mut challenges.clone(), CHALLENGE, ALICE, expires_at).is_some());
+        assert!(redeem_challenge(&mut challenges, CHALLENGE, ALICE, expires_at + 1).is_none());
+
+        // Hardened challenges live longer
+        let expires_at = issue_challenge(&mut challenges, CHALLENGE, ALICE, 42, true, 1_000);
+        assert_eq!(expires_at, 1_000 + HARDENED_CHALLENGE_TTL_SECS);
+        assert!(redeem_challenge(&mut challenges, CHALLENGE, ALICE, 1_000 + CHALLENGE_TTL_SECS + 1).unwrap().hardened);
+    }
+
+    #[test]
+    fn issuing_prunes_expired_challenges() {
+        let mut challenges = HashMap::new();
+        issue_challenge(&mut challenges, "aa", ALICE, 42, false, 1_000);
+        issue_challenge(&mut challenges, "bb", BOB, 42, true, 1_000);
+        issue_challenge(&mut challenges, "cc", ALICE, 42, false, 1_000 + CHALLENGE_TTL_SECS + 1);
+        let mut keys: Vec<&String> = challenges.keys().collect();
+        keys.sort();
+        assert_eq!(keys, [&format!("bb:{}", BOB), &format!("cc:{}", ALICE)]);
+    }
+
+    // sha256(00112233 ‖ nonce_be): nonce 100 is the first whose head is
+    // below the 8-bit threshold; its head is 35952895819614952
+    const POW_CHALLENGE: &str = "00112233";
+
+    #[test]
+    fn verify_pow_accepts_a_solved_nonce() {
+        let target = abuse::pow_target_for_bits(8);
+        assert!(verify_pow(POW_CHALLENGE, "100", target));
+        assert!(verify_pow("0x00112233", "100", target));
+        assert!((0..100).all(|nonce| !verify_pow(POW_CHALLENGE, &nonce.to_string(), target)));
+    }
+
+    #[test]
+    fn verify_pow_checks_the_target() {
+        // The head is ~2^55, so 100 solves 8 bits but not 12
+        assert!(!verify_pow(POW_CHALLENGE, "100", abuse::pow_target_for_bits(12)));
+        assert!(verify_pow(POW_CHALLENGE, "100", 35_952_895_819_615));
+        assert!(!verify_pow(POW_CHALLENGE, "100", 35_952_895_819_614));
+        assert!(!verify_pow(POW_CHALLENGE, "100", 0));
+        // A huge target saturates instead of overflowing
+        assert!(verify_pow(POW_CHALLENGE, "0", u64::MAX));
+    }
+
+    #[test]
+    fn verify_pow_rejects_malformed_input() {
+        let target = u64::MAX;
+        assert!(!verify_pow("not hex", "100", target));
+        assert!(!verify_pow("0011223", "100", target));
+        assert!(!verify_pow(POW_CHALLENGE, "", target));
+        assert!(!verify_pow(POW_CHALLENGE, "-1", target));
+        assert!(!verify_pow(POW_CHALLENGE, "0x64", target));
+        assert!(!verify_pow(POW_CHALLENGE, "18446744073709551616", target));
+    }
+
+    fn account() -> AccountId {
+        AccountId::from_hex(ALICE).unwrap()
+    }
+
+    #[test]
+    fn parses_prefixed_and_bare_hex() {
+        assert_eq!(parse_account_id(ALICE).unwrap(), account());
+        assert_eq!(parse_account_id(ALICE.trim_start_matches("0x")).unwrap(), account());
+        assert_eq!(parse_account_id(&format!("  {}\n", ALICE)).unwrap(), account());
+    }
+
+    #[test]
+    fn parses_testnet_bech32() {
+        let address = account().to_bech32(NetworkId::Testnet);
+        assert!(address.starts_with("mtst1"), "{}", address);
+        assert_eq!(parse_account_id(&address).unwrap(), account());
+        // Both forms share one rate-limit key
+        assert_eq!(normalize_account_id(&address), normalize_account_id(ALICE));
+        assert_eq!(account_rate_key(&address, "MILO"), format!("{}:MILO", ALICE));
+    }
+
+    #[test]
+    fn rejects_invalid_account_ids() {
+        for input in ["", "0x", "0xzz", "0x1234", "hello", "mtst1"] {
+            let err = parse_account_id(input).unwrap_err();
+            assert!(err.contains("Invalid"), "{}: {}", input, err);
+        }
+        // A corrupted checksum is reported as such
+        let mut address = account().to_bech32(NetworkId::Testnet);
+        let last = if address.ends_with('q') { 'p' } else { 'q' };
+        address.pop();
+        address.push(last);
+        let err = parse_account_id(&address).unwrap_err();
+        assert!(err.contains("bad checksum"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_other_networks() {
+        let address = account().to_bech32(NetworkId::Mainnet);
+        let err = parse_account_id(&address).unwrap_err();
+        assert!(err.contains("only serves testnet"), "{}", err);
+    }
+
+    #[test]
+    fn bech32_must_not_mix_case() {
+        let address = account().to_bech32(NetworkId::Testnet);
+        assert_eq!(parse_account_id(&address.to_uppercase()).unwrap(), account());
+        let mixed = format!("MTST{}", &address[4..]);
+        let err = parse_account_id(&mixed).unwrap_err();
+        assert!(err.contains("mixed upper and lower case"), "{}", err);
+    }
+
+    fn mint_request(recipient: &str, amount: u64, note_type: NoteType) -> MintRequest {
+        MintRequest {
+            faucet_id_hex: milo_registry::MILO_FAUCET_ID_HEX.to_string(),
+            recipient_id_hex: recipient.to_string(),
+            amount,
+            token_symbol: "MILO".to_string(),
+            note_type,
+            queued_at: Instant::now(),
+            // Not from a queue; taking it is a no-op
+            queue_ticket: QueueTicket(u64::MAX),
+            span: Span::none(),
+            wait_for_commit: false,
+            reply: tokio::sync::oneshot::channel().0,
+        }
+    }
+
+    fn faucet() -> AccountId {
+        AccountId::from_hex(milo_registry::MILO_FAUCET_ID_HEX).unwrap()
+    }
+
+    fn rng() -> miden_client::crypto::RpoRandomCoin {
+        miden_client::crypto::RpoRandomCoin::new([Felt::new(7); 4].into())
+    }
+
+    #[test]
+    fn only_an_explicit_flag_mints_private() {
+        for flag in [Some("true"), Some("TRUE"), Some("1")] {
+            assert_eq!(claim_note_type(flag), NoteType::Private, "{:?}", flag);
+        }
+        for flag in [None, Some("false"), Some("0"), Some("yes"), Some("")] {
+            assert_eq!(claim_note_type(flag), NoteType::Public, "{:?}", flag);
+        }
+    }
+
+    #[test]
+    fn public_mint_has_no_note_to_import() {
+        let req = mint_request(BOB, 1_000, NoteType::Public);
+        let notes = mint_notes(faucet(), std::slice::from_ref(&req), &mut rng()).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].metadata().note_type(), NoteType::Public);
+        assert!(private_note_for(&req, &notes[0]).is_none());
+    }
+
+    #[test]
+    fn private_mint_hands_over_the_full_note() {
+        use miden_client::utils::Deserializable;
+
+        let req = mint_request(BOB, 1_000, NoteType::Private);
+        let notes = mint_notes(faucet(), std::slice::from_ref(&req), &mut rng()).unwrap();
+        assert_eq!(notes[0].metadata().note_type(), NoteType::Private);
+
+        let private = private_note_for(&req, &notes[0]).unwrap();
+        assert_eq!(private.note_id, notes[0].id().to_hex());
+        let imported = Note::read_from_bytes(&hex::decode(&private.note).unwrap()).unwrap();
+        assert_eq!(imported.id(), notes[0].id());
+        assert_eq!(imported.assets(), notes[0].assets());
+    }
+
+    #[test]
+    fn failed_private_mint_is_rejected_with_400() {
+        let lone = [mint_request(BOB, 1_000, NoteType::Private)];
+        assert_eq!(private_mint_prefix(&lone), PRIVATE_MINT_ERROR);
+        // Even when the client's reason mentions auth, the flag is what was refused
+        let e = format!("{}build mint note: recipient auth component unsupported", private_mint_prefix(&lone));
+        let (status, body) = mint_failure_response(&e, "MILO");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "private_note_rejected");
+        assert!(body["error"].as_str().unwrap().ends_with("recipient auth component unsupported"));
+    }
+
+    #[test]
+    fn failed_public_or_batched_mint_is_a_server_error() {
+        let batch = [mint_request(BOB, 1, NoteType::Private), mint_request(ALICE, 1, NoteType::Public)];
+        assert_eq!(private_mint_prefix(&batch), "");
+        assert_eq!(private_mint_prefix(&[mint_request(BOB, 1, NoteType::Public)]), "");
+
+        let (status, body) = mint_failure_response("build mint tx: invalid asset", "MILO");
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.get("code").is_none());
+    }
+
+    #[test]
+    fn bad_recipient_fails_the_whole_mint() {
+        let reqs = [mint_request(BOB, 1, NoteType::Public), mint_request("0xnope", 1, NoteType::Public)];
+        let err = mint_notes(faucet(), &reqs, &mut rng()).unwrap_err();
+        assert!(err.contains("Invalid account ID"), "{}", err);
+    }
+
+    /// `n` public mints of MILO to BOB, each with a queue slot taken
+    fn queue_mints(n: usize, queue: &MintQueue, tx: &std::sync::mpsc::Sender<WorkerRequest>) {
+        for _ in 0..n {
+            let mut req = mint_request(BOB, 1_000, NoteType::Public);
+            req.queue_ticket = queue.try_push().unwrap();
+            tx.send(WorkerRequest::Mint(req)).unwrap();
+        }
+    }
+
+    #[test]
+    fn batch_takes_queued_mints_up_to_the_max() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let queue = MintQueue::new(100);
+        queue_mints(5, &queue, &tx);
+
+        let mut deferred = VecDeque::new();
+        let first = mint_request(ALICE, 1, NoteType::Public);
+        let batch = collect_mint_batch(first, &rx, 4, &queue, &mut deferred);
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch[0].recipient_id_hex, ALICE);
+        assert_eq!(queue.depth(), 2);
+        assert!(deferred.is_empty());
+
+        // The rest make the next batch
+        let first = match rx.recv().unwrap() {
+            WorkerRequest::Mint(r) => r,
+            _ => unreachable!(),
+        };
+        queue.take(first.queue_ticket);
+        assert_eq!(collect_mint_batch(first, &rx, 4, &queue, &mut deferred).len(), 2);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[test]
+    fn other_requests_are_set_aside_while_batching() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let queue = MintQueue::new(100);
+        queue_mints(1, &queue, &tx);
+        tx.send(WorkerRequest::TxStatus(TxStatusRequest {
+            tx_id_hex: "0xabc".to_string(),
+            sync: false,
+            reply: tokio::sync::oneshot::channel().0,
+        }))
+        .unwrap();
+        queue_mints(1, &queue, &tx);
+
+        let mut deferred = VecDeque::new();
+        let batch = collect_mint_batch(mint_request(ALICE, 1, NoteType::Public), &rx, 16, &queue, &mut deferred);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(deferred.len(), 1);
+        assert!(matches!(deferred.front(), Some(WorkerRequest::TxStatus(_))));
+    }
+
+    #[test]
+    fn batch_is_grouped_by_faucet_in_arrival_order() {
+        let mut other = mint_request(BOB, 3, NoteType::Public);
+        other.faucet_id_hex = milo_registry::MUSDC_FAUCET_ID_HEX.to_string();
+        let batch = vec![mint_request(ALICE, 1, NoteType::Public), other, mint_request(BOB, 2, NoteType::Public)];
+
+        let groups = group_mints_by_faucet(batch);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, milo_registry::MILO_FAUCET_ID_HEX);
+        assert_eq!(groups[0].1.iter().map(|r| r.amount).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(groups[1].1[0].amount, 3);
+    }
+
+    #[test]
+    fn n_mints_make_one_transaction() {
+        let reqs = [
+            mint_request(ALICE, 1, NoteType::Public),
+            mint_request(BOB, 2, NoteType::Public),
+            mint_request(BOB, 3, NoteType::Private),
+        ];
+        let (tx_request, receipt_notes) = mint_tx_request(faucet(), &reqs, &mut rng()).unwrap();
+        let notes = tx_request.expected_output_own_notes();
+        assert_eq!(notes.len(), reqs.len());
+        assert_eq!(receipt_notes.len(), reqs.len());
+        // Each request gets its own note in the shared transaction
+        let ids: HashSet<_> = receipt_notes.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(ids.len(), reqs.len());
+        assert!(notes.iter().all(|n| ids.contains(&n.id().to_hex())));
+        assert_eq!(receipt_notes.iter().filter(|(_, p)| p.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn one_bad_recipient_fails_the_batch_but_not_the_others() {
+        let reqs = [
+            mint_request(ALICE, 1, NoteType::Public),
+            mint_request("0xnope", 2, NoteType::Public),
+            mint_request(BOB, 3, NoteType::Public),
+        ];
+        let err = mint_tx_request(faucet(), &reqs, &mut rng()).err().unwrap();
+        assert_eq!(retry::classify(&err), ErrorClass::Permanent);
+        // Which is what the worker's one-by-one fallback relies on
+        for req in &reqs {
+            let alone = mint_tx_request(faucet(), std::slice::from_ref(req), &mut rng());
+            assert_eq!(alone.is_ok(), req.recipient_id_hex != "0xnope");
+        }
+    }
+
+    #[test]
+    fn full_queue_rejects_with_retry_after() {
+        let queue = MintQueue::new(2);
+        let released = std::cell::Cell::new(0);
+        let release = || released.set(released.get() + 1);
+        let first = take_queue_slot(&queue, release).unwrap();
+        assert!(take_queue_slot(&queue, release).is_ok());
+        assert_eq!(released.get(), 0);
+
+        let body = take_queue_slot(&queue, release).unwrap_err();
+        assert_eq!(body["code"], "queue_full");
+        assert_eq!(body["queue_depth"], 2);
+        assert_eq!(body["retry_after"], MINT_QUEUE_RETRY_AFTER_SECS);
+        assert_eq!(released.get(), 1);
+
+        // Once the worker takes a mint there is room again
+        queue.take(first);
+        assert!(take_queue_slot(&queue, release).is_ok());
+        assert_eq!(released.get(), 1);
+    }
+
+    #[test]
+    fn rejected_claim_gives_its_reservation_back() {
+        let store = RateLimitStore::open(":memory:").unwrap();
+        let limits = [Limit { key: "0xalice:MILO", max: 1_000, min_interval_secs: 0 }];
+        let now = 1_700_000_000;
+        let Ok(grants) = store.reserve(&limits, now, 400) else { panic!("reservation refused") };
+        assert_eq!(store.usage("0xalice:MILO", now).unwrap().used, 400);
+
+        let queue = MintQueue::new(0);
+        assert!(take_queue_slot(&queue, || store.release(&grants)).is_err());
+        assert_eq!(store.usage("0xalice:MILO", now).unwrap().used, 0);
+        // The full allowance is available again
+        assert!(store.reserve(&limits, now, 1_000).is_ok());
+    }
+
+    #[test]
+    fn queue_reports_depth_and_oldest_age() {
+        let queue = MintQueue::new(10);
+        assert_eq!(queue.depth(), 0);
+        assert!(queue.oldest_age().is_none());
+
+        let first = queue.try_push().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let second = queue.try_push().unwrap();
+        assert_eq!(queue.depth(), 2);
+        let oldest = queue.oldest_age().unwrap();
+        assert!(oldest >= Duration::from_millis(20));
+
+        queue.cancel(second);
+        assert_eq!(queue.depth(), 1);
+        assert!(queue.oldest_age().unwrap() >= oldest);
+        queue.take(first);
+        assert!(queue.oldest_age().is_none());
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn accounts_from_one_ip_share_its_allowance() {
+        let faucet = FaucetEntry {
+            symbol: "MILO".to_string(),
+            faucet_id: milo_registry::MILO_FAUCET_ID_HEX.to_string(),
+            decimals: 8,
+            daily_cap: 10,
+            max_per_request: None,
+            ip_daily_cap: Some(15),
+        };
+        let store = RateLimitStore::open(":memory:").unwrap();
+        let now = 1_700_000_000;
+        let peer: SocketAddr = "203.0.113.7:5000".parse().unwrap();
+        // A forged header doesn't give the second account a fresh IP
+        let ip_key = ip_rate_key(forwarded_client_ip(&HeaderMap::new(), peer, &[]), "MILO");
+        assert_eq!(ip_rate_key(forwarded_client_ip(&forwarded("198.51.100.1"), peer, &[]), "MILO"), ip_key);
+
+        let alice = account_rate_key(ALICE, "MILO");
+        assert!(store.reserve(&claim_limits(&faucet, &alice, &ip_key, 0), now, 10).is_ok());
+
+        let bob = account_rate_key(BOB, "MILO");
+        match store.reserve(&claim_limits(&faucet, &bob, &ip_key, 0), now, 10) {
+            Err(ReserveError::LimitReached { key, remaining, .. }) => {
+                assert_eq!(key, ip_key);
+                assert_eq!(remaining, 5);
+            }
+            _ => panic!("second account should hit the IP cap"),
+        }
+        assert!(store.reserve(&claim_limits(&faucet, &bob, &ip_key, 0), now, 5).is_ok());
+        assert_eq!(store.usage(&bob, now).unwrap().used, 5);
+        assert_eq!(store.usage(&ip_key, now).unwrap().used, 15);
+    }
+
+    fn get_params(query: &str) -> GetTokensParams {
+        let uri: axum::http::Uri = format!("/get_tokens?{}", query).parse().unwrap();
+        let Ok(Query(params)) = Query::<GetTokensParams>::try_from_uri(&uri) else { panic!("bad query: {}", query) };
+        params
+    }
+
+    #[test]
+    fn get_and_post_parse_the_same_claim() {
+        let get = get_params(&format!(
+            "account_id={}&asset_amount=500&challenge=00112233&nonce=100&token_symbol=milo&is_private_note=true",
+            ALICE
+        ));
+        // JSON clients send numbers and booleans where the query has strings
+        let post: GetTokensParams = serde_json::from_value(json!({
+            "account_id": ALICE,
+            "asset_amount": 500,
+            "challenge": "00112233",
+            "nonce": 100,
+            "token_symbol": "milo",
+            "is_private_note": true,
+        }))
+        .unwrap();
+
+        for params in [&get, &post] {
+            assert_eq!(params.asset_amount.as_deref(), Some("500"));
+            assert_eq!(params.nonce, "100");
+            assert_eq!(claim_token_symbol(params.token_symbol.as_deref()), "MILO");
+            assert_eq!(claim_note_type(params.is_private_note.as_deref()), NoteType::Private);
+        }
+    }
+
+    #[test]
+    fn get_and_post_share_one_allowance() {
+        let address = account().to_bech32(NetworkId::Testnet);
+        let get = get_params(&format!("account_id={}&challenge=c&nonce=1&asset_amount=6", ALICE));
+        let post: GetTokensParams =
+            serde_json::from_value(json!({ "account_id": address, "challenge": "c", "nonce": 2, "asset_amount": "6" })).unwrap();
+
+        let key = |p: &GetTokensParams| account_rate_key(&p.account_id, &claim_token_symbol(p.token_symbol.as_deref()));
+        assert_eq!(key(&get), key(&post));
+
+        let store = RateLimitStore::open(":memory:").unwrap();
+        let now = 1_700_000_000;
+        let get_key = key(&get);
+        assert!(store.reserve(&[Limit { key: &get_key, max: 10, min_interval_secs: 0 }], now, 6).is_ok());
+        let post_key = key(&post);
+        assert!(matches!(
+            store.reserve(&[Limit { key: &post_key, max: 10, min_interval_secs: 0 }], now, 6),
+            Err(ReserveError::LimitReached { remaining: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn parses_decimal_token_amounts() {
+        assert_eq!(parse_token_amount("2.5", 8), Ok(250_000_000));
+        assert_eq!(parse_token_amount(" 10 ", 8), Ok(1_000_000_000));
+        assert_eq!(parse_token_amount("5.", 8), Ok(500_000_000));
+        assert_eq!(parse_token_amount("0.5", 8), Ok(50_000_000));
+        assert_eq!(parse_token_amount(".5", 8), Ok(50_000_000));
+        assert_eq!(parse_token_amount("0.00000001", 8), Ok(1));
+        assert_eq!(parse_token_amount("0", 8), Ok(0));
+        assert_eq!(parse_token_amount("7", 0), Ok(7));
+    }
+
+    #[test]
+    fn refuses_excess_decimal_places() {
+        let err = parse_token_amount("0.000000001", 8).unwrap_err();
+        assert!(err.contains("more than 8 decimal places"), "{}", err);
+        assert!(parse_token_amount("1.5", 0).unwrap_err().contains("more than 0 decimal places"));
+    }
+
+    #[test]
+    fn refuses_amounts_past_u64() {
+        // u64::MAX base units at 8 decimals
+        assert_eq!(parse_token_amount("184467440737.09551615", 8), Ok(u64::MAX));
+        for input in ["184467440737.09551616", "184467440738", "99999999999999999999999"] {
+            let err = parse_token_amount(input, 8).unwrap_err();
+            assert!(err.contains("too large"), "{}: {}", input, err);
+        }
+        // 10^20 doesn't fit a u64, so no amount can be scaled
+        assert!(parse_token_amount("1", 20).unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn refuses_malformed_amounts() {
+        for input in ["", ".", "abc", "1.2.3", "-1", "1e5", "+2", "2,5"] {
+            let err = parse_token_amount(input, 8).unwrap_err();
+            assert!(err.contains("bad amount_tokens"), "{}: {}", input, err);
+        }
+    }
+
+    #[test]
+    fn formats_base_units_without_trailing_zeros() {
+        assert_eq!(format_token_amount(250_000_000, 8), "2.5");
+        assert_eq!(format_token_amount(100_000_000, 8), "1");
+        assert_eq!(format_token_amount(1, 8), "0.00000001");
+        assert_eq!(format_token_amount(0, 8), "0");
+        assert_eq!(format_token_amount(5, 0), "5");
+        assert_eq!(format_token_amount(u64::MAX, 8), "184467440737.09551615");
+        // Decimals past u64 are shown in base units
+        assert_eq!(format_token_amount(42, 20), "42");
+    }
+
+    #[test]
+    fn token_amounts_round_trip() {
+        for amount in [0, 1, 50_000_000, 123_456_789, 250_000_000, 1_000_000_000, u64::MAX] {
+            for decimals in [0, 6, 8] {
+                let formatted = format_token_amount(amount, decimals);
+                assert_eq!(parse_token_amount(&formatted, decimals), Ok(amount), "{} at {}", formatted, decimals);
+            }
+        }
+    }
+
+    fn header(headers: &HeaderMap, name: &str) -> u64 {
+        headers[name].to_str().unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn allowance_and_headers_match_enforcement() {
+        let store = RateLimitStore::open(":memory:").unwrap();
+        let now = 1_700_000_000;
+        // Claimed under the hex form, queried under the bech32 form
+        let address = account().to_bech32(NetworkId::Testnet);
+        let key = account_rate_key(ALICE, "MILO");
+        let limits = [Limit { key: &key, max: 10, min_interval_secs: 0 }];
+        assert!(store.reserve(&limits, now, 6).is_ok());
+
+        let allowance = account_allowance(&store, &address, "MILO", 10, now + 60).unwrap();
+        assert_eq!((allowance.used, allowance.remaining()), (6, 4));
+        assert_eq!(allowance.reset_at, now + rate_limits::RATE_LIMIT_WINDOW_SECS);
+
+        let headers = allowance.headers();
+        assert_eq!(header(&headers, "x-ratelimit-limit"), 10);
+        assert_eq!(header(&headers, "x-ratelimit-remaining"), 4);
+        assert_eq!(header(&headers, "x-ratelimit-reset"), now + rate_limits::RATE_LIMIT_WINDOW_SECS);
+
+        // The claim that gets 429 sees the same numbers in its headers
+        match store.reserve(&limits, now + 60, 5) {
+            Err(ReserveError::LimitReached { remaining, reset_at, .. }) => {
+                let headers = account_allowance(&store, ALICE, "MILO", 10, now + 60).unwrap().headers();
+                assert_eq!(header(&headers, "x-ratelimit-remaining"), remaining);
+                assert_eq!(header(&headers, "x-ratelimit-reset"), reset_at);
+            }
+            _ => panic!("claim past the cap should be refused"),
+        }
+    }
+
+    #[test]
+    fn allowance_frees_up_after_the_window() {
+        let store = RateLimitStore::open(":memory:").unwrap();
+        let now = 1_700_000_000;
+        let key = account_rate_key(ALICE, "MILO");
+        assert!(store.reserve(&[Limit { key: &key, max: 10, min_interval_secs: 0 }], now, 10).is_ok());
+
+        assert_eq!(account_allowance(&store, ALICE, "MILO", 10, now + 1).unwrap().remaining(), 0);
+        let later = now + rate_limits::RATE_LIMIT_WINDOW_SECS;
+        let allowance = account_allowance(&store, ALICE, "MILO", 10, later).unwrap();
+        assert_eq!(allowance.remaining(), 10);
+        // Nothing in the window: resets now
+        assert_eq!(allowance.reset_at, later);
+    }
+
+    #[test]
+    fn second_claim_is_refused_while_the_first_is_in_flight() {
+        let set = Arc::new(Mutex::new(HashSet::new()));
+        let first = InFlightMint::start(&set, ALICE, "MILO");
+        assert!(first.is_some());
+        assert!(InFlightMint::start(&set, ALICE, "MILO").is_none());
+        // Other tokens and other accounts are not held up
+        assert!(InFlightMint::start(&set, ALICE, "MUSDC").is_some());
+        assert!(InFlightMint::start(&set, BOB, "MILO").is_some());
+
+        drop(first);
+        assert!(set.lock().unwrap().is_empty());
+        assert!(InFlightMint::start(&set, ALICE, "MILO").is_some());
+    }
+
+    #[test]
+    fn both_account_forms_share_one_in_flight_slot() {
+        let set = Arc::new(Mutex::new(HashSet::new()));
+        let address = account().to_bech32(NetworkId::Testnet);
+        let _first = InFlightMint::start(&set, &normalize_account_id(ALICE), "MILO");
+        assert!(InFlightMint::start(&set, &normalize_account_id(&address), "MILO").is_none());
+    }
+
+    #[tokio::test]
+    async fn slot_stays_taken_until_a_timed_out_mint_finishes() {
+        let set = Arc::new(Mutex::new(HashSet::new()));
+        let in_flight = InFlightMint::start(&set, ALICE, "MILO").unwrap();
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel::<()>();
+        // As claim_tokens does when a wait=true claim gives up on the worker
+        let task = tokio::spawn(async move {
+            let _in_flight = in_flight;
+            let _ = reply_rx.await;
+        });
+
+        assert!(InFlightMint::start(&set, ALICE, "MILO").is_none());
+        reply_tx.send(()).unwrap();
+        task.await.unwrap();
+        assert!(InFlightMint::start(&set, ALICE, "MILO").is_some());
+    }
+
+    fn faucet_entry(symbol: &str, faucet_id: &str) -> FaucetEntry {
+        FaucetEntry {
+            symbol: symbol.to_string(),
+            faucet_id: faucet_id.to_string(),
+            decimals: 8,
+            daily_cap: 10,
+            max_per_request: None,
+            ip_daily_cap: None,
+        }
+    }
+
+    #[test]
+    fn registration_is_rechecked_against_the_current_config() {
+        let config = FaucetsConfig { faucets: vec![faucet_entry("MILO", milo_registry::MILO_FAUCET_ID_HEX)] };
+        let melo = faucet_entry("MELO", milo_registry::MELO_FAUCET_ID_HEX);
+        let added = config_with_faucet(&config, &melo).unwrap();
+        assert_eq!(added.faucets.len(), 2);
+
+        // A concurrent registration of MELO finished first: the second one loses
+        let (status, error) = config_with_faucet(&added, &melo).unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(error.contains("MELO"));
+        // Same faucet under another symbol
+        let (status, _) = config_with_faucet(&added, &faucet_entry("MELO2", milo_registry::MELO_FAUCET_ID_HEX)).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(config.faucets.len(), 1);
+    }
+
+    #[test]
+    fn interleaved_tokens_free_only_their_own_slots() {
+        let queue = MintQueue::new(10);
+        let milo = queue.try_push().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let melo = queue.try_push().unwrap();
+        let musdc = queue.try_push().unwrap();
+
+        // MELO's worker takes its mint first: MILO's is still the oldest
+        queue.take(melo);
+        assert!(queue.oldest_age().unwrap() >= Duration::from_millis(20));
+        // A MUSDC claim whose worker was down gives back its own slot, not MILO's
+        queue.cancel(musdc);
+        assert_eq!(queue.depth(), 1);
+        assert!(queue.oldest_age().unwrap() >= Duration::from_millis(20));
+        // Giving a slot back twice frees nothing else
+        queue.cancel(musdc);
+        assert_eq!(queue.depth(), 1);
+        queue.take(milo);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    const ADMIN_TOKEN: &str = "test-admin-token";
+
+    /// State serving MILO (daily cap 10) whose only worker is the returned
+    /// channel: the test answers the worker's requests itself
+    fn test_state() -> (AppState, std::sync::mpsc::Receiver<WorkerRequest>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let workers = HashMap::from([("MILO".to_string(), FaucetWorker { tx, handle: std::thread::spawn(|| {}) })]);
+        let faucets = Arc::new(Mutex::new(FaucetSet {
+            config: FaucetsConfig { faucets: vec![faucet_entry("MILO", milo_registry::MILO_FAUCET_ID_HEX)] },
+            status: HashMap::from([("MILO".to_string(), FaucetCheck { active: true, checked_at: 0 })]),
+        }));
+        let faucet_supply = Arc::new(Mutex::new(HashMap::new()));
+        let mint_queue = Arc::new(MintQueue::new(10));
+        let metrics = Arc::new(Mutex::new(FaucetMetrics::default()));
+        let mint_history = Arc::new(MintHistory::open(":memory:", 1).unwrap());
+        let shutdown = Arc::new(Shutdown::default());
+        let state = AppState {
+            workers: Arc::new(Mutex::new(workers)),
+            worker_ctx: WorkerContext {
+                keystore_path: String::new(),
+                store_path: String::new(),
+                endpoint: Endpoint::testnet(),
+                faucet_supply: faucet_supply.clone(),
+                faucets: faucets.clone(),
+                mint_queue: mint_queue.clone(),
+                metrics: metrics.clone(),
+                mint_history: mint_history.clone(),
+                shutdown: shutdown.clone(),
+                max_mint_batch: 1,
+                recheck_interval: Duration::from_secs(60),
+            },
+            faucets,
+            rate_limits: Arc::new(RateLimitStore::open(":memory:").unwrap()),
+            request_cooldown_secs: 0,
+            trusted_proxies: Arc::new(Vec::new()),
+            admin_token: Some(Arc::new(ADMIN_TOKEN.to_string())),
+            abuse: Arc::new(Mutex::new(AbuseTracker::default())),
+            abuse_config: AbuseConfig { pow_score: u32::MAX, slow_score: u32::MAX, base_pow_bits: 0 },
+            pow_challenges: Arc::new(Mutex::new(HashMap::new())),
+            slow_queue: Arc::new(tokio::sync::Semaphore::new(1)),
+            faucet_supply,
+            mint_queue,
+            in_flight_mints: Arc::new(Mutex::new(HashSet::new())),
+            metrics,
+            mint_history,
+            shutdown,
+        };
+        (state, rx)
+    }
+
+    fn peer() -> SocketAddr {
+        SocketAddr::from(([203, 0, 113, 7], 40_000))
+    }
+
+    /// A MILO claim by Alice
+    fn claim(amount: u64, wait: bool) -> GetTokensParams {
+        GetTokensParams {
+            account_id: ALICE.to_string(),
+            is_private_note: None,
+            asset_amount: Some(amount.to_string()),
+            amount_tokens: None,
+            challenge: CHALLENGE.to_string(),
+            nonce: "0".to_string(),
+            token_symbol: Some("MILO".to_string()),
+            wait: wait.then(|| "true".to_string()),
+        }
+    }
+
+    /// A challenge the claim counts as solved, as a batch's is
+    fn solved() -> Option<IssuedChallenge> {
+        Some(IssuedChallenge { target: 0, hardened: false, expires_at: u64::MAX })
+    }
+
+    /// Next request the fake worker got, which must be a mint
+    fn next_mint(worker: &std::sync::mpsc::Receiver<WorkerRequest>) -> MintRequest {
+        let Ok(WorkerRequest::Mint(req)) = worker.recv_timeout(Duration::from_secs(5)) else {
+            panic!("expected a mint request");
+        };
+        req
+    }
+
+    fn receipt(commit: Option<CommitStatus>) -> MintReceipt {
+        use miden_client::utils::Deserializable;
+        MintReceipt {
+            tx_id: "0x01".to_string(),
+            transaction_id: TransactionId::read_from_bytes(&[0; 32]).unwrap(),
+            note_id: "0x02".to_string(),
+            private_note: None,
+            commit,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simultaneous_claims_reach_the_worker_once() {
+        let (state, worker) = test_state();
+        let mut claims = tokio::task::JoinSet::new();
+        for _ in 0..2 {
+            let state = state.clone();
+            claims.spawn(async move { claim_tokens(peer(), &HeaderMap::new(), claim(5, false), &state, solved()).await });
+        }
+
+        // The worker holds one mint; the duplicate is refused without reaching it
+        let mint = next_mint(&worker);
+        let (status, Json(body)) = claims.join_next().await.unwrap().unwrap();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "mint_in_progress");
+
+        let _ = mint.reply.send(Ok(receipt(None)));
+        let (status, _) = claims.join_next().await.unwrap().unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(worker.try_recv().is_err());
+        assert!(state.in_flight_mints.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn only_waiting_claims_report_the_commit() {
+        let (state, worker) = test_state();
+
+        // Default: answered once submitted, without commit fields
+        let claim_task = {
+            let state = state.clone();
+            tokio::spawn(async move { claim_tokens(peer(), &HeaderMap::new(), claim(5, false), &state, solved()).await })
+        };
+        let mint = next_mint(&worker);
+        assert!(!mint.wait_for_commit);
+        let _ = mint.reply.send(Ok(receipt(None)));
+        let (status, Json(body)) = claim_task.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.get("committed").is_none() && body.get("block_num").is_none(), "{}", body);
+
+        // wait=true: the worker waits for the commit and the answer carries it
+        let claim_task = {
+            let state = state.clone();
+            tokio::spawn(async move { claim_tokens(peer(), &HeaderMap::new(), claim(5, true), &state, solved()).await })
+        };
+        let mint = next_mint(&worker);
+        assert!(mint.wait_for_commit);
+        let _ = mint.reply.send(Ok(receipt(Some(CommitStatus { committed: true, block_num: Some(812) }))));
+        let (status, Json(body)) = claim_task.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["committed"], true);
+        assert_eq!(body["block_num"], 812);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_timed_out_wait_keeps_the_reservation_until_the_worker_answers() {
+        let (state, worker) = test_state();
+        let rate_key = account_rate_key(ALICE, "MILO");
+        let used = |state: &AppState| {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+            state.rate_limits.usage(&rate_key, now).unwrap().used
+        };
+
+        let claim_task = {
+            let state = state.clone();
+            tokio::spawn(async move {
+                let params = claim(5, true);
+                claim_tokens_within(peer(), &HeaderMap::new(), params, &state, solved(), Duration::from_millis(50)).await
+            })
+        };
+        let mint = next_mint(&worker);
+        let (status, Json(body)) = claim_task.await.unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["code"], "mint_pending");
+
+        // The mint is still running: its amount stays reserved and the
+        // account can't start another one
+        assert_eq!(used(&state), 5);
+        assert!(InFlightMint::start(&state.in_flight_mints, &normalize_account_id(ALICE), "MILO").is_none());
+
+        // The worker gives up on it after all: the reservation is released
+        let _ = mint.reply.send(Err("transaction rejected".to_string()));
+        // once the task holding the in-flight slot is done
+        for _ in 0..100 {
+            if state.in_flight_mints.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(state.in_flight_mints.lock().unwrap().is_empty());
+        assert_eq!(used(&state), 0);
+    }
+
+    #[test]
+    fn admin_endpoints_need_the_token() {
+        let (mut state, _worker) = test_state();
+        let authorization =
+            |value: &str| HeaderMap::from_iter([(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap())]);
+        let valid = authorization(&format!("Bearer {}", ADMIN_TOKEN));
+        assert!(check_admin(&valid, &state).is_ok());
+        for headers in [HeaderMap::new(), authorization("Bearer wrong"), authorization(ADMIN_TOKEN)] {
+            let (status, _) = check_admin(&headers, &state).unwrap_err();
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+
+        // Without FAUCET_ADMIN_TOKEN the endpoints are off for everyone
+        state.admin_token = None;
+        let (status, _) = check_admin(&valid, &state).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    fn set_limits(token_symbol: &str, daily_cap: u64, per_request_cap: Option<u64>) -> SetLimitsParams {
+        SetLimitsParams { token_symbol: token_symbol.to_string(), daily_cap, per_request_cap, ip_daily_cap: None }
+    }
+
+    #[test]
+    fn changed_limits_survive_a_restart() {
+        let config = FaucetsConfig {
+            faucets: vec![
+                faucet_entry("MILO", milo_registry::MILO_FAUCET_ID_HEX),
+                faucet_entry("MELO", milo_registry::MELO_FAUCET_ID_HEX),
+            ],
+        };
+        let updated = config_with_limits(&config, "MILO", &set_limits("MILO", 500, Some(50))).unwrap();
+
+        let path = std::env::temp_dir().join(format!("milo_faucet_limits_{}.json", std::process::id()));
+        save_faucets_config(&path, &updated).unwrap();
+        let loaded = load_faucets_config(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(limits_json(&loaded), limits_json(&updated));
+        let milo = loaded.get("MILO").unwrap();
+        assert_eq!((milo.daily_cap, milo.max_per_request()), (500, 50));
+        assert_eq!(loaded.get("MELO").unwrap().daily_cap, 10);
+    }
+
+    #[test]
+    fn invalid_limits_are_refused() {
+        let config = FaucetsConfig { faucets: vec![faucet_entry("MILO", milo_registry::MILO_FAUCET_ID_HEX)] };
+        assert!(config_with_limits(&config, "MUSDC", &set_limits("MUSDC", 500, None)).is_err());
+        assert!(config_with_limits(&config, "MILO", &set_limits("MILO", 0, None)).is_err());
+        assert!(config_with_limits(&config, "MILO", &set_limits("MILO", 500, Some(0))).is_err());
+        assert!(config_with_limits(&config, "MILO", &set_limits("MILO", 500, Some(501))).is_err());
+    }
+
+    #[tokio::test]
+    async fn a_lowered_cap_refuses_the_next_claim() {
+        let (state, worker) = test_state();
+        let worker = std::thread::spawn(move || {
+            let _ = next_mint(&worker).reply.send(Ok(receipt(None)));
+            worker
+        });
+        let (status, _) = claim_tokens(peer(), &HeaderMap::new(), claim(6, false), &state, solved()).await;
+        assert_eq!(status, StatusCode::OK);
+        let worker = worker.join().unwrap();
+
+        // Another 4 would fit under the old cap of 10, not under the new one
+        {
+            let mut set = state.faucets.lock().unwrap();
+            set.config = config_with_limits(&set.config, "MILO", &set_limits("MILO", 8, None)).unwrap();
         }
+        let (status, Json(body)) = claim_tokens(peer(), &HeaderMap::new(), claim(4, false), &state, solved()).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["remaining"], 2);
+        assert!(worker.try_recv().is_err());
     }
 }
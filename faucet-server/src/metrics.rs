@@ -0,0 +1,81 @@
+//! Prometheus metrics for the faucet
+//!
+//! Claim handlers and the per-faucet workers update one `FaucetMetrics`
+//! behind an `Arc<Mutex<>>`; `GET /metrics` renders it together with the
+//! current mint queue depth.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the mint latency histogram buckets
+const LATENCY_BUCKETS_SECS: [f64; 9] = [0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+#[derive(Debug, Default)]
+pub struct FaucetMetrics {
+    /// (token, status) -> mints
+    mints_total: BTreeMap<(String, String), u64>,
+    rate_limited_total: u64,
+    /// Cumulative count per bucket in `LATENCY_BUCKETS_SECS`
+    latency_buckets: [u64; LATENCY_BUCKETS_SECS.len()],
+    latency_sum_secs: f64,
+    latency_count: u64,
+}
+
+impl FaucetMetrics {
+    /// A claim for `token` ended with `status` (success, error, queue_full)
+    pub fn record_mint(&mut self, token: &str, status: &str) {
+        *self.mints_total.entry((token.to_string(), status.to_string())).or_default() += 1;
+    }
+
+    /// A claim was refused by the daily limit
+    pub fn record_rate_limited(&mut self) {
+        self.rate_limited_total += 1;
+    }
+
+    /// Time from a mint being queued to the worker answering it
+    pub fn observe_mint_latency(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        for (bucket, bound) in self.latency_buckets.iter_mut().zip(LATENCY_BUCKETS_SECS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.latency_sum_secs += secs;
+        self.latency_count += 1;
+    }
+
+    /// Prometheus text exposition of every metric
+    pub fn render(&self, worker_queue_depth: usize) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP faucet_mints_total Mint claims by token and outcome\n# TYPE faucet_mints_total counter");
+        for ((token, status), n) in &self.mints_total {
+            let _ = writeln!(out, "faucet_mints_total{{token=\"{}\",status=\"{}\"}} {}", token, status, n);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP faucet_rate_limited_total Claims refused by the daily limit\n\
+             # TYPE faucet_rate_limited_total counter\n\
+             faucet_rate_limited_total {}",
+            self.rate_limited_total
+        );
+        let _ = writeln!(
+            out,
+            "# HELP faucet_worker_queue_depth Mints waiting for a worker thread\n\
+             # TYPE faucet_worker_queue_depth gauge\n\
+             faucet_worker_queue_depth {}",
+            worker_queue_depth
+        );
+
+        let _ = writeln!(out, "# HELP faucet_mint_latency_seconds Time from queueing a mint to its result\n# TYPE faucet_mint_latency_seconds histogram");
+        for (bound, count) in LATENCY_BUCKETS_SECS.iter().zip(self.latency_buckets) {
+            let _ = writeln!(out, "faucet_mint_latency_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+        }
+        let _ = writeln!(out, "faucet_mint_latency_seconds_bucket{{le=\"+Inf\"}} {}", self.latency_count);
+        let _ = writeln!(out, "faucet_mint_latency_seconds_sum {}", self.latency_sum_secs);
+        let _ = writeln!(out, "faucet_mint_latency_seconds_count {}", self.latency_count);
+        out
+    }
+}
@@ -0,0 +1,153 @@
+//! History of every mint the workers answered
+//!
+//! Each outcome, success or failure, is appended to its own SQLite file so
+//! support can answer "I never got my tokens" and abuse reviews can see what
+//! an account received. The most recent mints are also kept in a ring buffer
+//! for `GET /recent_mints`. Rows older than the retention are pruned on the
+//! first mint of each day.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+pub const MINT_HISTORY_PATH: &str = "mint_history.sqlite3";
+/// Mints kept in memory for /recent_mints
+pub const RECENT_MINTS_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MintRecord {
+    /// Row id; pass as `before` to get the next page
+    pub id: i64,
+    /// Unix seconds
+    pub timestamp: u64,
+    /// Lowercase "0x…" account ID
+    pub account_id: String,
+    pub token: String,
+    pub amount: u64,
+    pub tx_id: Option<String>,
+    /// "success" or "error"
+    pub outcome: String,
+    pub error: Option<String>,
+}
+
+pub struct MintHistory {
+    conn: Mutex<Connection>,
+    recent: Mutex<VecDeque<MintRecord>>,
+    retention_secs: u64,
+    /// Day of the last prune
+    pruned_day: AtomicU64,
+}
+
+impl MintHistory {
+    /// Open the table and fill the ring buffer with its newest rows
+    pub fn open(path: &str, retention_days: u64) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS mint_history (
+                 id         INTEGER PRIMARY KEY AUTOINCREMENT,
+                 timestamp  INTEGER NOT NULL,
+                 account_id TEXT NOT NULL,
+                 token      TEXT NOT NULL,
+                 amount     INTEGER NOT NULL,
+                 tx_id      TEXT,
+                 outcome    TEXT NOT NULL,
+                 error      TEXT
+             );
+             CREATE INDEX IF NOT EXISTS idx_mint_history_account ON mint_history (account_id, id);",
+        )?;
+        let history = Self {
+            conn: Mutex::new(conn),
+            recent: Mutex::new(VecDeque::new()),
+            retention_secs: retention_days * 86400,
+            pruned_day: AtomicU64::new(0),
+        };
+        let mut newest = history.page(None, None, RECENT_MINTS_CAPACITY)?;
+        newest.reverse();
+        *history.recent.lock().unwrap() = newest.into();
+        Ok(history)
+    }
+
+    /// Append a mint outcome to the table and the ring buffer
+    pub fn record(
+        &self,
+        timestamp: u64,
+        account_id: &str,
+        token: &str,
+        amount: u64,
+        result: Result<&str, &str>,
+    ) -> rusqlite::Result<()> {
+        let day = timestamp / 86400;
+        if self.pruned_day.swap(day, Ordering::Relaxed) < day {
+            self.prune(timestamp)?;
+        }
+
+        let (tx_id, outcome, error) = match result {
+            Ok(tx_id) => (Some(tx_id.to_string()), "success", None),
+            Err(e) => (None, "error", Some(e.to_string())),
+        };
+        let id = {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO mint_history (timestamp, account_id, token, amount, tx_id, outcome, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![timestamp as i64, account_id, token, amount as i64, tx_id, outcome, error],
+            )?;
+            conn.last_insert_rowid()
+        };
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_MINTS_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(MintRecord {
+            id,
+            timestamp,
+            account_id: account_id.to_string(),
+            token: token.to_string(),
+            amount,
+            tx_id,
+            outcome: outcome.to_string(),
+            error,
+        });
+        Ok(())
+    }
+
+    /// Delete rows older than the retention; returns how many went
+    pub fn prune(&self, now: u64) -> rusqlite::Result<usize> {
+        let cutoff = now.saturating_sub(self.retention_secs);
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM mint_history WHERE timestamp < ?1", [cutoff as i64])
+    }
+
+    /// Up to `limit` mints, newest first, optionally for one account and
+    /// only those with an id below `before`
+    pub fn page(&self, account_id: Option<&str>, before: Option<i64>, limit: usize) -> rusqlite::Result<Vec<MintRecord>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, account_id, token, amount, tx_id, outcome, error FROM mint_history
+             WHERE (?1 IS NULL OR account_id = ?1) AND (?2 IS NULL OR id < ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![account_id, before, limit as i64], |row| {
+            Ok(MintRecord {
+                id: row.get(0)?,
+                timestamp: row.get::<_, i64>(1)? as u64,
+                account_id: row.get(2)?,
+                token: row.get(3)?,
+                amount: row.get::<_, i64>(4)? as u64,
+                tx_id: row.get(5)?,
+                outcome: row.get(6)?,
+                error: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// The last `limit` mints across all accounts, newest first
+    pub fn recent(&self, limit: usize) -> Vec<MintRecord> {
+        self.recent.lock().unwrap().iter().rev().take(limit).cloned().collect()
+    }
+}
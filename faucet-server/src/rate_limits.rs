@@ -0,0 +1,343 @@
+//! Persistent rolling-window faucet limits
+//!
+//! Every reserved claim is a timestamped grant in its own SQLite file, so a
+//! restart doesn't reset anyone's usage. A key's usage is the sum of its
+//! grants in the trailing 24 hours, so a cap can't be taken twice around a
+//! fixed reset time. A claim reserves its amount before the mint is sent to
+//! the worker; a mint that fails deletes its grant again.
+//!
+//! The latest grant of a key also spaces out claims by a minimum interval.
+//!
+//! Per-account totals use `account_id:token` keys and per-IP totals
+//! `ip:<addr>:token`. Grants that left the window are pruned at most once a
+//! minute, so fresh wallets and fresh IPs can't grow the table past one
+//! window's worth of claims.
+//!
+//! Stores from before the rolling window kept one `(day, total_amount)` row
+//! per key in `rate_limits`; today's totals become one grant each on first
+//! open and the old table is dropped.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+pub const RATE_LIMITS_PATH: &str = "rate_limits.sqlite3";
+/// Length of the rolling window a cap applies to
+pub const RATE_LIMIT_WINDOW_SECS: u64 = 86400;
+/// Minimum time between two prunes of expired grants
+const PRUNE_INTERVAL_SECS: u64 = 60;
+
+/// One limit a claim must fit under
+pub struct Limit<'a> {
+    pub key: &'a str,
+    /// Total over the window
+    pub max: u64,
+    /// Seconds required since the key's last grant (0 = no cooldown)
+    pub min_interval_secs: u64,
+}
+
+/// Why a reservation was refused
+pub enum ReserveError {
+    /// Over the window limit of `key`; what is available now, and when
+    /// (unix seconds) the oldest grant in the window expires
+    LimitReached { key: String, remaining: u64, reset_at: u64 },
+    /// `key` was granted less than its minimum interval ago
+    Cooldown { key: String, retry_after: u64 },
+    Store(rusqlite::Error),
+}
+
+/// Grants of one key within the window
+#[derive(Debug, Clone, Copy)]
+pub struct WindowUsage {
+    pub used: u64,
+    /// When the oldest grant in the window expires and frees its amount
+    /// (unix seconds); `now` when nothing is in the window
+    pub reset_at: u64,
+}
+
+pub struct RateLimitStore {
+    conn: Mutex<Connection>,
+    /// Unix seconds of the last prune; 0 until the first claim
+    pruned_at: AtomicU64,
+}
+
+impl RateLimitStore {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Store on an already open connection (tests use an in-memory one)
+    pub fn with_connection(conn: Connection) -> rusqlite::Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS rate_limit_grants (
+                 id     INTEGER PRIMARY KEY,
+                 key    TEXT NOT NULL,
+                 ts     INTEGER NOT NULL,
+                 amount INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_rate_limit_grants_key_ts ON rate_limit_grants (key, ts);
+             CREATE INDEX IF NOT EXISTS idx_rate_limit_grants_ts ON rate_limit_grants (ts);",
+        )?;
+        migrate_daily_totals(&conn)?;
+        Ok(Self { conn: Mutex::new(conn), pruned_at: AtomicU64::new(0) })
+    }
+
+    /// Drop grants that are outside the window at `now`; they no longer limit anything
+    pub fn prune(&self, now: u64) -> rusqlite::Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let pruned = conn.execute("DELETE FROM rate_limit_grants WHERE ts <= ?1", [window_start(now) as i64])?;
+        self.pruned_at.store(now, Ordering::Relaxed);
+        Ok(pruned)
+    }
+
+    pub fn count(&self) -> rusqlite::Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM rate_limit_grants", [], |row| row.get::<_, i64>(0))
+            .map(|n| n as u64)
+    }
+
+    /// Grant `amount` under every limit unless that exceeds any of them over
+    /// the window or one is still cooling down; either all are granted or
+    /// none is. Returns the grant ids, for [`RateLimitStore::release`].
+    pub fn reserve(&self, limits: &[Limit], now: u64, amount: u64) -> Result<Vec<i64>, ReserveError> {
+        if self.pruned_at.load(Ordering::Relaxed) + PRUNE_INTERVAL_SECS <= now {
+            if let Err(e) = self.prune(now) {
+                tracing::warn!(error = %e, "rate limit prune failed");
+            }
+        }
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(ReserveError::Store)?;
+        let mut grants = Vec::with_capacity(limits.len());
+        for limit in limits {
+            if limit.min_interval_secs > 0 {
+                if let Some(last) = last_grant(&tx, limit.key).map_err(ReserveError::Store)? {
+                    let next_allowed = last + limit.min_interval_secs;
+                    if now < next_allowed {
+                        return Err(ReserveError::Cooldown { key: limit.key.to_string(), retry_after: next_allowed - now });
+                    }
+                }
+            }
+
+            let usage = window_usage(&tx, limit.key, now).map_err(ReserveError::Store)?;
+            if usage.used + amount > limit.max {
+                return Err(ReserveError::LimitReached {
+                    key: limit.key.to_string(),
+                    remaining: limit.max.saturating_sub(usage.used),
+                    reset_at: usage.reset_at,
+                });
+            }
+
+            tx.execute(
+                "INSERT INTO rate_limit_grants (key, ts, amount) VALUES (?1, ?2, ?3)",
+                params![limit.key, now as i64, amount as i64],
+            )
+            .map_err(ReserveError::Store)?;
+            grants.push(tx.last_insert_rowid());
+        }
+        tx.commit().map_err(ReserveError::Store)?;
+        Ok(grants)
+    }
+
+    /// `key`'s usage over the window ending at `now`, as `reserve` counts it
+    pub fn usage(&self, key: &str, now: u64) -> rusqlite::Result<WindowUsage> {
+        let conn = self.conn.lock().unwrap();
+        window_usage(&conn, key, now)
+    }
+
+    /// Give back the grants of a claim whose mint failed. Grants that were
+    /// already pruned are simply gone.
+    pub fn release(&self, grants: &[i64]) {
+        let conn = self.conn.lock().unwrap();
+        for id in grants {
+            if let Err(e) = conn.execute("DELETE FROM rate_limit_grants WHERE id = ?1", [id]) {
+                tracing::warn!(grant = id, error = %e, "rate limit release failed");
+            }
+        }
+    }
+}
+
+/// Grants at or before this are outside the window ending at `now`: one made
+/// exactly 24 hours ago no longer counts
+fn window_start(now: u64) -> u64 {
+    now.saturating_sub(RATE_LIMIT_WINDOW_SECS)
+}
+
+fn window_usage(conn: &Connection, key: &str, now: u64) -> rusqlite::Result<WindowUsage> {
+    let (used, oldest): (i64, Option<i64>) = conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0), MIN(ts) FROM rate_limit_grants WHERE key = ?1 AND ts > ?2",
+        params![key, window_start(now) as i64],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(WindowUsage {
+        used: used as u64,
+        reset_at: oldest.map_or(now, |ts| ts as u64 + RATE_LIMIT_WINDOW_SECS),
+    })
+}
+
+fn last_grant(conn: &Connection, key: &str) -> rusqlite::Result<Option<u64>> {
+    conn.query_row("SELECT MAX(ts) FROM rate_limit_grants WHERE key = ?1", [key], |row| row.get::<_, Option<i64>>(0))
+        .map(|ts| ts.map(|ts| ts as u64))
+}
+
+/// Carry today's totals from the per-day table over as grants, so upgrading
+/// doesn't hand everyone a fresh cap. The day total is stamped with its last
+/// request time (start of the day for rows that predate that column).
+fn migrate_daily_totals(conn: &Connection) -> rusqlite::Result<()> {
+    let has_daily_table = conn
+        .query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'rate_limits'", [], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !has_daily_table {
+        return Ok(());
+    }
+    let has_last_request: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('rate_limits') WHERE name = 'last_request_ts'",
+        [],
+        |row| row.get(0),
+    )?;
+    let stamp = if has_last_request { "MAX(last_request_ts, day * 86400)" } else { "day * 86400" };
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    conn.execute_batch(&format!(
+        "BEGIN;
+         INSERT INTO rate_limit_grants (key, ts, amount)
+             SELECT key, {stamp}, total_amount FROM rate_limits WHERE day = {today} AND total_amount > 0;
+         DROP TABLE rate_limits;
+         COMMIT;",
+        today = now / 86400,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn limit(key: &str, max: u64) -> Limit<'_> {
+        Limit { key, max, min_interval_secs: 0 }
+    }
+
+    fn store() -> RateLimitStore {
+        RateLimitStore::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("milo_rate_limits_{}_{}.sqlite3", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn usage_survives_a_restart() {
+        let path = temp_path("restart");
+        {
+            let store = RateLimitStore::open(&path).unwrap();
+            assert!(store.reserve(&[limit("alice:MILO", 10), limit("ip:1.2.3.4:MILO", 30)], NOW, 6).is_ok());
+        }
+
+        let store = RateLimitStore::open(&path).unwrap();
+        assert_eq!(store.usage("alice:MILO", NOW + 60).unwrap().used, 6);
+        assert_eq!(store.usage("ip:1.2.3.4:MILO", NOW + 60).unwrap().used, 6);
+        assert!(matches!(
+            store.reserve(&[limit("alice:MILO", 10)], NOW + 60, 5),
+            Err(ReserveError::LimitReached { remaining: 4, .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn todays_daily_totals_are_migrated() {
+        let path = temp_path("migrate");
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let today = now / 86400;
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(&format!(
+                "CREATE TABLE rate_limits (key TEXT, day INTEGER, total_amount INTEGER, PRIMARY KEY (key, day));
+                 INSERT INTO rate_limits VALUES ('alice:MILO', {today}, 7), ('bob:MILO', {yesterday}, 9);",
+                today = today,
+                yesterday = today - 1,
+            ))
+            .unwrap();
+        }
+
+        let store = RateLimitStore::open(&path).unwrap();
+        assert_eq!(store.usage("alice:MILO", now).unwrap().used, 7);
+        assert_eq!(store.usage("bob:MILO", now).unwrap().used, 0);
+        assert_eq!(store.count().unwrap(), 1);
+        // The old table is gone, so a second open doesn't migrate twice
+        drop(store);
+        assert_eq!(RateLimitStore::open(&path).unwrap().count().unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn grant_exactly_a_window_old_no_longer_counts() {
+        let store = store();
+        assert!(store.reserve(&[limit("alice:MILO", 10)], NOW, 10).is_ok());
+
+        let last_second = NOW + RATE_LIMIT_WINDOW_SECS - 1;
+        assert_eq!(store.usage("alice:MILO", last_second).unwrap().used, 10);
+        assert!(matches!(
+            store.reserve(&[limit("alice:MILO", 10)], last_second, 1),
+            Err(ReserveError::LimitReached { remaining: 0, reset_at, .. }) if reset_at == NOW + RATE_LIMIT_WINDOW_SECS
+        ));
+
+        let edge = NOW + RATE_LIMIT_WINDOW_SECS;
+        let usage = store.usage("alice:MILO", edge).unwrap();
+        assert_eq!((usage.used, usage.reset_at), (0, edge));
+        assert!(store.reserve(&[limit("alice:MILO", 10)], edge, 10).is_ok());
+    }
+
+    #[test]
+    fn grants_straddling_the_window_edge() {
+        let store = store();
+        assert!(store.reserve(&[limit("alice:MILO", 10)], NOW, 4).is_ok());
+        assert!(store.reserve(&[limit("alice:MILO", 10)], NOW + 3600, 5).is_ok());
+
+        // Both still in the window; the older one frees up first
+        let usage = store.usage("alice:MILO", NOW + RATE_LIMIT_WINDOW_SECS - 1).unwrap();
+        assert_eq!((usage.used, usage.reset_at), (9, NOW + RATE_LIMIT_WINDOW_SECS));
+
+        // The first has left the window, the second hasn't
+        let edge = NOW + RATE_LIMIT_WINDOW_SECS;
+        let usage = store.usage("alice:MILO", edge).unwrap();
+        assert_eq!((usage.used, usage.reset_at), (5, NOW + 3600 + RATE_LIMIT_WINDOW_SECS));
+        assert!(matches!(
+            store.reserve(&[limit("alice:MILO", 10)], edge, 6),
+            Err(ReserveError::LimitReached { remaining: 5, .. })
+        ));
+        assert!(store.reserve(&[limit("alice:MILO", 10)], edge, 5).is_ok());
+    }
+
+    #[test]
+    fn released_reservation_frees_its_amount() {
+        let store = store();
+        let limits = [limit("alice:MILO", 10), limit("ip:1.2.3.4:MILO", 30)];
+        let Ok(grants) = store.reserve(&limits, NOW, 10) else {
+            panic!("first claim should fit");
+        };
+        assert_eq!(grants.len(), 2);
+        assert!(store.reserve(&limits, NOW + 1, 1).is_err());
+
+        // The mint failed
+        store.release(&grants);
+        assert_eq!(store.usage("alice:MILO", NOW + 1).unwrap().used, 0);
+        assert_eq!(store.usage("ip:1.2.3.4:MILO", NOW + 1).unwrap().used, 0);
+        assert!(store.reserve(&limits, NOW + 1, 10).is_ok());
+    }
+
+    #[test]
+    fn refused_reservation_grants_nothing() {
+        let store = store();
+        let limits = [limit("alice:MILO", 10), limit("ip:1.2.3.4:MILO", 3)];
+        assert!(matches!(
+            store.reserve(&limits, NOW, 5),
+            Err(ReserveError::LimitReached { ref key, .. }) if key == "ip:1.2.3.4:MILO"
+        ));
+        assert_eq!(store.usage("alice:MILO", NOW).unwrap().used, 0);
+        assert_eq!(store.count().unwrap(), 0);
+    }
+}
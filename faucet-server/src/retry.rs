@@ -0,0 +1,170 @@
+//! Retrying mint submissions that failed on the way to the node
+//!
+//! A timeout or dropped connection during `submit_new_transaction` used to
+//! fail the claim outright. Such errors are now retried after a fresh sync,
+//! with exponential backoff; errors from building, executing or signing the
+//! transaction would fail the same way again and are returned at once.
+
+use std::time::Duration;
+
+/// Retries after the first attempt before a transient error is returned
+pub const MINT_MAX_RETRIES: u32 = 3;
+pub const MINT_RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// Substrings (lowercase) of network and timeout failures in the client's
+/// error text. The client surfaces transport errors as nested gRPC and
+/// hyper errors, so the text is the most stable thing to match on.
+const TRANSIENT_MARKERS: &[&str] = &[
+    "timeout",
+    "timed out",
+    "deadline",
+    "connection",
+    "connect error",
+    "transport error",
+    "broken pipe",
+    "unavailable",
+    "dns error",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Network or timeout: worth another attempt
+    Transient,
+    /// Validation, execution, proving or signing: retrying won't help
+    Permanent,
+}
+
+/// Classify a submission error by its text
+pub fn classify(error: &str) -> ErrorClass {
+    let error = error.to_lowercase();
+    if TRANSIENT_MARKERS.iter().any(|marker| error.contains(marker)) {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Permanent
+    }
+}
+
+/// Wait before retry `retry` (1-based): `MINT_RETRY_BASE_DELAY_MS * 2^(retry-1)`
+pub fn backoff_delay(retry: u32) -> Duration {
+    let factor = 1u64.checked_shl(retry.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_millis(MINT_RETRY_BASE_DELAY_MS.saturating_mul(factor))
+}
+
+/// Run `attempt` against `client` until it succeeds, fails with a permanent
+/// error or `MINT_MAX_RETRIES` retries are used up. `before_retry` gets the
+/// retry number (1-based) and the error, and does the backoff and re-sync.
+/// The class of the last error is returned with it.
+pub async fn retry_transient<C, T>(
+    client: &mut C,
+    mut attempt: impl AsyncFnMut(&mut C) -> Result<T, String>,
+    mut before_retry: impl AsyncFnMut(&mut C, u32, &str),
+) -> Result<T, (String, ErrorClass)> {
+    let mut retry = 0;
+    loop {
+        let error = match attempt(client).await {
+            Ok(value) => {
+                if retry > 0 {
+                    tracing::info!(retries = retry, "mint succeeded after retry");
+                }
+                return Ok(value);
+            }
+            Err(e) => e,
+        };
+        let class = classify(&error);
+        if class == ErrorClass::Permanent || retry >= MINT_MAX_RETRIES {
+            return Err((error, class));
+        }
+        retry += 1;
+        before_retry(client, retry, &error).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_failures_are_transient() {
+        for error in [
+            "status: Unavailable, message: \"error trying to connect: tcp connect error\"",
+            "transport error: Connection reset by peer",
+            "request timed out",
+            "Deadline Exceeded",
+            "dns error: failed to lookup address information",
+            "Broken pipe (os error 32)",
+        ] {
+            assert_eq!(classify(error), ErrorClass::Transient, "{}", error);
+        }
+    }
+
+    #[test]
+    fn build_and_execution_failures_are_permanent() {
+        for error in [
+            "transaction executor error: failed to execute transaction kernel program",
+            "account nonce mismatch",
+            "insufficient balance in faucet",
+            "failed to sign transaction",
+            "",
+        ] {
+            assert_eq!(classify(error), ErrorClass::Permanent, "{}", error);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_and_saturates() {
+        assert_eq!(backoff_delay(1), Duration::from_millis(MINT_RETRY_BASE_DELAY_MS));
+        assert_eq!(backoff_delay(2), Duration::from_millis(MINT_RETRY_BASE_DELAY_MS * 2));
+        assert_eq!(backoff_delay(3), Duration::from_millis(MINT_RETRY_BASE_DELAY_MS * 4));
+        // retry 0 is treated like the first retry
+        assert_eq!(backoff_delay(0), backoff_delay(1));
+        assert_eq!(backoff_delay(200), Duration::from_millis(u64::MAX));
+    }
+
+    /// Stands in for the client: fails with the queued errors, then succeeds
+    #[derive(Default)]
+    struct FakeClient {
+        failures: Vec<&'static str>,
+        attempts: u32,
+        syncs: Vec<u32>,
+    }
+
+    async fn submit(client: &mut FakeClient) -> Result<&'static str, String> {
+        client.attempts += 1;
+        if client.failures.is_empty() {
+            Ok("0xtx")
+        } else {
+            Err(client.failures.remove(0).to_string())
+        }
+    }
+
+    async fn run(client: &mut FakeClient) -> Result<&'static str, (String, ErrorClass)> {
+        retry_transient(client, async |c| submit(c).await, async |c, retry, _| c.syncs.push(retry)).await
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_after_a_sync() {
+        let mut client = FakeClient { failures: vec!["request timed out", "connection reset"], ..Default::default() };
+        assert_eq!(run(&mut client).await, Ok("0xtx"));
+        assert_eq!(client.attempts, 3);
+        assert_eq!(client.syncs, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn permanent_error_is_returned_at_once() {
+        let mut client = FakeClient { failures: vec!["failed to sign transaction"], ..Default::default() };
+        assert_eq!(
+            run(&mut client).await,
+            Err(("failed to sign transaction".to_string(), ErrorClass::Permanent))
+        );
+        assert_eq!(client.attempts, 1);
+        assert!(client.syncs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let mut client = FakeClient { failures: vec!["transport error"; MINT_MAX_RETRIES as usize + 2], ..Default::default() };
+        assert_eq!(run(&mut client).await, Err(("transport error".to_string(), ErrorClass::Transient)));
+        assert_eq!(client.attempts, MINT_MAX_RETRIES + 1);
+        assert_eq!(client.syncs, (1..=MINT_MAX_RETRIES).collect::<Vec<_>>());
+    }
+}
@@ -2,6 +2,8 @@ type PowChallenge = {
   challenge: string;
   target: number;
   timestamp: number;
+  // Set when the faucet flags the request; the nonce is then verified
+  difficulty_bits?: number;
 };
 
 type FaucetTokensResponse = {
@@ -65,10 +67,19 @@ export const requestPow = async (apiUrl: string, accountId: string, amount: stri
   return (await response.json()) as PowChallenge;
 };
 
-export const solvePow = async (challengeHex: string, target: number) => {
-  // PoW disabled for testnet - return 0 as dummy nonce
-  console.log(`⚙️ PoW disabled - using dummy nonce`);
-  return 0;
+export const solvePow = async (challengeHex: string, target: number, difficultyBits?: number) => {
+  if (difficultyBits === undefined) {
+    // Servers that predate mandatory PoW only sent difficulty_bits for flagged requests
+    console.log(`⚙️ PoW disabled - using dummy nonce`);
+    return 0;
+  }
+  console.log(`⚙️ Solving PoW (${difficultyBits} bits)...`);
+  const challengeBytes = hexToBytes(challengeHex);
+  let nonce = 0;
+  while (!(await hashMeetsTarget(challengeBytes, nonce, target))) {
+    nonce += 1;
+  }
+  return nonce;
 };
 
 export const requestTokens = async (params: {
@@ -82,7 +93,7 @@ export const requestTokens = async (params: {
   console.log(`📡 Requesting PoW challenge from ${params.apiUrl}/pow...`);
   const challenge = await requestPow(params.apiUrl, params.accountId, params.amount, params.tokenSymbol);
   console.log(`✅ PoW challenge received:`, challenge);
-  const nonce = await solvePow(challenge.challenge, challenge.target);
+  const nonce = await solvePow(challenge.challenge, challenge.target, challenge.difficulty_bits);
   const assetAmountStr = params.amount.toString();
   const assetAmountParsed = parseInt(assetAmountStr, 10);
   console.log(`📤 Faucet request: amount string="${assetAmountStr}", parsed=${assetAmountParsed}, type=${typeof assetAmountParsed}`);
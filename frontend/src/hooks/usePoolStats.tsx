@@ -29,6 +29,7 @@ export const recordTrade = async (
   amountIn: bigint,
   amountOut: bigint,
   feeAmount: bigint,
+  userId?: string | null,
   decimals: number = 8
 ): Promise<boolean> => {
   try {
@@ -41,6 +42,7 @@ export const recordTrade = async (
         amount_in: Number(amountIn) / divisor,
         amount_out: Number(amountOut) / divisor,
         fee_amount: Number(feeAmount) / divisor,
+        user_id: userId ?? undefined,
       }),
     });
 
@@ -746,7 +746,7 @@ export default function Trade({ client, accountId, account, poolReserves, userBa
       // Record trade for APY calculation (0.1% fee)
       const feeAmount = amountRaw / BigInt(1000); // 0.1% fee
       if (selectedPool?.poolAccountIdHex) {
-        recordTrade(selectedPool.poolAccountIdHex, amountRaw, estimatedOut, feeAmount);
+        recordTrade(selectedPool.poolAccountIdHex, amountRaw, estimatedOut, feeAmount, accountId);
       }
 
       toast.success('✅ Swap submitted! Auto-consuming tokens...');
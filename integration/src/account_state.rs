@@ -0,0 +1,128 @@
+//! Local vs on-chain account state
+//!
+//! A transaction the client submitted but the node never committed leaves
+//! the local copy of the account ahead of the chain: its nonce is higher and
+//! its commitment differs. The faucet's `/admin/tx_diagnostics` and
+//! `milo account-state` both compare the two through [`compare_account_state`].
+
+use miden_client::{
+    account::AccountId,
+    rpc::{GrpcClient, NodeRpcClient},
+};
+use serde::Serialize;
+
+use crate::cli::MidenClient;
+
+/// One account's nonce and commitment (hex)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub nonce: u64,
+    pub commitment: String,
+}
+
+/// Local vs on-chain view of one account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountStateComparison {
+    pub account_id: String,
+    pub local_nonce: Option<u64>,
+    pub on_chain_nonce: Option<u64>,
+    pub local_commitment: Option<String>,
+    pub on_chain_commitment: Option<String>,
+    pub in_sync: bool,
+    /// The local nonce is ahead of the chain: a transaction was applied
+    /// locally but never committed
+    pub local_ahead: bool,
+}
+
+impl AccountStateComparison {
+    /// Compare two snapshots. Private accounts have an on-chain commitment
+    /// but no nonce, so `on_chain_nonce` is given separately.
+    pub fn new(
+        account_id: AccountId,
+        local: Option<AccountSnapshot>,
+        on_chain_nonce: Option<u64>,
+        on_chain_commitment: Option<String>,
+    ) -> Self {
+        let local_nonce = local.as_ref().map(|s| s.nonce);
+        let local_commitment = local.map(|s| s.commitment);
+        Self {
+            account_id: account_id.to_hex(),
+            in_sync: local_commitment.is_some() && local_commitment == on_chain_commitment,
+            local_ahead: matches!((local_nonce, on_chain_nonce), (Some(local), Some(chain)) if local > chain),
+            local_nonce,
+            on_chain_nonce,
+            local_commitment,
+            on_chain_commitment,
+        }
+    }
+}
+
+/// Compare the client's local copy of `account_id` with a fresh fetch from the node
+pub async fn compare_account_state(
+    client: &MidenClient,
+    rpc_api: &GrpcClient,
+    account_id: AccountId,
+) -> Result<AccountStateComparison, String> {
+    let local = client
+        .get_account(account_id)
+        .await
+        .map_err(|e| format!("local account: {}", e))?;
+    let on_chain = rpc_api
+        .get_account_details(account_id)
+        .await
+        .map_err(|e| format!("on-chain account: {}", e))?;
+
+    let local = local.map(|r| AccountSnapshot {
+        nonce: r.account().nonce().as_int(),
+        commitment: r.account().commitment().to_hex(),
+    });
+    Ok(AccountStateComparison::new(
+        account_id,
+        local,
+        on_chain.account().map(|a| a.nonce().as_int()),
+        Some(on_chain.commitment().to_hex()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account() -> AccountId {
+        AccountId::from_hex(milo_registry::MILO_FAUCET_ID_HEX).unwrap()
+    }
+
+    fn snapshot(nonce: u64, commitment: &str) -> Option<AccountSnapshot> {
+        Some(AccountSnapshot { nonce, commitment: commitment.to_string() })
+    }
+
+    #[test]
+    fn matching_commitments_are_in_sync() {
+        let cmp = AccountStateComparison::new(account(), snapshot(3, "0xaa"), Some(3), Some("0xaa".to_string()));
+        assert!(cmp.in_sync);
+        assert!(!cmp.local_ahead);
+        assert_eq!(cmp.account_id, account().to_hex());
+    }
+
+    #[test]
+    fn uncommitted_transaction_leaves_local_ahead() {
+        let cmp = AccountStateComparison::new(account(), snapshot(4, "0xbb"), Some(3), Some("0xaa".to_string()));
+        assert!(!cmp.in_sync);
+        assert!(cmp.local_ahead);
+    }
+
+    #[test]
+    fn account_missing_locally_is_not_in_sync() {
+        let cmp = AccountStateComparison::new(account(), None, Some(3), Some("0xaa".to_string()));
+        assert!(!cmp.in_sync);
+        assert!(!cmp.local_ahead);
+        assert_eq!(cmp.local_nonce, None);
+    }
+
+    #[test]
+    fn private_account_compares_commitments_only() {
+        let cmp = AccountStateComparison::new(account(), snapshot(5, "0xaa"), None, Some("0xaa".to_string()));
+        assert!(cmp.in_sync);
+        assert!(!cmp.local_ahead);
+    }
+}
@@ -0,0 +1,364 @@
+//! Account-scoped API keys for bots calling the daemons
+//!
+//! Keys are created and revoked by an admin, bound to one user account and a
+//! permission set, and stored hashed (SHA-256) in `api_keys.json`. A request
+//! carrying `X-Api-Key` acts on behalf of the bound account; requests without
+//! the header keep the existing unauthenticated behaviour.
+//!
+//! Key format: `milo_<key_id>_<secret>`. The key id is not secret — it is what
+//! appears in logs, rate-limit buckets and the audit log (`api_key_audit.jsonl`).
+//!
+//! Both daemons read the same file; the store reloads it whenever it changes
+//! on disk, so a revocation applies to the next request on either daemon.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+pub const DEFAULT_API_KEYS_FILE: &str = "api_keys.json";
+pub const DEFAULT_AUDIT_FILE: &str = "api_key_audit.jsonl";
+
+const KEY_PREFIX: &str = "milo_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// Query the bound account's own data
+    Read,
+    /// Track notes, consume, place and cancel limit orders
+    Trade,
+    /// Withdraw liquidity
+    Withdraw,
+}
+
+impl Permission {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Trade => "trade",
+            Permission::Withdraw => "withdraw",
+        }
+    }
+}
+
+/// Stored key; the secret itself is never kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    pub key_hash: String,
+    pub account_id: String,
+    pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub label: String,
+    pub created_at: u64,
+    #[serde(default)]
+    pub revoked_at: Option<u64>,
+}
+
+/// Who an authenticated request acts as
+#[derive(Debug, Clone)]
+pub struct ApiKeyIdentity {
+    pub key_id: String,
+    pub account_id: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Default)]
+struct Loaded {
+    records: BTreeMap<String, ApiKeyRecord>,
+    modified: Option<SystemTime>,
+}
+
+pub struct ApiKeyStore {
+    path: PathBuf,
+    audit_path: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn random_hex(bytes: usize) -> String {
+    (0..bytes).map(|_| format!("{:02x}", rand::random::<u8>())).collect()
+}
+
+/// Key id embedded in a presented key, without checking the secret
+pub fn key_id_from_token(token: &str) -> Option<&str> {
+    let rest = token.strip_prefix(KEY_PREFIX)?;
+    let (key_id, secret) = rest.split_once('_')?;
+    (!key_id.is_empty() && !secret.is_empty()).then_some(key_id)
+}
+
+impl ApiKeyStore {
+    pub fn open(path: impl Into<PathBuf>, audit_path: impl Into<PathBuf>) -> Self {
+        let store = Self {
+            path: path.into(),
+            audit_path: audit_path.into(),
+            loaded: Mutex::new(Loaded::default()),
+        };
+        store.reload_if_changed(&mut store.loaded.lock().unwrap());
+        store
+    }
+
+    /// Store at the default paths in the working directory
+    pub fn open_default() -> Self {
+        Self::open(DEFAULT_API_KEYS_FILE, DEFAULT_AUDIT_FILE)
+    }
+
+    fn reload_if_changed(&self, loaded: &mut Loaded) {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified == loaded.modified {
+            return;
+        }
+        loaded.records = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        loaded.modified = modified;
+    }
+
+    fn save(&self, loaded: &mut Loaded) -> Result<()> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&loaded.records)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+        loaded.modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        Ok(())
+    }
+
+    /// Create a key; the returned plaintext key is shown once and never stored
+    pub fn create(&self, account_id: &str, permissions: Vec<Permission>, label: &str) -> Result<(ApiKeyRecord, String)> {
+        if permissions.is_empty() {
+            anyhow::bail!("At least one permission is required");
+        }
+        let mut loaded = self.loaded.lock().unwrap();
+        self.reload_if_changed(&mut loaded);
+
+        let key_id = random_hex(8);
+        let key = format!("{}{}_{}", KEY_PREFIX, key_id, random_hex(32));
+        let mut permissions = permissions;
+        permissions.sort();
+        permissions.dedup();
+
+        let record = ApiKeyRecord {
+            key_id: key_id.clone(),
+            key_hash: hash_key(&key),
+            account_id: account_id.to_lowercase(),
+            permissions,
+            label: label.to_string(),
+            created_at: now_secs(),
+            revoked_at: None,
+        };
+        loaded.records.insert(key_id, record.clone());
+        self.save(&mut loaded)?;
+        Ok((record, key))
+    }
+
+    /// Revoke a key; returns false if it doesn't exist or was already revoked
+    pub fn revoke(&self, key_id: &str) -> Result<bool> {
+        let mut loaded = self.loaded.lock().unwrap();
+        self.reload_if_changed(&mut loaded);
+        match loaded.records.get_mut(key_id) {
+            Some(record) if record.revoked_at.is_none() => {
+                record.revoked_at = Some(now_secs());
+                self.save(&mut loaded)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub fn list(&self) -> Vec<ApiKeyRecord> {
+        let mut loaded = self.loaded.lock().unwrap();
+        self.reload_if_changed(&mut loaded);
+        loaded.records.values().cloned().collect()
+    }
+
+    /// Resolve a presented key, or `None` if unknown or revoked
+    pub fn authenticate(&self, token: &str) -> Option<ApiKeyIdentity> {
+        let key_id = key_id_from_token(token)?;
+        let mut loaded = self.loaded.lock().unwrap();
+        self.reload_if_changed(&mut loaded);
+        let record = loaded.records.get(key_id)?;
+        if record.revoked_at.is_some() || record.key_hash != hash_key(token) {
+            return None;
+        }
+        Some(ApiKeyIdentity {
+            key_id: record.key_id.clone(),
+            account_id: record.account_id.clone(),
+            permissions: record.permissions.clone(),
+        })
+    }
+
+    /// Append an entry to the audit log (and print it)
+    pub fn audit(&self, identity: &ApiKeyIdentity, action: &str, detail: serde_json::Value) {
+        println!("🔑 [key {}] {} for {}", identity.key_id, action, identity.account_id);
+        let line = serde_json::json!({
+            "timestamp": now_secs(),
+            "key_id": identity.key_id,
+            "account_id": identity.account_id,
+            "action": action,
+            "detail": detail,
+        });
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.audit_path)
+            .and_then(|mut f| writeln!(f, "{}", line));
+        if let Err(e) = written {
+            println!("⚠️  Failed to write audit log: {}", e);
+        }
+    }
+
+    /// Check the `X-Api-Key` header, if any. `Ok(None)` means no key was
+    /// sent (the unauthenticated frontend path); a bad key or a missing
+    /// permission is an error response.
+    pub fn authorize(
+        &self,
+        headers: &HeaderMap,
+        permission: Permission,
+    ) -> Result<Option<ApiKeyIdentity>, (StatusCode, Json<serde_json::Value>)> {
+        let Some(token) = headers.get(API_KEY_HEADER) else {
+            return Ok(None);
+        };
+        let identity = token
+            .to_str()
+            .ok()
+            .and_then(|t| self.authenticate(t.trim()))
+            .ok_or_else(|| {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(serde_json::json!({ "error": "Invalid or revoked API key" })),
+                )
+            })?;
+        if !identity.permissions.contains(&permission) {
+            println!("🔑 [key {}] denied: missing {} permission", identity.key_id, permission.as_str());
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({
+                    "error": format!("API key lacks the `{}` permission", permission.as_str())
+                })),
+            ));
+        }
+        Ok(Some(identity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    const ACCOUNT: &str = "0x6B10BD738877EA101DB7175839E152";
+
+    fn store(name: &str) -> (ApiKeyStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("milo_api_keys_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        (ApiKeyStore::open(dir.join("api_keys.json"), dir.join("audit.jsonl")), dir)
+    }
+
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        headers
+    }
+
+    #[test]
+    fn hash_is_lowercase_sha256_hex() {
+        assert_eq!(hash_key("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn key_id_is_read_from_the_token() {
+        assert_eq!(key_id_from_token("milo_0011aabb_secret"), Some("0011aabb"));
+        assert_eq!(key_id_from_token("milo__secret"), None);
+        assert_eq!(key_id_from_token("milo_0011aabb_"), None);
+        assert_eq!(key_id_from_token("milo_0011aabb"), None);
+        assert_eq!(key_id_from_token("other_0011aabb_secret"), None);
+    }
+
+    #[test]
+    fn created_key_is_stored_hashed_and_authenticates() {
+        let (store, dir) = store("create");
+        let (record, key) = store.create(ACCOUNT, vec![Permission::Trade, Permission::Read, Permission::Trade], "bot").unwrap();
+        assert_eq!(record.permissions, vec![Permission::Read, Permission::Trade]);
+        assert_eq!(record.account_id, ACCOUNT.to_lowercase());
+        assert_eq!(key_id_from_token(&key), Some(record.key_id.as_str()));
+
+        let on_disk = fs::read_to_string(dir.join("api_keys.json")).unwrap();
+        assert!(on_disk.contains(&hash_key(&key)));
+        assert!(!on_disk.contains(&key));
+
+        let identity = store.authenticate(&key).unwrap();
+        assert_eq!((identity.key_id, identity.account_id), (record.key_id, ACCOUNT.to_lowercase()));
+        assert!(store.create(ACCOUNT, Vec::new(), "none").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wrong_secret_and_revoked_keys_are_rejected() {
+        let (store, dir) = store("revoke");
+        let (record, key) = store.create(ACCOUNT, vec![Permission::Read], "").unwrap();
+        let forged = format!("milo_{}_{}", record.key_id, "00".repeat(32));
+        assert!(store.authenticate(&forged).is_none());
+
+        assert!(store.revoke(&record.key_id).unwrap());
+        assert!(!store.revoke(&record.key_id).unwrap());
+        assert!(!store.revoke("unknown").unwrap());
+        assert!(store.authenticate(&key).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn authorize_checks_the_permission() {
+        let (store, dir) = store("authorize");
+        let (_, key) = store.create(ACCOUNT, vec![Permission::Read, Permission::Trade], "").unwrap();
+
+        assert!(store.authorize(&HeaderMap::new(), Permission::Withdraw).unwrap().is_none());
+        assert!(store.authorize(&headers(&key), Permission::Trade).unwrap().is_some());
+        let (status, _) = store.authorize(&headers(&key), Permission::Withdraw).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = store.authorize(&headers("milo_nope_nope"), Permission::Read).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn revocation_by_another_daemon_is_picked_up() {
+        let (store, dir) = store("reload");
+        let (record, key) = store.create(ACCOUNT, vec![Permission::Trade], "").unwrap();
+        assert!(store.authenticate(&key).is_some());
+
+        // Let the file's mtime move on before the other daemon writes it
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let other = ApiKeyStore::open(dir.join("api_keys.json"), dir.join("audit.jsonl"));
+        assert!(other.revoke(&record.key_id).unwrap());
+
+        assert!(store.authenticate(&key).is_none());
+        assert!(store.list()[0].revoked_at.is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
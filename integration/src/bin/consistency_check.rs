@@ -0,0 +1,194 @@
+//! Cross-service consistency check
+//! Faucet, swap ve likidite kayıtlarını zincirdeki bakiyelerle karşılaştırır
+//!
+//! Every service appends the token movements it causes in user wallets to a
+//! ledger (see `integration::ledger`): faucet mints, both legs of each swap,
+//! liquidity deposits, refunds and withdrawals. The ledgers are summed into
+//! the net amount of each token every account should hold, which is compared
+//! with the account's vault plus its unconsumed notes. Differences above the
+//! tolerance are reported with the ledger entries behind them.
+//!
+//! Tokens that moved outside the services (wallet-to-wallet transfers,
+//! balances from before the ledgers existed) and swaps or deposits still in
+//! flight also show up as differences, so read the drill-down before acting.
+//!
+//! Usage: cargo run -p integration --bin consistency_check --release -- [--account <hex>] [--json <path>]
+//! Ledgers: MILO_FAUCET_LEDGER, MILO_SWAP_LEDGER, MILO_LIQUIDITY_LEDGER
+//! Tolerance: MILO_CONSISTENCY_TOLERANCE_BPS (default 50 = 0.5%)
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use integration::cli::{load_milo_config, MidenClient};
+use integration::ledger::{self, Holdings, LedgerEntry};
+use miden_client::{
+    account::AccountId,
+    asset::Asset,
+    builder::ClientBuilder,
+    keystore::FilesystemKeyStore,
+    rpc::GrpcClient,
+};
+use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// Own store, so imported user accounts don't end up in the CLI's store
+const CHECK_STORE_PATH: &str = "consistency_store.sqlite3";
+// Defaults assume the repo root, where the daemons run
+const DEFAULT_FAUCET_LEDGER: &str = "faucet-server/faucet_ledger.jsonl";
+const DEFAULT_SWAP_LEDGER: &str = "swap_ledger.jsonl";
+const DEFAULT_LIQUIDITY_LEDGER: &str = "liquidity_ledger.jsonl";
+const DEFAULT_TOLERANCE_BPS: u64 = 50;
+
+#[derive(Parser, Debug)]
+#[command(about = "Compare service ledgers with on-chain balances")]
+struct Args {
+    /// Only check this account (hex); defaults to every account in the ledgers
+    #[arg(long)]
+    account: Option<String>,
+
+    /// Where to write the JSON report
+    #[arg(long, default_value = "consistency_report.json")]
+    json: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    println!("🧾 Servisler Arası Tutarlılık Kontrolü\n");
+
+    let args = Args::parse();
+    let tolerance_bps = std::env::var("MILO_CONSISTENCY_TOLERANCE_BPS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_TOLERANCE_BPS);
+
+    let mut entries: Vec<LedgerEntry> = Vec::new();
+    for (env, default) in [
+        ("MILO_FAUCET_LEDGER", DEFAULT_FAUCET_LEDGER),
+        ("MILO_SWAP_LEDGER", DEFAULT_SWAP_LEDGER),
+        ("MILO_LIQUIDITY_LEDGER", DEFAULT_LIQUIDITY_LEDGER),
+    ] {
+        let path = std::env::var(env).unwrap_or_else(|_| default.to_string());
+        let (loaded, skipped) = ledger::load_entries(&path)?;
+        println!("📄 {}: {} kayıt{}", path, loaded.len(),
+            if skipped > 0 { format!(" ({} bozuk satır atlandı)", skipped) } else { String::new() });
+        entries.extend(loaded);
+    }
+
+    if let Some(ref account) = args.account {
+        let account = AccountId::from_hex(account).context("Geçersiz account ID")?.to_hex();
+        entries.retain(|e| e.account_id == account);
+    }
+    println!("   Tolerans: {} bps\n", tolerance_bps);
+
+    let expected = ledger::expected_holdings(&entries);
+    let mut accounts: BTreeSet<String> = expected.keys().map(|(account, _)| account.clone()).collect();
+    if let Some(ref account) = args.account {
+        accounts.insert(AccountId::from_hex(account)?.to_hex());
+    }
+
+    let mut client = init_check_client().await?;
+    println!("🔄 Sync yapılıyor...");
+    client.sync_state().await?;
+    println!("   ✅ Sync tamamlandı\n");
+
+    let mut actual = Holdings::new();
+    let mut unreadable = Vec::new();
+    for account in &accounts {
+        match account_holdings(&mut client, account).await {
+            Ok(holdings) => actual.extend(holdings),
+            Err(e) => {
+                println!("⚠️  {} okunamadı: {:#}", account, e);
+                unreadable.push(serde_json::json!({ "account_id": account, "error": format!("{:#}", e) }));
+            }
+        }
+    }
+
+    // Unreadable accounts would otherwise show their whole ledger as missing
+    let unreadable_ids: Vec<&str> = unreadable.iter().filter_map(|u| u["account_id"].as_str()).collect();
+    let expected: Holdings = expected
+        .into_iter()
+        .filter(|((account, _), _)| !unreadable_ids.contains(&account.as_str()))
+        .collect();
+
+    let discrepancies = ledger::find_discrepancies(&entries, &expected, &actual, tolerance_bps);
+
+    println!("{:<32} {:<32} {:>16} {:>16} {:>16}", "Account", "Token", "Beklenen", "Zincirde", "Fark");
+    for d in &discrepancies {
+        println!("{:<32} {:<32} {:>16} {:>16} {:>+16}", d.account_id, d.faucet_id, d.expected, d.actual, d.difference);
+        for e in &d.entries {
+            let sign = if e.signed_amount() < 0 { "-" } else { "+" };
+            println!("      {} {:<8} {:<16} {}{} ({})", e.timestamp, e.service, e.kind, sign, e.amount, e.reference);
+        }
+    }
+    if discrepancies.is_empty() {
+        println!("(yok)");
+    }
+
+    let report = serde_json::json!({
+        "account": args.account,
+        "tolerance_bps": tolerance_bps,
+        "accounts_checked": accounts.len() - unreadable.len(),
+        "ledger_entries": entries.len(),
+        "unreadable_accounts": unreadable,
+        "discrepancies": discrepancies,
+    });
+    std::fs::write(&args.json, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("{} yazılamadı", args.json))?;
+    println!("\n💾 JSON rapor: {}", args.json);
+
+    if !discrepancies.is_empty() {
+        return Err(anyhow::anyhow!("{} tutarsızlık bulundu", discrepancies.len()));
+    }
+
+    println!("🎉 Kayıtlar zincirle tutarlı!");
+    Ok(())
+}
+
+/// Client on a separate store; it only reads, never submits
+async fn init_check_client() -> Result<MidenClient> {
+    let config = load_milo_config()?;
+    let rpc_api = Arc::new(GrpcClient::new(&config.endpoint()?, 30_000));
+    let keystore = FilesystemKeyStore::new(PathBuf::from(&config.keystore_path))
+        .context("Keystore açılamadı")?;
+
+    ClientBuilder::new()
+        .rpc(rpc_api)
+        .authenticator(Arc::new(keystore))
+        .sqlite_store(CHECK_STORE_PATH.into())
+        .build()
+        .await
+        .context("Client oluşturulamadı")
+}
+
+/// Vault balances plus unconsumed notes addressed to the account
+async fn account_holdings(client: &mut MidenClient, account_hex: &str) -> Result<Holdings> {
+    let account_id = AccountId::from_hex(account_hex)?;
+    if client.get_account(account_id).await?.is_none() {
+        // Private accounts can't be imported by ID
+        client.import_account_by_id(account_id).await.context("Account import edilemedi")?;
+        client.sync_state().await?;
+    }
+    let record = client
+        .get_account(account_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Account bulunamadı"))?;
+
+    let mut holdings = Holdings::new();
+    let mut add = |asset: &Asset| {
+        if let Asset::Fungible(fungible) = asset {
+            *holdings
+                .entry((account_hex.to_string(), fungible.faucet_id().to_hex()))
+                .or_insert(0) += fungible.amount() as i128;
+        }
+    };
+    for asset in record.account().vault().assets() {
+        add(&asset);
+    }
+    for (note, _) in client.get_consumable_notes(Some(account_id)).await? {
+        for asset in note.assets().iter() {
+            add(asset);
+        }
+    }
+    Ok(holdings)
+}
@@ -0,0 +1,64 @@
+//! Milo Swap CLI
+//! Kurulum, mint, likidite ve pool kontrol komutları tek binary'de
+//!
+//! Usage: cargo run --bin milo --release -- <COMMAND>
+//!
+//! Örnek:
+//!     cargo run --bin milo --release -- setup
+//!     cargo run --bin milo --release -- mint MILO 100 0x1234567890abcdef
+//!     cargo run --bin milo --release -- reserves --json
+//!     cargo run --bin milo --release -- add-liquidity --curve stable --amp 100 --pair melo_musdc
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use integration::cli::{
+    self, account_state::AccountStateArgs, add_liquidity::AddLiquidityArgs, mint::MintArgs, reserves::ReservesArgs,
+    swap::SwapArgs,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "milo", version, about = "Milo Swap CLI")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create the user wallet and MILO, MELO, MUSDC faucets
+    Setup,
+    /// Create the pools if needed and seed them with liquidity
+    AddLiquidity(AddLiquidityArgs),
+    /// Mint tokens from a deployed faucet
+    Mint(MintArgs),
+    /// Check a wallet can fund a swap on a pool
+    Swap(SwapArgs),
+    /// Consume pending P2ID notes on every pool
+    Consume,
+    /// Show pool accounts and their reserves
+    Reserves(ReservesArgs),
+    /// Check that faucet and pool accounts exist
+    Accounts,
+    /// Compare an account's local state with the chain (stuck transactions)
+    AccountState(AccountStateArgs),
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let command = Cli::parse().command;
+    // Symbols in pools.json resolve to the faucets of the last setup. Setup
+    // resolves none and rewrites accounts.json, so an old one can't stop it.
+    if !matches!(command, Command::Setup) {
+        cli::install_token_registry()?;
+    }
+    match command {
+        Command::Setup => cli::setup::run().await,
+        Command::AddLiquidity(args) => cli::add_liquidity::run(args).await,
+        Command::Mint(args) => cli::mint::run(args).await,
+        Command::Swap(args) => cli::swap::run(args).await,
+        Command::Consume => cli::consume::run().await,
+        Command::Reserves(args) => cli::reserves::run(args).await,
+        Command::Accounts => cli::accounts::run().await,
+        Command::AccountState(args) => cli::account_state::run(args).await,
+    }
+}
@@ -0,0 +1,57 @@
+//! Validate pools.json files in place
+//! pools.json ve pool-daemon/pools.json dosyalarını doğrular
+//!
+//! Usage: cargo run --bin validate_config [path...]
+
+use anyhow::Result;
+use integration::pool_config::{load_pools_config, PoolsConfig};
+
+const DEFAULT_PATHS: [&str; 2] = ["pools.json", "pool-daemon/pools.json"];
+
+fn main() -> Result<()> {
+    println!("🔍 Config Doğrulama\n");
+    integration::cli::install_token_registry()?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let paths: Vec<String> = if args.is_empty() {
+        DEFAULT_PATHS.iter().map(|p| p.to_string()).collect()
+    } else {
+        args
+    };
+
+    let mut loaded: Vec<(String, PoolsConfig)> = Vec::new();
+    let mut failed = 0;
+
+    for path in &paths {
+        match load_pools_config(path) {
+            Ok(config) => {
+                println!("✅ {} geçerli ({} pool)", path, config.pools.len());
+                for entry in &config.pools {
+                    println!("   - {}: {} ({})", entry.pair, entry.pool_id.to_hex(), entry.address);
+                }
+                loaded.push((path.clone(), config));
+            }
+            Err(e) => {
+                println!("❌ {}: {:#}", path, e);
+                failed += 1;
+            }
+        }
+    }
+
+    // Copies written by the same tool must agree
+    if let Some((first_path, first)) = loaded.first() {
+        for (path, config) in loaded.iter().skip(1) {
+            if config != first {
+                println!("❌ {} ile {} farklı pool'lar içeriyor", path, first_path);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} doğrulama hatası", failed));
+    }
+
+    println!("\n🎉 Tüm config dosyaları geçerli!");
+    Ok(())
+}
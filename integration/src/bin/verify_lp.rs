@@ -0,0 +1,149 @@
+//! Verify LP accounting against on-chain reserves
+//! Kayıtlı LP miktarlarını pool rezervleriyle karşılaştırır
+//!
+//! LP is tracked off-chain by the liquidity daemon (the `user_deposits` table
+//! in its SQLite store):
+//! each credited deposit adds its amount, and a withdrawal of `lp` pays
+//! `lp * reserve_x / (reserve_a + reserve_b)` of each token. Redeeming every
+//! position therefore pays out `total_lp` in total, so `total_lp` must not
+//! exceed `reserve_a + reserve_b`. Anything above that (beyond the tolerance)
+//! means LP was over-credited and the last withdrawers can't be paid.
+//!
+//! Usage: cargo run --bin verify_lp --release [liquidity_store.sqlite3]
+//! Tolerance: MILO_LP_TOLERANCE_BPS (default 50 = 0.5%)
+
+use anyhow::{Context, Result};
+use integration::cli::{init_client, install_token_registry, MidenClient, POOLS_CONFIG_PATH};
+use integration::pool_config::load_pools_config;
+use miden_client::{account::AccountId, asset::Asset};
+
+const DEFAULT_LIQUIDITY_STORE_PATH: &str = "liquidity_store.sqlite3";
+const DEFAULT_TOLERANCE_BPS: u64 = 50;
+
+/// The columns of the daemon's user_deposits table this check needs
+#[derive(Debug)]
+struct LpPosition {
+    user_account_id: String,
+    pool_account_id: String,
+    total_deposited: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    println!("🧮 LP Doğrulama\n");
+    install_token_registry()?;
+
+    let store_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_LIQUIDITY_STORE_PATH.to_string());
+    let tolerance_bps = std::env::var("MILO_LP_TOLERANCE_BPS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_TOLERANCE_BPS);
+
+    let config = load_pools_config(POOLS_CONFIG_PATH)?;
+    let positions = load_positions(&store_path)
+        .with_context(|| format!("{} okunamadı", store_path))?;
+
+    println!("📄 {} pozisyon ({})", positions.len(), store_path);
+    println!("   Tolerans: {} bps\n", tolerance_bps);
+
+    let (mut client, _keystore) = init_client().await?;
+    println!("🔄 Sync yapılıyor...");
+    client.sync_state().await?;
+    println!("   ✅ Sync tamamlandı\n");
+
+    let mut failed = 0;
+
+    // Positions that point at a pool we don't know about can't be redeemed
+    let known: Vec<String> = config.pools.iter().map(|p| p.pool_id.to_hex()).collect();
+    for position in positions.iter() {
+        if !known.contains(&position.pool_account_id) {
+            println!(
+                "❌ {} bilinmeyen pool'da {} LP tutuyor: {}",
+                position.user_account_id, position.total_deposited, position.pool_account_id
+            );
+            failed += 1;
+        }
+    }
+
+    for entry in &config.pools {
+        let pool_hex = entry.pool_id.to_hex();
+        let total_lp: u128 = positions
+            .iter()
+            .filter(|p| p.pool_account_id == pool_hex)
+            .map(|p| p.total_deposited as u128)
+            .sum();
+
+        println!("🔍 {} ({})", entry.pair, pool_hex);
+        let reserves = match pool_reserves(&mut client, entry.pool_id).await {
+            Ok(reserves) => reserves,
+            Err(e) => {
+                println!("   ❌ Rezervler okunamadı: {:#}\n", e);
+                failed += 1;
+                continue;
+            }
+        };
+        for (faucet_id, amount) in &reserves {
+            println!("   Rezerv {}: {}", faucet_id.to_hex(), amount);
+        }
+
+        let redeemable: u128 = reserves.iter().map(|(_, amount)| *amount as u128).sum();
+        let limit = redeemable + redeemable * tolerance_bps as u128 / 10_000;
+        println!("   Toplam LP: {}", total_lp);
+        println!("   Ödenebilir: {}", redeemable);
+
+        if total_lp > limit {
+            println!(
+                "   ❌ LP fazla kredilendi: tüm pozisyonlar {} öder, pool'da {} var (fark {})\n",
+                total_lp, redeemable, total_lp - redeemable
+            );
+            failed += 1;
+        } else {
+            let coverage_bps = if total_lp > 0 { redeemable * 10_000 / total_lp } else { 0 };
+            println!("   ✅ Tutarlı (karşılama {} bps)\n", coverage_bps);
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} LP tutarsızlığı bulundu", failed));
+    }
+
+    println!("🎉 LP muhasebesi rezervlerle tutarlı!");
+    Ok(())
+}
+
+/// Every row of the daemon's user_deposits table
+fn load_positions(store_path: &str) -> Result<Vec<LpPosition>> {
+    let conn = rusqlite::Connection::open_with_flags(store_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = conn.prepare("SELECT user_account_id, pool_account_id, total_deposited FROM user_deposits")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(LpPosition {
+            user_account_id: row.get(0)?,
+            pool_account_id: row.get(1)?,
+            total_deposited: row.get::<_, i64>(2)? as u64,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Fungible balances in the pool vault
+async fn pool_reserves(client: &mut MidenClient, pool_id: AccountId) -> Result<Vec<(AccountId, u64)>> {
+    // Pool may not be in this store yet
+    let _ = client.import_account_by_id(pool_id).await;
+    let record = client
+        .get_account(pool_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account bulunamadı"))?;
+
+    let mut reserves = Vec::new();
+    for asset in record.account().vault().assets() {
+        if let Asset::Fungible(fungible) = asset {
+            let amount: u64 = fungible.amount().try_into().map_err(|_| {
+                anyhow::anyhow!("Rezerv {} u64'e sığmıyor ({})", fungible.amount(), fungible.faucet_id().to_hex())
+            })?;
+            reserves.push((fungible.faucet_id(), amount));
+        }
+    }
+    Ok(reserves)
+}
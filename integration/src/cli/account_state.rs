@@ -0,0 +1,44 @@
+//! `milo account-state` - compare an account's local state with the chain
+//!
+//! For a transaction that was submitted but never committed: the local
+//! nonce stays ahead of the on-chain one until the store is resynced.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use miden_client::{account::AccountId, rpc::GrpcClient};
+
+use super::{init_client, load_milo_config, sync, RPC_TIMEOUT_MS};
+use crate::account_state::compare_account_state;
+
+#[derive(Args, Debug)]
+pub struct AccountStateArgs {
+    /// Account ID (hex), e.g. a faucet whose mint never committed
+    pub account_id: String,
+}
+
+pub async fn run(args: AccountStateArgs) -> Result<()> {
+    let account_id = AccountId::from_hex(&args.account_id)
+        .with_context(|| format!("Geçersiz hesap ID: {}", args.account_id))?;
+    let endpoint = load_milo_config()?.endpoint()?;
+    let rpc_api = GrpcClient::new(&endpoint, RPC_TIMEOUT_MS);
+
+    let (mut client, _keystore) = init_client().await?;
+    sync(&mut client).await?;
+
+    let cmp = compare_account_state(&client, &rpc_api, account_id)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let show = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+    println!("📝 Hesap: {}", cmp.account_id);
+    println!("   Nonce (yerel / zincir):      {} / {}", show(cmp.local_nonce.map(|n| n.to_string())), show(cmp.on_chain_nonce.map(|n| n.to_string())));
+    println!("   Commitment (yerel):          {}", show(cmp.local_commitment.clone()));
+    println!("   Commitment (zincir):         {}", show(cmp.on_chain_commitment.clone()));
+    if cmp.in_sync {
+        println!("   ✅ Yerel durum zincirle aynı");
+    } else if cmp.local_ahead {
+        println!("   ⚠️  Yerel nonce zincirden ileride: commit edilmemiş bir işlem var");
+    } else {
+        println!("   ⚠️  Yerel durum zincirden farklı");
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! `milo accounts` - check that faucet and pool accounts exist
+
+use anyhow::Result;
+use miden_client::account::AccountId;
+
+use super::{init_client, load_accounts_config, load_pools, sync, MidenClient};
+
+pub async fn run() -> Result<()> {
+    println!("🔍 Hesap kontrolü...\n");
+
+    let accounts = load_accounts_config()?;
+    let pools = load_pools()?;
+
+    let (mut client, _keystore) = init_client().await?;
+    sync(&mut client).await?;
+
+    let faucets = [
+        ("MILO", accounts.milo_faucet_id.clone()),
+        ("MELO", accounts.melo_faucet_id.clone()),
+        ("MUSDC", accounts.musdc_faucet_id.clone()),
+    ];
+
+    println!("📝 Faucet Hesapları:");
+    for (name, id_hex) in &faucets {
+        check_account(&mut client, name, id_hex).await;
+    }
+
+    println!("\n📝 Pool Hesapları:");
+    for entry in &pools.pools {
+        check_account(&mut client, &entry.pair.to_uppercase().replace('_', "/"), &entry.pool_id.to_hex()).await;
+    }
+
+    Ok(())
+}
+
+async fn check_account(client: &mut MidenClient, name: &str, id_hex: &str) {
+    let id = match AccountId::from_hex(id_hex) {
+        Ok(id) => id,
+        Err(e) => {
+            println!("   ❌ {}: {} - Geçersiz ID: {}", name, id_hex, e);
+            return;
+        }
+    };
+
+    match client.get_account(id).await {
+        Ok(account_record) => {
+            println!("   ✅ {}: {} - Mevcut", name, id.to_hex().chars().take(16).collect::<String>());
+            if let Some(account) = account_record {
+                println!("       Type: {:?}", account.account().account_type());
+            }
+        }
+        Err(e) => {
+            println!("   ❌ {}: {} - Bulunamadı: {}", name, id_hex, e);
+        }
+    }
+}
@@ -0,0 +1,481 @@
+//! `milo add-liquidity` - create the pools if needed and seed them with liquidity
+//! MILO/MUSDC ve MELO/MUSDC pool'larına likidite ekler
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use miden_client::{
+    Felt,
+    account::{Account, AccountBuilder, AccountId, AccountStorageMode, AccountType},
+    asset::{Asset, FungibleAsset},
+    auth::AuthSecretKey,
+    keystore::FilesystemKeyStore,
+    note::{create_p2id_note, NoteType},
+    transaction::{OutputNote, TransactionRequestBuilder},
+};
+use miden_lib::account::{auth::AuthRpoFalcon512, wallets::BasicWallet};
+use milo_registry::FAUCET_MAX_SUPPLY;
+use rand::rngs::StdRng;
+use rand::RngCore;
+use tokio::time::sleep;
+
+use super::setup::create_token_faucet;
+use super::{
+    consume_notes_for, init_client, load_accounts_config, wait_for_transaction, MidenClient,
+    POOLS_CONFIG_PATH,
+};
+use crate::helpers::reserve_amount;
+use crate::lp_token::{lp_supply, lp_token_symbol, lp_tokens_for_deposit};
+use crate::pool_component::pool_component;
+use crate::pool_config::{
+    load_pool_curves_config, load_pools_config, save_pool_curves_config, save_pools_config, AmmCurve,
+    PoolsConfig, MAX_AMPLIFICATION, POOL_CURVES_CONFIG_PATH,
+};
+
+#[derive(Args, Debug, Default)]
+pub struct AddLiquidityArgs {
+    /// Pricing curve to record in pool_config.json; pools keep their current curve when omitted
+    #[arg(long, value_enum)]
+    pub curve: Option<CurveKind>,
+
+    /// Amplification coefficient for `--curve stable`
+    #[arg(long, default_value_t = 100)]
+    pub amp: u64,
+
+    /// Pool pair the curve applies to (repeatable, e.g. `--pair milo_musdc`); defaults to every pool
+    #[arg(long = "pair")]
+    pub pairs: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CurveKind {
+    /// Constant product (x * y = k)
+    Constant,
+    /// Curve-style stable swap for pegged pairs
+    Stable,
+}
+
+impl AddLiquidityArgs {
+    fn amm_curve(&self) -> Result<Option<AmmCurve>> {
+        match self.curve {
+            None => Ok(None),
+            Some(CurveKind::Constant) => Ok(Some(AmmCurve::ConstantProduct)),
+            Some(CurveKind::Stable) => {
+                if self.amp == 0 || self.amp > MAX_AMPLIFICATION {
+                    anyhow::bail!("--amp 1 ile {} arasında olmalı", MAX_AMPLIFICATION);
+                }
+                Ok(Some(AmmCurve::StableSwap { amplification: self.amp }))
+            }
+        }
+    }
+}
+
+pub async fn run(args: AddLiquidityArgs) -> Result<()> {
+    println!("🚀 Milo Swap - Likidite Ekleniyor...\n");
+
+    // Checked before any pool is created so a bad flag costs nothing
+    let curve = args.amm_curve()?;
+
+    let accounts = load_accounts_config()?;
+
+    println!("📄 Config yüklendi:");
+    println!("   - User Wallet: {}...", accounts.user_wallet_id.chars().take(16).collect::<String>());
+    println!("   - MILO Faucet: {}...", accounts.milo_faucet_id.chars().take(16).collect::<String>());
+    println!("   - MELO Faucet: {}...", accounts.melo_faucet_id.chars().take(16).collect::<String>());
+    println!("   - MUSDC Faucet: {}...", accounts.musdc_faucet_id.chars().take(16).collect::<String>());
+    println!();
+
+    let (mut client, keystore) = init_client().await?;
+
+    let user_wallet_id = AccountId::from_hex(&accounts.user_wallet_id)?;
+    let milo_faucet_id = AccountId::from_hex(&accounts.milo_faucet_id)?;
+    let melo_faucet_id = AccountId::from_hex(&accounts.melo_faucet_id)?;
+    let musdc_faucet_id = AccountId::from_hex(&accounts.musdc_faucet_id)?;
+
+    client.sync_state().await?;
+
+    // Check if pools exist, or create them
+    let (milo_pool_id, melo_pool_id) = if PathBuf::from(POOLS_CONFIG_PATH).exists() {
+        println!("📄 Mevcut pools.json bulundu, pool'lar yükleniyor...");
+        load_existing_pools(&mut client).await?
+    } else {
+        println!("📝 Pool hesapları oluşturuluyor...");
+        create_pools(&mut client, &keystore, (milo_faucet_id, musdc_faucet_id), (melo_faucet_id, musdc_faucet_id)).await?
+    };
+
+    println!("   - MILO/MUSDC Pool: {}", milo_pool_id.to_hex());
+    println!("   - MELO/MUSDC Pool: {}", melo_pool_id.to_hex());
+    println!();
+
+    if let Some(curve) = curve {
+        save_pool_curve(curve, &args.pairs)?;
+    }
+
+    // Mint tokens regardless (always mint more for liquidity)
+    // Amounts in base units: tokens × 10^8 (8 decimals)
+    println!("💰 Token'lar mint ediliyor...");
+    mint_token(&mut client, milo_faucet_id, user_wallet_id, 200_000 * 100_000_000).await?;
+    mint_token(&mut client, melo_faucet_id, user_wallet_id, 200_000 * 100_000_000).await?;
+    mint_token(&mut client, musdc_faucet_id, user_wallet_id, 500_000 * 100_000_000).await?;
+
+    // Consume mint notes
+    println!("   📝 Mint notları tüketiliyor...");
+    client.sync_state().await?;
+    sleep(Duration::from_secs(3)).await;
+    consume_notes_for(&mut client, user_wallet_id).await?;
+
+    // Pools created before LP tokens have no LP faucet and get none minted
+    let pools = load_pools_config(POOLS_CONFIG_PATH)?;
+
+    // Add liquidity to MILO/MUSDC pool (amounts in base units)
+    println!("\n📝 Adım 1: MILO/MUSDC Pool'a likidite ekleniyor...");
+    add_liquidity_to_pool(&mut client, user_wallet_id, milo_faucet_id, musdc_faucet_id, milo_pool_id, pools.lp_faucet_id(milo_pool_id), 100_000 * 100_000_000, 200_000 * 100_000_000).await?;
+
+    // Add liquidity to MELO/MUSDC pool (amounts in base units)
+    println!("\n📝 Adım 2: MELO/MUSDC Pool'a likidite ekleniyor...");
+    add_liquidity_to_pool(&mut client, user_wallet_id, melo_faucet_id, musdc_faucet_id, melo_pool_id, pools.lp_faucet_id(melo_pool_id), 100_000 * 100_000_000, 200_000 * 100_000_000).await?;
+
+    println!("\n🎉 Likidite ekleme tamamlandı!");
+
+    Ok(())
+}
+
+/// Record `curve` for `pairs` (every pool when empty) in pool_config.json,
+/// next to both copies of pools.json
+fn save_pool_curve(curve: AmmCurve, pairs: &[String]) -> Result<()> {
+    let pools = load_pools_config(POOLS_CONFIG_PATH)?;
+    let mut curves = load_pool_curves_config(POOL_CURVES_CONFIG_PATH, &pools)?;
+    let pairs: Vec<String> = if pairs.is_empty() {
+        pools.pools.iter().map(|p| p.pair.clone()).collect()
+    } else {
+        pairs.iter().map(|p| p.to_lowercase()).collect()
+    };
+    for pair in &pairs {
+        pools.require_pool_id(pair)?;
+        curves.set_curve(pair, curve);
+        println!("   📐 {} curve: {}", pair, curve);
+    }
+
+    save_pool_curves_config(POOL_CURVES_CONFIG_PATH, &curves, &pools)
+        .with_context(|| format!("{} kaydedilemedi", POOL_CURVES_CONFIG_PATH))?;
+    println!("   💾 {} kaydedildi", POOL_CURVES_CONFIG_PATH);
+
+    let daemon_path = format!("pool-daemon/{}", POOL_CURVES_CONFIG_PATH);
+    save_pool_curves_config(&daemon_path, &curves, &pools)
+        .with_context(|| format!("{} kaydedilemedi", daemon_path))?;
+    println!("   💾 {} kaydedildi", daemon_path);
+    println!("   ℹ️  Swap daemon'u yeniden başlatın, curve başlangıçta okunur");
+    Ok(())
+}
+
+/// Load existing pools from pools.json and import to client
+async fn load_existing_pools(client: &mut MidenClient) -> Result<(AccountId, AccountId)> {
+    let config = load_pools_config(POOLS_CONFIG_PATH)?;
+
+    let milo_pool_id = config.require_pool_id("milo_musdc")?;
+    let melo_pool_id = config.require_pool_id("melo_musdc")?;
+
+    // Import accounts to local client
+    println!("   📥 Pool'lar yerel client'e aktarılıyor...");
+
+    // Try to import - if they exist locally already, this will just return
+    let _ = client.import_account_by_id(milo_pool_id).await;
+    let _ = client.import_account_by_id(melo_pool_id).await;
+    for lp_faucet_id in config.pools.iter().filter_map(|p| p.lp_faucet_id) {
+        let _ = client.import_account_by_id(lp_faucet_id).await;
+    }
+
+    Ok((milo_pool_id, melo_pool_id))
+}
+
+/// Create new pool accounts and save to pools.json + poolConfig.ts
+async fn create_pools(
+    client: &mut MidenClient,
+    keystore: &FilesystemKeyStore<StdRng>,
+    milo_pair: (AccountId, AccountId),
+    melo_pair: (AccountId, AccountId),
+) -> Result<(AccountId, AccountId)> {
+    // Create MILO/MUSDC pool account
+    println!("   📝 MILO/MUSDC pool hesabı oluşturuluyor...");
+    let milo_pool = create_pool_account(client, keystore, milo_pair).await?;
+    let milo_pool_id = milo_pool.id();
+    println!("   ✅ MILO/MUSDC Pool ID: {}", milo_pool_id.to_hex());
+    let milo_lp_faucet_id = create_lp_faucet(client, keystore, &lp_token_symbol("milo_musdc")).await?;
+
+    // Create MELO/MUSDC pool account
+    println!("   📝 MELO/MUSDC pool hesabı oluşturuluyor...");
+    let melo_pool = create_pool_account(client, keystore, melo_pair).await?;
+    let melo_pool_id = melo_pool.id();
+    println!("   ✅ MELO/MUSDC Pool ID: {}", melo_pool_id.to_hex());
+    let melo_lp_faucet_id = create_lp_faucet(client, keystore, &lp_token_symbol("melo_musdc")).await?;
+
+    // Save pools.json (root dir for daemon)
+    let mut pools_config = PoolsConfig::default();
+    pools_config.insert("milo_musdc", milo_pool_id, Some(milo_lp_faucet_id));
+    pools_config.insert("melo_musdc", melo_pool_id, Some(melo_lp_faucet_id));
+
+    save_pools_config(POOLS_CONFIG_PATH, &pools_config)
+        .context("pools.json kaydedilemedi")?;
+    println!("   💾 pools.json kaydedildi");
+
+    // Also save to pool-daemon/pools.json
+    save_pools_config("pool-daemon/pools.json", &pools_config)
+        .context("pool-daemon/pools.json kaydedilemedi")?;
+    println!("   💾 pool-daemon/pools.json kaydedildi");
+
+    // Update frontend poolConfig.ts
+    update_pool_config(&milo_pool_id, &melo_pool_id)?;
+
+    client.sync_state().await?;
+    Ok((milo_pool_id, melo_pool_id))
+}
+
+/// Create a pool account: BasicWallet for deposits plus the on-chain pool
+/// component (pair, reserves and the checked swap payout). The reserves
+/// start empty; the first deposit's `sync_reserves` records them.
+pub async fn create_pool_account(
+    client: &mut MidenClient,
+    keystore: &FilesystemKeyStore<StdRng>,
+    (token0, token1): (AccountId, AccountId),
+) -> Result<Account> {
+    let mut init_seed = [0u8; 32];
+    client.rng().fill_bytes(&mut init_seed);
+
+    let key_pair = AuthSecretKey::new_rpo_falcon512();
+
+    let builder = AccountBuilder::new(init_seed)
+        .account_type(AccountType::RegularAccountUpdatableCode)
+        .storage_mode(AccountStorageMode::Public)
+        .with_auth_component(AuthRpoFalcon512::new(key_pair.public_key().to_commitment()))
+        .with_component(BasicWallet)
+        .with_component(
+            pool_component(token0, token1, 0, 0).map_err(|e| anyhow::anyhow!("Pool MASM derlenemedi: {}", e))?,
+        );
+
+    let account = builder.build().context("Pool hesabı oluşturulamadı")?;
+    client.add_account(&account, true).await?;
+    keystore.add_key(&key_pair).context("Pool anahtarı keystore'a kaydedilemedi")?;
+    client.sync_state().await?;
+
+    Ok(account)
+}
+
+/// Create a pool's LP token faucet. Its key goes into the shared keystore:
+/// the liquidity daemon mints LP tokens to depositors and burns them on
+/// withdrawal.
+pub async fn create_lp_faucet(
+    client: &mut MidenClient,
+    keystore: &FilesystemKeyStore<StdRng>,
+    symbol: &str,
+) -> Result<AccountId> {
+    println!("   📝 {} LP faucet oluşturuluyor...", symbol);
+    let lp_faucet = create_token_faucet(client, keystore, symbol, FAUCET_MAX_SUPPLY)
+        .await
+        .with_context(|| format!("{} LP faucet oluşturulamadı", symbol))?;
+    println!("   ✅ {} LP Faucet ID: {}", symbol, lp_faucet.id().to_hex());
+    Ok(lp_faucet.id())
+}
+
+/// Update frontend/src/config/poolConfig.ts with new pool IDs
+fn update_pool_config(milo_pool_id: &AccountId, melo_pool_id: &AccountId) -> Result<()> {
+    let pool_config_content = format!(
+        r#"// Pool Account IDs for Milo Swap Protocol v0.12
+// Auto-generated by `milo add-liquidity`
+// Pool accounts created with BasicWallet and the Milo pool component
+
+export const MILO_MUSDC_POOL_ACCOUNT_ID_HEX = '{}';
+export const MELO_MUSDC_POOL_ACCOUNT_ID_HEX = '{}';
+
+// Get pool account ID hex for a specific token pair
+export function getPoolAccountIdHex(tokenA: string, tokenB: string): string {{
+  const pair = `${{tokenA}}/${{tokenB}}`;
+  if (pair === 'MILO/MUSDC' || pair === 'MUSDC/MILO') {{
+    return MILO_MUSDC_POOL_ACCOUNT_ID_HEX;
+  }} else if (pair === 'MELO/MUSDC' || pair === 'MUSDC/MELO') {{
+    return MELO_MUSDC_POOL_ACCOUNT_ID_HEX;
+  }}
+  return MILO_MUSDC_POOL_ACCOUNT_ID_HEX;
+}}
+
+// Get default pool account ID hex
+export function getDefaultPoolAccountIdHex(): string {{
+  return MILO_MUSDC_POOL_ACCOUNT_ID_HEX;
+}}
+
+// Backward compatibility functions
+export function getMiloMusdcPoolAccountId(): string {{
+  return MILO_MUSDC_POOL_ACCOUNT_ID_HEX;
+}}
+
+export function getMeloMusdcPoolAccountId(): string {{
+  return MELO_MUSDC_POOL_ACCOUNT_ID_HEX;
+}}
+
+export function getPoolAccountIdForPair(tokenA: string, tokenB: string): string {{
+  return getPoolAccountIdHex(tokenA, tokenB);
+}}
+
+// Legacy constant for backward compatibility
+export const POOL_ACCOUNT_ID = MILO_MUSDC_POOL_ACCOUNT_ID_HEX;
+
+// Pool configurations
+export const POOLS = [
+  {{
+    pair: 'MILO/MUSDC',
+    tokenA: 'MILO',
+    tokenB: 'MUSDC',
+    poolAccountIdHex: MILO_MUSDC_POOL_ACCOUNT_ID_HEX,
+  }},
+  {{
+    pair: 'MELO/MUSDC',
+    tokenA: 'MELO',
+    tokenB: 'MUSDC',
+    poolAccountIdHex: MELO_MUSDC_POOL_ACCOUNT_ID_HEX,
+  }},
+];
+"#,
+        milo_pool_id.to_hex(),
+        melo_pool_id.to_hex(),
+    );
+
+    fs::write("frontend/src/config/poolConfig.ts", pool_config_content)
+        .context("frontend/src/config/poolConfig.ts kaydedilemedi")?;
+    println!("   💾 frontend poolConfig.ts güncellendi");
+
+    Ok(())
+}
+
+/// Mint tokens to user wallet
+async fn mint_token(
+    client: &mut MidenClient,
+    faucet_id: AccountId,
+    user_wallet_id: AccountId,
+    amount: u64,
+) -> Result<()> {
+    let asset = FungibleAsset::new(faucet_id, amount)
+        .with_context(|| "Asset oluşturulamadı")?;
+
+    let tx_request = TransactionRequestBuilder::new()
+        .build_mint_fungible_asset(asset, user_wallet_id, NoteType::Public, client.rng())
+        .with_context(|| "Mint tx oluşturulamadı")?;
+
+    let tx_id = client
+        .submit_new_transaction(faucet_id, tx_request)
+        .await
+        .with_context(|| "Mint tx gönderilemedi")?;
+
+    wait_for_transaction(client, tx_id).await?;
+    println!("   ✅ {} {} mint edildi", amount, faucet_id.to_hex().chars().take(8).collect::<String>());
+
+    Ok(())
+}
+
+/// Add liquidity to a pool. Pools with an LP faucet mint LP tokens for it to
+/// the user wallet, like the liquidity daemon does for tracked deposits.
+pub async fn add_liquidity_to_pool(
+    client: &mut MidenClient,
+    user_wallet_id: AccountId,
+    token_faucet_id: AccountId,
+    stable_faucet_id: AccountId,
+    pool_id: AccountId,
+    lp_faucet_id: Option<AccountId>,
+    token_amount: u64,
+    stable_amount: u64,
+) -> Result<()> {
+    client.sync_state().await?;
+
+    // LP tokens are priced against the pool before these notes land
+    let lp_amount = match lp_faucet_id {
+        Some(lp_faucet_id) => {
+            let liquidity_before = pool_liquidity(client, pool_id).await?;
+            let lp_faucet = client.get_account(lp_faucet_id).await?
+                .ok_or_else(|| anyhow::anyhow!("LP faucet {} yerel store'da yok", lp_faucet_id.to_hex()))?;
+            let supply = lp_supply(lp_faucet.account())?;
+            Some(lp_tokens_for_deposit(token_amount.saturating_add(stable_amount), liquidity_before, supply))
+        }
+        None => None,
+    };
+
+    // Create token asset
+    let token_asset = FungibleAsset::new(token_faucet_id, token_amount)
+        .with_context(|| "Token asset oluşturulamadı")?;
+    
+    // Create stable asset
+    let stable_asset = FungibleAsset::new(stable_faucet_id, stable_amount)
+        .with_context(|| "Stable asset oluşturulamadı")?;
+
+    // Create P2ID note for token
+    println!("   💧 Token notu oluşturuluyor...");
+    let token_note = create_p2id_note(
+        user_wallet_id,
+        pool_id,
+        vec![token_asset.into()],
+        NoteType::Public,
+        Felt::new(0),
+        client.rng(),
+    ).with_context(|| "Token notu oluşturulamadı")?;
+
+    let tx_request_1 = TransactionRequestBuilder::new()
+        .own_output_notes(vec![OutputNote::Full(token_note)])
+        .build()?;
+
+    let tx_id_1 = client
+        .submit_new_transaction(user_wallet_id, tx_request_1)
+        .await?;
+
+    wait_for_transaction(client, tx_id_1).await?;
+    println!("   ✅ Token notu gönderildi");
+
+    // Create P2ID note for stable
+    println!("   💧 Stablecoin notu oluşturuluyor...");
+    let stable_note = create_p2id_note(
+        user_wallet_id,
+        pool_id,
+        vec![stable_asset.into()],
+        NoteType::Public,
+        Felt::new(0),
+        client.rng(),
+    ).with_context(|| "Stable notu oluşturulamadı")?;
+
+    let tx_request_2 = TransactionRequestBuilder::new()
+        .own_output_notes(vec![OutputNote::Full(stable_note)])
+        .build()?;
+
+    let tx_id_2 = client
+        .submit_new_transaction(user_wallet_id, tx_request_2)
+        .await?;
+
+    wait_for_transaction(client, tx_id_2).await?;
+    println!("   ✅ Stablecoin notu gönderildi");
+
+    if let (Some(lp_faucet_id), Some(lp_amount)) = (lp_faucet_id, lp_amount) {
+        println!("   🪙 {} LP token mint ediliyor...", lp_amount);
+        mint_token(client, lp_faucet_id, user_wallet_id, lp_amount).await?;
+    }
+
+    // Pool consumes notes
+    println!("   🔍 Pool notları tüketiyor...");
+    client.sync_state().await?;
+    sleep(Duration::from_secs(5)).await;
+
+    let notes = client.get_consumable_notes(Some(pool_id)).await?;
+    println!("   ✅ {} not tüketildi", notes.len());
+
+    Ok(())
+}
+
+/// Sum of a pool's reserves in the local store
+async fn pool_liquidity(client: &mut MidenClient, pool_id: AccountId) -> Result<u64> {
+    let pool = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool {} yerel store'da yok", pool_id.to_hex()))?;
+    let mut total = 0u64;
+    for asset in pool.account().vault().assets() {
+        if let Asset::Fungible(fungible) = asset {
+            total = total.saturating_add(reserve_amount(&fungible)?);
+        }
+    }
+    Ok(total)
+}
@@ -0,0 +1,79 @@
+//! `milo consume` - consume pending P2ID notes on every pool
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use miden_client::{account::AccountId, transaction::TransactionRequestBuilder};
+use tokio::time::sleep;
+
+use super::{init_client, load_pools, sync, wait_for_transaction, MidenClient};
+
+pub async fn run() -> Result<()> {
+    println!("🔍 Pool Not Tüketme\n");
+
+    let config = load_pools()?;
+
+    let (mut client, _keystore) = init_client().await?;
+    sync(&mut client).await?;
+
+    for entry in &config.pools {
+        consume_pool_notes(&mut client, entry.pool_id, &entry.pair).await?;
+    }
+
+    println!("\n🎉 İşlem tamamlandı!");
+    println!("💡 Vault bilgisi için `milo reserves` çalıştırın.");
+
+    Ok(())
+}
+
+async fn consume_pool_notes(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    pool_name: &str,
+) -> Result<()> {
+    println!("🔍 {} Pool notları kontrol ediliyor...", pool_name);
+
+    // Sync twice so notes committed moments ago are visible
+    client.sync_state().await?;
+    sleep(Duration::from_secs(2)).await;
+    client.sync_state().await?;
+
+    let notes = client.get_consumable_notes(Some(pool_id)).await?;
+
+    if notes.is_empty() {
+        println!("   ℹ️ Tüketilecek not yok.");
+        return Ok(());
+    }
+
+    println!("   📝 {} not bulundu, tüketiliyor...", notes.len());
+
+    let mut consumed = 0;
+    for (note, _) in notes {
+        println!("      - Not tüketiliyor: {}", note.id().to_hex().chars().take(16).collect::<String>());
+
+        let tx_request = TransactionRequestBuilder::new()
+            .authenticated_input_notes([(note.id(), None)])
+            .build()
+            .context("Tx request oluşturulamadı")?;
+
+        let tx_id = client
+            .submit_new_transaction(pool_id, tx_request)
+            .await
+            .context("Tx gönderilemedi")?;
+
+        println!("         Tx: {}", tx_id.to_hex().chars().take(16).collect::<String>());
+
+        if wait_for_transaction(client, tx_id).await.is_ok() {
+            consumed += 1;
+            println!("         ✅ Tüketildi!");
+        } else {
+            println!("         ⚠️ Tüketilemedi!");
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    println!("   ✅ {} not tüketildi.\n", consumed);
+
+    Ok(())
+}
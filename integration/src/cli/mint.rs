@@ -0,0 +1,94 @@
+//! `milo mint` - mint tokens from a deployed faucet to a recipient
+
+use anyhow::{Context, Result};
+use clap::Args;
+use miden_client::{
+    account::AccountId,
+    asset::FungibleAsset,
+    note::NoteType,
+    transaction::TransactionRequestBuilder,
+};
+
+use super::{init_client, load_faucets, sync, wait_for_transaction, ACCOUNTS_CONFIG_PATH};
+
+#[derive(Args, Debug)]
+pub struct MintArgs {
+    /// Token symbol; one of the faucets in accounts.json (MILO, MELO, MUSDC
+    /// after `milo setup`)
+    pub symbol: String,
+
+    /// Amount in base units
+    pub amount: u64,
+
+    /// Recipient account ID (hex)
+    pub recipient: String,
+}
+
+pub async fn run(args: MintArgs) -> Result<()> {
+    println!("🚀 Milo Swap - Token Mint\n");
+
+    let token_symbol = args.symbol.to_uppercase();
+
+    // Faucets come from what `milo setup` wrote, so mints target the live
+    // faucets; the compiled-in IDs are only used without accounts.json
+    let faucets = load_faucets()?;
+    let source = if std::path::Path::new(ACCOUNTS_CONFIG_PATH).exists() {
+        ACCOUNTS_CONFIG_PATH
+    } else {
+        "milo_accounts (varsayılan)"
+    };
+    let available: Vec<&str> = faucets.iter().map(|(s, _)| s.as_str()).collect();
+    let Some(faucet_id) = faucets.iter().find(|(s, _)| *s == token_symbol).map(|(_, id)| *id) else {
+        anyhow::bail!(
+            "Bilinmeyen token: {}\n   Kullanım: milo mint <{}> <AMOUNT> <RECIPIENT_ACCOUNT_ID>\n   Faucet'ler: {}",
+            token_symbol, available.join("|"), source
+        );
+    };
+
+    println!("📝 Mint İsteği:");
+    println!("   Token: {}", token_symbol);
+    println!("   Amount: {}", args.amount);
+    println!("   Recipient: {}", args.recipient);
+    println!("   Faucet: {} ({})", faucet_id.to_hex(), source);
+    println!();
+
+    let recipient_id = AccountId::from_hex(&args.recipient)
+        .context("Geçersiz recipient ID")?;
+
+    let (mut client, _keystore) = init_client().await?;
+    sync(&mut client).await?;
+
+    client.get_account(faucet_id).await
+        .context("Faucet hesabı alınamadı. Faucet deploy edilmiş olmalı!")?
+        .context("Faucet hesabı bu store'da yok. Faucet'i oluşturan store ile çalıştırın.")?;
+
+    println!("   ✅ Faucet hesabı bulundu: {}", faucet_id.to_hex());
+
+    let asset = FungibleAsset::new(faucet_id, args.amount)
+        .context("Asset oluşturulamadı")?;
+
+    println!("   💰 Asset oluşturuldu: {} {}", args.amount, token_symbol);
+
+    println!("\n📤 Mint transaction gönderiliyor...");
+
+    let tx_request = TransactionRequestBuilder::new()
+        .build_mint_fungible_asset(asset, recipient_id, NoteType::Public, client.rng())
+        .context("Mint tx oluşturulamadı")?;
+
+    let tx_id = client
+        .submit_new_transaction(faucet_id, tx_request)
+        .await
+        .context("Mint tx gönderilemedi")?;
+
+    println!("   ✅ Transaction gönderildi: {}", tx_id.to_hex().chars().take(16).collect::<String>());
+
+    wait_for_transaction(&mut client, tx_id).await?;
+
+    println!("\n🎉 Mint Başarılı!");
+    println!("   Token: {}", token_symbol);
+    println!("   Amount: {}", args.amount);
+    println!("   Recipient: {}", args.recipient);
+    println!("\n💡 Not: Token'ları almak için recipient cüzdanını sync etmeli ve not'ları tüketmeli.");
+
+    Ok(())
+}
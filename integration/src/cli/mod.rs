@@ -0,0 +1,226 @@
+//! Milo CLI - shared client and config plumbing
+//! `milo` binary'si ve eski tekil script'ler bu modülü kullanır
+//!
+//! Each subcommand lives in its own module with a `run` entry point; the
+//! `milo` binary dispatches to them and the legacy binaries (`setup_milo`,
+//! `mint_tokens`, ...) are thin wrappers around the same functions.
+
+pub mod account_state;
+pub mod accounts;
+pub mod add_liquidity;
+pub mod consume;
+pub mod mint;
+pub mod reserves;
+pub mod setup;
+pub mod swap;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use miden_client::{
+    account::AccountId,
+    builder::ClientBuilder,
+    keystore::FilesystemKeyStore,
+    rpc::GrpcClient,
+    store::TransactionFilter,
+    transaction::TransactionRequestBuilder,
+};
+use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use milo_config::{MiloConfig, Service};
+use milo_registry::TokenRegistry;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::milo_accounts::{MELO_MUSDC_POOL_ACCOUNT_ID_HEX, MILO_MUSDC_POOL_ACCOUNT_ID_HEX, USER_WALLET_ID_HEX};
+use crate::pool_config::{load_pools_config, PoolsConfig};
+
+pub const ACCOUNTS_CONFIG_PATH: &str = "accounts.json";
+pub const POOLS_CONFIG_PATH: &str = "pools.json";
+
+const RPC_TIMEOUT_MS: u64 = 30_000;
+const TX_WAIT_ATTEMPTS: u32 = 120;
+
+pub type MidenClient = miden_client::Client<FilesystemKeyStore<StdRng>>;
+
+/// Account IDs written by `milo setup` (accounts.json)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountsConfig {
+    pub user_wallet_id: String,
+    pub user_wallet_address: String,
+    pub milo_faucet_id: String,
+    pub milo_faucet_address: String,
+    pub melo_faucet_id: String,
+    pub melo_faucet_address: String,
+    pub musdc_faucet_id: String,
+    pub musdc_faucet_address: String,
+}
+
+/// Keystore, store and node of the CLI: the `[cli]` settings in milo.toml,
+/// else `keystore/`, `store.sqlite3` and testnet
+pub fn load_milo_config() -> Result<MiloConfig> {
+    MiloConfig::load(Service::Cli)
+}
+
+/// Initialize the Miden client on the local keystore and sqlite store
+pub async fn init_client() -> Result<(MidenClient, FilesystemKeyStore<StdRng>)> {
+    let config = load_milo_config()?;
+    let endpoint = config.endpoint()?;
+    let rpc_api = Arc::new(GrpcClient::new(&endpoint, RPC_TIMEOUT_MS));
+
+    let keystore_path = PathBuf::from(&config.keystore_path);
+    if !keystore_path.exists() {
+        fs::create_dir_all(&keystore_path)?;
+        println!("   📁 Keystore klasörü oluşturuldu");
+    }
+
+    let keystore = FilesystemKeyStore::new(keystore_path)
+        .context("Keystore oluşturulamadı")?;
+
+    let client = ClientBuilder::new()
+        .rpc(rpc_api)
+        .authenticator(Arc::new(keystore.clone()))
+        .in_debug_mode(true.into())
+        .sqlite_store(PathBuf::from(&config.store_path))
+        .build()
+        .await
+        .context("Client oluşturulamadı")?;
+
+    Ok((client, keystore))
+}
+
+/// Sync with progress output
+pub async fn sync(client: &mut MidenClient) -> Result<()> {
+    println!("🔄 Sync yapılıyor...");
+    client.sync_state().await?;
+    println!("   ✅ Sync tamamlandı\n");
+    Ok(())
+}
+
+/// Load accounts.json written by `milo setup`
+/// Install the `TokenRegistry` of the last setup (accounts.json), so token
+/// symbols in pools.json resolve to its faucets. Every binary except setup
+/// calls this at startup.
+pub fn install_token_registry() -> Result<()> {
+    TokenRegistry::load(ACCOUNTS_CONFIG_PATH)?.install();
+    Ok(())
+}
+
+pub fn load_accounts_config() -> Result<AccountsConfig> {
+    let data = fs::read_to_string(ACCOUNTS_CONFIG_PATH)
+        .with_context(|| format!("{} bulunamadı! Önce `milo setup` çalıştırın.", ACCOUNTS_CONFIG_PATH))?;
+    serde_json::from_str(&data).with_context(|| format!("{} parse edilemedi", ACCOUNTS_CONFIG_PATH))
+}
+
+pub fn save_accounts_config(config: &AccountsConfig) -> Result<()> {
+    let data = serde_json::to_string_pretty(config).context("Config serileştirilemedi")?;
+    fs::write(ACCOUNTS_CONFIG_PATH, data).context("Config kaydedilemedi")?;
+    println!("   💾 Hesap config kaydedildi: {}", ACCOUNTS_CONFIG_PATH);
+    Ok(())
+}
+
+/// Load pools.json, with a hint when it hasn't been created yet
+pub fn load_pools() -> Result<PoolsConfig> {
+    if !PathBuf::from(POOLS_CONFIG_PATH).exists() {
+        return Err(anyhow::anyhow!("{} bulunamadı! Önce `milo add-liquidity` çalıştırın.", POOLS_CONFIG_PATH));
+    }
+    load_pools_config(POOLS_CONFIG_PATH)
+}
+
+/// pools.json, or the pools in `milo_accounts` when the file doesn't exist.
+/// A file that exists but can't be parsed is still an error.
+pub fn load_pools_or_defaults() -> Result<PoolsConfig> {
+    if PathBuf::from(POOLS_CONFIG_PATH).exists() {
+        return load_pools_config(POOLS_CONFIG_PATH);
+    }
+    println!("⚠️  {} yok, milo_accounts'taki pool ID'leri kullanılıyor", POOLS_CONFIG_PATH);
+    let mut config = PoolsConfig::default();
+    for (pair, hex) in [("milo_musdc", MILO_MUSDC_POOL_ACCOUNT_ID_HEX), ("melo_musdc", MELO_MUSDC_POOL_ACCOUNT_ID_HEX)] {
+        config.insert(pair, AccountId::from_hex(hex).with_context(|| format!("Geçersiz pool ID: {}", hex))?, None);
+    }
+    Ok(config)
+}
+
+/// The user wallet from accounts.json, or the one in `milo_accounts` when
+/// the file doesn't exist
+pub fn resolve_user_wallet_id() -> Result<AccountId> {
+    let hex = if PathBuf::from(ACCOUNTS_CONFIG_PATH).exists() {
+        load_accounts_config()?.user_wallet_id
+    } else {
+        USER_WALLET_ID_HEX.to_string()
+    };
+    AccountId::from_hex(&hex).with_context(|| format!("Geçersiz wallet ID: {}", hex))
+}
+
+/// Every known faucet as (symbol, ID): accounts.json when present, else
+/// the compiled-in IDs. A present but unreadable accounts.json is an error
+/// rather than a silent fallback to IDs a fresh setup replaced.
+pub fn load_faucets() -> Result<Vec<(String, AccountId)>> {
+    let registry = TokenRegistry::load(ACCOUNTS_CONFIG_PATH)?;
+    Ok(registry.faucets().iter().map(|f| (f.symbol.clone(), f.faucet_id)).collect())
+}
+
+/// Faucet ID for a token symbol (case insensitive) from `load_faucets`; an
+/// unknown symbol is an error listing the available ones
+pub fn resolve_faucet_id(symbol: &str) -> Result<AccountId> {
+    let faucets = load_faucets()?;
+    faucets
+        .iter()
+        .find(|(s, _)| s.eq_ignore_ascii_case(symbol))
+        .map(|(_, id)| *id)
+        .with_context(|| {
+            let available: Vec<&str> = faucets.iter().map(|(s, _)| s.as_str()).collect();
+            format!("Bilinmeyen token: {} (mevcut: {})", symbol, available.join(", "))
+        })
+}
+
+/// Wait until a submitted transaction shows up in the local transaction log
+pub async fn wait_for_transaction(
+    client: &mut MidenClient,
+    tx_id: miden_objects::transaction::TransactionId,
+) -> Result<()> {
+    println!("   ⏳ Tx bekleniyor: {}...",
+        tx_id.to_hex().chars().take(16).collect::<String>());
+
+    for i in 0..TX_WAIT_ATTEMPTS {
+        match client.get_transactions(TransactionFilter::Ids(vec![tx_id])).await {
+            Ok(transactions) if !transactions.is_empty() => {
+                println!("   ✅ Tx tamamlandı!");
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(e) => println!("   ⚠️ Tx sorgulama hatası: {:?}", e),
+        }
+
+        if i % 20 == 0 && i > 0 {
+            println!("   ⏳ Hala bekleniyor... ({}s)", i / 2);
+        }
+
+        let _ = client.sync_state().await;
+        sleep(Duration::from_millis(500)).await;
+    }
+
+    Err(anyhow::anyhow!("Tx zaman aşımı - transaction log'da bulunamadı"))
+}
+
+/// Consume every note waiting for `account_id`
+pub async fn consume_notes_for(client: &mut MidenClient, account_id: AccountId) -> Result<usize> {
+    let notes = client.get_consumable_notes(Some(account_id)).await?;
+    println!("   📝 {} not bulundu, tüketiliyor...", notes.len());
+
+    let mut consumed = 0;
+    for (note, _) in notes {
+        let consume_req = TransactionRequestBuilder::new()
+            .authenticated_input_notes([(note.id(), None)])
+            .build()?;
+
+        client.submit_new_transaction(account_id, consume_req).await?;
+        println!("   ✅ Not tüketildi: {}", note.id().to_hex().chars().take(16).collect::<String>());
+        consumed += 1;
+    }
+
+    Ok(consumed)
+}
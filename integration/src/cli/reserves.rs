@@ -0,0 +1,223 @@
+//! `milo reserves` - check pool accounts and their vault balances
+//!
+//! Lists each pool's fungible reserves in whole tokens and the price they
+//! imply for the pair. `--json` prints one JSON document on stdout instead,
+//! for scripts and monitoring.
+
+use anyhow::Result;
+use clap::Args;
+use miden_client::{account::AccountId, asset::Asset};
+use serde_json::{json, Value as JsonValue};
+
+use super::{init_client, load_faucets, load_pools, sync, MidenClient};
+
+/// Decimals of every Milo token; reserves are shown as `amount / 10^8`
+const TOKEN_DECIMALS: u32 = 8;
+
+#[derive(Args, Debug, Default)]
+pub struct ReservesArgs {
+    /// Print the reserves as JSON on stdout (progress output is suppressed)
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// One fungible asset in a pool's vault
+struct Reserve {
+    faucet_id: AccountId,
+    /// Symbol from accounts.json / milo_accounts, if the faucet is known
+    symbol: Option<String>,
+    amount: u64,
+}
+
+/// What was found for one pool
+struct PoolReport {
+    pair: String,
+    pool_id: AccountId,
+    /// `None` when the account isn't on chain or couldn't be read
+    reserves: Option<Vec<Reserve>>,
+    error: Option<String>,
+}
+
+impl PoolReport {
+    /// Quote tokens per base token, from the reserves of the pair's two
+    /// tokens (`milo_musdc` → MUSDC per MILO)
+    fn price(&self) -> Option<(String, String, f64)> {
+        let (base, quote) = self.pair.split_once('_')?;
+        let amount_of = |symbol: &str| {
+            self.reserves
+                .as_ref()?
+                .iter()
+                .find(|r| r.symbol.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(symbol)))
+                .map(|r| r.amount)
+        };
+        let (base_amount, quote_amount) = (amount_of(base)?, amount_of(quote)?);
+        if base_amount == 0 {
+            return None;
+        }
+        Some((base.to_uppercase(), quote.to_uppercase(), quote_amount as f64 / base_amount as f64))
+    }
+}
+
+/// Base units as whole tokens, e.g. `12345678900` → `123.456789`
+fn format_amount(amount: u64) -> String {
+    let scale = 10u64.pow(TOKEN_DECIMALS);
+    let (whole, frac) = (amount / scale, amount % scale);
+    if frac == 0 {
+        return whole.to_string();
+    }
+    let frac = format!("{:0width$}", frac, width = TOKEN_DECIMALS as usize);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+pub async fn run(args: ReservesArgs) -> Result<()> {
+    let config = load_pools()?;
+    let faucets = load_faucets()?;
+
+    if !args.json {
+        println!("🔍 Pool Kontrol\n");
+        println!("📄 Pool ID'leri:");
+        for entry in &config.pools {
+            println!("   - {}: {}", entry.pair, entry.pool_id.to_hex());
+        }
+        println!();
+        println!("🔗 Explorer Linkleri:");
+        for entry in &config.pools {
+            println!("   - {}: https://testnet.midenscan.com/account/{}", entry.pair, entry.pool_id.to_hex());
+        }
+        println!();
+    }
+
+    let (mut client, _keystore) = init_client().await?;
+    if args.json {
+        client.sync_state().await?;
+    } else {
+        sync(&mut client).await?;
+    }
+
+    let mut reports = Vec::with_capacity(config.pools.len());
+    for entry in &config.pools {
+        if !args.json {
+            println!("🔍 {} Pool kontrol ediliyor...", entry.pair);
+        }
+        let report = check_pool(&mut client, entry.pool_id, &entry.pair, &faucets, !args.json).await;
+        if !args.json {
+            print_report(&report);
+        }
+        reports.push(report);
+    }
+
+    if args.json {
+        let pools: Vec<JsonValue> = reports.iter().map(report_json).collect();
+        println!("{}", serde_json::to_string_pretty(&json!({ "pools": pools }))?);
+    }
+
+    Ok(())
+}
+
+async fn check_pool(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    pool_name: &str,
+    faucets: &[(String, AccountId)],
+    verbose: bool,
+) -> PoolReport {
+    let mut report = PoolReport {
+        pair: pool_name.to_string(),
+        pool_id,
+        reserves: None,
+        error: None,
+    };
+
+    // Try to import account first
+    match client.import_account_by_id(pool_id).await {
+        Ok(_) if verbose => println!("   ✅ Account import edildi"),
+        Err(e) if verbose => println!("   ⚠️ Import hatası: {:?}", e),
+        _ => {}
+    }
+
+    match client.get_account(pool_id).await {
+        Ok(Some(record)) => {
+            let reserves = record
+                .account()
+                .vault()
+                .assets()
+                .filter_map(|asset| match asset {
+                    Asset::Fungible(fungible) => Some(Reserve {
+                        faucet_id: fungible.faucet_id(),
+                        symbol: faucets
+                            .iter()
+                            .find(|(_, id)| *id == fungible.faucet_id())
+                            .map(|(symbol, _)| symbol.clone()),
+                        amount: fungible.amount(),
+                    }),
+                    _ => None,
+                })
+                .collect();
+            report.reserves = Some(reserves);
+        }
+        Ok(None) => {}
+        Err(e) => report.error = Some(format!("{:?}", e)),
+    }
+    report
+}
+
+fn print_report(report: &PoolReport) {
+    match (&report.reserves, &report.error) {
+        (Some(reserves), _) => {
+            println!("   ✅ Account blockchain'de MEVCUT");
+            println!("      ID: {}", report.pool_id.to_hex());
+            if reserves.is_empty() {
+                println!("      ⚠️ Vault boş - likidite yok");
+            }
+            for reserve in reserves {
+                println!(
+                    "      Rezerv {} ({}): {} ({} base units)",
+                    reserve.symbol.as_deref().unwrap_or("?"),
+                    reserve.faucet_id.to_hex(),
+                    format_amount(reserve.amount),
+                    reserve.amount
+                );
+            }
+            if let Some((base, quote, price)) = report.price() {
+                println!("      Fiyat: 1 {} = {:.6} {} (1 {} = {:.6} {})", base, price, quote, quote, 1.0 / price, base);
+            }
+        }
+        (None, Some(e)) => {
+            println!("   ❌ Hata: {}", e);
+        }
+        (None, None) => {
+            println!("   ❌ Account blockchain'de BULUNAMADI!");
+            println!("   💡 Bu, pool'un henüz deploy edilmediğini gösterir.");
+            println!("      Önce `milo setup` ve `milo add-liquidity` çalıştırın.");
+        }
+    }
+    println!();
+}
+
+fn report_json(report: &PoolReport) -> JsonValue {
+    let reserves: Option<Vec<JsonValue>> = report.reserves.as_ref().map(|reserves| {
+        reserves
+            .iter()
+            .map(|r| {
+                json!({
+                    "faucet_id": r.faucet_id.to_hex(),
+                    "symbol": r.symbol,
+                    // Base units as a string: u64 doesn't survive JSON numbers in JS
+                    "amount": r.amount.to_string(),
+                    "amount_display": format_amount(r.amount),
+                })
+            })
+            .collect()
+    });
+    let price = report.price().map(|(base, quote, price)| {
+        json!({ "base": base, "quote": quote, "quote_per_base": price })
+    });
+    json!({
+        "pair": report.pair,
+        "pool_id": report.pool_id.to_hex(),
+        "exists": report.reserves.is_some(),
+        "reserves": reserves,
+        "price": price,
+        "error": report.error,
+    })
+}
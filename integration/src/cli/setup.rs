@@ -0,0 +1,352 @@
+//! `milo setup` - create the user wallet and MILO, MELO, MUSDC faucets
+//! Faucet'leri oluşturur, token mint eder ve accounts.json yazar
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use miden_client::{
+    Felt,
+    account::{Account, AccountBuilder, AccountStorageMode, AccountType, NetworkId},
+    asset::{FungibleAsset, TokenSymbol},
+    auth::AuthSecretKey,
+    keystore::FilesystemKeyStore,
+    note::NoteType,
+    transaction::TransactionRequestBuilder,
+};
+use miden_lib::account::{auth::AuthRpoFalcon512, faucets::BasicFungibleFaucet, wallets::BasicWallet};
+use milo_registry::{FAUCET_DECIMALS, FAUCET_MAX_SUPPLY};
+use rand::RngCore;
+use rand::rngs::StdRng;
+use tokio::time::sleep;
+
+use crate::faucet_config::{
+    load_faucets_config, save_faucets_config, FaucetEntry, FaucetsConfig, FAUCETS_CONFIG_FILE,
+};
+
+use super::{
+    consume_notes_for, init_client, save_accounts_config, wait_for_transaction, AccountsConfig,
+    load_milo_config, MidenClient,
+};
+
+const KEYS_DIR: &str = "keys";
+
+pub async fn run() -> Result<()> {
+    println!("🚀 Milo Swap Protocol - Setup Başlıyor...\n");
+
+    // Clean up old files
+    let milo_config = load_milo_config()?;
+    cleanup_old_files(&milo_config.store_path)?;
+
+    let (mut client, keystore) = init_client().await?;
+
+    // Step 1: Create user wallet
+    println!("📝 Adım 1: User Wallet oluşturuluyor...");
+    let (user_wallet, _user_key) = create_basic_account(&mut client, &keystore).await?;
+    println!("   ✅ User Wallet ID: {}", user_wallet.id().to_hex());
+    println!("   📍 Address: {}\n", user_wallet.id().to_bech32(NetworkId::Testnet));
+
+    // Steps 2-4: Create faucets
+    println!("📝 Adım 2: MILO Faucet oluşturuluyor...");
+    let milo_faucet = create_token_faucet(&mut client, &keystore, "MILO", FAUCET_MAX_SUPPLY).await?;
+    println!("   ✅ MILO Faucet ID: {}\n", milo_faucet.id().to_hex());
+
+    println!("📝 Adım 3: MELO Faucet oluşturuluyor...");
+    let melo_faucet = create_token_faucet(&mut client, &keystore, "MELO", FAUCET_MAX_SUPPLY).await?;
+    println!("   ✅ MELO Faucet ID: {}\n", melo_faucet.id().to_hex());
+
+    println!("📝 Adım 4: MUSDC Faucet oluşturuluyor...");
+    let musdc_faucet = create_token_faucet(&mut client, &keystore, "MUSDC", FAUCET_MAX_SUPPLY).await?;
+    println!("   ✅ MUSDC Faucet ID: {}\n", musdc_faucet.id().to_hex());
+
+    // Step 5: Mint tokens to user wallet
+    println!("📝 Adım 5: Token'lar Mint Ediliyor...");
+    mint_tokens(&mut client, &user_wallet, &milo_faucet, &melo_faucet, &musdc_faucet).await?;
+    println!();
+
+    let config = AccountsConfig {
+        user_wallet_id: user_wallet.id().to_hex(),
+        user_wallet_address: user_wallet.id().to_bech32(NetworkId::Testnet),
+        milo_faucet_id: milo_faucet.id().to_hex(),
+        milo_faucet_address: milo_faucet.id().to_bech32(NetworkId::Testnet),
+        melo_faucet_id: melo_faucet.id().to_hex(),
+        melo_faucet_address: melo_faucet.id().to_bech32(NetworkId::Testnet),
+        musdc_faucet_id: musdc_faucet.id().to_hex(),
+        musdc_faucet_address: musdc_faucet.id().to_bech32(NetworkId::Testnet),
+    };
+    save_accounts_config(&config)?;
+    update_faucet_server_ids(&config.milo_faucet_id, &config.melo_faucet_id, &config.musdc_faucet_id)?;
+    update_frontend_registry(&config)?;
+
+    println!("🎉 Setup Tamamlandı!");
+    println!("\n📁 Oluşturulan Dosyalar:");
+    println!("   - {} (keystore)", milo_config.keystore_path);
+    println!("   - {} (database)", milo_config.store_path);
+    println!("   - accounts.json (hesap ID'leri)");
+    println!("   - {} (faucet tanımları)", FAUCETS_CONFIG_FILE);
+    println!("   - keys/ (key yedekleri)");
+    println!("\n📝 Sonraki Adımlar:");
+    println!("   1. Pool contract'larını derle ve dağıt");
+    println!("   2. Likidite eklemek için `milo add-liquidity` çalıştır");
+    println!("   3. Swap öncesi kontrol için `milo swap` kullan");
+
+    Ok(())
+}
+
+/// Clean up old files
+fn cleanup_old_files(store_path: &str) -> Result<()> {
+    // Store silmiyoruz - mevcut hesapları koruyoruz!
+    // Sadece WAL/SHM dosyalarını temizleyelim
+    if let Some(db_path) = store_path.strip_suffix(".sqlite3") {
+        if Path::new(&format!("{}-wal", db_path)).exists() {
+            fs::remove_file(format!("{}-wal", db_path))?;
+        }
+        if Path::new(&format!("{}-shm", db_path)).exists() {
+            fs::remove_file(format!("{}-shm", db_path))?;
+        }
+    }
+
+    if !Path::new(KEYS_DIR).exists() {
+        fs::create_dir_all(KEYS_DIR)?;
+        println!("   📁 {} klasörü oluşturuldu", KEYS_DIR);
+    }
+
+    Ok(())
+}
+
+/// Creates a basic regular account
+async fn create_basic_account(
+    client: &mut MidenClient,
+    keystore: &FilesystemKeyStore<StdRng>,
+) -> Result<(Account, AuthSecretKey)> {
+    let mut init_seed = [0_u8; 32];
+    client.rng().fill_bytes(&mut init_seed);
+    
+    let key_pair = AuthSecretKey::new_rpo_falcon512();
+    
+    let builder = AccountBuilder::new(init_seed)
+        .account_type(AccountType::RegularAccountUpdatableCode)
+        .storage_mode(AccountStorageMode::Public)
+        .with_auth_component(AuthRpoFalcon512::new(key_pair.public_key().to_commitment()))
+        .with_component(BasicWallet);
+    
+    let account = builder.build().context("User wallet oluşturulamadı")?;
+    // true = blockchain'e commit et, false = sadece local kaydet
+    client.add_account(&account, true).await?;
+    
+    keystore.add_key(&key_pair).context("User wallet anahtarı keystore'a kaydedilemedi")?;
+    
+    client.sync_state().await?;
+    Ok((account, key_pair))
+}
+
+/// Creates a fungible token faucet
+pub(super) async fn create_token_faucet(
+    client: &mut MidenClient,
+    keystore: &FilesystemKeyStore<StdRng>,
+    symbol: &str,
+    max_supply: u64,
+) -> Result<Account> {
+    let mut init_seed = [0u8; 32];
+    client.rng().fill_bytes(&mut init_seed);
+    
+    let key_pair = AuthSecretKey::new_rpo_falcon512();
+    let token_symbol = TokenSymbol::new(symbol)
+        .map_err(|err| anyhow::anyhow!("{} token symbol oluşturulamadı: {}", symbol, err))?;
+    let max_supply_felt = Felt::new(max_supply);
+
+    let builder = AccountBuilder::new(init_seed)
+        .account_type(AccountType::FungibleFaucet)
+        .storage_mode(AccountStorageMode::Public)
+        .with_auth_component(AuthRpoFalcon512::new(key_pair.public_key().to_commitment()))
+        .with_component(
+            BasicFungibleFaucet::new(token_symbol, FAUCET_DECIMALS, max_supply_felt)
+                .with_context(|| format!("{} faucet bileşeni oluşturulamadı", symbol))?,
+        );
+    
+    let account = builder.build().with_context(|| format!("{} faucet hesabı oluşturulamadı", symbol))?;
+    // true = blockchain'e commit et, false = sadece local kaydet
+    client.add_account(&account, true).await?;
+    
+    keystore.add_key(&key_pair).with_context(|| format!("{} faucet anahtarı keystore'a kaydedilemedi", symbol))?;
+    
+    client.sync_state().await?;
+    Ok(account)
+}
+
+/// Mint the initial balances to the user wallet and consume the notes
+async fn mint_tokens(
+    client: &mut MidenClient,
+    user_wallet: &Account,
+    milo_faucet: &Account,
+    melo_faucet: &Account,
+    musdc_faucet: &Account,
+) -> Result<()> {
+    client.sync_state().await?;
+
+    // Whole tokens; amounts are minted in base units (× 10^8)
+    let mints = [
+        ("MILO", milo_faucet, 500_000u64),
+        ("MELO", melo_faucet, 500_000u64),
+        ("MUSDC", musdc_faucet, 1_000_000u64),
+    ];
+
+    for (symbol, faucet, tokens) in mints {
+        println!("   💰 {} {} mint ediliyor...", tokens, symbol);
+        let asset = FungibleAsset::new(faucet.id(), tokens * 100_000_000)
+            .with_context(|| format!("{} asset oluşturulamadı", symbol))?;
+
+        let tx_request = TransactionRequestBuilder::new()
+            .build_mint_fungible_asset(asset, user_wallet.id(), NoteType::Public, client.rng())
+            .with_context(|| format!("{} Mint tx oluşturulamadı", symbol))?;
+
+        let tx_id = client
+            .submit_new_transaction(faucet.id(), tx_request)
+            .await
+            .with_context(|| format!("{} Mint tx gönderilemedi", symbol))?;
+
+        wait_for_transaction(client, tx_id).await?;
+        println!("   ✅ {} {} mint edildi", tokens, symbol);
+    }
+
+    // Sync and consume notes
+    client.sync_state().await?;
+    sleep(Duration::from_secs(3)).await;
+    consume_notes_for(client, user_wallet.id()).await?;
+
+    Ok(())
+}
+
+/// Write faucet-server/faucets.json; caps already set there are kept
+fn update_faucet_server_ids(milo_id: &str, melo_id: &str, musdc_id: &str) -> Result<()> {
+    let existing = load_faucets_config(FAUCETS_CONFIG_FILE).unwrap_or_default();
+    // (symbol, id, default daily cap, default max per request) - MUSDC is the
+    // stablecoin side of every pool, so testers need far more of it
+    let faucets = [
+        ("MILO", milo_id, 10_00000000, None),
+        ("MELO", melo_id, 10_00000000, None),
+        ("MUSDC", musdc_id, 1000_00000000, Some(100_00000000)),
+    ];
+
+    let config = FaucetsConfig {
+        faucets: faucets
+            .into_iter()
+            .map(|(symbol, faucet_id, daily_cap, max_per_request)| {
+                let (daily_cap, max_per_request, ip_daily_cap) = existing
+                    .get(symbol)
+                    .map_or((daily_cap, max_per_request, None), |f| (f.daily_cap, f.max_per_request, f.ip_daily_cap));
+                FaucetEntry {
+                    symbol: symbol.to_string(),
+                    faucet_id: faucet_id.to_string(),
+                    decimals: FAUCET_DECIMALS.into(),
+                    daily_cap,
+                    max_per_request,
+                    ip_daily_cap,
+                }
+            })
+            .collect(),
+    };
+    save_faucets_config(FAUCETS_CONFIG_FILE, &config)
+        .with_context(|| format!("{} kaydedilemedi", FAUCETS_CONFIG_FILE))?;
+
+    println!("   💾 Faucet IDs güncellendi: {}", FAUCETS_CONFIG_FILE);
+    Ok(())
+}
+
+/// Update frontend/src/tokenRegistry.ts
+fn update_frontend_registry(config: &AccountsConfig) -> Result<()> {
+    let registry_content = format!(
+        r#"// Auto-generated token registry - Updated with real faucet IDs
+// Generated by `milo setup`
+import {{ FAUCET_URL }} from './config/api';
+
+export interface TokenInfo {{
+  symbol: string;
+  name: string;
+  faucetId: string;
+  decimals: number;
+  logo: string;
+  color: string;
+  faucetApiUrl?: string;
+  legacyFaucetIds?: string[];
+}}
+
+export const CONFIG: {{ apiUrl: string; faucetServerUrl: string; userWalletId: string; userWalletAddress: string; tokens: Record<string, TokenInfo> }} = {{
+  apiUrl: FAUCET_URL,
+  faucetServerUrl: FAUCET_URL,
+  userWalletId: '{}',
+  userWalletAddress: '{}',
+  tokens: {{
+    MILO: {{
+      symbol: 'MILO',
+      name: 'Milo Token',
+      faucetId: '{}',
+      decimals: 8,
+      logo: '/tokens/milo.svg',
+      color: '#6366f1',
+      faucetApiUrl: FAUCET_URL,
+    }},
+    MELO: {{
+      symbol: 'MELO',
+      name: 'Melo Token',
+      faucetId: '{}',
+      decimals: 8,
+      logo: '/tokens/melo.svg',
+      color: '#10b981',
+      faucetApiUrl: FAUCET_URL,
+    }},
+    MUSDC: {{
+      symbol: 'MUSDC',
+      name: 'Milo USDC',
+      faucetId: '{}',
+      decimals: 8,
+      logo: '/tokens/usdc.svg',
+      color: '#2563eb',
+      faucetApiUrl: FAUCET_URL,
+    }},
+    MIDEN: {{
+      symbol: 'MIDEN',
+      name: 'Miden Network',
+      faucetId: '0x54bf4e12ef20082070758b022456c7',
+      decimals: 6,
+      logo: '/tokens/miden.svg',
+      color: '#ff6b35',
+    }},
+  }},
+}};
+
+export const TOKEN_LIST = Object.values(CONFIG.tokens);
+export const TOKEN_SYMBOLS = TOKEN_LIST.map((t) => t.symbol);
+
+// Helper function to get token metadata by faucet ID
+export function getTokenMetadata(faucetId: string): {{ symbol: string; decimals: number }} | undefined {{
+  const normalizedFaucetId = faucetId.toLowerCase().replace(/^0x/, '');
+  for (const token of TOKEN_LIST) {{
+    const tokenFaucetId = token.faucetId.toLowerCase().replace(/^0x/, '');
+    if (tokenFaucetId === normalizedFaucetId) {{
+      return {{ symbol: token.symbol, decimals: token.decimals }};
+    }}
+  }}
+  return undefined;
+}}
+
+// Helper function to get token by symbol
+export function getTokenBySymbol(symbol: string) {{
+  const upperSymbol = symbol.toUpperCase();
+  return TOKEN_LIST.find(t => t.symbol.toUpperCase() === upperSymbol);
+}}
+"#,
+        config.user_wallet_id,
+        config.user_wallet_address,
+        config.milo_faucet_id,
+        config.melo_faucet_id,
+        config.musdc_faucet_id
+    );
+
+    fs::write("frontend/src/tokenRegistry.ts", registry_content)
+        .context("frontend/src/tokenRegistry.ts kaydedilemedi")?;
+
+    println!("   💾 Frontend registry güncellendi: frontend/src/tokenRegistry.ts");
+    Ok(())
+}
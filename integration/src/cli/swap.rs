@@ -0,0 +1,208 @@
+//! `milo swap` - send a P2ID swap note to a pool and hand it to the swap daemon
+//!
+//! The note carries the input asset from the wallet to the pool; the swap
+//! daemon executes the swap once it is registered at `/track_note` with the
+//! swap metadata (pair, amount, minimum output). The expected output is
+//! quoted locally with the same curve math the daemon uses.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use miden_client::{account::AccountId, asset::Asset};
+use milo_sdk::SwapTxBuilder;
+
+use super::{
+    init_client, load_pools_or_defaults, resolve_faucet_id, resolve_user_wallet_id, sync, wait_for_transaction,
+};
+use crate::curve::curve_amount_out;
+use crate::pool_config::{load_pool_curves_config, POOL_CURVES_CONFIG_PATH};
+
+/// The swap daemon's fee outside volatile markets; a higher dynamic fee is
+/// absorbed by the slippage allowance
+const BASE_FEE_BPS: u64 = 10;
+
+#[derive(Args, Debug)]
+pub struct SwapArgs {
+    /// Pool account ID (hex)
+    #[arg(long)]
+    pub pool_id: String,
+
+    /// Input token symbol (MILO, MELO, MUSDC)
+    #[arg(long)]
+    pub token_in: String,
+
+    /// Amount of tokens to swap (base units)
+    #[arg(long)]
+    pub amount: u64,
+
+    /// Wallet account ID (hex); defaults to the user wallet in accounts.json
+    #[arg(long)]
+    pub wallet_id: Option<String>,
+
+    /// Smallest acceptable output (base units); defaults to the quote minus `--slippage-bps`
+    #[arg(long)]
+    pub min_amount_out: Option<u64>,
+
+    /// Allowed shortfall against the quote when `--min-amount-out` is omitted
+    #[arg(long, default_value_t = 50)]
+    pub slippage_bps: u64,
+
+    /// Swap daemon base URL
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    pub daemon_url: String,
+
+    /// Only check the balance and print the quote
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub async fn run(args: SwapArgs) -> Result<()> {
+    println!("=== Milo Swap Token Exchange ===\n");
+
+    if args.slippage_bps >= 10_000 {
+        anyhow::bail!("--slippage-bps 10000'den küçük olmalı");
+    }
+    let pool_id = AccountId::from_hex(&args.pool_id).context("Geçersiz pool ID")?;
+    let wallet_id = match args.wallet_id {
+        Some(id) => AccountId::from_hex(&id).context("Geçersiz wallet ID")?,
+        None => resolve_user_wallet_id()?,
+    };
+
+    // The pool's pair (e.g. `milo_musdc`) gives the output token; IDs come
+    // from what `milo setup` / `milo add-liquidity` wrote, so a fresh setup
+    // is picked up without a rebuild
+    let pools = load_pools_or_defaults()?;
+    let entry = pools
+        .pools
+        .iter()
+        .find(|p| p.pool_id == pool_id)
+        .with_context(|| format!("Pool {} pools.json'da yok", pool_id.to_hex()))?;
+    let token_in = args.token_in.to_uppercase();
+    let (base, quote) = entry
+        .pair
+        .split_once('_')
+        .with_context(|| format!("Geçersiz pair: {}", entry.pair))?;
+    let token_out = if token_in.eq_ignore_ascii_case(base) {
+        quote.to_uppercase()
+    } else if token_in.eq_ignore_ascii_case(quote) {
+        base.to_uppercase()
+    } else {
+        return Err(anyhow::anyhow!("{} bu pool'da işlem görmüyor ({})", token_in, entry.pair));
+    };
+
+    let token_in_id = resolve_faucet_id(&token_in)?;
+    let token_out_id = resolve_faucet_id(&token_out)?;
+    let curve = load_pool_curves_config(POOL_CURVES_CONFIG_PATH, &pools)?.curve(&entry.pair);
+
+    println!("Pool ID: {} ({}, {})", pool_id.to_hex(), entry.pair, curve);
+    println!("Wallet ID: {}", wallet_id.to_hex());
+    println!("Token In:  {} ({})", token_in, token_in_id.to_hex());
+    println!("Token Out: {} ({})", token_out, token_out_id.to_hex());
+    println!();
+
+    let (mut client, _keystore) = init_client().await?;
+    sync(&mut client).await?;
+
+    println!("Fetching wallet account...");
+    let wallet = client
+        .get_account(wallet_id)
+        .await?
+        .context("Wallet bu store'da bulunamadı")?;
+
+    let balance: u64 = wallet
+        .account()
+        .vault()
+        .assets()
+        .filter_map(|asset| match asset {
+            Asset::Fungible(fa) if fa.faucet_id() == token_in_id => Some(fa.amount()),
+            _ => None,
+        })
+        .sum();
+    println!("Balance: {} {}", balance, token_in);
+
+    if balance < args.amount {
+        println!("\n⚠️  Wallet doesn't have enough {} tokens!", token_in);
+        println!("   Please mint tokens first using `milo mint` or the faucet.");
+        return Ok(());
+    }
+
+    println!("✓ Sufficient balance found");
+
+    // ── quote at the pool's current reserves ────────────────────────────
+    let pool = client
+        .get_account(pool_id)
+        .await?
+        .context("Pool bu store'da bulunamadı")?;
+    let reserve_of = |faucet_id: AccountId| -> u64 {
+        pool.account()
+            .vault()
+            .assets()
+            .filter_map(|asset| match asset {
+                Asset::Fungible(fa) if fa.faucet_id() == faucet_id => Some(fa.amount()),
+                _ => None,
+            })
+            .sum()
+    };
+    let (reserve_in, reserve_out) = (reserve_of(token_in_id), reserve_of(token_out_id));
+    if reserve_in == 0 || reserve_out == 0 {
+        anyhow::bail!("Pool'da likidite yok ({} {} / {} {})", reserve_in, token_in, reserve_out, token_out);
+    }
+    let expected_out = curve_amount_out(curve, args.amount, reserve_in, reserve_out, BASE_FEE_BPS);
+    if expected_out == 0 || expected_out >= reserve_out as u128 {
+        anyhow::bail!("{} {} için geçerli bir çıktı yok (rezerv {} {})", args.amount, token_in, reserve_out, token_out);
+    }
+    let expected_out = expected_out as u64;
+    let min_amount_out = args.min_amount_out.unwrap_or(
+        (expected_out as u128 * (10_000 - args.slippage_bps) as u128 / 10_000) as u64,
+    );
+
+    println!("\n=== Swap Summary ===");
+    println!("Input:    {} {}", args.amount, token_in);
+    println!("Expected: {} {} (at {} bps fee)", expected_out, token_out, BASE_FEE_BPS);
+    println!("Minimum:  {} {}", min_amount_out, token_out);
+    println!("Reserves: {} {} / {} {}", reserve_in, token_in, reserve_out, token_out);
+    println!("Pool:     {}", pool_id.to_hex());
+
+    if args.dry_run {
+        println!("\n💡 --dry-run: nothing was sent.");
+        return Ok(());
+    }
+
+    // ── send the input to the pool as a P2ID note ───────────────────────
+    println!("\n📤 Swap notu oluşturuluyor...");
+    let builder = SwapTxBuilder::new(pool_id, token_in_id, token_out_id, args.amount, min_amount_out, wallet_id);
+    let swap = builder.build(client.rng())?;
+    let note_id = swap.note_id.to_hex();
+    let tx_request = swap.request.build()?;
+    let tx_id = client
+        .submit_new_transaction(wallet_id, tx_request)
+        .await
+        .context("Swap tx gönderilemedi")?;
+    wait_for_transaction(&mut client, tx_id).await?;
+
+    // ── register the note so the daemon knows what to pay out ───────────
+    println!("🔔 Swap daemon'a bildiriliyor ({})...", args.daemon_url);
+    let timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as u64;
+    let response = reqwest::Client::new()
+        .post(format!("{}/track_note", args.daemon_url.trim_end_matches('/')))
+        .json(&builder.track_note_body(swap.note_id, timestamp_ms))
+        .send()
+        .await
+        .context("Swap daemon'a ulaşılamadı")?;
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        anyhow::bail!(
+            "Swap daemon notu reddetti ({}): {}. Not {} pool'a gönderildi; daemon'u düzeltip /track_note'u tekrar çağırın",
+            status, body["error"].as_str().unwrap_or("bilinmeyen hata"), note_id
+        );
+    }
+
+    println!("\n✅ Swap notu gönderildi!");
+    println!("   Tx ID:   {}", tx_id.to_hex());
+    println!("   Note ID: {}", note_id);
+    println!("💡 Daemon notu tükettiğinde {} cüzdana P2ID olarak gelir.", token_out);
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! Swap output for each pool curve
+//!
+//! Pools trade on constant product unless pool_config.json gives them a
+//! stable-swap curve. Both take the fee from the input before pricing, so
+//! `fee_bps` means the same thing on either curve.
+
+use crate::pool_config::AmmCurve;
+
+/// Newton iterations before the invariant solvers give up
+const MAX_ITERATIONS: usize = 255;
+
+/// Output for `amount_in` on `curve`, before the reserve guard
+pub fn curve_amount_out(curve: AmmCurve, amount_in: u64, reserve_in: u64, reserve_out: u64, fee_bps: u64) -> u128 {
+    match curve {
+        AmmCurve::ConstantProduct => amm_amount_out(amount_in, reserve_in, reserve_out, fee_bps),
+        AmmCurve::StableSwap { amplification } => {
+            calculate_stable_swap_output(reserve_in, reserve_out, amount_in, fee_bps, amplification) as u128
+        }
+    }
+}
+
+/// Constant-product output for `amount_in` after a fee of `fee_bps`
+/// (5 = 0.05%, 10 = 0.1%, 30 = 0.3%). The formula lives in milo-sdk, so
+/// client-side quotes match what the daemon executes.
+pub fn amm_amount_out(amount_in: u64, reserve_in: u64, reserve_out: u64, fee_bps: u64) -> u128 {
+    milo_sdk::estimate_output(reserve_in, reserve_out, amount_in, fee_bps) as u128
+}
+
+/// Stable-swap output for selling `amount_in` of token A into a two-token
+/// pool, after a fee of `fee_bps`. Uses the Curve invariant with n = 2:
+///
+/// A*n^n*sum + D = A*D*n^n + D^(n+1) / (n^n * prod)
+///
+/// D is solved for the current reserves, then the new B reserve y for the
+/// A reserve after the trade. Output is rounded down by one unit in favour of
+/// the pool. Returns 0 when either reserve is empty or the solvers overflow
+/// or fail to converge.
+pub fn calculate_stable_swap_output(reserve_a: u64, reserve_b: u64, amount_in: u64, fee_bps: u64, amp: u64) -> u64 {
+    stable_swap_output(reserve_a, reserve_b, amount_in, fee_bps, amp).unwrap_or(0)
+}
+
+fn stable_swap_output(reserve_a: u64, reserve_b: u64, amount_in: u64, fee_bps: u64, amp: u64) -> Option<u64> {
+    if amp == 0 {
+        return None;
+    }
+    let ann = amp as u128 * 4;
+    let x = reserve_a as u128;
+    let y = reserve_b as u128;
+
+    let d = stable_swap_d(x, y, ann)?;
+    let amount_in_after_fee = amount_in as u128 * (10000 - fee_bps as u128) / 10000;
+    let new_y = stable_swap_y(x + amount_in_after_fee, d, ann)?;
+
+    let out = y.checked_sub(new_y)?.saturating_sub(1);
+    u64::try_from(out).ok()
+}
+
+/// Solve the invariant for D given both reserves (Newton's method from D = x + y)
+fn stable_swap_d(x: u128, y: u128, ann: u128) -> Option<u128> {
+    if x == 0 || y == 0 {
+        return None;
+    }
+    let sum = x + y;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D_P = D^3 / (4xy)
+        let d_p = d.checked_mul(d)? / (x * 2);
+        let d_p = d_p.checked_mul(d)? / (y * 2);
+        let previous = d;
+        let numerator = ann.checked_mul(sum)?.checked_add(d_p * 2)?.checked_mul(d)?;
+        let denominator = (ann - 1).checked_mul(d)?.checked_add(d_p.checked_mul(3)?)?;
+        d = numerator / denominator;
+        if d.abs_diff(previous) <= 1 {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Solve the invariant for the other reserve given one reserve `x` and D
+fn stable_swap_y(x: u128, d: u128, ann: u128) -> Option<u128> {
+    // y^2 + (b - D) y = c, with b = x + D/Ann and c = D^3 / (4x * Ann)
+    let c = d.checked_mul(d)? / (x * 2);
+    let c = c.checked_mul(d)? / (ann * 2);
+    let b = x + d / ann;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let previous = y;
+        let denominator = (y * 2 + b).checked_sub(d).filter(|den| *den > 0)?;
+        y = y.checked_mul(y)?.checked_add(c)? / denominator;
+        if y.abs_diff(previous) <= 1 {
+            return Some(y);
+        }
+    }
+    None
+}
+
+/// Marginal price of the input token in output-token units, fee excluded
+pub fn spot_price(curve: AmmCurve, reserve_in: u64, reserve_out: u64) -> f64 {
+    let (x, y) = (reserve_in as f64, reserve_out as f64);
+    match curve {
+        AmmCurve::ConstantProduct => y / x,
+        AmmCurve::StableSwap { amplification } => {
+            // Ratio of the invariant's partial derivatives: (Ann + D_P/x) / (Ann + D_P/y)
+            let ann = amplification as f64 * 4.0;
+            let d = stable_swap_d(reserve_in as u128, reserve_out as u128, amplification as u128 * 4)
+                .map(|d| d as f64)
+                .unwrap_or(x + y);
+            let d_p = d * d * d / (4.0 * x * y);
+            (ann + d_p / x) / (ann + d_p / y)
+        }
+    }
+}
+
+/// Price impact of selling `amount_in` into `reserve_in`, in basis points:
+/// amount_in * 10000 / (reserve_in + amount_in). On constant product with no
+/// fee this is exactly how far the output falls short of the spot price.
+pub fn price_impact_bps(amount_in: u64, reserve_in: u64) -> u64 {
+    let total = reserve_in as u128 + amount_in as u128;
+    if total == 0 {
+        return 0;
+    }
+    (amount_in as u128 * 10_000 / total) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STABLE: AmmCurve = AmmCurve::StableSwap { amplification: 100 };
+
+    #[test]
+    fn stable_swap_is_near_one_to_one_on_balanced_reserves() {
+        // No fee: only the one unit rounded in favour of the pool is lost
+        assert_eq!(calculate_stable_swap_output(1_000_000, 1_000_000, 10_000, 0, 100), 9_999);
+        assert_eq!(calculate_stable_swap_output(1_000_000, 1_000_000, 10_000, 30, 100), 9_969);
+    }
+
+    #[test]
+    fn stable_swap_slips_less_than_constant_product() {
+        let stable = curve_amount_out(STABLE, 100_000, 1_000_000, 1_000_000, 30);
+        let constant = curve_amount_out(AmmCurve::ConstantProduct, 100_000, 1_000_000, 1_000_000, 30);
+        assert_eq!(stable, 99_650);
+        assert_eq!(constant, 90_661);
+        // A lower amplification is closer to constant product
+        let flatter = curve_amount_out(AmmCurve::StableSwap { amplification: 10 }, 100_000, 1_000_000, 1_000_000, 30);
+        assert!(constant < flatter && flatter < stable, "{} {} {}", constant, flatter, stable);
+    }
+
+    #[test]
+    fn stable_swap_never_drains_the_pool() {
+        let out = calculate_stable_swap_output(1_000_000, 1_000_000, 2_000_000, 30, 100);
+        assert_eq!(out, 998_331);
+        let out = calculate_stable_swap_output(1_000_000, 1_000_000, u64::MAX / 4, 0, 100);
+        assert!(out < 1_000_000, "{}", out);
+    }
+
+    #[test]
+    fn stable_swap_degenerate_inputs_give_nothing() {
+        assert_eq!(calculate_stable_swap_output(0, 1_000_000, 1_000, 30, 100), 0);
+        assert_eq!(calculate_stable_swap_output(1_000_000, 0, 1_000, 30, 100), 0);
+        assert_eq!(calculate_stable_swap_output(1_000_000, 1_000_000, 1_000, 30, 0), 0);
+        assert_eq!(calculate_stable_swap_output(1_000_000, 1_000_000, 0, 30, 100), 0);
+    }
+
+    #[test]
+    fn spot_price_per_curve() {
+        assert_eq!(spot_price(AmmCurve::ConstantProduct, 1_000, 2_000), 2.0);
+        assert!((spot_price(STABLE, 1_000_000, 1_000_000) - 1.0).abs() < 1e-12);
+        // Token B is scarcer, so A buys a little less of it, but far more
+        // than constant product would give
+        let stable = spot_price(STABLE, 1_200_000, 800_000);
+        assert!(stable < 1.0 && stable > 0.9, "{}", stable);
+        assert!(stable > spot_price(AmmCurve::ConstantProduct, 1_200_000, 800_000));
+    }
+
+    #[test]
+    fn price_impact_in_basis_points() {
+        assert_eq!(price_impact_bps(0, 0), 0);
+        assert_eq!(price_impact_bps(1_000, 9_000), 1_000);
+        assert_eq!(price_impact_bps(1_000, 0), 10_000);
+        assert_eq!(price_impact_bps(u64::MAX, u64::MAX), 5_000);
+    }
+}
@@ -0,0 +1,118 @@
+//! Canonical load/save for faucet-server/faucets.json
+//!
+//! `milo setup` writes the faucets it creates here and the faucet server
+//! reads the file at startup (and again on /admin/reload_faucets), so
+//! changing faucets no longer needs a rebuild. Loading validates every entry;
+//! saving refuses invalid configs and writes atomically (temp file + rename).
+//! Amounts are in base units.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use miden_client::account::AccountId;
+use serde::{Deserialize, Serialize};
+
+/// Where `milo setup` writes the file, relative to the repository root
+pub const FAUCETS_CONFIG_FILE: &str = "faucet-server/faucets.json";
+
+/// Without an `ip_daily_cap`, one IP may claim this many accounts' daily caps
+pub const DEFAULT_IP_CAP_MULTIPLIER: u64 = 3;
+
+/// One mintable token and its limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaucetEntry {
+    pub symbol: String,
+    pub faucet_id: String,
+    pub decimals: u64,
+    /// Per account per day; 0 disables minting (the admin account excepted)
+    pub daily_cap: u64,
+    /// Largest single claim; defaults to the daily cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_request: Option<u64>,
+    /// Per client IP per day, shared by every account claiming from it;
+    /// defaults to `DEFAULT_IP_CAP_MULTIPLIER` times the daily cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_daily_cap: Option<u64>,
+}
+
+impl FaucetEntry {
+    pub fn max_per_request(&self) -> u64 {
+        self.max_per_request.unwrap_or(self.daily_cap)
+    }
+
+    pub fn ip_daily_cap(&self) -> u64 {
+        self.ip_daily_cap.unwrap_or(self.daily_cap.saturating_mul(DEFAULT_IP_CAP_MULTIPLIER))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaucetsConfig {
+    pub faucets: Vec<FaucetEntry>,
+}
+
+impl FaucetsConfig {
+    pub fn get(&self, symbol: &str) -> Option<&FaucetEntry> {
+        self.faucets.iter().find(|f| f.symbol == symbol)
+    }
+}
+
+/// Parse and validate faucets.json contents
+pub fn parse_faucets_config(json: &str) -> Result<FaucetsConfig> {
+    let config: FaucetsConfig = serde_json::from_str(json)
+        .context("faucets.json must be {\"faucets\": [{symbol, faucet_id, decimals, daily_cap}, ...]}")?;
+    validate_faucets_config(&config)?;
+    Ok(config)
+}
+
+/// Check every entry: symbol set and unique, faucet ID valid and unique
+pub fn validate_faucets_config(config: &FaucetsConfig) -> Result<()> {
+    if config.faucets.is_empty() {
+        anyhow::bail!("No faucets listed");
+    }
+
+    let mut seen_ids: Vec<AccountId> = Vec::new();
+    for (i, entry) in config.faucets.iter().enumerate() {
+        if entry.symbol.is_empty() || entry.symbol != entry.symbol.to_uppercase() {
+            anyhow::bail!("Faucet #{} symbol `{}` must be non-empty and uppercase", i + 1, entry.symbol);
+        }
+        if config.faucets[..i].iter().any(|f| f.symbol == entry.symbol) {
+            anyhow::bail!("Faucet `{}` is listed more than once", entry.symbol);
+        }
+        let faucet_id = AccountId::from_hex(&entry.faucet_id).with_context(|| {
+            format!("Faucet `{}` id `{}` is not a valid account id", entry.symbol, entry.faucet_id)
+        })?;
+        if seen_ids.contains(&faucet_id) {
+            anyhow::bail!("Faucet id {} is listed more than once", faucet_id.to_hex());
+        }
+        seen_ids.push(faucet_id);
+        if entry.max_per_request.is_some_and(|max| max > entry.daily_cap) {
+            anyhow::bail!("Faucet `{}` max_per_request is above its daily_cap", entry.symbol);
+        }
+        if entry.ip_daily_cap.is_some_and(|cap| cap < entry.max_per_request()) {
+            anyhow::bail!("Faucet `{}` ip_daily_cap is below its max_per_request", entry.symbol);
+        }
+    }
+
+    Ok(())
+}
+
+/// Load and validate faucets.json
+pub fn load_faucets_config(path: impl AsRef<Path>) -> Result<FaucetsConfig> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path).with_context(|| format!("{} not found", path.display()))?;
+    parse_faucets_config(&json).with_context(|| format!("{} is invalid", path.display()))
+}
+
+/// Validate and write faucets.json atomically
+pub fn save_faucets_config(path: impl AsRef<Path>, config: &FaucetsConfig) -> Result<()> {
+    let path = path.as_ref();
+    validate_faucets_config(config).context("Refusing to save invalid faucets config")?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(config)?)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+    Ok(())
+}
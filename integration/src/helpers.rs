@@ -8,15 +8,17 @@ use miden_client::{
         component::{AuthRpoFalcon512, BasicWallet},
         Account, AccountStorageMode, AccountType,
     },
+    asset::FungibleAsset,
     auth::{AuthSecretKey, PublicKeyCommitment},
     builder::ClientBuilder,
     crypto::rpo_falcon512::SecretKey,
     keystore::FilesystemKeyStore,
-    rpc::{Endpoint, GrpcClient},
+    rpc::GrpcClient,
     Client,
     transaction::TransactionRequestBuilder,
 };
 use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use milo_config::{MiloConfig, Service};
 use miden_objects::account::AccountBuilder;
 use rand::rngs::StdRng;
 
@@ -28,20 +30,20 @@ pub struct ClientSetup {
 
 /// Initializes test infrastructure with client and keystore
 pub async fn setup_client() -> Result<ClientSetup> {
-    // Initialize RPC connection to testnet
-    // Using official Miden Testnet RPC
-    let endpoint = Endpoint::testnet();
+    // Node, keystore and store of the CLI (milo.toml `[cli]`, else testnet,
+    // keystore/ and store.sqlite3)
+    let config = MiloConfig::load(Service::Cli)?;
+    let endpoint = config.endpoint()?;
     let timeout_ms = 300_000; // Increased timeout to 300 seconds (5 minutes)
     let rpc_client = Arc::new(GrpcClient::new(&endpoint, timeout_ms));
 
-    // Initialize keystore - use main project keystore (absolute path)
-    let keystore_path = std::path::PathBuf::from("/Users/nihataltuntas/Desktop/projeler/milo/milo-swap/keystore");
+    let keystore_path = std::path::PathBuf::from(&config.keystore_path);
     let keystore = Arc::new(
         FilesystemKeyStore::<StdRng>::new(keystore_path)
             .context("Failed to initialize keystore")?,
     );
 
-    let store_path = std::path::PathBuf::from("./store.sqlite3");
+    let store_path = std::path::PathBuf::from(&config.store_path);
 
     let client = ClientBuilder::new()
         .rpc(rpc_client)
@@ -134,3 +136,12 @@ pub async fn deploy_account(
 
     Ok(())
 }
+
+/// Fungible asset amount as u64, with an error naming the faucet and raw amount
+pub fn reserve_amount(asset: &FungibleAsset) -> Result<u64> {
+    let raw = asset.amount();
+    raw.try_into().map_err(|_| anyhow::anyhow!(
+        "Reserve amount {} for faucet {} does not fit in u64",
+        raw, asset.faucet_id().to_hex()
+    ))
+}
@@ -0,0 +1,275 @@
+//! Per-account token movements recorded by each service
+//!
+//! The faucet, swap daemon and liquidity daemon each append the movements
+//! they cause in a user's wallet to their own JSONL file: mints, swap legs,
+//! liquidity deposits, refunds and withdrawals. `consistency_check` sums them
+//! into the holdings each account should have and compares that with the
+//! chain.
+//!
+//! Daemons write a line once the service considers the movement final (the
+//! transaction committed, or was credited on timeout); the faucet writes it
+//! when the mint is submitted. An in-flight swap or deposit therefore shows up
+//! as a temporary discrepancy.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+pub const FAUCET_LEDGER_FILE: &str = "faucet_ledger.jsonl";
+pub const SWAP_LEDGER_FILE: &str = "swap_ledger.jsonl";
+pub const LIQUIDITY_LEDGER_FILE: &str = "liquidity_ledger.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Tokens sent to the account
+    In,
+    /// Tokens taken from the account
+    Out,
+}
+
+/// One movement of one token in or out of a user account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub timestamp: u64,
+    /// `faucet`, `swap` or `liquidity`
+    pub service: String,
+    /// `mint`, `swap_in`, `swap_out`, `swap_rollback`, `deposit`,
+    /// `deposit_rollback`, `refund` or `withdraw`
+    pub kind: String,
+    pub account_id: String,
+    pub faucet_id: String,
+    pub amount: u64,
+    pub direction: Direction,
+    /// Transaction or note ID the movement came from
+    pub reference: String,
+}
+
+impl LedgerEntry {
+    pub fn new(service: &str, kind: &str, account_id: &str, faucet_id: &str, amount: u64, direction: Direction, reference: &str) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            service: service.to_string(),
+            kind: kind.to_string(),
+            account_id: account_id.to_lowercase(),
+            faucet_id: faucet_id.to_lowercase(),
+            amount,
+            direction,
+            reference: reference.to_string(),
+        }
+    }
+
+    /// Change to the account's holdings
+    pub fn signed_amount(&self) -> i128 {
+        match self.direction {
+            Direction::In => self.amount as i128,
+            Direction::Out => -(self.amount as i128),
+        }
+    }
+}
+
+/// Append an entry to a ledger file; failures are logged, never fatal
+pub fn append_entry(path: &str, entry: &LedgerEntry) {
+    let written = serde_json::to_string(entry)
+        .map_err(std::io::Error::other)
+        .and_then(|line| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut f| writeln!(f, "{}", line))
+        });
+    if let Err(e) = written {
+        println!("⚠️  Failed to write ledger {}: {}", path, e);
+    }
+}
+
+/// Read a ledger file. A missing file is an empty ledger; malformed lines
+/// are skipped and counted.
+pub fn load_entries(path: &str) -> Result<(Vec<LedgerEntry>, usize)> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e).with_context(|| format!("{} okunamadı", path)),
+    };
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in data.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((entries, skipped))
+}
+
+/// (account_id, faucet_id) → net amount the ledgers say the account holds
+pub type Holdings = BTreeMap<(String, String), i128>;
+
+pub fn expected_holdings(entries: &[LedgerEntry]) -> Holdings {
+    let mut holdings = Holdings::new();
+    for entry in entries {
+        *holdings
+            .entry((entry.account_id.clone(), entry.faucet_id.clone()))
+            .or_insert(0) += entry.signed_amount();
+    }
+    holdings
+}
+
+/// A token balance that differs from what the ledgers expect
+#[derive(Debug, Clone, Serialize)]
+pub struct Discrepancy {
+    pub account_id: String,
+    pub faucet_id: String,
+    pub expected: i128,
+    pub actual: i128,
+    pub difference: i128,
+    /// The ledger entries behind `expected`, for drill-down
+    pub entries: Vec<LedgerEntry>,
+}
+
+/// Compare expected against actual holdings. A difference is reported when it
+/// exceeds `tolerance_bps` of the larger of the two amounts. Pairs missing on
+/// one side count as zero.
+pub fn find_discrepancies(
+    entries: &[LedgerEntry],
+    expected: &Holdings,
+    actual: &Holdings,
+    tolerance_bps: u64,
+) -> Vec<Discrepancy> {
+    let mut keys: Vec<&(String, String)> = expected.keys().chain(actual.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut discrepancies = Vec::new();
+    for key in keys {
+        let expected_amount = expected.get(key).copied().unwrap_or(0);
+        let actual_amount = actual.get(key).copied().unwrap_or(0);
+        let difference = actual_amount - expected_amount;
+        let scale = expected_amount.abs().max(actual_amount.abs());
+        if difference.abs() * 10_000 <= scale * tolerance_bps as i128 {
+            continue;
+        }
+        discrepancies.push(Discrepancy {
+            account_id: key.0.clone(),
+            faucet_id: key.1.clone(),
+            expected: expected_amount,
+            actual: actual_amount,
+            difference,
+            entries: entries
+                .iter()
+                .filter(|e| e.account_id == key.0 && e.faucet_id == key.1)
+                .cloned()
+                .collect(),
+        });
+    }
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "0xalice";
+    const BOB: &str = "0xbob";
+    const MILO: &str = "0xmilo";
+    const MUSDC: &str = "0xmusdc";
+
+    fn entry(service: &str, kind: &str, account: &str, faucet: &str, amount: u64, direction: Direction) -> LedgerEntry {
+        LedgerEntry::new(service, kind, account, faucet, amount, direction, &format!("0xtx-{}-{}", kind, amount))
+    }
+
+    /// Alice claims MILO, swaps part of it for MUSDC and deposits some MUSDC;
+    /// Bob claims MUSDC and gets a refund of a deposit
+    fn fixture() -> Vec<LedgerEntry> {
+        vec![
+            entry("faucet", "mint", ALICE, MILO, 1_000, Direction::In),
+            entry("swap", "swap_in", ALICE, MILO, 400, Direction::Out),
+            entry("swap", "swap_out", ALICE, MUSDC, 190, Direction::In),
+            entry("liquidity", "deposit", ALICE, MUSDC, 100, Direction::Out),
+            entry("faucet", "mint", BOB, MUSDC, 500, Direction::In),
+            entry("liquidity", "deposit", BOB, MUSDC, 200, Direction::Out),
+            entry("liquidity", "refund", BOB, MUSDC, 200, Direction::In),
+        ]
+    }
+
+    fn holdings(rows: &[(&str, &str, i128)]) -> Holdings {
+        rows.iter().map(|(a, f, n)| ((a.to_string(), f.to_string()), *n)).collect()
+    }
+
+    #[test]
+    fn ledgers_sum_to_expected_holdings() {
+        let expected = expected_holdings(&fixture());
+        assert_eq!(expected, holdings(&[(ALICE, MILO, 600), (ALICE, MUSDC, 90), (BOB, MUSDC, 500)]));
+    }
+
+    #[test]
+    fn matching_chain_state_has_no_discrepancies() {
+        let entries = fixture();
+        let expected = expected_holdings(&entries);
+        assert!(find_discrepancies(&entries, &expected, &expected, 0).is_empty());
+    }
+
+    #[test]
+    fn missing_swap_output_is_reported_with_its_entries() {
+        let entries = fixture();
+        let expected = expected_holdings(&entries);
+        // The swap output note never reached Alice
+        let actual = holdings(&[(ALICE, MILO, 600), (BOB, MUSDC, 500)]);
+
+        let found = find_discrepancies(&entries, &expected, &actual, 100);
+        assert_eq!(found.len(), 1);
+        let d = &found[0];
+        assert_eq!((d.account_id.as_str(), d.faucet_id.as_str()), (ALICE, MUSDC));
+        assert_eq!((d.expected, d.actual, d.difference), (90, 0, -90));
+        let kinds: Vec<&str> = d.entries.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, ["swap_out", "deposit"]);
+    }
+
+    #[test]
+    fn small_differences_are_within_tolerance() {
+        let entries = fixture();
+        let expected = expected_holdings(&entries);
+        let actual = holdings(&[(ALICE, MILO, 599), (ALICE, MUSDC, 90), (BOB, MUSDC, 500), (BOB, MILO, 7)]);
+
+        // 1 in 600 is under 1%; an untracked token is never within tolerance
+        let found = find_discrepancies(&entries, &expected, &actual, 100);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].faucet_id.as_str(), found[0].expected, found[0].actual), (MILO, 0, 7));
+        assert!(found[0].entries.is_empty());
+        assert_eq!(find_discrepancies(&entries, &expected, &actual, 0).len(), 2);
+    }
+
+    #[test]
+    fn entries_are_normalized_to_lowercase() {
+        let e = LedgerEntry::new("faucet", "mint", "0xABC", "0xDEF", 5, Direction::In, "0xtx");
+        assert_eq!((e.account_id.as_str(), e.faucet_id.as_str()), ("0xabc", "0xdef"));
+        assert_eq!(e.signed_amount(), 5);
+        assert_eq!(entry("swap", "swap_in", ALICE, MILO, 5, Direction::Out).signed_amount(), -5);
+    }
+
+    #[test]
+    fn ledger_file_round_trips_and_skips_bad_lines() {
+        let path = std::env::temp_dir().join(format!("milo_ledger_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(load_entries(path).unwrap().0.len(), 0);
+
+        for e in fixture() {
+            append_entry(path, &e);
+        }
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        writeln!(file, "not json\n").unwrap();
+
+        let (entries, skipped) = load_entries(path).unwrap();
+        assert_eq!((entries.len(), skipped), (fixture().len(), 1));
+        assert_eq!(expected_holdings(&entries), expected_holdings(&fixture()));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,5 +1,18 @@
+pub mod account_state;
+pub mod api_keys;
+pub mod cli;
+pub mod curve;
+pub mod faucet_config;
 pub mod helpers;
+pub mod ledger;
+pub mod lp_token;
+pub mod maintenance;
 pub mod milo_accounts;
+pub mod pool_component;
+pub mod pool_config;
+pub mod rate_limit;
+pub mod shutdown;
+pub mod startup_config;
 
 use miden_objects::assembly::{Assembler, DefaultSourceManager, LibraryPath, Module, ModuleKind};
 use std::sync::Arc;
@@ -0,0 +1,169 @@
+//! Pool LP tokens
+//!
+//! Pools created by `milo add-liquidity` get their own fungible faucet
+//! (pools.json `<pair>_lp_faucet_id`). The liquidity daemon mints LP tokens
+//! from it to every credited depositor, in proportion to what the deposit
+//! adds to the pool's liquidity (the sum of both reserves, in base units).
+//! A withdrawal hands LP tokens back to the pool, pays out the same fraction
+//! of each reserve and sends the tokens to the faucet in a burn note
+//! (`masm/lp_burn.masm`), so the issued supply is always what LPs hold.
+
+use anyhow::{Context, Result};
+use miden_client::{
+    account::{Account, AccountId},
+    asset::FungibleAsset,
+    crypto::FeltRng,
+    note::{
+        Note, NoteAssets, NoteExecutionHint, NoteInputs, NoteMetadata, NoteRecipient, NoteScript, NoteTag,
+        NoteType,
+    },
+    Felt,
+};
+use miden_lib::transaction::TransactionKernel;
+
+use crate::pool_config::PoolsConfig;
+
+pub const LP_BURN_NOTE_SOURCE: &str = include_str!("../masm/lp_burn.masm");
+
+/// Fungible faucets keep their total issuance in the last element of this
+/// reserved storage slot
+const FAUCET_SYSDATA_SLOT: u8 = 0;
+
+/// Symbol of the LP token of pool `pair`, e.g. MIMULP for `milo_musdc`:
+/// two letters of each token, as token symbols are at most 6 letters
+pub fn lp_token_symbol(pair: &str) -> String {
+    let prefix: String = pair.split('_').flat_map(|symbol| symbol.chars().take(2)).collect();
+    format!("{}LP", prefix.to_uppercase())
+}
+
+/// Pair in `pools` other than `pair` whose LP token symbol is the one `pair`
+/// would get. Two LP faucets can't share a symbol, so such a pool can't be
+/// created.
+pub fn lp_symbol_owner<'a>(pools: &'a PoolsConfig, pair: &str) -> Option<&'a str> {
+    let symbol = lp_token_symbol(pair);
+    pools
+        .pools
+        .iter()
+        .find(|p| p.pair != pair && p.lp_faucet_id.is_some() && lp_token_symbol(&p.pair) == symbol)
+        .map(|p| p.pair.as_str())
+}
+
+/// LP tokens issued by `lp_faucet`, burned ones excluded
+pub fn lp_supply(lp_faucet: &Account) -> Result<u64> {
+    let sysdata = lp_faucet.storage().get_item(FAUCET_SYSDATA_SLOT).context("LP faucet storage")?;
+    Ok(sysdata[3].as_int())
+}
+
+/// LP tokens for adding `added` to a pool that held `liquidity_before`
+/// against `supply` LP tokens. The first LP tokens of a pool are minted 1:1
+/// with its liquidity.
+pub fn lp_tokens_for_deposit(added: u64, liquidity_before: u64, supply: u64) -> u64 {
+    if supply == 0 || liquidity_before == 0 {
+        return added;
+    }
+    (added as u128 * supply as u128 / liquidity_before as u128).min(u64::MAX as u128) as u64
+}
+
+/// Part of `reserve` that `lp_amount` out of `supply` LP tokens redeem
+pub fn lp_redemption(lp_amount: u64, supply: u64, reserve: u64) -> u64 {
+    if supply == 0 {
+        return 0;
+    }
+    (lp_amount.min(supply) as u128 * reserve as u128 / supply as u128) as u64
+}
+
+/// Note from `pool_id` carrying `lp_amount` LP tokens to their faucet, which
+/// burns them when it consumes the note
+pub fn lp_burn_note(pool_id: AccountId, lp_faucet_id: AccountId, lp_amount: u64, rng: &mut impl FeltRng) -> Result<Note> {
+    let program = TransactionKernel::assembler()
+        .assemble_program(LP_BURN_NOTE_SOURCE)
+        .map_err(|e| anyhow::anyhow!("LP burn note MASM failed to assemble: {}", e))?;
+    let asset = FungibleAsset::new(lp_faucet_id, lp_amount)?;
+    let metadata = NoteMetadata::new(
+        pool_id,
+        NoteType::Public,
+        NoteTag::from_account_id(lp_faucet_id),
+        NoteExecutionHint::always(),
+        Felt::new(0),
+    )?;
+    let recipient = NoteRecipient::new(rng.draw_word(), NoteScript::new(program), NoteInputs::new(vec![])?);
+    Ok(Note::new(NoteAssets::new(vec![asset.into()])?, metadata, recipient))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miden_client::crypto::RpoRandomCoin;
+
+    #[test]
+    fn first_deposit_mints_one_to_one() {
+        assert_eq!(lp_tokens_for_deposit(5_000, 0, 0), 5_000);
+        // Liquidity without LP tokens (or the reverse) restarts at 1:1
+        assert_eq!(lp_tokens_for_deposit(5_000, 10_000, 0), 5_000);
+        assert_eq!(lp_tokens_for_deposit(5_000, 0, 10_000), 5_000);
+    }
+
+    #[test]
+    fn later_deposits_mint_in_proportion() {
+        // Pool doubled its liquidity through fees: LP tokens are worth 2 each
+        assert_eq!(lp_tokens_for_deposit(1_000, 20_000, 10_000), 500);
+        assert_eq!(lp_tokens_for_deposit(1_000, 10_000, 10_000), 1_000);
+        // Rounds down, in the pool's favour
+        assert_eq!(lp_tokens_for_deposit(1, 3, 2), 0);
+        assert_eq!(lp_tokens_for_deposit(u64::MAX, 1, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn redemption_pays_out_the_share_of_each_reserve() {
+        assert_eq!(lp_redemption(250, 1_000, 40_000), 10_000);
+        assert_eq!(lp_redemption(1_000, 1_000, 40_000), 40_000);
+        // More than the supply redeems no more than the whole reserve
+        assert_eq!(lp_redemption(2_000, 1_000, 40_000), 40_000);
+        assert_eq!(lp_redemption(1, 3, 2), 0);
+        assert_eq!(lp_redemption(10, 0, 40_000), 0);
+        assert_eq!(lp_redemption(u64::MAX, u64::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn deposit_then_redeem_round_trips() {
+        let (reserve, supply) = (30_000u64, 15_000u64);
+        let lp = lp_tokens_for_deposit(6_000, reserve, supply);
+        assert_eq!(lp, 3_000);
+        assert_eq!(lp_redemption(lp, supply + lp, reserve + 6_000), 6_000);
+    }
+
+    #[test]
+    fn burn_note_carries_the_lp_tokens_to_their_faucet() {
+        let pool = AccountId::from_hex(milo_registry::MILO_FAUCET_ID_HEX).unwrap();
+        let lp_faucet = AccountId::from_hex(milo_registry::MUSDC_FAUCET_ID_HEX).unwrap();
+        let mut rng = RpoRandomCoin::new([Felt::new(3); 4].into());
+        let note = lp_burn_note(pool, lp_faucet, 700, &mut rng).unwrap();
+
+        assert_eq!(note.metadata().sender(), pool);
+        assert_eq!(note.metadata().tag(), NoteTag::from_account_id(lp_faucet));
+        let assets: Vec<_> = note.assets().iter().cloned().collect();
+        assert_eq!(assets, vec![FungibleAsset::new(lp_faucet, 700).unwrap().into()]);
+    }
+
+    #[test]
+    fn lp_symbols_take_two_letters_of_each_token() {
+        assert_eq!(lp_token_symbol("milo_musdc"), "MIMULP");
+        assert_eq!(lp_token_symbol("melo_musdc"), "MEMULP");
+        assert_eq!(lp_token_symbol("x_musdc"), "XMULP");
+    }
+
+    #[test]
+    fn colliding_lp_symbol_names_the_pool_holding_it() {
+        let faucet = AccountId::from_hex(milo_registry::MILO_FAUCET_ID_HEX).unwrap();
+        let pool = AccountId::from_hex(milo_registry::MELO_FAUCET_ID_HEX).unwrap();
+        let mut pools = PoolsConfig::default();
+        pools.insert("milo_musdc", pool, Some(faucet));
+        assert_eq!(lp_symbol_owner(&pools, "mint_musdc"), Some("milo_musdc"));
+        assert_eq!(lp_symbol_owner(&pools, "melo_musdc"), None);
+        // A pool never collides with itself
+        assert_eq!(lp_symbol_owner(&pools, "milo_musdc"), None);
+        // Pools without an LP faucet hold no symbol
+        pools.insert("milo_musdc", pool, None);
+        assert_eq!(lp_symbol_owner(&pools, "mint_musdc"), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Maintenance mode for the daemons
+//!
+//! While maintenance is on, `MaintenanceLayer` answers every state-changing
+//! request (anything but GET, HEAD and OPTIONS) with 503, so no new work
+//! reaches the worker. Reads, `/health`, `/maintenance` itself and `/admin/*`
+//! keep working. Workers check `is_enabled()` and stop starting background
+//! work (auto-poll, limit orders, ...) while still tracking transactions they
+//! already submitted, so in-flight operations finish cleanly.
+//!
+//! Attach it to the whole router, after the routes:
+//!
+//! ```ignore
+//! let app = Router::new().route(...).layer(maintenance.layer());
+//! ```
+//!
+//! `MILO_MAINTENANCE=1` starts a daemon in maintenance mode.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service};
+
+/// Paths that stay writable during maintenance (prefix match)
+const EXEMPT_PATHS: [&str; 2] = ["/maintenance", "/admin/"];
+
+/// Current maintenance state, as reported by `/health` and `/maintenance`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MaintenanceStatus {
+    pub maintenance: bool,
+    pub reason: Option<String>,
+    /// Unix seconds when maintenance was turned on
+    pub since: Option<u64>,
+}
+
+/// Body of `POST /maintenance`
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+    pub reason: Option<String>,
+}
+
+/// Shared maintenance flag; clones refer to the same flag
+#[derive(Clone, Default)]
+pub struct Maintenance {
+    enabled: Arc<AtomicBool>,
+    status: Arc<Mutex<MaintenanceStatus>>,
+}
+
+impl Maintenance {
+    /// Off unless `MILO_MAINTENANCE` is set to `1` or `true`
+    pub fn from_env() -> Self {
+        let maintenance = Self::default();
+        if matches!(std::env::var("MILO_MAINTENANCE").as_deref(), Ok("1") | Ok("true")) {
+            maintenance.set(true, Some("Started in maintenance mode (MILO_MAINTENANCE)".to_string()));
+        }
+        maintenance
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Turn maintenance on or off and return the new status
+    pub fn set(&self, enabled: bool, reason: Option<String>) -> MaintenanceStatus {
+        let mut status = self.status.lock().unwrap();
+        if enabled {
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            // Keep the original start time when only the reason changes
+            status.since = status.since.or(Some(now));
+            status.reason = reason;
+            println!("🛠️  Maintenance mode ON{}", status.reason.as_deref().map(|r| format!(": {}", r)).unwrap_or_default());
+        } else {
+            status.since = None;
+            status.reason = None;
+            println!("🛠️  Maintenance mode OFF");
+        }
+        status.maintenance = enabled;
+        self.enabled.store(enabled, Ordering::Relaxed);
+        status.clone()
+    }
+
+    pub fn layer(&self) -> MaintenanceLayer {
+        MaintenanceLayer { maintenance: self.clone() }
+    }
+}
+
+/// Tower layer rejecting state-changing requests while maintenance is on
+#[derive(Clone)]
+pub struct MaintenanceLayer {
+    maintenance: Maintenance,
+}
+
+impl<S> Layer<S> for MaintenanceLayer {
+    type Service = MaintenanceGuard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MaintenanceGuard {
+            inner,
+            maintenance: self.maintenance.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MaintenanceGuard<S> {
+    inner: S,
+    maintenance: Maintenance,
+}
+
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+impl<S> Service<Request<Body>> for MaintenanceGuard<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let path = req.uri().path();
+        if self.maintenance.is_enabled()
+            && !is_read(req.method())
+            && !EXEMPT_PATHS.iter().any(|p| path.starts_with(p))
+        {
+            println!("🛠️  {} {} rejected: maintenance", req.method(), path);
+            let status = self.maintenance.status();
+            let response = (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": "maintenance",
+                    "maintenance": true,
+                    "reason": status.reason,
+                    "since": status.since,
+                })),
+            )
+                .into_response();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
@@ -15,12 +15,9 @@ pub const USER_WALLET_ID_HEX: &str = "0x9468e1645902cc101d38fa5b785276";
 pub const USER_WALLET_ADDRESS: &str = "mtst1az2x3ctytypvcyqa8ra9k7zjwclrpfs0";
 
 // ============ TOKEN FAUCETS ============
-/// MILO Token Faucet ID (Orange color token)
-pub const MILO_FAUCET_ID_HEX: &str = "0xa28b4f998be3a32047b88ae20e0a35";
-/// MELO Token Faucet ID (Green color token)
-pub const MELO_FAUCET_ID_HEX: &str = "0x13bde3e49deaf92074138cbcaf8d4f";
-/// MUSDC Token Faucet ID (Blue - USDC stablecoin)
-pub const MUSDC_FAUCET_ID_HEX: &str = "0x2c09e8d9f4ef022044cfee2d14d3a8";
+// Compiled-in fallbacks; the faucets of the last `milo setup` come from
+// accounts.json through milo_registry::TokenRegistry
+pub use milo_registry::{MELO_FAUCET_ID_HEX, MILO_FAUCET_ID_HEX, MUSDC_FAUCET_ID_HEX};
 
 // ============ LIQUIDITY POOLS ============
 /// MILO/MUSDC Pool Account ID
@@ -52,14 +49,10 @@ pub const MUSDC_LIQUIDITY_AMOUNT: u64 = 100_000;
 
 // ============ HELPER FUNCTIONS ============
 
-/// Get faucet ID by token symbol (case insensitive)
+/// Get faucet ID by token symbol (case insensitive), from the installed
+/// `TokenRegistry` (the compiled-in IDs if none was installed)
 pub fn get_faucet_id_by_symbol(symbol: &str) -> Option<&'static str> {
-    match symbol.to_uppercase().as_str() {
-        "MILO" => Some(MILO_FAUCET_ID_HEX),
-        "MELO" => Some(MELO_FAUCET_ID_HEX),
-        "MUSDC" => Some(MUSDC_FAUCET_ID_HEX),
-        _ => None,
-    }
+    milo_registry::TokenRegistry::global().faucet_id_hex(symbol)
 }
 
 /// Get pool ID by trading pair (returns (base_pool_id, quote_pool_id))
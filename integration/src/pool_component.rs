@@ -0,0 +1,181 @@
+//! On-chain pool component
+//!
+//! Pools are BasicWallet accounts, with the AMM pricing done by the swap
+//! daemon. The component in `masm/milo_pool.masm` sits next to the wallet,
+//! records the pair and reserves in account storage and offers a checked
+//! `swap` payout that fails the transaction if `reserve0 * reserve1` would
+//! shrink.
+//!
+//! The daemons keep the recorded reserves current: deposits run
+//! `sync_reserves_script` (same check as `swap`), withdrawals and refunds
+//! `record_reserves_script`. Swaps sync on constant-product pools and record
+//! on stable-swap pools, whose trades don't preserve the product. Pools
+//! created before the component have none and are left without a script, see
+//! `has_pool_component`.
+
+use miden_lib::{transaction::TransactionKernel, utils::ScriptBuilder};
+use miden_objects::{
+    account::{AccountCode, AccountComponent, AccountId, StorageSlot},
+    assembly::Library,
+    transaction::TransactionScript,
+    Felt, Word,
+};
+
+use crate::create_library;
+
+/// Library path the pool's procedures are exported under (`milo::pool::swap`)
+pub const POOL_LIBRARY_PATH: &str = "milo::pool";
+pub const POOL_COMPONENT_SOURCE: &str = include_str!("../masm/milo_pool.masm");
+
+const SYNC_RESERVES_SCRIPT: &str = "
+use.milo::pool
+
+begin
+    call.pool::sync_reserves
+end
+";
+
+const RECORD_RESERVES_SCRIPT: &str = "
+use.milo::pool
+
+begin
+    call.pool::record_reserves
+end
+";
+
+/// Assemble the pool module with the transaction kernel's assembler, which
+/// links `miden::*` and the standard library
+pub fn pool_library() -> Result<Library, Box<dyn std::error::Error>> {
+    create_library(TransactionKernel::assembler(), POOL_LIBRARY_PATH, POOL_COMPONENT_SOURCE)
+}
+
+/// Whether an account's code includes the pool component. Pools created
+/// before it was attached are plain wallets: a reserves script would fail
+/// their transactions, so the daemons leave them without one.
+pub fn has_pool_component(code: &AccountCode) -> Result<bool, Box<dyn std::error::Error>> {
+    let root = pool_library()?
+        .get_procedure_root_by_name(format!("{}::sync_reserves", POOL_LIBRARY_PATH))
+        .ok_or("milo::pool has no sync_reserves procedure")?;
+    Ok(code.has_procedure(root))
+}
+
+/// `script()` for a transaction on the pool with `code`, or None for a pool
+/// without the component
+pub fn script_for_pool(
+    code: &AccountCode,
+    script: fn() -> Result<TransactionScript, Box<dyn std::error::Error>>,
+) -> Result<Option<TransactionScript>, Box<dyn std::error::Error>> {
+    if !has_pool_component(code)? {
+        return Ok(None);
+    }
+    script().map(Some)
+}
+
+/// Transaction script for a pool deposit: after the notes are received,
+/// `sync_reserves` checks the invariant and records the new balances
+pub fn sync_reserves_script() -> Result<TransactionScript, Box<dyn std::error::Error>> {
+    reserves_script(SYNC_RESERVES_SCRIPT)
+}
+
+/// Transaction script for a withdrawal or refund: `record_reserves` stores
+/// the balances left after the payout notes, without the check
+pub fn record_reserves_script() -> Result<TransactionScript, Box<dyn std::error::Error>> {
+    reserves_script(RECORD_RESERVES_SCRIPT)
+}
+
+/// Compile a script that calls into the pool library. The library is linked
+/// dynamically: its procedures run from the pool account's own code.
+fn reserves_script(source: &str) -> Result<TransactionScript, Box<dyn std::error::Error>> {
+    let script = ScriptBuilder::default()
+        .with_dynamically_linked_library(&pool_library()?)?
+        .compile_tx_script(source)?;
+    Ok(script)
+}
+
+/// `[reserve0, reserve1, 0, 0]`, the RESERVES slot's layout
+pub fn reserves_word(reserve0: u64, reserve1: u64) -> Word {
+    Word::from([Felt::new(reserve0), Felt::new(reserve1), Felt::new(0), Felt::new(0)])
+}
+
+/// The pool component for `token0`/`token1`, with the reserves seeded to the
+/// balances the pool is created with. A new, empty pool starts at 0 and its
+/// first deposit's `sync_reserves` records the real balances.
+pub fn pool_component(
+    token0: AccountId,
+    token1: AccountId,
+    reserve0: u64,
+    reserve1: u64,
+) -> Result<AccountComponent, Box<dyn std::error::Error>> {
+    let pair = Word::from([token0.suffix(), token0.prefix().as_felt(), token1.suffix(), token1.prefix().as_felt()]);
+    let slots = vec![StorageSlot::Value(pair), StorageSlot::Value(reserves_word(reserve0, reserve1))];
+    let component = AccountComponent::new(pool_library()?, slots)?.with_supports_all_types();
+    Ok(component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miden_client::auth::AuthSecretKey;
+    use miden_lib::account::{auth::AuthRpoFalcon512, wallets::BasicWallet};
+    use miden_objects::account::{Account, AccountBuilder, AccountStorageMode, AccountType};
+
+    fn tokens() -> (AccountId, AccountId) {
+        (
+            AccountId::from_hex(milo_registry::MILO_FAUCET_ID_HEX).unwrap(),
+            AccountId::from_hex(milo_registry::MUSDC_FAUCET_ID_HEX).unwrap(),
+        )
+    }
+
+    fn pool_account(component: Option<AccountComponent>) -> Account {
+        let key_pair = AuthSecretKey::new_rpo_falcon512();
+        let mut builder = AccountBuilder::new([7; 32])
+            .account_type(AccountType::RegularAccountUpdatableCode)
+            .storage_mode(AccountStorageMode::Public)
+            .with_auth_component(AuthRpoFalcon512::new(key_pair.public_key().to_commitment()))
+            .with_component(BasicWallet);
+        if let Some(component) = component {
+            builder = builder.with_component(component);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn module_assembles() {
+        pool_library().unwrap();
+    }
+
+    #[test]
+    fn reserves_scripts_compile() {
+        let sync = sync_reserves_script().unwrap();
+        let record = record_reserves_script().unwrap();
+        assert_ne!(sync.root(), record.root());
+    }
+
+    #[test]
+    fn builds_a_pool_account_with_seeded_reserves() {
+        let (token0, token1) = tokens();
+        let component = pool_component(token0, token1, 1_000, 2_000).unwrap();
+        let account = pool_account(Some(component));
+        let wallet = pool_account(None);
+
+        // get_pair, get_reserves, swap, sync_reserves and record_reserves on
+        // top of the wallet's
+        assert_eq!(account.code().procedures().len(), wallet.code().procedures().len() + 5);
+        assert!(has_pool_component(account.code()).unwrap());
+        assert!(!has_pool_component(wallet.code()).unwrap());
+
+        let slots: Vec<Word> = account
+            .storage()
+            .slots()
+            .iter()
+            .filter_map(|slot| match slot {
+                StorageSlot::Value(word) => Some(*word),
+                _ => None,
+            })
+            .collect();
+        let pair = Word::from([token0.suffix(), token0.prefix().as_felt(), token1.suffix(), token1.prefix().as_felt()]);
+        assert!(slots.contains(&pair));
+        assert!(slots.contains(&reserves_word(1_000, 2_000)));
+        assert!(!slots.contains(&reserves_word(0, 0)));
+    }
+}
@@ -0,0 +1,530 @@
+//! Canonical load/save for pools.json
+//!
+//! pools.json is written by the setup scripts and read by both daemons, so
+//! every reader and writer goes through this module. Entries are flat keys:
+//! `<base>_<quote>_pool_id` (hex) and `<base>_<quote>_pool_address` (bech32),
+//! plus `<base>_<quote>_lp_faucet_id` (hex) for pools that issue LP tokens.
+//! Loading validates every entry; saving refuses invalid configs, sorts keys
+//! and writes atomically (temp file + rename).
+//!
+//! pool_config.json holds per-pool settings keyed by pair: the AMM curve and
+//! the largest price impact a single swap may have. It is optional: pools
+//! without an entry trade on constant product with the default impact cap.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use miden_client::account::{AccountId, NetworkId};
+use serde::{Deserialize, Serialize};
+
+use crate::milo_accounts::get_faucet_id_by_symbol;
+
+const POOL_ID_SUFFIX: &str = "_pool_id";
+const POOL_ADDRESS_SUFFIX: &str = "_pool_address";
+const LP_FAUCET_ID_SUFFIX: &str = "_lp_faucet_id";
+
+/// Per-pool settings file, next to pools.json
+pub const POOL_CURVES_CONFIG_PATH: &str = "pool_config.json";
+/// Upper bound for a stable-swap amplification coefficient
+pub const MAX_AMPLIFICATION: u64 = 10_000;
+/// Price impact cap of pools that don't set `max_price_impact_bps` (5%)
+pub const DEFAULT_MAX_PRICE_IMPACT_BPS: u64 = 500;
+
+/// One pool entry, e.g. pair `milo_musdc`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolEntry {
+    pub pair: String,
+    pub pool_id: AccountId,
+    pub address: String,
+    /// Faucet of the pool's LP token; pools created before LP tokens have
+    /// none and keep the liquidity daemon's share accounting
+    pub lp_faucet_id: Option<AccountId>,
+}
+
+/// Validated contents of pools.json, sorted by pair
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoolsConfig {
+    pub pools: Vec<PoolEntry>,
+}
+
+impl PoolsConfig {
+    /// Build an entry from a freshly created pool account and its LP faucet
+    pub fn insert(&mut self, pair: &str, pool_id: AccountId, lp_faucet_id: Option<AccountId>) {
+        self.pools.retain(|p| p.pair != pair);
+        self.pools.push(PoolEntry {
+            pair: pair.to_string(),
+            pool_id,
+            address: pool_id.to_bech32(NetworkId::Testnet),
+            lp_faucet_id,
+        });
+        self.pools.sort_by(|a, b| a.pair.cmp(&b.pair));
+    }
+
+    pub fn pool_id(&self, pair: &str) -> Option<AccountId> {
+        self.pools.iter().find(|p| p.pair == pair).map(|p| p.pool_id)
+    }
+
+    /// Like `pool_id`, but a missing pair is an error naming the file contents
+    pub fn require_pool_id(&self, pair: &str) -> Result<AccountId> {
+        self.pool_id(pair).ok_or_else(|| {
+            let known: Vec<&str> = self.pools.iter().map(|p| p.pair.as_str()).collect();
+            anyhow::anyhow!("pools.json has no `{}` pool (found: {:?})", pair, known)
+        })
+    }
+
+    pub fn pool_ids(&self) -> Vec<AccountId> {
+        self.pools.iter().map(|p| p.pool_id).collect()
+    }
+
+    /// LP faucet of the pool with id `pool_id`, if it issues LP tokens
+    pub fn lp_faucet_id(&self, pool_id: AccountId) -> Option<AccountId> {
+        self.pools.iter().find(|p| p.pool_id == pool_id).and_then(|p| p.lp_faucet_id)
+    }
+}
+
+/// Parse and validate pools.json contents
+pub fn parse_pools_config(json: &str) -> Result<PoolsConfig> {
+    let raw: BTreeMap<String, String> =
+        serde_json::from_str(json).context("pools.json must be a flat object of string values")?;
+
+    // Group id/address/LP faucet fields by pair
+    let mut ids: BTreeMap<String, String> = BTreeMap::new();
+    let mut addresses: BTreeMap<String, String> = BTreeMap::new();
+    let mut lp_faucets: BTreeMap<String, String> = BTreeMap::new();
+    for (key, value) in raw {
+        if let Some(pair) = key.strip_suffix(LP_FAUCET_ID_SUFFIX) {
+            lp_faucets.insert(pair.to_string(), value);
+        } else if let Some(pair) = key.strip_suffix(POOL_ID_SUFFIX) {
+            ids.insert(pair.to_string(), value);
+        } else if let Some(pair) = key.strip_suffix(POOL_ADDRESS_SUFFIX) {
+            addresses.insert(pair.to_string(), value);
+        } else {
+            anyhow::bail!("Unknown key `{}` in pools.json", key);
+        }
+    }
+
+    if let Some(pair) = addresses.keys().find(|pair| !ids.contains_key(*pair)) {
+        anyhow::bail!("Pool `{}` has an address but no {}{}", pair, pair, POOL_ID_SUFFIX);
+    }
+    if let Some(pair) = lp_faucets.keys().find(|pair| !ids.contains_key(*pair)) {
+        anyhow::bail!("Pool `{}` has an LP faucet but no {}{}", pair, pair, POOL_ID_SUFFIX);
+    }
+
+    let mut config = PoolsConfig::default();
+    for (pair, id_hex) in ids {
+        let address = addresses
+            .remove(&pair)
+            .ok_or_else(|| anyhow::anyhow!("Pool `{}` is missing {}{}", pair, pair, POOL_ADDRESS_SUFFIX))?;
+        let pool_id = AccountId::from_hex(&id_hex)
+            .with_context(|| format!("Pool `{}` id `{}` is not a valid account id", pair, id_hex))?;
+        let lp_faucet_id = lp_faucets
+            .remove(&pair)
+            .map(|hex| {
+                AccountId::from_hex(&hex)
+                    .with_context(|| format!("Pool `{}` LP faucet `{}` is not a valid account id", pair, hex))
+            })
+            .transpose()?;
+        config.pools.push(PoolEntry { pair, pool_id, address, lp_faucet_id });
+    }
+
+    validate_pools_config(&config)?;
+    Ok(config)
+}
+
+/// Check every entry: address matches id, pair tokens exist in the registry, no duplicates
+pub fn validate_pools_config(config: &PoolsConfig) -> Result<()> {
+    let mut seen_ids: Vec<AccountId> = Vec::new();
+
+    for entry in &config.pools {
+        let expected_address = entry.pool_id.to_bech32(NetworkId::Testnet);
+        if entry.address != expected_address {
+            anyhow::bail!(
+                "Pool `{}` address {} does not match id {} (expected {})",
+                entry.pair, entry.address, entry.pool_id.to_hex(), expected_address
+            );
+        }
+
+        let symbols: Vec<&str> = entry.pair.split('_').collect();
+        if symbols.len() != 2 {
+            anyhow::bail!("Pool pair `{}` must be <base>_<quote>", entry.pair);
+        }
+        for symbol in symbols {
+            if get_faucet_id_by_symbol(symbol).is_none() {
+                anyhow::bail!("Pool `{}` references unknown token `{}`", entry.pair, symbol);
+            }
+        }
+
+        if seen_ids.contains(&entry.pool_id) {
+            anyhow::bail!("Pool id {} is listed more than once", entry.pool_id.to_hex());
+        }
+        seen_ids.push(entry.pool_id);
+        if let Some(lp_faucet_id) = entry.lp_faucet_id {
+            if seen_ids.contains(&lp_faucet_id) {
+                anyhow::bail!("LP faucet {} of pool `{}` is listed more than once", lp_faucet_id.to_hex(), entry.pair);
+            }
+            seen_ids.push(lp_faucet_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Load and validate pools.json
+pub fn load_pools_config(path: impl AsRef<Path>) -> Result<PoolsConfig> {
+    let path = path.as_ref();
+    let json = fs::read_to_string(path).with_context(|| format!("{} not found", path.display()))?;
+    parse_pools_config(&json).with_context(|| format!("{} is invalid", path.display()))
+}
+
+/// Validate and write pools.json atomically with keys in canonical order
+pub fn save_pools_config(path: impl AsRef<Path>, config: &PoolsConfig) -> Result<()> {
+    let path = path.as_ref();
+    validate_pools_config(config).context("Refusing to save invalid pools config")?;
+
+    let mut out: BTreeMap<String, String> = BTreeMap::new();
+    for entry in &config.pools {
+        out.insert(format!("{}{}", entry.pair, POOL_ADDRESS_SUFFIX), entry.address.clone());
+        out.insert(format!("{}{}", entry.pair, POOL_ID_SUFFIX), entry.pool_id.to_hex());
+        if let Some(lp_faucet_id) = entry.lp_faucet_id {
+            out.insert(format!("{}{}", entry.pair, LP_FAUCET_ID_SUFFIX), lp_faucet_id.to_hex());
+        }
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(&out)?)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+    Ok(())
+}
+
+/// Pricing curve a pool trades on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "curve", rename_all = "snake_case")]
+pub enum AmmCurve {
+    /// x * y = k
+    #[default]
+    ConstantProduct,
+    /// Curve-style invariant for pegged pairs; higher `amplification` keeps
+    /// the price flatter around equal reserves
+    StableSwap { amplification: u64 },
+}
+
+impl fmt::Display for AmmCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmmCurve::ConstantProduct => write!(f, "constant_product"),
+            AmmCurve::StableSwap { amplification } => write!(f, "stable_swap (A={})", amplification),
+        }
+    }
+}
+
+fn default_max_price_impact_bps() -> u64 {
+    DEFAULT_MAX_PRICE_IMPACT_BPS
+}
+
+/// One pair's entry in pool_config.json, e.g.
+/// `{ "curve": "constant_product", "max_price_impact_bps": 300 }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSettings {
+    #[serde(flatten)]
+    pub curve: AmmCurve,
+    /// Swaps whose `price_impact_bps` is above this are refused
+    #[serde(default = "default_max_price_impact_bps")]
+    pub max_price_impact_bps: u64,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self { curve: AmmCurve::default(), max_price_impact_bps: DEFAULT_MAX_PRICE_IMPACT_BPS }
+    }
+}
+
+/// Validated contents of pool_config.json, keyed by pair
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolCurvesConfig {
+    #[serde(flatten)]
+    pub pools: BTreeMap<String, PoolSettings>,
+}
+
+impl PoolCurvesConfig {
+    /// Settings for a pair; pairs without an entry use the defaults
+    pub fn settings(&self, pair: &str) -> PoolSettings {
+        self.pools.get(pair).copied().unwrap_or_default()
+    }
+
+    /// Curve for a pair; pairs without an entry use constant product
+    pub fn curve(&self, pair: &str) -> AmmCurve {
+        self.settings(pair).curve
+    }
+
+    /// Change a pair's curve, keeping its other settings
+    pub fn set_curve(&mut self, pair: &str, curve: AmmCurve) {
+        self.pools.entry(pair.to_string()).or_default().curve = curve;
+    }
+
+    /// Settings for every pool in `pools`, keyed by pool id
+    pub fn settings_by_pool_id(&self, pools: &PoolsConfig) -> HashMap<AccountId, PoolSettings> {
+        pools.pools.iter().map(|p| (p.pool_id, self.settings(&p.pair))).collect()
+    }
+}
+
+/// Check every entry: known pair, amplification and impact cap within bounds
+pub fn validate_pool_curves_config(config: &PoolCurvesConfig, pools: &PoolsConfig) -> Result<()> {
+    for (pair, settings) in &config.pools {
+        if pools.pool_id(pair).is_none() {
+            anyhow::bail!("Curve set for `{}`, which is not a pool in pools.json", pair);
+        }
+        if settings.max_price_impact_bps == 0 || settings.max_price_impact_bps > 10_000 {
+            anyhow::bail!(
+                "Pool `{}` max_price_impact_bps {} must be between 1 and 10000",
+                pair, settings.max_price_impact_bps
+            );
+        }
+        if let AmmCurve::StableSwap { amplification } = settings.curve {
+            if amplification == 0 || amplification > MAX_AMPLIFICATION {
+                anyhow::bail!(
+                    "Pool `{}` amplification {} must be between 1 and {}",
+                    pair, amplification, MAX_AMPLIFICATION
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load and validate pool_config.json against the loaded pools; a missing
+/// file means every pool trades on constant product
+pub fn load_pool_curves_config(path: impl AsRef<Path>, pools: &PoolsConfig) -> Result<PoolCurvesConfig> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(PoolCurvesConfig::default());
+    }
+    let json = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let config: PoolCurvesConfig = serde_json::from_str(&json)
+        .with_context(|| format!("{} is invalid", path.display()))?;
+    validate_pool_curves_config(&config, pools).with_context(|| format!("{} is invalid", path.display()))?;
+    Ok(config)
+}
+
+/// Validate and write pool_config.json atomically
+pub fn save_pool_curves_config(path: impl AsRef<Path>, config: &PoolCurvesConfig, pools: &PoolsConfig) -> Result<()> {
+    let path = path.as_ref();
+    validate_pool_curves_config(config, pools).context("Refusing to save invalid pool curve config")?;
+
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(config)?)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MILO_MUSDC_POOL: &str = "0x6b10bd738877ea101db7175839e152";
+    const MELO_MUSDC_POOL: &str = "0x563a995fec149d105728eaa1bd4332";
+
+    fn id(hex: &str) -> AccountId {
+        AccountId::from_hex(hex).unwrap()
+    }
+
+    fn address(hex: &str) -> String {
+        id(hex).to_bech32(NetworkId::Testnet)
+    }
+
+    fn pools_json(entries: &[(&str, &str, &str)]) -> String {
+        let mut raw = BTreeMap::new();
+        for (key, suffix, value) in entries {
+            raw.insert(format!("{}{}", key, suffix), value.to_string());
+        }
+        serde_json::to_string(&raw).unwrap()
+    }
+
+    fn two_pools() -> PoolsConfig {
+        let mut config = PoolsConfig::default();
+        config.insert("milo_musdc", id(MILO_MUSDC_POOL), None);
+        config.insert("melo_musdc", id(MELO_MUSDC_POOL), Some(id(milo_registry::MILO_FAUCET_ID_HEX)));
+        config
+    }
+
+    fn parse_err(json: &str) -> String {
+        format!("{:#}", parse_pools_config(json).unwrap_err())
+    }
+
+    #[test]
+    fn parses_valid_pools() {
+        let json = pools_json(&[
+            ("milo_musdc", POOL_ID_SUFFIX, MILO_MUSDC_POOL),
+            ("milo_musdc", POOL_ADDRESS_SUFFIX, &address(MILO_MUSDC_POOL)),
+            ("melo_musdc", POOL_ID_SUFFIX, MELO_MUSDC_POOL),
+            ("melo_musdc", POOL_ADDRESS_SUFFIX, &address(MELO_MUSDC_POOL)),
+            ("melo_musdc", LP_FAUCET_ID_SUFFIX, milo_registry::MILO_FAUCET_ID_HEX),
+        ]);
+        let config = parse_pools_config(&json).unwrap();
+        assert_eq!(config, two_pools());
+        assert_eq!(config.require_pool_id("milo_musdc").unwrap(), id(MILO_MUSDC_POOL));
+        assert_eq!(config.lp_faucet_id(id(MILO_MUSDC_POOL)), None);
+        assert_eq!(config.lp_faucet_id(id(MELO_MUSDC_POOL)), Some(id(milo_registry::MILO_FAUCET_ID_HEX)));
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(parse_err("[]").contains("flat object"));
+        assert!(parse_err(r#"{"milo_musdc_pool_name": "x"}"#).contains("Unknown key"));
+        let dangling = pools_json(&[("milo_musdc", POOL_ADDRESS_SUFFIX, &address(MILO_MUSDC_POOL))]);
+        assert!(parse_err(&dangling).contains("has an address but no"));
+        let no_address = pools_json(&[("milo_musdc", POOL_ID_SUFFIX, MILO_MUSDC_POOL)]);
+        assert!(parse_err(&no_address).contains("is missing milo_musdc_pool_address"));
+        let bad_id = pools_json(&[
+            ("milo_musdc", POOL_ID_SUFFIX, "0xnothex"),
+            ("milo_musdc", POOL_ADDRESS_SUFFIX, &address(MILO_MUSDC_POOL)),
+        ]);
+        assert!(parse_err(&bad_id).contains("is not a valid account id"));
+    }
+
+    #[test]
+    fn rejects_address_of_another_pool() {
+        let json = pools_json(&[
+            ("milo_musdc", POOL_ID_SUFFIX, MILO_MUSDC_POOL),
+            ("milo_musdc", POOL_ADDRESS_SUFFIX, &address(MELO_MUSDC_POOL)),
+        ]);
+        assert!(parse_err(&json).contains("does not match id"));
+    }
+
+    #[test]
+    fn rejects_unknown_tokens_and_bad_pairs() {
+        let mut config = PoolsConfig::default();
+        config.insert("milo_doge", id(MILO_MUSDC_POOL), None);
+        let err = validate_pools_config(&config).unwrap_err().to_string();
+        assert!(err.contains("unknown token `doge`"), "{}", err);
+
+        let mut config = PoolsConfig::default();
+        config.insert("milo", id(MILO_MUSDC_POOL), None);
+        let err = validate_pools_config(&config).unwrap_err().to_string();
+        assert!(err.contains("must be <base>_<quote>"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_duplicate_ids() {
+        let mut config = two_pools();
+        config.insert("milo_musdc", id(MELO_MUSDC_POOL), None);
+        let err = validate_pools_config(&config).unwrap_err().to_string();
+        assert!(err.contains("listed more than once"), "{}", err);
+
+        let mut config = two_pools();
+        config.insert("milo_musdc", id(MILO_MUSDC_POOL), Some(id(MELO_MUSDC_POOL)));
+        let err = validate_pools_config(&config).unwrap_err().to_string();
+        assert!(err.contains("LP faucet"), "{}", err);
+    }
+
+    #[test]
+    fn save_writes_sorted_keys_and_loads_back() {
+        let path = std::env::temp_dir().join(format!("milo_pools_{}.json", std::process::id()));
+        save_pools_config(&path, &two_pools()).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        let positions: Vec<usize> = [
+            "melo_musdc_lp_faucet_id",
+            "melo_musdc_pool_address",
+            "melo_musdc_pool_id",
+            "milo_musdc_pool_address",
+            "milo_musdc_pool_id",
+        ]
+        .iter()
+        .map(|key| json.find(key).unwrap())
+        .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", json);
+        assert_eq!(load_pools_config(&path).unwrap(), two_pools());
+        assert!(!path.with_extension("json.tmp").exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn save_refuses_invalid_config() {
+        let path = std::env::temp_dir().join(format!("milo_pools_invalid_{}.json", std::process::id()));
+        let mut config = PoolsConfig::default();
+        config.insert("milo_doge", id(MILO_MUSDC_POOL), None);
+        assert!(save_pools_config(&path, &config).is_err());
+        assert!(!path.exists());
+    }
+
+    fn curves(json: &str) -> PoolCurvesConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn curve_err(json: &str) -> String {
+        validate_pool_curves_config(&curves(json), &two_pools()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_pool_curves() {
+        let config = curves(
+            r#"{
+                "milo_musdc": { "curve": "stable_swap", "amplification": 100 },
+                "melo_musdc": { "curve": "constant_product", "max_price_impact_bps": 300 }
+            }"#,
+        );
+        validate_pool_curves_config(&config, &two_pools()).unwrap();
+        assert_eq!(config.curve("milo_musdc"), AmmCurve::StableSwap { amplification: 100 });
+        assert_eq!(config.settings("milo_musdc").max_price_impact_bps, DEFAULT_MAX_PRICE_IMPACT_BPS);
+        assert_eq!(config.settings("melo_musdc").max_price_impact_bps, 300);
+        // Pools without an entry trade on constant product
+        assert_eq!(PoolCurvesConfig::default().curve("milo_musdc"), AmmCurve::ConstantProduct);
+
+        let by_id = config.settings_by_pool_id(&two_pools());
+        assert_eq!(by_id[&id(MILO_MUSDC_POOL)].curve, AmmCurve::StableSwap { amplification: 100 });
+        assert_eq!(by_id[&id(MELO_MUSDC_POOL)].curve, AmmCurve::ConstantProduct);
+    }
+
+    #[test]
+    fn rejects_invalid_pool_curves() {
+        assert!(curve_err(r#"{"milo_doge": {"curve": "constant_product"}}"#).contains("not a pool in pools.json"));
+        assert!(curve_err(r#"{"milo_musdc": {"curve": "stable_swap", "amplification": 0}}"#)
+            .contains("amplification 0 must be between 1 and 10000"));
+        assert!(curve_err(r#"{"milo_musdc": {"curve": "stable_swap", "amplification": 10001}}"#)
+            .contains("must be between 1 and 10000"));
+        assert!(curve_err(r#"{"milo_musdc": {"curve": "constant_product", "max_price_impact_bps": 0}}"#)
+            .contains("max_price_impact_bps 0"));
+        assert!(curve_err(r#"{"milo_musdc": {"curve": "constant_product", "max_price_impact_bps": 10001}}"#)
+            .contains("max_price_impact_bps 10001"));
+        // A stable pool needs its amplification; unknown curves don't parse
+        assert!(serde_json::from_str::<PoolCurvesConfig>(r#"{"milo_musdc": {"curve": "stable_swap"}}"#).is_err());
+        assert!(serde_json::from_str::<PoolCurvesConfig>(r#"{"milo_musdc": {"curve": "linear"}}"#).is_err());
+    }
+
+    #[test]
+    fn set_curve_keeps_the_impact_cap() {
+        let mut config = curves(r#"{"milo_musdc": {"curve": "constant_product", "max_price_impact_bps": 300}}"#);
+        config.set_curve("milo_musdc", AmmCurve::StableSwap { amplification: 50 });
+        assert_eq!(
+            config.settings("milo_musdc"),
+            PoolSettings { curve: AmmCurve::StableSwap { amplification: 50 }, max_price_impact_bps: 300 }
+        );
+        assert_eq!(config.curve("milo_musdc").to_string(), "stable_swap (A=50)");
+        assert_eq!(AmmCurve::ConstantProduct.to_string(), "constant_product");
+    }
+
+    #[test]
+    fn pool_curves_round_trip_and_default_when_missing() {
+        let path = std::env::temp_dir().join(format!("milo_pool_config_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(load_pool_curves_config(&path, &two_pools()).unwrap(), PoolCurvesConfig::default());
+
+        let mut config = PoolCurvesConfig::default();
+        config.set_curve("milo_musdc", AmmCurve::StableSwap { amplification: 100 });
+        save_pool_curves_config(&path, &config, &two_pools()).unwrap();
+        assert_eq!(load_pool_curves_config(&path, &two_pools()).unwrap(), config);
+
+        // A curve for a pool that is gone from pools.json no longer loads
+        let mut fewer = PoolsConfig::default();
+        fewer.insert("melo_musdc", id(MELO_MUSDC_POOL), None);
+        assert!(load_pool_curves_config(&path, &fewer).is_err());
+        assert!(save_pool_curves_config(&path, &config, &fewer).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
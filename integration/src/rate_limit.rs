@@ -0,0 +1,401 @@
+//! Token-bucket rate limiting as a tower layer
+//!
+//! The daemons run every write through a single worker thread, so one
+//! misbehaving client can starve everyone else. `RateLimitLayer` keeps a
+//! token bucket per key (the id of an authenticated API key, else caller IP,
+//! by default) and answers 429 once a bucket is empty. Attach it per route:
+//!
+//! ```ignore
+//! .route("/consume", post(consume_handler).layer(RateLimitLayer::for_route("consume", RateLimitConfig::per_minute(10), &api_keys)))
+//! ```
+//!
+//! Limits can be overridden with `MILO_RATE_LIMIT_<ROUTE>=<requests>/<seconds>`
+//! (e.g. `MILO_RATE_LIMIT_CONSUME=20/60`) or disabled with `off`. Behind a
+//! reverse proxy, list it in `MILO_TRUSTED_PROXIES` so callers are told apart
+//! by their X-Forwarded-For address.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use tower::{Layer, Service};
+
+use crate::api_keys::{ApiKeyStore, API_KEY_HEADER};
+
+/// Buckets are swept once the map grows past this many keys
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// Comma-separated proxy addresses whose X-Forwarded-For the daemons believe
+pub const TRUSTED_PROXIES_VAR: &str = "MILO_TRUSTED_PROXIES";
+
+/// Maps a request to its bucket key; `None` lets the request through unlimited
+pub type KeyExtractor = Arc<dyn Fn(&Request<Body>) -> Option<String> + Send + Sync>;
+
+/// Bucket size and refill rate
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    /// Burst size (bucket capacity)
+    pub capacity: u32,
+    /// Tokens added back per second
+    pub refill_per_sec: f64,
+}
+
+impl RateLimitConfig {
+    /// `requests` per `secs`, allowing the whole allowance as a burst
+    pub fn new(requests: u32, secs: u64) -> Self {
+        Self {
+            capacity: requests,
+            refill_per_sec: requests as f64 / secs.max(1) as f64,
+        }
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, 60)
+    }
+
+    /// Parse `<requests>/<seconds>`
+    pub fn parse(s: &str) -> Option<Self> {
+        let (requests, secs) = s.trim().split_once('/')?;
+        let requests: u32 = requests.trim().parse().ok().filter(|r| *r > 0)?;
+        let secs: u64 = secs.trim().parse().ok().filter(|s| *s > 0)?;
+        Some(Self::new(requests, secs))
+    }
+
+    /// `MILO_RATE_LIMIT_<ROUTE>` override, `default` when unset; `None` when set to `off`
+    pub fn from_env(route: &str, default: Self) -> Option<Self> {
+        let var = format!("MILO_RATE_LIMIT_{}", route.to_uppercase().replace(['/', '-'], "_"));
+        match std::env::var(&var) {
+            Ok(v) if v.eq_ignore_ascii_case("off") => None,
+            Ok(v) => match Self::parse(&v) {
+                Some(config) => Some(config),
+                None => {
+                    println!("⚠️  Ignoring invalid {}={} (expected <requests>/<seconds> or off)", var, v);
+                    Some(default)
+                }
+            },
+            Err(_) => Some(default),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets for one route
+pub struct TokenBuckets {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl TokenBuckets {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one token for `key`, or return how long until one is available
+    pub fn try_acquire(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let capacity = self.config.capacity as f64;
+        let refill = self.config.refill_per_sec;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_KEYS && !buckets.contains_key(key) {
+            // Drop buckets that have refilled; they behave the same as new ones
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * refill < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / refill))
+        }
+    }
+}
+
+/// Caller's IP. X-Forwarded-For is only believed when the peer is one of
+/// `trusted_proxies` (anyone else could forge it): the caller is then the
+/// last hop that isn't a trusted proxy itself. Otherwise the peer address.
+pub fn forwarded_client_ip(headers: &HeaderMap, peer: SocketAddr, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let peer = peer.ip();
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    let Some(forwarded) = headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) else {
+        return peer;
+    };
+    let mut hops = Vec::new();
+    for hop in forwarded.split(',') {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => hops.push(ip),
+            // Everything left of a malformed hop is unverifiable
+            Err(_) => hops.clear(),
+        }
+    }
+    hops.iter()
+        .rev()
+        .find(|ip| !trusted_proxies.contains(ip))
+        .or(hops.first())
+        .copied()
+        .unwrap_or(peer)
+}
+
+/// Parse `var`'s comma-separated list of trusted proxy addresses
+pub fn parse_trusted_proxies(var: &str, value: &str) -> Result<Vec<IpAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| format!("{}: `{}` is not an IP address", var, s)))
+        .collect()
+}
+
+/// The daemons' trusted proxies from `MILO_TRUSTED_PROXIES`, read once.
+/// An invalid list trusts no proxy, so X-Forwarded-For is never believed.
+pub fn trusted_proxies() -> &'static [IpAddr] {
+    static PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        let value = std::env::var(TRUSTED_PROXIES_VAR).unwrap_or_default();
+        parse_trusted_proxies(TRUSTED_PROXIES_VAR, &value).unwrap_or_else(|e| {
+            println!("⚠️  {} (trusting no proxy)", e);
+            Vec::new()
+        })
+    })
+}
+
+/// Caller IP, see [`forwarded_client_ip`]; `None` without a peer address
+/// (requires serving with `into_make_service_with_connect_info::<SocketAddr>()`)
+pub fn client_ip(req: &Request<Body>) -> Option<IpAddr> {
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>()?.0;
+    Some(forwarded_client_ip(req.headers(), peer, trusted_proxies()))
+}
+
+/// One bucket per caller IP
+pub fn client_ip_key(req: &Request<Body>) -> Option<String> {
+    client_ip(req).map(|ip| ip.to_string())
+}
+
+/// Default key extractor: bots with a valid `X-Api-Key` get a bucket per
+/// key, everyone else one per caller IP. A key that `api_keys` doesn't accept
+/// counts against the caller's IP, so made-up keys can't mint fresh buckets.
+pub fn caller_key(req: &Request<Body>, api_keys: &ApiKeyStore) -> Option<String> {
+    req.headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| api_keys.authenticate(v.trim()))
+        .map(|identity| format!("key:{}", identity.key_id))
+        .or_else(|| client_ip_key(req))
+}
+
+/// Tower layer applying one route's token buckets
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    route: Arc<str>,
+    buckets: Option<Arc<TokenBuckets>>,
+    key_fn: KeyExtractor,
+}
+
+impl RateLimitLayer {
+    /// Limit per API key of `api_keys` or caller IP, with `default`
+    /// overridable through the environment
+    pub fn for_route(route: &str, default: RateLimitConfig, api_keys: &Arc<ApiKeyStore>) -> Self {
+        let api_keys = api_keys.clone();
+        Self::with_key(route, RateLimitConfig::from_env(route, default), move |req| caller_key(req, &api_keys))
+    }
+
+    /// Limit with a custom key extractor; `config: None` disables the limit
+    pub fn with_key<F>(route: &str, config: Option<RateLimitConfig>, key_fn: F) -> Self
+    where
+        F: Fn(&Request<Body>) -> Option<String> + Send + Sync + 'static,
+    {
+        match config {
+            Some(c) => println!("🚦 Rate limit /{}: {} burst, {:.2}/s", route, c.capacity, c.refill_per_sec),
+            None => println!("🚦 Rate limit /{}: off", route),
+        }
+        Self {
+            route: Arc::from(route),
+            buckets: config.map(|c| Arc::new(TokenBuckets::new(c))),
+            key_fn: Arc::new(key_fn),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        if let Some(buckets) = &self.layer.buckets {
+            if let Some(key) = (self.layer.key_fn)(&req) {
+                if let Err(retry_after) = buckets.try_acquire(&key, Instant::now()) {
+                    let retry_secs = retry_after.as_secs().max(1);
+                    println!("🚦 /{} rate limited: {} (retry in {}s)", self.layer.route, key, retry_secs);
+                    let response = (
+                        StatusCode::TOO_MANY_REQUESTS,
+                        [(header::RETRY_AFTER, retry_secs.to_string())],
+                        Json(serde_json::json!({
+                            "error": format!("Rate limit exceeded for /{}", self.layer.route),
+                            "retry_after_secs": retry_secs,
+                        })),
+                    )
+                        .into_response();
+                    return Box::pin(async move { Ok(response) });
+                }
+            }
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::Permission;
+    use axum::http::HeaderValue;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    const PROXY: &str = "10.0.0.1:443";
+
+    #[test]
+    fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let peer: SocketAddr = "203.0.113.7:5000".parse().unwrap();
+        let forged = forwarded("198.51.100.1");
+        assert_eq!(forwarded_client_ip(&forged, peer, &[]), peer.ip());
+        assert_eq!(forwarded_client_ip(&forged, peer, &["10.0.0.1".parse().unwrap()]), peer.ip());
+    }
+
+    #[test]
+    fn forwarded_for_names_the_client_behind_a_trusted_proxy() {
+        let peer: SocketAddr = PROXY.parse().unwrap();
+        let trusted: Vec<IpAddr> = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let client: IpAddr = "198.51.100.1".parse().unwrap();
+
+        assert_eq!(forwarded_client_ip(&forwarded("198.51.100.1"), peer, &trusted), client);
+        // Through a second trusted proxy
+        assert_eq!(forwarded_client_ip(&forwarded("198.51.100.1, 10.0.0.2"), peer, &trusted), client);
+        // A hop the client prepended itself is not believed
+        assert_eq!(forwarded_client_ip(&forwarded("192.0.2.99, 198.51.100.1"), peer, &trusted), client);
+        assert_eq!(forwarded_client_ip(&forwarded("192.0.2.99, junk, 198.51.100.1"), peer, &trusted), client);
+        // Nothing usable: the proxy itself
+        assert_eq!(forwarded_client_ip(&HeaderMap::new(), peer, &trusted), peer.ip());
+        assert_eq!(forwarded_client_ip(&forwarded("junk"), peer, &trusted), peer.ip());
+    }
+
+    #[test]
+    fn parses_trusted_proxies() {
+        assert_eq!(parse_trusted_proxies(TRUSTED_PROXIES_VAR, "").unwrap(), Vec::<IpAddr>::new());
+        assert_eq!(
+            parse_trusted_proxies(TRUSTED_PROXIES_VAR, "10.0.0.1, ::1,").unwrap(),
+            vec!["10.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]
+        );
+        let err = parse_trusted_proxies("FAUCET_TRUSTED_PROXIES", "10.0.0.0/8").unwrap_err();
+        assert!(err.starts_with("FAUCET_TRUSTED_PROXIES") && err.contains("10.0.0.0/8"));
+    }
+
+    #[test]
+    fn rotating_forwarded_for_keeps_an_untrusted_caller_in_one_bucket() {
+        let peer: SocketAddr = "203.0.113.7:5000".parse().unwrap();
+        let request = |hop: &str| {
+            let mut req = Request::builder().header("x-forwarded-for", hop).body(Body::empty()).unwrap();
+            req.extensions_mut().insert(ConnectInfo(peer));
+            req
+        };
+        assert_eq!(client_ip_key(&request("198.51.100.1")), Some(peer.ip().to_string()));
+        assert_eq!(client_ip_key(&request("198.51.100.2")), Some(peer.ip().to_string()));
+    }
+
+    fn api_keys(name: &str) -> ApiKeyStore {
+        let dir = std::env::temp_dir().join(format!("milo_rate_limit_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        ApiKeyStore::open(dir.join("api_keys.json"), dir.join("audit.jsonl"))
+    }
+
+    fn keyed_request(key: &str) -> Request<Body> {
+        let peer: SocketAddr = "203.0.113.7:5000".parse().unwrap();
+        let mut req = Request::builder().header(API_KEY_HEADER, key).body(Body::empty()).unwrap();
+        req.extensions_mut().insert(ConnectInfo(peer));
+        req
+    }
+
+    #[test]
+    fn only_authenticated_keys_get_their_own_bucket() {
+        let store = api_keys("authenticated");
+        let (record, key) = store.create("0xabc", vec![Permission::Trade], "bot").unwrap();
+        assert_eq!(caller_key(&keyed_request(&key), &store), Some(format!("key:{}", record.key_id)));
+
+        // Well-formed but unknown, or a known id with the wrong secret: the IP bucket
+        for forged in ["milo_0011aabb_x", &format!("milo_{}_wrong", record.key_id)] {
+            assert_eq!(caller_key(&keyed_request(forged), &store), Some("203.0.113.7".to_string()));
+        }
+        store.revoke(&record.key_id).unwrap();
+        assert_eq!(caller_key(&keyed_request(&key), &store), Some("203.0.113.7".to_string()));
+    }
+
+    #[test]
+    fn random_keys_share_the_callers_bucket() {
+        let store = api_keys("random");
+        let buckets = TokenBuckets::new(RateLimitConfig::new(2, 60));
+        let now = Instant::now();
+        let results: Vec<bool> = (0..3)
+            .map(|i| {
+                let key = caller_key(&keyed_request(&format!("milo_{:08x}_x", i)), &store).unwrap();
+                buckets.try_acquire(&key, now).is_ok()
+            })
+            .collect();
+        assert_eq!(results, vec![true, true, false]);
+    }
+}
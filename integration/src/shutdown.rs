@@ -0,0 +1,109 @@
+//! Graceful shutdown for the services
+//!
+//! `main` races `axum::serve` against `shutdown_signal()` (Ctrl-C or
+//! SIGTERM). Once the signal wins, the listener is dropped so no new requests
+//! come in, `request()` tells the worker thread to stop, and `wait_for_drain`
+//! gives requests already handed to the worker up to a timeout to finish.
+//!
+//! Handlers call `begin()` before sending a request to the worker (and
+//! `cancel()` if the send fails); the worker calls `finish()` once it has
+//! answered it. The worker leaves its loop when `should_exit()` is true.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long `main` waits for in-flight worker requests after a signal
+pub const DRAIN_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    /// Requests sent to the worker and not yet answered
+    in_flight: AtomicUsize,
+    /// Requests answered after shutdown was requested
+    drained: AtomicU64,
+}
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// A request is about to be sent to the worker
+    pub fn begin(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The send failed; the worker never saw the request
+    pub fn cancel(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// The worker answered a request
+    pub fn finish(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if self.is_requested() {
+            self.drained.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn drained(&self) -> u64 {
+        self.drained.load(Ordering::SeqCst)
+    }
+
+    /// Shutdown was requested and nothing is left for the worker
+    pub fn should_exit(&self) -> bool {
+        self.is_requested() && self.in_flight() == 0
+    }
+
+    /// Poll until no request is in flight or `timeout` passes.
+    /// Returns whether everything drained.
+    pub async fn wait_for_drain(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while self.in_flight() > 0 {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        true
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("⚠️  Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                eprintln!("⚠️  Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -0,0 +1,155 @@
+//! Effective configuration echo at startup
+//!
+//! Each service records every resolved setting in a `StartupConfig` as it
+//! reads it, then prints the whole set once with `log_startup_config`. Every
+//! line says where the value came from (`env`, a config file, the command line
+//! or the built-in default), so a misconfigured deployment can be diagnosed from the logs
+//! alone. Secrets are only reported as set or unset.
+
+use std::fmt::Display;
+
+use milo_config::MiloConfig;
+
+use crate::milo_accounts::get_faucet_id_by_symbol;
+use crate::pool_config::PoolsConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Set through an environment variable
+    Env,
+    /// Read from a config file (the path)
+    File(String),
+    /// Given on the command line
+    Arg,
+    /// Built-in default or compiled-in constant
+    Default,
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Env => write!(f, "env"),
+            ConfigSource::File(path) => write!(f, "file:{}", path),
+            ConfigSource::Arg => write!(f, "arg"),
+            ConfigSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+/// Resolved settings of one service, in the order they were recorded
+#[derive(Debug, Clone)]
+pub struct StartupConfig {
+    service: String,
+    entries: Vec<ConfigEntry>,
+}
+
+/// Where a `MiloConfig` value came from: milo.toml or the binary's default
+pub fn milo_config_source(config: &MiloConfig, key: &str) -> ConfigSource {
+    match config.source_file(key) {
+        Some(path) => ConfigSource::File(path.display().to_string()),
+        None => ConfigSource::Default,
+    }
+}
+
+/// Whether an environment variable is set to a non-empty value
+fn env_is_set(var: &str) -> bool {
+    std::env::var(var).is_ok_and(|v| !v.trim().is_empty())
+}
+
+impl StartupConfig {
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: service.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, key: &str, value: impl Display, source: ConfigSource) -> &mut Self {
+        self.entries.push(ConfigEntry {
+            key: key.to_string(),
+            value: value.to_string(),
+            source,
+        });
+        self
+    }
+
+    /// A value resolved from `var`, falling back to a default when unset.
+    /// An unparseable variable still counts as `env`; the value shows what
+    /// was actually used.
+    pub fn env(&mut self, var: &str, value: impl Display) -> &mut Self {
+        let source = if env_is_set(var) { ConfigSource::Env } else { ConfigSource::Default };
+        self.record(var, value, source)
+    }
+
+    /// A secret read from `var`; only whether it is set is printed
+    pub fn secret(&mut self, var: &str) -> &mut Self {
+        let set = env_is_set(var);
+        let source = if set { ConfigSource::Env } else { ConfigSource::Default };
+        self.record(var, if set { "(set)" } else { "(unset)" }, source)
+    }
+
+    /// A value taken from the command line when `given`, else its default
+    pub fn arg(&mut self, key: &str, value: impl Display, given: bool) -> &mut Self {
+        self.record(key, value, if given { ConfigSource::Arg } else { ConfigSource::Default })
+    }
+
+    /// A value loaded from a config file
+    pub fn file(&mut self, key: &str, path: &str, value: impl Display) -> &mut Self {
+        self.record(key, value, ConfigSource::File(path.to_string()))
+    }
+
+    /// A setting resolved by milo.toml: the file when it set `key`, else the default
+    pub fn milo(&mut self, key: &str, value: impl Display, config: &MiloConfig) -> &mut Self {
+        self.record(key, value, milo_config_source(config, key))
+    }
+
+    /// A compiled-in value (paths, ports, endpoints)
+    pub fn constant(&mut self, key: &str, value: impl Display) -> &mut Self {
+        self.record(key, value, ConfigSource::Default)
+    }
+
+    /// Every pool in a loaded pools.json with the faucets of its token pair
+    pub fn pools(&mut self, path: &str, pools: &PoolsConfig) -> &mut Self {
+        for entry in &pools.pools {
+            let tokens: Vec<String> = entry
+                .pair
+                .split('_')
+                .map(|symbol| {
+                    let symbol = symbol.to_uppercase();
+                    match get_faucet_id_by_symbol(&symbol) {
+                        Some(faucet_id) => format!("{}={}", symbol, faucet_id),
+                        None => symbol,
+                    }
+                })
+                .collect();
+            let value = format!("{} ({})", entry.pool_id.to_hex(), tokens.join(" / "));
+            self.file(&format!("pool.{}", entry.pair), path, value);
+        }
+        self
+    }
+
+    pub fn entries(&self) -> &[ConfigEntry] {
+        &self.entries
+    }
+}
+
+/// Print every recorded setting, one per line, aligned
+pub fn log_startup_config(config: &StartupConfig) {
+    let key_width = config.entries.iter().map(|e| e.key.len()).max().unwrap_or(0);
+    let value_width = config.entries.iter().map(|e| e.value.chars().count()).max().unwrap_or(0).min(66);
+    println!("⚙️  Effective configuration ({}):", config.service);
+    for entry in &config.entries {
+        println!(
+            "   {:<key_width$}  {:<value_width$}  [{}]",
+            entry.key, entry.value, entry.source,
+        );
+    }
+    println!();
+}
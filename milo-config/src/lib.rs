@@ -0,0 +1,192 @@
+//! Deployment settings shared by every Milo binary
+//!
+//! `milo.toml` in the working directory says where keys and client stores
+//! live, which node to talk to, how often the daemons poll and which port a
+//! server listens on. Top-level keys apply to every binary; the table named
+//! after a binary (`[cli]`, `[faucet_server]`, `[swap_daemon]`,
+//! `[liquidity_daemon]`) overrides them for that one. Store paths and ports
+//! can only be set per binary, since no two binaries may share them. Without
+//! the file every binary keeps its compiled-in defaults.
+//!
+//! ```toml
+//! rpc_endpoint = "testnet"
+//! keystore_path = "/data/keystore"
+//!
+//! [swap_daemon]
+//! store_path = "/data/swap_store.sqlite3"
+//! port = 8080
+//! poll_interval_secs = 30
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use miden_client::rpc::Endpoint;
+use serde::Deserialize;
+
+pub const CONFIG_PATH: &str = "milo.toml";
+
+/// The binary a config is loaded for; picks its defaults and its table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    /// `milo` and the legacy scripts, run from `integration/`
+    Cli,
+    FaucetServer,
+    /// Run from the repository root, like the liquidity daemon
+    SwapDaemon,
+    LiquidityDaemon,
+}
+
+impl Service {
+    /// Name of the service's table in milo.toml
+    pub fn table(self) -> &'static str {
+        match self {
+            Service::Cli => "cli",
+            Service::FaucetServer => "faucet_server",
+            Service::SwapDaemon => "swap_daemon",
+            Service::LiquidityDaemon => "liquidity_daemon",
+        }
+    }
+
+    /// Compiled-in settings of the service
+    pub fn defaults(self) -> MiloConfig {
+        let (keystore_path, store_path, port) = match self {
+            Service::Cli => ("keystore", "store.sqlite3", 0),
+            Service::FaucetServer => ("keystore", "faucet_store.sqlite3", 8084),
+            Service::SwapDaemon => ("integration/keystore", "integration/swap_store.sqlite3", 8080),
+            Service::LiquidityDaemon => ("integration/keystore", "integration/liquidity_store.sqlite3", 8090),
+        };
+        MiloConfig {
+            keystore_path: keystore_path.to_string(),
+            store_path: store_path.to_string(),
+            rpc_endpoint: "testnet".to_string(),
+            poll_interval_secs: 15,
+            port,
+            file: None,
+            from_file: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiloConfig {
+    pub keystore_path: String,
+    /// The Miden client's SQLite store
+    pub store_path: String,
+    /// `testnet`, `devnet`, `localhost` or a node URL
+    pub rpc_endpoint: String,
+    /// Seconds between the daemons' auto-polls
+    pub poll_interval_secs: u64,
+    /// Listening port of a server (unused by the CLI)
+    pub port: u16,
+    /// The milo.toml that was read, if any
+    file: Option<PathBuf>,
+    /// Keys whose value came from that file
+    from_file: Vec<&'static str>,
+}
+
+/// Keys of one milo.toml table; anything unset keeps the value below it
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Overrides {
+    keystore_path: Option<String>,
+    store_path: Option<String>,
+    rpc_endpoint: Option<String>,
+    poll_interval_secs: Option<u64>,
+    port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    keystore_path: Option<String>,
+    rpc_endpoint: Option<String>,
+    poll_interval_secs: Option<u64>,
+    cli: Option<Overrides>,
+    faucet_server: Option<Overrides>,
+    swap_daemon: Option<Overrides>,
+    liquidity_daemon: Option<Overrides>,
+}
+
+impl MiloConfig {
+    /// milo.toml in the working directory, or the service's defaults when
+    /// there is none. A file that exists but is invalid is an error.
+    pub fn load(service: Service) -> Result<Self> {
+        Self::load_from(CONFIG_PATH, service)
+    }
+
+    pub fn load_from(path: impl AsRef<Path>, service: Service) -> Result<Self> {
+        let path = path.as_ref();
+        let mut config = service.defaults();
+        if !path.exists() {
+            return Ok(config);
+        }
+        let text = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        let file: ConfigFile =
+            toml::from_str(&text).with_context(|| format!("could not parse {}", path.display()))?;
+
+        config.apply(Overrides {
+            keystore_path: file.keystore_path,
+            rpc_endpoint: file.rpc_endpoint,
+            poll_interval_secs: file.poll_interval_secs,
+            ..Default::default()
+        });
+        let table = match service {
+            Service::Cli => file.cli,
+            Service::FaucetServer => file.faucet_server,
+            Service::SwapDaemon => file.swap_daemon,
+            Service::LiquidityDaemon => file.liquidity_daemon,
+        };
+        if let Some(table) = table {
+            config.apply(table);
+        }
+        config.file = Some(path.to_path_buf());
+
+        config
+            .endpoint()
+            .with_context(|| format!("{}: invalid rpc_endpoint", path.display()))?;
+        if service != Service::Cli && config.port == 0 {
+            anyhow::bail!("{}: [{}] port must not be 0", path.display(), service.table());
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, overrides: Overrides) {
+        if let Some(v) = overrides.keystore_path {
+            self.keystore_path = v;
+            self.from_file.push("keystore_path");
+        }
+        if let Some(v) = overrides.store_path {
+            self.store_path = v;
+            self.from_file.push("store_path");
+        }
+        if let Some(v) = overrides.rpc_endpoint {
+            self.rpc_endpoint = v;
+            self.from_file.push("rpc_endpoint");
+        }
+        if let Some(v) = overrides.poll_interval_secs {
+            self.poll_interval_secs = v;
+            self.from_file.push("poll_interval_secs");
+        }
+        if let Some(v) = overrides.port {
+            self.port = v;
+            self.from_file.push("port");
+        }
+    }
+
+    /// The node `rpc_endpoint` names
+    pub fn endpoint(&self) -> Result<Endpoint> {
+        match self.rpc_endpoint.as_str() {
+            "testnet" => Ok(Endpoint::testnet()),
+            "devnet" => Ok(Endpoint::devnet()),
+            "localhost" => Ok(Endpoint::localhost()),
+            url => Endpoint::try_from(url).map_err(|e| anyhow::anyhow!("{}: {}", url, e)),
+        }
+    }
+
+    /// The milo.toml that set `key`, or `None` when it is a default
+    pub fn source_file(&self, key: &str) -> Option<&Path> {
+        self.file.as_deref().filter(|_| self.from_file.contains(&key))
+    }
+}
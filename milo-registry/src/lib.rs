@@ -0,0 +1,127 @@
+//! Token metadata shared by every Milo binary
+//!
+//! `milo setup` creates the MILO, MELO and MUSDC faucets and writes their IDs
+//! to `accounts.json`; every new setup replaces all three. Binaries load that
+//! file with [`TokenRegistry::load`] at startup and [`TokenRegistry::install`]
+//! it, so a symbol resolves to the faucets of the last setup rather than to
+//! IDs compiled in at some earlier one. Without the file the compiled-in IDs
+//! below are used.
+
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use miden_client::account::AccountId;
+use serde::Deserialize;
+
+/// MILO Token Faucet ID (Orange color token)
+pub const MILO_FAUCET_ID_HEX: &str = "0xa28b4f998be3a32047b88ae20e0a35";
+/// MELO Token Faucet ID (Green color token)
+pub const MELO_FAUCET_ID_HEX: &str = "0x13bde3e49deaf92074138cbcaf8d4f";
+/// MUSDC Token Faucet ID (Blue - USDC stablecoin)
+pub const MUSDC_FAUCET_ID_HEX: &str = "0x2c09e8d9f4ef022044cfee2d14d3a8";
+
+/// Decimals `milo setup` creates every faucet with
+pub const FAUCET_DECIMALS: u8 = 8;
+/// Max supply `milo setup` creates every faucet with (10 billion tokens, 8 decimals)
+pub const FAUCET_MAX_SUPPLY: u64 = 1_000_000_000_000_000_000;
+
+static INSTALLED: OnceLock<TokenRegistry> = OnceLock::new();
+
+/// One token faucet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaucetInfo {
+    pub symbol: String,
+    pub faucet_id: AccountId,
+    pub decimals: u8,
+    /// In base units
+    pub max_supply: u64,
+}
+
+/// The faucet fields of accounts.json; the wallet fields are the CLI's business
+#[derive(Deserialize)]
+struct AccountsFile {
+    milo_faucet_id: String,
+    melo_faucet_id: String,
+    musdc_faucet_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenRegistry {
+    faucets: Vec<FaucetInfo>,
+    /// `faucets[i].faucet_id` as hex, for callers that compare strings
+    faucet_id_hexes: Vec<String>,
+}
+
+impl TokenRegistry {
+    /// The faucets in `path` (accounts.json), or the compiled-in ones when
+    /// the file doesn't exist. A file that exists but can't be read or holds
+    /// an invalid ID is an error rather than a silent fallback to IDs a newer
+    /// setup replaced.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::compiled_in());
+        }
+        let text = fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+        let file: AccountsFile =
+            serde_json::from_str(&text).with_context(|| format!("could not parse {}", path.display()))?;
+        Self::from_hexes([("MILO", &file.milo_faucet_id), ("MELO", &file.melo_faucet_id), ("MUSDC", &file.musdc_faucet_id)])
+            .with_context(|| format!("{}: invalid faucet ID", path.display()))
+    }
+
+    /// The faucet IDs compiled into this crate
+    pub fn compiled_in() -> Self {
+        Self::from_hexes([("MILO", MILO_FAUCET_ID_HEX), ("MELO", MELO_FAUCET_ID_HEX), ("MUSDC", MUSDC_FAUCET_ID_HEX)])
+            .expect("compiled-in faucet IDs are valid")
+    }
+
+    fn from_hexes<S: AsRef<str>>(hexes: [(&str, S); 3]) -> Result<Self> {
+        let mut faucets = Vec::with_capacity(hexes.len());
+        let mut faucet_id_hexes = Vec::with_capacity(hexes.len());
+        for (symbol, hex) in hexes {
+            let hex = hex.as_ref();
+            let faucet_id = AccountId::from_hex(hex).with_context(|| format!("{}: {}", symbol, hex))?;
+            faucets.push(FaucetInfo {
+                symbol: symbol.to_string(),
+                faucet_id,
+                decimals: FAUCET_DECIMALS,
+                max_supply: FAUCET_MAX_SUPPLY,
+            });
+            faucet_id_hexes.push(faucet_id.to_hex());
+        }
+        Ok(Self { faucets, faucet_id_hexes })
+    }
+
+    /// Make this the registry [`TokenRegistry::global`] returns. Binaries call
+    /// it once at startup; a registry that was already installed (or already
+    /// handed out by `global`) stays.
+    pub fn install(self) -> &'static TokenRegistry {
+        INSTALLED.get_or_init(|| self)
+    }
+
+    /// The installed registry, or the compiled-in one if none was installed
+    pub fn global() -> &'static TokenRegistry {
+        INSTALLED.get_or_init(Self::compiled_in)
+    }
+
+    /// The faucet of a token symbol (case insensitive)
+    pub fn faucet(&self, symbol: &str) -> Option<FaucetInfo> {
+        self.position(symbol).map(|i| self.faucets[i].clone())
+    }
+
+    /// Faucet ID hex of a token symbol (case insensitive)
+    pub fn faucet_id_hex(&self, symbol: &str) -> Option<&str> {
+        self.position(symbol).map(|i| self.faucet_id_hexes[i].as_str())
+    }
+
+    /// Every faucet, in setup order (MILO, MELO, MUSDC)
+    pub fn faucets(&self) -> &[FaucetInfo] {
+        &self.faucets
+    }
+
+    fn position(&self, symbol: &str) -> Option<usize> {
+        self.faucets.iter().position(|f| f.symbol.eq_ignore_ascii_case(symbol))
+    }
+}
@@ -0,0 +1,27 @@
+//! Client-side building blocks for Milo swaps and deposits
+//!
+//! A swap or deposit is a P2ID note from the user's wallet to a pool,
+//! registered with the pool's daemon at `/track_note` so it knows what the
+//! note is for. [`SwapTxBuilder`] and [`DepositTxBuilder`] create those notes
+//! and hand back a `TransactionRequestBuilder` for the caller's own client to
+//! submit, plus the `/track_note` bodies to post once the transaction is in.
+//!
+//! ```ignore
+//! let min_out = estimate_output(reserve_in, reserve_out, 1_000, 10) * 995 / 1000;
+//! let builder = SwapTxBuilder::new(pool_id, milo_id, musdc_id, 1_000, min_out, wallet_id);
+//! let swap = builder.build(client.rng())?;
+//! let tx_id = client.submit_new_transaction(wallet_id, swap.request.build()?).await?;
+//! // POST builder.track_note_body(swap.note_id, now_ms) to the swap daemon's /track_note
+//! ```
+//!
+//! [`estimate_output`] is the constant-product formula the swap daemon
+//! executes with. It has no dependencies, so with `default-features = false`
+//! the crate builds for WASM frontends.
+
+mod quote;
+#[cfg(feature = "tx")]
+mod tx;
+
+pub use quote::estimate_output;
+#[cfg(feature = "tx")]
+pub use tx::{DepositTx, DepositTxBuilder, SwapTx, SwapTxBuilder};
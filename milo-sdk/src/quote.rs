@@ -0,0 +1,20 @@
+//! Swap quotes without a client
+
+/// Constant-product output for selling `amount_in` into a pool holding
+/// `reserve_in` / `reserve_out`, after a fee of `fee_bps` taken from the
+/// input (10 = 0.1%):
+///
+/// amount_out = (amount_in * (10000 - fee_bps) * reserve_out) / (reserve_in * 10000 + amount_in * (10000 - fee_bps))
+///
+/// Rounds down, like the daemon. 0 for an empty pool; fees above 100% are
+/// treated as 100%.
+pub fn estimate_output(reserve_in: u64, reserve_out: u64, amount_in: u64, fee_bps: u64) -> u64 {
+    let fee_multiplier = 10_000u128 - fee_bps.min(10_000) as u128;
+    let amount_in_with_fee = amount_in as u128 * fee_multiplier;
+    let denominator = reserve_in as u128 * 10_000 + amount_in_with_fee;
+    if denominator == 0 {
+        return 0;
+    }
+    // Always below reserve_out, so it fits in a u64
+    (amount_in_with_fee * reserve_out as u128 / denominator) as u64
+}
@@ -0,0 +1,158 @@
+//! Swap and deposit transactions from the user's wallet
+
+use anyhow::{ensure, Result};
+use miden_client::{
+    account::AccountId,
+    asset::FungibleAsset,
+    crypto::FeltRng,
+    note::{create_p2id_note, Note, NoteId, NoteType},
+    transaction::{OutputNote, TransactionRequestBuilder},
+    Felt,
+};
+use serde_json::{json, Value as JsonValue};
+
+/// One public P2ID note moving `amount` of `faucet_id` from `user_id` to `pool_id`
+fn pool_note(user_id: AccountId, pool_id: AccountId, faucet_id: AccountId, amount: u64, rng: &mut impl FeltRng) -> Result<Note> {
+    ensure!(amount > 0, "amount of {} must be greater than 0", faucet_id.to_hex());
+    let asset = FungibleAsset::new(faucet_id, amount)?;
+    Ok(create_p2id_note(user_id, pool_id, vec![asset.into()], NoteType::Public, Felt::new(0), rng)?)
+}
+
+/// Sell `amount_in` of `sell_token` for at least `min_amount_out` of `buy_token`
+#[derive(Debug, Clone, Copy)]
+pub struct SwapTxBuilder {
+    pool_id: AccountId,
+    sell_token: AccountId,
+    buy_token: AccountId,
+    amount_in: u64,
+    min_amount_out: u64,
+    user_id: AccountId,
+}
+
+/// A built swap: submit `request` from the user's wallet, then register
+/// `note_id` with [`SwapTxBuilder::track_note_body`]
+pub struct SwapTx {
+    pub request: TransactionRequestBuilder,
+    pub note_id: NoteId,
+}
+
+impl SwapTxBuilder {
+    pub fn new(
+        pool_id: AccountId,
+        sell_token: AccountId,
+        buy_token: AccountId,
+        amount_in: u64,
+        min_amount_out: u64,
+        user_id: AccountId,
+    ) -> Self {
+        Self { pool_id, sell_token, buy_token, amount_in, min_amount_out, user_id }
+    }
+
+    /// Create the swap note; `rng` is the submitting client's (`client.rng()`)
+    pub fn build(self, rng: &mut impl FeltRng) -> Result<SwapTx> {
+        ensure!(self.sell_token != self.buy_token, "sell and buy token are the same");
+        let note = pool_note(self.user_id, self.pool_id, self.sell_token, self.amount_in, rng)?;
+        let note_id = note.id();
+        let request = TransactionRequestBuilder::new().own_output_notes(vec![OutputNote::Full(note)]);
+        Ok(SwapTx { request, note_id })
+    }
+
+    /// Body of the swap daemon's `POST /track_note` for the note built from this
+    pub fn track_note_body(&self, note_id: NoteId, timestamp_ms: u64) -> JsonValue {
+        json!({
+            "note_id": note_id.to_hex(),
+            "note_type": "P2ID_SWAP",
+            "pool_account_id": self.pool_id.to_hex(),
+            "swap_info": {
+                "noteId": note_id.to_hex(),
+                "poolAccountId": self.pool_id.to_hex(),
+                "sellTokenId": self.sell_token.to_hex(),
+                "buyTokenId": self.buy_token.to_hex(),
+                "amountIn": self.amount_in.to_string(),
+                "minAmountOut": self.min_amount_out.to_string(),
+                "userAccountId": self.user_id.to_hex(),
+                "timestamp": timestamp_ms,
+            },
+        })
+    }
+}
+
+/// Deposit `amount_a` of `token_a_faucet` and `amount_b` of `token_b_faucet`
+/// into a pool: one note per token, both in a single transaction
+#[derive(Debug, Clone, Copy)]
+pub struct DepositTxBuilder {
+    pool_id: AccountId,
+    token_a_faucet: AccountId,
+    token_b_faucet: AccountId,
+    amount_a: u64,
+    amount_b: u64,
+    user_id: AccountId,
+    min_lp_a: u64,
+    min_lp_b: u64,
+}
+
+/// A built deposit: submit `request` from the user's wallet, then register
+/// both notes with [`DepositTxBuilder::track_note_bodies`]
+pub struct DepositTx {
+    pub request: TransactionRequestBuilder,
+    pub note_a_id: NoteId,
+    pub note_b_id: NoteId,
+}
+
+impl DepositTxBuilder {
+    pub fn new(
+        pool_id: AccountId,
+        token_a_faucet: AccountId,
+        token_b_faucet: AccountId,
+        amount_a: u64,
+        amount_b: u64,
+        user_id: AccountId,
+    ) -> Self {
+        Self { pool_id, token_a_faucet, token_b_faucet, amount_a, amount_b, user_id, min_lp_a: 0, min_lp_b: 0 }
+    }
+
+    /// Smallest LP amount each note may be credited with; below it the
+    /// liquidity daemon refunds the note (0, the default, accepts any)
+    pub fn min_lp_amount_out(mut self, min_lp_a: u64, min_lp_b: u64) -> Self {
+        self.min_lp_a = min_lp_a;
+        self.min_lp_b = min_lp_b;
+        self
+    }
+
+    /// Create both deposit notes; `rng` is the submitting client's (`client.rng()`)
+    pub fn build(self, rng: &mut impl FeltRng) -> Result<DepositTx> {
+        ensure!(self.token_a_faucet != self.token_b_faucet, "token A and token B are the same");
+        let note_a = pool_note(self.user_id, self.pool_id, self.token_a_faucet, self.amount_a, rng)?;
+        let note_b = pool_note(self.user_id, self.pool_id, self.token_b_faucet, self.amount_b, rng)?;
+        let (note_a_id, note_b_id) = (note_a.id(), note_b.id());
+        let request = TransactionRequestBuilder::new()
+            .own_output_notes(vec![OutputNote::Full(note_a), OutputNote::Full(note_b)]);
+        Ok(DepositTx { request, note_a_id, note_b_id })
+    }
+
+    /// Bodies of the liquidity daemon's `POST /track_note`, one per note
+    /// built from this
+    pub fn track_note_bodies(&self, note_a_id: NoteId, note_b_id: NoteId, timestamp_ms: u64) -> [JsonValue; 2] {
+        let b = self;
+        let body = |note_id: NoteId, token: AccountId, amount: u64, min_lp: u64| {
+            json!({
+                "note_id": note_id.to_hex(),
+                "note_type": "P2ID_DEPOSIT",
+                "pool_account_id": b.pool_id.to_hex(),
+                "deposit_info": {
+                    "noteId": note_id.to_hex(),
+                    "poolAccountId": b.pool_id.to_hex(),
+                    "tokenId": token.to_hex(),
+                    "amount": amount.to_string(),
+                    "userAccountId": b.user_id.to_hex(),
+                    "minLpAmountOut": min_lp.to_string(),
+                    "timestamp": timestamp_ms,
+                },
+            })
+        };
+        [
+            body(note_a_id, b.token_a_faucet, b.amount_a, b.min_lp_a),
+            body(note_b_id, b.token_b_faucet, b.amount_b, b.min_lp_b),
+        ]
+    }
+}
@@ -1,43 +1,125 @@
 //! Liquidity Daemon - Consumes P2ID DEPOSIT notes for pool accounts
-//! Runs on port 8090
+//! Runs on port 8090 (milo.toml `[liquidity_daemon] port`)
 //! Pattern: Same as swap_daemon.rs (P2ID notes + metadata)
 
 use anyhow::{Context, Result};
 use axum::{
-    extract::{Query, State},
-    http::{header, Method, StatusCode},
+    extract::{Path as UrlPath, Query, State},
+    http::{header, HeaderMap, Method, StatusCode},
     response::{IntoResponse, Json},
     routing::{get, post},
     Router,
 };
 use miden_client::{
-    account::AccountId,
+    account::{Account, AccountId},
     asset::FungibleAsset,
     builder::ClientBuilder,
     keystore::FilesystemKeyStore,
-    note::{create_p2id_note, NoteType},
-    rpc::{Endpoint, GrpcClient},
-    store::TransactionFilter,
-    transaction::{OutputNote, TransactionRequestBuilder},
+    note::{create_p2id_note, Note, NoteType},
+    rpc::GrpcClient,
+    store::{InputNoteRecord, TransactionFilter},
+    transaction::{OutputNote, TransactionRequest, TransactionRequestBuilder, TransactionScript, TransactionStatus},
+    utils::{Deserializable, Serializable},
     Felt,
 };
+use integration::api_keys::{ApiKeyStore, Permission};
+use integration::ledger::{self, Direction, LedgerEntry};
+use integration::maintenance::{Maintenance, SetMaintenanceRequest};
+use integration::milo_accounts::get_faucet_id_by_symbol;
+use milo_registry::TokenRegistry;
+use integration::cli::add_liquidity::{add_liquidity_to_pool, create_lp_faucet, create_pool_account};
+use integration::cli::AccountsConfig;
+use integration::helpers::reserve_amount;
+use integration::lp_token::{
+    lp_burn_note, lp_redemption, lp_supply, lp_symbol_owner, lp_token_symbol, lp_tokens_for_deposit,
+};
+use integration::pool_component::{record_reserves_script, script_for_pool, sync_reserves_script};
+use integration::pool_config::{
+    load_pool_curves_config, load_pools_config, save_pool_curves_config, save_pools_config, AmmCurve, PoolsConfig,
+    MAX_AMPLIFICATION, POOL_CURVES_CONFIG_PATH,
+};
+use integration::rate_limit::{trusted_proxies, RateLimitConfig, RateLimitLayer, TRUSTED_PROXIES_VAR};
+use integration::startup_config::{log_startup_config, StartupConfig};
+use milo_config::{MiloConfig, Service};
+use pool_daemon::metrics::MetricsState;
+use pool_daemon::poll::resolve_poll_interval;
+use pool_daemon::retry::{backoff_delay, sync_with_retry, SYNC_BASE_DELAY_MS, SYNC_MAX_ATTEMPTS};
 use miden_client_sqlite_store::ClientBuilderSqliteExt;
 use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fs,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     time::{Duration, Instant},
 };
-use tokio::time::sleep;
+use tokio::{sync::Semaphore, time::sleep};
 use tower_http::cors::{Any, CorsLayer};
 
 type MidenClient = miden_client::Client<FilesystemKeyStore<StdRng>>;
 
-const KEYSTORE_PATH: &str = "integration/keystore";
-const STORE_PATH: &str = "integration/liquidity_store.sqlite3";
+// milo.toml `[liquidity_daemon]` settings, loaded at the top of main
+static MILO_CONFIG: OnceLock<MiloConfig> = OnceLock::new();
+
+fn milo_config() -> &'static MiloConfig {
+    MILO_CONFIG.get().expect("milo.toml loaded in main")
+}
+
+// Faucet IDs written by `milo setup`, run from integration/
+const ACCOUNTS_PATH: &str = "integration/accounts.json";
+// Default cap on concurrent transaction submissions (MILO_MAX_INFLIGHT_SUBMISSIONS)
+const DEFAULT_MAX_INFLIGHT_SUBMISSIONS: usize = 4;
+// Default cap on notes processed per pool per cycle (MILO_MAX_NOTES_PER_CYCLE)
+const DEFAULT_MAX_NOTES_PER_CYCLE: usize = 20;
+// Cached consumable-notes lists older than this are re-fetched even if not drained
+const NOTE_CACHE_TTL_SECS: u64 = 120;
+// Default window in which a previous sync is reused instead of syncing again (MILO_SYNC_FRESHNESS_MS)
+const DEFAULT_SYNC_FRESHNESS_MS: u64 = 3000;
+
+// When the worker's client last synced successfully
+struct SyncFreshness {
+    last_sync: Option<Instant>,
+    window: Duration,
+}
+
+impl SyncFreshness {
+    fn is_fresh(&self) -> bool {
+        self.last_sync.is_some_and(|t| t.elapsed() < self.window)
+    }
+
+    // Age of the local state in ms (None before the first successful sync)
+    fn state_age_ms(&self) -> Option<u64> {
+        self.last_sync.map(|t| t.elapsed().as_millis() as u64)
+    }
+}
+
+/// Sync unless the last successful sync is inside the freshness window.
+/// `force` always syncs. Returns whether a sync ran.
+async fn sync_if_stale(client: &mut MidenClient, freshness: &mut SyncFreshness, force: bool) -> Result<bool> {
+    if !force && freshness.is_fresh() {
+        return Ok(false);
+    }
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+    freshness.last_sync = Some(Instant::now());
+    Ok(true)
+}
+
+// Consumable notes fetched for one pool, paged through across poll cycles
+struct NoteBatch {
+    notes: VecDeque<InputNoteRecord>,
+    fetched_at: Instant,
+}
+
+// Per-pool note batches plus the per-cycle processing cap
+struct NoteCache {
+    batches: HashMap<AccountId, NoteBatch>,
+    max_per_cycle: usize,
+}
 
 // Tracked notes
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +127,7 @@ struct TrackedNote {
     note_id: String,
     note_type: String,
     timestamp: u64,
+    status: String, // Pending, Consumed
 }
 
 // Deposit info - metadata from frontend about P2ID deposit notes
@@ -60,40 +143,641 @@ struct DepositInfo {
     timestamp: u64,
 }
 
+// Submitted consume transaction awaiting commitment.
+// The deposit is credited by the confirmation tracker, not at submission.
+struct PendingTx {
+    tx_id: miden_objects::transaction::TransactionId,
+    note_id_hex: String,
+    pool_id: AccountId,
+    submitted_at: Instant,
+    deposit: Option<DepositInfo>,
+    // LP the deposit was worth at pre-consume reserves (checked against min_lp_amount_out)
+    lp_quote: Option<u64>,
+}
+
+// What to do when a deposit's LP quote is below its min_lp_amount_out
+// (MILO_DEPOSIT_SLIPPAGE_ACTION = refund | credit)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SlippageAction {
+    Refund,
+    Credit,
+}
+
+// Credited deposit kept for re-verification until its tx is reorg_depth blocks deep.
+// block_num is None when it was credited on timeout and hasn't been seen committed yet.
+struct CommittedTx {
+    tx_id: miden_objects::transaction::TransactionId,
+    note_id_hex: String,
+    pool_id: AccountId,
+    user_account_id: String,
+    token_id: String,
+    credited: u64,
+    // LP shares minted for the credit, taken back again on rollback
+    lp_shares: u64,
+    block_num: Option<u32>,
+    credited_at: Instant,
+}
+
+const CONFIRMATION_TIMEOUT_SECS: u64 = 60;
+const CONFIRMATION_CHECK_INTERVAL_SECS: u64 = 2;
+// /pool_create waits for the pool account, the LP faucet and the seed deposit
+const POOL_CREATE_TIMEOUT_SECS: u64 = 300;
+// Default number of blocks a commit is re-verified for (MILO_REORG_DEPTH_BLOCKS)
+const DEFAULT_REORG_DEPTH_BLOCKS: u32 = 10;
+// Timeout-credited deposits never seen committed stop being tracked after this
+const UNCONFIRMED_CREDIT_TRACKING_SECS: u64 = 600;
+// wait_for_transaction polls: 250ms, 500ms, 1s, ... (about a minute in total)
+const TX_WAIT_BASE_DELAY_MS: u64 = 250;
+const TX_WAIT_MAX_ATTEMPTS: u32 = 8;
+// LP shares a deposit of the pool's whole liquidity is worth
+const SHARE_PRECISION: u64 = 1_000_000_000_000;
+// Failed mints of a deposit's LP tokens before the deposit is refunded instead
+const LP_MINT_MAX_ATTEMPTS: u32 = 5;
+
 // Per-user deposit tracking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct UserPoolDeposit {
     user_account_id: String,
     pool_account_id: String,
     total_deposited: u64,
+    // Claim on the pool's reserves; deposits credited before share tracking have 0
+    #[serde(default)]
+    lp_shares: u64,
     deposit_count: u32,
     last_deposit_time: u64,
+    #[serde(default)]
+    refunds: Vec<DepositRefund>,
+}
+
+// Deposit (or part of one) sent back to the user instead of being credited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DepositRefund {
+    note_id: String,
+    amount: u64,
+    // "slippage" (LP below min_lp_amount_out), "share_cap" or "lp_mint" (no LP
+    // tokens could be minted for it)
+    reason: String,
+    refund_tx_id: Option<String>,
+    timestamp: u64,
 }
 
-const USER_DEPOSITS_FILE: &str = "user_deposits.json";
+/// Open the liquidity store and create the deposit and fee tables if this
+/// database predates them. The miden client owns the rest of the file.
+fn open_user_deposits_db() -> rusqlite::Result<rusqlite::Connection> {
+    let conn = rusqlite::Connection::open(&milo_config().store_path)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS user_deposits (
+             user_account_id   TEXT NOT NULL,
+             pool_account_id   TEXT NOT NULL,
+             total_deposited   INTEGER NOT NULL,
+             deposit_count     INTEGER NOT NULL,
+             last_deposit_time INTEGER NOT NULL,
+             lp_shares         INTEGER NOT NULL,
+             PRIMARY KEY (user_account_id, pool_account_id)
+         );
+         CREATE TABLE IF NOT EXISTS user_deposit_refunds (
+             user_account_id TEXT NOT NULL,
+             pool_account_id TEXT NOT NULL,
+             note_id         TEXT NOT NULL,
+             amount          INTEGER NOT NULL,
+             reason          TEXT NOT NULL,
+             refund_tx_id    TEXT,
+             timestamp       INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_user_deposit_refunds_key
+             ON user_deposit_refunds (user_account_id, pool_account_id);
+         CREATE TABLE IF NOT EXISTS pool_fees (
+             pool_id               TEXT PRIMARY KEY,
+             total_fees_lifetime   INTEGER NOT NULL,
+             total_volume_lifetime INTEGER NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS lp_mint_queue (
+             deposit_tx_id   TEXT PRIMARY KEY,
+             pool_account_id TEXT NOT NULL,
+             lp_faucet_id    TEXT NOT NULL,
+             user_account_id TEXT NOT NULL,
+             token_id        TEXT NOT NULL,
+             note_id         TEXT NOT NULL,
+             credited        INTEGER NOT NULL,
+             lp_shares       INTEGER NOT NULL,
+             lp_amount       INTEGER NOT NULL,
+             attempts        INTEGER NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS lp_pending_burns (
+             note_id      TEXT PRIMARY KEY,
+             lp_faucet_id TEXT NOT NULL,
+             lp_amount    INTEGER NOT NULL,
+             note         BLOB NOT NULL
+         );",
+    )?;
+    // Fee tables from before the 24h figures were persisted lack their columns
+    let has_volume_24h: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('pool_fees') WHERE name = 'volume_24h'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_volume_24h {
+        conn.execute_batch(
+            "ALTER TABLE pool_fees ADD COLUMN volume_24h INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE pool_fees ADD COLUMN fees_24h INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE pool_fees ADD COLUMN trades_24h INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE pool_fees ADD COLUMN last_updated INTEGER NOT NULL DEFAULT 0;",
+        )?;
+    }
+    Ok(conn)
+}
 
+/// Every tracked deposit keyed by "user:pool", refunds attached in the order they happened
 fn load_user_deposits() -> HashMap<String, UserPoolDeposit> {
-    match fs::read_to_string(USER_DEPOSITS_FILE) {
+    let loaded = open_user_deposits_db().and_then(|conn| {
+        let mut deposits = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT user_account_id, pool_account_id, total_deposited, deposit_count, last_deposit_time, lp_shares
+             FROM user_deposits",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(UserPoolDeposit {
+                user_account_id: row.get(0)?,
+                pool_account_id: row.get(1)?,
+                total_deposited: row.get::<_, i64>(2)? as u64,
+                deposit_count: row.get::<_, i64>(3)? as u32,
+                last_deposit_time: row.get::<_, i64>(4)? as u64,
+                lp_shares: row.get::<_, i64>(5)? as u64,
+                refunds: Vec::new(),
+            })
+        })?;
+        for deposit in rows {
+            let deposit = deposit?;
+            deposits.insert(format!("{}:{}", deposit.user_account_id, deposit.pool_account_id), deposit);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT user_account_id, pool_account_id, note_id, amount, reason, refund_tx_id, timestamp
+             FROM user_deposit_refunds ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let key = format!("{}:{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?);
+            Ok((key, DepositRefund {
+                note_id: row.get(2)?,
+                amount: row.get::<_, i64>(3)? as u64,
+                reason: row.get(4)?,
+                refund_tx_id: row.get(5)?,
+                timestamp: row.get::<_, i64>(6)? as u64,
+            }))
+        })?;
+        for refund in rows {
+            let (key, refund) = refund?;
+            if let Some(deposit) = deposits.get_mut(&key) {
+                deposit.refunds.push(refund);
+            }
+        }
+        Ok(deposits)
+    });
+    match loaded {
+        Ok(deposits) => deposits,
+        Err(e) => {
+            println!("⚠️  Failed to load user deposits: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Write one user's deposit row and its refunds in a single transaction, so a
+/// crash mid-write leaves the previous state rather than a partial one
+fn save_user_deposit(deposit: &UserPoolDeposit) {
+    let saved = open_user_deposits_db().and_then(|mut conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO user_deposits
+                 (user_account_id, pool_account_id, total_deposited, deposit_count, last_deposit_time, lp_shares)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (user_account_id, pool_account_id) DO UPDATE SET
+                 total_deposited = excluded.total_deposited,
+                 deposit_count = excluded.deposit_count,
+                 last_deposit_time = excluded.last_deposit_time,
+                 lp_shares = excluded.lp_shares",
+            rusqlite::params![
+                deposit.user_account_id,
+                deposit.pool_account_id,
+                deposit.total_deposited as i64,
+                deposit.deposit_count as i64,
+                deposit.last_deposit_time as i64,
+                deposit.lp_shares as i64,
+            ],
+        )?;
+        tx.execute(
+            "DELETE FROM user_deposit_refunds WHERE user_account_id = ?1 AND pool_account_id = ?2",
+            rusqlite::params![deposit.user_account_id, deposit.pool_account_id],
+        )?;
+        for refund in &deposit.refunds {
+            tx.execute(
+                "INSERT INTO user_deposit_refunds
+                     (user_account_id, pool_account_id, note_id, amount, reason, refund_tx_id, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    deposit.user_account_id,
+                    deposit.pool_account_id,
+                    refund.note_id,
+                    refund.amount as i64,
+                    refund.reason,
+                    refund.refund_tx_id,
+                    refund.timestamp as i64,
+                ],
+            )?;
+        }
+        tx.commit()
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist deposit of {} in {}: {}", deposit.user_account_id, deposit.pool_account_id, e);
+    }
+}
+
+/// Trade volume per pool from /record_trade, as of the last recorded trade.
+/// 24h figures whose window ended while the daemon was down are reset, as
+/// the next trade would do. Rows of pools pools.json doesn't list (recorded
+/// before /record_trade checked pool ids) are deleted.
+fn load_trade_volumes(now: u64, pools: &PoolsConfig) -> HashMap<String, TradeVolume> {
+    let loaded = open_user_deposits_db().and_then(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT pool_id, volume_24h, fees_24h, trades_24h, last_updated, total_fees_lifetime, total_volume_lifetime
+             FROM pool_fees",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(TradeVolume {
+                pool_id: row.get(0)?,
+                volume_24h: row.get::<_, i64>(1)? as u64,
+                fees_24h: row.get::<_, i64>(2)? as u64,
+                trades_24h: row.get::<_, i64>(3)? as u32,
+                last_updated: row.get::<_, i64>(4)? as u64,
+                total_fees_lifetime: row.get::<_, i64>(5)? as u64,
+                total_volume_lifetime: row.get::<_, i64>(6)? as u64,
+            })
+        })?;
+        rows.map(|volume| volume.map(|v| (v.pool_id.clone(), v))).collect::<rusqlite::Result<HashMap<_, _>>>()
+    });
+    match loaded {
+        Ok(mut volumes) => {
+            for pool_id in retain_known_pools(&mut volumes, pools) {
+                println!("🧹 Dropping trade volume of unknown pool {}", pool_id);
+                if let Err(e) = open_user_deposits_db()
+                    .and_then(|conn| conn.execute("DELETE FROM pool_fees WHERE pool_id = ?1", [&pool_id]))
+                {
+                    println!("⚠️  Failed to delete trade volume of {}: {}", pool_id, e);
+                }
+            }
+            for volume in volumes.values_mut() {
+                if now.saturating_sub(volume.last_updated) > 86400 {
+                    volume.volume_24h = 0;
+                    volume.fees_24h = 0;
+                    volume.trades_24h = 0;
+                }
+            }
+            volumes
+        }
+        Err(e) => {
+            println!("⚠️  Failed to load trade volumes: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Remove the volumes whose key isn't the canonical id of a pools.json pool;
+/// returns the removed keys
+fn retain_known_pools(volumes: &mut HashMap<String, TradeVolume>, pools: &PoolsConfig) -> Vec<String> {
+    let unknown: Vec<String> = volumes
+        .keys()
+        .filter(|pool_id| known_pool_id(pools, pool_id).as_ref() != Some(*pool_id))
+        .cloned()
+        .collect();
+    for pool_id in &unknown {
+        volumes.remove(pool_id);
+    }
+    unknown
+}
+
+/// Upsert one pool's volume row; callers pass ids `known_pool_id` accepted
+fn save_trade_volume(volume: &TradeVolume) {
+    let saved = open_user_deposits_db().and_then(|conn| {
+        conn.execute(
+            "INSERT INTO pool_fees (pool_id, volume_24h, fees_24h, trades_24h, last_updated, total_fees_lifetime, total_volume_lifetime)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (pool_id) DO UPDATE SET
+                 volume_24h = excluded.volume_24h,
+                 fees_24h = excluded.fees_24h,
+                 trades_24h = excluded.trades_24h,
+                 last_updated = excluded.last_updated,
+                 total_fees_lifetime = excluded.total_fees_lifetime,
+                 total_volume_lifetime = excluded.total_volume_lifetime",
+            rusqlite::params![
+                volume.pool_id,
+                volume.volume_24h as i64,
+                volume.fees_24h as i64,
+                volume.trades_24h as i64,
+                volume.last_updated as i64,
+                volume.total_fees_lifetime as i64,
+                volume.total_volume_lifetime as i64,
+            ],
+        )
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist trade volume of {}: {}", volume.pool_id, e);
+    }
+}
+
+/// Account ID stored as hex in column `idx`
+fn account_id_column(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<AccountId> {
+    let hex: String = row.get(idx)?;
+    AccountId::from_hex(&hex).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.to_string().into())
+    })
+}
+
+/// LP mints still owed to depositors, in the order they were queued
+fn load_lp_mint_queue() -> Vec<PendingLpMint> {
+    let loaded = open_user_deposits_db().and_then(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT deposit_tx_id, pool_account_id, lp_faucet_id, user_account_id, token_id, note_id,
+                    credited, lp_shares, lp_amount, attempts
+             FROM lp_mint_queue ORDER BY rowid",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(PendingLpMint {
+                deposit_tx_id: row.get(0)?,
+                pool_id: account_id_column(row, 1)?,
+                lp_faucet_id: account_id_column(row, 2)?,
+                user_account_id: row.get(3)?,
+                token_id: row.get(4)?,
+                note_id: row.get(5)?,
+                credited: row.get::<_, i64>(6)? as u64,
+                lp_shares: row.get::<_, i64>(7)? as u64,
+                lp_amount: row.get::<_, i64>(8)? as u64,
+                ready: false,
+                attempts: row.get::<_, i64>(9)? as u32,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match loaded {
+        Ok(mints) => mints,
+        Err(e) => {
+            println!("⚠️  Failed to load the LP mint queue: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn save_lp_mint(mint: &PendingLpMint) {
+    let saved = open_user_deposits_db().and_then(|conn| {
+        conn.execute(
+            "INSERT INTO lp_mint_queue
+                 (deposit_tx_id, pool_account_id, lp_faucet_id, user_account_id, token_id, note_id,
+                  credited, lp_shares, lp_amount, attempts)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+             ON CONFLICT (deposit_tx_id) DO UPDATE SET attempts = excluded.attempts",
+            rusqlite::params![
+                mint.deposit_tx_id,
+                mint.pool_id.to_hex(),
+                mint.lp_faucet_id.to_hex(),
+                mint.user_account_id,
+                mint.token_id,
+                mint.note_id,
+                mint.credited as i64,
+                mint.lp_shares as i64,
+                mint.lp_amount as i64,
+                mint.attempts as i64,
+            ],
+        )
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist the LP mint for deposit tx {}: {}", mint.deposit_tx_id, e);
+    }
+}
+
+fn delete_lp_mint(deposit_tx_id: &str) {
+    let deleted = open_user_deposits_db()
+        .and_then(|conn| conn.execute("DELETE FROM lp_mint_queue WHERE deposit_tx_id = ?1", [deposit_tx_id]));
+    if let Err(e) = deleted {
+        println!("⚠️  Failed to remove the LP mint for deposit tx {}: {}", deposit_tx_id, e);
+    }
+}
+
+/// Burn notes sent to LP faucets and not consumed yet
+fn load_pending_burns() -> Vec<PendingBurn> {
+    let loaded = open_user_deposits_db().and_then(|conn| {
+        let mut stmt = conn.prepare("SELECT lp_faucet_id, lp_amount, note FROM lp_pending_burns ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| {
+            let bytes: Vec<u8> = row.get(2)?;
+            let note = Note::read_from_bytes(&bytes).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Blob, e.to_string().into())
+            })?;
+            Ok(PendingBurn {
+                lp_faucet_id: account_id_column(row, 0)?,
+                note,
+                lp_amount: row.get::<_, i64>(1)? as u64,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match loaded {
+        Ok(burns) => burns,
+        Err(e) => {
+            println!("⚠️  Failed to load pending LP burns: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn save_pending_burn(burn: &PendingBurn) {
+    let saved = open_user_deposits_db().and_then(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO lp_pending_burns (note_id, lp_faucet_id, lp_amount, note) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                burn.note.id().to_hex(),
+                burn.lp_faucet_id.to_hex(),
+                burn.lp_amount as i64,
+                burn.note.to_bytes(),
+            ],
+        )
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist LP burn note {}: {}", burn.note.id().to_hex(), e);
+    }
+}
+
+fn delete_pending_burn(note_id: &str) {
+    let deleted = open_user_deposits_db()
+        .and_then(|conn| conn.execute("DELETE FROM lp_pending_burns WHERE note_id = ?1", [note_id]));
+    if let Err(e) = deleted {
+        println!("⚠️  Failed to remove LP burn note {}: {}", note_id, e);
+    }
+}
+
+// Liquidity mining campaign - loaded from points_campaign.json
+// Points accrue per (user, pool) as credited liquidity × hours held × pool multiplier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PointsCampaign {
+    start_time: u64,
+    end_time: u64,
+    #[serde(default)]
+    pool_multipliers: HashMap<String, f64>,
+}
+
+// Accrued points per (user, pool)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UserPoints {
+    user_account_id: String,
+    pool_account_id: String,
+    points: f64,
+    // Credited liquidity as of last_accrued
+    liquidity: u64,
+    last_accrued: u64,
+}
+
+const POINTS_CAMPAIGN_FILE: &str = "points_campaign.json";
+const USER_POINTS_FILE: &str = "liquidity_points.json";
+const POINTS_ACCRUAL_INTERVAL_SECS: u64 = 15;
+
+fn load_points_campaign() -> Option<PointsCampaign> {
+    let data = fs::read_to_string(POINTS_CAMPAIGN_FILE).ok()?;
+    match serde_json::from_str::<PointsCampaign>(&data) {
+        Ok(c) if c.end_time > c.start_time => Some(c),
+        Ok(_) => {
+            eprintln!("⚠️  {}: end_time must be after start_time - points disabled", POINTS_CAMPAIGN_FILE);
+            None
+        }
+        Err(e) => {
+            eprintln!("⚠️  {} is invalid ({}) - points disabled", POINTS_CAMPAIGN_FILE, e);
+            None
+        }
+    }
+}
+
+fn load_user_points() -> HashMap<String, UserPoints> {
+    match fs::read_to_string(USER_POINTS_FILE) {
         Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
         Err(_) => HashMap::new(),
     }
 }
 
-fn save_user_deposits(deposits: &HashMap<String, UserPoolDeposit>) {
-    let data = serde_json::to_string_pretty(deposits).unwrap_or_default();
-    let _ = fs::write(USER_DEPOSITS_FILE, data);
+fn save_user_points(points: &HashMap<String, UserPoints>) {
+    let data = serde_json::to_string_pretty(points).unwrap_or_default();
+    let _ = fs::write(USER_POINTS_FILE, data);
+}
+
+/// Points earned by holding `liquidity` in `pool_id` from `from` to `to`,
+/// clipped to the campaign window
+fn points_between(campaign: &PointsCampaign, pool_id: &str, liquidity: u64, from: u64, to: u64) -> f64 {
+    let start = from.max(campaign.start_time);
+    let end = to.min(campaign.end_time);
+    if end <= start || liquidity == 0 {
+        return 0.0;
+    }
+    let multiplier = campaign.pool_multipliers.get(pool_id).copied().unwrap_or(1.0);
+    liquidity as f64 * multiplier * (end - start) as f64 / 3600.0
+}
+
+/// Bring every (user, pool) points entry up to `now`.
+/// Accrual is derived from the persisted `last_accrued` timestamp, so
+/// downtime between restarts is credited at the liquidity held before it.
+fn accrue_points(
+    campaign: &PointsCampaign,
+    deposits: &HashMap<String, UserPoolDeposit>,
+    points: &mut HashMap<String, UserPoints>,
+    now: u64,
+) {
+    for (key, deposit) in deposits {
+        let entry = points.entry(key.clone()).or_insert_with(|| UserPoints {
+            user_account_id: deposit.user_account_id.clone(),
+            pool_account_id: deposit.pool_account_id.clone(),
+            points: 0.0,
+            liquidity: 0,
+            last_accrued: now,
+        });
+        entry.points += points_between(campaign, &entry.pool_account_id, entry.liquidity, entry.last_accrued, now);
+        entry.liquidity = deposit.total_deposited;
+        entry.last_accrued = now;
+    }
+}
+
+/// Points balance including what has accrued since the last accrual tick
+fn current_points(campaign: &PointsCampaign, entry: &UserPoints, now: u64) -> f64 {
+    entry.points + points_between(campaign, &entry.pool_account_id, entry.liquidity, entry.last_accrued, now)
+}
+
+// Query params for points endpoints
+#[derive(Debug, Deserialize)]
+struct PointsQuery {
+    user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    limit: Option<usize>,
+}
+
+// Query params for deposit_quote endpoint
+#[derive(Debug, Deserialize)]
+struct DepositQuoteQuery {
+    pool_id: String,
+    user_id: String,
+    amount: u64,
+}
+
+// Query params for withdraw_preview endpoint
+#[derive(Debug, Deserialize)]
+struct WithdrawPreviewQuery {
+    pool_id: String,
+    user_id: String,
+    lp_amount: u64,
+}
+
+// Query params for exposure endpoint
+#[derive(Debug, Deserialize)]
+struct ExposureQuery {
+    pool_id: String,
+    limit: Option<usize>,
+}
+
+// Query params for pool_reserves endpoint
+#[derive(Debug, Deserialize)]
+struct PoolReservesQuery {
+    #[serde(default)]
+    force_sync: bool,
 }
 
 // Query params for user_deposits endpoint
 #[derive(Debug, Deserialize)]
 struct UserDepositsQuery {
-    user_id: String,
+    // Optional with an API key (defaults to the key's account)
+    user_id: Option<String>,
+}
+
+// Body for POST /admin/api_keys
+#[derive(Debug, Deserialize)]
+struct CreateApiKeyRequest {
+    account_id: String,
+    permissions: Vec<Permission>,
+    #[serde(default)]
+    label: String,
+}
+
+// Body for POST /admin/api_keys/revoke
+#[derive(Debug, Deserialize)]
+struct RevokeApiKeyRequest {
+    key_id: String,
 }
 
 // Pool reserves response
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct PoolReservesResponse {
     pools: Vec<PoolReserveEntry>,
+    // Age of the state the reserves were read from, in ms
+    #[serde(default)]
+    state_age_ms: Option<u64>,
+    // Whether this request triggered a sync
+    #[serde(default)]
+    synced: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -110,14 +794,61 @@ struct ReserveAsset {
 }
 
 struct PoolReservesRequest {
+    // Sync even if the last sync is within the freshness window
+    force_sync: bool,
     reply: tokio::sync::oneshot::Sender<Result<PoolReservesResponse, String>>,
 }
 
-// Worker message enum - consume, withdraw, or pool_reserves
+// Body for POST /pool_create
+#[derive(Debug, Deserialize)]
+struct PoolCreateRequest {
+    token_a: String,
+    token_b: String,
+    // Seed liquidity from the accounts.json user wallet; both or neither
+    #[serde(default)]
+    initial_a: u64,
+    #[serde(default)]
+    initial_b: u64,
+    // "constant_product" (default) or "stable_swap"
+    curve: Option<String>,
+    // Amplification coefficient for "stable_swap"
+    amplification: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct PoolCreateResponse {
+    pair: String,
+    account_id: String,
+    address: String,
+    lp_faucet_id: String,
+    curve: String,
+    seeded: bool,
+}
+
+// Pool creation request - sent to the pool creation thread
+struct CreatePoolWorkerRequest {
+    pair: String,
+    token_a: AccountId,
+    token_b: AccountId,
+    initial_a: u64,
+    initial_b: u64,
+    curve: AmmCurve,
+    reply: tokio::sync::oneshot::Sender<Result<PoolCreateResponse, String>>,
+}
+
+// A pool the pool creation thread created, handed to the worker's store
+struct AddPoolRequest {
+    pool: Account,
+    lp_faucet: Account,
+    reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+}
+
+// Worker message enum - consume, withdraw, pool_reserves or add_pool
 enum WorkerRequest {
     Consume(ConsumeRequest),
     Withdraw(WithdrawWorkerRequest),
     PoolReserves(PoolReservesRequest),
+    AddPool(AddPoolRequest),
 }
 
 // Shared state
@@ -127,7 +858,31 @@ struct AppState {
     deposit_info_map: Arc<Mutex<HashMap<String, DepositInfo>>>,
     user_deposits: Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
     worker_tx: Arc<std::sync::mpsc::Sender<WorkerRequest>>,
+    // /pool_create runs on its own thread and client, so deposits don't wait on it
+    pool_create_tx: Arc<std::sync::mpsc::Sender<CreatePoolWorkerRequest>>,
     trade_volumes: Arc<Mutex<HashMap<String, TradeVolume>>>,
+    daily_stats: Arc<Mutex<DailyStats>>,
+    // Bot API keys; created/revoked via /admin/api_keys, shared with the swap daemon
+    api_keys: Arc<ApiKeyStore>,
+    points_campaign: Option<Arc<PointsCampaign>>,
+    user_points: Arc<Mutex<HashMap<String, UserPoints>>>,
+    // Bearer token for /admin/* routes (MILO_ADMIN_TOKEN); disabled when unset
+    admin_token: Option<Arc<String>>,
+    // Per-user share cap in bps (MILO_MAX_USER_POOL_SHARE_BPS); None = unlimited
+    max_user_share_bps: Option<u64>,
+    // Every submit_new_transaction holds a permit; bounds in-flight submissions
+    submit_permits: Arc<Semaphore>,
+    max_inflight_submissions: usize,
+    // Credited deposits later found discarded (rolled back by the confirmation tracker)
+    reorgs_detected: Arc<AtomicU64>,
+    // Per-pool counters and gauges for /metrics, updated by the worker
+    metrics: Arc<Mutex<MetricsState>>,
+    // Last reserves the worker read per pool; lets /withdraw_preview answer without it
+    reserve_cache: Arc<Mutex<HashMap<String, PoolReserveEntry>>>,
+    // Set via POST /maintenance; rejects writes and pauses auto-poll
+    maintenance: Maintenance,
+    // Seconds between the worker's auto-polls
+    poll_interval_secs: u64,
 }
 
 struct ConsumeRequest {
@@ -157,6 +912,8 @@ struct WithdrawRequest {
     lp_amount: String,
     min_token_a_out: String,
     min_token_b_out: String,
+    // P2ID note handing the LP tokens to the pool; required for pools with an LP faucet
+    lp_note_id: Option<String>,
     token_a: Option<String>,
     token_b: Option<String>,
 }
@@ -177,9 +934,89 @@ struct WithdrawWorkerRequest {
     lp_amount: u64,
     min_token_a_out: u64,
     min_token_b_out: u64,
+    lp_note_id: Option<String>,
     reply: tokio::sync::oneshot::Sender<Result<WithdrawResponse, String>>,
 }
 
+// LP tokens a withdrawal sent back to their faucet, until the faucet has
+// consumed the burn note; they no longer count towards the LP supply
+struct PendingBurn {
+    lp_faucet_id: AccountId,
+    note: Note,
+    lp_amount: u64,
+}
+
+// LP tokens owed for a credited deposit. They are minted once the deposit's
+// consume tx is reorg_depth blocks deep, so a rollback only drops the entry
+// and never has LP tokens in the user's hands to take back.
+struct PendingLpMint {
+    deposit_tx_id: String,
+    pool_id: AccountId,
+    lp_faucet_id: AccountId,
+    user_account_id: String,
+    token_id: String,
+    note_id: String,
+    credited: u64,
+    lp_shares: u64,
+    lp_amount: u64,
+    // The deposit is final, or its tracking was lost in a restart: mint it
+    ready: bool,
+    attempts: u32,
+}
+
+// LP mints and burns that aren't on chain yet. Both are kept in the
+// liquidity store, so a restart neither forgets what depositors are owed
+// nor counts tokens already sent for burning again.
+#[derive(Default)]
+struct LpQueues {
+    mints: Vec<PendingLpMint>,
+    burns: Vec<PendingBurn>,
+}
+
+impl LpQueues {
+    fn load() -> Self {
+        let mut mints = load_lp_mint_queue();
+        // Reorg tracking is in memory, so queued deposits count as final now
+        for mint in &mut mints {
+            mint.ready = true;
+        }
+        Self { mints, burns: load_pending_burns() }
+    }
+
+    /// LP tokens of `lp_faucet_id` held by LPs or owed to them, out of the
+    /// `issued` supply: tokens sent for burning no longer count, queued
+    /// mints already do
+    fn circulating(&self, lp_faucet_id: AccountId, issued: u64) -> u64 {
+        let burning: u64 = self.burns.iter().filter(|b| b.lp_faucet_id == lp_faucet_id).map(|b| b.lp_amount).sum();
+        let owed: u64 = self.mints.iter().filter(|m| m.lp_faucet_id == lp_faucet_id).map(|m| m.lp_amount).sum();
+        issued.saturating_sub(burning).saturating_add(owed)
+    }
+
+    fn queue_mint(&mut self, mint: PendingLpMint) {
+        save_lp_mint(&mint);
+        self.mints.push(mint);
+    }
+
+    /// Drop the mint owed for a deposit whose consume tx was discarded;
+    /// the caller deletes it from the store
+    fn take_mint(&mut self, deposit_tx_id: &str) -> Option<PendingLpMint> {
+        let index = self.mints.iter().position(|m| m.deposit_tx_id == deposit_tx_id)?;
+        Some(self.mints.remove(index))
+    }
+
+    /// The deposit behind a queued mint is final: mint on the next pass
+    fn release_mint(&mut self, deposit_tx_id: &str) {
+        for mint in self.mints.iter_mut().filter(|m| m.deposit_tx_id == deposit_tx_id) {
+            mint.ready = true;
+        }
+    }
+
+    fn queue_burn(&mut self, burn: PendingBurn) {
+        save_pending_burn(&burn);
+        self.burns.push(burn);
+    }
+}
+
 // Trade volume tracking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct TradeVolume {
@@ -188,6 +1025,11 @@ struct TradeVolume {
     fees_24h: u64,
     trades_24h: u32,
     last_updated: u64,
+    // Never reset; the whole record is persisted in the pool_fees table
+    #[serde(default)]
+    total_fees_lifetime: u64,
+    #[serde(default)]
+    total_volume_lifetime: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -196,39 +1038,166 @@ struct RecordTradeRequest {
     amount_in: u64,
     amount_out: u64,
     fee_amount: u64,
+    // Trader account, for unique-trader counts in the daily export
+    #[serde(default)]
+    user_id: Option<String>,
+}
+
+// Per-pool aggregates for one UTC day, for the statistics export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DailyPoolStats {
+    volume: u64,
+    fees: u64,
+    swap_count: u32,
+    traders: BTreeSet<String>,
+}
+
+impl DailyPoolStats {
+    /// Add one trade. Amounts come from an unauthenticated endpoint, so the
+    /// sums saturate rather than overflow while the stats lock is held.
+    fn record(&mut self, amount_in: u64, fee_amount: u64, user_id: Option<&str>) {
+        self.volume = self.volume.saturating_add(amount_in);
+        self.fees = self.fees.saturating_add(fee_amount);
+        self.swap_count = self.swap_count.saturating_add(1);
+        if let Some(user_id) = user_id {
+            self.traders.insert(user_id.to_lowercase());
+        }
+    }
+}
+
+// day number since epoch → pool_id → stats
+type DailyStats = BTreeMap<u64, BTreeMap<String, DailyPoolStats>>;
+
+const DAILY_STATS_FILE: &str = "daily_stats.json";
+const DAILY_STATS_RETENTION_DAYS: u64 = 60;
+const EXPORTS_DIR: &str = "exports";
+const DEFAULT_EXPORT_INTERVAL_SECS: u64 = 3600;
+
+fn load_daily_stats() -> DailyStats {
+    match fs::read_to_string(DAILY_STATS_FILE) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_daily_stats(stats: &DailyStats) {
+    let data = serde_json::to_string_pretty(stats).unwrap_or_default();
+    let _ = fs::write(DAILY_STATS_FILE, data);
+}
+
+// One pool's row in a daily snapshot
+#[derive(Debug, Serialize)]
+struct PoolDaySnapshot {
+    pool_id: String,
+    pair: String,
+    volume: u64,
+    fees: u64,
+    swap_count: u32,
+    unique_traders: usize,
+    lp_count: usize,
+    // Twice the quote-token reserve (both sides valued at the pool price)
+    tvl_quote: u64,
+    reserves: Vec<ReserveAsset>,
+}
+
+// Daily statistics export (exports/milo-stats-<date>.{json,csv}.gz)
+#[derive(Debug, Serialize)]
+struct DailySnapshot {
+    date: String,
+    pools: Vec<PoolDaySnapshot>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("🚀 Liquidity Daemon starting on port 8090...\n");
+    // Keystore, store, node, port and poll interval; set once, read everywhere
+    MILO_CONFIG.set(MiloConfig::load(Service::LiquidityDaemon)?).expect("milo.toml loaded once");
+    // Token symbols resolve to the faucets of the last `milo setup`
+    TokenRegistry::load(ACCOUNTS_PATH)?.install();
+    let port = milo_config().port;
+    println!("🚀 Liquidity Daemon starting on port {}...\n", port);
+    let (poll_interval_secs, poll_interval_source) = resolve_poll_interval(None, milo_config());
 
     // Load pool IDs
-    let pools_json = fs::read_to_string("pools.json")
-        .context("pools.json not found")?;
-    let pools: serde_json::Value = serde_json::from_str(&pools_json)?;
-
-    let milo_pool_id = AccountId::from_hex(pools["milo_musdc_pool_id"].as_str().unwrap())?;
-    let melo_pool_id = AccountId::from_hex(pools["melo_musdc_pool_id"].as_str().unwrap())?;
+    let pools = load_pools_config("pools.json")?;
 
-    println!("📋 Monitoring pools:");
-    println!("   - MILO/MUSDC: {}", milo_pool_id.to_hex());
-    println!("   - MELO/MUSDC: {}", melo_pool_id.to_hex());
-    println!();
+    let milo_pool_id = pools.require_pool_id("milo_musdc")?;
+    let melo_pool_id = pools.require_pool_id("melo_musdc")?;
+    // Pools that issue LP tokens; the rest keep share accounting
+    let lp_faucets: HashMap<AccountId, AccountId> = pools.pools.iter()
+        .filter_map(|p| p.lp_faucet_id.map(|lp_faucet_id| (p.pool_id, lp_faucet_id)))
+        .collect();
+    println!("🪙 {} of {} pool(s) issue LP tokens", lp_faucets.len(), pools.pools.len());
 
     // Load persisted user deposits
     let user_deposits: Arc<Mutex<HashMap<String, UserPoolDeposit>>> =
         Arc::new(Mutex::new(load_user_deposits()));
     println!("📦 Loaded {} user deposit record(s)", user_deposits.lock().unwrap().len());
 
+    // Liquidity mining campaign (optional)
+    let points_campaign = load_points_campaign().map(Arc::new);
+    let user_points: Arc<Mutex<HashMap<String, UserPoints>>> =
+        Arc::new(Mutex::new(load_user_points()));
+
     // Shared deposit_info_map - create before worker thread for auto-poll access
     let deposit_info_map: Arc<Mutex<HashMap<String, DepositInfo>>> = Arc::new(Mutex::new(HashMap::new()));
+    // The worker marks notes consumed, and pending again when a reorg undoes that
+    let tracked_notes: Arc<Mutex<Vec<TrackedNote>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let max_inflight_submissions = std::env::var("MILO_MAX_INFLIGHT_SUBMISSIONS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_INFLIGHT_SUBMISSIONS);
+    let submit_permits = Arc::new(Semaphore::new(max_inflight_submissions));
+
+    let max_notes_per_cycle = std::env::var("MILO_MAX_NOTES_PER_CYCLE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_NOTES_PER_CYCLE);
+
+    let slippage_action = match std::env::var("MILO_DEPOSIT_SLIPPAGE_ACTION").as_deref() {
+        Ok("credit") => SlippageAction::Credit,
+        _ => SlippageAction::Refund,
+    };
+
+    let maintenance = Maintenance::from_env();
+
+    let reorg_depth = std::env::var("MILO_REORG_DEPTH_BLOCKS")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_REORG_DEPTH_BLOCKS);
+    let reorgs_detected = Arc::new(AtomicU64::new(0));
+    let metrics: Arc<Mutex<MetricsState>> = Arc::new(Mutex::new(MetricsState::default()));
+    let reserve_cache: Arc<Mutex<HashMap<String, PoolReserveEntry>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let sync_freshness_window = Duration::from_millis(
+        std::env::var("MILO_SYNC_FRESHNESS_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SYNC_FRESHNESS_MS),
+    );
+
+    // Per-user cap on share of a pool's credited liquidity, in bps (e.g. 4000 = 40%)
+    let max_user_share_bps = std::env::var("MILO_MAX_USER_POOL_SHARE_BPS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|bps| *bps > 0 && *bps < 10_000);
 
     // Initialize client in worker thread
     let (worker_tx, worker_rx) = std::sync::mpsc::channel::<WorkerRequest>();
     let user_deposits_worker = user_deposits.clone();
     let deposit_info_map_worker = deposit_info_map.clone();
+    let tracked_notes_worker = tracked_notes.clone();
+    let submit_permits_worker = submit_permits.clone();
+    let reorgs_detected_worker = reorgs_detected.clone();
+    let metrics_worker = metrics.clone();
+    let reserve_cache_worker = reserve_cache.clone();
+    let maintenance_worker = maintenance.clone();
 
     std::thread::spawn(move || {
+        // /pool_create adds pools with their LP faucets
+        let mut lp_faucets = lp_faucets;
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
             // Initialize client
@@ -242,26 +1211,64 @@ async fn main() -> Result<()> {
 
             println!("✅ Client initialized in worker thread\n");
 
+            // LP faucets created by `milo add-liquidity` live in the CLI's store;
+            // minting and burning need them in this one
+            for lp_faucet_id in lp_faucets.values() {
+                if matches!(client.get_account(*lp_faucet_id).await, Ok(Some(_))) {
+                    continue;
+                }
+                match client.import_account_by_id(*lp_faucet_id).await {
+                    Ok(_) => println!("   ✅ LP faucet {} imported", lp_faucet_id.to_hex()),
+                    Err(e) => println!("   ⚠️  LP faucet {} import failed: {:?}", lp_faucet_id.to_hex(), e),
+                }
+            }
+            refresh_reserve_cache(&mut client, &reserve_cache_worker).await;
+
             let mut last_poll = Instant::now();
+            let mut last_confirmation_check = Instant::now();
+            let mut pending_txs: Vec<PendingTx> = Vec::new();
+            let mut committed_txs: Vec<CommittedTx> = Vec::new();
+            let mut note_cache = NoteCache {
+                batches: HashMap::new(),
+                max_per_cycle: max_notes_per_cycle,
+            };
+            let mut sync_freshness = SyncFreshness { last_sync: None, window: sync_freshness_window };
+            let mut lp_queues = LpQueues::load();
+            if !lp_queues.mints.is_empty() || !lp_queues.burns.is_empty() {
+                println!("🪙 {} LP mint(s) and {} burn(s) queued from the last run\n",
+                    lp_queues.mints.len(), lp_queues.burns.len());
+            }
 
-            // Non-blocking event loop: HTTP requests + auto-poll
+            // Non-blocking event loop: HTTP requests + auto-poll + confirmation tracking
             loop {
                 // Check for HTTP-triggered requests (non-blocking)
-                match worker_rx.try_recv() {
+                let request = worker_rx.try_recv();
+                if request.is_ok() {
+                    metrics_worker.lock().unwrap().dequeued();
+                }
+                match request {
                     Ok(WorkerRequest::Consume(req)) => {
-                        let result = consume_pool_notes(&mut client, req.pool_id_opt, req.deposit_info_map, &user_deposits_worker, false,).await;
+                        let result = consume_pool_notes(&mut client, req.pool_id_opt, req.deposit_info_map, &lp_faucets, &mut pending_txs, &mut note_cache, &submit_permits_worker, &mut sync_freshness, false).await;
                         let _ = req.reply.send(result.map_err(|e| format!("{:?}", e)));
                         last_poll = Instant::now();
                     }
                     Ok(WorkerRequest::Withdraw(req)) => {
-                        let result = execute_withdraw(&mut client, req.pool_id, req.user_id, req.lp_amount, req.min_token_a_out, req.min_token_b_out, &user_deposits_worker).await;
+                        let result = match lp_faucets.get(&req.pool_id) {
+                            Some(lp_faucet_id) => execute_lp_withdraw(&mut client, req.pool_id, *lp_faucet_id, req.user_id, req.lp_note_id, req.lp_amount, req.min_token_a_out, req.min_token_b_out, &submit_permits_worker, &mut lp_queues).await,
+                            None => execute_withdraw(&mut client, req.pool_id, req.user_id, req.lp_amount, req.min_token_a_out, req.min_token_b_out, &user_deposits_worker, &submit_permits_worker).await,
+                        };
                         let _ = req.reply.send(result.map_err(|e| format!("{:?}", e)));
+                        refresh_reserve_cache(&mut client, &reserve_cache_worker).await;
                         last_poll = Instant::now();
                     }
                     Ok(WorkerRequest::PoolReserves(req)) => {
-                        let result = get_pool_reserves(&mut client).await;
+                        let result = get_pool_reserves(&mut client, &mut sync_freshness, req.force_sync, &reserve_cache_worker).await;
                         let _ = req.reply.send(result.map_err(|e| format!("{:?}", e)));
                     }
+                    Ok(WorkerRequest::AddPool(req)) => {
+                        let result = add_created_pool(&mut client, &req.pool, &req.lp_faucet, &mut lp_faucets).await;
+                        let _ = req.reply.send(result.map_err(|e| format!("{:#}", e)));
+                    }
                     Err(std::sync::mpsc::TryRecvError::Empty) => {
                         // No HTTP request pending
                     }
@@ -271,53 +1278,201 @@ async fn main() -> Result<()> {
                     }
                 }
 
-                // Auto-poll every 15 seconds
-                if last_poll.elapsed() >= Duration::from_secs(15) {
+                // Auto-poll every poll_interval_secs (paused in maintenance; in-flight txs are still tracked below)
+                if last_poll.elapsed() >= Duration::from_secs(poll_interval_secs) && !maintenance_worker.is_enabled() {
                     let deposit_info = deposit_info_map_worker.lock().unwrap().clone();
-                    let result = consume_pool_notes(&mut client, None, deposit_info, &user_deposits_worker, true).await;
-                    if let Ok(ref resp) = result {
-                        if resp.consumed > 0 {
-                            println!("🔄 Auto-poll: consumed {} deposit note(s)", resp.consumed);
+                    let result = consume_pool_notes(&mut client, None, deposit_info, &lp_faucets, &mut pending_txs, &mut note_cache, &submit_permits_worker, &mut sync_freshness, true).await;
+                    match result {
+                        Ok(ref resp) if resp.consumed > 0 => {
+                            println!("🔄 Auto-poll: submitted {} deposit note(s)", resp.consumed);
+                        }
+                        Ok(_) => {}
+                        Err(ref e) => {
+                            println!("⚠️  Auto-poll failed: {:#}", e);
+                            metrics_worker.lock().unwrap().record_auto_poll_error();
                         }
                     }
+                    retry_lp_burns(&mut client, &submit_permits_worker, &mut lp_queues).await;
+                    mint_owed_lp(&mut client, &submit_permits_worker, &mut lp_queues, &user_deposits_worker).await;
+                    refresh_reserve_cache(&mut client, &reserve_cache_worker).await;
                     last_poll = Instant::now();
                 }
 
+                // Confirmation tracker: credit deposits once their consume tx commits,
+                // then re-verify recent credits so reorgs roll them back
+                if (!pending_txs.is_empty() || !committed_txs.is_empty())
+                    && last_confirmation_check.elapsed() >= Duration::from_secs(CONFIRMATION_CHECK_INTERVAL_SECS)
+                {
+                    let committed = process_pending_txs(
+                        &mut client, &mut pending_txs, &mut committed_txs, &user_deposits_worker,
+                        max_user_share_bps, slippage_action, &submit_permits_worker, &metrics_worker, &mut sync_freshness,
+                        &lp_faucets, &mut lp_queues, &tracked_notes_worker,
+                    ).await;
+                    if committed > 0 {
+                        println!("✅ Confirmed {} deposit note(s) ({} still pending)", committed, pending_txs.len());
+                    }
+                    verify_committed_txs(
+                        &mut client, &mut committed_txs, reorg_depth,
+                        &user_deposits_worker, &reorgs_detected_worker, &mut lp_queues, &tracked_notes_worker,
+                    ).await;
+                    last_confirmation_check = Instant::now();
+                }
+
                 sleep(Duration::from_millis(100)).await;
             }
         });
     });
 
-    // Initialize trade volumes for each pool
-    let mut initial_volumes = HashMap::new();
-    initial_volumes.insert(milo_pool_id.to_hex(), TradeVolume {
-        pool_id: milo_pool_id.to_hex(),
-        volume_24h: 0,
-        fees_24h: 0,
-        trades_24h: 0,
-        last_updated: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    });
-    initial_volumes.insert(melo_pool_id.to_hex(), TradeVolume {
-        pool_id: melo_pool_id.to_hex(),
-        volume_24h: 0,
-        fees_24h: 0,
-        trades_24h: 0,
-        last_updated: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    });
+    // Pool creation waits on several transactions; its own client and store
+    // keep that off the deposit/withdraw worker
+    let (pool_create_tx, pool_create_rx) = std::sync::mpsc::channel::<CreatePoolWorkerRequest>();
+    {
+        let worker_tx = worker_tx.clone();
+        let metrics = metrics.clone();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let store_path = pool_create_store_path(&milo_config().store_path);
+                let mut client = match init_client_at(&store_path).await {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("❌ Failed to initialize pool creation client: {:?}", e);
+                        return;
+                    }
+                };
+                println!("✅ Client initialized in pool creation thread ({})\n", store_path.display());
 
-    // Build app state
-    let state = AppState {
-        tracked_notes: Arc::new(Mutex::new(Vec::new())),
-        deposit_info_map,
-        user_deposits,
-        worker_tx: Arc::new(worker_tx),
+                // Blocks until a request arrives; ends when the HTTP side drops its sender
+                while let Ok(req) = pool_create_rx.recv() {
+                    let result = create_pool(&mut client, &req, &worker_tx, &metrics).await;
+                    let _ = req.reply.send(result.map_err(|e| format!("{:#}", e)));
+                }
+                println!("Pool creation thread shutting down");
+            });
+        });
+    }
+
+    // Trade volumes from the store; pools without trades yet start at zero
+    let now_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut initial_volumes = load_trade_volumes(now_secs, &pools);
+    for pool_id in [milo_pool_id.to_hex(), melo_pool_id.to_hex()] {
+        initial_volumes.entry(pool_id.clone()).or_insert(TradeVolume {
+            pool_id,
+            volume_24h: 0,
+            fees_24h: 0,
+            trades_24h: 0,
+            last_updated: now_secs,
+            total_fees_lifetime: 0,
+            total_volume_lifetime: 0,
+        });
+    }
+
+    // Accrue liquidity mining points on an interval (HTTP side - no client needed)
+    if let Some(campaign) = points_campaign.clone() {
+        let user_deposits = user_deposits.clone();
+        let user_points = user_points.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(POINTS_ACCRUAL_INTERVAL_SECS));
+            loop {
+                ticker.tick().await;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+                let deposits = user_deposits.lock().unwrap().clone();
+                let mut points = user_points.lock().unwrap();
+                accrue_points(&campaign, &deposits, &mut points, now);
+                save_user_points(&points);
+            }
+        });
+    }
+
+    let admin_token = std::env::var("MILO_ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .map(Arc::new);
+
+    // Daily statistics export: snapshot the previous UTC day once it has closed
+    let daily_stats = Arc::new(Mutex::new(load_daily_stats()));
+    let export_interval = std::env::var("MILO_EXPORT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_EXPORT_INTERVAL_SECS);
+
+    let mut config = StartupConfig::new("liquidity-daemon");
+    config
+        .milo("port", format!("127.0.0.1:{}", port), milo_config())
+        .milo("rpc_endpoint", &milo_config().rpc_endpoint, milo_config())
+        .milo("keystore_path", &milo_config().keystore_path, milo_config())
+        .milo("store_path", &milo_config().store_path, milo_config())
+        .constant("ledger", ledger::LIQUIDITY_LEDGER_FILE)
+        .constant("accounts", ACCOUNTS_PATH)
+        .pools("pools.json", &pools)
+        .constant("monitored_pools", "milo_musdc, melo_musdc");
+    match points_campaign {
+        Some(ref c) => config.file("points_campaign", POINTS_CAMPAIGN_FILE, format!("{} → {} ({} pool multiplier(s))",
+            c.start_time, c.end_time, c.pool_multipliers.len())),
+        None => config.constant("points_campaign", format!("none ({} not found)", POINTS_CAMPAIGN_FILE)),
+    };
+    config
+        .env("MILO_MAX_INFLIGHT_SUBMISSIONS", max_inflight_submissions)
+        .env("MILO_MAX_NOTES_PER_CYCLE", max_notes_per_cycle)
+        .env("MILO_DEPOSIT_SLIPPAGE_ACTION", format!("{:?}", slippage_action))
+        .env("MILO_REORG_DEPTH_BLOCKS", reorg_depth)
+        .env("MILO_SYNC_FRESHNESS_MS", sync_freshness_window.as_millis())
+        .env("MILO_MAX_USER_POOL_SHARE_BPS", max_user_share_bps.map_or("unlimited".to_string(), |bps| bps.to_string()))
+        .env("MILO_EXPORT_INTERVAL_SECS", format!("{} (into {}/)", export_interval, EXPORTS_DIR))
+        .env("MILO_MAINTENANCE", if maintenance.is_enabled() { "on" } else { "off" })
+        .env(TRUSTED_PROXIES_VAR, trusted_proxies().iter().map(ToString::to_string).collect::<Vec<_>>().join(","))
+        .record("MILO_POLL_INTERVAL_SECS", poll_interval_secs, poll_interval_source)
+        .secret("MILO_ADMIN_TOKEN");
+    log_startup_config(&config);
+    {
+        let worker_tx = worker_tx.clone();
+        let daily_stats = daily_stats.clone();
+        let user_deposits = user_deposits.clone();
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(export_interval));
+            loop {
+                ticker.tick().await;
+                match run_daily_export(&worker_tx, &metrics, &daily_stats, &user_deposits).await {
+                    Ok(Some(name)) => println!("📦 Exported {}", name),
+                    Ok(None) => {}
+                    Err(e) => println!("⚠️  Daily export failed: {:#}", e),
+                }
+            }
+        });
+    }
+
+    // Shared by the handlers and the rate limiter, which buckets valid keys separately
+    let api_keys = Arc::new(ApiKeyStore::open_default());
+
+    // Build app state
+    let state = AppState {
+        tracked_notes,
+        deposit_info_map,
+        user_deposits,
+        worker_tx: Arc::new(worker_tx),
+        pool_create_tx: Arc::new(pool_create_tx),
         trade_volumes: Arc::new(Mutex::new(initial_volumes)),
+        daily_stats,
+        api_keys: api_keys.clone(),
+        points_campaign,
+        user_points,
+        admin_token,
+        max_user_share_bps,
+        submit_permits,
+        max_inflight_submissions,
+        reorgs_detected,
+        metrics,
+        reserve_cache,
+        maintenance: maintenance.clone(),
+        poll_interval_secs,
     };
 
     // Setup CORS
@@ -326,28 +1481,45 @@ async fn main() -> Result<()> {
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
         .allow_headers([header::CONTENT_TYPE]);
 
+    // Write endpoints are rate limited per caller IP; /consume_note shares /consume's buckets
+    let consume_limit = RateLimitLayer::for_route("consume", RateLimitConfig::per_minute(10), &api_keys);
+
     // Build router
     let app = Router::new()
         .route("/health", get(health_handler))
-        .route("/track_note", post(track_note_handler))
-        .route("/consume", post(consume_handler))
-        .route("/consume_note", post(consume_handler))
+        .route("/track_note", post(track_note_handler).layer(RateLimitLayer::for_route("track_note", RateLimitConfig::per_minute(30), &api_keys)))
+        .route("/consume", post(consume_handler).layer(consume_limit.clone()))
+        .route("/consume_note", post(consume_handler).layer(consume_limit))
         .route("/tracked_notes", get(list_tracked_notes_handler))
-        .route("/withdraw", post(withdraw_handler))
+        .route("/withdraw", post(withdraw_handler).layer(RateLimitLayer::for_route("withdraw", RateLimitConfig::per_minute(5), &api_keys)))
+        .route("/withdraw_preview", get(withdraw_preview_handler))
         .route("/user_deposits", get(user_deposits_handler))
-        .route("/record_trade", post(record_trade_handler))
+        .route("/deposit_quote", get(deposit_quote_handler))
+        .route("/exposure", get(exposure_handler))
+        .route("/record_trade", post(record_trade_handler).layer(RateLimitLayer::for_route("record_trade", RateLimitConfig::per_minute(60), &api_keys)))
         .route("/trade_volume", get(get_trade_volume_handler))
         .route("/apy", get(get_apy_handler))
+        .route("/exports", get(list_exports_handler))
+        .route("/exports/:name", get(get_export_handler))
         .route("/pool_reserves", get(pool_reserves_handler))
+        .route("/pool_create", post(pool_create_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/points", get(points_handler))
+        .route("/points/leaderboard", get(points_leaderboard_handler))
+        .route("/admin/points_export", get(points_export_handler))
+        .route("/admin/api_keys", get(list_api_keys_handler).post(create_api_key_handler))
+        .route("/admin/api_keys/revoke", post(revoke_api_key_handler))
+        .route("/maintenance", get(get_maintenance_handler).post(set_maintenance_handler))
+        .layer(maintenance.layer())
         .layer(cors)
         .with_state(state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8090")
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
         .await
-        .context("Failed to bind to port 8090")?;
+        .with_context(|| format!("Failed to bind to port {}", port))?;
 
-    println!("🎯 Liquidity daemon listening on http://127.0.0.1:8090");
+    println!("🎯 Liquidity daemon listening on http://127.0.0.1:{} (auto-poll every {}s)", port, poll_interval_secs);
     println!("   Endpoints:");
     println!("   - GET  /health");
     println!("   - POST /track_note");
@@ -355,35 +1527,116 @@ async fn main() -> Result<()> {
     println!("   - POST /consume_note (alias)");
     println!("   - GET  /tracked_notes");
     println!("   - POST /withdraw");
+    println!("   - GET  /withdraw_preview?pool_id=<hex>&user_id=<hex>&lp_amount=<u64>");
     println!("   - GET  /user_deposits?user_id=<hex>");
     println!("   - POST /record_trade");
     println!("   - GET  /trade_volume");
     println!("   - GET  /apy");
+    println!("   - GET  /exports");
+    println!("   - GET  /exports/<name>");
     println!("   - GET  /pool_reserves");
+    println!("   - POST /pool_create (admin)");
+    println!("   - GET  /deposit_quote?pool_id=<hex>&user_id=<hex>&amount=<u64>");
+    println!("   - GET  /exposure?pool_id=<hex>&limit=10");
+    println!("   - GET  /metrics");
+    println!("   - GET  /points?user_id=<hex>");
+    println!("   - GET  /points/leaderboard?limit=50");
+    println!("   - GET  /admin/points_export");
+    println!("   - GET  /admin/api_keys, POST /admin/api_keys, POST /admin/api_keys/revoke");
+    println!("   - GET  /maintenance, POST /maintenance (admin)");
     println!("   Auto-polling: every 15 seconds");
     println!();
 
-    axum::serve(listener, app)
+    // Peer address is the rate limiter's fallback key when there's no X-Forwarded-For
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .context("Server error")?;
 
     Ok(())
 }
 
-async fn health_handler() -> impl IntoResponse {
+async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "healthy",
         "daemon": "liquidity-daemon",
-        "port": 8090
+        "port": milo_config().port,
+        "maintenance": state.maintenance.is_enabled(),
+        "poll_interval_secs": state.poll_interval_secs
     }))
 }
 
+async fn get_maintenance_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.maintenance.status())
+}
+
+// Admin: turn maintenance mode on or off
+async fn set_maintenance_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SetMaintenanceRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let status = state.maintenance.set(payload.enabled, payload.reason);
+    (StatusCode::OK, Json(serde_json::json!(status)))
+}
+
+// Prometheus text exposition of daemon metrics
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let in_flight = state.max_inflight_submissions
+        .saturating_sub(state.submit_permits.available_permits());
+
+    let body = format!(
+        "# HELP milo_submission_permits Maximum concurrent transaction submissions\n\
+         # TYPE milo_submission_permits gauge\n\
+         milo_submission_permits {}\n\
+         # HELP milo_submissions_in_flight Transaction submissions currently holding a permit\n\
+         # TYPE milo_submissions_in_flight gauge\n\
+         milo_submissions_in_flight {}\n\
+         # HELP milo_reorgs_detected_total Committed transactions later found discarded and rolled back\n\
+         # TYPE milo_reorgs_detected_total counter\n\
+         milo_reorgs_detected_total {}\n",
+        state.max_inflight_submissions, in_flight,
+        state.reorgs_detected.load(Ordering::Relaxed),
+    ) + &state.metrics.lock().unwrap().render();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Add `note` unless its id is already tracked or has deposit info, so a
+/// retried /track_note (at-least-once delivery from the frontend) is a no-op.
+/// False for such a duplicate.
+fn track_once(state: &AppState, note: TrackedNote) -> bool {
+    let mut tracked_notes = state.tracked_notes.lock().unwrap();
+    if tracked_notes.iter().any(|n| n.note_id == note.note_id)
+        || state.deposit_info_map.lock().unwrap().contains_key(&note.note_id)
+    {
+        return false;
+    }
+    tracked_notes.push(note);
+    true
+}
+
 async fn track_note_handler(
     State(state): State<AppState>,
-    Json(payload): Json<TrackNoteRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<TrackNoteRequest>,
 ) -> impl IntoResponse {
     println!("📝 Tracking note: {} (type: {})", payload.note_id, payload.note_type);
 
+    // API-key callers act for their bound account: the deposit is credited to it
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    if let Some(ref identity) = identity {
+        if let Some(ref mut deposit_info) = payload.deposit_info {
+            deposit_info.user_account_id = identity.account_id.clone();
+        }
+        state.api_keys.audit(identity, "track_note", serde_json::json!({ "note_id": payload.note_id }));
+    }
+
     let tracked = TrackedNote {
         note_id: payload.note_id.clone(),
         note_type: payload.note_type.clone(),
@@ -391,9 +1644,17 @@ async fn track_note_handler(
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs(),
+        status: "Pending".to_string(),
     };
 
-    state.tracked_notes.lock().unwrap().push(tracked);
+    if !track_once(&state, tracked) {
+        println!("   ↩️  Already tracked, ignoring retry");
+        return (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "note_id": payload.note_id,
+            "duplicate": true
+        })));
+    }
 
     // Store deposit info if provided (for P2ID deposits)
     let has_deposit_info = if let Some(ref deposit_info) = payload.deposit_info {
@@ -416,10 +1677,17 @@ async fn track_note_handler(
 
 async fn consume_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     println!("🔄 Consume request received");
 
+    match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(Some(identity)) => state.api_keys.audit(&identity, "consume", payload.clone()),
+        Ok(None) => {}
+        Err(resp) => return resp,
+    }
+
     let pool_id_opt = payload.get("pool_account_id")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
@@ -435,7 +1703,9 @@ async fn consume_handler(
         reply: reply_tx,
     };
 
+    state.metrics.lock().unwrap().enqueued();
     if state.worker_tx.send(WorkerRequest::Consume(req)).is_err() {
+        state.metrics.lock().unwrap().dequeued();
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
@@ -487,11 +1757,17 @@ async fn list_tracked_notes_handler(State(state): State<AppState>) -> impl IntoR
 }
 
 async fn init_client() -> Result<MidenClient> {
+    init_client_at(&PathBuf::from(&milo_config().store_path)).await
+}
+
+/// Client on the store at `store_path`. SQLite stores can't be shared
+/// between clients, so each thread with a client has its own.
+async fn init_client_at(store_path: &Path) -> Result<MidenClient> {
     let timeout_ms = 30_000;
-    let endpoint = Endpoint::testnet();
+    let endpoint = milo_config().endpoint()?;
     let rpc_api = Arc::new(GrpcClient::new(&endpoint, timeout_ms));
 
-    let keystore_path = PathBuf::from(KEYSTORE_PATH);
+    let keystore_path = PathBuf::from(&milo_config().keystore_path);
     let keystore = FilesystemKeyStore::new(keystore_path)
         .context("Failed to create keystore")?;
 
@@ -499,7 +1775,7 @@ async fn init_client() -> Result<MidenClient> {
         .rpc(rpc_api)
         .authenticator(Arc::new(keystore.clone()))
         .in_debug_mode(true.into())
-        .sqlite_store(STORE_PATH.into())
+        .sqlite_store(store_path.to_path_buf())
         .build()
         .await
         .context("Failed to build client")?;
@@ -507,24 +1783,55 @@ async fn init_client() -> Result<MidenClient> {
     Ok(client)
 }
 
+/// Submit a transaction while holding a permit from the shared submission
+/// semaphore, so a backlog burst can't flood the RPC
+async fn submit_with_permit(
+    client: &mut MidenClient,
+    submit_permits: &Semaphore,
+    account_id: AccountId,
+    tx_request: TransactionRequest,
+) -> Result<miden_objects::transaction::TransactionId> {
+    let _permit = submit_permits.acquire().await
+        .context("Submission semaphore closed")?;
+    let tx_id = client.submit_new_transaction(account_id, tx_request).await?;
+    Ok(tx_id)
+}
+
+/// Start a transaction on `pool_id` that runs `script` after its notes, so
+/// the pool component's recorded reserves follow the vault. Pools created
+/// before the component get a plain request.
+async fn pool_tx_builder(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    script: fn() -> Result<TransactionScript, Box<dyn std::error::Error>>,
+) -> Result<TransactionRequestBuilder> {
+    let pool = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool {} not found in the local store", pool_id.to_hex()))?;
+    let script = script_for_pool(pool.account().code(), script)
+        .map_err(|e| anyhow::anyhow!("Pool reserves script failed to compile: {}", e))?;
+    let builder = TransactionRequestBuilder::new();
+    Ok(match script {
+        Some(script) => builder.custom_script(script),
+        None => builder,
+    })
+}
+
 async fn consume_pool_notes(
     client: &mut MidenClient,
     pool_id_opt: Option<String>,
     deposit_info_map: HashMap<String, DepositInfo>,
-    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    lp_faucets: &HashMap<AccountId, AccountId>,
+    pending_txs: &mut Vec<PendingTx>,
+    note_cache: &mut NoteCache,
+    submit_permits: &Semaphore,
+    sync_freshness: &mut SyncFreshness,
     auto_poll: bool,
 ) -> Result<ConsumeResponse> {
     // Load pool IDs
-    let pools_json = fs::read_to_string("pools.json")?;
-    let pools: serde_json::Value = serde_json::from_str(&pools_json)?;
-
     let pool_ids = if let Some(pool_id_hex) = pool_id_opt {
         vec![AccountId::from_hex(&pool_id_hex)?]
     } else {
-        vec![
-            AccountId::from_hex(pools["milo_musdc_pool_id"].as_str().unwrap())?,
-            AccountId::from_hex(pools["melo_musdc_pool_id"].as_str().unwrap())?,
-        ]
+        load_pools_config("pools.json")?.pool_ids()
     };
 
     let mut total_consumed = 0;
@@ -538,29 +1845,26 @@ async fn consume_pool_notes(
         if !auto_poll {
             println!("   🔄 Syncing state...");
         }
-        match tokio::time::timeout(Duration::from_secs(45), client.sync_state()).await {
-            Ok(Ok(_)) => {
+        match sync_if_stale(client, sync_freshness, false).await {
+            Ok(true) => {
                 if !auto_poll { println!("   ✅ Sync completed"); }
             }
-            Ok(Err(e)) => {
-                if !auto_poll {
-                    println!("   ⚠️  Sync failed: {:?}", e);
-                    println!("   ⏩ Continuing anyway to check local store");
-                }
+            Ok(false) => {
+                if !auto_poll { println!("   ✅ State is fresh - sync skipped"); }
             }
-            Err(_) => {
+            Err(e) => {
                 if !auto_poll {
-                    println!("   ⚠️  Sync timeout");
-                    println!("   ⏩ Continuing with stale data");
+                    println!("   ⚠️  Sync failed: {:#}", e);
+                    println!("   ⏩ Continuing anyway to check local store");
                 }
             }
         }
 
-        // Get consumable P2ID notes for pool
-        let notes = client.get_consumable_notes(Some(*pool_id)).await?;
+        // Next page of consumable P2ID notes - HTTP requests always re-fetch
+        let (notes, queued) = next_note_page(client, note_cache, *pool_id, !auto_poll).await?;
 
         if !auto_poll || !notes.is_empty() {
-            println!("   📝 Found {} consumable P2ID note(s)", notes.len());
+            println!("   📝 Processing {} consumable P2ID note(s) ({} queued for later cycles)", notes.len(), queued);
         }
 
         if notes.is_empty() {
@@ -568,9 +1872,26 @@ async fn consume_pool_notes(
             continue;
         }
 
-        for (note, _) in notes {
+        for note in notes {
             let note_id = note.id();
             let note_id_hex = note_id.to_hex();
+
+            // Already submitted - waiting on the confirmation tracker
+            if pending_txs.iter().any(|p| p.note_id_hex == note_id_hex) {
+                continue;
+            }
+
+            // LP tokens handed back for a /withdraw - consuming them here
+            // would count them as reserves
+            if let Some(lp_faucet_id) = lp_faucets.get(pool_id) {
+                if carries_asset_of(&note, *lp_faucet_id) {
+                    if !auto_poll {
+                        println!("      🪙 LP token note {} left for /withdraw", note_id_hex.chars().take(16).collect::<String>());
+                    }
+                    continue;
+                }
+            }
+
             println!("      🔄 Processing P2ID note: {}", note_id_hex.chars().take(16).collect::<String>());
 
             // Check if this note has deposit info
@@ -585,88 +1906,49 @@ async fn consume_pool_notes(
                 println!("         📝 Regular P2ID note (no deposit info) - consuming...");
             }
 
-            // Consume the P2ID note (pool receives tokens)
-            let tx_request = TransactionRequestBuilder::new()
+            // Sync before each submission so the pool's account state isn't stale
+            if let Err(e) = sync_if_stale(client, sync_freshness, true).await {
+                println!("         ⚠️  Pre-submit sync failed: {:#}", e);
+            }
+
+            // Quote LP at pre-consume reserves so slippage can be checked on commit
+            let lp_quote = match deposit_info {
+                Some(info) => match quote_deposit_lp(client, *pool_id, info).await {
+                    Ok(lp) => {
+                        println!("         🧮 LP quote: {} (min: {})", lp, info.min_lp_amount_out);
+                        Some(lp)
+                    }
+                    Err(e) => {
+                        println!("         ⚠️  LP quote failed: {:?}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            // Consume the P2ID note (pool receives tokens) and sync the reserves
+            let tx_request = pool_tx_builder(client, *pool_id, sync_reserves_script).await?
                 .authenticated_input_notes([(note_id, None)])
                 .build()?;
 
-            match client.submit_new_transaction(*pool_id, tx_request).await {
+            // Submit and move on - crediting happens once the tx commits
+            match submit_with_permit(client, submit_permits, *pool_id, tx_request).await {
                 Ok(tx_id) => {
                     println!("         📤 Tx submitted: {}", tx_id.to_hex().chars().take(16).collect::<String>());
-
-                    match tokio::time::timeout(
-                        Duration::from_secs(30),
-                        wait_for_transaction(client, tx_id)
-                    ).await {
-                        Ok(Ok(_)) => {
-                            total_consumed += 1;
-                            println!("         ✅ Consumed!");
-
-                            // Track deposit per user if deposit_info exists
-                            if let Some(info) = deposit_info {
-                                let amount: u64 = info.amount.parse().unwrap_or(0);
-                                if amount > 0 {
-                                    let key = format!("{}:{}", info.user_account_id, pool_id.to_hex());
-                                    let now = std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs();
-                                    let mut deps = user_deposits.lock().unwrap();
-                                    let entry = deps.entry(key).or_insert(UserPoolDeposit {
-                                        user_account_id: info.user_account_id.clone(),
-                                        pool_account_id: pool_id.to_hex(),
-                                        total_deposited: 0,
-                                        deposit_count: 0,
-                                        last_deposit_time: 0,
-                                    });
-                                    entry.total_deposited += amount;
-                                    entry.deposit_count += 1;
-                                    entry.last_deposit_time = now;
-                                    println!("         💾 User deposit tracked: {} total for {}",
-                                        entry.total_deposited, info.user_account_id);
-                                    save_user_deposits(&deps);
-                                }
-                            }
-                        }
-                        Ok(Err(e)) => {
-                            println!("         ⚠️  Wait failed: {:?}", e);
-                        }
-                        Err(_) => {
-                            println!("         ⚠️  Wait timeout (tx may still succeed)");
-                            total_consumed += 1;
-
-                            // Also track on timeout since tx may succeed
-                            if let Some(info) = deposit_info {
-                                let amount: u64 = info.amount.parse().unwrap_or(0);
-                                if amount > 0 {
-                                    let key = format!("{}:{}", info.user_account_id, pool_id.to_hex());
-                                    let now = std::time::SystemTime::now()
-                                        .duration_since(std::time::UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs();
-                                    let mut deps = user_deposits.lock().unwrap();
-                                    let entry = deps.entry(key).or_insert(UserPoolDeposit {
-                                        user_account_id: info.user_account_id.clone(),
-                                        pool_account_id: pool_id.to_hex(),
-                                        total_deposited: 0,
-                                        deposit_count: 0,
-                                        last_deposit_time: 0,
-                                    });
-                                    entry.total_deposited += amount;
-                                    entry.deposit_count += 1;
-                                    entry.last_deposit_time = now;
-                                    save_user_deposits(&deps);
-                                }
-                            }
-                        }
-                    }
+                    total_consumed += 1;
+                    pending_txs.push(PendingTx {
+                        tx_id,
+                        note_id_hex: note_id_hex.clone(),
+                        pool_id: *pool_id,
+                        submitted_at: Instant::now(),
+                        deposit: deposit_info.cloned(),
+                        lp_quote,
+                    });
                 }
                 Err(e) => {
                     println!("         ❌ Submit failed: {:?}", e);
                 }
             }
-
-            sleep(Duration::from_secs(1)).await;
         }
     }
 
@@ -676,520 +1958,3314 @@ async fn consume_pool_notes(
     })
 }
 
-async fn wait_for_transaction(
-    client: &mut MidenClient,
-    tx_id: miden_objects::transaction::TransactionId,
-) -> Result<()> {
-    for _ in 0..60 {
-        match client.get_transactions(TransactionFilter::Ids(vec![tx_id])).await {
-            Ok(transactions) => {
-                if !transactions.is_empty() {
-                    return Ok(());
-                }
-            }
-            Err(_) => {}
+/// Largest amount `user_id` may add to `pool_id` without their share of the
+/// pool's credited liquidity exceeding `max_share_bps`. `None` means unlimited:
+/// no cap is configured, or nobody else holds liquidity in the pool (a sole
+/// LP always holds 100%, so capping them would block the pool from growing).
+fn deposit_headroom(
+    deposits: &HashMap<String, UserPoolDeposit>,
+    pool_id_hex: &str,
+    user_id: &str,
+    max_share_bps: Option<u64>,
+) -> Option<u64> {
+    let cap_bps = max_share_bps? as u128;
+    let (mut user_total, mut pool_total) = (0u128, 0u128);
+    for d in deposits.values().filter(|d| d.pool_account_id == pool_id_hex) {
+        pool_total += d.total_deposited as u128;
+        if d.user_account_id == user_id {
+            user_total += d.total_deposited as u128;
         }
-        sleep(Duration::from_millis(500)).await;
     }
-    Err(anyhow::anyhow!("Transaction timeout"))
+    if pool_total == user_total {
+        return None;
+    }
+
+    // (user + x) / (pool + x) <= cap  =>  x <= (cap * pool - user) / (1 - cap)
+    let allowed = (cap_bps * pool_total).saturating_sub(user_total * 10_000);
+    Some((allowed / (10_000 - cap_bps)).min(u64::MAX as u128) as u64)
 }
 
-/// Execute withdrawal: read pool reserves, calculate proportional amounts,
-/// create P2ID notes from pool to user for both tokens
-/// Enforces per-user deposit limits to prevent draining
-async fn execute_withdraw(
-    client: &mut MidenClient,
-    pool_id: AccountId,
-    user_id: AccountId,
-    lp_amount: u64,
-    _min_token_a_out: u64,
-    _min_token_b_out: u64,
-    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
-) -> Result<WithdrawResponse> {
-    println!("   🔄 Executing withdrawal...");
-    println!("      Pool: {}", pool_id.to_hex());
-    println!("      User: {}", user_id.to_hex());
-    println!("      LP Amount requested: {}", lp_amount);
+/// LP shares for crediting `amount` to a pool holding `total_liquidity`
+/// (both reserves, including the deposit). A pool read that doesn't show the
+/// deposit yet counts the deposit as the whole pool.
+fn shares_for_deposit(amount: u64, total_liquidity: u64) -> u64 {
+    let liquidity = total_liquidity.max(amount);
+    if liquidity == 0 {
+        return 0;
+    }
+    (amount as u128 * SHARE_PRECISION as u128 / liquidity as u128).min(u64::MAX as u128) as u64
+}
 
-    // Check user's tracked deposits - limit withdrawal to what they deposited
-    let deposit_key = format!("{}:{}", user_id.to_hex(), pool_id.to_hex());
-    let max_withdrawal = {
-        let deps = user_deposits.lock().unwrap();
-        deps.get(&deposit_key).map(|d| d.total_deposited).unwrap_or(0)
-    };
+/// Shares of a pool held across all tracked deposits
+fn outstanding_shares(deposits: &HashMap<String, UserPoolDeposit>, pool_id_hex: &str) -> u128 {
+    deposits.values()
+        .filter(|d| d.pool_account_id == pool_id_hex)
+        .map(|d| d.lp_shares as u128)
+        .sum()
+}
 
-    if max_withdrawal == 0 {
-        return Err(anyhow::anyhow!(
-            "No tracked deposits found for user {} in pool {}. You can only withdraw what you deposited.",
-            user_id.to_hex(), pool_id.to_hex()
-        ));
+/// Part of `total_liquidity` that `shares` out of `outstanding` are worth
+fn share_value(shares: u64, outstanding: u128, total_liquidity: u64) -> u64 {
+    if outstanding == 0 {
+        return 0;
     }
+    (shares as u128 * total_liquidity as u128 / outstanding).min(u64::MAX as u128) as u64
+}
 
-    // Clamp lp_amount to user's max withdrawal
-    let actual_lp_amount = lp_amount.min(max_withdrawal);
-    println!("      User max withdrawal: {}", max_withdrawal);
-    println!("      Actual LP amount: {}", actual_lp_amount);
-
-    // Sync state
-    client.sync_state().await?;
+/// Sum of a pool's reserves in the local store
+async fn pool_liquidity(client: &mut MidenClient, pool_id: AccountId) -> Result<u64> {
+    Ok(vault_reserves(client, pool_id).await?
+        .into_iter()
+        .fold(0u64, |total, reserve| total.saturating_add(reserve)))
+}
 
-    // Read pool account and vault
+/// Fungible balances in a pool's vault, in vault order
+async fn vault_reserves(client: &mut MidenClient, pool_id: AccountId) -> Result<Vec<u64>> {
     let pool_account = client.get_account(pool_id).await?
         .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
-    let pool_vault = pool_account.account().vault();
-
-    // Get all fungible assets in pool vault (these are the reserves)
-    let mut token_reserves: Vec<(AccountId, u64)> = Vec::new();
-    for asset in pool_vault.assets() {
+    let mut reserves = Vec::new();
+    for asset in pool_account.account().vault().assets() {
         if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
-            let faucet_id = fungible_asset.faucet_id();
-            let amount: u64 = fungible_asset.amount().try_into()?;
-            println!("      Reserve: {} = {}", faucet_id.to_hex(), amount);
-            token_reserves.push((faucet_id, amount));
+            reserves.push(reserve_amount(&fungible_asset)?);
         }
     }
+    Ok(reserves)
+}
 
-    if token_reserves.len() < 2 {
-        return Err(anyhow::anyhow!("Pool must have at least 2 token reserves, found {}", token_reserves.len()));
-    }
+/// Whether a note holds any of `faucet_id`'s token
+fn carries_asset_of(note: &InputNoteRecord, faucet_id: AccountId) -> bool {
+    note.assets().iter().any(|asset| matches!(
+        asset, miden_client::asset::Asset::Fungible(fungible) if fungible.faucet_id() == faucet_id
+    ))
+}
 
-    let (token_a_faucet, reserve_a) = token_reserves[0];
-    let (token_b_faucet, reserve_b) = token_reserves[1];
-    let total_liquidity = reserve_a + reserve_b;
+/// LP tokens of `lp_faucet_id` held by LPs or owed to them: its issued
+/// supply minus what withdrawals sent back for burning, plus queued mints
+async fn circulating_lp_supply(client: &mut MidenClient, lp_faucet_id: AccountId, lp_queues: &LpQueues) -> Result<u64> {
+    let lp_faucet = client.get_account(lp_faucet_id).await?
+        .ok_or_else(|| anyhow::anyhow!("LP faucet {} not in the local store", lp_faucet_id.to_hex()))?;
+    Ok(lp_queues.circulating(lp_faucet_id, lp_supply(lp_faucet.account())?))
+}
 
-    if total_liquidity == 0 {
-        return Err(anyhow::anyhow!("Pool has no liquidity"));
+/// LP tokens owed for `credited` added to a pool that held `liquidity_before`
+async fn quote_lp_mint(
+    client: &mut MidenClient,
+    lp_faucet_id: AccountId,
+    credited: u64,
+    liquidity_before: u64,
+    lp_queues: &LpQueues,
+) -> Result<u64> {
+    let supply = circulating_lp_supply(client, lp_faucet_id, lp_queues).await?;
+    let lp_amount = lp_tokens_for_deposit(credited, liquidity_before, supply);
+    if lp_amount == 0 {
+        anyhow::bail!("Deposit of {} is worth 0 LP tokens ({} supply over {} liquidity)", credited, supply, liquidity_before);
     }
+    Ok(lp_amount)
+}
 
-    // Calculate proportional amounts using clamped amount
-    let token_a_out = ((actual_lp_amount as u128) * (reserve_a as u128) / (total_liquidity as u128)) as u64;
-    let token_b_out = ((actual_lp_amount as u128) * (reserve_b as u128) / (total_liquidity as u128)) as u64;
+/// Mint the LP tokens of a queued mint to its depositor
+async fn submit_lp_mint(
+    client: &mut MidenClient,
+    submit_permits: &Semaphore,
+    mint: &PendingLpMint,
+) -> Result<miden_objects::transaction::TransactionId> {
+    let user_id = AccountId::from_hex(&mint.user_account_id)?;
+    let asset = FungibleAsset::new(mint.lp_faucet_id, mint.lp_amount)?;
+    let tx_request = TransactionRequestBuilder::new()
+        .build_mint_fungible_asset(asset, user_id, NoteType::Public, client.rng())?;
+    submit_with_permit(client, submit_permits, mint.lp_faucet_id, tx_request).await
+}
 
-    println!("      Token A out: {} (faucet: {})", token_a_out, token_a_faucet.to_hex());
-    println!("      Token B out: {} (faucet: {})", token_b_out, token_b_faucet.to_hex());
+/// Mint the LP tokens owed for final deposits. A failed mint is retried on
+/// the next pass; after LP_MINT_MAX_ATTEMPTS the deposit is refunded
+/// instead and its credit reversed.
+async fn mint_owed_lp(
+    client: &mut MidenClient,
+    submit_permits: &Semaphore,
+    lp_queues: &mut LpQueues,
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+) {
+    let mut still_owed = Vec::new();
+    for mut mint in std::mem::take(&mut lp_queues.mints) {
+        if !mint.ready {
+            still_owed.push(mint);
+            continue;
+        }
+        let error = match submit_lp_mint(client, submit_permits, &mint).await {
+            Ok(tx_id) => {
+                println!("   🪙 Minted {} LP to {} (tx {})",
+                    mint.lp_amount, mint.user_account_id, tx_id.to_hex().chars().take(16).collect::<String>());
+                delete_lp_mint(&mint.deposit_tx_id);
+                continue;
+            }
+            Err(e) => e,
+        };
+        mint.attempts += 1;
+        println!("   ❌ LP mint of {} to {} failed ({}/{}): {:?}",
+            mint.lp_amount, mint.user_account_id, mint.attempts, LP_MINT_MAX_ATTEMPTS, error);
+        if mint.attempts < LP_MINT_MAX_ATTEMPTS {
+            save_lp_mint(&mint);
+            still_owed.push(mint);
+            continue;
+        }
 
-    if token_a_out == 0 && token_b_out == 0 {
-        return Err(anyhow::anyhow!("Calculated output amounts are both 0"));
+        // Give the deposit back rather than keep it without LP tokens
+        let info = DepositInfo {
+            note_id: mint.note_id.clone(),
+            pool_account_id: mint.pool_id.to_hex(),
+            token_id: mint.token_id.clone(),
+            amount: mint.credited.to_string(),
+            user_account_id: mint.user_account_id.clone(),
+            min_lp_amount_out: "0".to_string(),
+            timestamp: 0,
+        };
+        match refund_excess(client, submit_permits, mint.pool_id, &info, mint.credited).await {
+            Ok(refund_tx) => {
+                println!("   ↩️  Refunded the {} deposit of {} instead (tx {})",
+                    mint.credited, mint.user_account_id, refund_tx.to_hex().chars().take(16).collect::<String>());
+                reverse_credit(user_deposits, &mint.user_account_id, mint.pool_id, mint.credited, mint.lp_shares);
+                record_refund(user_deposits, &info, mint.pool_id, mint.credited, "lp_mint", Some(refund_tx.to_hex()));
+                delete_lp_mint(&mint.deposit_tx_id);
+            }
+            Err(e) => {
+                println!("   ❌ Refund of {} to {} failed, retrying next poll: {:?}", mint.credited, mint.user_account_id, e);
+                still_owed.push(mint);
+            }
+        }
     }
+    lp_queues.mints = still_owed;
+}
 
-    let mut last_tx_id = String::new();
-
-    // Create P2ID note from pool to user for token A
-    if token_a_out > 0 {
-        println!("      📤 Creating P2ID note for token A...");
-        let asset_a = FungibleAsset::new(token_a_faucet, token_a_out)?;
-        let note_a = create_p2id_note(
-            pool_id,
-            user_id,
-            vec![asset_a.into()],
-            NoteType::Public,
-            Felt::new(0),
-            client.rng(),
-        )?;
-
-        let tx_a = TransactionRequestBuilder::new()
-            .own_output_notes(vec![OutputNote::Full(note_a)])
-            .build()?;
-
-        let tx_id_a = client.submit_new_transaction(pool_id, tx_a).await?;
-        last_tx_id = tx_id_a.to_hex();
-        println!("      📤 Token A tx submitted: {}", last_tx_id.chars().take(16).collect::<String>());
+/// Credit a consumed deposit note to `deposits`, up to the per-user share
+/// cap, minting LP shares against `total_liquidity`. Returns the excess that
+/// was not credited, the shares minted and the key of the credited entry
+/// (`None` when nothing was credited).
+fn apply_deposit_credit(
+    deposits: &mut HashMap<String, UserPoolDeposit>,
+    info: &DepositInfo,
+    pool_id_hex: &str,
+    max_share_bps: Option<u64>,
+    total_liquidity: u64,
+    now: u64,
+) -> (u64, u64, Option<String>) {
+    let amount: u64 = info.amount.parse().unwrap_or(0);
+    if amount == 0 {
+        return (0, 0, None);
+    }
+    let credited = match deposit_headroom(deposits, pool_id_hex, &info.user_account_id, max_share_bps) {
+        Some(headroom) => amount.min(headroom),
+        None => amount,
+    };
+    let excess = amount - credited;
+    if credited == 0 {
+        return (excess, 0, None);
+    }
+    let key = format!("{}:{}", info.user_account_id, pool_id_hex);
+    let entry = deposits.entry(key.clone()).or_insert(UserPoolDeposit {
+        user_account_id: info.user_account_id.clone(),
+        pool_account_id: pool_id_hex.to_string(),
+        total_deposited: 0,
+        lp_shares: 0,
+        deposit_count: 0,
+        last_deposit_time: 0,
+        refunds: Vec::new(),
+    });
+    let shares = shares_for_deposit(credited, total_liquidity);
+    entry.total_deposited += credited;
+    entry.lp_shares += shares;
+    entry.deposit_count += 1;
+    entry.last_deposit_time = now;
+    (excess, shares, Some(key))
+}
 
-        match tokio::time::timeout(Duration::from_secs(30), wait_for_transaction(client, tx_id_a)).await {
-            Ok(Ok(_)) => println!("      ✅ Token A sent to user!"),
-            Ok(Err(e)) => println!("      ⚠️  Token A wait failed: {:?}", e),
-            Err(_) => println!("      ⚠️  Token A wait timeout (tx may still succeed)"),
+/// Credit a consumed deposit note to the user's tracked deposits and save
+/// the entry (see `apply_deposit_credit`). Returns the excess that must be
+/// refunded to the user and the shares minted.
+fn credit_deposit(
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    info: &DepositInfo,
+    pool_id: AccountId,
+    max_share_bps: Option<u64>,
+    total_liquidity: u64,
+) -> (u64, u64) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut deps = user_deposits.lock().unwrap();
+    let (excess, shares, key) =
+        apply_deposit_credit(&mut deps, info, &pool_id.to_hex(), max_share_bps, total_liquidity, now);
+    let Some(entry) = key.and_then(|key| deps.get(&key)) else {
+        if excess > 0 {
+            println!("         🚫 Share cap reached - nothing credited for {}", info.user_account_id);
         }
-
-        sleep(Duration::from_secs(1)).await;
+        return (excess, shares);
+    };
+    println!("         💾 User deposit tracked: {} total, {} LP shares for {}",
+        entry.total_deposited, entry.lp_shares, info.user_account_id);
+    if excess > 0 {
+        println!("         🚫 Share cap: {} over the limit", excess);
     }
+    save_user_deposit(entry);
+    (excess, shares)
+}
 
-    // Create P2ID note from pool to user for token B
-    if token_b_out > 0 {
-        println!("      📤 Creating P2ID note for token B...");
+/// Send the uncredited part of a capped deposit back to the user as a P2ID note
+async fn refund_excess(
+    client: &mut MidenClient,
+    submit_permits: &Semaphore,
+    pool_id: AccountId,
+    info: &DepositInfo,
+    excess: u64,
+) -> Result<miden_objects::transaction::TransactionId> {
+    let user_id = AccountId::from_hex(&info.user_account_id)?;
+    let token_id = AccountId::from_hex(&info.token_id)?;
 
-        // Re-sync state after first tx
-        client.sync_state().await?;
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
 
-        let asset_b = FungibleAsset::new(token_b_faucet, token_b_out)?;
-        let note_b = create_p2id_note(
-            pool_id,
-            user_id,
-            vec![asset_b.into()],
-            NoteType::Public,
-            Felt::new(0),
-            client.rng(),
-        )?;
+    let asset = FungibleAsset::new(token_id, excess)?;
+    let note = create_p2id_note(
+        pool_id,
+        user_id,
+        vec![asset.into()],
+        NoteType::Public,
+        Felt::new(0),
+        client.rng(),
+    )?;
+    let tx_request = pool_tx_builder(client, pool_id, record_reserves_script).await?
+        .own_output_notes(vec![OutputNote::Full(note)])
+        .build()?;
+
+    submit_with_permit(client, submit_permits, pool_id, tx_request).await
+}
 
-        let tx_b = TransactionRequestBuilder::new()
-            .own_output_notes(vec![OutputNote::Full(note_b)])
-            .build()?;
+/// Take the next page of up to `max_per_cycle` consumable notes for a pool.
+/// The full list is only re-fetched when the cached batch is drained, older
+/// than NOTE_CACHE_TTL_SECS, or `refresh` is set. Returns the page and the
+/// number of notes still queued.
+async fn next_note_page(
+    client: &mut MidenClient,
+    note_cache: &mut NoteCache,
+    pool_id: AccountId,
+    refresh: bool,
+) -> Result<(Vec<InputNoteRecord>, usize)> {
+    let stale = match note_cache.batches.get(&pool_id) {
+        Some(batch) => batch.notes.is_empty()
+            || batch.fetched_at.elapsed() >= Duration::from_secs(NOTE_CACHE_TTL_SECS),
+        None => true,
+    };
 
-        let tx_id_b = client.submit_new_transaction(pool_id, tx_b).await?;
-        last_tx_id = tx_id_b.to_hex();
-        println!("      📤 Token B tx submitted: {}", last_tx_id.chars().take(16).collect::<String>());
+    if refresh || stale {
+        let notes = client.get_consumable_notes(Some(pool_id)).await?;
+        note_cache.batches.insert(pool_id, NoteBatch {
+            notes: notes.into_iter().map(|(note, _)| note).collect(),
+            fetched_at: Instant::now(),
+        });
+    }
 
-        match tokio::time::timeout(Duration::from_secs(30), wait_for_transaction(client, tx_id_b)).await {
-            Ok(Ok(_)) => println!("      ✅ Token B sent to user!"),
-            Ok(Err(e)) => println!("      ⚠️  Token B wait failed: {:?}", e),
-            Err(_) => println!("      ⚠️  Token B wait timeout (tx may still succeed)"),
-        }
+    let max_per_cycle = note_cache.max_per_cycle;
+    let batch = note_cache.batches.get_mut(&pool_id)
+        .ok_or_else(|| anyhow::anyhow!("Note batch missing for pool {}", pool_id.to_hex()))?;
+    let take = batch.notes.len().min(max_per_cycle);
+    let page: Vec<InputNoteRecord> = batch.notes.drain(..take).collect();
+    Ok((page, batch.notes.len()))
+}
+
+/// Confirmation tracker: check outstanding consume transactions and credit
+/// deposits for those that committed. Returns the number committed.
+/// Timed-out transactions are still credited (the tx may yet succeed),
+/// matching the behaviour of the old blocking wait.
+async fn process_pending_txs(
+    client: &mut MidenClient,
+    pending_txs: &mut Vec<PendingTx>,
+    committed_txs: &mut Vec<CommittedTx>,
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    max_share_bps: Option<u64>,
+    slippage_action: SlippageAction,
+    submit_permits: &Semaphore,
+    metrics: &Mutex<MetricsState>,
+    sync_freshness: &mut SyncFreshness,
+    lp_faucets: &HashMap<AccountId, AccountId>,
+    lp_queues: &mut LpQueues,
+    tracked_notes: &Mutex<Vec<TrackedNote>>,
+) -> usize {
+    if let Err(e) = sync_if_stale(client, sync_freshness, true).await {
+        println!("⚠️  Confirmation sync failed: {:#}", e);
+        return 0;
+    }
+    if pending_txs.is_empty() {
+        return 0;
     }
 
-    // Deduct withdrawn amount from user's tracked deposits
-    {
-        let mut deps = user_deposits.lock().unwrap();
-        if let Some(entry) = deps.get_mut(&deposit_key) {
-            let withdrawn = token_a_out + token_b_out;
-            if withdrawn >= entry.total_deposited {
-                entry.total_deposited = 0;
-            } else {
-                entry.total_deposited -= withdrawn;
+    let ids = pending_txs.iter().map(|p| p.tx_id).collect();
+    let statuses: HashMap<String, TransactionStatus> = match client.get_transactions(TransactionFilter::Ids(ids)).await {
+        Ok(records) => records.into_iter().map(|r| (r.id.to_hex(), r.status)).collect(),
+        Err(e) => {
+            println!("⚠️  Failed to read pending transactions: {:?}", e);
+            return 0;
+        }
+    };
+
+    let mut committed = 0;
+    let mut still_pending = Vec::new();
+    let mut to_credit = Vec::new();
+    let mut touched_pools: Vec<AccountId> = Vec::new();
+    for tx in pending_txs.drain(..) {
+        let tx_id_short = tx.tx_id.to_hex().chars().take(16).collect::<String>();
+        match statuses.get(&tx.tx_id.to_hex()) {
+            Some(TransactionStatus::Committed { block_number, .. }) => {
+                committed += 1;
+                println!("   ✅ Consumed! (tx {})", tx_id_short);
+                metrics.lock().unwrap().record_consumed(&tx.pool_id.to_hex());
+                if !touched_pools.contains(&tx.pool_id) {
+                    touched_pools.push(tx.pool_id);
+                }
+                to_credit.push((tx, Some(block_number.as_u32())));
             }
-            println!("      💾 User deposit updated: {} remaining", entry.total_deposited);
-            save_user_deposits(&deps);
+            Some(TransactionStatus::Discarded(cause)) => {
+                println!("   ❌ Consume tx {} discarded: {:?} - note will be retried", tx_id_short, cause);
+            }
+            _ if tx.submitted_at.elapsed() >= Duration::from_secs(CONFIRMATION_TIMEOUT_SECS) => {
+                println!("   ⚠️  Consume tx {} wait timeout (tx may still succeed)", tx_id_short);
+                to_credit.push((tx, None));
+            }
+            _ => still_pending.push(tx),
         }
     }
+    *pending_txs = still_pending;
+
+    for (tx, block_num) in to_credit {
+        set_note_status(&mut tracked_notes.lock().unwrap(), &tx.note_id_hex, "Consumed");
+        let Some(ref info) = tx.deposit else { continue };
+        ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+            "liquidity", "deposit", &info.user_account_id, &info.token_id,
+            info.amount.parse().unwrap_or(0), Direction::Out, &tx.tx_id.to_hex(),
+        ));
 
-    println!("   ✅ Withdrawal complete!");
+        // Pool ratio moved since the user built the note: refund instead of crediting
+        if let Some((lp_quote, min_lp)) = lp_quote_shortfall(tx.lp_quote, &info.min_lp_amount_out) {
+            if slippage_action == SlippageAction::Refund {
+                let amount: u64 = info.amount.parse().unwrap_or(0);
+                println!("   🚫 Deposit {} below min LP ({} < {}) - refunding {}",
+                    tx.note_id_hex.chars().take(16).collect::<String>(), lp_quote, min_lp, amount);
+                let refund_tx = match refund_excess(client, submit_permits, tx.pool_id, info, amount).await {
+                    Ok(refund_tx) => Some(refund_tx.to_hex()),
+                    Err(e) => {
+                        println!("   ❌ Slippage refund of {} to {} failed: {:?}", amount, info.user_account_id, e);
+                        None
+                    }
+                };
+                record_refund(user_deposits, info, tx.pool_id, amount, "slippage", refund_tx);
+                continue;
+            }
+            println!("   ⚠️  Deposit below min LP ({} < {}) - crediting anyway", lp_quote, min_lp);
+        }
+
+        // Reserves after the consume, so the deposit is part of the liquidity
+        let total_liquidity = match pool_liquidity(client, tx.pool_id).await {
+            Ok(total) => total,
+            Err(e) => {
+                println!("   ⚠️  Could not read pool liquidity for LP shares: {:?}", e);
+                0
+            }
+        };
+        let (excess, lp_shares) = credit_deposit(user_deposits, info, tx.pool_id, max_share_bps, total_liquidity);
+        let amount = info.amount.parse::<u64>().unwrap_or(0);
+        let mut credited = amount.saturating_sub(excess);
+        if let (Some(lp_faucet_id), true) = (lp_faucets.get(&tx.pool_id), credited > 0) {
+            // The whole note is in the reserves until the excess is refunded
+            let liquidity_before = total_liquidity.saturating_sub(amount);
+            match quote_lp_mint(client, *lp_faucet_id, credited, liquidity_before, lp_queues).await {
+                Ok(lp_amount) => {
+                    println!("   🪙 {} LP owed to {}, minted once the deposit is final", lp_amount, info.user_account_id);
+                    lp_queues.queue_mint(PendingLpMint {
+                        deposit_tx_id: tx.tx_id.to_hex(),
+                        pool_id: tx.pool_id,
+                        lp_faucet_id: *lp_faucet_id,
+                        user_account_id: info.user_account_id.clone(),
+                        token_id: info.token_id.clone(),
+                        note_id: info.note_id.clone(),
+                        credited,
+                        lp_shares,
+                        lp_amount,
+                        ready: false,
+                        attempts: 0,
+                    });
+                }
+                Err(e) => {
+                    // No LP tokens for it: give the credited part back too
+                    println!("   ❌ LP quote for {} of {} failed: {:?} - refunding", credited, info.user_account_id, e);
+                    reverse_credit(user_deposits, &info.user_account_id, tx.pool_id, credited, lp_shares);
+                    let refund_tx = match refund_excess(client, submit_permits, tx.pool_id, info, credited).await {
+                        Ok(refund_tx) => Some(refund_tx.to_hex()),
+                        Err(e) => {
+                            println!("   ❌ Refund of {} to {} failed: {:?}", credited, info.user_account_id, e);
+                            None
+                        }
+                    };
+                    record_refund(user_deposits, info, tx.pool_id, credited, "lp_mint", refund_tx);
+                    credited = 0;
+                }
+            }
+        }
+        if credited > 0 {
+            committed_txs.push(CommittedTx {
+                tx_id: tx.tx_id,
+                note_id_hex: tx.note_id_hex.clone(),
+                pool_id: tx.pool_id,
+                user_account_id: info.user_account_id.clone(),
+                token_id: info.token_id.clone(),
+                credited,
+                lp_shares,
+                block_num,
+                credited_at: Instant::now(),
+            });
+        }
+        if excess > 0 {
+            let refund_tx = match refund_excess(client, submit_permits, tx.pool_id, info, excess).await {
+                Ok(refund_tx) => {
+                    println!("   ↩️  Refunded {} to {} (tx {})",
+                        excess, info.user_account_id, refund_tx.to_hex().chars().take(16).collect::<String>());
+                    Some(refund_tx.to_hex())
+                }
+                Err(e) => {
+                    println!("   ❌ Refund of {} to {} failed: {:?}", excess, info.user_account_id, e);
+                    None
+                }
+            };
+            record_refund(user_deposits, info, tx.pool_id, excess, "share_cap", refund_tx);
+        }
+    }
+
+    // Gauges follow the pool's vault order
+    for pool_id in touched_pools {
+        match vault_reserves(client, pool_id).await {
+            Ok(reserves) if reserves.len() == 2 => {
+                metrics.lock().unwrap().set_reserves(&pool_id.to_hex(), reserves[0], reserves[1]);
+            }
+            Ok(_) => {}
+            Err(e) => println!("⚠️  Failed to read reserves of pool {} for metrics: {:?}", pool_id.to_hex(), e),
+        }
+    }
+    committed
+}
+
+/// Record a refunded deposit on the user's entry so /user_deposits can show why
+fn record_refund(
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    info: &DepositInfo,
+    pool_id: AccountId,
+    amount: u64,
+    reason: &str,
+    refund_tx_id: Option<String>,
+) {
+    let key = format!("{}:{}", info.user_account_id, pool_id.to_hex());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if let Some(ref refund_tx_id) = refund_tx_id {
+        ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+            "liquidity", "refund", &info.user_account_id, &info.token_id, amount, Direction::In, refund_tx_id,
+        ));
+    }
+    let mut deps = user_deposits.lock().unwrap();
+    let entry = deps.entry(key).or_insert(UserPoolDeposit {
+        user_account_id: info.user_account_id.clone(),
+        pool_account_id: pool_id.to_hex(),
+        total_deposited: 0,
+        lp_shares: 0,
+        deposit_count: 0,
+        last_deposit_time: 0,
+        refunds: Vec::new(),
+    });
+    entry.refunds.push(DepositRefund {
+        note_id: info.note_id.clone(),
+        amount,
+        reason: reason.to_string(),
+        refund_tx_id,
+        timestamp: now,
+    });
+    save_user_deposit(entry);
+}
+
+/// LP a single-sided deposit is worth at the pool's current reserves.
+/// Withdrawals pay out `lp * reserve / total_reserves` of each token, so a
+/// deposit of `amount` of token X is worth `amount * total / (2 * reserve_x)`;
+/// at a balanced pool that is `amount`. Empty pools quote 1:1.
+async fn quote_deposit_lp(client: &mut MidenClient, pool_id: AccountId, info: &DepositInfo) -> Result<u64> {
+    let amount: u64 = info.amount.parse()?;
+    let token_id = AccountId::from_hex(&info.token_id)?;
+
+    let pool_account = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+
+    let mut reserve_in: u64 = 0;
+    let mut total_reserves: u128 = 0;
+    for asset in pool_account.account().vault().assets() {
+        if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+            let reserve = reserve_amount(&fungible_asset)?;
+            total_reserves += reserve as u128;
+            if fungible_asset.faucet_id() == token_id {
+                reserve_in = reserve;
+            }
+        }
+    }
+
+    Ok(deposit_lp_quote(amount, reserve_in, total_reserves))
+}
+
+/// LP worth of depositing `amount` of a token whose reserve is `reserve_in`,
+/// valuing it at the pool's ratio; an empty side quotes the amount itself
+fn deposit_lp_quote(amount: u64, reserve_in: u64, total_reserves: u128) -> u64 {
+    if reserve_in == 0 || total_reserves == 0 {
+        return amount;
+    }
+    let lp = (amount as u128) * total_reserves / (2 * reserve_in as u128);
+    lp.min(u64::MAX as u128) as u64
+}
+
+/// The quote and minimum when a deposit's LP quote misses its
+/// `min_lp_amount_out`; deposits without a quote are never refunded
+fn lp_quote_shortfall(lp_quote: Option<u64>, min_lp_amount_out: &str) -> Option<(u64, u64)> {
+    let min_lp: u64 = min_lp_amount_out.parse().unwrap_or(0);
+    lp_quote.filter(|lp| *lp < min_lp).map(|lp| (lp, min_lp))
+}
+
+/// Re-verify recently credited deposits until their tx is `reorg_depth` blocks
+/// deep. A consume tx that turns out discarded has its deposit credit reversed,
+/// its queued LP mint dropped and its note set back to Pending; its DepositInfo
+/// is still tracked, so the note is retried once consumable again. Deposits
+/// that are final release their LP mint.
+async fn verify_committed_txs(
+    client: &mut MidenClient,
+    committed_txs: &mut Vec<CommittedTx>,
+    reorg_depth: u32,
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    reorgs_detected: &AtomicU64,
+    lp_queues: &mut LpQueues,
+    tracked_notes: &Mutex<Vec<TrackedNote>>,
+) {
+    if committed_txs.is_empty() {
+        return;
+    }
+
+    let sync_height = match client.get_sync_height().await {
+        Ok(height) => height.as_u32(),
+        Err(e) => {
+            println!("⚠️  Failed to read sync height: {:?}", e);
+            return;
+        }
+    };
+
+    let ids = committed_txs.iter().map(|c| c.tx_id).collect();
+    let statuses: HashMap<String, TransactionStatus> = match client.get_transactions(TransactionFilter::Ids(ids)).await {
+        Ok(records) => records.into_iter().map(|r| (r.id.to_hex(), r.status)).collect(),
+        Err(e) => {
+            println!("⚠️  Failed to re-verify committed transactions: {:?}", e);
+            return;
+        }
+    };
+
+    let mut still_recent = Vec::new();
+    for mut tx in committed_txs.drain(..) {
+        let tx_id = tx.tx_id.to_hex();
+        let status = statuses.get(&tx_id);
+        match check_committed_tx(&mut tx, status, sync_height, reorg_depth) {
+            CommitCheck::Discarded => {
+                reorgs_detected.fetch_add(1, Ordering::Relaxed);
+                println!("🚨 REORG: consume tx {} was discarded after crediting: {:?} - rolling back", tx_id, status);
+
+                let (entry, mint) = roll_back_deposit(
+                    &tx, &mut user_deposits.lock().unwrap(), &mut tracked_notes.lock().unwrap(), lp_queues,
+                );
+                if let Some(entry) = entry {
+                    save_user_deposit(&entry);
+                    ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+                        "liquidity", "deposit_rollback", &tx.user_account_id, &tx.token_id,
+                        tx.credited, Direction::In, &tx_id,
+                    ));
+                    println!("   ↩️  Reversed credit of {} for {} ({} remaining)",
+                        tx.credited, tx.user_account_id, entry.total_deposited);
+                }
+                // LP tokens are only minted for final deposits, so none are out yet
+                if let Some(mint) = mint {
+                    delete_lp_mint(&tx_id);
+                    println!("   ↩️  Dropped the {} LP owed to {} for it", mint.lp_amount, mint.user_account_id);
+                }
+                println!("   ↩️  Note {} is pending again", tx.note_id_hex);
+            }
+            CommitCheck::Final => lp_queues.release_mint(&tx_id),
+            CommitCheck::Unconfirmed => {
+                println!("⚠️  Credited deposit tx {} never seen committed - no longer tracking",
+                    tx_id.chars().take(16).collect::<String>());
+                // The credit stands, so the LP tokens are owed
+                lp_queues.release_mint(&tx_id);
+            }
+            CommitCheck::Recent => still_recent.push(tx),
+        }
+    }
+    *committed_txs = still_recent;
+}
+
+/// Where a credited deposit's consume tx stands on re-verification
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CommitCheck {
+    /// Dropped by a reorg: the credit has to be undone
+    Discarded,
+    /// reorg_depth blocks deep
+    Final,
+    /// Credited on timeout and never seen committed since
+    Unconfirmed,
+    /// Keep re-verifying
+    Recent,
+}
+
+/// Classify `tx` by its latest `status`, recording the block it committed in
+fn check_committed_tx(
+    tx: &mut CommittedTx,
+    status: Option<&TransactionStatus>,
+    sync_height: u32,
+    reorg_depth: u32,
+) -> CommitCheck {
+    match status {
+        Some(TransactionStatus::Discarded(_)) => return CommitCheck::Discarded,
+        Some(TransactionStatus::Committed { block_number, .. }) => tx.block_num = Some(block_number.as_u32()),
+        _ => {}
+    }
+    match tx.block_num {
+        Some(block) if sync_height >= block.saturating_add(reorg_depth) => CommitCheck::Final,
+        None if tx.credited_at.elapsed() >= Duration::from_secs(UNCONFIRMED_CREDIT_TRACKING_SECS) => {
+            CommitCheck::Unconfirmed
+        }
+        _ => CommitCheck::Recent,
+    }
+}
+
+/// Undo a discarded deposit in memory: the credit and its LP shares come off
+/// the user's entry, the LP mint owed for it is dropped, and its note is
+/// pending again so the next poll consumes and credits it anew. Returns the
+/// changed entry and the dropped mint, for the caller to persist.
+fn roll_back_deposit(
+    tx: &CommittedTx,
+    deposits: &mut HashMap<String, UserPoolDeposit>,
+    tracked_notes: &mut [TrackedNote],
+    lp_queues: &mut LpQueues,
+) -> (Option<UserPoolDeposit>, Option<PendingLpMint>) {
+    let key = format!("{}:{}", tx.user_account_id, tx.pool_id.to_hex());
+    let entry = deposits.get_mut(&key).map(|entry| {
+        take_back_credit(entry, tx.credited, tx.lp_shares);
+        entry.clone()
+    });
+    let mint = lp_queues.take_mint(&tx.tx_id.to_hex());
+    set_note_status(tracked_notes, &tx.note_id_hex, "Pending");
+    (entry, mint)
+}
+
+/// Set the status of `note_id` if it is tracked
+fn set_note_status(tracked_notes: &mut [TrackedNote], note_id: &str, status: &str) {
+    for note in tracked_notes.iter_mut().filter(|n| n.note_id == note_id) {
+        note.status = status.to_string();
+    }
+}
+
+/// Take a credited deposit back off the user's entry (refund). Returns what
+/// the entry still holds, or None if it isn't tracked.
+fn reverse_credit(
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    user_account_id: &str,
+    pool_id: AccountId,
+    credited: u64,
+    lp_shares: u64,
+) -> Option<u64> {
+    let key = format!("{}:{}", user_account_id, pool_id.to_hex());
+    let mut deps = user_deposits.lock().unwrap();
+    let entry = deps.get_mut(&key)?;
+    take_back_credit(entry, credited, lp_shares);
+    save_user_deposit(entry);
+    Some(entry.total_deposited)
+}
+
+/// Remove one credit of `credited` and its LP shares from `entry`
+fn take_back_credit(entry: &mut UserPoolDeposit, credited: u64, lp_shares: u64) {
+    deduct_deposit(entry, credited);
+    entry.lp_shares = entry.lp_shares.saturating_sub(lp_shares);
+    entry.deposit_count = entry.deposit_count.saturating_sub(1);
+}
+
+/// Poll until the transaction shows up in the store, backing off from
+/// TX_WAIT_BASE_DELAY_MS between polls
+async fn wait_for_transaction(
+    client: &mut MidenClient,
+    tx_id: miden_objects::transaction::TransactionId,
+) -> Result<()> {
+    let started = Instant::now();
+    for attempt in 1..=TX_WAIT_MAX_ATTEMPTS {
+        match client.get_transactions(TransactionFilter::Ids(vec![tx_id])).await {
+            Ok(transactions) => {
+                if !transactions.is_empty() {
+                    return Ok(());
+                }
+            }
+            Err(e) => println!("      ⚠️  Transaction poll {}/{} failed ({:.1}s): {:?}",
+                attempt, TX_WAIT_MAX_ATTEMPTS, started.elapsed().as_secs_f64(), e),
+        }
+        sleep(backoff_delay(TX_WAIT_BASE_DELAY_MS, attempt)).await;
+    }
+    Err(anyhow::anyhow!("Transaction timeout"))
+}
+
+/// Clamp a requested withdrawal to what the user may withdraw.
+/// Returns `None` when that is nothing.
+fn clamp_withdrawal(requested: u64, max_withdrawal: u64) -> Option<u64> {
+    if max_withdrawal == 0 {
+        return None;
+    }
+    Some(requested.min(max_withdrawal))
+}
+
+/// Tokens paid out for withdrawing `lp_amount` of `total_liquidity`: the same
+/// fraction of each reserve
+fn estimate_withdraw(reserve_a: u64, reserve_b: u64, total_liquidity: u64, lp_amount: u64) -> (u64, u64) {
+    if total_liquidity == 0 {
+        return (0, 0);
+    }
+    let token_a_out = (lp_amount as u128) * (reserve_a as u128) / (total_liquidity as u128);
+    let token_b_out = (lp_amount as u128) * (reserve_b as u128) / (total_liquidity as u128);
+    (token_a_out as u64, token_b_out as u64)
+}
+
+/// Deduct a completed withdrawal from a tracked deposit, never going below zero
+fn deduct_deposit(entry: &mut UserPoolDeposit, withdrawn: u64) {
+    entry.total_deposited = entry.total_deposited.saturating_sub(withdrawn);
+}
+
+/// A user's tracked deposit, LP shares and the pool's outstanding shares.
+/// Nothing tracked is an error: users can only withdraw what they deposited.
+fn tracked_claim(
+    deposits: &HashMap<String, UserPoolDeposit>,
+    user_id_hex: &str,
+    pool_id_hex: &str,
+) -> Result<(u64, u64, u128)> {
+    let entry = deposits.get(&format!("{}:{}", user_id_hex, pool_id_hex));
+    let total_deposited = entry.map(|d| d.total_deposited).unwrap_or(0);
+    let user_shares = entry.map(|d| d.lp_shares).unwrap_or(0);
+    if total_deposited == 0 && user_shares == 0 {
+        anyhow::bail!(
+            "No tracked deposits found for user {} in pool {}. You can only withdraw what you deposited.",
+            user_id_hex, pool_id_hex
+        );
+    }
+    Ok((total_deposited, user_shares, outstanding_shares(deposits, pool_id_hex)))
+}
+
+/// A share-based withdrawal worked out against the pool's reserves
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WithdrawPlan {
+    // Most the user's claim is worth at these reserves
+    max_withdrawal: u64,
+    // Requested amount clamped to max_withdrawal
+    amount: u64,
+    shares_burned: u64,
+    // Taken off total_deposited: the same fraction as the shares burned, or
+    // the payout for deposits from before share tracking
+    deposit_deducted: u64,
+    token_a_out: u64,
+    token_b_out: u64,
+}
+
+/// Work out a withdrawal of `lp_amount` for a user holding `user_shares` of
+/// `outstanding` shares (or `total_deposited` for deposits from before share
+/// tracking) in a pool with `reserve_a` / `reserve_b`
+fn plan_withdrawal(
+    total_deposited: u64,
+    user_shares: u64,
+    outstanding: u128,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_amount: u64,
+) -> Result<WithdrawPlan> {
+    let total_liquidity = reserve_a.saturating_add(reserve_b);
+    if total_liquidity == 0 {
+        anyhow::bail!("Pool has no liquidity");
+    }
+    let max_withdrawal = if user_shares > 0 {
+        share_value(user_shares, outstanding, total_liquidity)
+    } else {
+        total_deposited
+    };
+    let amount = clamp_withdrawal(lp_amount, max_withdrawal)
+        .ok_or_else(|| anyhow::anyhow!("Tracked share is worth nothing at current reserves"))?;
+    // Shares burned in proportion to the part of the claim withdrawn
+    let shares_burned = (user_shares as u128 * amount as u128 / max_withdrawal as u128) as u64;
+    let (token_a_out, token_b_out) = estimate_withdraw(reserve_a, reserve_b, total_liquidity, amount);
+    if token_a_out == 0 && token_b_out == 0 {
+        anyhow::bail!("Calculated output amounts are both 0");
+    }
+    // Deducting the payout would leave a share holder whose pool lost value
+    // with a deposit but no shares, which the legacy path pays out again
+    let deposit_deducted = if user_shares > 0 {
+        (total_deposited as u128 * amount as u128 / max_withdrawal as u128) as u64
+    } else {
+        token_a_out + token_b_out
+    };
+    Ok(WithdrawPlan { max_withdrawal, amount, shares_burned, deposit_deducted, token_a_out, token_b_out })
+}
+
+/// Execute withdrawal: read pool reserves, calculate proportional amounts,
+/// create P2ID notes from pool to user for both tokens
+/// Limited to the user's pro-rata share of the reserves (their LP shares over
+/// all outstanding shares); deposits from before share tracking are limited to
+/// the amount deposited. If either amount is below its minimum nothing is sent
+/// and the response carries `success: false` and the shortfall.
+async fn execute_withdraw(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    user_id: AccountId,
+    lp_amount: u64,
+    min_token_a_out: u64,
+    min_token_b_out: u64,
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+    submit_permits: &Semaphore,
+) -> Result<WithdrawResponse> {
+    println!("   🔄 Executing withdrawal...");
+    println!("      Pool: {}", pool_id.to_hex());
+    println!("      User: {}", user_id.to_hex());
+    println!("      LP Amount requested: {}", lp_amount);
+
+    // Check user's tracked deposits - nothing tracked, nothing to withdraw
+    let deposit_key = format!("{}:{}", user_id.to_hex(), pool_id.to_hex());
+    let (total_deposited, user_shares, outstanding) =
+        tracked_claim(&user_deposits.lock().unwrap(), &user_id.to_hex(), &pool_id.to_hex())?;
+
+    // Sync state
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+
+    // Read pool account and vault
+    let pool_account = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+    let pool_vault = pool_account.account().vault();
+
+    // Get all fungible assets in pool vault (these are the reserves)
+    let mut token_reserves: Vec<(AccountId, u64)> = Vec::new();
+    for asset in pool_vault.assets() {
+        if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+            let faucet_id = fungible_asset.faucet_id();
+            let amount = reserve_amount(&fungible_asset)?;
+            println!("      Reserve: {} = {}", faucet_id.to_hex(), amount);
+            token_reserves.push((faucet_id, amount));
+        }
+    }
+
+    if token_reserves.len() < 2 {
+        return Err(anyhow::anyhow!("Pool must have at least 2 token reserves, found {}", token_reserves.len()));
+    }
+
+    let (token_a_faucet, reserve_a) = token_reserves[0];
+    let (token_b_faucet, reserve_b) = token_reserves[1];
+
+    let plan = plan_withdrawal(total_deposited, user_shares, outstanding, reserve_a, reserve_b, lp_amount)
+        .with_context(|| format!("Withdrawal from pool {}", pool_id.to_hex()))?;
+    let WithdrawPlan { shares_burned, deposit_deducted, token_a_out, token_b_out, .. } = plan;
+    println!("      User max withdrawal: {} ({} LP shares of {})", plan.max_withdrawal, user_shares, outstanding);
+    println!("      Actual LP amount: {} ({} shares burned)", plan.amount, shares_burned);
+    println!("      Token A out: {} (faucet: {})", token_a_out, token_a_faucet.to_hex());
+    println!("      Token B out: {} (faucet: {})", token_b_out, token_b_faucet.to_hex());
+
+    // Slippage floors: reserves may have moved since the user's estimate
+    let shortfalls: Vec<String> = [
+        ("token A", token_a_faucet, token_a_out, min_token_a_out),
+        ("token B", token_b_faucet, token_b_out, min_token_b_out),
+    ]
+    .iter()
+    .filter(|(_, _, out, min)| out < min)
+    .map(|(leg, faucet, out, min)| format!(
+        "{} ({}) out {} is below min {} by {}", leg, faucet.to_hex(), out, min, min - out
+    ))
+    .collect();
+    if !shortfalls.is_empty() {
+        println!("      ❌ Slippage: {}", shortfalls.join("; "));
+        return Ok(WithdrawResponse {
+            success: false,
+            tx_id: None,
+            token_a_out: token_a_out.to_string(),
+            token_b_out: token_b_out.to_string(),
+            error: Some(format!("Slippage exceeded: {}", shortfalls.join("; "))),
+        });
+    }
+
+    let mut last_tx_id = String::new();
+
+    // Create P2ID note from pool to user for token A
+    if token_a_out > 0 {
+        println!("      📤 Creating P2ID note for token A...");
+        let asset_a = FungibleAsset::new(token_a_faucet, token_a_out)?;
+        let note_a = create_p2id_note(
+            pool_id,
+            user_id,
+            vec![asset_a.into()],
+            NoteType::Public,
+            Felt::new(0),
+            client.rng(),
+        )?;
+
+        let tx_a = pool_tx_builder(client, pool_id, record_reserves_script).await?
+            .own_output_notes(vec![OutputNote::Full(note_a)])
+            .build()?;
+
+        let tx_id_a = submit_with_permit(client, submit_permits, pool_id, tx_a).await?;
+        last_tx_id = tx_id_a.to_hex();
+        println!("      📤 Token A tx submitted: {}", last_tx_id.chars().take(16).collect::<String>());
+        ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+            "liquidity", "withdraw", &user_id.to_hex(), &token_a_faucet.to_hex(), token_a_out, Direction::In, &last_tx_id,
+        ));
+
+        match tokio::time::timeout(Duration::from_secs(30), wait_for_transaction(client, tx_id_a)).await {
+            Ok(Ok(_)) => println!("      ✅ Token A sent to user!"),
+            Ok(Err(e)) => println!("      ⚠️  Token A wait failed: {:?}", e),
+            Err(_) => println!("      ⚠️  Token A wait timeout (tx may still succeed)"),
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    // Create P2ID note from pool to user for token B
+    if token_b_out > 0 {
+        println!("      📤 Creating P2ID note for token B...");
+
+        // Re-sync state after first tx
+        sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+
+        let asset_b = FungibleAsset::new(token_b_faucet, token_b_out)?;
+        let note_b = create_p2id_note(
+            pool_id,
+            user_id,
+            vec![asset_b.into()],
+            NoteType::Public,
+            Felt::new(0),
+            client.rng(),
+        )?;
+
+        let tx_b = pool_tx_builder(client, pool_id, record_reserves_script).await?
+            .own_output_notes(vec![OutputNote::Full(note_b)])
+            .build()?;
+
+        let tx_id_b = submit_with_permit(client, submit_permits, pool_id, tx_b).await?;
+        last_tx_id = tx_id_b.to_hex();
+        println!("      📤 Token B tx submitted: {}", last_tx_id.chars().take(16).collect::<String>());
+        ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+            "liquidity", "withdraw", &user_id.to_hex(), &token_b_faucet.to_hex(), token_b_out, Direction::In, &last_tx_id,
+        ));
+
+        match tokio::time::timeout(Duration::from_secs(30), wait_for_transaction(client, tx_id_b)).await {
+            Ok(Ok(_)) => println!("      ✅ Token B sent to user!"),
+            Ok(Err(e)) => println!("      ⚠️  Token B wait failed: {:?}", e),
+            Err(_) => println!("      ⚠️  Token B wait timeout (tx may still succeed)"),
+        }
+    }
+
+    // Deduct withdrawn amount from user's tracked deposits
+    {
+        let mut deps = user_deposits.lock().unwrap();
+        if let Some(entry) = deps.get_mut(&deposit_key) {
+            deduct_deposit(entry, deposit_deducted);
+            entry.lp_shares = entry.lp_shares.saturating_sub(shares_burned);
+            println!("      💾 User deposit updated: {} remaining, {} LP shares", entry.total_deposited, entry.lp_shares);
+            save_user_deposit(entry);
+        }
+    }
+
+    println!("   ✅ Withdrawal complete!");
+
+    Ok(WithdrawResponse {
+        success: true,
+        tx_id: Some(last_tx_id),
+        token_a_out: token_a_out.to_string(),
+        token_b_out: token_b_out.to_string(),
+        error: None,
+    })
+}
+
+/// Execute a withdrawal from a pool with an LP faucet: the user has sent
+/// `lp_amount` LP tokens to the pool in note `lp_note_id`. They redeem
+/// `lp_amount / supply` of each reserve. One pool transaction consumes the
+/// note, pays out both tokens and sends the LP tokens to their faucet in a
+/// burn note; the faucet then consumes it. Below either minimum nothing is
+/// sent and the LP note stays with the pool for a retry.
+async fn execute_lp_withdraw(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    lp_faucet_id: AccountId,
+    user_id: AccountId,
+    lp_note_id: Option<String>,
+    lp_amount: u64,
+    min_token_a_out: u64,
+    min_token_b_out: u64,
+    submit_permits: &Semaphore,
+    lp_queues: &mut LpQueues,
+) -> Result<WithdrawResponse> {
+    println!("   🔄 Executing LP token withdrawal...");
+    println!("      Pool: {}", pool_id.to_hex());
+    println!("      User: {}", user_id.to_hex());
+    println!("      LP Amount: {}", lp_amount);
+
+    let lp_note_id = lp_note_id.ok_or_else(|| anyhow::anyhow!(
+        "Pool {} issues LP tokens: send them to the pool and pass the note as lp_note_id", pool_id.to_hex()
+    ))?;
+
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+
+    // The LP note must be the user's, waiting at the pool, and hold exactly lp_amount LP tokens
+    let lp_note = client.get_consumable_notes(Some(pool_id)).await?
+        .into_iter()
+        .map(|(note, _)| note)
+        .find(|note| note.id().to_hex() == lp_note_id)
+        .ok_or_else(|| anyhow::anyhow!("LP note {} is not consumable by pool {} (yet)", lp_note_id, pool_id.to_hex()))?;
+    if lp_note.metadata().map(|m| m.sender()) != Some(user_id) {
+        anyhow::bail!("LP note {} was not sent by {}", lp_note_id, user_id.to_hex());
+    }
+    let mut note_lp = 0u64;
+    for asset in lp_note.assets().iter() {
+        match asset {
+            miden_client::asset::Asset::Fungible(fungible) if fungible.faucet_id() == lp_faucet_id => {
+                note_lp = note_lp.saturating_add(reserve_amount(fungible)?);
+            }
+            _ => anyhow::bail!("LP note {} holds assets other than the pool's LP token", lp_note_id),
+        }
+    }
+    if note_lp != lp_amount {
+        anyhow::bail!("LP note {} holds {} LP tokens, not the {} requested", lp_note_id, note_lp, lp_amount);
+    }
+
+    let token_reserves: Vec<(AccountId, u64)> = {
+        let pool_account = client.get_account(pool_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+        let mut reserves = Vec::new();
+        for asset in pool_account.account().vault().assets() {
+            if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+                reserves.push((fungible_asset.faucet_id(), reserve_amount(&fungible_asset)?));
+            }
+        }
+        reserves
+    };
+    if token_reserves.len() < 2 {
+        return Err(anyhow::anyhow!("Pool must have at least 2 token reserves, found {}", token_reserves.len()));
+    }
+    let (token_a_faucet, reserve_a) = token_reserves[0];
+    let (token_b_faucet, reserve_b) = token_reserves[1];
+
+    let supply = circulating_lp_supply(client, lp_faucet_id, lp_queues).await?;
+    if lp_amount > supply {
+        anyhow::bail!("{} LP tokens exceed the {} in circulation", lp_amount, supply);
+    }
+    let token_a_out = lp_redemption(lp_amount, supply, reserve_a);
+    let token_b_out = lp_redemption(lp_amount, supply, reserve_b);
+    println!("      LP supply: {}", supply);
+    println!("      Token A out: {} (faucet: {})", token_a_out, token_a_faucet.to_hex());
+    println!("      Token B out: {} (faucet: {})", token_b_out, token_b_faucet.to_hex());
+
+    if token_a_out == 0 && token_b_out == 0 {
+        return Err(anyhow::anyhow!("Calculated output amounts are both 0"));
+    }
+
+    let shortfalls: Vec<String> = [
+        ("token A", token_a_faucet, token_a_out, min_token_a_out),
+        ("token B", token_b_faucet, token_b_out, min_token_b_out),
+    ]
+    .iter()
+    .filter(|(_, _, out, min)| out < min)
+    .map(|(leg, faucet, out, min)| format!(
+        "{} ({}) out {} is below min {} by {}", leg, faucet.to_hex(), out, min, min - out
+    ))
+    .collect();
+    if !shortfalls.is_empty() {
+        println!("      ❌ Slippage: {}", shortfalls.join("; "));
+        return Ok(WithdrawResponse {
+            success: false,
+            tx_id: None,
+            token_a_out: token_a_out.to_string(),
+            token_b_out: token_b_out.to_string(),
+            error: Some(format!("Slippage exceeded: {}", shortfalls.join("; "))),
+        });
+    }
+
+    let mut output_notes = Vec::new();
+    for (faucet_id, amount) in [(token_a_faucet, token_a_out), (token_b_faucet, token_b_out)] {
+        if amount > 0 {
+            let asset = FungibleAsset::new(faucet_id, amount)?;
+            let note = create_p2id_note(pool_id, user_id, vec![asset.into()], NoteType::Public, Felt::new(0), client.rng())?;
+            output_notes.push(OutputNote::Full(note));
+        }
+    }
+    let burn_note = lp_burn_note(pool_id, lp_faucet_id, lp_amount, client.rng())?;
+    output_notes.push(OutputNote::Full(burn_note.clone()));
+
+    let tx_request = pool_tx_builder(client, pool_id, record_reserves_script).await?
+        .authenticated_input_notes([(lp_note.id(), None)])
+        .own_output_notes(output_notes)
+        .build()?;
+    let tx_id = submit_with_permit(client, submit_permits, pool_id, tx_request).await?;
+    let tx_id_hex = tx_id.to_hex();
+    println!("      📤 Withdraw tx submitted: {}", tx_id_hex.chars().take(16).collect::<String>());
+    for (faucet_id, amount) in [(token_a_faucet, token_a_out), (token_b_faucet, token_b_out)] {
+        if amount > 0 {
+            ledger::append_entry(ledger::LIQUIDITY_LEDGER_FILE, &LedgerEntry::new(
+                "liquidity", "withdraw", &user_id.to_hex(), &faucet_id.to_hex(), amount, Direction::In, &tx_id_hex,
+            ));
+        }
+    }
+
+    match tokio::time::timeout(Duration::from_secs(30), wait_for_transaction(client, tx_id)).await {
+        Ok(Ok(_)) => println!("      ✅ Tokens sent to user!"),
+        Ok(Err(e)) => println!("      ⚠️  Withdraw wait failed: {:?}", e),
+        Err(_) => println!("      ⚠️  Withdraw wait timeout (tx may still succeed)"),
+    }
+
+    // Retried from the poll loop if the faucet can't take the note yet
+    lp_queues.queue_burn(PendingBurn { lp_faucet_id, note: burn_note, lp_amount });
+    retry_lp_burns(client, submit_permits, lp_queues).await;
+
+    println!("   ✅ LP withdrawal complete!");
+
+    Ok(WithdrawResponse {
+        success: true,
+        tx_id: Some(tx_id_hex),
+        token_a_out: token_a_out.to_string(),
+        token_b_out: token_b_out.to_string(),
+        error: None,
+    })
+}
+
+/// Have each LP faucet consume the burn notes withdrawals sent it. Notes
+/// whose burn could not be submitted stay queued for the next call.
+async fn retry_lp_burns(client: &mut MidenClient, submit_permits: &Semaphore, lp_queues: &mut LpQueues) {
+    let mut still_pending = Vec::new();
+    for burn in std::mem::take(&mut lp_queues.burns) {
+        let note_id_short = burn.note.id().to_hex().chars().take(16).collect::<String>();
+        let submitted = match TransactionRequestBuilder::new()
+            .unauthenticated_input_notes([(burn.note.clone(), None)])
+            .build()
+        {
+            Ok(tx_request) => submit_with_permit(client, submit_permits, burn.lp_faucet_id, tx_request).await,
+            Err(e) => Err(e.into()),
+        };
+        match submitted {
+            Ok(tx_id) => {
+                println!("   🔥 Burned {} LP (note {}, tx {})",
+                    burn.lp_amount, note_id_short, tx_id.to_hex().chars().take(16).collect::<String>());
+                delete_pending_burn(&burn.note.id().to_hex());
+            }
+            Err(e) => {
+                println!("   ⚠️  LP burn note {} not consumed yet: {:?}", note_id_short, e);
+                still_pending.push(burn);
+            }
+        }
+    }
+    lp_queues.burns = still_pending;
+}
+
+/// Store of the pool creation thread: `store.sqlite3` → `store.pool_create.sqlite3`
+fn pool_create_store_path(base: &str) -> PathBuf {
+    let base = Path::new(base);
+    let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("store");
+    let ext = base.extension().and_then(|s| s.to_str()).unwrap_or("sqlite3");
+    base.with_file_name(format!("{}.pool_create.{}", stem, ext))
+}
+
+/// Create a pool for `req.pair` on the pool creation thread's client: the
+/// pool account, its LP faucet and the entries in pools.json and
+/// pool_config.json, then seed it from the accounts.json user wallet if
+/// asked. pools.json is written before seeding, so a pool whose seed fails
+/// is still listed. Either way both accounts are then handed to the worker,
+/// which consumes the pool's notes and mints its LP tokens.
+async fn create_pool(
+    client: &mut MidenClient,
+    req: &CreatePoolWorkerRequest,
+    worker_tx: &std::sync::mpsc::Sender<WorkerRequest>,
+    metrics: &Mutex<MetricsState>,
+) -> Result<PoolCreateResponse> {
+    println!("🏗️  Creating pool {}...", req.pair);
+    let keystore = FilesystemKeyStore::new(PathBuf::from(&milo_config().keystore_path))
+        .context("Failed to open keystore")?;
+
+    // Checked again here: two requests for one pair may have queued
+    let mut pools = load_pools_config("pools.json")?;
+    if pools.pool_id(&req.pair).is_some() {
+        anyhow::bail!("Pool {} already exists", req.pair);
+    }
+    if let Some(owner) = lp_symbol_owner(&pools, &req.pair) {
+        anyhow::bail!("LP token symbol {} is already used by pool {}", lp_token_symbol(&req.pair), owner);
+    }
+
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+    let pool_id = create_pool_account(client, &keystore, (req.token_a, req.token_b)).await?.id();
+    println!("   ✅ Pool account: {}", pool_id.to_hex());
+    let lp_faucet_id = create_lp_faucet(client, &keystore, &lp_token_symbol(&req.pair)).await?;
+
+    pools.insert(&req.pair, pool_id, Some(lp_faucet_id));
+    let address = pools.pools.iter().find(|p| p.pair == req.pair).map(|p| p.address.clone()).unwrap_or_default();
+    save_pools_config("pools.json", &pools).context("pools.json could not be saved")?;
+    let mut curves = load_pool_curves_config(POOL_CURVES_CONFIG_PATH, &pools)?;
+    curves.set_curve(&req.pair, req.curve);
+    save_pool_curves_config(POOL_CURVES_CONFIG_PATH, &curves, &pools)
+        .with_context(|| format!("{} could not be saved", POOL_CURVES_CONFIG_PATH))?;
+    println!("   💾 {} added to pools.json ({})", req.pair, req.curve);
+
+    let seeded = req.initial_a > 0 && req.initial_b > 0;
+    // Seeding mints from the LP faucet, so the worker gets the accounts after it
+    let seed_result = if seeded {
+        seed_pool(client, req, pool_id, lp_faucet_id).await
+    } else {
+        Ok(())
+    };
+    hand_over_pool(client, worker_tx, metrics, pool_id, lp_faucet_id).await?;
+    seed_result.with_context(|| format!("Pool {} was created but seeding it failed", pool_id.to_hex()))?;
+    if seeded {
+        println!("   💧 Seeded with {} / {}", req.initial_a, req.initial_b);
+    }
+
+    Ok(PoolCreateResponse {
+        pair: req.pair.clone(),
+        account_id: pool_id.to_hex(),
+        address,
+        lp_faucet_id: lp_faucet_id.to_hex(),
+        curve: req.curve.to_string(),
+        seeded,
+    })
+}
+
+/// Seed a new pool from the accounts.json user wallet
+async fn seed_pool(
+    client: &mut MidenClient,
+    req: &CreatePoolWorkerRequest,
+    pool_id: AccountId,
+    lp_faucet_id: AccountId,
+) -> Result<()> {
+    let accounts: AccountsConfig = serde_json::from_str(
+        &fs::read_to_string(ACCOUNTS_PATH).with_context(|| format!("{} not found", ACCOUNTS_PATH))?,
+    )
+    .with_context(|| format!("{} is invalid", ACCOUNTS_PATH))?;
+    let user_wallet_id = AccountId::from_hex(&accounts.user_wallet_id)?;
+    if client.get_account(user_wallet_id).await?.is_none() {
+        client.import_account_by_id(user_wallet_id).await
+            .context("User wallet could not be imported for seeding")?;
+    }
+    add_liquidity_to_pool(
+        client, user_wallet_id, req.token_a, req.token_b, pool_id, Some(lp_faucet_id), req.initial_a, req.initial_b,
+    )
+    .await
+}
+
+/// Send a new pool and its LP faucet, as this client last saw them, to the
+/// worker and wait until its store has them. A new LP faucet isn't on-chain
+/// until its first mint, so the worker can't import it by id.
+async fn hand_over_pool(
+    client: &mut MidenClient,
+    worker_tx: &std::sync::mpsc::Sender<WorkerRequest>,
+    metrics: &Mutex<MetricsState>,
+    pool_id: AccountId,
+    lp_faucet_id: AccountId,
+) -> Result<()> {
+    let mut accounts = Vec::with_capacity(2);
+    for account_id in [pool_id, lp_faucet_id] {
+        let record = client.get_account(account_id).await?
+            .ok_or_else(|| anyhow::anyhow!("Account {} not in the pool creation store", account_id.to_hex()))?;
+        accounts.push(record.account().clone());
+    }
+    let (lp_faucet, pool) = (accounts.pop().unwrap(), accounts.pop().unwrap());
+
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    metrics.lock().unwrap().enqueued();
+    if worker_tx.send(WorkerRequest::AddPool(AddPoolRequest { pool, lp_faucet, reply: reply_tx })).is_err() {
+        metrics.lock().unwrap().dequeued();
+        anyhow::bail!("Worker thread not available");
+    }
+    reply_rx.await
+        .context("Worker dropped the pool handover")?
+        .map_err(|e| anyhow::anyhow!("Worker could not add pool {}: {}", pool_id.to_hex(), e))
+}
+
+/// Track a pool created on the pool creation thread in the worker's store;
+/// deposits and withdrawals use it from the next request on
+async fn add_created_pool(
+    client: &mut MidenClient,
+    pool: &Account,
+    lp_faucet: &Account,
+    lp_faucets: &mut HashMap<AccountId, AccountId>,
+) -> Result<()> {
+    for account in [pool, lp_faucet] {
+        client.add_account(account, true).await
+            .with_context(|| format!("Account {} could not be added", account.id().to_hex()))?;
+    }
+    lp_faucets.insert(pool.id(), lp_faucet.id());
+    println!("🏊 Pool {} and LP faucet {} added to the worker", pool.id().to_hex(), lp_faucet.id().to_hex());
+    Ok(())
+}
+
+// Admin: create a pool on-chain and add it to pools.json
+async fn pool_create_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<PoolCreateRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let bad_request = |error: String| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": error })));
+
+    // Both tokens must be registry faucets, so the pair has a pools.json name
+    let registry = TokenRegistry::global();
+    let mut tokens = Vec::with_capacity(2);
+    for hex in [&payload.token_a, &payload.token_b] {
+        let faucet_id = match AccountId::from_hex(hex) {
+            Ok(id) => id,
+            Err(e) => return bad_request(format!("Invalid faucet ID {}: {:?}", hex, e)),
+        };
+        let Some(faucet) = registry.faucets().iter().find(|f| f.faucet_id == faucet_id) else {
+            return bad_request(format!("Faucet {} is not a known token", hex));
+        };
+        tokens.push((faucet_id, faucet.symbol.to_lowercase()));
+    }
+    let ((token_a, symbol_a), (token_b, symbol_b)) = (tokens[0].clone(), tokens[1].clone());
+    if token_a == token_b {
+        return bad_request("token_a and token_b are the same".to_string());
+    }
+    if (payload.initial_a == 0) != (payload.initial_b == 0) {
+        return bad_request("Seed both initial_a and initial_b, or neither".to_string());
+    }
+    let curve = match (payload.curve.as_deref().unwrap_or("constant_product"), payload.amplification) {
+        ("constant_product", _) => AmmCurve::ConstantProduct,
+        ("stable_swap", Some(amplification)) if amplification > 0 && amplification <= MAX_AMPLIFICATION => {
+            AmmCurve::StableSwap { amplification }
+        }
+        ("stable_swap", _) => {
+            return bad_request(format!("stable_swap needs an amplification between 1 and {}", MAX_AMPLIFICATION));
+        }
+        (other, _) => return bad_request(format!("Unknown curve `{}`", other)),
+    };
+
+    let pair = format!("{}_{}", symbol_a, symbol_b);
+    match load_pools_config("pools.json") {
+        Ok(pools) => {
+            let reversed = format!("{}_{}", symbol_b, symbol_a);
+            if let Some(existing) = [&pair, &reversed].into_iter().find_map(|p| pools.pool_id(p)) {
+                return (StatusCode::CONFLICT, Json(serde_json::json!({
+                    "error": format!("A {}/{} pool already exists", symbol_a, symbol_b),
+                    "account_id": existing.to_hex(),
+                })));
+            }
+            if let Some(owner) = lp_symbol_owner(&pools, &pair) {
+                return (StatusCode::CONFLICT, Json(serde_json::json!({
+                    "error": format!("LP token symbol {} is already used by pool {}", lp_token_symbol(&pair), owner),
+                })));
+            }
+        }
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": format!("{:#}", e) })));
+        }
+    }
+
+    println!("🏗️  Pool create request: {} (curve {})", pair, curve);
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    let req = CreatePoolWorkerRequest {
+        pair,
+        token_a,
+        token_b,
+        initial_a: payload.initial_a,
+        initial_b: payload.initial_b,
+        curve,
+        reply: reply_tx,
+    };
+    if state.pool_create_tx.send(req).is_err() {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": "Pool creation thread not available" })));
+    }
+
+    // Account creation, the LP faucet and seeding each wait for their transactions
+    match tokio::time::timeout(Duration::from_secs(POOL_CREATE_TIMEOUT_SECS), reply_rx).await {
+        Ok(Ok(Ok(response))) => {
+            println!("✅ Pool {} created: {}", response.pair, response.account_id);
+            (StatusCode::OK, Json(serde_json::json!(response)))
+        }
+        Ok(Ok(Err(e))) => {
+            eprintln!("❌ Pool create error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e })))
+        }
+        _ => (StatusCode::REQUEST_TIMEOUT, Json(serde_json::json!({
+            "error": "Timeout - the pool may still be created; check pools.json"
+        }))),
+    }
+}
+
+// Withdraw handler - processes LP token withdrawal
+async fn withdraw_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<WithdrawRequest>,
+) -> impl IntoResponse {
+    // API-key callers can only withdraw their bound account's liquidity
+    match state.api_keys.authorize(&headers, Permission::Withdraw) {
+        Ok(Some(identity)) => {
+            payload.user_account_id = identity.account_id.clone();
+            state.api_keys.audit(&identity, "withdraw", serde_json::json!({
+                "pool_account_id": payload.pool_account_id,
+                "lp_amount": payload.lp_amount,
+            }));
+        }
+        Ok(None) => {}
+        Err(resp) => return resp,
+    }
+
+    println!("🔄 Withdraw request: {} LP from pool {}", payload.lp_amount, payload.pool_account_id);
+    println!("   User: {}", payload.user_account_id);
+
+    // Parse IDs and amounts
+    let pool_id = match AccountId::from_hex(&payload.pool_account_id) {
+        Ok(id) => id,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
+                success: false,
+                tx_id: None,
+                token_a_out: "0".to_string(),
+                token_b_out: "0".to_string(),
+                error: Some(format!("Invalid pool account ID: {:?}", e)),
+            })));
+        }
+    };
+
+    let user_id = match AccountId::from_hex(&payload.user_account_id) {
+        Ok(id) => id,
+        Err(e) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
+                success: false,
+                tx_id: None,
+                token_a_out: "0".to_string(),
+                token_b_out: "0".to_string(),
+                error: Some(format!("Invalid user account ID: {:?}", e)),
+            })));
+        }
+    };
+
+    let lp_amount: u64 = payload.lp_amount.parse().unwrap_or(0);
+    let min_token_a_out: u64 = payload.min_token_a_out.parse().unwrap_or(0);
+    let min_token_b_out: u64 = payload.min_token_b_out.parse().unwrap_or(0);
+
+    if lp_amount == 0 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
+            success: false,
+            tx_id: None,
+            token_a_out: "0".to_string(),
+            token_b_out: "0".to_string(),
+            error: Some("LP amount must be greater than 0".to_string()),
+        })));
+    }
+
+    // Send to worker thread
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    let req = WithdrawWorkerRequest {
+        pool_id,
+        user_id,
+        lp_amount,
+        min_token_a_out,
+        min_token_b_out,
+        lp_note_id: payload.lp_note_id.clone(),
+        reply: reply_tx,
+    };
+
+    state.metrics.lock().unwrap().enqueued();
+    if state.worker_tx.send(WorkerRequest::Withdraw(req)).is_err() {
+        state.metrics.lock().unwrap().dequeued();
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!(WithdrawResponse {
+            success: false,
+            tx_id: None,
+            token_a_out: "0".to_string(),
+            token_b_out: "0".to_string(),
+            error: Some("Worker thread not available".to_string()),
+        })));
+    }
+
+    // Wait for response
+    match tokio::time::timeout(Duration::from_secs(120), reply_rx).await {
+        // Below min_token_a_out / min_token_b_out: nothing was sent
+        Ok(Ok(Ok(response))) if !response.success => {
+            eprintln!("❌ Withdraw rejected: {}", response.error.as_deref().unwrap_or(""));
+            (StatusCode::CONFLICT, Json(serde_json::json!(response)))
+        }
+        Ok(Ok(Ok(response))) => {
+            println!("✅ Withdraw processed: {} tokenA, {} tokenB", response.token_a_out, response.token_b_out);
+            (StatusCode::OK, Json(serde_json::json!(response)))
+        }
+        Ok(Ok(Err(e))) => {
+            eprintln!("❌ Withdraw error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!(WithdrawResponse {
+                success: false,
+                tx_id: None,
+                token_a_out: "0".to_string(),
+                token_b_out: "0".to_string(),
+                error: Some(e),
+            })))
+        }
+        _ => {
+            (StatusCode::REQUEST_TIMEOUT, Json(serde_json::json!(WithdrawResponse {
+                success: false,
+                tx_id: None,
+                token_a_out: "0".to_string(),
+                token_b_out: "0".to_string(),
+                error: Some("Timeout".to_string()),
+            })))
+        }
+    }
+}
+
+// Get user deposits for a specific user
+async fn user_deposits_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UserDepositsQuery>,
+) -> impl IntoResponse {
+    // API-key callers only see their own account
+    let user_id = match state.api_keys.authorize(&headers, Permission::Read) {
+        Ok(Some(identity)) => identity.account_id,
+        Ok(None) => match query.user_id {
+            Some(user_id) => user_id,
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "user_id is required"
+            }))),
+        },
+        Err(resp) => return resp,
+    };
+
+    // Current reserves for estimated_value; deposits are still listed without them
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    state.metrics.lock().unwrap().enqueued();
+    let reserves: HashMap<String, Vec<ReserveAsset>> =
+        if state.worker_tx.send(WorkerRequest::PoolReserves(PoolReservesRequest { force_sync: false, reply: reply_tx })).is_ok() {
+            match tokio::time::timeout(Duration::from_secs(60), reply_rx).await {
+                Ok(Ok(Ok(response))) => response.pools.into_iter().map(|p| (p.pool_id, p.reserves)).collect(),
+                _ => HashMap::new(),
+            }
+        } else {
+            state.metrics.lock().unwrap().dequeued();
+            HashMap::new()
+        };
+
+    let deposits = state.user_deposits.lock().unwrap();
+    let user_deps: Vec<serde_json::Value> = deposits
+        .values()
+        .filter(|d| d.user_account_id.eq_ignore_ascii_case(&user_id))
+        .map(|d| {
+            let outstanding = outstanding_shares(&deposits, &d.pool_account_id);
+            let share = if outstanding > 0 { d.lp_shares as f64 / outstanding as f64 } else { 0.0 };
+            let estimated_value = reserves.get(&d.pool_account_id).map(|pool_reserves| {
+                pool_reserves.iter()
+                    .map(|r| {
+                        let amount: u64 = r.amount.parse().unwrap_or(0);
+                        (r.faucet_id.clone(), serde_json::json!(share_value(d.lp_shares, outstanding, amount).to_string()))
+                    })
+                    .collect::<serde_json::Map<_, _>>()
+            });
+            let mut value = serde_json::json!(d);
+            value["share_percent"] = serde_json::json!(share * 100.0);
+            value["estimated_value"] = serde_json::json!(estimated_value);
+            value
+        })
+        .collect();
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "user_id": user_id,
+        "deposits": user_deps
+    })))
+}
+
+// What a withdrawal would pay out at the last reserves the worker read.
+// Answers from the reserve cache, never from the worker
+async fn withdraw_preview_handler(
+    State(state): State<AppState>,
+    Query(query): Query<WithdrawPreviewQuery>,
+) -> impl IntoResponse {
+    let (pool_id, user_id) = match (AccountId::from_hex(&query.pool_id), AccountId::from_hex(&query.user_id)) {
+        (Ok(pool_id), Ok(user_id)) => (pool_id.to_hex(), user_id.to_hex()),
+        _ => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "pool_id and user_id must be hex account IDs"
+        }))),
+    };
+
+    let Some(pool) = state.reserve_cache.lock().unwrap().get(&pool_id).cloned() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({
+            "error": format!("Reserves of pool {} have not been read yet", pool_id)
+        })));
+    };
+    if pool.reserves.len() < 2 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Pool must have at least 2 token reserves, found {}", pool.reserves.len())
+        })));
+    }
+    let reserve_a: u64 = pool.reserves[0].amount.parse().unwrap_or(0);
+    let reserve_b: u64 = pool.reserves[1].amount.parse().unwrap_or(0);
+    let total_liquidity = reserve_a + reserve_b;
+    if total_liquidity == 0 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": "Pool has no liquidity" })));
+    }
+
+    // Same limit as execute_withdraw
+    let (max_withdrawal, share) = {
+        let deposits = state.user_deposits.lock().unwrap();
+        let Some(entry) = deposits.get(&format!("{}:{}", user_id, pool_id)) else {
+            return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+                "error": format!("No tracked deposits found for user {} in pool {}", user_id, pool_id)
+            })));
+        };
+        let outstanding = outstanding_shares(&deposits, &pool_id);
+        let max_withdrawal = if entry.lp_shares > 0 {
+            share_value(entry.lp_shares, outstanding, total_liquidity)
+        } else {
+            entry.total_deposited
+        };
+        let share = if outstanding > 0 { entry.lp_shares as f64 / outstanding as f64 } else { 0.0 };
+        (max_withdrawal, share)
+    };
+    let Some(actual_lp_amount) = clamp_withdrawal(query.lp_amount, max_withdrawal) else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Tracked share of pool {} is worth nothing at current reserves", pool_id)
+        })));
+    };
+
+    let (token_a_out, token_b_out) = estimate_withdraw(reserve_a, reserve_b, total_liquidity, actual_lp_amount);
+
+    // Proportional withdrawals leave the price alone up to rounding; this is that rounding
+    let price_before = reserve_b as f64 / reserve_a as f64;
+    let (after_a, after_b) = (reserve_a - token_a_out, reserve_b - token_b_out);
+    let price_impact_bps = if reserve_a > 0 && after_a > 0 {
+        let price_after = after_b as f64 / after_a as f64;
+        ((price_after - price_before) / price_before).abs() * 10_000.0
+    } else {
+        0.0
+    };
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "pool_id": pool_id,
+        "user_id": user_id,
+        "lp_amount": query.lp_amount,
+        "actual_lp_amount": actual_lp_amount,
+        "max_withdrawal": max_withdrawal,
+        "token_a_faucet_id": pool.reserves[0].faucet_id,
+        "token_a_out": token_a_out.to_string(),
+        "token_b_faucet_id": pool.reserves[1].faucet_id,
+        "token_b_out": token_b_out.to_string(),
+        "share_percent": share * 100.0,
+        "price_impact_bps": price_impact_bps.round() as u64,
+    })))
+}
+
+// How much of a prospective deposit would be credited under the share cap
+async fn deposit_quote_handler(
+    State(state): State<AppState>,
+    Query(query): Query<DepositQuoteQuery>,
+) -> impl IntoResponse {
+    let deposits = state.user_deposits.lock().unwrap();
+    let headroom = deposit_headroom(&deposits, &query.pool_id, &query.user_id, state.max_user_share_bps);
+    let creditable = headroom.map_or(query.amount, |h| query.amount.min(h));
+
+    let (user_total, pool_total) = deposits.values()
+        .filter(|d| d.pool_account_id == query.pool_id)
+        .fold((0u64, 0u64), |(u, p), d| {
+            let u = if d.user_account_id == query.user_id { u + d.total_deposited } else { u };
+            (u, p + d.total_deposited)
+        });
+    let current_share = if pool_total > 0 { user_total as f64 / pool_total as f64 } else { 0.0 };
+
+    Json(serde_json::json!({
+        "pool_id": query.pool_id,
+        "user_id": query.user_id,
+        "amount": query.amount,
+        "creditable": creditable,
+        "refunded_excess": query.amount - creditable,
+        "headroom": headroom,
+        "max_share_bps": state.max_user_share_bps,
+        "current_share": current_share,
+    }))
+}
+
+// Credited liquidity of one pool by holder, largest first
+struct PoolExposure {
+    holders: Vec<(String, u64)>,
+    total: u64,
+}
+
+impl PoolExposure {
+    fn new(deposits: &HashMap<String, UserPoolDeposit>, pool_id_hex: &str) -> Self {
+        let mut holders: Vec<(String, u64)> = deposits.values()
+            .filter(|d| d.pool_account_id == pool_id_hex && d.total_deposited > 0)
+            .map(|d| (d.user_account_id.clone(), d.total_deposited))
+            .collect();
+        holders.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let total = holders.iter().map(|(_, amount)| amount).sum();
+        Self { holders, total }
+    }
+
+    fn share(&self, amount: u64) -> f64 {
+        if self.total > 0 { amount as f64 / self.total as f64 } else { 0.0 }
+    }
+
+    // Herfindahl-Hirschman index: sum of squared shares (1.0 = single holder)
+    fn hhi(&self) -> f64 {
+        self.holders.iter().map(|(_, amount)| self.share(*amount).powi(2)).sum()
+    }
+
+    fn largest_share(&self) -> f64 {
+        self.holders.first().map(|(_, amount)| self.share(*amount)).unwrap_or(0.0)
+    }
+}
+
+// Top holders and concentration metrics for a pool's credited liquidity
+async fn exposure_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ExposureQuery>,
+) -> impl IntoResponse {
+    let exposure = PoolExposure::new(&state.user_deposits.lock().unwrap(), &query.pool_id);
+
+    let top: Vec<serde_json::Value> = exposure.holders.iter()
+        .take(query.limit.unwrap_or(10))
+        .map(|(user, amount)| serde_json::json!({
+            "user_id": user,
+            "credited": amount,
+            "share": exposure.share(*amount),
+        }))
+        .collect();
+
+    Json(serde_json::json!({
+        "pool_id": query.pool_id,
+        "total_credited": exposure.total,
+        "holder_count": exposure.holders.len(),
+        "largest_share": exposure.largest_share(),
+        "hhi": exposure.hhi(),
+        "max_share_bps": state.max_user_share_bps,
+        "top_holders": top,
+    }))
+}
+
+/// Canonical hex of `pool_id` if pools.json lists it. /record_trade is
+/// unauthenticated, so trades on unknown pools are refused before they reach
+/// the stats.
+fn known_pool_id(pools: &PoolsConfig, pool_id: &str) -> Option<String> {
+    let pool_id = AccountId::from_hex(pool_id.trim()).ok()?;
+    pools.pool_ids().contains(&pool_id).then(|| pool_id.to_hex())
+}
+
+// Record a trade for volume tracking
+async fn record_trade_handler(
+    State(state): State<AppState>,
+    Json(mut payload): Json<RecordTradeRequest>,
+) -> impl IntoResponse {
+    let pools = match load_pools_config("pools.json") {
+        Ok(pools) => pools,
+        Err(e) => {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "success": false,
+                "error": format!("{:#}", e),
+            })));
+        }
+    };
+    payload.pool_id = match known_pool_id(&pools, &payload.pool_id) {
+        Some(pool_id) => pool_id,
+        None => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unknown pool {}", payload.pool_id),
+            })));
+        }
+    };
+    println!("📊 Recording trade: {} volume, {} fee for pool {}",
+        payload.amount_in, payload.fee_amount, payload.pool_id);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut volumes = state.trade_volumes.lock().unwrap();
+
+    if let Some(volume) = volumes.get_mut(&payload.pool_id) {
+        if now.saturating_sub(volume.last_updated) > 86400 {
+            volume.volume_24h = 0;
+            volume.fees_24h = 0;
+            volume.trades_24h = 0;
+        }
+
+        volume.volume_24h = volume.volume_24h.saturating_add(payload.amount_in);
+        volume.fees_24h = volume.fees_24h.saturating_add(payload.fee_amount);
+        volume.trades_24h = volume.trades_24h.saturating_add(1);
+        volume.last_updated = now;
+        volume.total_volume_lifetime = volume.total_volume_lifetime.saturating_add(payload.amount_in);
+        volume.total_fees_lifetime = volume.total_fees_lifetime.saturating_add(payload.fee_amount);
+        save_trade_volume(volume);
+
+        println!("   Updated: volume_24h={}, fees_24h={}, trades_24h={}, fees_lifetime={}",
+            volume.volume_24h, volume.fees_24h, volume.trades_24h, volume.total_fees_lifetime);
+    } else {
+        let volume = TradeVolume {
+            pool_id: payload.pool_id.clone(),
+            volume_24h: payload.amount_in,
+            fees_24h: payload.fee_amount,
+            trades_24h: 1,
+            last_updated: now,
+            total_fees_lifetime: payload.fee_amount,
+            total_volume_lifetime: payload.amount_in,
+        };
+        save_trade_volume(&volume);
+        volumes.insert(payload.pool_id.clone(), volume);
+    }
+    drop(volumes);
+
+    let today = now / 86400;
+    let mut stats = state.daily_stats.lock().unwrap();
+    stats.entry(today).or_default().entry(payload.pool_id.clone()).or_default()
+        .record(payload.amount_in, payload.fee_amount, payload.user_id.as_deref());
+    stats.retain(|d, _| today.saturating_sub(*d) <= DAILY_STATS_RETENTION_DAYS);
+    save_daily_stats(&stats);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "pool_id": payload.pool_id
+    })))
+}
+
+/// Twice the quote-token reserve of a pair (both sides valued at the pool
+/// price); 0 when the quote reserve isn't known
+fn tvl_quote(pair: &str, reserves: &[ReserveAsset]) -> u64 {
+    let quote_faucet = pair.split('_').nth(1).and_then(get_faucet_id_by_symbol);
+    reserves
+        .iter()
+        .find(|r| quote_faucet.is_some_and(|q| q.eq_ignore_ascii_case(&r.faucet_id)))
+        .and_then(|r| r.amount.parse::<u64>().ok())
+        .map(|quote| quote.saturating_mul(2))
+        .unwrap_or(0)
+}
+
+/// Build the snapshot for `date` from that day's trade stats, current LP
+/// positions and closing reserves. Pure, so output is fully determined by inputs.
+fn build_daily_snapshot(
+    date: &str,
+    day_stats: &BTreeMap<String, DailyPoolStats>,
+    deposits: &HashMap<String, UserPoolDeposit>,
+    reserves: &PoolReservesResponse,
+    pools: &PoolsConfig,
+) -> DailySnapshot {
+    let mut rows = Vec::new();
+    for entry in &pools.pools {
+        let pool_id = entry.pool_id.to_hex();
+        let stats = day_stats.get(&pool_id).cloned().unwrap_or_default();
+        let lp_count = deposits
+            .values()
+            .filter(|d| d.pool_account_id == pool_id && d.total_deposited > 0)
+            .count();
+
+        let pool_reserves = reserves
+            .pools
+            .iter()
+            .find(|p| p.pool_id == pool_id)
+            .map(|p| p.reserves.clone())
+            .unwrap_or_default();
+        let tvl_quote = tvl_quote(&entry.pair, &pool_reserves);
+
+        rows.push(PoolDaySnapshot {
+            pool_id,
+            pair: entry.pair.to_uppercase().replace('_', "/"),
+            volume: stats.volume,
+            fees: stats.fees,
+            swap_count: stats.swap_count,
+            unique_traders: stats.traders.len(),
+            lp_count,
+            tvl_quote,
+            reserves: pool_reserves,
+        });
+    }
+    DailySnapshot { date: date.to_string(), pools: rows }
+}
+
+/// CSV form of a snapshot; reserves are `faucet_id:amount` pairs joined by `;`
+fn snapshot_to_csv(snapshot: &DailySnapshot) -> String {
+    let mut out = String::from("date,pool_id,pair,volume,fees,swap_count,unique_traders,lp_count,tvl_quote,reserves\n");
+    for row in &snapshot.pools {
+        let reserves: Vec<String> = row.reserves.iter().map(|r| format!("{}:{}", r.faucet_id, r.amount)).collect();
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            snapshot.date, row.pool_id, row.pair, row.volume, row.fees, row.swap_count,
+            row.unique_traders, row.lp_count, row.tvl_quote, reserves.join(";")
+        ));
+    }
+    out
+}
+
+/// Gzip `data` to `path` via a temp file, so a half-written export never looks done
+fn write_gzip(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&tmp_path)?, flate2::Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", tmp_path.display()))?;
+    Ok(())
+}
+
+/// Export the previous UTC day if it hasn't been exported yet. Returns the
+/// snapshot name when files were written.
+async fn run_daily_export(
+    worker_tx: &std::sync::mpsc::Sender<WorkerRequest>,
+    metrics: &Mutex<MetricsState>,
+    daily_stats: &Arc<Mutex<DailyStats>>,
+    user_deposits: &Arc<Mutex<HashMap<String, UserPoolDeposit>>>,
+) -> Result<Option<String>> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let day = now / 86400 - 1;
+    let date = chrono::DateTime::from_timestamp((day * 86400) as i64, 0)
+        .context("Invalid export date")?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let name = format!("milo-stats-{}", date);
+    let json_path = Path::new(EXPORTS_DIR).join(format!("{}.json.gz", name));
+    let csv_path = Path::new(EXPORTS_DIR).join(format!("{}.csv.gz", name));
+    if json_path.exists() && csv_path.exists() {
+        return Ok(None);
+    }
+
+    // Closing reserves come from the worker (it owns the client)
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    metrics.lock().unwrap().enqueued();
+    if worker_tx.send(WorkerRequest::PoolReserves(PoolReservesRequest { force_sync: true, reply: reply_tx })).is_err() {
+        metrics.lock().unwrap().dequeued();
+        anyhow::bail!("Worker thread not available");
+    }
+    let reserves = tokio::time::timeout(Duration::from_secs(60), reply_rx)
+        .await
+        .context("Timed out waiting for pool reserves")?
+        .context("Worker dropped the reserves reply")?
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+    let pools = load_pools_config("pools.json")?;
+    let day_stats = daily_stats.lock().unwrap().get(&day).cloned().unwrap_or_default();
+    let deposits = user_deposits.lock().unwrap().clone();
+    let snapshot = build_daily_snapshot(&date, &day_stats, &deposits, &reserves, &pools);
+
+    fs::create_dir_all(EXPORTS_DIR)?;
+    write_gzip(&json_path, serde_json::to_string_pretty(&snapshot)?.as_bytes())?;
+    write_gzip(&csv_path, snapshot_to_csv(&snapshot).as_bytes())?;
+    Ok(Some(name))
+}
+
+// List available daily snapshots
+async fn list_exports_handler() -> impl IntoResponse {
+    let mut exports: Vec<serde_json::Value> = match fs::read_dir(EXPORTS_DIR) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                if !is_export_name(&name) {
+                    return None;
+                }
+                let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+                Some(serde_json::json!({ "name": name, "size": size }))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    exports.sort_by(|a, b| b["name"].as_str().cmp(&a["name"].as_str()));
+
+    Json(serde_json::json!({ "exports": exports }))
+}
+
+fn is_export_name(name: &str) -> bool {
+    name.starts_with("milo-stats-")
+        && (name.ends_with(".json.gz") || name.ends_with(".csv.gz"))
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && !name.contains("..")
+}
+
+// Serve one snapshot file
+async fn get_export_handler(UrlPath(name): UrlPath<String>) -> axum::response::Response {
+    if !is_export_name(&name) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "Invalid export name"
+        }))).into_response();
+    }
+    match fs::read(Path::new(EXPORTS_DIR).join(&name)) {
+        Ok(bytes) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/gzip".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+            ],
+            bytes,
+        ).into_response(),
+        Err(_) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "Export not found"
+        }))).into_response(),
+    }
+}
+
+// Get trade volume for pools
+async fn get_trade_volume_handler(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let volumes = state.trade_volumes.lock().unwrap();
+    let volume_list: Vec<&TradeVolume> = volumes.values().collect();
+
+    Json(serde_json::json!({
+        "volumes": volume_list
+    }))
+}
+
+// Calculate and return APY for each pool, against the TVL of its on-chain reserves
+async fn get_apy_handler(
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let pools = match load_pools_config("pools.json") {
+        Ok(p) => p,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "error": format!("Invalid pool configuration: {:#}", e)
+            }));
+        }
+    };
+
+    // Current reserves through the worker; without them TVL (and APY) is 0
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    state.metrics.lock().unwrap().enqueued();
+    let reserves: HashMap<String, Vec<ReserveAsset>> =
+        if state.worker_tx.send(WorkerRequest::PoolReserves(PoolReservesRequest { force_sync: false, reply: reply_tx })).is_ok() {
+            match tokio::time::timeout(Duration::from_secs(60), reply_rx).await {
+                Ok(Ok(Ok(response))) => response.pools.into_iter().map(|p| (p.pool_id, p.reserves)).collect(),
+                _ => HashMap::new(),
+            }
+        } else {
+            state.metrics.lock().unwrap().dequeued();
+            HashMap::new()
+        };
+
+    let volumes = state.trade_volumes.lock().unwrap();
+    let mut apy_data: Vec<serde_json::Value> = Vec::new();
+    for entry in &pools.pools {
+        let pool_id = entry.pool_id.to_hex();
+        let Some(volume) = volumes.get(&pool_id) else { continue };
+        let tvl = reserves.get(&pool_id).map(|r| tvl_quote(&entry.pair, r)).unwrap_or(0);
+        let daily_fee_rate = if tvl > 0 {
+            volume.fees_24h as f64 / tvl as f64
+        } else {
+            0.0
+        };
+        let apy = ((1.0 + daily_fee_rate).powf(365.0) - 1.0) * 100.0;
+
+        apy_data.push(serde_json::json!({
+            "pool": entry.pair.to_uppercase().replace('_', "/"),
+            "pool_id": pool_id,
+            "apy": format!("{:.2}", apy),
+            "volume_24h": volume.volume_24h,
+            "fees_24h": volume.fees_24h,
+            "trades_24h": volume.trades_24h,
+            "total_fees_lifetime": volume.total_fees_lifetime,
+            "total_volume_lifetime": volume.total_volume_lifetime,
+            "tvl": tvl
+        }));
+    }
 
-    Ok(WithdrawResponse {
-        success: true,
-        tx_id: Some(last_tx_id),
-        token_a_out: token_a_out.to_string(),
-        token_b_out: token_b_out.to_string(),
-        error: None,
-    })
+    Json(serde_json::json!({
+        "pools": apy_data
+    }))
 }
 
-// Withdraw handler - processes LP token withdrawal
-async fn withdraw_handler(
+// Pool reserves handler - returns reserves for all pools
+async fn pool_reserves_handler(
     State(state): State<AppState>,
-    Json(payload): Json<WithdrawRequest>,
+    Query(query): Query<PoolReservesQuery>,
 ) -> impl IntoResponse {
-    println!("🔄 Withdraw request: {} LP from pool {}", payload.lp_amount, payload.pool_account_id);
-    println!("   User: {}", payload.user_account_id);
+    println!("📊 Pool reserves request received");
 
-    // Parse IDs and amounts
-    let pool_id = match AccountId::from_hex(&payload.pool_account_id) {
-        Ok(id) => id,
-        Err(e) => {
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
-                success: false,
-                tx_id: None,
-                token_a_out: "0".to_string(),
-                token_b_out: "0".to_string(),
-                error: Some(format!("Invalid pool account ID: {:?}", e)),
-            })));
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    let req = PoolReservesRequest {
+        force_sync: query.force_sync,
+        reply: reply_tx,
+    };
+
+    state.metrics.lock().unwrap().enqueued();
+    if state.worker_tx.send(WorkerRequest::PoolReserves(req)).is_err() {
+        state.metrics.lock().unwrap().dequeued();
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "error": "Worker thread not available"
+            }))
+        );
+    }
+
+    match tokio::time::timeout(Duration::from_secs(60), reply_rx).await {
+        Ok(Ok(Ok(response))) => {
+            (StatusCode::OK, Json(serde_json::json!(response)))
+        }
+        Ok(Ok(Err(e))) => {
+            eprintln!("❌ Pool reserves error: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": e
+            })))
+        }
+        _ => {
+            (StatusCode::REQUEST_TIMEOUT, Json(serde_json::json!({
+                "error": "Timeout"
+            })))
+        }
+    }
+}
+
+// Get pool reserves from on-chain state (reuses a sync from the freshness window unless forced)
+async fn get_pool_reserves(
+    client: &mut MidenClient,
+    sync_freshness: &mut SyncFreshness,
+    force_sync: bool,
+    reserve_cache: &Mutex<HashMap<String, PoolReserveEntry>>,
+) -> Result<PoolReservesResponse> {
+    let synced = sync_if_stale(client, sync_freshness, force_sync).await?;
+    let entries = read_pool_reserves(client).await?;
+    cache_reserves(reserve_cache, &entries);
+
+    Ok(PoolReservesResponse {
+        pools: entries,
+        state_age_ms: sync_freshness.state_age_ms(),
+        synced,
+    })
+}
+
+/// Every pool's vault from the local store, without syncing
+async fn read_pool_reserves(client: &mut MidenClient) -> Result<Vec<PoolReserveEntry>> {
+    let pools = load_pools_config("pools.json")?;
+
+    let pool_configs = vec![
+        ("MILO/MUSDC", pools.require_pool_id("milo_musdc")?),
+        ("MELO/MUSDC", pools.require_pool_id("melo_musdc")?),
+    ];
+
+    let mut entries = Vec::new();
+
+    for (pair_name, pool_id) in pool_configs {
+
+        match client.get_account(pool_id).await? {
+            Some(pool_account) => {
+                let pool_vault = pool_account.account().vault();
+                let mut reserves = Vec::new();
+
+                for asset in pool_vault.assets() {
+                    if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+                        let amount = reserve_amount(&fungible_asset)?;
+                        reserves.push(ReserveAsset {
+                            faucet_id: fungible_asset.faucet_id().to_hex(),
+                            amount: amount.to_string(),
+                        });
+                    }
+                }
+
+                entries.push(PoolReserveEntry {
+                    pool_id: pool_id.to_hex(),
+                    pair: pair_name.to_string(),
+                    reserves,
+                });
+            }
+            None => {
+                println!("   ⚠️  Pool {} not found in local store", pool_id.to_hex());
+            }
         }
+    }
+
+    Ok(entries)
+}
+
+fn cache_reserves(reserve_cache: &Mutex<HashMap<String, PoolReserveEntry>>, entries: &[PoolReserveEntry]) {
+    let mut cache = reserve_cache.lock().unwrap();
+    for entry in entries {
+        cache.insert(entry.pool_id.clone(), entry.clone());
+    }
+}
+
+/// Re-read reserves into the cache after the worker may have changed them
+async fn refresh_reserve_cache(client: &mut MidenClient, reserve_cache: &Mutex<HashMap<String, PoolReserveEntry>>) {
+    match read_pool_reserves(client).await {
+        Ok(entries) => cache_reserves(reserve_cache, &entries),
+        Err(e) => println!("⚠️  Failed to refresh reserve cache: {:?}", e),
+    }
+}
+
+/// Require `Authorization: Bearer <MILO_ADMIN_TOKEN>` on admin routes
+fn check_admin(headers: &HeaderMap, state: &AppState) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err((StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": "Admin endpoints disabled (MILO_ADMIN_TOKEN not set)"
+        }))));
     };
+    let provided = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided != Some(expected.as_str()) {
+        return Err((StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Invalid or missing admin token"
+        }))));
+    }
+    Ok(())
+}
 
-    let user_id = match AccountId::from_hex(&payload.user_account_id) {
-        Ok(id) => id,
-        Err(e) => {
-            return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
-                success: false,
-                tx_id: None,
-                token_a_out: "0".to_string(),
-                token_b_out: "0".to_string(),
-                error: Some(format!("Invalid user account ID: {:?}", e)),
-            })));
+// Points balance per pool for one user
+async fn points_handler(
+    State(state): State<AppState>,
+    Query(query): Query<PointsQuery>,
+) -> impl IntoResponse {
+    let Some(ref campaign) = state.points_campaign else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No points campaign configured"
+        })));
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let points = state.user_points.lock().unwrap();
+    let pools: Vec<serde_json::Value> = points.values()
+        .filter(|p| p.user_account_id == query.user_id)
+        .map(|p| serde_json::json!({
+            "pool_id": p.pool_account_id,
+            "points": current_points(campaign, p, now),
+            "liquidity": p.liquidity,
+        }))
+        .collect();
+    let total: f64 = pools.iter().filter_map(|p| p["points"].as_f64()).sum();
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "user_id": query.user_id,
+        "total_points": total,
+        "pools": pools,
+        "campaign_start": campaign.start_time,
+        "campaign_end": campaign.end_time,
+    })))
+}
+
+/// Total points per user across all pools, highest first
+fn points_totals(campaign: &PointsCampaign, points: &HashMap<String, UserPoints>, now: u64) -> Vec<(String, f64)> {
+    let mut totals: HashMap<String, f64> = HashMap::new();
+    for p in points.values() {
+        *totals.entry(p.user_account_id.clone()).or_insert(0.0) += current_points(campaign, p, now);
+    }
+    let mut totals: Vec<(String, f64)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    totals
+}
+
+async fn points_leaderboard_handler(
+    State(state): State<AppState>,
+    Query(query): Query<LeaderboardQuery>,
+) -> impl IntoResponse {
+    let Some(ref campaign) = state.points_campaign else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No points campaign configured"
+        })));
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let points = state.user_points.lock().unwrap();
+    let leaderboard: Vec<serde_json::Value> = points_totals(campaign, &points, now)
+        .into_iter()
+        .take(query.limit.unwrap_or(50))
+        .enumerate()
+        .map(|(i, (user, total))| serde_json::json!({
+            "rank": i + 1,
+            "user_id": user,
+            "points": total,
+        }))
+        .collect();
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "leaderboard": leaderboard,
+        "campaign_start": campaign.start_time,
+        "campaign_end": campaign.end_time,
+    })))
+}
+
+// Final points balances - only available once the campaign has ended
+async fn points_export_handler(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+
+    let Some(ref campaign) = state.points_campaign else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "error": "No points campaign configured"
+        })));
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if now < campaign.end_time {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Campaign has not ended yet",
+            "campaign_end": campaign.end_time,
+        })));
+    }
+
+    // Balances are final: points_between clips accrual at end_time
+    let points = state.user_points.lock().unwrap();
+    let balances: Vec<serde_json::Value> = points_totals(campaign, &points, campaign.end_time)
+        .into_iter()
+        .map(|(user, total)| serde_json::json!({ "user_id": user, "points": total }))
+        .collect();
+    let export = serde_json::json!({
+        "campaign_start": campaign.start_time,
+        "campaign_end": campaign.end_time,
+        "balances": balances,
+    });
+
+    let export_file = format!("points_final_{}_{}.json", campaign.start_time, campaign.end_time);
+    if let Err(e) = fs::write(&export_file, serde_json::to_string_pretty(&export).unwrap_or_default()) {
+        eprintln!("⚠️  Failed to write {}: {}", export_file, e);
+    } else {
+        println!("🏆 Points export written to {}", export_file);
+    }
+
+    (StatusCode::OK, Json(export))
+}
+
+// Create a bot API key; the plaintext key is only returned here
+async fn create_api_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let account_id = match AccountId::from_hex(&payload.account_id) {
+        Ok(id) => id.to_hex(),
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Invalid account ID: {:?}", e)
+        }))),
+    };
+
+    match state.api_keys.create(&account_id, payload.permissions, &payload.label) {
+        Ok((record, key)) => {
+            println!("🔑 API key {} created for {} ({:?})", record.key_id, record.account_id, record.permissions);
+            (StatusCode::OK, Json(serde_json::json!({
+                "key_id": record.key_id,
+                "api_key": key,
+                "account_id": record.account_id,
+                "permissions": record.permissions,
+                "label": record.label,
+            })))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("{:#}", e)
+        }))),
+    }
+}
+
+// List API keys (hashes omitted)
+async fn list_api_keys_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let keys: Vec<serde_json::Value> = state.api_keys.list().into_iter()
+        .map(|k| serde_json::json!({
+            "key_id": k.key_id,
+            "account_id": k.account_id,
+            "permissions": k.permissions,
+            "label": k.label,
+            "created_at": k.created_at,
+            "revoked_at": k.revoked_at,
+        }))
+        .collect();
+    (StatusCode::OK, Json(serde_json::json!({ "keys": keys })))
+}
+
+// Revoke an API key; takes effect on the next request to either daemon
+async fn revoke_api_key_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RevokeApiKeyRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    match state.api_keys.revoke(&payload.key_id) {
+        Ok(true) => {
+            println!("🔑 API key {} revoked", payload.key_id);
+            (StatusCode::OK, Json(serde_json::json!({ "success": true, "key_id": payload.key_id })))
         }
+        Ok(false) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "success": false,
+            "error": "Key not found or already revoked"
+        }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "success": false,
+            "error": format!("{:#}", e)
+        }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pool_daemon::fixtures::{
+        deposit_info_for, p2id_note_record, pool_with_reserves, FixturePool, MELO_MUSDC_POOL, MILO_MUSDC_POOL, USER_ACCOUNT,
     };
 
-    let lp_amount: u64 = payload.lp_amount.parse().unwrap_or(0);
-    let min_token_a_out: u64 = payload.min_token_a_out.parse().unwrap_or(0);
-    let min_token_b_out: u64 = payload.min_token_b_out.parse().unwrap_or(0);
+    const POOL: &str = MILO_MUSDC_POOL;
+
+    fn deposit_info(note: &str, user: &str, amount: u64) -> DepositInfo {
+        let pool = pool_with_reserves(0, 0);
+        serde_json::from_value(deposit_info_for(note, &pool, user, &pool.token_a, amount, 0)).unwrap()
+    }
+
+    /// One step of an invariant scenario
+    #[derive(Debug, Clone)]
+    enum Event {
+        /// Deposit note consumed into reserve A (or B) and credited
+        Deposit { note: &'static str, user: &'static str, amount: u64, into_b: bool },
+        /// Swap note selling `amount_in` of token A for B at 0.3%
+        Swap { note: &'static str, amount_in: u64 },
+        Withdraw { user: &'static str, amount: u64 },
+        /// Daemon restarts and reloads the saved deposits
+        Restart,
+        /// Deposit consumed and credited in memory, then the daemon dies
+        /// before saving the entry
+        CrashAfterCredit { note: &'static str, user: &'static str, amount: u64 },
+        /// Withdrawal planned at the current reserves, a swap lands on the
+        /// pool, then the planned payout goes out
+        SwapDuringWithdraw { note: &'static str, amount_in: u64, user: &'static str, amount: u64 },
+    }
+
+    /// The daemon's accounting, driven through the same helpers, plus the
+    /// totals the invariants are checked against
+    struct Sim {
+        max_share_bps: Option<u64>,
+        pool: FixturePool,
+        deposits: HashMap<String, UserPoolDeposit>,
+        saved: String,
+        // What came into the pool from deposits, net of refunds
+        deposited: u128,
+        // Swap inputs minus outputs: what the LPs earned
+        fees: i128,
+        paid_out: u128,
+        // Credited minus deducted, from the helpers' return values
+        credited: u128,
+        outcomes: Vec<(&'static str, &'static str)>,
+        events: Vec<Event>,
+    }
+
+    impl Sim {
+        fn new(max_share_bps: Option<u64>) -> Self {
+            Self {
+                max_share_bps,
+                pool: pool_with_reserves(0, 0),
+                deposits: HashMap::new(),
+                saved: "{}".to_string(),
+                deposited: 0,
+                fees: 0,
+                paid_out: 0,
+                credited: 0,
+                outcomes: Vec::new(),
+                events: Vec::new(),
+            }
+        }
+
+        fn fail(&self, msg: String) -> ! {
+            panic!("{}\nevents:\n{:#?}", msg, self.events)
+        }
+
+        fn save(&mut self) {
+            self.saved = serde_json::to_string(&self.deposits).unwrap();
+        }
+
+        fn credit(&mut self, note: &'static str, user: &'static str, amount: u64, into_b: bool) {
+            if into_b {
+                self.pool.reserve_b += amount;
+            } else {
+                self.pool.reserve_a += amount;
+            }
+            let liquidity = self.pool.reserve_a + self.pool.reserve_b;
+            let (excess, _, _) = apply_deposit_credit(
+                &mut self.deposits, &deposit_info(note, user, amount), POOL, self.max_share_bps, liquidity, 0,
+            );
+            // The excess goes back out of the reserve it came into
+            if into_b {
+                self.pool.reserve_b -= excess;
+            } else {
+                self.pool.reserve_a -= excess;
+            }
+            self.deposited += (amount - excess) as u128;
+            self.credited += (amount - excess) as u128;
+            self.outcomes.push((note, if excess == amount { "refunded" } else { "credited" }));
+        }
+
+        fn swap(&mut self, note: &'static str, amount_in: u64) {
+            let out = self.pool.amount_out(&self.pool.token_a, amount_in, 30);
+            // The swap daemon's drain guard refunds the note instead
+            if out == 0 || out >= self.pool.reserve_b {
+                self.outcomes.push((note, "refunded"));
+                return;
+            }
+            self.pool.reserve_a += amount_in;
+            self.pool.reserve_b -= out;
+            self.fees += amount_in as i128 - out as i128;
+            self.outcomes.push((note, "swapped"));
+        }
+
+        fn plan(&self, user: &str, amount: u64) -> Option<WithdrawPlan> {
+            let entry = self.deposits.get(&format!("{}:{}", user, POOL))?;
+            plan_withdrawal(
+                entry.total_deposited,
+                entry.lp_shares,
+                outstanding_shares(&self.deposits, POOL),
+                self.pool.reserve_a,
+                self.pool.reserve_b,
+                amount,
+            )
+            .ok()
+        }
+
+        fn pay_out(&mut self, user: &str, plan: WithdrawPlan) {
+            if plan.token_a_out > self.pool.reserve_a || plan.token_b_out > self.pool.reserve_b {
+                self.fail(format!(
+                    "withdrawal of {}/{} exceeds reserves {}/{}",
+                    plan.token_a_out, plan.token_b_out, self.pool.reserve_a, self.pool.reserve_b
+                ));
+            }
+            self.pool.reserve_a -= plan.token_a_out;
+            self.pool.reserve_b -= plan.token_b_out;
+            self.paid_out += (plan.token_a_out + plan.token_b_out) as u128;
+            let entry = self.deposits.get_mut(&format!("{}:{}", user, POOL)).unwrap();
+            let before = entry.total_deposited;
+            deduct_deposit(entry, plan.deposit_deducted);
+            entry.lp_shares = entry.lp_shares.saturating_sub(plan.shares_burned);
+            self.credited -= (before - entry.total_deposited) as u128;
+        }
+
+        fn step(&mut self, event: Event) {
+            self.events.push(event.clone());
+            match event {
+                Event::Deposit { note, user, amount, into_b } => {
+                    self.credit(note, user, amount, into_b);
+                    self.save();
+                }
+                Event::Swap { note, amount_in } => self.swap(note, amount_in),
+                Event::Withdraw { user, amount } => {
+                    if let Some(plan) = self.plan(user, amount) {
+                        self.pay_out(user, plan);
+                        self.save();
+                    }
+                }
+                Event::Restart => {
+                    self.deposits = serde_json::from_str(&self.saved).unwrap();
+                    self.credited = self.deposits.values().map(|d| d.total_deposited as u128).sum();
+                }
+                Event::CrashAfterCredit { note, user, amount } => {
+                    self.credit(note, user, amount, false);
+                    self.deposits = serde_json::from_str(&self.saved).unwrap();
+                    self.credited = self.deposits.values().map(|d| d.total_deposited as u128).sum();
+                }
+                Event::SwapDuringWithdraw { note, amount_in, user, amount } => {
+                    let plan = self.plan(user, amount);
+                    self.swap(note, amount_in);
+                    if let Some(plan) = plan {
+                        self.pay_out(user, plan);
+                        self.save();
+                    }
+                }
+            }
+            self.check();
+        }
+
+        fn check(&self) {
+            // Reserves are exactly what came in minus what went out
+            let reserves = self.pool.reserve_a as i128 + self.pool.reserve_b as i128;
+            if reserves != self.deposited as i128 + self.fees - self.paid_out as i128 {
+                self.fail(format!("reserves {} don't match deposits, fees and payouts", reserves));
+            }
+
+            // Users can never be owed more than deposits plus fees
+            let outstanding = outstanding_shares(&self.deposits, POOL);
+            let liquidity = self.pool.reserve_a + self.pool.reserve_b;
+            let claims: u128 = self.deposits.values()
+                .map(|d| if d.lp_shares > 0 { share_value(d.lp_shares, outstanding, liquidity) } else { d.total_deposited })
+                .map(|v| v as u128)
+                .sum();
+            if self.paid_out as i128 + claims as i128 > self.deposited as i128 + self.fees {
+                self.fail(format!("paid out {} + claims {} exceed deposits and fees", self.paid_out, claims));
+            }
+
+            // The pool's credited total is the sum over its users
+            let per_user: u128 = self.deposits.values()
+                .filter(|d| d.pool_account_id == POOL)
+                .map(|d| d.total_deposited as u128)
+                .sum();
+            if per_user != self.credited {
+                self.fail(format!("credited total {} != sum over users {}", self.credited, per_user));
+            }
+
+            // Every note has exactly one outcome
+            let mut seen = BTreeSet::new();
+            for (note, outcome) in &self.outcomes {
+                if !seen.insert(*note) {
+                    self.fail(format!("note {} has a second outcome ({})", note, outcome));
+                }
+            }
+        }
+    }
+
+    fn run(max_share_bps: Option<u64>, events: Vec<Event>) -> Sim {
+        let mut sim = Sim::new(max_share_bps);
+        for event in events {
+            sim.step(event);
+        }
+        sim
+    }
+
+    #[test]
+    fn deposits_swaps_and_full_withdrawals() {
+        let sim = run(None, vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 1_000_000, into_b: false },
+            Event::Deposit { note: "d2", user: "alice", amount: 1_000_000, into_b: true },
+            Event::Deposit { note: "d3", user: "bob", amount: 500_000, into_b: false },
+            Event::Swap { note: "s1", amount_in: 10_000 },
+            Event::Swap { note: "s2", amount_in: 25_000 },
+            Event::Withdraw { user: "alice", amount: u64::MAX },
+            Event::Withdraw { user: "bob", amount: u64::MAX },
+        ]);
+        assert!(sim.pool.reserve_a + sim.pool.reserve_b <= 2, "rounding dust only: {}/{}", sim.pool.reserve_a, sim.pool.reserve_b);
+    }
+
+    #[test]
+    fn share_cap_refunds_the_excess() {
+        let sim = run(Some(4_000), vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 600_000, into_b: false },
+            Event::Deposit { note: "d2", user: "bob", amount: 600_000, into_b: true },
+            Event::Deposit { note: "d3", user: "alice", amount: 10_000_000, into_b: false },
+            Event::Withdraw { user: "bob", amount: 100_000 },
+        ]);
+        let alice = &sim.deposits[&format!("alice:{}", POOL)];
+        assert!(alice.total_deposited < 10_600_000);
+    }
+
+    #[test]
+    fn restart_keeps_saved_credits() {
+        let sim = run(None, vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 800_000, into_b: false },
+            Event::Restart,
+            Event::Deposit { note: "d2", user: "bob", amount: 800_000, into_b: true },
+            Event::Swap { note: "s1", amount_in: 40_000 },
+            Event::Restart,
+            Event::Withdraw { user: "alice", amount: 300_000 },
+        ]);
+        assert_eq!(sim.deposits.len(), 2);
+    }
 
-    if lp_amount == 0 {
-        return (StatusCode::BAD_REQUEST, Json(serde_json::json!(WithdrawResponse {
-            success: false,
-            tx_id: None,
-            token_a_out: "0".to_string(),
-            token_b_out: "0".to_string(),
-            error: Some("LP amount must be greater than 0".to_string()),
-        })));
+    #[test]
+    fn crash_between_credit_and_save_never_over_credits() {
+        let sim = run(None, vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 1_000_000, into_b: false },
+            Event::Deposit { note: "d1b", user: "alice", amount: 1_000_000, into_b: true },
+            Event::CrashAfterCredit { note: "d2", user: "bob", amount: 1_000_000 },
+            Event::Swap { note: "s1", amount_in: 5_000 },
+            Event::Withdraw { user: "bob", amount: u64::MAX },
+            Event::Withdraw { user: "alice", amount: u64::MAX },
+        ]);
+        // Bob's credit was lost with the crash; his deposit stayed in the pool
+        assert!(!sim.deposits.contains_key(&format!("bob:{}", POOL)));
     }
 
-    // Send to worker thread
-    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
-    let req = WithdrawWorkerRequest {
-        pool_id,
-        user_id,
-        lp_amount,
-        min_token_a_out,
-        min_token_b_out,
-        reply: reply_tx,
-    };
+    #[test]
+    fn swap_racing_a_withdrawal() {
+        run(None, vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 2_000_000, into_b: false },
+            Event::Deposit { note: "d2", user: "bob", amount: 2_000_000, into_b: true },
+            Event::SwapDuringWithdraw { note: "s1", amount_in: 400_000, user: "alice", amount: u64::MAX },
+            Event::SwapDuringWithdraw { note: "s2", amount_in: 100_000, user: "bob", amount: 1_000_000 },
+        ]);
+    }
 
-    if state.worker_tx.send(WorkerRequest::Withdraw(req)).is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!(WithdrawResponse {
-            success: false,
-            tx_id: None,
-            token_a_out: "0".to_string(),
-            token_b_out: "0".to_string(),
-            error: Some("Worker thread not available".to_string()),
-        })));
+    #[test]
+    fn oversized_swap_is_refunded_once() {
+        let sim = run(None, vec![
+            Event::Deposit { note: "d1", user: "alice", amount: 1_000, into_b: false },
+            Event::Deposit { note: "d2", user: "alice", amount: 10, into_b: true },
+            Event::Swap { note: "s1", amount_in: 1 },
+            Event::Withdraw { user: "alice", amount: 500 },
+            Event::Swap { note: "s2", amount_in: 1_000_000_000 },
+        ]);
+        assert!(sim.outcomes.contains(&("s1", "refunded")));
     }
 
-    // Wait for response
-    match tokio::time::timeout(Duration::from_secs(120), reply_rx).await {
-        Ok(Ok(Ok(response))) => {
-            println!("✅ Withdraw processed: {} tokenA, {} tokenB", response.token_a_out, response.token_b_out);
-            (StatusCode::OK, Json(serde_json::json!(response)))
-        }
-        Ok(Ok(Err(e))) => {
-            eprintln!("❌ Withdraw error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!(WithdrawResponse {
-                success: false,
-                tx_id: None,
-                token_a_out: "0".to_string(),
-                token_b_out: "0".to_string(),
-                error: Some(e),
-            })))
-        }
-        _ => {
-            (StatusCode::REQUEST_TIMEOUT, Json(serde_json::json!(WithdrawResponse {
-                success: false,
-                tx_id: None,
-                token_a_out: "0".to_string(),
-                token_b_out: "0".to_string(),
-                error: Some("Timeout".to_string()),
-            })))
+    const HOUR: u64 = 3600;
+
+    fn campaign() -> PointsCampaign {
+        PointsCampaign {
+            start_time: 10 * HOUR,
+            end_time: 20 * HOUR,
+            pool_multipliers: HashMap::from([("0xboosted".to_string(), 2.0)]),
         }
     }
-}
 
-// Get user deposits for a specific user
-async fn user_deposits_handler(
-    State(state): State<AppState>,
-    Query(query): Query<UserDepositsQuery>,
-) -> impl IntoResponse {
-    let deposits = state.user_deposits.lock().unwrap();
-    let user_deps: Vec<&UserPoolDeposit> = deposits
-        .values()
-        .filter(|d| d.user_account_id == query.user_id)
-        .collect();
+    fn tracked(user: &str, pool: &str, total_deposited: u64) -> (String, UserPoolDeposit) {
+        (format!("{}:{}", user, pool), UserPoolDeposit {
+            user_account_id: user.to_string(),
+            pool_account_id: pool.to_string(),
+            total_deposited,
+            lp_shares: 0,
+            deposit_count: 1,
+            last_deposit_time: 0,
+            refunds: Vec::new(),
+        })
+    }
 
-    Json(serde_json::json!({
-        "user_id": query.user_id,
-        "deposits": user_deps
-    }))
-}
+    #[test]
+    fn points_are_clipped_to_the_campaign_window() {
+        let c = campaign();
+        assert_eq!(points_between(&c, POOL, 100, 0, 10 * HOUR), 0.0);
+        assert_eq!(points_between(&c, POOL, 100, 9 * HOUR, 11 * HOUR), 100.0);
+        assert_eq!(points_between(&c, POOL, 100, 19 * HOUR, 30 * HOUR), 100.0);
+        assert_eq!(points_between(&c, POOL, 100, 25 * HOUR, 30 * HOUR), 0.0);
+        assert_eq!(points_between(&c, "0xboosted", 100, 10 * HOUR, 11 * HOUR), 200.0);
+    }
 
-// Record a trade for volume tracking
-async fn record_trade_handler(
-    State(state): State<AppState>,
-    Json(payload): Json<RecordTradeRequest>,
-) -> impl IntoResponse {
-    println!("📊 Recording trade: {} volume, {} fee for pool {}",
-        payload.amount_in, payload.fee_amount, payload.pool_id);
+    #[test]
+    fn accrual_uses_the_liquidity_held_since_the_last_tick() {
+        let c = campaign();
+        let mut deposits = HashMap::from([tracked("alice", POOL, 100)]);
+        let mut points = HashMap::new();
+        accrue_points(&c, &deposits, &mut points, 10 * HOUR);
+        // Alice doubles her deposit after two hours
+        deposits.get_mut(&format!("alice:{}", POOL)).unwrap().total_deposited = 200;
+        accrue_points(&c, &deposits, &mut points, 12 * HOUR);
+        accrue_points(&c, &deposits, &mut points, 13 * HOUR);
+        let entry = &points[&format!("alice:{}", POOL)];
+        assert_eq!(entry.points, 100.0 * 2.0 + 200.0);
+        assert_eq!(current_points(&c, entry, 14 * HOUR), 600.0);
+    }
 
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+    #[test]
+    fn restart_downtime_is_credited_from_timestamps() {
+        let c = campaign();
+        let deposits = HashMap::from([tracked("alice", POOL, 50)]);
+
+        // Ticking every 15 seconds for three hours...
+        let mut ticking = HashMap::new();
+        let mut now = 11 * HOUR;
+        while now <= 14 * HOUR {
+            accrue_points(&c, &deposits, &mut ticking, now);
+            now += POINTS_ACCRUAL_INTERVAL_SECS;
+        }
 
-    let mut volumes = state.trade_volumes.lock().unwrap();
+        // ...earns the same as a daemon that was down for those three hours
+        let mut restarted = HashMap::new();
+        accrue_points(&c, &deposits, &mut restarted, 11 * HOUR);
+        let saved = serde_json::to_string(&restarted).unwrap();
+        let mut restarted: HashMap<String, UserPoints> = serde_json::from_str(&saved).unwrap();
+        accrue_points(&c, &deposits, &mut restarted, 14 * HOUR);
 
-    if let Some(volume) = volumes.get_mut(&payload.pool_id) {
-        if now - volume.last_updated > 86400 {
-            volume.volume_24h = 0;
-            volume.fees_24h = 0;
-            volume.trades_24h = 0;
-        }
+        let key = format!("alice:{}", POOL);
+        assert_eq!(restarted[&key].points, 150.0);
+        assert!((ticking[&key].points - restarted[&key].points).abs() < 1e-6);
+    }
 
-        volume.volume_24h += payload.amount_in;
-        volume.fees_24h += payload.fee_amount;
-        volume.trades_24h += 1;
-        volume.last_updated = now;
+    #[test]
+    fn leaderboard_sums_pools_per_user() {
+        let c = campaign();
+        let deposits = HashMap::from([
+            tracked("alice", POOL, 100),
+            tracked("alice", "0xboosted", 10),
+            tracked("bob", POOL, 150),
+        ]);
+        let mut points = HashMap::new();
+        accrue_points(&c, &deposits, &mut points, 10 * HOUR);
+        let totals = points_totals(&c, &points, 11 * HOUR);
+        assert_eq!(totals, vec![("bob".to_string(), 150.0), ("alice".to_string(), 120.0)]);
+        // Balances stop growing at the end of the campaign
+        assert_eq!(points_totals(&c, &points, c.end_time), points_totals(&c, &points, c.end_time + HOUR));
+    }
 
-        println!("   Updated: volume_24h={}, fees_24h={}, trades_24h={}",
-            volume.volume_24h, volume.fees_24h, volume.trades_24h);
-    } else {
-        volumes.insert(payload.pool_id.clone(), TradeVolume {
-            pool_id: payload.pool_id.clone(),
-            volume_24h: payload.amount_in,
-            fees_24h: payload.fee_amount,
-            trades_24h: 1,
-            last_updated: now,
-        });
+    /// Credit `amount` to `user` through the worker's crediting path, into a
+    /// pool that already holds `pool_liquidity` besides it
+    fn credited(deposits: &mut HashMap<String, UserPoolDeposit>, user: &str, amount: u64, pool_liquidity: u64) {
+        let info = deposit_info("note", user, amount);
+        let (excess, _, key) = apply_deposit_credit(deposits, &info, POOL, None, pool_liquidity + amount, 0);
+        assert_eq!((excess, key), (0, Some(format!("{}:{}", user, POOL))));
     }
 
-    (StatusCode::OK, Json(serde_json::json!({
-        "success": true,
-        "pool_id": payload.pool_id
-    })))
-}
+    /// Withdraw through the same steps as execute_withdraw, against `reserves`
+    fn withdraw(deposits: &mut HashMap<String, UserPoolDeposit>, user: &str, amount: u64, reserves: (u64, u64)) -> Result<WithdrawPlan> {
+        let (total_deposited, user_shares, outstanding) = tracked_claim(deposits, user, POOL)?;
+        let plan = plan_withdrawal(total_deposited, user_shares, outstanding, reserves.0, reserves.1, amount)?;
+        let entry = deposits.get_mut(&format!("{}:{}", user, POOL)).unwrap();
+        deduct_deposit(entry, plan.deposit_deducted);
+        entry.lp_shares = entry.lp_shares.saturating_sub(plan.shares_burned);
+        Ok(plan)
+    }
 
-// Get trade volume for pools
-async fn get_trade_volume_handler(
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    let volumes = state.trade_volumes.lock().unwrap();
-    let volume_list: Vec<&TradeVolume> = volumes.values().collect();
+    #[test]
+    fn clamp_withdrawal_caps_at_the_claim() {
+        assert_eq!(clamp_withdrawal(500, 1_000), Some(500));
+        assert_eq!(clamp_withdrawal(5_000, 1_000), Some(1_000));
+        assert_eq!(clamp_withdrawal(5_000, 0), None);
+    }
 
-    Json(serde_json::json!({
-        "volumes": volume_list
-    }))
-}
+    #[test]
+    fn deduct_deposit_never_goes_below_zero() {
+        let (_, mut entry) = tracked("alice", POOL, 300);
+        deduct_deposit(&mut entry, 200);
+        assert_eq!(entry.total_deposited, 100);
+        deduct_deposit(&mut entry, 200);
+        assert_eq!(entry.total_deposited, 0);
+    }
 
-// Calculate and return APY for each pool
-async fn get_apy_handler(
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    let volumes = state.trade_volumes.lock().unwrap();
+    #[test]
+    fn over_withdrawal_is_clamped_then_rejected() {
+        let d = 1_000_000;
+        let mut deposits = HashMap::new();
+        credited(&mut deposits, "alice", d, 0);
+
+        // Withdrawing more than D pays out D and empties the entry
+        let plan = withdraw(&mut deposits, "alice", 3 * d, (d, 0)).unwrap();
+        assert_eq!(plan.amount, d);
+        assert_eq!(plan.token_a_out + plan.token_b_out, d);
+        let entry = &deposits[&format!("alice:{}", POOL)];
+        assert_eq!((entry.total_deposited, entry.lp_shares), (0, 0));
+
+        // A second withdrawal has nothing left to take
+        let err = withdraw(&mut deposits, "alice", 1, (0, 0)).unwrap_err();
+        assert!(err.to_string().contains("No tracked deposits"), "{}", err);
+        let err = withdraw(&mut deposits, "bob", 1, (0, 0)).unwrap_err();
+        assert!(err.to_string().contains("No tracked deposits"), "{}", err);
+    }
 
-    let pools_json = match fs::read_to_string("pools.json") {
-        Ok(json) => json,
-        Err(_) => {
-            return Json(serde_json::json!({
-                "error": "Could not load pool configuration"
-            }));
-        }
-    };
+    #[test]
+    fn partial_withdrawal_leaves_the_remainder() {
+        let mut deposits = HashMap::from([tracked("bob", POOL, 400_000)]);
+        credited(&mut deposits, "alice", 600_000, 0);
+        let shares_before = deposits[&format!("alice:{}", POOL)].lp_shares;
+
+        let plan = withdraw(&mut deposits, "alice", 150_000, (360_000, 240_000)).unwrap();
+        assert_eq!((plan.amount, plan.token_a_out, plan.token_b_out), (150_000, 90_000, 60_000));
+        let alice = &deposits[&format!("alice:{}", POOL)];
+        assert_eq!(alice.total_deposited, 450_000);
+        assert_eq!(alice.lp_shares, shares_before - shares_before / 4);
+        // Bob's legacy claim is untouched
+        assert_eq!(deposits[&format!("bob:{}", POOL)].total_deposited, 400_000);
+    }
 
-    let pools: serde_json::Value = match serde_json::from_str(&pools_json) {
-        Ok(p) => p,
-        Err(_) => {
-            return Json(serde_json::json!({
-                "error": "Invalid pool configuration"
-            }));
-        }
-    };
+    #[test]
+    fn legacy_deposit_withdraws_up_to_the_amount_deposited() {
+        let mut deposits = HashMap::from([tracked("alice", POOL, 500)]);
+        let plan = withdraw(&mut deposits, "alice", 800, (1_000, 1_000)).unwrap();
+        assert_eq!((plan.amount, plan.token_a_out, plan.token_b_out), (500, 250, 250));
+        assert_eq!(deposits[&format!("alice:{}", POOL)].total_deposited, 0);
+    }
 
-    let mut apy_data: Vec<serde_json::Value> = Vec::new();
+    fn pool_of(entries: &[(&str, u64)]) -> HashMap<String, UserPoolDeposit> {
+        entries.iter().map(|(user, total)| tracked(user, POOL, *total)).collect()
+    }
 
-    // MILO/MUSDC pool APY
-    let milo_pool_id = pools["milo_musdc_pool_id"].as_str().unwrap_or("");
-    if let Some(volume) = volumes.get(milo_pool_id) {
-        let tvl: u64 = 600000;
-        let daily_fee_rate = if tvl > 0 {
-            volume.fees_24h as f64 / tvl as f64
-        } else {
-            0.0
-        };
-        let apy = ((1.0 + daily_fee_rate).powf(365.0) - 1.0) * 100.0;
+    #[test]
+    fn headroom_is_unlimited_without_cap_or_other_holders() {
+        let deposits = pool_of(&[("alice", 900)]);
+        assert_eq!(deposit_headroom(&deposits, POOL, "alice", None), None);
+        // A sole LP is never capped, nor is the first depositor
+        assert_eq!(deposit_headroom(&deposits, POOL, "alice", Some(4_000)), None);
+        assert_eq!(deposit_headroom(&HashMap::new(), POOL, "alice", Some(4_000)), None);
+    }
 
-        apy_data.push(serde_json::json!({
-            "pool": "MILO/MUSDC",
-            "pool_id": milo_pool_id,
-            "apy": format!("{:.2}", apy),
-            "volume_24h": volume.volume_24h,
-            "fees_24h": volume.fees_24h,
-            "trades_24h": volume.trades_24h,
-            "tvl": tvl
-        }));
+    #[test]
+    fn headroom_keeps_the_user_at_the_cap() {
+        // bob 600 of 1000; a 40% cap lets alice add x with x / (1000 + x) <= 0.4
+        let deposits = pool_of(&[("bob", 600), ("carol", 400)]);
+        let headroom = deposit_headroom(&deposits, POOL, "alice", Some(4_000)).unwrap();
+        assert_eq!(headroom, 666);
+        assert!((headroom as f64) / (1_000 + headroom) as f64 <= 0.4);
+        assert!((headroom as f64 + 1.0) / (1_000 + headroom + 1) as f64 > 0.4);
+
+        // carol already holds her 40%
+        assert_eq!(deposit_headroom(&deposits, POOL, "carol", Some(4_000)), Some(0));
+        // bob is above the cap and can't add at all
+        assert_eq!(deposit_headroom(&deposits, POOL, "bob", Some(4_000)), Some(0));
+        // Deposits in other pools don't count
+        let mut deposits = deposits;
+        deposits.extend([tracked("alice", "0xother", 1_000_000)]);
+        assert_eq!(deposit_headroom(&deposits, POOL, "alice", Some(4_000)), Some(666));
     }
 
-    // MELO/MUSDC pool APY
-    let melo_pool_id = pools["melo_musdc_pool_id"].as_str().unwrap_or("");
-    if let Some(volume) = volumes.get(melo_pool_id) {
-        let tvl: u64 = 600000;
-        let daily_fee_rate = if tvl > 0 {
-            volume.fees_24h as f64 / tvl as f64
-        } else {
-            0.0
-        };
-        let apy = ((1.0 + daily_fee_rate).powf(365.0) - 1.0) * 100.0;
+    #[test]
+    fn capped_deposit_credits_the_headroom_and_refunds_the_rest() {
+        let mut deposits = pool_of(&[("bob", 600), ("carol", 400)]);
+        let (excess, _, key) =
+            apply_deposit_credit(&mut deposits, &deposit_info("n1", "alice", 1_000), POOL, Some(4_000), 2_000, 0);
+        assert_eq!(excess, 334);
+        assert_eq!(deposits[&key.unwrap()].total_deposited, 666);
+
+        let (excess, shares, key) =
+            apply_deposit_credit(&mut deposits, &deposit_info("n2", "carol", 50), POOL, Some(4_000), 2_000, 0);
+        assert_eq!((excess, shares, key), (50, 0, None));
+    }
 
-        apy_data.push(serde_json::json!({
-            "pool": "MELO/MUSDC",
-            "pool_id": melo_pool_id,
-            "apy": format!("{:.2}", apy),
-            "volume_24h": volume.volume_24h,
-            "fees_24h": volume.fees_24h,
-            "trades_24h": volume.trades_24h,
-            "tvl": tvl
-        }));
+    #[test]
+    fn exposure_ranks_holders_and_measures_concentration() {
+        let mut deposits = pool_of(&[("alice", 500), ("bob", 300), ("carol", 200), ("dave", 0)]);
+        deposits.extend([tracked("erin", "0xother", 10_000)]);
+        let exposure = PoolExposure::new(&deposits, POOL);
+        let users: Vec<&str> = exposure.holders.iter().map(|(user, _)| user.as_str()).collect();
+        assert_eq!(users, ["alice", "bob", "carol"]);
+        assert_eq!(exposure.total, 1_000);
+        assert!((exposure.largest_share() - 0.5).abs() < 1e-9);
+        assert!((exposure.hhi() - (0.25 + 0.09 + 0.04)).abs() < 1e-9);
+
+        let single = PoolExposure::new(&pool_of(&[("alice", 7)]), POOL);
+        assert!((single.hhi() - 1.0).abs() < 1e-9);
+        let empty = PoolExposure::new(&HashMap::new(), POOL);
+        assert_eq!((empty.largest_share(), empty.hhi()), (0.0, 0.0));
     }
 
-    Json(serde_json::json!({
-        "pools": apy_data
-    }))
-}
+    #[test]
+    fn lp_quote_values_the_deposit_at_the_pool_ratio() {
+        // 1000 A / 2000 B: 100 A plus the 200 B it is worth, halved
+        let pool = pool_with_reserves(1_000, 2_000);
+        assert_eq!(quote_into(&pool, &pool.token_a, 100), 150);
+        assert_eq!(quote_into(&pool, &pool.token_b, 100), 75);
+        // An empty side or pool quotes the amount
+        assert_eq!(quote_into(&pool_with_reserves(0, 3_000), &pool.token_a, 100), 100);
+        assert_eq!(quote_into(&pool_with_reserves(0, 0), &pool.token_a, 100), 100);
+    }
 
-// Pool reserves handler - returns reserves for all pools
-async fn pool_reserves_handler(
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    println!("📊 Pool reserves request received");
+    /// LP quote for depositing `amount` of `token` into `pool`, as the worker reads it
+    fn quote_into(pool: &FixturePool, token: &str, amount: u64) -> u64 {
+        deposit_lp_quote(amount, pool.reserve(token), pool.reserve_a as u128 + pool.reserve_b as u128)
+    }
 
-    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
-    let req = PoolReservesRequest {
-        reply: reply_tx,
-    };
+    #[test]
+    fn deposit_below_min_lp_is_refunded() {
+        // The ratio moved against the user after the note was built
+        let pool = pool_with_reserves(1_000, 2_000);
+        let info = serde_json::from_value::<DepositInfo>(
+            deposit_info_for("n1", &pool, "alice", &pool.token_b, 100, 150),
+        ).unwrap();
+        let quote = quote_into(&pool, &info.token_id, 100);
+        assert_eq!(lp_quote_shortfall(Some(quote), &info.min_lp_amount_out), Some((75, 150)));
+        assert_eq!(lp_quote_shortfall(Some(149), "150"), Some((149, 150)));
+    }
 
-    if state.worker_tx.send(WorkerRequest::PoolReserves(req)).is_err() {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "Worker thread not available"
-            }))
+    #[test]
+    fn deposit_at_or_above_min_lp_is_credited() {
+        assert_eq!(lp_quote_shortfall(Some(150), "150"), None);
+        assert_eq!(lp_quote_shortfall(Some(151), "150"), None);
+        assert_eq!(lp_quote_shortfall(Some(0), "0"), None);
+        // No minimum given, or no quote because reading the pool failed
+        assert_eq!(lp_quote_shortfall(Some(10), "not a number"), None);
+        assert_eq!(lp_quote_shortfall(None, "150"), None);
+    }
+
+    fn snapshot_fixture() -> DailySnapshot {
+        let mut pools = PoolsConfig::default();
+        pools.insert("milo_musdc", AccountId::from_hex(MILO_MUSDC_POOL).unwrap(), None);
+        pools.insert("melo_musdc", AccountId::from_hex(MELO_MUSDC_POOL).unwrap(), None);
+
+        let day_stats = BTreeMap::from([(MILO_MUSDC_POOL.to_string(), DailyPoolStats {
+            volume: 5_000,
+            fees: 15,
+            swap_count: 3,
+            traders: BTreeSet::from(["alice".to_string(), "bob".to_string()]),
+        })]);
+        let deposits = HashMap::from([
+            tracked("alice", MILO_MUSDC_POOL, 100),
+            tracked("bob", MILO_MUSDC_POOL, 0),
+            tracked("carol", MELO_MUSDC_POOL, 50),
+        ]);
+        // The melo pool's reserves couldn't be read
+        let reserves = PoolReservesResponse {
+            pools: vec![serde_json::from_value(pool_with_reserves(1_000, 250).reserves_json()).unwrap()],
+            state_age_ms: Some(0),
+            synced: true,
+        };
+        build_daily_snapshot("2026-10-15", &day_stats, &deposits, &reserves, &pools)
+    }
+
+    #[test]
+    fn daily_snapshot_json_matches_golden() {
+        let expected = serde_json::json!({
+            "date": "2026-10-15",
+            "pools": [
+                {
+                    "pool_id": MELO_MUSDC_POOL,
+                    "pair": "MELO/MUSDC",
+                    "volume": 0,
+                    "fees": 0,
+                    "swap_count": 0,
+                    "unique_traders": 0,
+                    "lp_count": 1,
+                    "tvl_quote": 0,
+                    "reserves": [],
+                },
+                {
+                    "pool_id": MILO_MUSDC_POOL,
+                    "pair": "MILO/MUSDC",
+                    "volume": 5000,
+                    "fees": 15,
+                    "swap_count": 3,
+                    "unique_traders": 2,
+                    "lp_count": 1,
+                    "tvl_quote": 500,
+                    "reserves": [
+                        { "faucet_id": pool_with_reserves(0, 0).token_a, "amount": "1000" },
+                        { "faucet_id": pool_with_reserves(0, 0).token_b, "amount": "250" },
+                    ],
+                },
+            ],
+        });
+        assert_eq!(serde_json::to_value(snapshot_fixture()).unwrap(), expected);
+    }
+
+    #[test]
+    fn daily_snapshot_csv_matches_golden() {
+        let expected = format!(
+            "date,pool_id,pair,volume,fees,swap_count,unique_traders,lp_count,tvl_quote,reserves\n\
+             2026-10-15,{melo},MELO/MUSDC,0,0,0,0,1,0,\n\
+             2026-10-15,{milo},MILO/MUSDC,5000,15,3,2,1,500,{milo_faucet}:1000;{musdc_faucet}:250\n",
+            melo = MELO_MUSDC_POOL,
+            milo = MILO_MUSDC_POOL,
+            milo_faucet = pool_with_reserves(0, 0).token_a,
+            musdc_faucet = pool_with_reserves(0, 0).token_b,
         );
+        assert_eq!(snapshot_to_csv(&snapshot_fixture()), expected);
     }
 
-    match tokio::time::timeout(Duration::from_secs(60), reply_rx).await {
-        Ok(Ok(Ok(response))) => {
-            (StatusCode::OK, Json(serde_json::json!(response)))
-        }
-        Ok(Ok(Err(e))) => {
-            eprintln!("❌ Pool reserves error: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
-                "error": e
-            })))
-        }
-        _ => {
-            (StatusCode::REQUEST_TIMEOUT, Json(serde_json::json!({
-                "error": "Timeout"
-            })))
+    #[test]
+    fn tvl_is_twice_the_quote_reserve() {
+        let reserves = vec![
+            ReserveAsset { faucet_id: milo_registry::MILO_FAUCET_ID_HEX.to_string(), amount: "9".to_string() },
+            ReserveAsset { faucet_id: milo_registry::MUSDC_FAUCET_ID_HEX.to_uppercase(), amount: "21".to_string() },
+        ];
+        assert_eq!(tvl_quote("milo_musdc", &reserves), 42);
+        assert_eq!(tvl_quote("musdc_milo", &reserves), 18);
+        assert_eq!(tvl_quote("milo_doge", &reserves), 0);
+        assert_eq!(tvl_quote("milo", &reserves), 0);
+    }
+
+    fn queued_mint(deposit_tx_id: &str, lp_faucet_id: AccountId, lp_amount: u64) -> PendingLpMint {
+        PendingLpMint {
+            deposit_tx_id: deposit_tx_id.to_string(),
+            pool_id: AccountId::from_hex(POOL).unwrap(),
+            lp_faucet_id,
+            user_account_id: "alice".to_string(),
+            token_id: pool_with_reserves(0, 0).token_a,
+            note_id: "0xnote".to_string(),
+            credited: lp_amount,
+            lp_shares: 0,
+            lp_amount,
+            ready: false,
+            attempts: 0,
         }
     }
-}
 
-// Get pool reserves from on-chain state
-async fn get_pool_reserves(client: &mut MidenClient) -> Result<PoolReservesResponse> {
-    let pools_json = fs::read_to_string("pools.json")?;
-    let pools: serde_json::Value = serde_json::from_str(&pools_json)?;
+    #[test]
+    fn circulating_lp_counts_owed_mints_and_not_burning_tokens() {
+        let lp_faucet = AccountId::from_hex(MILO_MUSDC_POOL).unwrap();
+        let other_faucet = AccountId::from_hex(MELO_MUSDC_POOL).unwrap();
+        let mut rng = miden_client::crypto::RpoRandomCoin::new([Felt::new(1); 4].into());
+        let burn_note = lp_burn_note(AccountId::from_hex(POOL).unwrap(), lp_faucet, 300, &mut rng).unwrap();
+        let queues = LpQueues {
+            mints: vec![queued_mint("0xa", lp_faucet, 50), queued_mint("0xb", other_faucet, 70)],
+            burns: vec![PendingBurn { lp_faucet_id: lp_faucet, note: burn_note, lp_amount: 300 }],
+        };
 
-    let pool_configs = vec![
-        ("MILO/MUSDC", pools["milo_musdc_pool_id"].as_str().unwrap()),
-        ("MELO/MUSDC", pools["melo_musdc_pool_id"].as_str().unwrap()),
-    ];
+        assert_eq!(queues.circulating(lp_faucet, 1_000), 1_000 - 300 + 50);
+        assert_eq!(queues.circulating(other_faucet, 1_000), 1_070);
+        // Nothing queued: the issued supply
+        assert_eq!(LpQueues::default().circulating(lp_faucet, 1_000), 1_000);
+    }
 
-    client.sync_state().await?;
+    #[test]
+    fn final_deposit_releases_only_its_own_mint() {
+        let lp_faucet = AccountId::from_hex(MILO_MUSDC_POOL).unwrap();
+        let mut queues = LpQueues {
+            mints: vec![queued_mint("0xa", lp_faucet, 50), queued_mint("0xb", lp_faucet, 70)],
+            burns: Vec::new(),
+        };
+        queues.release_mint("0xb");
+        assert_eq!(queues.mints.iter().map(|m| m.ready).collect::<Vec<_>>(), [false, true]);
+    }
 
-    let mut entries = Vec::new();
+    #[test]
+    fn pool_creation_store_sits_next_to_the_worker_store() {
+        assert_eq!(
+            pool_create_store_path("integration/liquidity_store.sqlite3"),
+            PathBuf::from("integration/liquidity_store.pool_create.sqlite3"),
+        );
+        assert_eq!(pool_create_store_path("/data/store"), PathBuf::from("/data/store.pool_create.sqlite3"));
+    }
 
-    for (pair_name, pool_id_hex) in pool_configs {
-        let pool_id = AccountId::from_hex(pool_id_hex)?;
+    #[test]
+    fn trades_are_only_recorded_for_known_pools() {
+        let mut pools = PoolsConfig::default();
+        pools.insert("milo_musdc", AccountId::from_hex(MILO_MUSDC_POOL).unwrap(), None);
+        let canonical = AccountId::from_hex(MILO_MUSDC_POOL).unwrap().to_hex();
+        assert_eq!(known_pool_id(&pools, MILO_MUSDC_POOL), Some(canonical.clone()));
+        assert_eq!(known_pool_id(&pools, &format!(" {} ", canonical)), Some(canonical));
+        assert_eq!(known_pool_id(&pools, MELO_MUSDC_POOL), None);
+        assert_eq!(known_pool_id(&pools, "not a pool"), None);
+    }
 
-        match client.get_account(pool_id).await? {
-            Some(pool_account) => {
-                let pool_vault = pool_account.account().vault();
-                let mut reserves = Vec::new();
+    #[test]
+    fn huge_trades_saturate_the_daily_stats() {
+        let mut day = DailyPoolStats::default();
+        day.record(u64::MAX, u64::MAX, Some("Alice"));
+        day.record(1, 1, Some("alice"));
+        assert_eq!((day.volume, day.fees, day.swap_count), (u64::MAX, u64::MAX, 2));
+        assert_eq!(day.traders.len(), 1);
+    }
 
-                for asset in pool_vault.assets() {
-                    if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
-                        let amount: u64 = fungible_asset.amount().try_into()?;
-                        reserves.push(ReserveAsset {
-                            faucet_id: fungible_asset.faucet_id().to_hex(),
-                            amount: amount.to_string(),
-                        });
-                    }
-                }
+    #[test]
+    fn persisted_volumes_of_unknown_pools_are_dropped() {
+        let mut pools = PoolsConfig::default();
+        pools.insert("milo_musdc", AccountId::from_hex(MILO_MUSDC_POOL).unwrap(), None);
+        let known = AccountId::from_hex(MILO_MUSDC_POOL).unwrap().to_hex();
+        let volume = |pool_id: &str| TradeVolume {
+            pool_id: pool_id.to_string(),
+            volume_24h: 1_000,
+            fees_24h: 3,
+            trades_24h: 1,
+            last_updated: 0,
+            total_fees_lifetime: 3,
+            total_volume_lifetime: 1_000,
+        };
+        let mut volumes: HashMap<String, TradeVolume> = [known.as_str(), MELO_MUSDC_POOL, "0xfake"]
+            .into_iter()
+            .map(|id| (id.to_string(), volume(id)))
+            .collect();
+        let mut dropped = retain_known_pools(&mut volumes, &pools);
+        dropped.sort();
+        assert_eq!(dropped, vec!["0xfake".to_string(), MELO_MUSDC_POOL.to_string()]);
+        assert_eq!(volumes.keys().collect::<Vec<_>>(), vec![&known]);
+    }
 
-                entries.push(PoolReserveEntry {
-                    pool_id: pool_id_hex.to_string(),
-                    pair: pair_name.to_string(),
-                    reserves,
-                });
-            }
-            None => {
-                println!("   ⚠️  Pool {} not found in local store", pool_id_hex);
+    fn tracked_note(note_id: &str, status: &str) -> TrackedNote {
+        TrackedNote { note_id: note_id.to_string(), note_type: "deposit".to_string(), timestamp: 0, status: status.to_string() }
+    }
+
+    #[test]
+    fn discarded_deposit_is_rolled_back_and_its_note_is_pending_again() {
+        let pool_id = AccountId::from_hex(POOL).unwrap();
+        let lp_faucet = AccountId::from_hex(MELO_MUSDC_POOL).unwrap();
+        let key = format!("alice:{}", pool_id.to_hex());
+        let mut deposits = HashMap::new();
+        apply_deposit_credit(&mut deposits, &deposit_info("0xold", "alice", 400), &pool_id.to_hex(), None, 400, 0);
+        let before = deposits[&key].clone();
+        let (_, lp_shares, _) =
+            apply_deposit_credit(&mut deposits, &deposit_info("0xnote", "alice", 100), &pool_id.to_hex(), None, 500, 0);
+
+        let mut tx = CommittedTx {
+            tx_id: miden_objects::transaction::TransactionId::read_from_bytes(&[1; 32]).unwrap(),
+            note_id_hex: "0xnote".to_string(),
+            pool_id,
+            user_account_id: "alice".to_string(),
+            token_id: pool_with_reserves(0, 0).token_a,
+            credited: 100,
+            lp_shares,
+            block_num: Some(100),
+            credited_at: Instant::now(),
+        };
+        let mut lp_queues = LpQueues {
+            mints: vec![queued_mint(&tx.tx_id.to_hex(), lp_faucet, 90), queued_mint("0xother", lp_faucet, 50)],
+            burns: Vec::new(),
+        };
+        let mut notes = vec![tracked_note("0xold", "Consumed"), tracked_note("0xnote", "Consumed")];
+
+        // Not reorg_depth deep yet, then reported discarded
+        assert_eq!(check_committed_tx(&mut tx, None, 105, 10), CommitCheck::Recent);
+        let discarded = TransactionStatus::Discarded(miden_client::transaction::DiscardCause::InputConsumed);
+        assert_eq!(check_committed_tx(&mut tx, Some(&discarded), 105, 10), CommitCheck::Discarded);
+
+        let (entry, mint) = roll_back_deposit(&tx, &mut deposits, &mut notes, &mut lp_queues);
+        let restored = &deposits[&key];
+        assert_eq!(
+            (restored.total_deposited, restored.lp_shares, restored.deposit_count),
+            (before.total_deposited, before.lp_shares, before.deposit_count),
+        );
+        assert_eq!(entry.unwrap().total_deposited, before.total_deposited);
+        assert_eq!(mint.unwrap().lp_amount, 90);
+        assert_eq!(lp_queues.mints.iter().map(|m| m.deposit_tx_id.as_str()).collect::<Vec<_>>(), ["0xother"]);
+        assert_eq!(notes.iter().map(|n| n.status.as_str()).collect::<Vec<_>>(), ["Consumed", "Pending"]);
+    }
+
+    #[test]
+    fn deep_enough_commit_is_final() {
+        let mut tx = CommittedTx {
+            tx_id: miden_objects::transaction::TransactionId::read_from_bytes(&[1; 32]).unwrap(),
+            note_id_hex: "0xnote".to_string(),
+            pool_id: AccountId::from_hex(POOL).unwrap(),
+            user_account_id: "alice".to_string(),
+            token_id: pool_with_reserves(0, 0).token_a,
+            credited: 100,
+            lp_shares: 0,
+            block_num: Some(100),
+            credited_at: Instant::now(),
+        };
+        assert_eq!(check_committed_tx(&mut tx, None, 109, 10), CommitCheck::Recent);
+        assert_eq!(check_committed_tx(&mut tx, None, 110, 10), CommitCheck::Final);
+        // Credited on timeout and not seen committed yet
+        tx.block_num = None;
+        assert_eq!(check_committed_tx(&mut tx, None, 110, 10), CommitCheck::Recent);
+    }
+
+    #[test]
+    fn notes_are_matched_by_the_faucet_of_their_asset() {
+        let pool = pool_with_reserves(0, 0);
+        let deposit = p2id_note_record(USER_ACCOUNT, &pool.pool_id, &pool.token_a, 250);
+        assert!(carries_asset_of(&deposit, AccountId::from_hex(&pool.token_a).unwrap()));
+        assert!(!carries_asset_of(&deposit, AccountId::from_hex(&pool.token_b).unwrap()));
+    }
+
+    /// Notes per minute credited by a virtual-clock run of `notes` deposits
+    /// through one worker: each takes `submit_secs` to prove and submit and
+    /// commits `commit_secs` later. Blocking waits for each commit before the
+    /// next note; split moves on and the tracker credits the commit at its
+    /// next check, every CONFIRMATION_CHECK_INTERVAL_SECS.
+    fn simulated_notes_per_minute(notes: u64, submit_secs: u64, commit_secs: u64, split: bool) -> f64 {
+        let check = CONFIRMATION_CHECK_INTERVAL_SECS;
+        let mut clock = 0;
+        let mut last_credit = 0;
+        for _ in 0..notes {
+            clock += submit_secs;
+            if split {
+                last_credit = last_credit.max((clock + commit_secs).div_ceil(check) * check);
+            } else {
+                clock += commit_secs;
+                last_credit = clock;
             }
         }
+        notes as f64 * 60.0 / last_credit as f64
     }
 
-    Ok(PoolReservesResponse { pools: entries })
+    #[test]
+    fn split_submission_raises_notes_per_minute() {
+        // Testnet-like: about 5s to prove and submit, committed 15s later
+        let blocking = simulated_notes_per_minute(30, 5, 15, false);
+        let split = simulated_notes_per_minute(30, 5, 15, true);
+        println!("notes/minute over 30 deposits: blocking {:.1}, split {:.1}", blocking, split);
+        assert!((blocking - 3.0).abs() < 0.01, "{}", blocking);
+        assert!(split > 10.0, "{}", split);
+    }
 }
@@ -1,41 +1,127 @@
 //! Swap Daemon - Consumes SWAP notes for pool accounts
-//! Runs on port 8080
+//! Runs on port 8080 (milo.toml `[swap_daemon] port`)
 //! Features: TWAP Price Oracle, Dynamic Fee, Auto-Polling
 
 use anyhow::{Context, Result};
 use axum::{
-    extract::{Query, State},
-    http::{header, Method, StatusCode},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{header, HeaderMap, Method, StatusCode},
     response::{IntoResponse, Json},
     routing::{get, post},
     Router,
 };
+use clap::Parser;
+use futures::{SinkExt, StreamExt};
 use miden_client::{
     account::AccountId,
     asset::FungibleAsset,
     builder::ClientBuilder,
     keystore::FilesystemKeyStore,
     note::{create_p2id_note, NoteAttachment, NoteType},
-    rpc::{Endpoint, GrpcClient},
+    rpc::GrpcClient,
     store::{AccountRecordData, InputNoteRecord, TransactionFilter},
-    transaction::{OutputNote, TransactionRequestBuilder},
+    transaction::{OutputNote, TransactionRequest, TransactionRequestBuilder, TransactionScript, TransactionStatus},
 };
+use integration::api_keys::{ApiKeyStore, Permission};
+use integration::helpers::reserve_amount;
+use integration::ledger::{self, Direction, LedgerEntry};
+use integration::maintenance::{Maintenance, SetMaintenanceRequest};
+use integration::milo_accounts::get_faucet_id_by_symbol;
+use milo_registry::TokenRegistry;
+use integration::pool_component::{record_reserves_script, script_for_pool, sync_reserves_script};
+use integration::pool_config::{load_pool_curves_config, load_pools_config, AmmCurve, PoolSettings, POOL_CURVES_CONFIG_PATH};
+use integration::rate_limit::{trusted_proxies, RateLimitConfig, RateLimitLayer, TRUSTED_PROXIES_VAR};
+use integration::startup_config::{log_startup_config, StartupConfig};
+use milo_config::{MiloConfig, Service};
+use integration::shutdown::{shutdown_signal, Shutdown, DRAIN_TIMEOUT_SECS};
+use pool_daemon::circuit_breaker::{CircuitBreakerConfig, CircuitBreakers};
+use pool_daemon::curve::{curve_amount_out, price_impact_bps, spot_price};
+use pool_daemon::metrics::MetricsState;
+use pool_daemon::poll::resolve_poll_interval;
+use pool_daemon::retry::{sync_with_retry, SYNC_BASE_DELAY_MS, SYNC_MAX_ATTEMPTS};
 use miden_client_sqlite_store::ClientBuilderSqliteExt;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fs,
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
     time::{Duration, Instant},
 };
-use tokio::time::sleep;
+use tokio::{
+    sync::{broadcast, Semaphore},
+    time::sleep,
+};
 use tower_http::cors::{Any, CorsLayer};
 
 type MidenClient = miden_client::Client<FilesystemKeyStore>;
 
-const KEYSTORE_PATH: &str = "integration/keystore";
-const STORE_PATH: &str = "integration/swap_store.sqlite3";
+// milo.toml `[swap_daemon]` settings, loaded at the top of main
+static MILO_CONFIG: OnceLock<MiloConfig> = OnceLock::new();
+
+fn milo_config() -> &'static MiloConfig {
+    MILO_CONFIG.get().expect("milo.toml loaded in main")
+}
+
+// Faucet IDs written by `milo setup`, run from integration/
+const ACCOUNTS_PATH: &str = "integration/accounts.json";
+// Store for the client that consumes output notes on users' behalf
+const DELIVERY_STORE_PATH: &str = "integration/delivery_store.sqlite3";
+// Default cap on concurrent transaction submissions (MILO_MAX_INFLIGHT_SUBMISSIONS)
+const DEFAULT_MAX_INFLIGHT_SUBMISSIONS: usize = 4;
+// Default cap on notes processed per pool per cycle (MILO_MAX_NOTES_PER_CYCLE)
+const DEFAULT_MAX_NOTES_PER_CYCLE: usize = 20;
+// Cached consumable-notes lists older than this are re-fetched even if not drained
+const NOTE_CACHE_TTL_SECS: u64 = 120;
+// Default window in which a previous sync is reused instead of syncing again (MILO_SYNC_FRESHNESS_MS)
+const DEFAULT_SYNC_FRESHNESS_MS: u64 = 3000;
+
+// When the worker's client last synced successfully
+struct SyncFreshness {
+    last_sync: Option<Instant>,
+    window: Duration,
+}
+
+impl SyncFreshness {
+    fn is_fresh(&self) -> bool {
+        self.last_sync.is_some_and(|t| t.elapsed() < self.window)
+    }
+
+    // Age of the local state in ms (None before the first successful sync)
+    fn state_age_ms(&self) -> Option<u64> {
+        self.last_sync.map(|t| t.elapsed().as_millis() as u64)
+    }
+}
+
+/// Sync unless the last successful sync is inside the freshness window.
+/// `force` always syncs. Returns whether a sync ran.
+async fn sync_if_stale(client: &mut MidenClient, freshness: &mut SyncFreshness, force: bool) -> Result<bool> {
+    if !force && freshness.is_fresh() {
+        return Ok(false);
+    }
+    sync_with_retry(client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await?;
+    freshness.last_sync = Some(Instant::now());
+    Ok(true)
+}
+
+// Consumable notes fetched for one pool, paged through across poll cycles
+struct NoteBatch {
+    notes: VecDeque<InputNoteRecord>,
+    fetched_at: Instant,
+}
+
+// Per-pool note batches plus the per-cycle processing cap
+struct NoteCache {
+    batches: HashMap<AccountId, NoteBatch>,
+    max_per_cycle: usize,
+}
 
 // Tracked notes
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +139,254 @@ struct PricePoint {
     price: f64,
     reserve_a: u64,
     reserve_b: u64,
+    // amount_in * 10000 / (reserve_in + amount_in) of the swap; lets analytics
+    // filter outlier trades. 0 for points recorded before it was tracked.
+    #[serde(default)]
+    price_impact_bps: u64,
+}
+
+// Messages a slow /ws client may fall behind by before it skips ahead
+const WS_BROADCAST_CAPACITY: usize = 256;
+const WS_PING_INTERVAL_SECS: u64 = 30;
+
+/// /ws message for a committed swap
+fn trade_event(point: &PricePoint, amount_in: u64, amount_out: u64) -> String {
+    serde_json::json!({
+        "event": "trade",
+        "pool_id": point.pool_id,
+        "price": point.price,
+        "amount_in": amount_in,
+        "amount_out": amount_out,
+        "timestamp": point.timestamp,
+    })
+    .to_string()
+}
+
+// In-memory TWAP window; older points stay only in the price_history table
+const PRICE_HISTORY_WINDOW_SECS: u64 = 86400;
+// Rows older than this are pruned from the price_history table
+const PRICE_HISTORY_RETENTION_SECS: u64 = 7 * 86400;
+
+/// Open the swap store and create the price history and fee tables if this
+/// database predates them. The miden client owns the rest of the file.
+fn open_price_history_db() -> rusqlite::Result<rusqlite::Connection> {
+    let conn = rusqlite::Connection::open(&milo_config().store_path)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS price_history (
+             timestamp INTEGER NOT NULL,
+             pool_id   TEXT NOT NULL,
+             price     REAL NOT NULL,
+             reserve_a INTEGER NOT NULL,
+             reserve_b INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_price_history_pool_ts ON price_history (pool_id, timestamp);
+         CREATE TABLE IF NOT EXISTS swap_fees (
+             tx_id      TEXT PRIMARY KEY,
+             timestamp  INTEGER NOT NULL,
+             pool_id    TEXT NOT NULL,
+             token_in   TEXT NOT NULL,
+             amount_in  INTEGER NOT NULL,
+             fee_amount INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_swap_fees_pool_ts ON swap_fees (pool_id, timestamp);
+         CREATE TABLE IF NOT EXISTS pool_fees (
+             pool_id               TEXT NOT NULL,
+             token_id              TEXT NOT NULL,
+             total_fees_lifetime   INTEGER NOT NULL,
+             total_volume_lifetime INTEGER NOT NULL,
+             swap_count            INTEGER NOT NULL,
+             PRIMARY KEY (pool_id, token_id)
+         );",
+    )?;
+    // Tables from before price impact tracking lack the column
+    let has_price_impact: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('price_history') WHERE name = 'price_impact_bps'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_price_impact {
+        conn.execute_batch("ALTER TABLE price_history ADD COLUMN price_impact_bps INTEGER NOT NULL DEFAULT 0;")?;
+    }
+    Ok(conn)
+}
+
+/// Price points from the last 24 hours, oldest first
+fn load_price_history() -> Vec<PricePoint> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let cutoff = now.saturating_sub(PRICE_HISTORY_WINDOW_SECS);
+    let loaded = open_price_history_db().and_then(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, pool_id, price, reserve_a, reserve_b, price_impact_bps FROM price_history
+             WHERE timestamp >= ?1 ORDER BY timestamp",
+        )?;
+        let rows = stmt.query_map([cutoff as i64], |row| {
+            Ok(PricePoint {
+                timestamp: row.get::<_, i64>(0)? as u64,
+                pool_id: row.get(1)?,
+                price: row.get(2)?,
+                reserve_a: row.get::<_, i64>(3)? as u64,
+                reserve_b: row.get::<_, i64>(4)? as u64,
+                price_impact_bps: row.get::<_, i64>(5)? as u64,
+            })
+        })?;
+        rows.collect()
+    });
+    match loaded {
+        Ok(points) => points,
+        Err(e) => {
+            println!("⚠️  Failed to load price history: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Persist a committed swap's price point and prune expired rows
+fn save_price_point(point: &PricePoint) {
+    let cutoff = point.timestamp.saturating_sub(PRICE_HISTORY_RETENTION_SECS);
+    let saved = open_price_history_db().and_then(|conn| {
+        conn.execute(
+            "INSERT INTO price_history (timestamp, pool_id, price, reserve_a, reserve_b, price_impact_bps)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                point.timestamp as i64, point.pool_id, point.price,
+                point.reserve_a as i64, point.reserve_b as i64, point.price_impact_bps as i64,
+            ],
+        )?;
+        conn.execute("DELETE FROM price_history WHERE timestamp < ?1", [cutoff as i64])
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist price point: {}", e);
+    }
+}
+
+/// Remove a price point whose swap was rolled back by a reorg
+fn delete_price_point(point: &PricePoint) {
+    let deleted = open_price_history_db().and_then(|conn| {
+        conn.execute(
+            "DELETE FROM price_history WHERE timestamp = ?1 AND pool_id = ?2 AND price = ?3",
+            rusqlite::params![point.timestamp as i64, point.pool_id, point.price],
+        )
+    });
+    if let Err(e) = deleted {
+        println!("⚠️  Failed to delete price point: {}", e);
+    }
+}
+
+// Fee charged by one committed swap, in its input token
+struct SwapFee<'a> {
+    tx_id: &'a str,
+    timestamp: u64,
+    pool_id: &'a str,
+    token_in: &'a str,
+    amount_in: u64,
+    fee_amount: u64,
+}
+
+/// Log a committed swap's fee (pruned like price_history) and add it to the
+/// pool's lifetime totals, in one transaction
+fn save_swap_fee(fee: &SwapFee) {
+    let cutoff = fee.timestamp.saturating_sub(PRICE_HISTORY_RETENTION_SECS);
+    let saved = open_price_history_db().and_then(|mut conn| {
+        let tx = conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO swap_fees (tx_id, timestamp, pool_id, token_in, amount_in, fee_amount)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![fee.tx_id, fee.timestamp as i64, fee.pool_id, fee.token_in, fee.amount_in as i64, fee.fee_amount as i64],
+        )?;
+        if inserted > 0 {
+            tx.execute(
+                "INSERT INTO pool_fees (pool_id, token_id, total_fees_lifetime, total_volume_lifetime, swap_count)
+                 VALUES (?1, ?2, ?3, ?4, 1)
+                 ON CONFLICT (pool_id, token_id) DO UPDATE SET
+                     total_fees_lifetime = total_fees_lifetime + excluded.total_fees_lifetime,
+                     total_volume_lifetime = total_volume_lifetime + excluded.total_volume_lifetime,
+                     swap_count = swap_count + 1",
+                rusqlite::params![fee.pool_id, fee.token_in, fee.fee_amount as i64, fee.amount_in as i64],
+            )?;
+        }
+        tx.execute("DELETE FROM swap_fees WHERE timestamp < ?1", [cutoff as i64])?;
+        tx.commit()
+    });
+    if let Err(e) = saved {
+        println!("⚠️  Failed to persist swap fee: {}", e);
+    }
+}
+
+/// Take a swap rolled back by a reorg out of the fee log and lifetime totals
+fn delete_swap_fee(tx_id: &str) {
+    let deleted = open_price_history_db().and_then(|mut conn| {
+        let tx = conn.transaction()?;
+        let row = tx
+            .query_row(
+                "SELECT pool_id, token_in, amount_in, fee_amount FROM swap_fees WHERE tx_id = ?1",
+                [tx_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, i64>(3)?)),
+            )
+            .optional()?;
+        if let Some((pool_id, token_in, amount_in, fee_amount)) = row {
+            tx.execute("DELETE FROM swap_fees WHERE tx_id = ?1", [tx_id])?;
+            tx.execute(
+                "UPDATE pool_fees SET
+                     total_fees_lifetime = MAX(total_fees_lifetime - ?3, 0),
+                     total_volume_lifetime = MAX(total_volume_lifetime - ?4, 0),
+                     swap_count = MAX(swap_count - 1, 0)
+                 WHERE pool_id = ?1 AND token_id = ?2",
+                rusqlite::params![pool_id, token_in, fee_amount, amount_in],
+            )?;
+        }
+        tx.commit()
+    });
+    if let Err(e) = deleted {
+        println!("⚠️  Failed to delete swap fee: {}", e);
+    }
+}
+
+// Fees a pool collected in one input token, in that token's base units
+#[derive(Debug, Default, Serialize)]
+struct TokenFees {
+    total_fees_lifetime: u64,
+    total_volume_lifetime: u64,
+    swap_count: u64,
+    fees_24h: u64,
+    fees_7d: u64,
+}
+
+/// A pool's fees keyed by input token ID
+fn load_pool_fees(pool_id: &str, now: u64) -> rusqlite::Result<BTreeMap<String, TokenFees>> {
+    let conn = open_price_history_db()?;
+    let mut fees = BTreeMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT token_id, total_fees_lifetime, total_volume_lifetime, swap_count FROM pool_fees WHERE pool_id = ?1",
+    )?;
+    let rows = stmt.query_map([pool_id], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64, row.get::<_, i64>(3)? as u64))
+    })?;
+    for row in rows {
+        let (token, total_fees_lifetime, total_volume_lifetime, swap_count) = row?;
+        fees.insert(token, TokenFees { total_fees_lifetime, total_volume_lifetime, swap_count, ..Default::default() });
+    }
+    let mut stmt = conn.prepare(
+        "SELECT token_in,
+                COALESCE(SUM(CASE WHEN timestamp >= ?2 THEN fee_amount END), 0),
+                COALESCE(SUM(fee_amount), 0)
+         FROM swap_fees WHERE pool_id = ?1 AND timestamp >= ?3 GROUP BY token_in",
+    )?;
+    let cutoff_24h = now.saturating_sub(86400) as i64;
+    let cutoff_7d = now.saturating_sub(PRICE_HISTORY_RETENTION_SECS) as i64;
+    let rows = stmt.query_map(rusqlite::params![pool_id, cutoff_24h, cutoff_7d], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64))
+    })?;
+    for row in rows {
+        let (token, fees_24h, fees_7d) = row?;
+        let entry = fees.entry(token).or_default();
+        entry.fees_24h = fees_24h;
+        entry.fees_7d = fees_7d;
+    }
+    Ok(fees)
 }
 
 // Shared state
@@ -61,23 +395,134 @@ struct AppState {
     tracked_notes: Arc<Mutex<Vec<TrackedNote>>>,
     swap_info_map: Arc<Mutex<HashMap<String, SwapInfo>>>,
     pool_ids: Arc<Vec<AccountId>>,
-    consume_tx: Arc<std::sync::mpsc::Sender<ConsumeRequest>>,
+    // Curve and price impact cap of each pool (pool_config.json; constant
+    // product and DEFAULT_MAX_PRICE_IMPACT_BPS by default)
+    pool_settings: Arc<HashMap<AccountId, PoolSettings>>,
+    worker_tx: Arc<std::sync::mpsc::Sender<WorkerRequest>>,
     price_history: Arc<Mutex<Vec<PricePoint>>>,
     limit_orders: Arc<Mutex<Vec<LimitOrder>>>,
+    // Faucet IDs held by each pool's vault, keyed by pool ID (hex)
+    pool_tokens: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    // Every submit_new_transaction holds a permit; bounds in-flight submissions
+    submit_permits: Arc<Semaphore>,
+    max_inflight_submissions: usize,
+    // Committed swaps later found discarded (rolled back by the confirmation tracker)
+    reorgs_detected: Arc<AtomicU64>,
+    // Per-pool counters and gauges for /metrics, updated by the worker
+    metrics: Arc<Mutex<MetricsState>>,
+    // Pools whose price moved too far too fast; swaps on them are paused
+    circuit_breakers: Arc<CircuitBreakers>,
+    // Bot API keys (managed by the liquidity daemon's admin endpoints)
+    api_keys: Arc<ApiKeyStore>,
+    // Auto-consume sessions and the swap output notes they cover
+    consume_sessions: Arc<Mutex<Vec<ConsumeSession>>>,
+    output_notes: Arc<Mutex<HashMap<String, OutputNoteRecord>>>,
+    // Auto-consume is only offered when a delegated keystore is configured
+    delivery_enabled: bool,
+    // Bearer token for POST /maintenance (MILO_ADMIN_TOKEN); disabled when unset
+    admin_token: Option<Arc<String>>,
+    // Set via POST /maintenance; rejects writes and pauses auto-poll
+    maintenance: Maintenance,
+    // Set on Ctrl-C/SIGTERM; counts requests the worker still has to answer
+    shutdown: Arc<Shutdown>,
+    // Seconds between the worker's auto-polls
+    poll_interval_secs: u64,
+    // Trade events for /ws clients; each connection subscribes its own receiver
+    broadcast_tx: broadcast::Sender<String>,
+}
+
+impl AppState {
+    fn settings(&self, pool_id: AccountId) -> PoolSettings {
+        self.pool_settings.get(&pool_id).copied().unwrap_or_default()
+    }
+
+    fn curve(&self, pool_id: AccountId) -> AmmCurve {
+        self.settings(pool_id).curve
+    }
+
+    /// Hand a request to the worker, counting it as queued and in flight
+    /// until answered. False when the worker is gone.
+    fn send_to_worker(&self, req: WorkerRequest) -> bool {
+        self.metrics.lock().unwrap().enqueued();
+        self.shutdown.begin();
+        if self.worker_tx.send(req).is_err() {
+            self.metrics.lock().unwrap().dequeued();
+            self.shutdown.cancel();
+            return false;
+        }
+        true
+    }
 }
 
 struct ConsumeRequest {
     pool_id_opt: Option<String>,
+    // Sync even if the last sync is within the freshness window
+    force_sync: bool,
     swap_info_map: Arc<Mutex<HashMap<String, SwapInfo>>>,
     reply: tokio::sync::oneshot::Sender<Result<ConsumeResponse, String>>,
 }
 
+struct ReservesRequest {
+    pool_id: AccountId,
+    // (faucet ID, amount) per vault asset and the age of the state they were read from
+    reply: tokio::sync::oneshot::Sender<Result<(Vec<(AccountId, u64)>, Option<u64>), String>>,
+}
+
+// Worker message enum - consume or read a pool's reserves
+enum WorkerRequest {
+    Consume(ConsumeRequest),
+    Reserves(ReservesRequest),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ConsumeResponse {
+    // Notes submitted this cycle; commitment is tracked by the confirmation tracker
     consumed: usize,
     pool_id: Option<String>,
+    // Age of the state swaps were priced from, in ms
+    state_age_ms: Option<u64>,
+}
+
+// Submitted transaction awaiting commitment - bookkeeping runs once it commits
+struct PendingTx {
+    tx_id: miden_protocol::transaction::TransactionId,
+    pool_id: AccountId,
+    note_id_hex: String,
+    submitted_at: Instant,
+    effect: PendingEffect,
+}
+
+// Post-commit bookkeeping for a pending transaction
+enum PendingEffect {
+    Swap {
+        price_point: PricePoint,
+        limit_order_id: Option<String>,
+        output_note_id: String,
+        amount_out: u64,
+        fee_amount: u64,
+    },
+    Consume,
+}
+
+// Committed swap kept for re-verification until it is reorg_depth blocks deep.
+// Holds what is needed to roll its bookkeeping back if it gets discarded.
+struct CommittedTx {
+    tx_id: miden_protocol::transaction::TransactionId,
+    note_id_hex: String,
+    block_num: u32,
+    swap_info: Option<SwapInfo>,
+    price_point: PricePoint,
+    limit_order_id: Option<String>,
+    output_note_id: String,
+    amount_out: u64,
+    fee_amount: u64,
 }
 
+const CONFIRMATION_TIMEOUT_SECS: u64 = 60;
+const CONFIRMATION_CHECK_INTERVAL_SECS: u64 = 2;
+// Default number of blocks a commit is re-verified for (MILO_REORG_DEPTH_BLOCKS)
+const DEFAULT_REORG_DEPTH_BLOCKS: u32 = 10;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct TrackNoteRequest {
     note_id: String,
@@ -97,9 +542,97 @@ struct SwapInfo {
     min_amount_out: String,
     user_account_id: String,
     timestamp: u64,
+    // Routed swaps: the first leg pays its output to this pool instead of the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_hop: Option<RouteHop>,
+    // Second leg of a routed swap: the input came from the first leg's pool,
+    // not from the user's wallet
+    #[serde(default)]
+    routed: bool,
+}
+
+impl SwapInfo {
+    /// Client-supplied swap info can't set routing; only /swap_route does
+    fn without_route(mut self) -> Self {
+        self.next_hop = None;
+        self.routed = false;
+        self
+    }
+}
+
+// Second leg of a routed swap, carried on the first leg's SwapInfo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RouteHop {
+    pool_account_id: String,
+    buy_token_id: String,
+    min_amount_out: String,
+}
+
+// One leg of a swap route
+#[derive(Debug, Clone, Serialize)]
+struct RouteLeg {
+    pool_id: String,
+    token_in: String,
+    token_out: String,
+}
+
+// Query params for route discovery
+#[derive(Debug, Deserialize)]
+struct RouteQuery {
+    // Symbol (MILO) or faucet ID hex
+    sell: String,
+    buy: String,
+    amount: Option<u64>,
+}
+
+// Body of POST /swap_route; the note must already be sent to the route's first pool
+#[derive(Debug, Deserialize)]
+struct SwapRouteRequest {
+    note_id: String,
+    sell_token: String,
+    buy_token: String,
+    amount_in: u64,
+    min_amount_out: u64,
+    // Optional with an API key (the key's account is used)
+    user_account_id: Option<String>,
 }
 
 // Limit Orders
+
+/// When a pending order fires, relative to its `min_amount_out`. Both sell
+/// the same `amount_in`; they differ in which side of the threshold the
+/// current quote has to be on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+enum OrderType {
+    /// Take profit: fires once selling `amount_in` would return at least
+    /// `min_amount_out`, i.e. the price has risen to the target
+    #[default]
+    Limit,
+    /// Protective sell: fires once selling `amount_in` would return at most
+    /// `min_amount_out`, i.e. the price has fallen to the stop. The fill can
+    /// be below the stop; the swap's own `min_amount_out` bounds how far.
+    StopLoss,
+}
+
+impl OrderType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().replace(['_', '-'], "").as_str() {
+            "limit" => Some(OrderType::Limit),
+            "stoploss" => Some(OrderType::StopLoss),
+            _ => None,
+        }
+    }
+
+    /// Whether a quote of `potential_output` triggers the order
+    fn is_triggered(self, potential_output: u64, min_amount_out: u64) -> bool {
+        match self {
+            OrderType::Limit => potential_output >= min_amount_out,
+            OrderType::StopLoss => potential_output <= min_amount_out,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct LimitOrder {
     order_id: String,
@@ -111,9 +644,11 @@ struct LimitOrder {
     amount_in: u64,
     target_price: f64,
     min_amount_out: u64,
+    #[serde(default)]
+    order_type: OrderType,
     created_at: u64,
     expires_at: u64,
-    status: String, // Pending, Filled, Expired, Cancelled
+    status: String, // Pending, Submitted, Filled, Expired, Cancelled
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -126,13 +661,17 @@ struct CreateLimitOrderRequest {
     amount_in: String,
     target_price: f64,
     min_amount_out: String,
+    // "Limit" (default) or "StopLoss"
+    #[serde(default)]
+    order_type: Option<String>,
     expires_in_secs: u64,
     swap_info: SwapInfo,
 }
 
 #[derive(Debug, Deserialize)]
 struct LimitOrdersQuery {
-    user_id: String,
+    // Optional with an API key (defaults to the key's account)
+    user_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -140,6 +679,155 @@ struct CancelOrderRequest {
     order_id: String,
 }
 
+// Auto-consume sessions: a user lets the daemon consume swap output notes
+// addressed to their account, for a bounded time and number of notes.
+// Only usable when the account's key is in the delegated keystore.
+const CONSUME_SESSIONS_FILE: &str = "consume_sessions.json";
+const DEFAULT_SESSION_TTL_SECS: u64 = 3600;
+const MAX_SESSION_TTL_SECS: u64 = 86400;
+const DEFAULT_SESSION_MAX_NOTES: u32 = 20;
+const MAX_SESSION_MAX_NOTES: u32 = 100;
+// How often the worker tries to deliver output notes
+const DELIVERY_INTERVAL_SECS: u64 = 5;
+// Consume attempts per output note before it is marked Failed
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+// Output note records are kept this long
+const OUTPUT_NOTE_RETENTION_SECS: u64 = 86400;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConsumeSession {
+    session_id: String,
+    user_account_id: String,
+    created_at: u64,
+    expires_at: u64,
+    max_notes: u32,
+    notes_consumed: u32,
+    #[serde(default)]
+    revoked_at: Option<u64>,
+    // Why the daemon ended the session itself (e.g. key not in the delegated keystore)
+    #[serde(default)]
+    end_reason: Option<String>,
+}
+
+impl ConsumeSession {
+    fn is_active(&self, now: u64) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at && self.notes_consumed < self.max_notes
+    }
+}
+
+/// The account's active session, if any
+fn active_session_mut<'a>(
+    sessions: &'a mut [ConsumeSession],
+    user_account_id: &str,
+    now: u64,
+) -> Option<&'a mut ConsumeSession> {
+    sessions.iter_mut().find(|s| s.user_account_id.eq_ignore_ascii_case(user_account_id) && s.is_active(now))
+}
+
+/// A session for `user_account_id` starting at `now`; TTL and note count
+/// default when not given and are clamped to their bounds
+fn new_consume_session(
+    session_id: String,
+    user_account_id: String,
+    ttl_secs: Option<u64>,
+    max_notes: Option<u32>,
+    now: u64,
+) -> ConsumeSession {
+    let ttl_secs = ttl_secs.unwrap_or(DEFAULT_SESSION_TTL_SECS).clamp(1, MAX_SESSION_TTL_SECS);
+    let max_notes = max_notes.unwrap_or(DEFAULT_SESSION_MAX_NOTES).clamp(1, MAX_SESSION_MAX_NOTES);
+    ConsumeSession {
+        session_id,
+        user_account_id,
+        created_at: now,
+        expires_at: now + ttl_secs,
+        max_notes,
+        notes_consumed: 0,
+        revoked_at: None,
+        end_reason: None,
+    }
+}
+
+/// Add a session; one active session per account, so a new one replaces the old
+fn open_consume_session(sessions: &mut Vec<ConsumeSession>, session: ConsumeSession, now: u64) {
+    if let Some(existing) = active_session_mut(sessions, &session.user_account_id, now) {
+        existing.revoked_at = Some(now);
+        existing.end_reason = Some("Replaced by a new session".to_string());
+    }
+    sessions.push(session);
+}
+
+/// Revoke a session that isn't revoked yet. With `owner` set (an API-key
+/// caller) only that account's sessions can be revoked.
+fn revoke_consume_session(
+    sessions: &mut [ConsumeSession],
+    session_id: &str,
+    owner: Option<&str>,
+    now: u64,
+) -> Option<ConsumeSession> {
+    let session = sessions.iter_mut().find(|s| {
+        s.session_id == session_id
+            && s.revoked_at.is_none()
+            && owner.is_none_or(|owner| s.user_account_id.eq_ignore_ascii_case(owner))
+    })?;
+    session.revoked_at = Some(now);
+    Some(session.clone())
+}
+
+fn load_consume_sessions() -> Vec<ConsumeSession> {
+    match fs::read_to_string(CONSUME_SESSIONS_FILE) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn save_consume_sessions(sessions: &[ConsumeSession]) {
+    let data = serde_json::to_string_pretty(sessions).unwrap_or_default();
+    let _ = fs::write(CONSUME_SESSIONS_FILE, data);
+}
+
+// P2ID output note created by a committed swap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OutputNoteRecord {
+    note_id: String,
+    swap_note_id: String,
+    pool_id: String,
+    user_account_id: String,
+    created_at: u64,
+    status: String, // Sent, Delivering, Delivered, Failed, RolledBack
+    session_id: Option<String>,
+    consume_tx_id: Option<String>,
+    attempts: u32,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateSessionRequest {
+    // Optional with an API key (the key's account is used)
+    user_account_id: Option<String>,
+    ttl_secs: Option<u64>,
+    max_notes: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RevokeSessionRequest {
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserQuery {
+    // Optional with an API key (defaults to the key's account)
+    user_id: Option<String>,
+}
+
+// Second client, on the delegated keystore, that consumes output notes as the user
+struct DeliveryClient {
+    client: MidenClient,
+    // User accounts imported into the delivery store
+    imported: HashSet<AccountId>,
+    // In-flight consume txs: output note ID -> tx ID
+    pending: HashMap<String, miden_protocol::transaction::TransactionId>,
+}
+
 // Query params for TWAP endpoint
 #[derive(Debug, Deserialize)]
 struct TwapQuery {
@@ -160,37 +848,164 @@ struct CurrentFeeQuery {
     pool_id: String,
 }
 
+// Query params for the two-way quote endpoint
+#[derive(Debug, Deserialize)]
+struct QuoteBothQuery {
+    pool_id: String,
+    amount: u64,
+}
+
+// Query params for single-direction quote endpoint
+#[derive(Debug, Deserialize)]
+struct QuoteQuery {
+    pool_id: String,
+    sell_token: String,
+    amount: u64,
+}
+
+// Query params for fee model endpoint
+#[derive(Debug, Deserialize)]
+struct FeeModelQuery {
+    std_dev: Option<f64>,
+}
+
+// Dynamic fee tiers: (exclusive std_dev upper bound, fee bps, label)
+const FEE_TIERS: [(f64, u64, &str); 3] = [
+    (0.001, 5, "low"),
+    (0.01, 10, "normal"),
+    (f64::INFINITY, 30, "high"),
+];
+// Fee used until a pool has enough price history to measure volatility
+const DEFAULT_FEE_BPS: u64 = 10;
+// Number of most recent price points used for the volatility measure
+const FEE_VOLATILITY_WINDOW: usize = 10;
+
+#[derive(Parser, Debug)]
+#[command(name = "swap-daemon", about = "Consumes swap notes for the Milo pools")]
+struct Cli {
+    /// Seconds between auto-polls (5-300); overrides MILO_POLL_INTERVAL_SECS and milo.toml
+    #[arg(long)]
+    poll_interval: Option<u64>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    println!("🚀 Swap Daemon starting on port 8080...\n");
+    let cli = Cli::parse();
+    // Keystore, store, node, port and poll interval; set once, read everywhere
+    MILO_CONFIG.set(MiloConfig::load(Service::SwapDaemon)?).expect("milo.toml loaded once");
+    // Token symbols resolve to the faucets of the last `milo setup`
+    TokenRegistry::load(ACCOUNTS_PATH)?.install();
+    let port = milo_config().port;
+    println!("🚀 Swap Daemon starting on port {}...\n", port);
+    let (poll_interval_secs, poll_interval_source) = resolve_poll_interval(cli.poll_interval, milo_config());
 
     // Load pool IDs
-    let pools_json = fs::read_to_string("pools.json")
-        .context("pools.json not found")?;
-    let pools: serde_json::Value = serde_json::from_str(&pools_json)?;
+    let pools = load_pools_config("pools.json")?;
 
-    let milo_pool_id = AccountId::from_hex(pools["milo_musdc_pool_id"].as_str().unwrap())?;
-    let melo_pool_id = AccountId::from_hex(pools["melo_musdc_pool_id"].as_str().unwrap())?;
+    let milo_pool_id = pools.require_pool_id("milo_musdc")?;
+    let melo_pool_id = pools.require_pool_id("melo_musdc")?;
 
     let pool_ids = vec![milo_pool_id, melo_pool_id];
-
-    println!("📋 Monitoring pools:");
-    println!("   - MILO/MUSDC: {}", milo_pool_id.to_hex());
-    println!("   - MELO/MUSDC: {}", melo_pool_id.to_hex());
-    println!();
+    let curves_config = load_pool_curves_config(POOL_CURVES_CONFIG_PATH, &pools)?;
+    let pool_settings = curves_config.settings_by_pool_id(&pools);
 
     // Shared state - create before worker thread
     let swap_info_map: Arc<Mutex<HashMap<String, SwapInfo>>> = Arc::new(Mutex::new(HashMap::new()));
-    let price_history: Arc<Mutex<Vec<PricePoint>>> = Arc::new(Mutex::new(Vec::new()));
+    let price_history: Arc<Mutex<Vec<PricePoint>>> = Arc::new(Mutex::new(load_price_history()));
+    println!("📈 Loaded {} price point(s) from the last 24h", price_history.lock().unwrap().len());
     let limit_orders: Arc<Mutex<Vec<LimitOrder>>> = Arc::new(Mutex::new(Vec::new()));
+    let pool_tokens: Arc<Mutex<HashMap<String, Vec<String>>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let max_inflight_submissions = std::env::var("MILO_MAX_INFLIGHT_SUBMISSIONS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_INFLIGHT_SUBMISSIONS);
+    let submit_permits = Arc::new(Semaphore::new(max_inflight_submissions));
+
+    let max_notes_per_cycle = std::env::var("MILO_MAX_NOTES_PER_CYCLE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_NOTES_PER_CYCLE);
+
+    let reorg_depth = std::env::var("MILO_REORG_DEPTH_BLOCKS")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_REORG_DEPTH_BLOCKS);
+    let reorgs_detected = Arc::new(AtomicU64::new(0));
+    let metrics: Arc<Mutex<MetricsState>> = Arc::new(Mutex::new(MetricsState::default()));
+    let breaker_config = CircuitBreakerConfig::from_env();
+    let circuit_breakers = Arc::new(CircuitBreakers::new(breaker_config));
+
+    let sync_freshness_window = Duration::from_millis(
+        std::env::var("MILO_SYNC_FRESHNESS_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SYNC_FRESHNESS_MS),
+    );
+
+    // Keystore holding keys of (test) wallets whose output notes may be auto-consumed
+    let delegated_keystore = std::env::var("MILO_DELEGATED_KEYSTORE").ok().filter(|p| !p.is_empty());
+    let delivery_enabled = delegated_keystore.is_some();
+    let consume_sessions: Arc<Mutex<Vec<ConsumeSession>>> = Arc::new(Mutex::new(load_consume_sessions()));
+    let output_notes: Arc<Mutex<HashMap<String, OutputNoteRecord>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let maintenance = Maintenance::from_env();
+
+    let mut config = StartupConfig::new("swap-daemon");
+    config
+        .milo("port", format!("127.0.0.1:{}", port), milo_config())
+        .milo("rpc_endpoint", &milo_config().rpc_endpoint, milo_config())
+        .milo("keystore_path", &milo_config().keystore_path, milo_config())
+        .milo("store_path", &milo_config().store_path, milo_config())
+        .constant("delivery_store", DELIVERY_STORE_PATH)
+        .constant("ledger", ledger::SWAP_LEDGER_FILE)
+        .constant("accounts", ACCOUNTS_PATH)
+        .constant("consume_sessions_file", CONSUME_SESSIONS_FILE)
+        .pools("pools.json", &pools)
+        .constant("monitored_pools", "milo_musdc, melo_musdc")
+        .env("MILO_MAX_INFLIGHT_SUBMISSIONS", max_inflight_submissions)
+        .env("MILO_MAX_NOTES_PER_CYCLE", max_notes_per_cycle)
+        .env("MILO_REORG_DEPTH_BLOCKS", reorg_depth)
+        .env("MILO_SYNC_FRESHNESS_MS", sync_freshness_window.as_millis())
+        .env("MILO_CIRCUIT_BREAKER_THRESHOLD_PCT", breaker_config.threshold_pct)
+        .env("MILO_CIRCUIT_BREAKER_WINDOW_SECS", breaker_config.window_secs)
+        .env("MILO_CIRCUIT_BREAKER_COOLDOWN_SECS", breaker_config.cooldown_secs)
+        .env("MILO_DELEGATED_KEYSTORE", delegated_keystore.as_deref().unwrap_or("(auto-consume disabled)"))
+        .env("MILO_MAINTENANCE", if maintenance.is_enabled() { "on" } else { "off" })
+        .env(TRUSTED_PROXIES_VAR, trusted_proxies().iter().map(ToString::to_string).collect::<Vec<_>>().join(","))
+        .secret("MILO_ADMIN_TOKEN")
+        .record("MILO_POLL_INTERVAL_SECS", poll_interval_secs, poll_interval_source);
+    for entry in &pools.pools {
+        let settings = curves_config.settings(&entry.pair);
+        config.file(&format!("curve.{}", entry.pair), POOL_CURVES_CONFIG_PATH, settings.curve);
+        config.file(&format!("max_price_impact_bps.{}", entry.pair), POOL_CURVES_CONFIG_PATH, settings.max_price_impact_bps);
+    }
+    log_startup_config(&config);
 
     // Initialize client in worker thread
-    let (consume_tx, consume_rx) = std::sync::mpsc::channel::<ConsumeRequest>();
+    let (worker_tx, worker_rx) = std::sync::mpsc::channel::<WorkerRequest>();
     let swap_info_map_worker = swap_info_map.clone();
     let price_history_worker = price_history.clone();
     let limit_orders_worker = limit_orders.clone();
-
-    std::thread::spawn(move || {
+    let pool_tokens_worker = pool_tokens.clone();
+    let pool_ids_worker = pool_ids.clone();
+    let pool_settings = Arc::new(pool_settings);
+    let pool_settings_worker = pool_settings.clone();
+    let submit_permits_worker = submit_permits.clone();
+    let reorgs_detected_worker = reorgs_detected.clone();
+    let metrics_worker = metrics.clone();
+    let circuit_breakers_worker = circuit_breakers.clone();
+    let consume_sessions_worker = consume_sessions.clone();
+    let output_notes_worker = output_notes.clone();
+    let maintenance_worker = maintenance.clone();
+    let shutdown = Arc::new(Shutdown::default());
+    let shutdown_worker = shutdown.clone();
+    let (broadcast_tx, _) = broadcast::channel::<String>(WS_BROADCAST_CAPACITY);
+    let broadcast_tx_worker = broadcast_tx.clone();
+
+    let worker = std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
             // Initialize client
@@ -206,37 +1021,70 @@ async fn main() -> Result<()> {
 
             // Import pool accounts from network and sync state
             println!("🔄 Importing pool accounts and syncing...");
-            if let Ok(pools_data) = fs::read_to_string("pools.json") {
-                if let Ok(pools_val) = serde_json::from_str::<serde_json::Value>(&pools_data) {
-                    for key in &["milo_musdc_pool_id", "melo_musdc_pool_id"] {
-                        if let Some(id_hex) = pools_val[key].as_str() {
-                            if let Ok(pool_id) = AccountId::from_hex(id_hex) {
-                                match client.import_account_by_id(pool_id).await {
-                                    Ok(_) => println!("   ✅ Pool {} imported", id_hex),
-                                    Err(e) => println!("   ⚠️  Pool {} import failed: {:?}", id_hex, e),
-                                }
-                            }
-                        }
-                    }
+            for pool_id in &pool_ids_worker {
+                match client.import_account_by_id(*pool_id).await {
+                    Ok(_) => println!("   ✅ Pool {} imported", pool_id.to_hex()),
+                    Err(e) => println!("   ⚠️  Pool {} import failed: {:?}", pool_id.to_hex(), e),
                 }
             }
-            match client.sync_state().await {
+            let mut sync_freshness = SyncFreshness { last_sync: None, window: sync_freshness_window };
+            match sync_if_stale(&mut client, &mut sync_freshness, true).await {
                 Ok(_) => println!("   ✅ State synced"),
                 Err(e) => println!("   ⚠️  Sync error: {:?}", e),
             }
 
+            // Record which tokens each pool holds so /track_note can validate pairs
+            refresh_pool_tokens(&mut client, &pool_ids_worker, &pool_tokens_worker).await;
+
             let mut last_poll = Instant::now();
+            let mut last_confirmation_check = Instant::now();
+            let mut pending_txs: Vec<PendingTx> = Vec::new();
+            let mut committed_txs: Vec<CommittedTx> = Vec::new();
+            let mut note_cache = NoteCache {
+                batches: HashMap::new(),
+                max_per_cycle: max_notes_per_cycle,
+            };
+
+            let mut delivery_client = match delegated_keystore {
+                Some(ref path) => match init_delivery_client(path).await {
+                    Ok(client) => {
+                        println!("   ✅ Delivery client ready (delegated keystore: {})", path);
+                        Some(DeliveryClient { client, imported: HashSet::new(), pending: HashMap::new() })
+                    }
+                    Err(e) => {
+                        println!("   ⚠️  Delivery client unavailable, auto-consume disabled: {:?}", e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            let mut last_delivery = Instant::now();
 
-            // Non-blocking event loop: HTTP requests + auto-poll
+            // Non-blocking event loop: HTTP requests + auto-poll + confirmation tracking
             loop {
-                // Check for HTTP-triggered consume requests (non-blocking)
-                match consume_rx.try_recv() {
-                    Ok(req) => {
+                // Check for HTTP-triggered requests (non-blocking)
+                let request = worker_rx.try_recv();
+                if request.is_ok() {
+                    metrics_worker.lock().unwrap().dequeued();
+                }
+                match request {
+                    Ok(WorkerRequest::Reserves(req)) => {
+                        let result = match sync_if_stale(&mut client, &mut sync_freshness, false).await {
+                            Ok(_) => read_pool_reserves(&mut client, req.pool_id).await
+                                .map(|reserves| (reserves, sync_freshness.state_age_ms())),
+                            Err(e) => Err(e),
+                        };
+                        let _ = req.reply.send(result.map_err(|e| format!("{:?}", e)));
+                        shutdown_worker.finish();
+                    }
+                    Ok(WorkerRequest::Consume(req)) => {
                         let result = consume_pool_notes(
                             &mut client, req.pool_id_opt, &req.swap_info_map,
-                            &price_history_worker, false,
+                            &price_history_worker, &pool_settings_worker, &mut pending_txs, &mut note_cache, &submit_permits_worker,
+                            &metrics_worker, &circuit_breakers_worker, &mut sync_freshness, req.force_sync, false,
                         ).await;
                         let _ = req.reply.send(result.map_err(|e| format!("{:?}", e)));
+                        shutdown_worker.finish();
                         last_poll = Instant::now(); // Reset poll timer after HTTP request
                     }
                     Err(std::sync::mpsc::TryRecvError::Empty) => {
@@ -248,15 +1096,37 @@ async fn main() -> Result<()> {
                     }
                 }
 
-                // Auto-poll every 15 seconds
-                if last_poll.elapsed() >= Duration::from_secs(15) {
+                if shutdown_worker.should_exit() {
+                    println!("Worker thread shutting down ({} submitted transaction(s) not yet confirmed)", pending_txs.len());
+                    break;
+                }
+
+                // Auto-poll every poll_interval_secs (paused in maintenance and on shutdown; in-flight txs are still tracked below)
+                if last_poll.elapsed() >= Duration::from_secs(poll_interval_secs) && !maintenance_worker.is_enabled() && !shutdown_worker.is_requested() {
+                    // Pools whose cooldown has passed trade again from this cycle
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    for pool in circuit_breakers_worker.reset_expired(now) {
+                        println!("🟢 Circuit breaker reset for pool {}", pool);
+                    }
+                    // Pools unreadable at startup or seeded since become swappable
+                    refresh_pool_tokens(&mut client, &pool_ids_worker, &pool_tokens_worker).await;
+
                     let result = consume_pool_notes(
                         &mut client, None, &swap_info_map_worker,
-                        &price_history_worker, true,
+                        &price_history_worker, &pool_settings_worker, &mut pending_txs, &mut note_cache, &submit_permits_worker,
+                        &metrics_worker, &circuit_breakers_worker, &mut sync_freshness, false, true,
                     ).await;
-                    if let Ok(ref resp) = result {
-                        if resp.consumed > 0 {
-                            println!("🔄 Auto-poll: consumed {} note(s)", resp.consumed);
+                    match result {
+                        Ok(ref resp) if resp.consumed > 0 => {
+                            println!("🔄 Auto-poll: submitted {} note(s)", resp.consumed);
+                        }
+                        Ok(_) => {}
+                        Err(ref e) => {
+                            println!("⚠️  Auto-poll failed: {:#}", e);
+                            metrics_worker.lock().unwrap().record_auto_poll_error();
                         }
                     }
 
@@ -266,24 +1136,93 @@ async fn main() -> Result<()> {
                         &limit_orders_worker,
                         &swap_info_map_worker,
                         &price_history_worker,
+                        &pool_settings_worker,
+                        &mut pending_txs,
+                        &submit_permits_worker,
+                        &metrics_worker,
+                        &circuit_breakers_worker,
+                        &mut sync_freshness,
                     ).await;
 
                     last_poll = Instant::now();
                 }
 
+                // Confirmation tracker: run post-commit bookkeeping for submitted txs,
+                // then re-verify recent commits so reorgs roll their bookkeeping back
+                if (!pending_txs.is_empty() || !committed_txs.is_empty())
+                    && last_confirmation_check.elapsed() >= Duration::from_secs(CONFIRMATION_CHECK_INTERVAL_SECS)
+                {
+                    let committed = process_pending_txs(
+                        &mut client,
+                        &mut pending_txs,
+                        &mut committed_txs,
+                        &swap_info_map_worker,
+                        &price_history_worker,
+                        &limit_orders_worker,
+                        &output_notes_worker,
+                        &metrics_worker,
+                        &broadcast_tx_worker,
+                        &mut sync_freshness,
+                    ).await;
+                    if committed > 0 {
+                        println!("✅ Confirmed {} transaction(s) ({} still pending)", committed, pending_txs.len());
+                    }
+                    verify_committed_txs(
+                        &mut client,
+                        &mut committed_txs,
+                        reorg_depth,
+                        &swap_info_map_worker,
+                        &price_history_worker,
+                        &limit_orders_worker,
+                        &output_notes_worker,
+                        &reorgs_detected_worker,
+                    ).await;
+                    last_confirmation_check = Instant::now();
+                }
+
+                // Auto-consume: deliver output notes to users with an active session
+                if let Some(ref mut delivery) = delivery_client {
+                    if last_delivery.elapsed() >= Duration::from_secs(DELIVERY_INTERVAL_SECS)
+                        && !maintenance_worker.is_enabled()
+                        && !shutdown_worker.is_requested()
+                    {
+                        deliver_output_notes(delivery, &output_notes_worker, &consume_sessions_worker, &pool_ids_worker).await;
+                        last_delivery = Instant::now();
+                    }
+                }
+
                 sleep(Duration::from_millis(100)).await;
             }
         });
     });
 
+    // Shared by the handlers and the rate limiter, which buckets valid keys separately
+    let api_keys = Arc::new(ApiKeyStore::open_default());
+
     // Build app state
     let state = AppState {
         tracked_notes: Arc::new(Mutex::new(Vec::new())),
         swap_info_map,
         pool_ids: Arc::new(pool_ids),
-        consume_tx: Arc::new(consume_tx),
+        pool_settings,
+        worker_tx: Arc::new(worker_tx),
         price_history,
         limit_orders,
+        pool_tokens,
+        submit_permits,
+        max_inflight_submissions,
+        reorgs_detected,
+        metrics,
+        circuit_breakers,
+        api_keys: api_keys.clone(),
+        consume_sessions,
+        output_notes,
+        delivery_enabled,
+        admin_token: std::env::var("MILO_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()).map(Arc::new),
+        maintenance: maintenance.clone(),
+        shutdown: shutdown.clone(),
+        poll_interval_secs,
+        broadcast_tx,
     };
 
     // Setup CORS
@@ -295,24 +1234,39 @@ async fn main() -> Result<()> {
     // Build router
     let app = Router::new()
         .route("/health", get(health_handler))
-        .route("/track_note", post(track_note_handler))
-        .route("/consume", post(consume_handler))
+        .route("/track_note", post(track_note_handler).layer(RateLimitLayer::for_route("track_note", RateLimitConfig::per_minute(30), &api_keys)))
+        .route("/consume", post(consume_handler).layer(RateLimitLayer::for_route("consume", RateLimitConfig::per_minute(10), &api_keys)))
         .route("/tracked_notes", get(list_tracked_notes_handler))
         .route("/twap", get(twap_handler))
         .route("/price_history", get(price_history_handler))
+        .route("/ws", get(ws_handler))
         .route("/current_fee", get(current_fee_handler))
-        .route("/limit_order", post(create_limit_order_handler))
+        .route("/fees", get(fees_handler))
+        .route("/circuit_breaker", get(circuit_breaker_handler))
+        .route("/quote", get(quote_handler))
+        .route("/quote_both", get(quote_both_handler))
+        .route("/route", get(route_handler))
+        .route("/swap_route", post(swap_route_handler).layer(RateLimitLayer::for_route("swap_route", RateLimitConfig::per_minute(30), &api_keys)))
+        .route("/fee_model", get(fee_model_handler))
+        .route("/limit_order", post(create_limit_order_handler).layer(RateLimitLayer::for_route("limit_order", RateLimitConfig::per_minute(10), &api_keys)))
         .route("/limit_orders", get(list_limit_orders_handler))
-        .route("/cancel_limit_order", post(cancel_limit_order_handler))
+        .route("/cancel_limit_order", post(cancel_limit_order_handler).layer(RateLimitLayer::for_route("cancel_limit_order", RateLimitConfig::per_minute(20), &api_keys)))
+        .route("/metrics", get(metrics_handler))
+        .route("/consume_session", post(create_session_handler).layer(RateLimitLayer::for_route("consume_session", RateLimitConfig::per_minute(10), &api_keys)))
+        .route("/consume_session/revoke", post(revoke_session_handler))
+        .route("/consume_sessions", get(list_sessions_handler))
+        .route("/output_notes", get(list_output_notes_handler))
+        .route("/maintenance", get(get_maintenance_handler).post(set_maintenance_handler))
+        .layer(maintenance.layer())
         .layer(cors)
         .with_state(state);
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
         .await
-        .context("Failed to bind to port 8080")?;
+        .with_context(|| format!("Failed to bind to port {}", port))?;
 
-    println!("🎯 Swap daemon listening on http://127.0.0.1:8080");
+    println!("🎯 Swap daemon listening on http://127.0.0.1:{} (auto-poll every {}s)", port, poll_interval_secs);
     println!("   Endpoints:");
     println!("   - GET  /health");
     println!("   - POST /track_note");
@@ -320,34 +1274,164 @@ async fn main() -> Result<()> {
     println!("   - GET  /tracked_notes");
     println!("   - GET  /twap?pool_id=<hex>&window=3600");
     println!("   - GET  /price_history?pool_id=<hex>&limit=100");
+    println!("   - GET  /ws (WebSocket: prices on connect, then trades)");
     println!("   - GET  /current_fee?pool_id=<hex>");
+    println!("   - GET  /fees");
+    println!("   - GET  /quote?pool_id=<hex>&sell_token=<hex>&amount=<u64>");
+    println!("   - GET  /quote_both?pool_id=<hex>&amount=<u64>");
+    println!("   - GET  /route?sell=<symbol|hex>&buy=<symbol|hex>&amount=<u64>");
+    println!("   - POST /swap_route");
+    println!("   - GET  /fee_model?std_dev=<f64>");
     println!("   - POST /limit_order");
     println!("   - GET  /limit_orders?user_id=<hex>");
     println!("   - POST /cancel_limit_order");
+    println!("   - GET  /metrics");
+    println!("   - POST /consume_session");
+    println!("   - POST /consume_session/revoke");
+    println!("   - GET  /consume_sessions?user_id=<hex>");
+    println!("   - GET  /output_notes?user_id=<hex>");
+    println!("   - GET  /maintenance, POST /maintenance (admin)");
     println!("   Auto-polling: every 15 seconds (swaps + limit orders)");
     println!();
 
-    axum::serve(listener, app)
-        .await
-        .context("Server error")?;
+    // Peer address is the rate limiter's fallback key when there's no X-Forwarded-For.
+    // On Ctrl-C/SIGTERM the server future (and the listener) is dropped, so no
+    // new requests come in; requests already sent to the worker get to finish.
+    tokio::select! {
+        result = axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()) => {
+            return result.context("Server error");
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    println!("\n🛑 Shutting down: no new requests, waiting for {} in flight", shutdown.in_flight());
+    shutdown.request();
+    if shutdown.wait_for_drain(Duration::from_secs(DRAIN_TIMEOUT_SECS)).await {
+        worker.join().ok();
+    }
+    println!(
+        "🛑 Stopped: {} request(s) completed, {} abandoned",
+        shutdown.drained(),
+        shutdown.in_flight()
+    );
 
     Ok(())
 }
 
-async fn health_handler() -> impl IntoResponse {
+async fn health_handler(State(state): State<AppState>) -> impl IntoResponse {
     Json(serde_json::json!({
         "status": "healthy",
         "daemon": "swap-daemon",
-        "port": 8080
+        "port": milo_config().port,
+        "maintenance": state.maintenance.is_enabled(),
+        "poll_interval_secs": state.poll_interval_secs
     }))
 }
 
+/// Require `Authorization: Bearer <MILO_ADMIN_TOKEN>` on admin routes
+fn check_admin(headers: &HeaderMap, state: &AppState) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err((StatusCode::FORBIDDEN, Json(serde_json::json!({
+            "error": "Admin endpoints disabled (MILO_ADMIN_TOKEN not set)"
+        }))));
+    };
+    let provided = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided != Some(expected.as_str()) {
+        return Err((StatusCode::UNAUTHORIZED, Json(serde_json::json!({
+            "error": "Invalid or missing admin token"
+        }))));
+    }
+    Ok(())
+}
+
+async fn get_maintenance_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.maintenance.status())
+}
+
+// Admin: turn maintenance mode on or off
+async fn set_maintenance_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SetMaintenanceRequest>,
+) -> impl IntoResponse {
+    if let Err(resp) = check_admin(&headers, &state) {
+        return resp;
+    }
+    let status = state.maintenance.set(payload.enabled, payload.reason);
+    (StatusCode::OK, Json(serde_json::json!(status)))
+}
+
+// Prometheus text exposition of daemon metrics
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let in_flight = state.max_inflight_submissions
+        .saturating_sub(state.submit_permits.available_permits());
+
+    let body = format!(
+        "# HELP milo_submission_permits Maximum concurrent transaction submissions\n\
+         # TYPE milo_submission_permits gauge\n\
+         milo_submission_permits {}\n\
+         # HELP milo_submissions_in_flight Transaction submissions currently holding a permit\n\
+         # TYPE milo_submissions_in_flight gauge\n\
+         milo_submissions_in_flight {}\n\
+         # HELP milo_reorgs_detected_total Committed transactions later found discarded and rolled back\n\
+         # TYPE milo_reorgs_detected_total counter\n\
+         milo_reorgs_detected_total {}\n",
+        state.max_inflight_submissions, in_flight,
+        state.reorgs_detected.load(Ordering::Relaxed),
+    ) + &state.metrics.lock().unwrap().render();
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Add `note` unless a note with the same id is already tracked, so a
+/// retried /track_note (at-least-once delivery from the frontend) is a no-op.
+/// False for such a duplicate.
+fn track_once(tracked_notes: &Mutex<Vec<TrackedNote>>, note: TrackedNote) -> bool {
+    let mut tracked_notes = tracked_notes.lock().unwrap();
+    if tracked_notes.iter().any(|n| n.note_id == note.note_id) {
+        return false;
+    }
+    tracked_notes.push(note);
+    true
+}
+
 async fn track_note_handler(
     State(state): State<AppState>,
-    Json(payload): Json<TrackNoteRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<TrackNoteRequest>,
 ) -> impl IntoResponse {
     println!("📝 Tracking note: {} (type: {})", payload.note_id, payload.note_type);
 
+    // API-key callers act for their bound account: the swap output goes there
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    payload.swap_info = payload.swap_info.map(SwapInfo::without_route);
+    if let Some(ref identity) = identity {
+        if let Some(ref mut swap_info) = payload.swap_info {
+            swap_info.user_account_id = identity.account_id.clone();
+        }
+        state.api_keys.audit(identity, "track_note", serde_json::json!({ "note_id": payload.note_id }));
+    }
+
+    // Swaps must name the pool explicitly - a token pair alone is ambiguous
+    // once several pools (fee tiers) exist for the same pair
+    if let Some(ref swap_info) = payload.swap_info {
+        let pool_tokens = state.pool_tokens.lock().unwrap();
+        if let Err((error, candidate_pools)) = validate_swap_pool(swap_info, &state.pool_ids, &pool_tokens) {
+            println!("   ❌ Rejected: {}", error);
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "note_id": payload.note_id,
+                "error": error,
+                "candidate_pools": candidate_pools
+            })));
+        }
+    }
+
     let tracked = TrackedNote {
         note_id: payload.note_id.clone(),
         note_type: payload.note_type.clone(),
@@ -357,11 +1441,19 @@ async fn track_note_handler(
             .as_secs(),
     };
 
-    state.tracked_notes.lock().unwrap().push(tracked);
+    if !track_once(&state.tracked_notes, tracked) {
+        println!("   ↩️  Already tracked, ignoring retry");
+        return (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "note_id": payload.note_id,
+            "duplicate": true
+        })));
+    }
 
     // Store swap info if provided (for P2ID swaps)
     let has_swap_info = if let Some(ref swap_info) = payload.swap_info {
         println!("   💾 Storing swap info for note: {}", payload.note_id);
+        println!("      Pool: {}", swap_info.pool_account_id);
         println!("      Sell: {} -> Buy: {}", swap_info.sell_token_id, swap_info.buy_token_id);
         println!("      Amount in: {}, Min out: {}", swap_info.amount_in, swap_info.min_amount_out);
         state.swap_info_map.lock().unwrap().insert(payload.note_id.clone(), swap_info.clone());
@@ -377,25 +1469,105 @@ async fn track_note_handler(
     })))
 }
 
-async fn consume_handler(
-    State(state): State<AppState>,
-    Json(payload): Json<serde_json::Value>,
-) -> impl IntoResponse {
+/// Check that the pool named in `swap_info` is monitored by this daemon and
+/// holds both the sell and buy token.
+/// On failure returns the error message and every pool that does hold the pair.
+fn validate_swap_pool(
+    swap_info: &SwapInfo,
+    pool_ids: &[AccountId],
+    pool_tokens: &HashMap<String, Vec<String>>,
+) -> Result<(), (String, Vec<String>)> {
+    let sell = AccountId::from_hex(&swap_info.sell_token_id)
+        .map_err(|e| (format!("Invalid sell token ID: {:?}", e), Vec::new()))?
+        .to_hex();
+    let buy = AccountId::from_hex(&swap_info.buy_token_id)
+        .map_err(|e| (format!("Invalid buy token ID: {:?}", e), Vec::new()))?
+        .to_hex();
+
+    let candidate_pools: Vec<String> = pool_ids.iter()
+        .map(|id| id.to_hex())
+        .filter(|id| {
+            pool_tokens.get(id)
+                .map(|tokens| tokens.contains(&sell) && tokens.contains(&buy))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    if swap_info.pool_account_id.is_empty() {
+        return Err(("pool_account_id is required in swap_info".to_string(), candidate_pools));
+    }
+
+    let pool = match AccountId::from_hex(&swap_info.pool_account_id) {
+        Ok(id) => id,
+        Err(e) => return Err((format!("Invalid pool account ID: {:?}", e), candidate_pools)),
+    };
+
+    if !pool_ids.contains(&pool) {
+        return Err((format!("Pool {} is not monitored by this daemon", pool.to_hex()), candidate_pools));
+    }
+
+    // Until the worker has read the pool's vault its tokens are unknown, and
+    // a pair that can't be checked is refused rather than let through
+    match pool_tokens.get(&pool.to_hex()) {
+        Some(tokens) if tokens.contains(&sell) && tokens.contains(&buy) => Ok(()),
+        Some(_) => Err((
+            format!("Pool {} does not contain the pair {} / {}", pool.to_hex(), sell, buy),
+            candidate_pools,
+        )),
+        None => Err((
+            format!("Tokens of pool {} are not known yet - retry after the next poll", pool.to_hex()),
+            candidate_pools,
+        )),
+    }
+}
+
+async fn consume_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<serde_json::Value>,
+) -> impl IntoResponse {
     println!("🔄 Consume request received");
 
+    match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(Some(identity)) => state.api_keys.audit(&identity, "consume", payload.clone()),
+        Ok(None) => {}
+        Err(resp) => return resp,
+    }
+
     let pool_id_opt = payload.get("pool_account_id")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
+    // A tripped pool takes no new swaps until its cooldown has passed
+    if let Some(pool) = pool_id_opt.as_deref().and_then(|hex| AccountId::from_hex(hex).ok()).map(|id| id.to_hex()) {
+        if state.circuit_breakers.is_tripped(&pool) {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({
+                    "error": format!("Swaps on pool {} are paused: price moved too far too fast", pool),
+                    "code": "circuit_breaker_tripped",
+                    "retry_after": state.circuit_breakers.remaining_cooldown(&pool, now),
+                }))
+            );
+        }
+    }
+
     // Send to worker thread
+    let force_sync = payload.get("force_sync").and_then(|v| v.as_bool()).unwrap_or(false);
+
     let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
     let req = ConsumeRequest {
         pool_id_opt,
+        force_sync,
         swap_info_map: state.swap_info_map.clone(),
         reply: reply_tx,
     };
 
-    if state.consume_tx.send(req).is_err() {
+    if !state.send_to_worker(WorkerRequest::Consume(req)) {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(serde_json::json!({
@@ -505,6 +1677,71 @@ async fn twap_handler(
     }))
 }
 
+// WebSocket stream: the latest price of each pool on connect, then a trade
+// event per committed swap and a ping every WS_PING_INTERVAL_SECS
+async fn ws_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| ws_connection(socket, state))
+}
+
+async fn ws_connection(socket: WebSocket, state: AppState) {
+    // Subscribe before reading the snapshot so no trade falls in between
+    let mut events = state.broadcast_tx.subscribe();
+    let latest: Vec<String> = {
+        let history = state.price_history.lock().unwrap();
+        let mut latest: BTreeMap<&str, &PricePoint> = BTreeMap::new();
+        for point in history.iter() {
+            let entry = latest.entry(point.pool_id.as_str()).or_insert(point);
+            if point.timestamp >= entry.timestamp {
+                *entry = point;
+            }
+        }
+        latest
+            .values()
+            .map(|point| {
+                serde_json::json!({
+                    "event": "price",
+                    "pool_id": point.pool_id,
+                    "price": point.price,
+                    "reserve_a": point.reserve_a,
+                    "reserve_b": point.reserve_b,
+                    "timestamp": point.timestamp,
+                })
+                .to_string()
+            })
+            .collect()
+    };
+
+    let (mut sender, mut receiver) = socket.split();
+    for message in latest {
+        if sender.send(Message::Text(message)).await.is_err() {
+            return;
+        }
+    }
+
+    let mut ping = tokio::time::interval(Duration::from_secs(WS_PING_INTERVAL_SECS));
+    ping.tick().await;
+    loop {
+        let message = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => event,
+                // Fell behind: drop the missed trades and keep streaming
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = ping.tick() => serde_json::json!({ "event": "ping" }).to_string(),
+            incoming = receiver.next() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Client messages (pongs included) are ignored
+                Some(Ok(_)) => continue,
+            },
+        };
+        // A failed send means the client is gone
+        if sender.send(Message::Text(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
 // Price history endpoint - returns recent price points for charting
 async fn price_history_handler(
     State(state): State<AppState>,
@@ -529,22 +1766,526 @@ async fn price_history_handler(
     }))
 }
 
+// Protocol revenue per pool: lifetime, 24h and 7-day fees per input token,
+// and the fee APR against the pool's current TVL. Fees in the base token are
+// valued in the quote token at the current pool price.
+async fn fees_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let pools = match load_pools_config("pools.json") {
+        Ok(p) => p,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "error": format!("Invalid pool configuration: {:#}", e)
+        }))),
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut out = Vec::new();
+    for entry in &pools.pools {
+        let pool_id_hex = entry.pool_id.to_hex();
+        let fees = match load_pool_fees(&pool_id_hex, now) {
+            Ok(fees) => fees,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+                "error": format!("Failed to read fees: {}", e)
+            }))),
+        };
+
+        // Quote-token value of each input token, 1.0 for the quote itself
+        let quote_id = entry.pair.split('_').nth(1)
+            .and_then(get_faucet_id_by_symbol)
+            .and_then(|hex| AccountId::from_hex(hex).ok());
+        let reserves = worker_reserves(&state, entry.pool_id).await.map(|(r, _)| r).unwrap_or_default();
+        let reserve_quote = reserves.iter().find(|(id, _)| Some(*id) == quote_id).map(|(_, amount)| *amount);
+        let quote_value = |token: &str| -> Option<f64> {
+            let token = AccountId::from_hex(token).ok()?;
+            if Some(token) == quote_id {
+                return Some(1.0);
+            }
+            let reserve_token = reserves.iter().find(|(id, _)| *id == token).map(|(_, amount)| *amount)?;
+            let reserve_quote = reserve_quote?;
+            (reserve_token > 0).then(|| reserve_quote as f64 / reserve_token as f64)
+        };
+        let in_quote = |pick: fn(&TokenFees) -> u64| -> Option<u64> {
+            fees.iter().map(|(token, f)| Some((pick(f) as f64 * quote_value(token)?) as u64)).sum()
+        };
+
+        let tvl_quote = reserve_quote.map(|quote| quote.saturating_mul(2));
+        let fees_24h_quote = in_quote(|f| f.fees_24h);
+        let fee_apr_pct = match (fees_24h_quote, tvl_quote) {
+            (Some(fees), Some(tvl)) if tvl > 0 => Some(fees as f64 / tvl as f64 * 365.0 * 100.0),
+            _ => None,
+        };
+
+        out.push(serde_json::json!({
+            "pool_id": pool_id_hex,
+            "pair": entry.pair.to_uppercase().replace('_', "/"),
+            "by_token": fees,
+            "total_fees_lifetime_quote": in_quote(|f| f.total_fees_lifetime),
+            "fees_24h_quote": fees_24h_quote,
+            "fees_7d_quote": in_quote(|f| f.fees_7d),
+            "tvl_quote": tvl_quote,
+            "fee_apr_pct": fee_apr_pct.map(|apr| format!("{:.2}", apr)),
+        }));
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({ "pools": out })))
+}
+
 // Current fee endpoint - returns the dynamic fee for a pool
 async fn current_fee_handler(
     State(state): State<AppState>,
     Query(query): Query<CurrentFeeQuery>,
 ) -> impl IntoResponse {
-    let history = state.price_history.lock().unwrap();
-    let (fee_bps, fee_pct) = calculate_dynamic_fee(&history, &query.pool_id);
+    let (fee_bps, fee_pct) = {
+        let history = state.price_history.lock().unwrap();
+        calculate_dynamic_fee(&history, &query.pool_id)
+    };
+    let curve = AccountId::from_hex(&query.pool_id).ok().map(|id| state.curve(id));
 
     Json(serde_json::json!({
         "pool_id": query.pool_id,
         "fee_bps": fee_bps,
         "fee_percent": fee_pct,
-        "fee_description": format!("{}%", fee_pct)
+        "fee_description": format!("{}%", fee_pct),
+        "curve": curve,
+    }))
+}
+
+// Circuit breaker endpoint - per-pool breaker state and the trip settings
+async fn circuit_breaker_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let config = state.circuit_breakers.config();
+    let breakers = state.circuit_breakers.snapshot();
+    let pools: Vec<serde_json::Value> = state.pool_ids.iter()
+        .map(|id| {
+            let pool = id.to_hex();
+            let breaker = breakers.iter().find(|(p, _)| *p == pool).map(|(_, b)| b.clone()).unwrap_or_default();
+            serde_json::json!({
+                "pool_id": pool,
+                "tripped": breaker.tripped,
+                "tripped_at": if breaker.tripped_at > 0 { Some(breaker.tripped_at) } else { None },
+                "cooldown_secs": config.cooldown_secs,
+                "remaining_cooldown_secs": breaker.remaining_cooldown(now),
+                "last_move_pct": breaker.last_move_pct,
+            })
+        })
+        .collect();
+
+    Json(serde_json::json!({
+        "threshold_pct": config.threshold_pct,
+        "window_secs": config.window_secs,
+        "cooldown_secs": config.cooldown_secs,
+        "pools": pools,
     }))
 }
 
+/// One direction of a quote against a reserve snapshot
+fn quote_direction(curve: AmmCurve, amount_in: u64, token_in: AccountId, reserve_in: u64, token_out: AccountId, reserve_out: u64, fee_bps: u64) -> serde_json::Value {
+    let fee_amount = amount_in as u128 * fee_bps as u128 / 10000;
+    let spot_price = spot_price(curve, reserve_in, reserve_out);
+    let (amount_out, error) = match check_output_within_reserve(curve_amount_out(curve, amount_in, reserve_in, reserve_out, fee_bps), reserve_out) {
+        Ok(out) => (Some(out), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    // Shortfall of the execution price against the spot price, fee included
+    let price_impact_pct = amount_out.map(|out| (1.0 - (out as f64 / amount_in as f64) / spot_price) * 100.0);
+
+    serde_json::json!({
+        "token_in": token_in.to_hex(),
+        "token_out": token_out.to_hex(),
+        "amount_in": amount_in,
+        "amount_out": amount_out,
+        "fee_amount": fee_amount,
+        "spot_price": spot_price,
+        "price_impact_pct": price_impact_pct,
+        "error": error,
+    })
+}
+
+type QuoteError = (StatusCode, Json<serde_json::Value>);
+
+/// Validate a quote's pool and amount, then read both reserves through the
+/// worker. The read never submits a transaction.
+async fn quote_reserves(state: &AppState, pool_id_hex: &str, amount: u64) -> Result<(AccountId, [(AccountId, u64); 2], Option<u64>), QuoteError> {
+    let pool_id = match AccountId::from_hex(pool_id_hex) {
+        Ok(id) if state.pool_ids.contains(&id) => id,
+        _ => return Err((StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Unknown pool {}", pool_id_hex)
+        })))),
+    };
+    if amount == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "amount must be greater than 0"
+        }))));
+    }
+
+    let (reserves, state_age_ms) = worker_reserves(state, pool_id).await?;
+    match reserves.as_slice() {
+        [a, b] if a.1 > 0 && b.1 > 0 => Ok((pool_id, [*a, *b], state_age_ms)),
+        _ => Err((StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Pool does not hold two funded tokens"
+        })))),
+    }
+}
+
+/// A pool's vault balances and the age of the state they were read from,
+/// read through the worker
+async fn worker_reserves(state: &AppState, pool_id: AccountId) -> Result<(Vec<(AccountId, u64)>, Option<u64>), QuoteError> {
+    let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+    if !state.send_to_worker(WorkerRequest::Reserves(ReservesRequest { pool_id, reply: reply_tx })) {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "error": "Worker thread not available"
+        }))));
+    }
+    match tokio::time::timeout(Duration::from_secs(60), reply_rx).await {
+        Ok(Ok(Ok(snapshot))) => Ok(snapshot),
+        Ok(Ok(Err(e))) => Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": e })))),
+        Ok(Err(_)) => Err((StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({
+            "error": "Worker dropped request"
+        })))),
+        Err(_) => Err((StatusCode::GATEWAY_TIMEOUT, Json(serde_json::json!({
+            "error": "Timeout reading pool reserves"
+        })))),
+    }
+}
+
+// Simulate selling `amount` of `sell_token` into a pool; nothing is submitted
+async fn quote_handler(
+    State(state): State<AppState>,
+    Query(query): Query<QuoteQuery>,
+) -> impl IntoResponse {
+    let (pool_id, reserves, state_age_ms) = match quote_reserves(&state, &query.pool_id, query.amount).await {
+        Ok(snapshot) => snapshot,
+        Err(resp) => return resp,
+    };
+    let sell_token = AccountId::from_hex(&query.sell_token).ok();
+    let [(token_in, reserve_in), (token_out, reserve_out)] = match reserves {
+        [a, b] if Some(a.0) == sell_token => [a, b],
+        [a, b] if Some(b.0) == sell_token => [b, a],
+        _ => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Pool {} does not trade token {}", pool_id.to_hex(), query.sell_token)
+        }))),
+    };
+
+    let (fee_bps, fee_pct) = {
+        let history = state.price_history.lock().unwrap();
+        calculate_dynamic_fee(&history, &pool_id.to_hex())
+    };
+    let settings = state.settings(pool_id);
+    let curve = settings.curve;
+    let amount_out = match check_output_within_reserve(curve_amount_out(curve, query.amount, reserve_in, reserve_out, fee_bps), reserve_out) {
+        Ok(out) => out,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e.to_string() }))),
+    };
+    // Same measure and cap the swap is checked against
+    let impact_bps = price_impact_bps(query.amount, reserve_in);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "pool_id": pool_id.to_hex(),
+        "sell_token": token_in.to_hex(),
+        "buy_token": token_out.to_hex(),
+        "curve": curve,
+        "amount_in": query.amount,
+        "amount_out": amount_out,
+        "fee_bps": fee_bps,
+        "fee_percent": fee_pct,
+        "price_impact_bps": impact_bps,
+        "max_price_impact_bps": settings.max_price_impact_bps,
+        "exceeds_max_price_impact": impact_bps > settings.max_price_impact_bps,
+        "effective_price": amount_out as f64 / query.amount as f64,
+        "reserve_in": reserve_in,
+        "reserve_out": reserve_out,
+        "state_age_ms": state_age_ms,
+    })))
+}
+
+// Quote `amount` in both directions of a pool from one reserve snapshot
+async fn quote_both_handler(
+    State(state): State<AppState>,
+    Query(query): Query<QuoteBothQuery>,
+) -> impl IntoResponse {
+    let (pool_id, [(token_a, reserve_a), (token_b, reserve_b)], state_age_ms) =
+        match quote_reserves(&state, &query.pool_id, query.amount).await {
+            Ok(snapshot) => snapshot,
+            Err(resp) => return resp,
+        };
+
+    // Same fee the swap would be charged now, for either direction
+    let (fee_bps, fee_pct) = {
+        let history = state.price_history.lock().unwrap();
+        calculate_dynamic_fee(&history, &pool_id.to_hex())
+    };
+    let curve = state.curve(pool_id);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "pool_id": pool_id.to_hex(),
+        "amount": query.amount,
+        "curve": curve,
+        "fee_bps": fee_bps,
+        "fee_percent": fee_pct,
+        "state_age_ms": state_age_ms,
+        "reserves": {
+            token_a.to_hex(): reserve_a,
+            token_b.to_hex(): reserve_b,
+        },
+        "a_to_b": quote_direction(curve, query.amount, token_a, reserve_a, token_b, reserve_b, fee_bps),
+        "b_to_a": quote_direction(curve, query.amount, token_b, reserve_b, token_a, reserve_a, fee_bps),
+    })))
+}
+
+/// Faucet ID hex for a token given by symbol (MILO) or by ID
+fn resolve_token(token: &str) -> Option<String> {
+    let hex = get_faucet_id_by_symbol(token).unwrap_or(token);
+    AccountId::from_hex(hex).ok().map(|id| id.to_hex())
+}
+
+/// Pools to swap `sell` into `buy` through: a direct pool when one holds the
+/// pair, else two pools sharing an intermediate token (MILO → MUSDC → MELO).
+/// Pools are tried in configuration order, so the route is deterministic.
+fn find_route(
+    sell: &str,
+    buy: &str,
+    pool_ids: &[AccountId],
+    pool_tokens: &HashMap<String, Vec<String>>,
+) -> Option<Vec<RouteLeg>> {
+    let pools: Vec<(String, &Vec<String>)> = pool_ids.iter()
+        .filter_map(|id| pool_tokens.get(&id.to_hex()).map(|tokens| (id.to_hex(), tokens)))
+        .collect();
+    let leg = |pool_id: &str, token_in: &str, token_out: &str| RouteLeg {
+        pool_id: pool_id.to_string(),
+        token_in: token_in.to_string(),
+        token_out: token_out.to_string(),
+    };
+
+    if let Some((pool_id, _)) = pools.iter().find(|(_, t)| t.iter().any(|x| x == sell) && t.iter().any(|x| x == buy)) {
+        return Some(vec![leg(pool_id, sell, buy)]);
+    }
+
+    for (first_pool, first_tokens) in pools.iter().filter(|(_, t)| t.iter().any(|x| x == sell)) {
+        for mid in first_tokens.iter().filter(|t| *t != sell) {
+            let second = pools.iter().find(|(id, t)| {
+                id != first_pool && t.iter().any(|x| x == mid) && t.iter().any(|x| x == buy)
+            });
+            if let Some((second_pool, _)) = second {
+                return Some(vec![leg(first_pool, sell, mid), leg(second_pool, mid, buy)]);
+            }
+        }
+    }
+    None
+}
+
+/// Resolve both tokens and find a route between them
+fn route_for(state: &AppState, sell: &str, buy: &str) -> Result<Vec<RouteLeg>, QuoteError> {
+    let (sell_id, buy_id) = match (resolve_token(sell), resolve_token(buy)) {
+        (Some(s), Some(b)) if s != b => (s, b),
+        (Some(_), Some(_)) => return Err((StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "sell and buy must be different tokens"
+        })))),
+        _ => return Err((StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("Unknown token {} / {} (use a symbol or a faucet ID)", sell, buy)
+        })))),
+    };
+    let pool_tokens = state.pool_tokens.lock().unwrap();
+    find_route(&sell_id, &buy_id, &state.pool_ids, &pool_tokens).ok_or_else(|| (StatusCode::NOT_FOUND, Json(serde_json::json!({
+        "error": format!("No route from {} to {}", sell, buy)
+    }))))
+}
+
+// Route discovery; with `amount`, each hop is quoted at the fee it would be charged now
+async fn route_handler(
+    State(state): State<AppState>,
+    Query(query): Query<RouteQuery>,
+) -> impl IntoResponse {
+    let route = match route_for(&state, &query.sell, &query.buy) {
+        Ok(route) => route,
+        Err(resp) => return resp,
+    };
+    let Some(amount) = query.amount else {
+        return (StatusCode::OK, Json(serde_json::json!({ "hops": route.len(), "route": route })));
+    };
+
+    let mut amount_in = amount;
+    let mut legs = Vec::new();
+    for leg in &route {
+        let (pool_id, reserves, _) = match quote_reserves(&state, &leg.pool_id, amount_in).await {
+            Ok(snapshot) => snapshot,
+            Err(resp) => return resp,
+        };
+        let [(_, reserve_in), (_, reserve_out)] = match reserves {
+            [a, b] if a.0.to_hex() == leg.token_in => [a, b],
+            [a, b] => [b, a],
+        };
+        let (fee_bps, _) = {
+            let history = state.price_history.lock().unwrap();
+            calculate_dynamic_fee(&history, &pool_id.to_hex())
+        };
+        let amount_out = match check_output_within_reserve(curve_amount_out(state.curve(pool_id), amount_in, reserve_in, reserve_out, fee_bps), reserve_out) {
+            Ok(out) => out,
+            Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e.to_string() }))),
+        };
+        legs.push(serde_json::json!({
+            "pool_id": leg.pool_id,
+            "token_in": leg.token_in,
+            "token_out": leg.token_out,
+            "amount_in": amount_in,
+            "amount_out": amount_out,
+            "fee_bps": fee_bps,
+        }));
+        amount_in = amount_out;
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "hops": route.len(),
+        "amount_in": amount,
+        "estimated_amount_out": amount_in,
+        "route": legs,
+    })))
+}
+
+// Track a swap note along the best route; a two-hop route pays the first
+// pool's output straight to the second pool
+async fn swap_route_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SwapRouteRequest>,
+) -> impl IntoResponse {
+    println!("🔀 Route swap: {} {} -> {}", payload.amount_in, payload.sell_token, payload.buy_token);
+
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    let user_account_id = match (&identity, &payload.user_account_id) {
+        (Some(identity), _) => identity.account_id.clone(),
+        (None, Some(account_id)) => account_id.clone(),
+        (None, None) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": "user_account_id is required"
+        }))),
+    };
+    if let Some(ref identity) = identity {
+        state.api_keys.audit(identity, "swap_route", serde_json::json!({ "note_id": payload.note_id }));
+    }
+    if payload.amount_in == 0 {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": "amount_in must be greater than 0"
+        })));
+    }
+
+    let route = match route_for(&state, &payload.sell_token, &payload.buy_token) {
+        Ok(route) => route,
+        Err(resp) => return resp,
+    };
+    let first = &route[0];
+    // Slippage is checked once, on the final leg; the first leg takes any output
+    let (min_amount_out, next_hop) = match route.get(1) {
+        Some(second) => ("0".to_string(), Some(RouteHop {
+            pool_account_id: second.pool_id.clone(),
+            buy_token_id: second.token_out.clone(),
+            min_amount_out: payload.min_amount_out.to_string(),
+        })),
+        None => (payload.min_amount_out.to_string(), None),
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let tracked = TrackedNote {
+        note_id: payload.note_id.clone(),
+        note_type: "P2ID_SWAP".to_string(),
+        timestamp: now,
+    };
+    if !track_once(&state.tracked_notes, tracked) {
+        println!("   ↩️  Already tracked, ignoring retry");
+        return (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "note_id": payload.note_id,
+            "duplicate": true
+        })));
+    }
+    state.swap_info_map.lock().unwrap().insert(payload.note_id.clone(), SwapInfo {
+        note_id: payload.note_id.clone(),
+        pool_account_id: first.pool_id.clone(),
+        sell_token_id: first.token_in.clone(),
+        buy_token_id: first.token_out.clone(),
+        amount_in: payload.amount_in.to_string(),
+        min_amount_out,
+        user_account_id,
+        timestamp: now,
+        next_hop,
+        routed: false,
+    });
+    println!("   💾 {} hop(s), first pool {}", route.len(), first.pool_id);
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "note_id": payload.note_id,
+        "send_to_pool": first.pool_id,
+        "hops": route.len(),
+        "route": route,
+    })))
+}
+
+// Fee model endpoint - tier thresholds, optionally classifying a given volatility
+async fn fee_model_handler(Query(query): Query<FeeModelQuery>) -> impl IntoResponse {
+    let tiers: Vec<serde_json::Value> = FEE_TIERS.iter()
+        .map(|(max_std_dev, bps, label)| serde_json::json!({
+            "tier": label,
+            // Upper bound is exclusive; the last tier is unbounded
+            "max_std_dev": if max_std_dev.is_finite() { Some(*max_std_dev) } else { None },
+            "fee_bps": bps,
+            "fee_percent": *bps as f64 / 100.0,
+        }))
+        .collect();
+
+    let mut response = serde_json::json!({
+        "tiers": tiers,
+        "default_fee_bps": DEFAULT_FEE_BPS,
+        "volatility_window": FEE_VOLATILITY_WINDOW,
+        "volatility_measure": "std dev of absolute relative price changes over the last trades",
+    });
+
+    if let Some(std_dev) = query.std_dev {
+        if !std_dev.is_finite() || std_dev < 0.0 {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "std_dev must be a non-negative number"
+            })));
+        }
+        let (fee_bps, fee_pct) = fee_for_std_dev(std_dev);
+        response["preview"] = serde_json::json!({
+            "std_dev": std_dev,
+            "tier": fee_tier_label(std_dev),
+            "fee_bps": fee_bps,
+            "fee_percent": fee_pct,
+        });
+    }
+
+    (StatusCode::OK, Json(response))
+}
+
+fn fee_tier_label(std_dev: f64) -> &'static str {
+    FEE_TIERS.iter()
+        .find(|(max_std_dev, _, _)| std_dev < *max_std_dev)
+        .map(|(_, _, label)| *label)
+        .unwrap_or("high")
+}
+
+/// Map a volatility (std dev of relative price changes) to its fee tier
+/// Returns (fee_basis_points, fee_percent)
+fn fee_for_std_dev(std_dev: f64) -> (u64, f64) {
+    let bps = FEE_TIERS.iter()
+        .find(|(max_std_dev, _, _)| std_dev < *max_std_dev)
+        .map(|(_, bps, _)| *bps)
+        .unwrap_or(FEE_TIERS[FEE_TIERS.len() - 1].1);
+    (bps, bps as f64 / 100.0)
+}
+
 /// Calculate dynamic fee based on price volatility
 /// Returns (fee_basis_points, fee_percent)
 /// - Low volatility: 5 bps (0.05%)
@@ -554,12 +2295,12 @@ fn calculate_dynamic_fee(price_history: &[PricePoint], pool_id: &str) -> (u64, f
     let recent: Vec<f64> = price_history.iter()
         .filter(|p| p.pool_id == pool_id)
         .rev()
-        .take(10)
+        .take(FEE_VOLATILITY_WINDOW)
         .map(|p| p.price)
         .collect();
 
     if recent.len() < 2 {
-        return (10, 0.1); // Default 0.1% (10 bps)
+        return (DEFAULT_FEE_BPS, DEFAULT_FEE_BPS as f64 / 100.0); // Default 0.1% (10 bps)
     }
 
     // Calculate price change standard deviation
@@ -573,21 +2314,15 @@ fn calculate_dynamic_fee(price_history: &[PricePoint], pool_id: &str) -> (u64, f
         .sum::<f64>() / changes.len() as f64;
     let std_dev = variance.sqrt();
 
-    if std_dev < 0.001 {
-        (5, 0.05)   // Low volatility: 0.05%
-    } else if std_dev < 0.01 {
-        (10, 0.1)   // Normal: 0.1%
-    } else {
-        (30, 0.3)   // High volatility: 0.3%
-    }
+    fee_for_std_dev(std_dev)
 }
 
 async fn init_client() -> Result<MidenClient> {
     let timeout_ms = 30_000;
-    let endpoint = Endpoint::testnet();
+    let endpoint = milo_config().endpoint()?;
     let rpc_api = Arc::new(GrpcClient::new(&endpoint, timeout_ms));
 
-    let keystore_path = PathBuf::from(KEYSTORE_PATH);
+    let keystore_path = PathBuf::from(&milo_config().keystore_path);
     let keystore = FilesystemKeyStore::new(keystore_path)
         .context("Failed to create keystore")?;
 
@@ -595,7 +2330,7 @@ async fn init_client() -> Result<MidenClient> {
         .rpc(rpc_api)
         .authenticator(Arc::new(keystore.clone()))
         .in_debug_mode(true.into())
-        .sqlite_store(STORE_PATH.into())
+        .sqlite_store(PathBuf::from(&milo_config().store_path))
         .build()
         .await
         .context("Failed to build client")?;
@@ -603,24 +2338,129 @@ async fn init_client() -> Result<MidenClient> {
     Ok(client)
 }
 
+/// Client that signs as users, backed by the delegated keystore and its own store
+async fn init_delivery_client(keystore_path: &str) -> Result<MidenClient> {
+    let timeout_ms = 30_000;
+    let endpoint = milo_config().endpoint()?;
+    let rpc_api = Arc::new(GrpcClient::new(&endpoint, timeout_ms));
+
+    let keystore = FilesystemKeyStore::new(PathBuf::from(keystore_path))
+        .context("Failed to open delegated keystore")?;
+
+    let client = ClientBuilder::new()
+        .rpc(rpc_api)
+        .authenticator(Arc::new(keystore))
+        .in_debug_mode(true.into())
+        .sqlite_store(DELIVERY_STORE_PATH.into())
+        .build()
+        .await
+        .context("Failed to build delivery client")?;
+
+    Ok(client)
+}
+
+/// Re-read the tokens of every pool. A pool whose read fails keeps the
+/// tokens last read; one never read stays unknown, so swaps on it are refused.
+async fn refresh_pool_tokens(client: &mut MidenClient, pool_ids: &[AccountId], pool_tokens: &Mutex<HashMap<String, Vec<String>>>) {
+    for pool_id in pool_ids {
+        match read_pool_tokens(client, *pool_id).await {
+            Ok(tokens) => {
+                let previous = pool_tokens.lock().unwrap().insert(pool_id.to_hex(), tokens.clone());
+                if previous.as_ref() != Some(&tokens) {
+                    println!("   ✅ Pool {} holds {} token(s)", pool_id.to_hex(), tokens.len());
+                }
+            }
+            Err(e) => println!("   ⚠️  Pool {} tokens unavailable: {:?}", pool_id.to_hex(), e),
+        }
+    }
+}
+
+/// Read the faucet IDs held in a pool's vault
+async fn read_pool_tokens(client: &mut MidenClient, pool_id: AccountId) -> Result<Vec<String>> {
+    let pool_account = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+    let pool_account_inner = match pool_account.account_data() {
+        AccountRecordData::Full(acc) => acc,
+        _ => return Err(anyhow::anyhow!("Pool account is not fully loaded")),
+    };
+
+    let mut tokens = Vec::new();
+    for asset in pool_account_inner.vault().assets() {
+        if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+            tokens.push(fungible_asset.faucet_id().to_hex());
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Submit a transaction while holding a permit from the shared submission
+/// semaphore, so a backlog burst can't flood the RPC
+async fn submit_with_permit(
+    client: &mut MidenClient,
+    submit_permits: &Semaphore,
+    account_id: AccountId,
+    tx_request: TransactionRequest,
+) -> Result<miden_protocol::transaction::TransactionId> {
+    let _permit = submit_permits.acquire().await
+        .context("Submission semaphore closed")?;
+    let tx_id = client.submit_new_transaction(account_id, tx_request).await?;
+    Ok(tx_id)
+}
+
+/// Start a transaction on `pool_id` that runs `script` after its notes, so
+/// the pool component's recorded reserves follow the vault. Pools created
+/// before the component get a plain request.
+async fn pool_tx_builder(
+    client: &mut MidenClient,
+    pool_id: AccountId,
+    script: fn() -> Result<TransactionScript, Box<dyn std::error::Error>>,
+) -> Result<TransactionRequestBuilder> {
+    let pool_account = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+    let pool_account_inner = match pool_account.account_data() {
+        AccountRecordData::Full(acc) => acc,
+        _ => return Err(anyhow::anyhow!("Pool account is not fully loaded")),
+    };
+    let script = script_for_pool(pool_account_inner.code(), script)
+        .map_err(|e| anyhow::anyhow!("Pool reserves script failed to compile: {}", e))?;
+    let builder = TransactionRequestBuilder::new();
+    Ok(match script {
+        Some(script) => builder.custom_script(script),
+        None => builder,
+    })
+}
+
+/// Reserves script for a swap: constant-product swaps must keep
+/// `reserve0 * reserve1` from shrinking, stable-swap trades may lower it and
+/// only record the new balances
+fn swap_reserves_script(curve: AmmCurve) -> fn() -> Result<TransactionScript, Box<dyn std::error::Error>> {
+    match curve {
+        AmmCurve::ConstantProduct => sync_reserves_script,
+        AmmCurve::StableSwap { .. } => record_reserves_script,
+    }
+}
+
 async fn consume_pool_notes(
     client: &mut MidenClient,
     pool_id_opt: Option<String>,
     swap_info_map: &Arc<Mutex<HashMap<String, SwapInfo>>>,
     price_history: &Arc<Mutex<Vec<PricePoint>>>,
+    pool_settings: &HashMap<AccountId, PoolSettings>,
+    pending_txs: &mut Vec<PendingTx>,
+    note_cache: &mut NoteCache,
+    submit_permits: &Semaphore,
+    metrics: &Mutex<MetricsState>,
+    circuit_breakers: &CircuitBreakers,
+    sync_freshness: &mut SyncFreshness,
+    force_sync: bool,
     auto_poll: bool,
 ) -> Result<ConsumeResponse> {
     // Load pool IDs
-    let pools_json = fs::read_to_string("pools.json")?;
-    let pools: serde_json::Value = serde_json::from_str(&pools_json)?;
-
     let pool_ids = if let Some(pool_id_hex) = pool_id_opt {
         vec![AccountId::from_hex(&pool_id_hex)?]
     } else {
-        vec![
-            AccountId::from_hex(pools["milo_musdc_pool_id"].as_str().unwrap())?,
-            AccountId::from_hex(pools["melo_musdc_pool_id"].as_str().unwrap())?,
-        ]
+        load_pools_config("pools.json")?.pool_ids()
     };
 
     let mut total_consumed = 0;
@@ -634,29 +2474,26 @@ async fn consume_pool_notes(
         if !auto_poll {
             println!("   🔄 Syncing state...");
         }
-        match tokio::time::timeout(Duration::from_secs(45), client.sync_state()).await {
-            Ok(Ok(_)) => {
+        match sync_if_stale(client, sync_freshness, force_sync).await {
+            Ok(true) => {
                 if !auto_poll { println!("   ✅ Sync completed"); }
             }
-            Ok(Err(e)) => {
-                if !auto_poll {
-                    println!("   ⚠️  Sync failed: {:?}", e);
-                    println!("   ⏩ Continuing anyway to check local store");
-                }
+            Ok(false) => {
+                if !auto_poll { println!("   ✅ State is fresh - sync skipped"); }
             }
-            Err(_) => {
+            Err(e) => {
                 if !auto_poll {
-                    println!("   ⚠️  Sync timeout");
-                    println!("   ⏩ Continuing with stale data");
+                    println!("   ⚠️  Sync failed: {:#}", e);
+                    println!("   ⏩ Continuing anyway to check local store");
                 }
             }
         }
 
-        // Get consumable P2ID notes for pool
-        let notes = client.get_consumable_notes(Some(*pool_id)).await?;
+        // Next page of consumable P2ID notes - HTTP requests always re-fetch
+        let (notes, queued) = next_note_page(client, note_cache, *pool_id, !auto_poll).await?;
 
         if !auto_poll || !notes.is_empty() {
-            println!("   📝 Found {} consumable P2ID note(s)", notes.len());
+            println!("   📝 Processing {} consumable P2ID note(s) ({} queued for later cycles)", notes.len(), queued);
         }
 
         if notes.is_empty() {
@@ -664,9 +2501,15 @@ async fn consume_pool_notes(
             continue;
         }
 
-        for (note, _) in notes {
+        for note in notes {
             let note_id = note.id();
             let note_id_hex = note_id.to_hex();
+
+            // Already submitted - waiting on the confirmation tracker
+            if pending_txs.iter().any(|p| p.note_id_hex == note_id_hex) {
+                continue;
+            }
+
             println!("      🔄 Processing P2ID note: {}", note_id_hex.chars().take(16).collect::<String>());
 
             // Check if this is a swap note (has swap_info)
@@ -677,21 +2520,39 @@ async fn consume_pool_notes(
                 println!("            Sell: {} -> Buy: {}", info.sell_token_id, info.buy_token_id);
                 println!("            Amount in: {}, Min out: {}", info.amount_in, info.min_amount_out);
 
+                // Tripped pool: the note stays unconsumed until the breaker resets
+                if circuit_breakers.is_tripped(&pool_id.to_hex()) {
+                    println!("         ⛔ Circuit breaker tripped for this pool - swap left for later");
+                    continue;
+                }
+
                 // Execute P2ID swap
-                match execute_p2id_swap(client, *pool_id, note, &info, price_history).await {
-                    Ok(_) => {
+                let settings = pool_settings.get(pool_id).copied().unwrap_or_default();
+                match execute_p2id_swap(client, *pool_id, settings, note, &info, price_history, submit_permits, metrics, circuit_breakers, sync_freshness, force_sync).await {
+                    Ok((tx_id, price_point, output_note_id, amount_out, fee_amount)) => {
                         total_consumed += 1;
-                        // Remove swap_info to prevent re-processing
-                        swap_info_map.lock().unwrap().remove(&note_id_hex);
-                        println!("         ✅ Swap executed! (note removed from tracking)");
+                        // swap_info is removed once the tx commits
+                        pending_txs.push(PendingTx {
+                            tx_id,
+                            pool_id: *pool_id,
+                            note_id_hex: note_id_hex.clone(),
+                            submitted_at: Instant::now(),
+                            effect: PendingEffect::Swap { price_point, limit_order_id: None, output_note_id, amount_out, fee_amount },
+                        });
                     }
                     Err(e) => {
                         println!("         ❌ Swap failed: {:?}", e);
+                        // Reserves may have been misread - resync before pricing the next note
+                        if e.downcast_ref::<ReserveGuardViolation>().is_some() {
+                            println!("         🚨 Reserve guard tripped - note left unconsumed, resyncing");
+                            let _ = sync_if_stale(client, sync_freshness, true).await;
+                            continue;
+                        }
                         // On state mismatch, sync state and skip remaining notes in this cycle
                         let err_str = format!("{:?}", e);
                         if err_str.contains("initial state commitment") {
                             println!("         🔄 State mismatch - syncing and retrying next cycle");
-                            let _ = client.sync_state().await;
+                            let _ = sync_if_stale(client, sync_freshness, true).await;
                             break;
                         }
                     }
@@ -702,31 +2563,21 @@ async fn consume_pool_notes(
 
                 let input_note: miden_protocol::note::Note = note.try_into()
                     .map_err(|e| anyhow::anyhow!("Failed to convert note: {:?}", e))?;
-                let tx_request = TransactionRequestBuilder::new()
+                let tx_request = pool_tx_builder(client, *pool_id, sync_reserves_script).await?
                     .input_notes([(input_note, None)])
                     .build()?;
 
-                match client.submit_new_transaction(*pool_id, tx_request).await {
+                match submit_with_permit(client, submit_permits, *pool_id, tx_request).await {
                     Ok(tx_id) => {
-                        let tx_id: miden_protocol::transaction::TransactionId = tx_id;
                         println!("         📤 Tx submitted: {}", tx_id.to_hex().chars().take(16).collect::<String>());
-
-                        match tokio::time::timeout(
-                            Duration::from_secs(30),
-                            wait_for_transaction(client, tx_id)
-                        ).await {
-                            Ok(Ok(_)) => {
-                                total_consumed += 1;
-                                println!("         ✅ Consumed!");
-                            }
-                            Ok(Err(e)) => {
-                                println!("         ⚠️  Wait failed: {:?}", e);
-                            }
-                            Err(_) => {
-                                println!("         ⚠️  Wait timeout (tx may still succeed)");
-                                total_consumed += 1;
-                            }
-                        }
+                        total_consumed += 1;
+                        pending_txs.push(PendingTx {
+                            tx_id,
+                            pool_id: *pool_id,
+                            note_id_hex: note_id_hex.clone(),
+                            submitted_at: Instant::now(),
+                            effect: PendingEffect::Consume,
+                        });
                     }
                     Err(e) => {
                         println!("         ❌ Submit failed: {:?}", e);
@@ -736,26 +2587,83 @@ async fn consume_pool_notes(
                 // Auto-poll: skip unknown notes (no swap_info)
                 println!("         ⏩ Skipping unknown note (no swap info) during auto-poll");
             }
-
-            sleep(Duration::from_secs(1)).await;
         }
     }
 
     Ok(ConsumeResponse {
         consumed: total_consumed,
         pool_id: None,
+        state_age_ms: sync_freshness.state_age_ms(),
     })
 }
 
+/// Fungible balances in a pool's vault
+async fn read_pool_reserves(client: &mut MidenClient, pool_id: AccountId) -> Result<Vec<(AccountId, u64)>> {
+    let pool_account = client.get_account(pool_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
+    let pool_account_inner = match pool_account.account_data() {
+        AccountRecordData::Full(acc) => acc,
+        _ => return Err(anyhow::anyhow!("Pool account is not fully loaded")),
+    };
+
+    let mut reserves = Vec::new();
+    for asset in pool_account_inner.vault().assets() {
+        if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
+            reserves.push((fungible_asset.faucet_id(), reserve_amount(&fungible_asset)?));
+        }
+    }
+    Ok(reserves)
+}
+
+// Share of the buy-token reserve a single swap must leave in the pool (100 = 1%)
+const MIN_RESERVE_LEFT_BPS: u128 = 100;
+
+// A swap output that would take (nearly) the whole buy-token reserve. The AMM
+// formula never yields this, so it means the reserves were misread.
+#[derive(Debug)]
+struct ReserveGuardViolation {
+    amount_out: u128,
+    reserve_out: u64,
+    max_out: u128,
+}
+
+impl std::fmt::Display for ReserveGuardViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Swap output {} exceeds the allowed {} of buy-token reserve {}",
+            self.amount_out, self.max_out, self.reserve_out)
+    }
+}
+
+impl std::error::Error for ReserveGuardViolation {}
+
+/// Reject an output that doesn't leave MIN_RESERVE_LEFT_BPS of the reserve in the pool
+fn check_output_within_reserve(amount_out: u128, reserve_out: u64) -> Result<u64, ReserveGuardViolation> {
+    let reserve = reserve_out as u128;
+    let max_out = reserve - (reserve * MIN_RESERVE_LEFT_BPS).div_ceil(10000);
+    if amount_out > max_out || amount_out >= reserve {
+        return Err(ReserveGuardViolation { amount_out, reserve_out, max_out });
+    }
+    Ok(amount_out as u64)
+}
+
 /// Execute a P2ID swap: consume user's note + send swapped tokens in a single atomic TX
-/// Uses dynamic fee based on price volatility and records price point for TWAP
+/// Uses dynamic fee based on price volatility. Returns the submitted tx id, the
+/// price point to record for TWAP once the tx commits, the output note ID and
+/// the amount sent to the user. Swaps above the pool's price impact cap are
+/// refused, leaving the note unconsumed.
 async fn execute_p2id_swap(
     client: &mut MidenClient,
     pool_id: AccountId,
+    settings: PoolSettings,
     note: InputNoteRecord,
     swap_info: &SwapInfo,
     price_history: &Arc<Mutex<Vec<PricePoint>>>,
-) -> Result<()> {
+    submit_permits: &Semaphore,
+    metrics: &Mutex<MetricsState>,
+    circuit_breakers: &CircuitBreakers,
+    sync_freshness: &mut SyncFreshness,
+    force_sync: bool,
+) -> Result<(miden_protocol::transaction::TransactionId, PricePoint, String, u64, u64)> {
     // Parse swap parameters
     let user_account_id = AccountId::from_hex(&swap_info.user_account_id)?;
     let sell_token_id = AccountId::from_hex(&swap_info.sell_token_id)?;
@@ -763,39 +2671,37 @@ async fn execute_p2id_swap(
     let amount_in: u64 = swap_info.amount_in.parse()?;
     let min_amount_out: u64 = swap_info.min_amount_out.parse()?;
 
+    // The note must be executed by the pool the user explicitly chose
+    let requested_pool_id = AccountId::from_hex(&swap_info.pool_account_id)?;
+    if requested_pool_id != pool_id {
+        return Err(anyhow::anyhow!(
+            "Swap requested pool {} but note was sent to pool {}",
+            requested_pool_id.to_hex(), pool_id.to_hex()
+        ));
+    }
+
     println!("         📊 Swap parameters:");
     println!("            User: {}...", user_account_id.to_hex().chars().take(16).collect::<String>());
     println!("            Sell token: {}...", sell_token_id.to_hex().chars().take(12).collect::<String>());
     println!("            Buy token: {}...", buy_token_id.to_hex().chars().take(12).collect::<String>());
     println!("            Amount in: {}, Min out: {}", amount_in, min_amount_out);
 
-    // Step 1: Read pool reserves BEFORE consumption
+    // Step 1: Read pool reserves BEFORE consumption (a sync from the last few seconds is reused)
     println!("         📊 Reading pool reserves...");
-    client.sync_state().await?;
-
-    let pool_account = client.get_account(pool_id).await?
-        .ok_or_else(|| anyhow::anyhow!("Pool account not found"))?;
-    let pool_account_inner = match pool_account.account_data() {
-        AccountRecordData::Full(acc) => acc,
-        _ => return Err(anyhow::anyhow!("Pool account is not fully loaded")),
-    };
-    let pool_vault = pool_account_inner.vault();
+    if !sync_if_stale(client, sync_freshness, force_sync).await? {
+        println!("            (state {}ms old, sync skipped)", sync_freshness.state_age_ms().unwrap_or(0));
+    }
 
     let mut reserve_in: u64 = 0;
     let mut reserve_out: u64 = 0;
 
-    for asset in pool_vault.assets() {
-        if let miden_client::asset::Asset::Fungible(fungible_asset) = asset {
-            let asset_faucet_id = fungible_asset.faucet_id();
-            let asset_amount: u64 = fungible_asset.amount().try_into()?;
-
-            if asset_faucet_id == sell_token_id {
-                reserve_in = asset_amount;
-                println!("            Reserve IN (sell token): {}", reserve_in);
-            } else if asset_faucet_id == buy_token_id {
-                reserve_out = asset_amount;
-                println!("            Reserve OUT (buy token): {}", reserve_out);
-            }
+    for (faucet_id, amount) in read_pool_reserves(client, pool_id).await? {
+        if faucet_id == sell_token_id {
+            reserve_in = amount;
+            println!("            Reserve IN (sell token): {}", reserve_in);
+        } else if faucet_id == buy_token_id {
+            reserve_out = amount;
+            println!("            Reserve OUT (buy token): {}", reserve_out);
         }
     }
 
@@ -811,36 +2717,53 @@ async fn execute_p2id_swap(
     };
     println!("         💰 Dynamic fee: {} bps ({}%)", fee_bps, fee_pct);
 
-    // Step 3: AMM calculation with dynamic fee
-    // fee_bps: 5 = 0.05%, 10 = 0.1%, 30 = 0.3%
-    // Formula: amount_out = (amount_in * (10000 - fee_bps) * reserve_out) / (reserve_in * 10000 + amount_in * (10000 - fee_bps))
-    let fee_multiplier = 10000u128 - fee_bps as u128;
-    let amount_in_with_fee = (amount_in as u128) * fee_multiplier;
-    let numerator = amount_in_with_fee * (reserve_out as u128);
-    let denominator = (reserve_in as u128) * 10000 + amount_in_with_fee;
-    let amount_out = (numerator / denominator) as u64;
+    // Step 3: AMM calculation with dynamic fee on the pool's curve
+    // Guard against misread reserves before any note is created; the swap
+    // note stays unconsumed on violation
+    let curve = settings.curve;
+    let amount_out = check_output_within_reserve(curve_amount_out(curve, amount_in, reserve_in, reserve_out, fee_bps), reserve_out)?;
 
-    println!("         🧮 AMM calculation:");
+    println!("         🧮 AMM calculation ({}):", curve);
     println!("            Amount in: {}", amount_in);
     println!("            Reserve in: {}, Reserve out: {}", reserve_in, reserve_out);
     println!("            Fee: {} bps ({}%)", fee_bps, fee_pct);
     println!("            Amount out: {}", amount_out);
+    let fee_amount = (amount_in as u128 * fee_bps as u128 / 10000) as u64;
+
+    let impact_bps = price_impact_bps(amount_in, reserve_in);
+    println!("            Price impact: {} bps (max {})", impact_bps, settings.max_price_impact_bps);
+    if impact_bps > settings.max_price_impact_bps {
+        return Err(anyhow::anyhow!(
+            "Price impact {} bps exceeds the pool's maximum of {} bps",
+            impact_bps, settings.max_price_impact_bps
+        ));
+    }
 
     if amount_out < min_amount_out {
         return Err(anyhow::anyhow!("Output {} less than minimum {}", amount_out, min_amount_out));
     }
 
-    // Step 4: Create P2ID output note for user with swapped tokens
+    // Step 4: Create P2ID output note for user with swapped tokens; the first
+    // leg of a routed swap pays the next pool, which swaps on to the user
     let output_asset = FungibleAsset::new(buy_token_id, amount_out)?;
+    let recipient = match swap_info.next_hop {
+        Some(ref hop) => {
+            let next_pool = AccountId::from_hex(&hop.pool_account_id)?;
+            println!("         🔀 Route leg 1: output goes to pool {}...", next_pool.to_hex().chars().take(16).collect::<String>());
+            next_pool
+        }
+        None => user_account_id,
+    };
 
     let output_note = create_p2id_note(
         pool_id,
-        user_account_id,
+        recipient,
         vec![output_asset.into()],
         NoteType::Public,
         NoteAttachment::default(),
         client.rng(),
     )?;
+    let output_note_id = output_note.id().to_hex();
 
     // Step 5: Single atomic TX - consume input note + create output note
     println!("         ⚡ Executing atomic swap (consume + send in single TX)...");
@@ -848,18 +2771,16 @@ async fn execute_p2id_swap(
     let input_note: miden_protocol::note::Note = note.try_into()
         .map_err(|e| anyhow::anyhow!("Failed to convert note: {:?}", e))?;
 
-    let tx_request = TransactionRequestBuilder::new()
+    let tx_request = pool_tx_builder(client, pool_id, swap_reserves_script(curve)).await?
         .input_notes([(input_note, None)])
         .own_output_notes(vec![OutputNote::Full(output_note)])
         .build()?;
 
-    let tx_id: miden_protocol::transaction::TransactionId = client.submit_new_transaction(pool_id, tx_request).await?;
+    let tx_id = submit_with_permit(client, submit_permits, pool_id, tx_request).await?;
     println!("         📤 Atomic swap TX submitted: {}", tx_id.to_hex().chars().take(16).collect::<String>());
+    metrics.lock().unwrap().record_swap(&pool_id_hex);
 
-    wait_for_transaction(client, tx_id).await?;
-    println!("         ✅ Atomic swap complete! Tokens sent to user.");
-
-    // Step 6: Record price point for TWAP oracle
+    // Step 6: Price point for TWAP oracle - recorded when the tx commits
     let new_reserve_in = reserve_in + amount_in;
     let new_reserve_out = reserve_out - amount_out;
     let price = new_reserve_out as f64 / new_reserve_in as f64;
@@ -868,41 +2789,581 @@ async fn execute_p2id_swap(
         .unwrap()
         .as_secs();
 
-    {
-        let mut history = price_history.lock().unwrap();
-        history.push(PricePoint {
-            timestamp: now,
-            pool_id: pool_id_hex,
-            price,
-            reserve_a: new_reserve_in,
-            reserve_b: new_reserve_out,
+    // Circuit breaker: compare in one token orientation whichever way this swap went
+    let oriented_price = if sell_token_id.to_hex() < buy_token_id.to_hex() { price } else { 1.0 / price };
+    if let Some(move_pct) = circuit_breakers.record_price(&pool_id_hex, now, oriented_price) {
+        println!("         🚨 Circuit breaker tripped: price moved {:.2}% within {}s - pausing swaps for {}s",
+            move_pct, circuit_breakers.config().window_secs, circuit_breakers.config().cooldown_secs);
+    }
+
+    Ok((tx_id, PricePoint {
+        timestamp: now,
+        pool_id: pool_id_hex,
+        price,
+        reserve_a: new_reserve_in,
+        reserve_b: new_reserve_out,
+        price_impact_bps: impact_bps,
+    }, output_note_id, amount_out, fee_amount))
+}
+
+/// Take the next page of up to `max_per_cycle` consumable notes for a pool.
+/// The full list is only re-fetched when the cached batch is drained, older
+/// than NOTE_CACHE_TTL_SECS, or `refresh` is set. Returns the page and the
+/// number of notes still queued.
+async fn next_note_page(
+    client: &mut MidenClient,
+    note_cache: &mut NoteCache,
+    pool_id: AccountId,
+    refresh: bool,
+) -> Result<(Vec<InputNoteRecord>, usize)> {
+    let stale = match note_cache.batches.get(&pool_id) {
+        Some(batch) => batch.notes.is_empty()
+            || batch.fetched_at.elapsed() >= Duration::from_secs(NOTE_CACHE_TTL_SECS),
+        None => true,
+    };
+
+    if refresh || stale {
+        let notes = client.get_consumable_notes(Some(pool_id)).await?;
+        note_cache.batches.insert(pool_id, NoteBatch {
+            notes: notes.into_iter().map(|(note, _)| note).collect(),
+            fetched_at: Instant::now(),
         });
+    }
 
-        // Cleanup: keep only last 24 hours of data
-        let cutoff = now.saturating_sub(86400);
-        history.retain(|p| p.timestamp >= cutoff);
+    let max_per_cycle = note_cache.max_per_cycle;
+    let batch = note_cache.batches.get_mut(&pool_id)
+        .ok_or_else(|| anyhow::anyhow!("Note batch missing for pool {}", pool_id.to_hex()))?;
+    let take = batch.notes.len().min(max_per_cycle);
+    let page: Vec<InputNoteRecord> = batch.notes.drain(..take).collect();
+    Ok((page, batch.notes.len()))
+}
+
+/// Append both legs of a committed swap to the swap ledger. A rollback
+/// appends the reverse legs so the ledger nets out.
+/// For routed swaps only the user's side is recorded: the sell leg on the
+/// first hop and the buy leg on the second.
+fn record_swap_ledger(info: &SwapInfo, amount_out: u64, tx_id: &str, rollback: bool) {
+    let amount_in: u64 = info.amount_in.parse().unwrap_or(0);
+    let (kind_in, kind_out) = if rollback { ("swap_rollback", "swap_rollback") } else { ("swap_in", "swap_out") };
+    let (sold, bought) = if rollback { (Direction::In, Direction::Out) } else { (Direction::Out, Direction::In) };
+    if !info.routed {
+        ledger::append_entry(ledger::SWAP_LEDGER_FILE, &LedgerEntry::new(
+            "swap", kind_in, &info.user_account_id, &info.sell_token_id, amount_in, sold, tx_id,
+        ));
+    }
+    if info.next_hop.is_none() {
+        ledger::append_entry(ledger::SWAP_LEDGER_FILE, &LedgerEntry::new(
+            "swap", kind_out, &info.user_account_id, &info.buy_token_id, amount_out, bought, tx_id,
+        ));
     }
+}
 
-    println!("         📈 Price recorded: {:.6} (reserves: {} / {})", price, new_reserve_in, new_reserve_out);
+/// Confirmation tracker: check outstanding transactions and run their
+/// post-commit bookkeeping (swap_info removal, price points, limit order
+/// status). Discarded or timed-out swaps keep their swap_info so the note
+/// is retried on a later cycle. Returns the number committed.
+async fn process_pending_txs(
+    client: &mut MidenClient,
+    pending_txs: &mut Vec<PendingTx>,
+    committed_txs: &mut Vec<CommittedTx>,
+    swap_info_map: &Arc<Mutex<HashMap<String, SwapInfo>>>,
+    price_history: &Arc<Mutex<Vec<PricePoint>>>,
+    limit_orders: &Arc<Mutex<Vec<LimitOrder>>>,
+    output_notes: &Arc<Mutex<HashMap<String, OutputNoteRecord>>>,
+    metrics: &Mutex<MetricsState>,
+    broadcast_tx: &broadcast::Sender<String>,
+    sync_freshness: &mut SyncFreshness,
+) -> usize {
+    if let Err(e) = sync_if_stale(client, sync_freshness, true).await {
+        println!("⚠️  Confirmation sync failed: {:#}", e);
+        return 0;
+    }
+    if pending_txs.is_empty() {
+        return 0;
+    }
 
-    Ok(())
+    let ids = pending_txs.iter().map(|p| p.tx_id).collect();
+    let statuses: HashMap<String, TransactionStatus> = match client.get_transactions(TransactionFilter::Ids(ids)).await {
+        Ok(records) => records.into_iter().map(|r| (r.id.to_hex(), r.status)).collect(),
+        Err(e) => {
+            println!("⚠️  Failed to read pending transactions: {:?}", e);
+            return 0;
+        }
+    };
+
+    let mut committed = 0;
+    let mut still_pending = Vec::new();
+    let mut consumed_pools = Vec::new();
+    for tx in pending_txs.drain(..) {
+        let tx_id_short = tx.tx_id.to_hex().chars().take(16).collect::<String>();
+        let outcome = match statuses.get(&tx.tx_id.to_hex()) {
+            Some(TransactionStatus::Committed { block_number, .. }) => Some(Ok(block_number.as_u32())),
+            Some(TransactionStatus::Discarded(cause)) => {
+                println!("   ❌ Tx {} discarded: {:?}", tx_id_short, cause);
+                Some(Err(()))
+            }
+            _ if tx.submitted_at.elapsed() >= Duration::from_secs(CONFIRMATION_TIMEOUT_SECS) => {
+                println!("   ⚠️  Tx {} wait timeout (tx may still succeed)", tx_id_short);
+                Some(Err(()))
+            }
+            _ => None,
+        };
+
+        match (outcome, tx.effect) {
+            (None, effect) => still_pending.push(PendingTx { effect, ..tx }),
+            (Some(Ok(block_num)), PendingEffect::Swap { price_point, limit_order_id, output_note_id, amount_out, fee_amount }) => {
+                committed += 1;
+                consumed_pools.push(tx.pool_id);
+                let swap_info = swap_info_map.lock().unwrap().remove(&tx.note_id_hex);
+                println!("   ✅ Atomic swap complete! Tokens sent to user. (tx {})", tx_id_short);
+
+                // Output note becomes eligible for auto-consume
+                if let Some(ref info) = swap_info {
+                    record_swap_ledger(info, amount_out, &tx.tx_id.to_hex(), false);
+                    save_swap_fee(&SwapFee {
+                        tx_id: &tx.tx_id.to_hex(),
+                        timestamp: price_point.timestamp,
+                        pool_id: &price_point.pool_id,
+                        token_in: &info.sell_token_id,
+                        amount_in: info.amount_in.parse().unwrap_or(0),
+                        fee_amount,
+                    });
+                    if let Some(ref hop) = info.next_hop {
+                        // First leg of a route: its output note is the next pool's swap note
+                        println!("   🔀 Route leg 2 queued: note {} in pool {}",
+                            output_note_id.chars().take(16).collect::<String>(), hop.pool_account_id);
+                        swap_info_map.lock().unwrap().insert(output_note_id.clone(), SwapInfo {
+                            note_id: output_note_id.clone(),
+                            pool_account_id: hop.pool_account_id.clone(),
+                            sell_token_id: info.buy_token_id.clone(),
+                            buy_token_id: hop.buy_token_id.clone(),
+                            amount_in: amount_out.to_string(),
+                            min_amount_out: hop.min_amount_out.clone(),
+                            user_account_id: info.user_account_id.clone(),
+                            timestamp: price_point.timestamp,
+                            next_hop: None,
+                            routed: true,
+                        });
+                    } else {
+                        output_notes.lock().unwrap().insert(output_note_id.clone(), OutputNoteRecord {
+                            note_id: output_note_id.clone(),
+                            swap_note_id: tx.note_id_hex.clone(),
+                            pool_id: price_point.pool_id.clone(),
+                            user_account_id: info.user_account_id.clone(),
+                            created_at: price_point.timestamp,
+                            status: "Sent".to_string(),
+                            session_id: None,
+                            consume_tx_id: None,
+                            attempts: 0,
+                            error: None,
+                        });
+                    }
+                }
+
+                println!("   📈 Price recorded: {:.6} (reserves: {} / {})",
+                    price_point.price, price_point.reserve_a, price_point.reserve_b);
+                {
+                    let mut history = price_history.lock().unwrap();
+                    let cutoff = price_point.timestamp.saturating_sub(PRICE_HISTORY_WINDOW_SECS);
+                    history.push(price_point.clone());
+                    // Cleanup: keep only last 24 hours of data
+                    history.retain(|p| p.timestamp >= cutoff);
+                }
+                save_price_point(&price_point);
+                // Err only means no /ws client is connected
+                let amount_in = swap_info.as_ref().and_then(|info| info.amount_in.parse().ok()).unwrap_or(0);
+                let _ = broadcast_tx.send(trade_event(&price_point, amount_in, amount_out));
+
+                if let Some(ref order_id) = limit_order_id {
+                    println!("✅ Limit order {} filled!", order_id);
+                    let mut orders = limit_orders.lock().unwrap();
+                    if let Some(o) = orders.iter_mut().find(|o| &o.order_id == order_id) {
+                        o.status = "Filled".to_string();
+                    }
+                }
+
+                committed_txs.push(CommittedTx {
+                    tx_id: tx.tx_id,
+                    note_id_hex: tx.note_id_hex,
+                    block_num,
+                    swap_info,
+                    price_point,
+                    limit_order_id,
+                    output_note_id,
+                    amount_out,
+                    fee_amount,
+                });
+            }
+            (Some(Ok(_)), PendingEffect::Consume) => {
+                committed += 1;
+                consumed_pools.push(tx.pool_id);
+                println!("   ✅ Consumed! (tx {})", tx_id_short);
+            }
+            (Some(Err(())), PendingEffect::Swap { limit_order_id, .. }) => {
+                // Put the limit order back so it can trigger again
+                if let Some(order_id) = limit_order_id {
+                    let mut orders = limit_orders.lock().unwrap();
+                    if let Some(o) = orders.iter_mut().find(|o| o.order_id == order_id && o.status == "Submitted") {
+                        o.status = "Pending".to_string();
+                    }
+                }
+            }
+            (Some(Err(())), PendingEffect::Consume) => {}
+        }
+    }
+    *pending_txs = still_pending;
+
+    for pool_id in &consumed_pools {
+        metrics.lock().unwrap().record_consumed(&pool_id.to_hex());
+    }
+    let touched_pools: HashSet<AccountId> = consumed_pools.into_iter().collect();
+    for pool_id in touched_pools {
+        // Gauges follow the pool's vault order, whatever the swap direction
+        match read_pool_reserves(client, pool_id).await {
+            Ok(reserves) if reserves.len() == 2 => {
+                metrics.lock().unwrap().set_reserves(&pool_id.to_hex(), reserves[0].1, reserves[1].1);
+            }
+            Ok(_) => {}
+            Err(e) => println!("⚠️  Failed to read reserves of pool {} for metrics: {:?}", pool_id.to_hex(), e),
+        }
+    }
+    committed
+}
+
+/// Re-verify recently committed swaps until they are `reorg_depth` blocks deep.
+/// A swap that turns out discarded has its bookkeeping rolled back: the price
+/// point is removed, swap_info is restored so the note is retried, a
+/// filled limit order goes back to Pending, and its output note is no
+/// longer delivered.
+async fn verify_committed_txs(
+    client: &mut MidenClient,
+    committed_txs: &mut Vec<CommittedTx>,
+    reorg_depth: u32,
+    swap_info_map: &Arc<Mutex<HashMap<String, SwapInfo>>>,
+    price_history: &Arc<Mutex<Vec<PricePoint>>>,
+    limit_orders: &Arc<Mutex<Vec<LimitOrder>>>,
+    output_notes: &Arc<Mutex<HashMap<String, OutputNoteRecord>>>,
+    reorgs_detected: &AtomicU64,
+) {
+    if committed_txs.is_empty() {
+        return;
+    }
+
+    let sync_height = match client.get_sync_height().await {
+        Ok(height) => height.as_u32(),
+        Err(e) => {
+            println!("⚠️  Failed to read sync height: {:?}", e);
+            return;
+        }
+    };
+
+    let ids = committed_txs.iter().map(|c| c.tx_id).collect();
+    let statuses: HashMap<String, TransactionStatus> = match client.get_transactions(TransactionFilter::Ids(ids)).await {
+        Ok(records) => records.into_iter().map(|r| (r.id.to_hex(), r.status)).collect(),
+        Err(e) => {
+            println!("⚠️  Failed to re-verify committed transactions: {:?}", e);
+            return;
+        }
+    };
+
+    let mut still_recent = Vec::new();
+    for tx in committed_txs.drain(..) {
+        match statuses.get(&tx.tx_id.to_hex()) {
+            Some(TransactionStatus::Discarded(cause)) => {
+                reorgs_detected.fetch_add(1, Ordering::Relaxed);
+                println!("🚨 REORG: swap tx {} (block {}) was discarded: {:?} - rolling back",
+                    tx.tx_id.to_hex(), tx.block_num, cause);
+
+                {
+                    let mut history = price_history.lock().unwrap();
+                    let pp = &tx.price_point;
+                    history.retain(|p| !(p.timestamp == pp.timestamp && p.pool_id == pp.pool_id && p.price == pp.price));
+                }
+                delete_price_point(&tx.price_point);
+                delete_swap_fee(&tx.tx_id.to_hex());
+                if let Some(info) = tx.swap_info {
+                    record_swap_ledger(&info, tx.amount_out, &tx.tx_id.to_hex(), true);
+                    let mut swap_infos = swap_info_map.lock().unwrap();
+                    // A rolled-back first leg never produced the second leg's note
+                    if info.next_hop.is_some() {
+                        swap_infos.remove(&tx.output_note_id);
+                    }
+                    swap_infos.insert(tx.note_id_hex.clone(), info);
+                }
+                if let Some(order_id) = tx.limit_order_id {
+                    let mut orders = limit_orders.lock().unwrap();
+                    if let Some(o) = orders.iter_mut().find(|o| o.order_id == order_id && o.status == "Filled") {
+                        o.status = "Pending".to_string();
+                    }
+                }
+                if let Some(record) = output_notes.lock().unwrap().get_mut(&tx.output_note_id) {
+                    if record.status == "Sent" {
+                        record.status = "RolledBack".to_string();
+                    }
+                }
+                println!("   ↩️  Rolled back price point and restored note {} for retry",
+                    tx.note_id_hex.chars().take(16).collect::<String>());
+            }
+            _ if sync_height >= tx.block_num.saturating_add(reorg_depth) => {
+                // Deep enough - considered final
+            }
+            _ => still_recent.push(tx),
+        }
+    }
+    *committed_txs = still_recent;
+}
+
+/// End a session the daemon can no longer serve, keeping the reason for the user
+fn end_session(consume_sessions: &Arc<Mutex<Vec<ConsumeSession>>>, session_id: &str, reason: String, now: u64) {
+    let mut sessions = consume_sessions.lock().unwrap();
+    if let Some(session) = sessions.iter_mut().find(|s| s.session_id == session_id && s.revoked_at.is_none()) {
+        println!("⛔ Consume session {} ended: {}", session_id, reason);
+        session.revoked_at = Some(now);
+        session.end_reason = Some(reason);
+        save_consume_sessions(&sessions);
+    }
+}
+
+/// Consume committed swaps' output notes on behalf of users with an active session
+/// Only notes recorded from our own swaps are eligible, and the note's sender
+/// must be the pool that executed the swap. The session is checked here rather
+/// than when the note was recorded, so a revoke takes effect immediately.
+async fn deliver_output_notes(
+    delivery: &mut DeliveryClient,
+    output_notes: &Arc<Mutex<HashMap<String, OutputNoteRecord>>>,
+    consume_sessions: &Arc<Mutex<Vec<ConsumeSession>>>,
+    pool_ids: &[AccountId],
+) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let candidates: Vec<OutputNoteRecord> = {
+        let mut notes = output_notes.lock().unwrap();
+        notes.retain(|_, r| now.saturating_sub(r.created_at) < OUTPUT_NOTE_RETENTION_SECS);
+        notes.values().filter(|r| r.status == "Sent").cloned().collect()
+    };
+    if candidates.is_empty() && delivery.pending.is_empty() {
+        return;
+    }
+
+    if let Err(e) = sync_with_retry(&mut delivery.client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await {
+        println!("⚠️  Delivery sync error: {:#}", e);
+        return;
+    }
+
+    // Settle consume txs submitted in earlier rounds
+    if !delivery.pending.is_empty() {
+        let ids = delivery.pending.values().copied().collect();
+        match delivery.client.get_transactions(TransactionFilter::Ids(ids)).await {
+            Ok(records) => {
+                let statuses: HashMap<String, TransactionStatus> =
+                    records.into_iter().map(|r| (r.id.to_hex(), r.status)).collect();
+                let mut notes = output_notes.lock().unwrap();
+                let mut sessions = consume_sessions.lock().unwrap();
+                delivery.pending.retain(|note_id, tx_id| {
+                    let Some(record) = notes.get_mut(note_id) else { return false };
+                    match statuses.get(&tx_id.to_hex()) {
+                        Some(TransactionStatus::Committed { .. }) => {
+                            println!("📬 Output note {} delivered to {}",
+                                note_id.chars().take(16).collect::<String>(), record.user_account_id);
+                            record.status = "Delivered".to_string();
+                            false
+                        }
+                        Some(TransactionStatus::Discarded(cause)) => {
+                            record.attempts += 1;
+                            record.error = Some(format!("Consume tx discarded: {:?}", cause));
+                            record.status = if record.attempts >= MAX_DELIVERY_ATTEMPTS { "Failed" } else { "Sent" }.to_string();
+                            record.consume_tx_id = None;
+                            // The note was not consumed, so it doesn't count against the session
+                            if let Some(session) = sessions.iter_mut().find(|s| Some(&s.session_id) == record.session_id.as_ref()) {
+                                session.notes_consumed = session.notes_consumed.saturating_sub(1);
+                            }
+                            false
+                        }
+                        _ => true,
+                    }
+                });
+                save_consume_sessions(&sessions);
+            }
+            Err(e) => println!("⚠️  Failed to read consume transactions: {:?}", e),
+        }
+    }
+
+    let mut consumable: HashMap<AccountId, Vec<InputNoteRecord>> = HashMap::new();
+    for record in candidates {
+        let session_id = {
+            let mut sessions = consume_sessions.lock().unwrap();
+            match active_session_mut(&mut sessions, &record.user_account_id, now) {
+                Some(session) => session.session_id.clone(),
+                None => continue,
+            }
+        };
+        let (Ok(user_id), Ok(pool_id)) = (AccountId::from_hex(&record.user_account_id), AccountId::from_hex(&record.pool_id)) else {
+            continue;
+        };
+        if !pool_ids.contains(&pool_id) {
+            continue;
+        }
+
+        if !delivery.imported.contains(&user_id) {
+            let tracked = matches!(delivery.client.get_account(user_id).await, Ok(Some(_)));
+            if !tracked {
+                if let Err(e) = delivery.client.import_account_by_id(user_id).await {
+                    end_session(consume_sessions, &session_id, format!("Account could not be imported: {}", e), now);
+                    continue;
+                }
+                if let Err(e) = sync_with_retry(&mut delivery.client, SYNC_MAX_ATTEMPTS, SYNC_BASE_DELAY_MS).await {
+                    println!("⚠️  Delivery sync error: {:#}", e);
+                    continue;
+                }
+            }
+            delivery.imported.insert(user_id);
+        }
+
+        if let std::collections::hash_map::Entry::Vacant(entry) = consumable.entry(user_id) {
+            match delivery.client.get_consumable_notes(Some(user_id)).await {
+                Ok(notes) => {
+                    entry.insert(notes.into_iter().map(|(note, _)| note).collect());
+                }
+                Err(e) => {
+                    println!("⚠️  Failed to get consumable notes for {}: {:?}", user_id.to_hex(), e);
+                    continue;
+                }
+            }
+        }
+        // Not visible to the delivery client yet - retried next round
+        let Some(note) = consumable[&user_id].iter().find(|n| n.id().to_hex() == record.note_id) else {
+            continue;
+        };
+
+        let note_id_short = record.note_id.chars().take(16).collect::<String>();
+        if note.metadata().map(|m| m.sender()) != Some(pool_id) {
+            println!("🚫 Output note {} was not sent by pool {} - not consuming", note_id_short, record.pool_id);
+            if let Some(r) = output_notes.lock().unwrap().get_mut(&record.note_id) {
+                r.status = "Failed".to_string();
+                r.error = Some("Note sender is not the pool".to_string());
+            }
+            continue;
+        }
+
+        let submitted = match TransactionRequestBuilder::new()
+            .authenticated_input_notes([(note.id(), None)])
+            .build()
+        {
+            Ok(tx_request) => delivery.client.submit_new_transaction(user_id, tx_request).await
+                .map_err(|e| format!("{}", e)),
+            Err(e) => Err(format!("{}", e)),
+        };
+
+        match submitted {
+            Ok(tx_id) => {
+                println!("📤 Consuming output note {} for {} (session {}), tx {}",
+                    note_id_short, record.user_account_id, session_id, tx_id.to_hex());
+                if let Some(r) = output_notes.lock().unwrap().get_mut(&record.note_id) {
+                    r.status = "Delivering".to_string();
+                    r.session_id = Some(session_id.clone());
+                    r.consume_tx_id = Some(tx_id.to_hex());
+                    r.error = None;
+                }
+                delivery.pending.insert(record.note_id.clone(), tx_id);
+                let mut sessions = consume_sessions.lock().unwrap();
+                if let Some(session) = sessions.iter_mut().find(|s| s.session_id == session_id) {
+                    session.notes_consumed += 1;
+                }
+                save_consume_sessions(&sessions);
+            }
+            Err(error) => {
+                println!("❌ Consuming output note {} failed: {}", note_id_short, error);
+                let failed = {
+                    let mut notes = output_notes.lock().unwrap();
+                    match notes.get_mut(&record.note_id) {
+                        Some(r) => {
+                            r.attempts += 1;
+                            r.error = Some(error.clone());
+                            if r.attempts >= MAX_DELIVERY_ATTEMPTS {
+                                r.status = "Failed".to_string();
+                                true
+                            } else {
+                                false
+                            }
+                        }
+                        None => false,
+                    }
+                };
+                // Repeated signing failures mean the key is missing or no longer valid;
+                // stop acting for this account until the user opens a new session
+                if failed {
+                    end_session(consume_sessions, &session_id, format!("Consume failed: {}", error), now);
+                }
+            }
+        }
+    }
 }
 
 // === Limit Order Handlers ===
 
 async fn create_limit_order_handler(
     State(state): State<AppState>,
-    Json(payload): Json<CreateLimitOrderRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<CreateLimitOrderRequest>,
 ) -> impl IntoResponse {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    if let Some(ref identity) = identity {
+        payload.user_account_id = identity.account_id.clone();
+        payload.swap_info.user_account_id = identity.account_id.clone();
+    }
+
+    {
+        let pool_tokens = state.pool_tokens.lock().unwrap();
+        if let Err((error, candidate_pools)) = validate_swap_pool(&payload.swap_info, &state.pool_ids, &pool_tokens) {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "error": error,
+                "candidate_pools": candidate_pools
+            })));
+        }
+    }
+
+    let order_type = match payload.order_type.as_deref() {
+        None => OrderType::Limit,
+        Some(value) => match OrderType::parse(value) {
+            Some(order_type) => order_type,
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "error": format!("Unknown order_type '{}', expected Limit or StopLoss", value)
+            }))),
+        },
+    };
+
     let order_id = format!("LO-{}-{}", &payload.note_id[..16.min(payload.note_id.len())], now);
     let amount_in: u64 = payload.amount_in.parse().unwrap_or(0);
     let min_amount_out: u64 = payload.min_amount_out.parse().unwrap_or(0);
 
+    // A stop-loss fires at or below its stop, so a swap floor above the stop
+    // would reject every fill
+    if order_type == OrderType::StopLoss {
+        let swap_floor: u64 = payload.swap_info.min_amount_out.parse().unwrap_or(0);
+        if swap_floor > min_amount_out {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "success": false,
+                "error": format!(
+                    "swap_info.minAmountOut ({}) is above the stop ({}); the order could never fill",
+                    swap_floor, min_amount_out
+                )
+            })));
+        }
+    }
+
     let order = LimitOrder {
         order_id: order_id.clone(),
         note_id: payload.note_id.clone(),
@@ -913,48 +3374,83 @@ async fn create_limit_order_handler(
         amount_in,
         target_price: payload.target_price,
         min_amount_out,
+        order_type,
         created_at: now,
         expires_at: now + payload.expires_in_secs,
         status: "Pending".to_string(),
     };
 
-    println!("📋 Limit order created: {}", order_id);
+    println!("📋 {:?} order created: {}", order_type, order_id);
+    if let Some(ref identity) = identity {
+        state.api_keys.audit(identity, "limit_order", serde_json::json!({
+            "order_id": order_id,
+            "note_id": payload.note_id,
+        }));
+    }
     println!("   Target price: {}, Amount: {}, Expires: {}s",
         payload.target_price, amount_in, payload.expires_in_secs);
 
     // Store the swap info for when the order triggers
-    state.swap_info_map.lock().unwrap().insert(payload.note_id.clone(), payload.swap_info);
+    state.swap_info_map.lock().unwrap().insert(payload.note_id.clone(), payload.swap_info.without_route());
     state.limit_orders.lock().unwrap().push(order);
 
     (StatusCode::OK, Json(serde_json::json!({
         "success": true,
         "order_id": order_id,
+        "order_type": order_type,
     })))
 }
 
 async fn list_limit_orders_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<LimitOrdersQuery>,
 ) -> impl IntoResponse {
+    // API-key callers only see their own account's orders
+    let user_id = match state.api_keys.authorize(&headers, Permission::Read) {
+        Ok(Some(identity)) => identity.account_id,
+        Ok(None) => match query.user_id {
+            Some(user_id) => user_id,
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "user_id is required"
+            }))),
+        },
+        Err(resp) => return resp,
+    };
+
     let orders = state.limit_orders.lock().unwrap();
     let user_orders: Vec<&LimitOrder> = orders.iter()
-        .filter(|o| o.user_account_id == query.user_id)
+        .filter(|o| o.user_account_id.eq_ignore_ascii_case(&user_id))
         .collect();
 
-    Json(serde_json::json!({
+    (StatusCode::OK, Json(serde_json::json!({
         "orders": user_orders,
         "count": user_orders.len()
-    }))
+    })))
 }
 
 async fn cancel_limit_order_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<CancelOrderRequest>,
 ) -> impl IntoResponse {
+    // API-key callers may only cancel their own account's orders
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+
     let mut orders = state.limit_orders.lock().unwrap();
-    if let Some(order) = orders.iter_mut().find(|o| o.order_id == payload.order_id && o.status == "Pending") {
+    if let Some(order) = orders.iter_mut().find(|o| {
+        o.order_id == payload.order_id
+            && o.status == "Pending"
+            && identity.as_ref().map_or(true, |id| o.user_account_id.eq_ignore_ascii_case(&id.account_id))
+    }) {
         order.status = "Cancelled".to_string();
         println!("❌ Limit order cancelled: {}", payload.order_id);
+        if let Some(ref identity) = identity {
+            state.api_keys.audit(identity, "cancel_limit_order", serde_json::json!({ "order_id": payload.order_id }));
+        }
         (StatusCode::OK, Json(serde_json::json!({
             "success": true,
             "order_id": payload.order_id,
@@ -968,6 +3464,160 @@ async fn cancel_limit_order_handler(
     }
 }
 
+// === Auto-consume Session Handlers ===
+
+async fn create_session_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<CreateSessionRequest>,
+) -> impl IntoResponse {
+    if !state.delivery_enabled {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": "Auto-consume is not enabled on this daemon (MILO_DELEGATED_KEYSTORE is not set)"
+        })));
+    }
+
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    if let Some(ref identity) = identity {
+        payload.user_account_id = Some(identity.account_id.clone());
+    }
+    let Some(user_account_id) = payload.user_account_id else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": "user_account_id is required"
+        })));
+    };
+    let user_id = match AccountId::from_hex(&user_account_id) {
+        Ok(id) => id,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": format!("Invalid user_account_id: {}", e)
+        }))),
+    };
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let session_id = format!("CS-{}", (0..8).map(|_| format!("{:02x}", rand::random::<u8>())).collect::<String>());
+    let session = new_consume_session(session_id.clone(), user_id.to_hex(), payload.ttl_secs, payload.max_notes, now);
+
+    {
+        let mut sessions = state.consume_sessions.lock().unwrap();
+        open_consume_session(&mut sessions, session.clone(), now);
+        save_consume_sessions(&sessions);
+    }
+
+    println!("🔓 Consume session {} opened for {} ({}s, max {} notes)",
+        session_id, session.user_account_id, session.expires_at - now, session.max_notes);
+    if let Some(ref identity) = identity {
+        state.api_keys.audit(identity, "consume_session", serde_json::json!({ "session_id": session_id }));
+    }
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "success": true,
+        "session": session,
+    })))
+}
+
+async fn revoke_session_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RevokeSessionRequest>,
+) -> impl IntoResponse {
+    // API-key callers may only revoke their own account's sessions
+    let identity = match state.api_keys.authorize(&headers, Permission::Trade) {
+        Ok(identity) => identity,
+        Err(resp) => return resp,
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut sessions = state.consume_sessions.lock().unwrap();
+    let owner = identity.as_ref().map(|id| id.account_id.as_str());
+    if let Some(session) = revoke_consume_session(&mut sessions, &payload.session_id, owner, now) {
+        save_consume_sessions(&sessions);
+        println!("🔒 Consume session revoked: {}", payload.session_id);
+        if let Some(ref identity) = identity {
+            state.api_keys.audit(identity, "revoke_consume_session", serde_json::json!({ "session_id": payload.session_id }));
+        }
+        (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "session": session,
+        })))
+    } else {
+        (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "success": false,
+            "error": "Session not found or already revoked"
+        })))
+    }
+}
+
+async fn list_sessions_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UserQuery>,
+) -> impl IntoResponse {
+    let user_id = match state.api_keys.authorize(&headers, Permission::Read) {
+        Ok(Some(identity)) => identity.account_id,
+        Ok(None) => match query.user_id {
+            Some(user_id) => user_id,
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "user_id is required"
+            }))),
+        },
+        Err(resp) => return resp,
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let sessions = state.consume_sessions.lock().unwrap();
+    let user_sessions: Vec<serde_json::Value> = sessions.iter()
+        .filter(|s| s.user_account_id.eq_ignore_ascii_case(&user_id))
+        .map(|s| serde_json::json!({ "session": s, "active": s.is_active(now) }))
+        .collect();
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "sessions": user_sessions,
+        "count": user_sessions.len()
+    })))
+}
+
+async fn list_output_notes_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UserQuery>,
+) -> impl IntoResponse {
+    let user_id = match state.api_keys.authorize(&headers, Permission::Read) {
+        Ok(Some(identity)) => identity.account_id,
+        Ok(None) => match query.user_id {
+            Some(user_id) => user_id,
+            None => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "error": "user_id is required"
+            }))),
+        },
+        Err(resp) => return resp,
+    };
+
+    let notes = state.output_notes.lock().unwrap();
+    let user_notes: Vec<&OutputNoteRecord> = notes.values()
+        .filter(|n| n.user_account_id.eq_ignore_ascii_case(&user_id))
+        .collect();
+
+    (StatusCode::OK, Json(serde_json::json!({
+        "output_notes": user_notes,
+        "count": user_notes.len()
+    })))
+}
+
 /// Check pending limit orders against current pool prices
 /// Execute orders when the price condition is met
 async fn check_limit_orders(
@@ -975,6 +3625,12 @@ async fn check_limit_orders(
     limit_orders: &Arc<Mutex<Vec<LimitOrder>>>,
     swap_info_map: &Arc<Mutex<HashMap<String, SwapInfo>>>,
     price_history: &Arc<Mutex<Vec<PricePoint>>>,
+    pool_settings: &HashMap<AccountId, PoolSettings>,
+    pending_txs: &mut Vec<PendingTx>,
+    submit_permits: &Semaphore,
+    metrics: &Mutex<MetricsState>,
+    circuit_breakers: &CircuitBreakers,
+    sync_freshness: &mut SyncFreshness,
 ) {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -1004,6 +3660,10 @@ async fn check_limit_orders(
             Ok(id) => id,
             Err(_) => continue,
         };
+        // Orders on a tripped pool wait for the breaker to reset
+        if circuit_breakers.is_tripped(&pool_id.to_hex()) {
+            continue;
+        }
 
         // Read current pool reserves
         let pool_account = match client.get_account(pool_id).await {
@@ -1030,9 +3690,12 @@ async fn check_limit_orders(
 
         for asset in pool_vault.assets() {
             if let miden_client::asset::Asset::Fungible(fa) = asset {
-                let amount: u64 = match fa.amount().try_into() {
+                let amount = match reserve_amount(&fa) {
                     Ok(a) => a,
-                    Err(_) => continue,
+                    Err(e) => {
+                        println!("⚠️  Limit order {}: {}", order.order_id, e);
+                        continue;
+                    }
                 };
                 if fa.faucet_id() == sell_token_id {
                     reserve_in = amount;
@@ -1046,21 +3709,19 @@ async fn check_limit_orders(
             continue;
         }
 
-        // Calculate AMM output at current reserves
+        // Calculate AMM output at current reserves on the pool's curve
         let (fee_bps, _) = {
             let history = price_history.lock().unwrap();
             calculate_dynamic_fee(&history, &order.pool_id)
         };
-        let fee_multiplier = 10000u128 - fee_bps as u128;
-        let amount_in_with_fee = (order.amount_in as u128) * fee_multiplier;
-        let numerator = amount_in_with_fee * (reserve_out as u128);
-        let denominator = (reserve_in as u128) * 10000 + amount_in_with_fee;
-        let potential_output = (numerator / denominator) as u64;
+        let settings = pool_settings.get(&pool_id).copied().unwrap_or_default();
+        let curve = settings.curve;
+        let potential_output = curve_amount_out(curve, order.amount_in, reserve_in, reserve_out, fee_bps) as u64;
 
-        // Check if output meets the order's min_amount_out
-        if potential_output >= order.min_amount_out {
-            println!("🎯 Limit order {} triggered! Output: {} >= min: {}",
-                order.order_id, potential_output, order.min_amount_out);
+        // Limit: output reached min_amount_out; stop-loss: output fell to it
+        if order.order_type.is_triggered(potential_output, order.min_amount_out) {
+            println!("🎯 {:?} order {} triggered! Output: {}, threshold: {}",
+                order.order_type, order.order_id, potential_output, order.min_amount_out);
 
             // Get swap info for this note
             let swap_info = swap_info_map.lock().unwrap().get(&order.note_id).cloned();
@@ -1070,13 +3731,29 @@ async fn check_limit_orders(
                     Ok(notes) => {
                         for (note, _) in notes {
                             if note.id().to_hex() == order.note_id {
-                                match execute_p2id_swap(client, pool_id, note, &info, price_history).await {
-                                    Ok(_) => {
-                                        println!("✅ Limit order {} filled!", order.order_id);
-                                        let mut orders = limit_orders.lock().unwrap();
-                                        if let Some(o) = orders.iter_mut().find(|o| o.order_id == order.order_id) {
-                                            o.status = "Filled".to_string();
+                                match execute_p2id_swap(client, pool_id, settings, note, &info, price_history, submit_permits, metrics, circuit_breakers, sync_freshness, false).await {
+                                    Ok((tx_id, price_point, output_note_id, amount_out, fee_amount)) => {
+                                        // Marked Filled by the confirmation tracker once the tx commits
+                                        println!("📤 Limit order {} submitted", order.order_id);
+                                        {
+                                            let mut orders = limit_orders.lock().unwrap();
+                                            if let Some(o) = orders.iter_mut().find(|o| o.order_id == order.order_id) {
+                                                o.status = "Submitted".to_string();
+                                            }
                                         }
+                                        pending_txs.push(PendingTx {
+                                            tx_id,
+                                            pool_id,
+                                            note_id_hex: order.note_id.clone(),
+                                            submitted_at: Instant::now(),
+                                            effect: PendingEffect::Swap {
+                                                price_point,
+                                                limit_order_id: Some(order.order_id.clone()),
+                                                output_note_id,
+                                                amount_out,
+                                                fee_amount,
+                                            },
+                                        });
                                     }
                                     Err(e) => {
                                         println!("❌ Limit order {} execution failed: {:?}", order.order_id, e);
@@ -1095,20 +3772,148 @@ async fn check_limit_orders(
     }
 }
 
-async fn wait_for_transaction(
-    client: &mut MidenClient,
-    tx_id: miden_protocol::transaction::TransactionId,
-) -> Result<()> {
-    for _ in 0..60 {
-        match client.get_transactions(TransactionFilter::Ids(vec![tx_id])).await {
-            Ok(transactions) => {
-                if !transactions.is_empty() {
-                    return Ok(());
-                }
-            }
-            Err(_) => {}
-        }
-        sleep(Duration::from_millis(500)).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pool_daemon::fixtures::{pool_tokens, pool_with_reserves, swap_info_for, two_pools, FixturePool, USER};
+
+    fn swap_info(pool: &FixturePool, sell: &str, buy: &str) -> SwapInfo {
+        serde_json::from_value(swap_info_for("0xnote", pool, sell, buy, 1_000, 0)).unwrap()
+    }
+
+    #[test]
+    fn pool_holding_the_pair_is_accepted() {
+        let [milo, melo] = two_pools(1_000, 1_000);
+        let (pool_ids, tokens) = pool_tokens(&[milo.clone(), melo]);
+        let info = swap_info(&milo, &milo.token_a, &milo.token_b);
+        assert!(validate_swap_pool(&info, &pool_ids, &tokens).is_ok());
+        let info = swap_info(&milo, &milo.token_b, &milo.token_a);
+        assert!(validate_swap_pool(&info, &pool_ids, &tokens).is_ok());
+    }
+
+    #[test]
+    fn pool_token_mismatch_lists_the_candidate_pools() {
+        let [milo, melo] = two_pools(1_000, 1_000);
+        let (pool_ids, tokens) = pool_tokens(&[milo.clone(), melo.clone()]);
+        let info = swap_info(&milo, &melo.token_a, &melo.token_b);
+        let (error, candidates) = validate_swap_pool(&info, &pool_ids, &tokens).unwrap_err();
+        assert!(error.contains("does not contain the pair"), "{}", error);
+        assert_eq!(candidates, vec![melo.pool_id]);
+    }
+
+    #[test]
+    fn pool_with_unknown_tokens_is_refused() {
+        let [milo, melo] = two_pools(1_000, 1_000);
+        let (pool_ids, mut tokens) = pool_tokens(&[milo.clone(), melo]);
+        tokens.remove(&milo.pool_id);
+        let info = swap_info(&milo, &milo.token_a, &milo.token_b);
+        let (error, _) = validate_swap_pool(&info, &pool_ids, &tokens).unwrap_err();
+        assert!(error.contains("not known yet"), "{}", error);
+    }
+
+    #[test]
+    fn missing_or_unmonitored_pool_is_refused() {
+        let [milo, melo] = two_pools(1_000, 1_000);
+        let (pool_ids, tokens) = pool_tokens(&[milo.clone(), melo]);
+        let mut info = swap_info(&milo, &milo.token_a, &milo.token_b);
+        info.pool_account_id = String::new();
+        let (error, candidates) = validate_swap_pool(&info, &pool_ids, &tokens).unwrap_err();
+        assert!(error.contains("pool_account_id is required"), "{}", error);
+        assert_eq!(candidates, vec![milo.pool_id.clone()]);
+
+        info.pool_account_id = milo.token_a.clone();
+        let (error, _) = validate_swap_pool(&info, &pool_ids, &tokens).unwrap_err();
+        assert!(error.contains("not monitored"), "{}", error);
+    }
+
+    fn session(id: &str, user: &str, now: u64) -> ConsumeSession {
+        new_consume_session(id.to_string(), user.to_string(), Some(600), Some(2), now)
+    }
+
+    #[test]
+    fn session_bounds_are_defaulted_and_clamped() {
+        let s = new_consume_session("CS-1".to_string(), USER.to_string(), None, None, 100);
+        assert_eq!((s.expires_at, s.max_notes), (100 + DEFAULT_SESSION_TTL_SECS, DEFAULT_SESSION_MAX_NOTES));
+        let s = new_consume_session("CS-1".to_string(), USER.to_string(), Some(10 * MAX_SESSION_TTL_SECS), Some(u32::MAX), 100);
+        assert_eq!((s.expires_at, s.max_notes), (100 + MAX_SESSION_TTL_SECS, MAX_SESSION_MAX_NOTES));
+        let s = new_consume_session("CS-1".to_string(), USER.to_string(), Some(0), Some(0), 100);
+        assert_eq!((s.expires_at, s.max_notes), (101, 1));
+    }
+
+    #[test]
+    fn session_ends_at_expiry_note_limit_or_revocation() {
+        let mut s = session("CS-1", USER, 1_000);
+        assert!(s.is_active(1_000));
+        assert!(s.is_active(1_599));
+        assert!(!s.is_active(1_600));
+
+        s.notes_consumed = 2;
+        assert!(!s.is_active(1_000));
+        // A discarded consume gives the note back
+        s.notes_consumed -= 1;
+        assert!(s.is_active(1_000));
+
+        s.revoked_at = Some(1_000);
+        assert!(!s.is_active(1_000));
+    }
+
+    #[test]
+    fn new_session_replaces_the_active_one() {
+        let mut sessions = Vec::new();
+        open_consume_session(&mut sessions, session("CS-1", "0xAbC", 1_000), 1_000);
+        open_consume_session(&mut sessions, session("CS-other", "0xdef", 1_000), 1_000);
+        open_consume_session(&mut sessions, session("CS-2", "0xabc", 1_100), 1_100);
+
+        assert_eq!(sessions[0].revoked_at, Some(1_100));
+        assert_eq!(sessions[0].end_reason.as_deref(), Some("Replaced by a new session"));
+        assert!(sessions[1].is_active(1_100));
+        let active = active_session_mut(&mut sessions, "0xABC", 1_100).unwrap();
+        assert_eq!(active.session_id, "CS-2");
+        assert!(active_session_mut(&mut sessions, "0xabc", 1_700).is_none());
+    }
+
+    #[test]
+    fn api_key_can_only_revoke_its_own_sessions() {
+        let mut sessions = vec![session("CS-1", "0xabc", 1_000), session("CS-2", "0xdef", 1_000)];
+        assert!(revoke_consume_session(&mut sessions, "CS-1", Some("0xdef"), 1_010).is_none());
+        assert!(sessions[0].is_active(1_010));
+
+        let revoked = revoke_consume_session(&mut sessions, "CS-1", Some("0xABC"), 1_010).unwrap();
+        assert_eq!(revoked.revoked_at, Some(1_010));
+        assert!(revoke_consume_session(&mut sessions, "CS-1", None, 1_020).is_none());
+        // Without a key (the frontend) any session id can be revoked
+        assert!(revoke_consume_session(&mut sessions, "CS-2", None, 1_020).is_some());
+        assert!(revoke_consume_session(&mut sessions, "CS-3", None, 1_020).is_none());
+    }
+
+    #[test]
+    fn output_within_the_reserve_is_allowed() {
+        let pool = pool_with_reserves(1_000_000, 1_000_000);
+        let out = curve_amount_out(AmmCurve::ConstantProduct, 10_000, pool.reserve_a, pool.reserve_b, 30);
+        assert_eq!(check_output_within_reserve(out, pool.reserve_b).unwrap() as u128, out);
+        // Exactly 1% left is still allowed
+        assert_eq!(check_output_within_reserve(990_000, 1_000_000).unwrap(), 990_000);
+    }
+
+    #[test]
+    fn output_draining_the_reserve_is_refused() {
+        let err = check_output_within_reserve(990_001, 1_000_000).unwrap_err();
+        assert_eq!((err.amount_out, err.reserve_out, err.max_out), (990_001, 1_000_000, 990_000));
+        assert!(check_output_within_reserve(1_000_000, 1_000_000).is_err());
+        assert!(check_output_within_reserve(2_000_000, 1_000_000).is_err());
+        // An empty or dust reserve can't pay anything out
+        assert!(check_output_within_reserve(1, 0).is_err());
+        assert!(check_output_within_reserve(1, 1).is_err());
+    }
+
+    #[test]
+    fn corrupted_reserve_reads_trip_the_guard() {
+        let pool = pool_with_reserves(1_000_000, 1_000_000);
+        // Sell-token reserve misread as empty: the formula prices the whole buy reserve
+        let out = curve_amount_out(AmmCurve::ConstantProduct, 10_000, 0, pool.reserve_b, 30);
+        assert!(check_output_within_reserve(out, pool.reserve_b).is_err());
+        // Buy-token reserve misread as far larger than the vault holds
+        let out = curve_amount_out(AmmCurve::ConstantProduct, 10_000, pool.reserve_a, 1_000 * pool.reserve_b, 30);
+        assert!(check_output_within_reserve(out, pool.reserve_b).is_err());
     }
-    Err(anyhow::anyhow!("Transaction timeout"))
 }
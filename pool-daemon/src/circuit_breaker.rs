@@ -0,0 +1,151 @@
+//! Per-pool circuit breaker for the swap daemon
+//!
+//! Every swap's post-trade spot price is compared with the oldest price seen
+//! for that pool within the window. A move beyond the threshold trips the
+//! pool's breaker: swap notes and limit orders for it are left alone until
+//! the cooldown has passed. Prices are kept in a fixed token orientation so
+//! swaps in opposite directions compare correctly.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+pub const DEFAULT_THRESHOLD_PCT: f64 = 10.0;
+pub const DEFAULT_WINDOW_SECS: u64 = 300;
+pub const DEFAULT_COOLDOWN_SECS: u64 = 600;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CircuitBreakerConfig {
+    /// Relative price move (percent) within the window that trips a pool
+    pub threshold_pct: f64,
+    pub window_secs: u64,
+    pub cooldown_secs: u64,
+}
+
+impl CircuitBreakerConfig {
+    /// Defaults overridden by MILO_CIRCUIT_BREAKER_{THRESHOLD_PCT,WINDOW_SECS,COOLDOWN_SECS}
+    pub fn from_env() -> Self {
+        let threshold_pct = std::env::var("MILO_CIRCUIT_BREAKER_THRESHOLD_PCT")
+            .ok()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|pct| pct.is_finite() && *pct > 0.0)
+            .unwrap_or(DEFAULT_THRESHOLD_PCT);
+        let window_secs = std::env::var("MILO_CIRCUIT_BREAKER_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(DEFAULT_WINDOW_SECS);
+        let cooldown_secs = std::env::var("MILO_CIRCUIT_BREAKER_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_COOLDOWN_SECS);
+        Self { threshold_pct, window_secs, cooldown_secs }
+    }
+}
+
+/// One pool's breaker
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CircuitBreakerState {
+    pub tripped: bool,
+    /// Unix seconds of the last trip (0 if never tripped)
+    pub tripped_at: u64,
+    pub cooldown_secs: u64,
+    /// Relative move (percent) that caused the last trip
+    pub last_move_pct: Option<f64>,
+    /// (unix seconds, price) within the window, oldest first
+    #[serde(skip)]
+    recent_prices: VecDeque<(u64, f64)>,
+}
+
+impl CircuitBreakerState {
+    /// Seconds until a tripped breaker resets
+    pub fn remaining_cooldown(&self, now: u64) -> u64 {
+        if !self.tripped {
+            return 0;
+        }
+        (self.tripped_at + self.cooldown_secs).saturating_sub(now)
+    }
+}
+
+/// Breakers of every pool, keyed by pool ID (hex)
+#[derive(Debug)]
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    pools: Mutex<HashMap<String, CircuitBreakerState>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self { config, pools: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn config(&self) -> CircuitBreakerConfig {
+        self.config
+    }
+
+    pub fn is_tripped(&self, pool: &str) -> bool {
+        self.pools.lock().unwrap().get(pool).is_some_and(|b| b.tripped)
+    }
+
+    /// Seconds until `pool`'s breaker resets (0 when it isn't tripped)
+    pub fn remaining_cooldown(&self, pool: &str, now: u64) -> u64 {
+        self.pools.lock().unwrap().get(pool).map_or(0, |b| b.remaining_cooldown(now))
+    }
+
+    /// Record a swap's post-trade price and compare it with the oldest price
+    /// in the window. Returns the relative move (percent) when this trips
+    /// the breaker.
+    pub fn record_price(&self, pool: &str, now: u64, price: f64) -> Option<f64> {
+        if !price.is_finite() || price <= 0.0 {
+            return None;
+        }
+        let mut pools = self.pools.lock().unwrap();
+        let breaker = pools.entry(pool.to_string()).or_insert_with(|| CircuitBreakerState {
+            cooldown_secs: self.config.cooldown_secs,
+            ..Default::default()
+        });
+
+        let cutoff = now.saturating_sub(self.config.window_secs);
+        while breaker.recent_prices.front().is_some_and(|(t, _)| *t < cutoff) {
+            breaker.recent_prices.pop_front();
+        }
+        let reference = breaker.recent_prices.front().map(|(_, p)| *p);
+        breaker.recent_prices.push_back((now, price));
+
+        let reference = reference?;
+        let move_pct = ((price - reference) / reference).abs() * 100.0;
+        if breaker.tripped || move_pct <= self.config.threshold_pct {
+            return None;
+        }
+        breaker.tripped = true;
+        breaker.tripped_at = now;
+        breaker.cooldown_secs = self.config.cooldown_secs;
+        breaker.last_move_pct = Some(move_pct);
+        Some(move_pct)
+    }
+
+    /// Reset breakers whose cooldown has passed; returns the reset pools.
+    /// The price window starts over so the old move can't trip it again.
+    pub fn reset_expired(&self, now: u64) -> Vec<String> {
+        let mut reset = Vec::new();
+        for (pool, breaker) in self.pools.lock().unwrap().iter_mut() {
+            if breaker.tripped && breaker.remaining_cooldown(now) == 0 {
+                breaker.tripped = false;
+                breaker.recent_prices.clear();
+                reset.push(pool.clone());
+            }
+        }
+        reset
+    }
+
+    /// State of every pool that has seen a swap
+    pub fn snapshot(&self) -> Vec<(String, CircuitBreakerState)> {
+        let mut pools: Vec<_> = self.pools.lock().unwrap()
+            .iter()
+            .map(|(pool, breaker)| (pool.clone(), breaker.clone()))
+            .collect();
+        pools.sort_by(|a, b| a.0.cmp(&b.0));
+        pools
+    }
+}
@@ -0,0 +1,6 @@
+//! Swap output for each pool curve
+//!
+//! The math lives in `integration::curve` so `milo swap` quotes a swap with
+//! exactly the formula the daemon executes it with.
+
+pub use integration::curve::*;
@@ -0,0 +1,227 @@
+//! Builders for daemon test inputs (`fixtures` feature)
+//!
+//! The daemons' logic is tested through their pure helpers, which take
+//! swap/deposit blobs, reserves and pool sets rather than a client. These
+//! builders produce those inputs with valid account ids from the compiled-in
+//! registry. `SwapInfo` and `DepositInfo` are private to the daemon binaries,
+//! so their builders return the camelCase JSON the frontend sends; the
+//! binaries' tests deserialize it into their own types.
+//!
+//! There is no mocked client layer: tests drive the helpers the worker calls
+//! instead. For the helpers that inspect notes, `p2id_note` builds the note a
+//! wallet sends and `p2id_note_record` the `InputNoteRecord` the client's
+//! `get_consumable_notes` returns for it.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use integration::curve::amm_amount_out;
+use miden_client::account::AccountId;
+use miden_client::asset::FungibleAsset;
+use miden_client::crypto::RpoRandomCoin;
+use miden_client::note::{create_p2id_note, Note, NoteType};
+use miden_client::store::InputNoteRecord;
+use miden_client::Felt;
+use milo_registry::{MELO_FAUCET_ID_HEX, MILO_FAUCET_ID_HEX, MUSDC_FAUCET_ID_HEX};
+use serde_json::{json, Value};
+
+/// Pool accounts of the default MILO/MUSDC and MELO/MUSDC pools
+pub const MILO_MUSDC_POOL: &str = "0x6b10bd738877ea101db7175839e152";
+pub const MELO_MUSDC_POOL: &str = "0x563a995fec149d105728eaa1bd4332";
+
+/// Account the builders use as the note sender
+pub const USER: &str = "0xuser";
+
+/// A wallet account id, for builders that need a real sender
+pub const USER_ACCOUNT: &str = "0xa28b4f998be3a32047b88ae20e0a35";
+
+/// Seeds the serial numbers of built notes, so no two share an id
+static NOTE_SEED: AtomicU64 = AtomicU64::new(1);
+
+/// A two-token pool and its reserves, token A first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixturePool {
+    pub pool_id: String,
+    pub pair: String,
+    pub token_a: String,
+    pub token_b: String,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+}
+
+/// Canonical hex of an account id, as the daemons print it
+pub fn account_hex(hex: &str) -> String {
+    AccountId::from_hex(hex).expect("fixture account id").to_hex()
+}
+
+/// MILO/MUSDC pool holding `a` MILO and `b` MUSDC
+///
+/// ```
+/// use pool_daemon::fixtures::pool_with_reserves;
+///
+/// let pool = pool_with_reserves(1_000_000, 500_000);
+/// assert_eq!(pool.pair, "milo_musdc");
+/// assert_eq!(pool.amount_out(&pool.token_a, 1_000, 30), 498);
+/// ```
+pub fn pool_with_reserves(a: u64, b: u64) -> FixturePool {
+    FixturePool {
+        pool_id: account_hex(MILO_MUSDC_POOL),
+        pair: "milo_musdc".to_string(),
+        token_a: account_hex(MILO_FAUCET_ID_HEX),
+        token_b: account_hex(MUSDC_FAUCET_ID_HEX),
+        reserve_a: a,
+        reserve_b: b,
+    }
+}
+
+/// MILO/MUSDC and MELO/MUSDC pools, the layout `milo setup` creates
+///
+/// ```
+/// use pool_daemon::fixtures::two_pools;
+///
+/// let [milo, melo] = two_pools(1_000, 2_000);
+/// assert_eq!(milo.token_b, melo.token_b);
+/// ```
+pub fn two_pools(a: u64, b: u64) -> [FixturePool; 2] {
+    let melo = FixturePool {
+        pool_id: account_hex(MELO_MUSDC_POOL),
+        pair: "melo_musdc".to_string(),
+        token_a: account_hex(MELO_FAUCET_ID_HEX),
+        ..pool_with_reserves(a, b)
+    };
+    [pool_with_reserves(a, b), melo]
+}
+
+/// Pool ids and the pool id → token ids map the swap daemon validates against
+///
+/// ```
+/// use pool_daemon::fixtures::{pool_tokens, two_pools};
+///
+/// let (pool_ids, tokens) = pool_tokens(&two_pools(1, 1));
+/// assert_eq!(pool_ids.len(), tokens.len());
+/// ```
+pub fn pool_tokens(pools: &[FixturePool]) -> (Vec<AccountId>, HashMap<String, Vec<String>>) {
+    let pool_ids = pools.iter().map(|p| AccountId::from_hex(&p.pool_id).expect("fixture pool id")).collect();
+    let tokens = pools.iter().map(|p| (p.pool_id.clone(), vec![p.token_a.clone(), p.token_b.clone()])).collect();
+    (pool_ids, tokens)
+}
+
+impl FixturePool {
+    /// Reserve of `token`; 0 if the pool doesn't hold it
+    pub fn reserve(&self, token: &str) -> u64 {
+        if token == self.token_a {
+            self.reserve_a
+        } else if token == self.token_b {
+            self.reserve_b
+        } else {
+            0
+        }
+    }
+
+    /// Constant-product output for selling `amount_in` of `sell` into the pool
+    pub fn amount_out(&self, sell: &str, amount_in: u64, fee_bps: u64) -> u64 {
+        let buy = if sell == self.token_a { &self.token_b } else { &self.token_a };
+        amm_amount_out(amount_in, self.reserve(sell), self.reserve(buy), fee_bps) as u64
+    }
+
+    /// The pool's entry in a `/pool_reserves` response
+    ///
+    /// ```
+    /// use pool_daemon::fixtures::pool_with_reserves;
+    ///
+    /// let entry = pool_with_reserves(7, 9).reserves_json();
+    /// assert_eq!(entry["reserves"][1]["amount"], "9");
+    /// ```
+    pub fn reserves_json(&self) -> Value {
+        json!({
+            "pool_id": self.pool_id,
+            "pair": self.pair.to_uppercase().replace('_', "/"),
+            "reserves": [
+                { "faucet_id": self.token_a, "amount": self.reserve_a.to_string() },
+                { "faucet_id": self.token_b, "amount": self.reserve_b.to_string() },
+            ],
+        })
+    }
+}
+
+/// Swap blob for `note`, selling `amount_in` of `sell` for `buy` in `pool`
+///
+/// ```
+/// use pool_daemon::fixtures::{pool_with_reserves, swap_info_for};
+///
+/// let pool = pool_with_reserves(1_000, 1_000);
+/// let info = swap_info_for("0xnote", &pool, &pool.token_a, &pool.token_b, 100, 90);
+/// assert_eq!(info["minAmountOut"], "90");
+/// ```
+pub fn swap_info_for(note: &str, pool: &FixturePool, sell: &str, buy: &str, amount_in: u64, min_out: u64) -> Value {
+    json!({
+        "noteId": note,
+        "poolAccountId": pool.pool_id,
+        "sellTokenId": sell,
+        "buyTokenId": buy,
+        "amountIn": amount_in.to_string(),
+        "minAmountOut": min_out.to_string(),
+        "userAccountId": USER,
+        "timestamp": 0,
+    })
+}
+
+/// Deposit blob for `note`, adding `amount` of `token` to `pool` for `user`
+///
+/// ```
+/// use pool_daemon::fixtures::{deposit_info_for, pool_with_reserves};
+///
+/// let pool = pool_with_reserves(1_000, 1_000);
+/// let info = deposit_info_for("0xnote", &pool, "0xalice", &pool.token_b, 250, 0);
+/// assert_eq!(info["amount"], "250");
+/// ```
+pub fn deposit_info_for(note: &str, pool: &FixturePool, user: &str, token: &str, amount: u64, min_lp: u64) -> Value {
+    json!({
+        "noteId": note,
+        "poolAccountId": pool.pool_id,
+        "tokenId": token,
+        "amount": amount.to_string(),
+        "userAccountId": user,
+        "minLpAmountOut": min_lp.to_string(),
+        "timestamp": 0,
+    })
+}
+
+/// Public P2ID note sending `amount` of `faucet` from `from` to `to` (hex
+/// account ids), as a wallet's deposit or swap note to a pool
+///
+/// ```
+/// use pool_daemon::fixtures::{p2id_note, pool_with_reserves, USER_ACCOUNT};
+///
+/// let pool = pool_with_reserves(1_000, 1_000);
+/// let note = p2id_note(USER_ACCOUNT, &pool.pool_id, &pool.token_a, 250);
+/// assert_eq!(note.metadata().sender().to_hex(), USER_ACCOUNT);
+/// assert_eq!(note.assets().num_assets(), 1);
+/// ```
+pub fn p2id_note(from: &str, to: &str, faucet: &str, amount: u64) -> Note {
+    let asset = FungibleAsset::new(AccountId::from_hex(faucet).expect("fixture faucet id"), amount)
+        .expect("fixture amount");
+    let mut rng = RpoRandomCoin::new([Felt::new(NOTE_SEED.fetch_add(1, Ordering::Relaxed)); 4].into());
+    create_p2id_note(
+        AccountId::from_hex(from).expect("fixture sender id"),
+        AccountId::from_hex(to).expect("fixture recipient id"),
+        vec![asset.into()],
+        NoteType::Public,
+        Felt::new(0),
+        &mut rng,
+    )
+    .expect("fixture P2ID note")
+}
+
+/// `p2id_note` as the client lists it among a pool's consumable notes
+///
+/// ```
+/// use pool_daemon::fixtures::{p2id_note_record, pool_with_reserves, USER_ACCOUNT};
+///
+/// let pool = pool_with_reserves(1_000, 1_000);
+/// let record = p2id_note_record(USER_ACCOUNT, &pool.pool_id, &pool.token_b, 250);
+/// assert_eq!(record.assets().num_assets(), 1);
+/// ```
+pub fn p2id_note_record(from: &str, to: &str, faucet: &str, amount: u64) -> InputNoteRecord {
+    p2id_note(from, to, faucet, amount).into()
+}
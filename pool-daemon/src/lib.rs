@@ -0,0 +1,9 @@
+//! Code shared by the swap and liquidity daemons
+
+pub mod circuit_breaker;
+pub mod curve;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod metrics;
+pub mod poll;
+pub mod retry;
@@ -0,0 +1,83 @@
+//! Prometheus metrics shared by both daemons
+//!
+//! The worker thread updates a `MetricsState` behind the same `Arc<Mutex<>>`
+//! pattern as the other shared maps; `/metrics` renders it after the
+//! daemon's own gauges. Per-pool series are labelled with the pool ID (hex).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Debug, Default)]
+pub struct MetricsState {
+    swaps_total: BTreeMap<String, u64>,
+    notes_consumed_total: BTreeMap<String, u64>,
+    /// Pool -> (reserve A, reserve B), in vault order
+    pool_reserves: BTreeMap<String, (u64, u64)>,
+    auto_poll_errors_total: u64,
+    worker_queue_depth: u64,
+}
+
+impl MetricsState {
+    /// A swap transaction was submitted for `pool`
+    pub fn record_swap(&mut self, pool: &str) {
+        *self.swaps_total.entry(pool.to_string()).or_default() += 1;
+    }
+
+    /// A transaction consuming a note into `pool` committed
+    pub fn record_consumed(&mut self, pool: &str) {
+        *self.notes_consumed_total.entry(pool.to_string()).or_default() += 1;
+    }
+
+    pub fn set_reserves(&mut self, pool: &str, reserve_a: u64, reserve_b: u64) {
+        self.pool_reserves.insert(pool.to_string(), (reserve_a, reserve_b));
+    }
+
+    pub fn record_auto_poll_error(&mut self) {
+        self.auto_poll_errors_total += 1;
+    }
+
+    /// A request was queued for the worker
+    pub fn enqueued(&mut self) {
+        self.worker_queue_depth += 1;
+    }
+
+    /// The worker picked up a queued request
+    pub fn dequeued(&mut self) {
+        self.worker_queue_depth = self.worker_queue_depth.saturating_sub(1);
+    }
+
+    /// Prometheus text exposition of every metric
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        render_per_pool(&mut out, "milo_swaps_total", "Swap transactions submitted", "counter",
+            self.swaps_total.iter().map(|(pool, n)| (pool, *n)));
+        render_per_pool(&mut out, "milo_notes_consumed_total", "Notes consumed by committed transactions", "counter",
+            self.notes_consumed_total.iter().map(|(pool, n)| (pool, *n)));
+        render_per_pool(&mut out, "milo_pool_reserve_a", "First vault reserve after the last consume", "gauge",
+            self.pool_reserves.iter().map(|(pool, (a, _))| (pool, *a)));
+        render_per_pool(&mut out, "milo_pool_reserve_b", "Second vault reserve after the last consume", "gauge",
+            self.pool_reserves.iter().map(|(pool, (_, b))| (pool, *b)));
+        render_single(&mut out, "milo_auto_poll_errors_total", "Auto-poll cycles that failed", "counter",
+            self.auto_poll_errors_total);
+        render_single(&mut out, "milo_worker_queue_depth", "HTTP requests waiting for the worker thread", "gauge",
+            self.worker_queue_depth);
+        out
+    }
+}
+
+fn render_per_pool<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    samples: impl Iterator<Item = (&'a String, u64)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    for (pool, value) in samples {
+        let _ = writeln!(out, "{}{{pool=\"{}\"}} {}", name, pool, value);
+    }
+}
+
+fn render_single(out: &mut String, name: &str, help: &str, kind: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+}
@@ -0,0 +1,32 @@
+//! Auto-poll interval of the daemons' workers
+//!
+//! The interval comes from a command-line value if given, else
+//! MILO_POLL_INTERVAL_SECS, else `poll_interval_secs` in milo.toml (15 by
+//! default). Values outside the bounds are clamped with a warning rather
+//! than refused.
+
+use integration::startup_config::{milo_config_source, ConfigSource};
+use milo_config::MiloConfig;
+
+pub const MIN_POLL_INTERVAL_SECS: u64 = 5;
+pub const MAX_POLL_INTERVAL_SECS: u64 = 300;
+
+/// Effective interval in seconds and where it came from. An env value that
+/// doesn't parse falls back to milo.toml.
+pub fn resolve_poll_interval(arg: Option<u64>, config: &MiloConfig) -> (u64, ConfigSource) {
+    let (secs, source) = match arg {
+        Some(secs) => (secs, ConfigSource::Arg),
+        None => match std::env::var("MILO_POLL_INTERVAL_SECS").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(secs) => (secs, ConfigSource::Env),
+            None => (config.poll_interval_secs, milo_config_source(config, "poll_interval_secs")),
+        },
+    };
+    let clamped = secs.clamp(MIN_POLL_INTERVAL_SECS, MAX_POLL_INTERVAL_SECS);
+    if clamped != secs {
+        println!(
+            "⚠️  Poll interval {}s is outside {}-{}s, using {}s",
+            secs, MIN_POLL_INTERVAL_SECS, MAX_POLL_INTERVAL_SECS, clamped
+        );
+    }
+    (clamped, source)
+}
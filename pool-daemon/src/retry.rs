@@ -0,0 +1,84 @@
+//! Retrying node calls with exponential backoff
+//!
+//! A single failed or slow `sync_state` used to leave the daemons working
+//! from stale state until the next poll. Calls are now retried, doubling the
+//! delay between attempts up to `MAX_DELAY_MS`.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use miden_client::{auth::TransactionAuthenticator, sync::SyncSummary, Client};
+use tokio::time::sleep;
+
+/// Attempts per sync before the caller sees the error
+pub const SYNC_MAX_ATTEMPTS: u32 = 4;
+pub const SYNC_BASE_DELAY_MS: u64 = 500;
+/// Backoff never waits longer than this between attempts
+pub const MAX_DELAY_MS: u64 = 60_000;
+/// One `sync_state` attempt is abandoned after this long
+const SYNC_ATTEMPT_TIMEOUT_SECS: u64 = 45;
+
+/// Delay after failed attempt `attempt` (1-based): `base_delay_ms * 2^(attempt-1)`, capped
+pub fn backoff_delay(base_delay_ms: u64, attempt: u32) -> Duration {
+    let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+    Duration::from_millis(base_delay_ms.saturating_mul(factor).min(MAX_DELAY_MS))
+}
+
+/// `sync_state` with up to `max_attempts` tries. Failures and a success after
+/// a retry are logged with the time spent so far.
+pub async fn sync_with_retry<AUTH>(
+    client: &mut Client<AUTH>,
+    max_attempts: u32,
+    base_delay_ms: u64,
+) -> Result<SyncSummary>
+where
+    AUTH: TransactionAuthenticator + Sync + 'static,
+{
+    let started = Instant::now();
+    let mut attempt = 1;
+    loop {
+        let error = match tokio::time::timeout(Duration::from_secs(SYNC_ATTEMPT_TIMEOUT_SECS), client.sync_state()).await {
+            Ok(Ok(summary)) => {
+                if attempt > 1 {
+                    println!("   ✅ Sync succeeded on attempt {}/{} ({:.1}s)",
+                        attempt, max_attempts, started.elapsed().as_secs_f64());
+                }
+                return Ok(summary);
+            }
+            Ok(Err(e)) => anyhow::anyhow!("sync_state failed: {}", e),
+            Err(_) => anyhow::anyhow!("sync_state timed out after {}s", SYNC_ATTEMPT_TIMEOUT_SECS),
+        };
+
+        println!("   ⚠️  Sync attempt {}/{} failed ({:.1}s): {}",
+            attempt, max_attempts, started.elapsed().as_secs_f64(), error);
+        if attempt >= max_attempts {
+            return Err(error.context(format!("Sync failed after {} attempts", max_attempts)));
+        }
+        sleep(backoff_delay(base_delay_ms, attempt)).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_per_attempt() {
+        assert_eq!(backoff_delay(500, 1), Duration::from_millis(500));
+        assert_eq!(backoff_delay(500, 2), Duration::from_millis(1_000));
+        assert_eq!(backoff_delay(500, 3), Duration::from_millis(2_000));
+        // attempt 0 is treated like the first attempt
+        assert_eq!(backoff_delay(500, 0), backoff_delay(500, 1));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        // 500 ms * 2^7 = 64 s
+        assert_eq!(backoff_delay(500, 8), Duration::from_millis(MAX_DELAY_MS));
+        assert_eq!(backoff_delay(MAX_DELAY_MS + 1, 1), Duration::from_millis(MAX_DELAY_MS));
+        // Shifts and multiplications past u64 saturate instead of wrapping
+        assert_eq!(backoff_delay(500, 64), Duration::from_millis(MAX_DELAY_MS));
+        assert_eq!(backoff_delay(u64::MAX, 200), Duration::from_millis(MAX_DELAY_MS));
+    }
+}